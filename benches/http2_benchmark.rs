@@ -0,0 +1,84 @@
+//! Throughput of intake submissions over HTTP/1.1 and HTTP/2.
+//!
+//! Each iteration logs 256 events, each submitted in its own request, to a local intake that
+//! answers after 10 ms to simulate the round trip to the real one, and waits until all are
+//! delivered.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+use pogr_tracing_rs::{BatchConfig, HttpConfig, HttpVersion, PogrAppender, PogrLayer};
+use std::convert::Infallible;
+use std::time::Duration;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+/// Events logged per iteration.
+const EVENTS: u64 = 256;
+
+/// Starts an intake serving HTTP/1.1 and HTTP/2 with a fixed latency and returns its logs endpoint.
+async fn start_intake() -> String {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|_request| async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok::<_, Infallible>(Response::new(Body::from(
+                r#"{"success":true,"payload":{"log_id":"bench_log_id"}}"#,
+            )))
+        }))
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let logs_endpoint = format!("http://{}/v1/intake/logs", server.local_addr());
+    tokio::spawn(server);
+    logs_endpoint
+}
+
+/// Logs `EVENTS` events through a layer and waits until they are delivered.
+async fn submit(layer: PogrLayer) {
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for event in 0..EVENTS {
+        tracing::info!(event, "Benchmark event");
+    }
+    guard.flush().await;
+}
+
+fn http2_benchmark(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+    let logs_endpoint = runtime.block_on(start_intake());
+
+    let configurations = [
+        ("http1_sequential", HttpVersion::Http1Only, 1),
+        ("http1_concurrent_8", HttpVersion::Http1Only, 8),
+        ("http1_concurrent_32", HttpVersion::Http1Only, 32),
+        ("http2_concurrent_8", HttpVersion::Http2PriorKnowledge, 8),
+        ("http2_concurrent_32", HttpVersion::Http2PriorKnowledge, 32),
+    ];
+
+    let mut group = c.benchmark_group("intake_submission");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(EVENTS));
+    for (name, version, concurrency) in configurations {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let appender = PogrAppender {
+                    client: reqwest::Client::new(),
+                    service_name: "bench".to_string(),
+                    environment: "bench".to_string(),
+                    service_type: "bench".to_string(),
+                    session_id: "bench_session_id".to_string(),
+                    logs_endpoint: logs_endpoint.clone(),
+                    init_endpoint: String::new(),
+                };
+                let layer = PogrLayer::new(appender)
+                    .with_http(HttpConfig::new().with_version(version))
+                    .with_batching(BatchConfig::new().with_max_concurrent_requests(concurrency));
+                runtime.block_on(submit(layer));
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, http2_benchmark);
+criterion_main!(benches);
@@ -1,7 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use pogr_tracing_rs::{PogrAppender, PogrLayer};
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use tracing_subscriber::Registry;
 use mockito::{Server};
 use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
@@ -40,9 +38,7 @@ async fn log_event(base_url: &str) {
     let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
 
     let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
-    let layer = PogrLayer {
-        appender: Arc::new(Mutex::new(appender)),
-    };
+    let layer = PogrLayer::new(appender);
     let subscriber = Registry::default().with(layer);
     tracing::subscriber::set_global_default(subscriber).expect("Failed to set global subscriber");
     tracing::info!("This is a benchmark test log");
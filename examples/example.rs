@@ -2,8 +2,6 @@
 
 use pogr_tracing_rs::{PogrAppender, PogrLayer};
 use serde_json::{json, to_string};
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use tracing::{event, field, Level};
 use tracing_subscriber::{layer::SubscriberExt, Registry};
 
@@ -12,9 +10,7 @@ async fn main() {
     // Setup POGR Appender
     let appender = PogrAppender::new(None, None).await;
 
-    let layer = PogrLayer {
-        appender: Arc::new(Mutex::new(appender)),
-    };
+    let layer = PogrLayer::new(appender);
 
     let subscriber = Registry::default().with(layer);
 
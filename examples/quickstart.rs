@@ -0,0 +1,9 @@
+use tracing::{info, warn};
+
+// `#[pogr_tracing_rs::main]` builds the runtime, installs the POGR subscriber and panic hook,
+// and flushes pending logs once `main` returns.
+#[pogr_tracing_rs::main]
+async fn main() {
+    info!(player_count = 12, "Match lobby opened");
+    warn!(latency_ms = 180, "High latency detected");
+}
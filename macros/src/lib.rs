@@ -0,0 +1,305 @@
+//! Procedural macros for `pogr_tracing_rs`.
+//!
+//! This crate is an implementation detail of `pogr_tracing_rs` and should not be used directly.
+//! The macros defined here are re-exported from the main crate, e.g. `#[pogr_tracing_rs::main]`
+//! and `#[pogr_tracing_rs::test]`, and expand to calls into `pogr_tracing_rs::__private`.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, ItemFn, LitStr, Token};
+
+/// Marks an async function as the entry point of a POGR-enabled application.
+///
+/// The function body is run on a multi-threaded tokio runtime after a subscriber with a
+/// `PogrLayer` has been installed as the global default and the POGR panic hook has been
+/// registered. Once the body completes (or panics), pending log submissions are flushed
+/// before the process exits.
+///
+/// ```rust,ignore
+/// #[pogr_tracing_rs::main]
+/// async fn main() {
+///     tracing::info!("Hello from POGR");
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    expand(args, item, Entry::Main)
+}
+
+/// Marks an async function as a test that runs with a POGR subscriber installed.
+///
+/// The test runs on a current-thread tokio runtime with a `PogrLayer` set as the
+/// scoped default subscriber for the duration of the test. Pending log submissions are
+/// flushed before the test returns.
+///
+/// ```rust,ignore
+/// #[pogr_tracing_rs::test]
+/// async fn logs_are_shipped() {
+///     tracing::info!("Hello from a test");
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    expand(args, item, Entry::Test)
+}
+
+/// The kind of entry point being generated.
+#[derive(Clone, Copy)]
+enum Entry {
+    Main,
+    Test,
+}
+
+/// Rewrites an `async fn` into a synchronous function that drives its body through
+/// the runtime helpers in `pogr_tracing_rs::__private`.
+fn expand(args: TokenStream, item: TokenStream, entry: Entry) -> TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(Span::call_site(), "this attribute does not take any arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let mut function = parse_macro_input!(item as ItemFn);
+
+    if function.sig.asyncness.take().is_none() {
+        return syn::Error::new_spanned(
+            function.sig.fn_token,
+            "the `async` keyword is missing from the function declaration",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    if !function.sig.inputs.is_empty() {
+        return syn::Error::new_spanned(&function.sig.inputs, "the function cannot accept arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let attrs = &function.attrs;
+    let vis = &function.vis;
+    let sig = &function.sig;
+    let body = &function.block;
+
+    let expanded = match entry {
+        Entry::Main => quote! {
+            #(#attrs)*
+            #vis #sig {
+                ::pogr_tracing_rs::__private::run_main(async move #body)
+            }
+        },
+        Entry::Test => quote! {
+            #[::core::prelude::v1::test]
+            #(#attrs)*
+            #vis #sig {
+                ::pogr_tracing_rs::__private::run_test(async move #body)
+            }
+        },
+    };
+
+    expanded.into()
+}
+
+/// Derives `pogr_tracing_rs::PogrEvent` for a struct implementing `serde::Serialize`, so it
+/// can be emitted as a typed event with `event.emit()`.
+///
+/// The struct accepts `#[pogr(severity = "...", type = "...", name = "...")]`, and its fields
+/// `#[pogr(redact)]`. The severity is one of `trace`, `debug`, `info` (the default), `warn` and
+/// `error`, and the name defaults to the struct's name in `snake_case`.
+///
+/// ```rust,ignore
+/// #[derive(serde::Serialize, pogr_tracing_rs::PogrEvent)]
+/// #[pogr(severity = "warn", type = "gameplay")]
+/// struct PlayerDied {
+///     player_id: u64,
+///     #[pogr(redact)]
+///     ip_address: String,
+/// }
+/// ```
+#[proc_macro_derive(PogrEvent, attributes(pogr))]
+pub fn derive_pogr_event(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match expand_event(&input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Generates the `PogrEvent` implementation of a struct.
+fn expand_event(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "`PogrEvent` can only be derived for structs"));
+    };
+
+    let mut name = snake_case(&input.ident.to_string());
+    let mut level = quote!(INFO);
+    let mut r#type = quote!(::core::option::Option::None);
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("pogr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("severity") {
+                let severity: LitStr = meta.value()?.parse()?;
+                level = match severity.value().to_ascii_lowercase().as_str() {
+                    "trace" => quote!(TRACE),
+                    "debug" => quote!(DEBUG),
+                    "info" => quote!(INFO),
+                    "warn" => quote!(WARN),
+                    "error" => quote!(ERROR),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            severity,
+                            "expected one of `trace`, `debug`, `info`, `warn` and `error`",
+                        ))
+                    }
+                };
+                Ok(())
+            } else if meta.path.is_ident("type") {
+                let value: LitStr = meta.value()?.parse()?;
+                r#type = quote!(::core::option::Option::Some(#value));
+                Ok(())
+            } else if meta.path.is_ident("name") {
+                let value: LitStr = meta.value()?.parse()?;
+                name = value.value();
+                Ok(())
+            } else {
+                Err(meta.error("expected `severity`, `type` or `name`"))
+            }
+        })?;
+    }
+
+    let mut redacted = Vec::new();
+    for field in &data.fields {
+        let mut redact = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("pogr")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("redact") {
+                    redact = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `redact`"))
+                }
+            })?;
+        }
+        if !redact {
+            continue;
+        }
+        let Some(ident) = &field.ident else {
+            return Err(syn::Error::new_spanned(field, "only named fields can be redacted"));
+        };
+        if has_serde_attr(&input.attrs, "rename_all")? {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "redacted fields cannot be renamed with `#[serde(rename_all)]`; rename them with `#[serde(rename = \"...\")]`",
+            ));
+        }
+        let serialized = serde_rename(&field.attrs)?.unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        redacted.push(serialized);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pogr_tracing_rs::PogrEvent for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #name;
+            const LEVEL: ::pogr_tracing_rs::__private::tracing::Level = ::pogr_tracing_rs::__private::tracing::Level::#level;
+            const TYPE: ::core::option::Option<&'static str> = #r#type;
+            const REDACTED_FIELDS: &'static [&'static str] = &[#(#redacted),*];
+
+            fn emit(&self) {
+                let fields = ::pogr_tracing_rs::__private::encode_fields(self);
+                match <Self as ::pogr_tracing_rs::PogrEvent>::TYPE {
+                    ::core::option::Option::Some(r#type) => ::pogr_tracing_rs::__private::tracing::event!(
+                        ::pogr_tracing_rs::__private::tracing::Level::#level,
+                        "pogr.event" = fields.as_str(),
+                        "pogr.type" = r#type,
+                        "{}",
+                        #name
+                    ),
+                    ::core::option::Option::None => ::pogr_tracing_rs::__private::tracing::event!(
+                        ::pogr_tracing_rs::__private::tracing::Level::#level,
+                        "pogr.event" = fields.as_str(),
+                        "{}",
+                        #name
+                    ),
+                }
+            }
+        }
+    })
+}
+
+/// Returns `true` if the `#[serde(...)]` attributes set `key`.
+fn has_serde_attr(attrs: &[Attribute], key: &str) -> syn::Result<bool> {
+    let mut found = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                found = true;
+            }
+            // Skip the value of any other key, e.g. `default` or `with = "..."`.
+            if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|nested| {
+                    if nested.input.peek(Token![=]) {
+                        nested.value()?.parse::<syn::Expr>()?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(found)
+}
+
+/// Returns the name a field is serialized with, if it is renamed with
+/// `#[serde(rename = "...")]`.
+fn serde_rename(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut renamed = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                renamed = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|nested| {
+                    if nested.path.is_ident("serialize") && nested.input.peek(Token![=]) && meta.path.is_ident("rename") {
+                        renamed = Some(nested.value()?.parse::<LitStr>()?.value());
+                    } else if nested.input.peek(Token![=]) {
+                        nested.value()?.parse::<syn::Expr>()?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(renamed)
+}
+
+/// Converts a type name such as `PlayerDied` to `player_died`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let chars: Vec<char> = name.chars().collect();
+    for (index, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            // Start a new word at a lowercase-to-uppercase change, and before the last capital
+            // of an acronym followed by a lowercase letter (`HTTPRequest` -> `http_request`).
+            let previous = index.checked_sub(1).map(|previous| chars[previous]);
+            let next = chars.get(index + 1);
+            let boundary = previous.is_some_and(|previous| {
+                previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+            });
+            if boundary {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
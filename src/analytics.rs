@@ -0,0 +1,271 @@
+//! Typed bindings for POGR's analytics endpoints.
+//!
+//! Where `PogrQueryClient::fetch_page` returns raw logs, the analytics endpoints aggregate
+//! them on the server: log counts by severity, log counts over time, and rollups of numeric
+//! metrics recorded in the logs. They share the query client's read credentials and take the
+//! same kind of filters, plus an optional time range:
+//!
+//! ```text
+//! GET /v1/analytics/severity_counts?service=...&from=...&to=...
+//! GET /v1/analytics/events_over_time?service=...&interval=1h
+//! GET /v1/analytics/metrics?service=...&metric=...&aggregation=p95&interval=1h
+//! ```
+
+use crate::clock::{format_rfc3339, parse_rfc3339};
+use crate::query::{PogrQueryClient, QueryError};
+use serde::{Deserialize, Deserializer};
+use std::time::SystemTime;
+
+/// Selects the logs an analytics query aggregates.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::AnalyticsQuery;
+/// use std::time::{Duration, SystemTime};
+///
+/// let now = SystemTime::now();
+/// let query = AnalyticsQuery::for_service("matchmaking")
+///     .with_environment("production")
+///     .with_range(now - Duration::from_secs(24 * 3600), now);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnalyticsQuery {
+    /// Filters as query parameters, in the order they were added.
+    filters: Vec<(&'static str, String)>,
+}
+
+impl AnalyticsQuery {
+    /// Aggregates the logs submitted by a service.
+    pub fn for_service(service: impl Into<String>) -> Self {
+        AnalyticsQuery {
+            filters: vec![("service", service.into())],
+        }
+    }
+
+    /// Aggregates the logs submitted in an intake session.
+    pub fn for_session(session_id: impl Into<String>) -> Self {
+        AnalyticsQuery {
+            filters: vec![("session_id", session_id.into())],
+        }
+    }
+
+    /// Only aggregates logs from the given environment.
+    pub fn with_environment(self, environment: impl Into<String>) -> Self {
+        self.with_filter("environment", environment.into())
+    }
+
+    /// Only aggregates logs captured from `from` (inclusive) to `to` (exclusive).
+    pub fn with_range(self, from: SystemTime, to: SystemTime) -> Self {
+        self.with_filter("from", format_rfc3339(from))
+            .with_filter("to", format_rfc3339(to))
+    }
+
+    /// Adds a filter, replacing an earlier one with the same name.
+    fn with_filter(mut self, name: &'static str, value: String) -> Self {
+        self.filters.retain(|(existing, _)| *existing != name);
+        self.filters.push((name, value));
+        self
+    }
+
+    /// Returns the filters followed by the given parameters.
+    fn parameters<'a>(&'a self, extra: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+        self.filters
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .chain(extra.iter().copied())
+            .collect()
+    }
+}
+
+/// Width of the time buckets of a time series.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interval {
+    /// One minute.
+    Minute,
+    /// One hour.
+    Hour,
+    /// One day.
+    Day,
+}
+
+impl Interval {
+    /// Returns the name used by the analytics endpoints.
+    fn as_str(self) -> &'static str {
+        match self {
+            Interval::Minute => "1m",
+            Interval::Hour => "1h",
+            Interval::Day => "1d",
+        }
+    }
+}
+
+/// How the values of a metric are combined within a time bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregation {
+    /// Number of values.
+    Count,
+    /// Sum of the values.
+    Sum,
+    /// Mean of the values.
+    Avg,
+    /// Smallest value.
+    Min,
+    /// Largest value.
+    Max,
+    /// Median.
+    P50,
+    /// 95th percentile.
+    P95,
+    /// 99th percentile.
+    P99,
+}
+
+impl Aggregation {
+    /// Returns the name used by the analytics endpoints.
+    fn as_str(self) -> &'static str {
+        match self {
+            Aggregation::Count => "count",
+            Aggregation::Sum => "sum",
+            Aggregation::Avg => "avg",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::P50 => "p50",
+            Aggregation::P95 => "p95",
+            Aggregation::P99 => "p99",
+        }
+    }
+}
+
+/// Number of logs with one severity.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[non_exhaustive]
+pub struct SeverityCount {
+    /// The severity, e.g. `ERROR`.
+    pub severity: String,
+    /// Number of logs with this severity.
+    pub count: u64,
+}
+
+/// Number of logs in one time bucket.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[non_exhaustive]
+pub struct EventCount {
+    /// Start of the bucket.
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: SystemTime,
+    /// Number of logs captured in the bucket.
+    pub count: u64,
+}
+
+/// The aggregated value of a metric in one time bucket.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct MetricPoint {
+    /// Start of the bucket.
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: SystemTime,
+    /// The aggregated value, or `None` if no log in the bucket recorded the metric.
+    pub value: Option<f64>,
+}
+
+/// Payload of the severity counts endpoint.
+#[derive(Deserialize)]
+struct SeverityCounts {
+    /// Counts per severity.
+    counts: Vec<SeverityCount>,
+}
+
+/// Payload of the events over time endpoint.
+#[derive(Deserialize)]
+struct EventsOverTime {
+    /// Counts per time bucket.
+    buckets: Vec<EventCount>,
+}
+
+/// Payload of the metrics endpoint.
+#[derive(Deserialize)]
+struct MetricRollup {
+    /// Values per time bucket.
+    points: Vec<MetricPoint>,
+}
+
+/// Deserializes an RFC 3339 timestamp.
+fn deserialize_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_rfc3339(&value).ok_or_else(|| serde::de::Error::custom(format!("invalid RFC 3339 timestamp `{}`", value)))
+}
+
+impl PogrQueryClient {
+    /// Counts the selected logs by severity.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{AnalyticsQuery, PogrQueryClient};
+    ///
+    /// # async fn run() {
+    /// let client = PogrQueryClient::from_env().unwrap();
+    /// for count in client.severity_counts(&AnalyticsQuery::for_service("matchmaking")).await.unwrap() {
+    ///     println!("{}: {}", count.severity, count.count);
+    /// }
+    /// # }
+    /// ```
+    pub async fn severity_counts(&self, query: &AnalyticsQuery) -> Result<Vec<SeverityCount>, QueryError> {
+        let url = self.analytics_url("severity_counts");
+        let payload: SeverityCounts = self.get(&url, &query.parameters(&[])).await?;
+        Ok(payload.counts)
+    }
+
+    /// Counts the selected logs per time bucket, oldest bucket first.
+    pub async fn events_over_time(&self, query: &AnalyticsQuery, interval: Interval) -> Result<Vec<EventCount>, QueryError> {
+        let url = self.analytics_url("events_over_time");
+        let payload: EventsOverTime = self.get(&url, &query.parameters(&[("interval", interval.as_str())])).await?;
+        Ok(payload.buckets)
+    }
+
+    /// Aggregates a numeric metric recorded in the selected logs per time bucket, oldest
+    /// bucket first.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Selects the logs.
+    /// * `metric` - Name of the field holding the metric, e.g. `latency_ms`.
+    /// * `aggregation` - How the values in a bucket are combined.
+    /// * `interval` - Width of the buckets.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{Aggregation, AnalyticsQuery, Interval, PogrQueryClient};
+    ///
+    /// # async fn run() {
+    /// let client = PogrQueryClient::from_env().unwrap();
+    /// let p95 = client
+    ///     .metric_rollup(&AnalyticsQuery::for_service("matchmaking"), "queue_time_ms", Aggregation::P95, Interval::Hour)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn metric_rollup(
+        &self,
+        query: &AnalyticsQuery,
+        metric: &str,
+        aggregation: Aggregation,
+        interval: Interval,
+    ) -> Result<Vec<MetricPoint>, QueryError> {
+        let url = self.analytics_url("metrics");
+        let parameters = query.parameters(&[
+            ("metric", metric),
+            ("aggregation", aggregation.as_str()),
+            ("interval", interval.as_str()),
+        ]);
+        let payload: MetricRollup = self.get(&url, &parameters).await?;
+        Ok(payload.points)
+    }
+
+    /// Returns the URL of an analytics endpoint.
+    fn analytics_url(&self, name: &str) -> String {
+        format!("{}/{}", self.analytics_endpoint.trim_end_matches('/'), name)
+    }
+}
@@ -0,0 +1,228 @@
+//! Versions of the intake API.
+//!
+//! The intake's API is versioned by path: `/v1/intake/logs`, `/v2/intake/logs`. Each version
+//! has request and response models of its own, and the client negotiates which one it uses
+//! when it initializes the session: the init request lists the versions the client can
+//! speak in the `X-POGR-API-Versions` header, most preferred first, and the intake answers with
+//! the one it selected as `api_version` in the response payload. Intakes that predate the
+//! negotiation do not answer, which selects version 1.
+//!
+//! The logs endpoint is then switched to the selected version by rewriting the version
+//! segment of its path. A logs endpoint without a version segment, e.g. behind a gateway that
+//! rewrites paths, cannot be switched, so only version 1 is offered for it.
+//!
+//! Version 1 submits a single record as a JSON object and several as a JSON array, and answers
+//! with `{"success": true, "payload": {"log_id": "..."}}`. Version 2 always submits an object,
+//! `{"records": [...]}`, and answers with `{"success": true, "payload": {"log_ids": [...]}}`,
+//! or `{"success": false, "error": {"code": "...", "message": "..."}}`.
+
+use crate::payload::LogEnvelope;
+use crate::pool;
+use crate::{DeliveryError, LogResponse};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Header of the init request listing the offered versions.
+pub(crate) const OFFER_HEADER: &str = "X-POGR-API-Versions";
+
+/// A version of the intake API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum IntakeApiVersion {
+    /// The original API: a record as an object, a batch as an array, one log ID per request.
+    V1,
+    /// Records wrapped in an object, one log ID per record, and structured errors.
+    V2,
+}
+
+impl IntakeApiVersion {
+    /// The versions the client supports, most preferred first.
+    const SUPPORTED: [IntakeApiVersion; 2] = [IntakeApiVersion::V2, IntakeApiVersion::V1];
+
+    /// Returns the number of the version, as in its path segment.
+    pub fn number(self) -> u32 {
+        match self {
+            IntakeApiVersion::V1 => 1,
+            IntakeApiVersion::V2 => 2,
+        }
+    }
+
+    /// Returns the version with the given number, if the client supports it.
+    pub fn from_number(number: u32) -> Option<Self> {
+        Self::SUPPORTED.into_iter().find(|version| version.number() == number)
+    }
+
+    /// Returns the version an endpoint targets, from the version segment of its path, and
+    /// version 1 if it has none.
+    pub(crate) fn of_endpoint(url: &str) -> Self {
+        version_segment(url)
+            .and_then(|(start, end)| url[start..end].parse().ok())
+            .and_then(Self::from_number)
+            .unwrap_or(IntakeApiVersion::V1)
+    }
+
+    /// Returns the endpoint with its version segment set to this version.
+    pub(crate) fn apply(self, url: &str) -> String {
+        match version_segment(url) {
+            Some((start, end)) => format!("{}{}{}", &url[..start], self.number(), &url[end..]),
+            None => url.to_string(),
+        }
+    }
+
+    /// Encodes records as a JSON request body.
+    pub(crate) fn encode(self, envelopes: Vec<LogEnvelope<'_>>) -> serde_json::Result<Vec<u8>> {
+        pool::to_json_vec(&self.request_body(envelopes))
+    }
+
+    /// Returns the request body holding the records, to be serialized in any self-describing
+    /// format.
+    pub(crate) fn request_body(self, mut envelopes: Vec<LogEnvelope<'_>>) -> RequestBody<'_> {
+        match self {
+            IntakeApiVersion::V1 if envelopes.len() == 1 => RequestBody::Record(envelopes.remove(0)),
+            IntakeApiVersion::V1 => RequestBody::Records(envelopes),
+            IntakeApiVersion::V2 => RequestBody::Batch(LogBatch { records: envelopes }),
+        }
+    }
+
+    /// Joins records that are already serialized as `LogEnvelope`s into a JSON request body.
+    pub(crate) fn encode_serialized(self, payloads: &[String]) -> String {
+        match (self, payloads) {
+            (IntakeApiVersion::V1, [payload]) => payload.clone(),
+            (IntakeApiVersion::V1, _) => format!("[{}]", payloads.join(",")),
+            (IntakeApiVersion::V2, _) => format!("{{\"records\":[{}]}}", payloads.join(",")),
+        }
+    }
+
+    /// Parses the response of the logs endpoint, returning the log ID of the first record.
+    pub(crate) fn parse_response(self, status: StatusCode, body: &[u8]) -> Result<String, DeliveryError> {
+        let parsed = match self {
+            IntakeApiVersion::V1 => serde_json::from_slice::<LogResponse>(body).map(|response| {
+                if response.success {
+                    Ok(response.payload.log_id)
+                } else {
+                    Err(format!("{:?}", response))
+                }
+            }),
+            IntakeApiVersion::V2 => serde_json::from_slice::<LogResponseV2>(body).map(|response| match response {
+                LogResponseV2 {
+                    success: true,
+                    payload: Some(payload),
+                    ..
+                } => Ok(payload.log_ids.into_iter().next().unwrap_or_default()),
+                LogResponseV2 { error: Some(error), .. } => Err(format!("{}: {}", error.code, error.message)),
+                response => Err(format!("{:?}", response)),
+            }),
+        };
+        match parsed {
+            Ok(Ok(log_id)) => Ok(log_id),
+            Ok(Err(rejection)) => Err(DeliveryError::Rejected(rejection)),
+            // Error pages from the intake or a proxy in front of it are not intake responses.
+            Err(_) if !status.is_success() => Err(DeliveryError::Rejected(format!(
+                "{}: {}",
+                status,
+                String::from_utf8_lossy(body)
+            ))),
+            Err(err) => Err(DeliveryError::InvalidResponse(err)),
+        }
+    }
+}
+
+impl fmt::Display for IntakeApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.number())
+    }
+}
+
+/// Returns the versions to offer for a logs endpoint: `pinned` only if set, otherwise every
+/// supported version if the endpoint can be switched, and version 1 if it cannot.
+pub(crate) fn offered(logs_endpoint: &str, pinned: Option<IntakeApiVersion>) -> Vec<IntakeApiVersion> {
+    match pinned {
+        Some(version) => vec![version],
+        None if version_segment(logs_endpoint).is_some() => IntakeApiVersion::SUPPORTED.to_vec(),
+        None => vec![IntakeApiVersion::V1],
+    }
+}
+
+/// Formats the offered versions for the `X-POGR-API-Versions` header.
+pub(crate) fn offer_header(offered: &[IntakeApiVersion]) -> String {
+    offered.iter().map(|version| version.number().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Checks the version the intake selected against the offered ones.
+pub(crate) fn negotiate(offered: &[IntakeApiVersion], selected: Option<u32>) -> Result<IntakeApiVersion, String> {
+    let number = selected.unwrap_or(1);
+    match IntakeApiVersion::from_number(number) {
+        Some(version) if offered.contains(&version) => Ok(version),
+        _ => Err(format!(
+            "The intake selected API version {}, but the client offered {}",
+            number,
+            offer_header(offered)
+        )),
+    }
+}
+
+/// Returns the byte range of the number in the version segment (`/v<number>/`) of an URL's
+/// path, if it has one.
+fn version_segment(url: &str) -> Option<(usize, usize)> {
+    let path_start = url.find("://").map_or(0, |scheme| scheme + 3);
+    let path_start = path_start + url[path_start..].find('/')?;
+    let path_end = url[path_start..].find(['?', '#']).map_or(url.len(), |end| path_start + end);
+
+    let mut offset = path_start;
+    for segment in url[path_start..path_end].split('/') {
+        if let Some(number) = segment.strip_prefix('v') {
+            if !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Some((offset + 1, offset + segment.len()));
+            }
+        }
+        offset += segment.len() + 1;
+    }
+    None
+}
+
+/// The request body of an API version.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub(crate) enum RequestBody<'a> {
+    /// A single record in version 1.
+    Record(LogEnvelope<'a>),
+    /// Several records in version 1.
+    Records(Vec<LogEnvelope<'a>>),
+    /// Any number of records in version 2.
+    Batch(LogBatch<'a>),
+}
+
+/// The request body of version 2.
+#[derive(Serialize)]
+pub(crate) struct LogBatch<'a> {
+    /// The submitted records.
+    records: Vec<LogEnvelope<'a>>,
+}
+
+/// The response of the logs endpoint in version 2.
+#[derive(Deserialize, Debug)]
+struct LogResponseV2 {
+    /// Indicates whether the records were accepted.
+    success: bool,
+    /// The log IDs of the accepted records, if they were.
+    payload: Option<LogIdsV2>,
+    /// Why the records were rejected, if they were.
+    error: Option<ErrorV2>,
+}
+
+/// The log IDs in a version 2 response, in the order of the submitted records.
+#[derive(Deserialize, Debug)]
+struct LogIdsV2 {
+    /// Unique identifiers assigned to the records.
+    log_ids: Vec<String>,
+}
+
+/// A structured error in a version 2 response.
+#[derive(Deserialize, Debug)]
+struct ErrorV2 {
+    /// Machine-readable error code.
+    code: String,
+    /// Human-readable description.
+    message: String,
+}
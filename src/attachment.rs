@@ -0,0 +1,331 @@
+//! Binary attachments for log events.
+//!
+//! Small binary artifacts, such as a save-state snippet, a screenshot thumbnail or a compressed
+//! repro blob, can be attached to an event with `attach`. Attachments are queued on the current
+//! thread and picked up by the next event that `PogrLayer` records on that thread, so they
+//! belong to that event. Like the event, they are submitted in the background. With several
+//! `PogrLayer`s in the subscriber stack, each of them submits the attachments along with its
+//! record of the event.
+//!
+//! Attachments are checked against the layer's `AttachmentConfig` when the event is captured.
+//! Attachments that exceed the limits are dropped, and their names are listed in the event's
+//! `pogr.dropped_attachments` tag.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Serialize;
+use crate::layer_scope::LayerId;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::fmt;
+use tracing::Event;
+
+/// Default maximum size, in bytes, of a single attachment.
+pub const DEFAULT_MAX_ATTACHMENT_SIZE: usize = 256 * 1024;
+
+/// Default maximum combined size, in bytes, of the attachments of one event.
+pub const DEFAULT_MAX_ATTACHMENTS_SIZE: usize = 512 * 1024;
+
+/// Tag listing the names of attachments that were dropped.
+const DROPPED_ATTACHMENTS_TAG: &str = "pogr.dropped_attachments";
+
+thread_local! {
+    /// Attachments waiting for the next event recorded on this thread.
+    static PENDING: RefCell<Pending> = const {
+        RefCell::new(Pending {
+            queued: Vec::new(),
+            claimed: Vec::new(),
+            event: 0,
+            layers: Vec::new(),
+        })
+    };
+}
+
+/// Attachments of the current thread, kept until every layer has seen their event.
+struct Pending {
+    /// Attachments waiting for the next event.
+    queued: Vec<Attachment>,
+    /// Attachments of the last event that picked them up.
+    claimed: Vec<Attachment>,
+    /// Address of that event.
+    event: usize,
+    /// Layers that have taken the claimed attachments.
+    layers: Vec<LayerId>,
+}
+
+/// A binary artifact submitted together with a log event.
+///
+/// # Examples
+///
+/// ```
+/// use pogr_tracing_rs::Attachment;
+///
+/// let attachment = Attachment::new("save.bin", "application/octet-stream", vec![0xde, 0xad, 0xbe, 0xef]);
+///
+/// assert_eq!(attachment.name(), "save.bin");
+/// assert_eq!(attachment.size(), 4);
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// File name of the attachment.
+    name: String,
+    /// MIME type of the attachment, e.g. `image/png`.
+    content_type: String,
+    /// Contents of the attachment.
+    data: Vec<u8>,
+}
+
+impl Attachment {
+    /// Creates an attachment.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The file name shown for the attachment, e.g. `thumbnail.png`.
+    /// * `content_type` - The MIME type of the contents, e.g. `image/png`.
+    /// * `data` - The contents of the attachment.
+    pub fn new(name: impl Into<String>, content_type: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        Attachment {
+            name: name.into(),
+            content_type: content_type.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Returns the file name of the attachment.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the MIME type of the attachment.
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    /// Returns the contents of the attachment.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the size of the contents in bytes.
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/// Shows the attachment's metadata only; the contents can be large and are binary.
+impl fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attachment")
+            .field("name", &self.name)
+            .field("content_type", &self.content_type)
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+/// How attachments are transferred to the intake.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AttachmentEncoding {
+    /// Attachments are embedded in the JSON payload as base64 strings. Events with attachments
+    /// can be batched with other events.
+    #[default]
+    Base64,
+    /// Events with attachments are sent on their own as `multipart/form-data`, with the JSON
+    /// payload in the `log` part and each attachment as a raw binary part. This avoids the
+    /// base64 overhead of a third.
+    Multipart,
+}
+
+/// Limits and encoding for event attachments.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{AttachmentConfig, AttachmentEncoding, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_attachments(
+///     AttachmentConfig::new()
+///         .with_max_attachment_size(64 * 1024)
+///         .with_encoding(AttachmentEncoding::Multipart),
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttachmentConfig {
+    /// Maximum size of a single attachment in bytes.
+    max_attachment_size: usize,
+    /// Maximum combined size of the attachments of one event in bytes.
+    max_attachments_size: usize,
+    /// How attachments are transferred.
+    encoding: AttachmentEncoding,
+}
+
+impl AttachmentConfig {
+    /// Creates the default configuration: 256 KiB per attachment, 512 KiB per event, base64.
+    pub fn new() -> Self {
+        AttachmentConfig {
+            max_attachment_size: DEFAULT_MAX_ATTACHMENT_SIZE,
+            max_attachments_size: DEFAULT_MAX_ATTACHMENTS_SIZE,
+            encoding: AttachmentEncoding::default(),
+        }
+    }
+
+    /// Sets the maximum size of a single attachment, in bytes.
+    pub fn with_max_attachment_size(mut self, max_attachment_size: usize) -> Self {
+        self.max_attachment_size = max_attachment_size;
+        self
+    }
+
+    /// Sets the maximum combined size of the attachments of one event, in bytes.
+    pub fn with_max_attachments_size(mut self, max_attachments_size: usize) -> Self {
+        self.max_attachments_size = max_attachments_size;
+        self
+    }
+
+    /// Sets how attachments are transferred to the intake.
+    pub fn with_encoding(mut self, encoding: AttachmentEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Returns the maximum size of a single attachment in bytes.
+    pub fn max_attachment_size(&self) -> usize {
+        self.max_attachment_size
+    }
+
+    /// Returns the maximum combined size of the attachments of one event in bytes.
+    pub fn max_attachments_size(&self) -> usize {
+        self.max_attachments_size
+    }
+
+    /// Returns how attachments are transferred to the intake.
+    pub fn encoding(&self) -> AttachmentEncoding {
+        self.encoding
+    }
+
+    /// Splits `attachments` into those within the limits and the names of those that are not.
+    ///
+    /// Attachments are accepted in the order they were attached until the combined limit is
+    /// reached.
+    pub(crate) fn apply_limits(&self, attachments: Vec<Attachment>) -> (Vec<Attachment>, Vec<String>) {
+        let mut accepted = Vec::new();
+        let mut dropped = Vec::new();
+        let mut total = 0;
+
+        for attachment in attachments {
+            if attachment.size() <= self.max_attachment_size && total + attachment.size() <= self.max_attachments_size {
+                total += attachment.size();
+                accepted.push(attachment);
+            } else {
+                dropped.push(attachment.name);
+            }
+        }
+
+        (accepted, dropped)
+    }
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Attaches a binary artifact to the next event recorded on the current thread.
+///
+/// Several attachments can be queued before the event. Attachments are only picked up by a
+/// `PogrLayer`; if no event follows on this thread, they are never sent.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{attach, Attachment};
+///
+/// # let thumbnail: Vec<u8> = Vec::new();
+/// attach(Attachment::new("desync.png", "image/png", thumbnail));
+/// tracing::error!(frame = 1024, "state desync detected");
+/// ```
+pub fn attach(attachment: Attachment) {
+    PENDING.with(|pending| pending.borrow_mut().queued.push(attachment));
+}
+
+/// Returns the attachments of `event` for the layer `layer`.
+///
+/// The first layer recording the event claims the attachments queued on the current thread.
+/// Other layers recording the same event get copies; a layer asking again, or a later
+/// event, gets none.
+pub(crate) fn take_pending(event: &Event<'_>, layer: LayerId) -> Vec<Attachment> {
+    let address = event as *const Event<'_> as usize;
+    PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        if !pending.queued.is_empty() {
+            pending.claimed = std::mem::take(&mut pending.queued);
+            pending.event = address;
+            pending.layers = vec![layer];
+            return pending.claimed.clone();
+        }
+        if pending.event == address && !pending.layers.contains(&layer) {
+            pending.layers.push(layer);
+            return pending.claimed.clone();
+        }
+        pending.claimed.clear();
+        Vec::new()
+    })
+}
+
+/// Adds the names of dropped attachments to an event's tags.
+pub(crate) fn annotate_dropped(tags: &mut Value, dropped: &[String]) {
+    if dropped.is_empty() {
+        return;
+    }
+
+    if let Value::Object(tags) = tags {
+        let names = tags.entry(DROPPED_ATTACHMENTS_TAG).or_insert_with(|| json!([]));
+        if let Value::Array(names) = names {
+            names.extend(dropped.iter().map(|name| json!(name)));
+        }
+    }
+}
+
+/// The description of an attachment included in the JSON payload.
+#[derive(Serialize, Debug)]
+pub(crate) struct AttachmentManifest<'a> {
+    /// File name of the attachment.
+    name: &'a str,
+    /// MIME type of the attachment.
+    content_type: &'a str,
+    /// Size of the contents in bytes, before encoding.
+    size: usize,
+    /// Base64-encoded contents, for `AttachmentEncoding::Base64`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    /// Name of the multipart part holding the contents, for `AttachmentEncoding::Multipart`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<String>,
+}
+
+impl<'a> AttachmentManifest<'a> {
+    /// Describes an attachment whose contents are embedded as base64.
+    pub(crate) fn inline(attachment: &'a Attachment) -> Self {
+        AttachmentManifest {
+            name: &attachment.name,
+            content_type: &attachment.content_type,
+            size: attachment.size(),
+            data: Some(STANDARD.encode(&attachment.data)),
+            part: None,
+        }
+    }
+
+    /// Describes an attachment whose contents are sent in the multipart part `part`.
+    pub(crate) fn multipart(attachment: &'a Attachment, part: String) -> Self {
+        AttachmentManifest {
+            name: &attachment.name,
+            content_type: &attachment.content_type,
+            size: attachment.size(),
+            data: None,
+            part: Some(part),
+        }
+    }
+}
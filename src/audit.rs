@@ -0,0 +1,110 @@
+//! Validation of audit events.
+//!
+//! Audit records must say who did what to which resource; a record missing one of these is
+//! worse than useless, because it looks complete in POGR. In audit mode, the layer checks the
+//! events of audit targets, and the events classified as audit with a `classification =
+//! "audit"` field, for the required fields (by default `actor`, `action` and `resource`).
+//!
+//! Fields may come from the event itself or from its spans and contexts. An event missing a
+//! required field, or carrying it with a null or empty value, is not sent: it is reported as
+//! an `Audit` diagnostic instead, naming the missing fields, so incomplete records are caught
+//! locally rather than shipped.
+
+use crate::filter;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Fields every audit event must carry by default.
+pub const DEFAULT_AUDIT_FIELDS: &[&str] = &["actor", "action", "resource"];
+
+/// Field classifying an event.
+const CLASSIFICATION_FIELD: &str = "classification";
+
+/// Classification of audit events.
+const AUDIT_CLASSIFICATION: &str = "audit";
+
+/// Which events are audit events, and the fields they must carry.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{AuditPolicy, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_audit(
+///     AuditPolicy::new()
+///         .with_targets(["admin", "economy::trades"])
+///         .with_required_fields(["actor", "action", "resource", "reason"]),
+/// );
+///
+/// // Events classified as audit are validated whatever their target.
+/// tracing::info!(classification = "audit", actor = "gm-7", action = "ban", resource = "player-42", "Player banned");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AuditPolicy {
+    /// Target prefixes whose events are all audit events.
+    targets: Vec<String>,
+    /// Fields every audit event must carry.
+    required_fields: Vec<String>,
+}
+
+impl Default for AuditPolicy {
+    fn default() -> Self {
+        AuditPolicy::new()
+    }
+}
+
+impl AuditPolicy {
+    /// Validates the events classified as audit against `DEFAULT_AUDIT_FIELDS`.
+    pub fn new() -> Self {
+        AuditPolicy {
+            targets: Vec::new(),
+            required_fields: DEFAULT_AUDIT_FIELDS.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+
+    /// Also treats every event whose target matches one of `targets` as an audit event.
+    ///
+    /// A target matches itself and every module below it.
+    pub fn with_targets<I, T>(mut self, targets: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.targets.extend(targets.into_iter().map(Into::into));
+        self
+    }
+
+    /// Replaces the fields every audit event must carry.
+    pub fn with_required_fields<I, T>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.required_fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns the required fields missing from an event, or `None` if it is not an audit
+    /// event.
+    pub(crate) fn missing_fields(&self, target: &str, fields: &HashMap<String, Value>) -> Option<Vec<&str>> {
+        let classified = fields.get(CLASSIFICATION_FIELD).and_then(Value::as_str) == Some(AUDIT_CLASSIFICATION);
+        if !classified && !self.targets.iter().any(|prefix| filter::target_matches(target, prefix)) {
+            return None;
+        }
+
+        let missing = self
+            .required_fields
+            .iter()
+            .filter(|field| match fields.get(field.as_str()) {
+                None | Some(Value::Null) => true,
+                Some(Value::String(value)) => value.is_empty(),
+                Some(_) => false,
+            })
+            .map(String::as_str)
+            .collect();
+        Some(missing)
+    }
+}
@@ -0,0 +1,73 @@
+//! A signal telling when the delivery pipeline is saturated.
+//!
+//! Logging an event is cheap, but not free, and every event queued while the intake cannot
+//! keep up makes the backlog worse. Latency-critical code, such as a game tick, can check the
+//! signal and skip optional logging while the pipeline is backpressured:
+//!
+//! ```
+//! # fn tick(guard: &pogr_tracing_rs::PogrGuard) {
+//! if !guard.is_backpressured() {
+//!     tracing::debug!(entities = 1204, "Tick simulated");
+//! }
+//! # }
+//! ```
+//!
+//! The pipeline is backpressured once the number of records waiting for delivery reaches the
+//! threshold, and until it drains to half of it, so the signal does not flap around the
+//! threshold.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::watch;
+
+/// Default number of pending records at which the pipeline is backpressured.
+pub const DEFAULT_BACKPRESSURE_THRESHOLD: usize = 10_000;
+
+/// Whether the pipeline of a layer is backpressured.
+#[derive(Debug)]
+pub(crate) struct Backpressure {
+    /// Number of pending records at which the pipeline is backpressured.
+    threshold: AtomicUsize,
+    /// The current state, watched by the application.
+    signal: watch::Sender<bool>,
+}
+
+impl Default for Backpressure {
+    fn default() -> Self {
+        Backpressure {
+            threshold: AtomicUsize::new(DEFAULT_BACKPRESSURE_THRESHOLD),
+            signal: watch::channel(false).0,
+        }
+    }
+}
+
+impl Backpressure {
+    /// Sets the number of pending records at which the pipeline is backpressured.
+    pub(crate) fn set_threshold(&self, threshold: usize) {
+        self.threshold.store(threshold.max(1), Ordering::Relaxed);
+    }
+
+    /// Updates the state for the number of pending records.
+    pub(crate) fn update(&self, pending: usize) {
+        let threshold = self.threshold.load(Ordering::Relaxed);
+        let backpressured = if pending >= threshold {
+            true
+        } else if pending <= threshold / 2 {
+            false
+        } else {
+            return;
+        };
+        if *self.signal.borrow() != backpressured {
+            self.signal.send_if_modified(|state| std::mem::replace(state, backpressured) != backpressured);
+        }
+    }
+
+    /// Returns `true` while the pipeline is backpressured.
+    pub(crate) fn is_backpressured(&self) -> bool {
+        *self.signal.borrow()
+    }
+
+    /// Returns a receiver notified whenever the state changes.
+    pub(crate) fn subscribe(&self) -> watch::Receiver<bool> {
+        self.signal.subscribe()
+    }
+}
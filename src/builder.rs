@@ -0,0 +1,250 @@
+//! Step-by-step construction of a `PogrAppender`.
+//!
+//! `PogrAppender::new` only takes the endpoints. The builder additionally describes the
+//! session in the init request (the game or service build, the platform, and arbitrary
+//! key/value metadata), so that sessions are attributed correctly in POGR from the start.
+
+use crate::validation::{ValidationError, ValidationReport};
+use crate::{check_endpoints, connection, endpoints, env_capture, ArgRedaction, LocaleInfo, init_failed, DistributionPlatform, IntakeApiVersion, PogrAppender};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Details about a session sent in the init request.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SessionMetadata {
+    /// Identifier of the build, e.g. a version or CI build number.
+    pub(crate) build_id: Option<String>,
+    /// Platform the session runs on, e.g. `steam` or `linux-x86_64`.
+    pub(crate) platform: Option<String>,
+    /// Arbitrary key/value metadata.
+    pub(crate) fields: Map<String, Value>,
+    /// Whether the hosting environment is detected and sent with the session.
+    pub(crate) detect_hosting: bool,
+    /// The only version of the intake API offered in the init request, if pinned.
+    pub(crate) api_version: Option<IntakeApiVersion>,
+    /// How the process's arguments are redacted, if the process is described in the init
+    /// request.
+    pub(crate) process: Option<ArgRedaction>,
+}
+
+impl Default for SessionMetadata {
+    fn default() -> Self {
+        SessionMetadata {
+            build_id: None,
+            platform: None,
+            fields: Map::new(),
+            detect_hosting: true,
+            api_version: None,
+            process: Some(ArgRedaction::new()),
+        }
+    }
+}
+
+/// Metadata of the sessions initialized in this process, by session ID.
+///
+/// A session re-initialized after the intake refused its predecessor is described by the same
+/// metadata. It is kept here rather than on `PogrAppender`, whose fields are public and which
+/// is built with struct literals.
+fn sessions() -> &'static Mutex<HashMap<String, SessionMetadata>> {
+    static SESSIONS: OnceLock<Mutex<HashMap<String, SessionMetadata>>> = OnceLock::new();
+    SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Remembers the metadata a session was initialized with.
+pub(crate) fn remember_session(session_id: &str, session: &SessionMetadata) {
+    let mut sessions = sessions().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    sessions.insert(session_id.to_string(), session.clone());
+}
+
+/// Returns the metadata a session was initialized with, or the default metadata of `new` for
+/// sessions that were not initialized by this process.
+pub(crate) fn session_metadata(session_id: &str) -> SessionMetadata {
+    let sessions = sessions().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    sessions.get(session_id).cloned().unwrap_or_default()
+}
+
+/// Builds a `PogrAppender` with session metadata.
+///
+/// Create one with `PogrAppender::builder`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::PogrAppender;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::builder()
+///     .with_build_id("2024.03.1-4711")
+///     .with_platform("steam")
+///     .with_session_metadata("region", "eu-west")
+///     .with_session_metadata("players", 4)
+///     .build()
+///     .await;
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PogrAppenderBuilder {
+    /// Custom URL of the init endpoint.
+    init_endpoint: Option<String>,
+    /// Custom URL of the logs endpoint.
+    logs_endpoint: Option<String>,
+    /// Details sent in the init request.
+    session: SessionMetadata,
+    /// Whether plain `http` endpoints are allowed.
+    allow_insecure: bool,
+}
+
+impl PogrAppenderBuilder {
+    /// Sets the URL of the init endpoint, instead of `POGR_INIT_ENDPOINT` or the default.
+    ///
+    /// The URL may contain the `{service}`, `{environment}` and `{type}` placeholders.
+    pub fn with_init_endpoint(mut self, init_endpoint: impl Into<String>) -> Self {
+        self.init_endpoint = Some(init_endpoint.into());
+        self
+    }
+
+    /// Sets the URL of the logs endpoint, instead of `POGR_LOGS_ENDPOINT` or the default.
+    ///
+    /// The URL may contain the `{service}`, `{environment}`, `{type}` and `{session}`
+    /// placeholders, resolved for every request.
+    pub fn with_logs_endpoint(mut self, logs_endpoint: impl Into<String>) -> Self {
+        self.logs_endpoint = Some(logs_endpoint.into());
+        self
+    }
+
+    /// Pins the version of the intake API, instead of negotiating the newest version both the
+    /// client and the intake support.
+    ///
+    /// The logs endpoint is switched to the version's path. Initialization fails if the intake
+    /// does not select it.
+    pub fn with_api_version(mut self, version: IntakeApiVersion) -> Self {
+        self.session.api_version = Some(version);
+        self
+    }
+
+    /// Allows endpoints with plain `http` URLs, e.g. an on-prem collector inside a VPC whose
+    /// TLS is terminated elsewhere.
+    ///
+    /// Endpoints must use `https` otherwise, except for loopback hosts such as `localhost`.
+    /// Setting `POGR_ALLOW_INSECURE` to `1` allows them too.
+    pub fn with_allow_insecure(mut self, allow_insecure: bool) -> Self {
+        self.allow_insecure = allow_insecure;
+        self
+    }
+
+    /// Sets the build the session runs, e.g. a game build ID or a CI build number.
+    pub fn with_build_id(mut self, build_id: impl Into<String>) -> Self {
+        self.session.build_id = Some(build_id.into());
+        self
+    }
+
+    /// Sets the platform the session runs on, e.g. `steam`, `epic` or `ps5`.
+    pub fn with_platform(mut self, platform: impl Into<String>) -> Self {
+        self.session.platform = Some(platform.into());
+        self
+    }
+
+    /// Attributes the session to the distribution platform the game was launched from.
+    ///
+    /// If `DistributionPlatform::detect` recognizes the platform, this sets it like
+    /// `with_platform` and adds the game's identifier on it, e.g. the Steam app ID, as the
+    /// `platform_id` session metadata. Otherwise the session is left unchanged.
+    pub fn with_platform_detection(mut self) -> Self {
+        if let Some(platform) = DistributionPlatform::detect() {
+            if let Some(id) = platform.id() {
+                self.session.fields.insert("platform_id".to_string(), Value::from(id));
+            }
+            self.session.platform = Some(platform.name().to_string());
+        }
+        self
+    }
+
+    /// Describes the session with the locale, region and timezone of the machine.
+    ///
+    /// What `LocaleInfo::detect` finds is added to the session metadata as `locale`,
+    /// `region`, `timezone` and `utc_offset`.
+    pub fn with_locale_detection(mut self) -> Self {
+        self.session.fields.extend(LocaleInfo::detect().tags());
+        self
+    }
+
+    /// Enables or disables detecting where the service runs.
+    ///
+    /// Hosting detection is enabled by default. Setting `POGR_DETECT_HOSTING` to `0` turns it
+    /// off regardless of this setting. The detected `Hosting` is sent with the session, and if `ENVIRONMENT` is not set, a
+    /// Kubernetes pod's namespace is used as the environment instead of `development`.
+    pub fn with_hosting_detection(mut self, enabled: bool) -> Self {
+        self.session.detect_hosting = enabled;
+        self
+    }
+
+    /// Attaches the allowlisted environment variables, e.g. `REGION` or `DEPLOY_ID`, to the
+    /// session.
+    ///
+    /// The variables are read once, now, and the ones that are set are sent in the `env`
+    /// object of the session metadata. No other variable is read, so secrets in the
+    /// environment are never captured. Use `PogrLayer::with_env_capture` to tag every event
+    /// with them as well.
+    pub fn with_env_capture<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        env_capture::insert_into_session(&mut self.session.fields, env_capture::capture(names));
+        self
+    }
+
+    /// Enables or disables describing the process in the init request.
+    ///
+    /// The process is described by default: its command-line arguments, redacted with
+    /// `ArgRedaction::new` unless `with_arg_redaction` is used, the operating system and its
+    /// version, and the CPU architecture.
+    pub fn with_process_metadata(mut self, enabled: bool) -> Self {
+        self.session.process = enabled.then(|| self.session.process.take().unwrap_or_default());
+        self
+    }
+
+    /// Sets how the command-line arguments sent with the session are redacted, and enables
+    /// describing the process if it was disabled.
+    pub fn with_arg_redaction(mut self, redaction: ArgRedaction) -> Self {
+        self.session.process = Some(redaction);
+        self
+    }
+
+    /// Adds a key/value pair to the session metadata, replacing an earlier value for the key.
+    pub fn with_session_metadata(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.session.fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// Initializes the session and returns the appender.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an endpoint is not a valid `https` URL, unless plain `http` is allowed, if
+    /// session initialization fails or if required environment variables are missing, like
+    /// `PogrAppender::new`.
+    pub async fn build(self) -> PogrAppender {
+        let (init_endpoint_url, logs_endpoint_url) = endpoints(self.init_endpoint, self.logs_endpoint);
+        check_endpoints(&init_endpoint_url, &logs_endpoint_url, self.allow_insecure || connection::allow_insecure_from_env())
+            .unwrap_or_else(|message| init_failed(message));
+        PogrAppender::initialize(connection::new_client(), init_endpoint_url, logs_endpoint_url, &self.session)
+            .await
+            .unwrap_or_else(|message| init_failed(message))
+    }
+
+    /// Initializes the session after verifying that logs can be delivered, like
+    /// `PogrAppender::new_validated`.
+    pub async fn build_validated(self) -> Result<(PogrAppender, ValidationReport), ValidationError> {
+        let allow_insecure = self.allow_insecure || connection::allow_insecure_from_env();
+        PogrAppender::validate(self.init_endpoint, self.logs_endpoint, &self.session, allow_insecure).await
+    }
+}
+
+impl PogrAppender {
+    /// Returns a builder for an appender with session metadata.
+    pub fn builder() -> PogrAppenderBuilder {
+        PogrAppenderBuilder::default()
+    }
+}
@@ -0,0 +1,113 @@
+//! Submitting records that did not originate from `tracing` events.
+//!
+//! Some records are not events of the running application: historical data being imported,
+//! records relayed from another system, or reports assembled from several events. A
+//! `PogrClient` submits such `LogRequest`s through the same delivery pipeline as the layer's
+//! events, so they are batched, retried, routed and mirrored the same way, without going
+//! through `tracing` and its filters.
+
+use crate::{FlushTimedOut, LogRequest, PogrAppender, PogrGuard, PogrHandle, PogrLayer};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+/// Submits `LogRequest`s directly, through the delivery pipeline of a `PogrLayer`.
+///
+/// A client is obtained from a layer with `PogrLayer::client`, sharing its pipeline with the
+/// events captured from `tracing`, or created on its own with `PogrClient::new`. Submitting
+/// only queues the records; `flush` waits until they are delivered.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{LogRequest, PogrAppender, PogrClient};
+/// use serde_json::json;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let client = PogrClient::new(appender);
+///
+/// let imported = LogRequest {
+///     service: "matchmaker".to_string(),
+///     environment: "production".to_string(),
+///     severity: "INFO".to_string(),
+///     r#type: "server".to_string(),
+///     log: "match finished".to_string(),
+///     data: json!({ "match_id": 1042 }),
+///     tags: json!({ "source": "import" }),
+/// };
+/// client.submit_at(UNIX_EPOCH + Duration::from_secs(1_700_000_000), imported);
+/// client.flush().await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PogrClient {
+    /// Queues records for the layer's workers.
+    handle: PogrHandle,
+    /// Waits for the layer's submissions.
+    guard: Arc<PogrGuard>,
+}
+
+impl PogrClient {
+    /// Creates a client with a pipeline of its own, with the default settings of a
+    /// `PogrLayer`.
+    ///
+    /// To batch, route or mirror the records differently, configure a layer and use
+    /// `PogrLayer::client` instead; the layer does not need to be added to a subscriber.
+    pub fn new(appender: PogrAppender) -> Self {
+        PogrLayer::new(appender).client()
+    }
+
+    /// Creates a client for the pipeline of a layer.
+    pub(crate) fn for_layer(layer: &PogrLayer) -> Self {
+        PogrClient {
+            handle: layer.handle(),
+            guard: Arc::new(layer.guard()),
+        }
+    }
+
+    /// Queues a log request, timestamped now, and returns its `event_id`.
+    ///
+    /// The request is sent as is, and routed by its `severity`, as `INFO` if it is not a level.
+    pub fn submit(&self, request: LogRequest) -> Uuid {
+        self.handle.log(request)
+    }
+
+    /// Queues a log request captured at `timestamp`, e.g. when importing historical data, and
+    /// returns its `event_id`.
+    ///
+    /// With an event time-to-live, records are dropped by the age of their timestamp, so
+    /// historical data should be imported through a layer without one.
+    pub fn submit_at(&self, timestamp: SystemTime, request: LogRequest) -> Uuid {
+        self.handle.log_at(Some(timestamp), request)
+    }
+
+    /// Queues several log requests, timestamped now, and returns their `event_id`s in order.
+    ///
+    /// The requests are batched according to the layer's batching settings, like events.
+    pub fn submit_batch(&self, requests: impl IntoIterator<Item = LogRequest>) -> Vec<Uuid> {
+        requests.into_iter().map(|request| self.submit(request)).collect()
+    }
+
+    /// Queues several log requests with the times they were captured at, and returns their
+    /// `event_id`s in order.
+    pub fn submit_batch_at(&self, requests: impl IntoIterator<Item = (SystemTime, LogRequest)>) -> Vec<Uuid> {
+        requests
+            .into_iter()
+            .map(|(timestamp, request)| self.submit_at(timestamp, request))
+            .collect()
+    }
+
+    /// Waits until every record queued in the pipeline has been delivered or handed to the
+    /// fallback sinks.
+    pub async fn flush(&self) {
+        self.guard.flush().await;
+    }
+
+    /// Waits until every record queued in the pipeline has been delivered, or `timeout` has
+    /// passed.
+    pub async fn flush_timeout(&self, timeout: Duration) -> Result<(), FlushTimedOut> {
+        self.guard.flush_timeout(timeout).await
+    }
+}
@@ -0,0 +1,275 @@
+//! Clock sources used to timestamp log messages.
+//!
+//! The wall clock can jump: NTP steps it, users change it, laptops resume from suspend. Reading
+//! `SystemTime::now()` for every event therefore produces timestamps that can go backwards or
+//! leap ahead between two events that were emitted milliseconds apart. `MonotonicClock` avoids
+//! this by reading the wall clock once as an anchor and adding monotonic elapsed time to it.
+//!
+//! Timestamps are taken through the `Clock` trait, so tests can inject a `ManualClock` and get
+//! deterministic payloads.
+
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A source of timestamps for log messages.
+///
+/// Implementations must be cheap to call, since the clock is read once for every captured event.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A clock combining a wall-clock anchor with monotonic elapsed time.
+///
+/// The wall clock is read once, when the clock is created. Every later reading is the anchor
+/// plus the time elapsed on the monotonic clock, so timestamps never go backwards and are not
+/// affected by wall-clock adjustments made while the process runs.
+///
+/// # Examples
+///
+/// ```
+/// use pogr_tracing_rs::{Clock, MonotonicClock};
+///
+/// let clock = MonotonicClock::new();
+/// let first = clock.now();
+/// let second = clock.now();
+///
+/// assert!(second >= first);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicClock {
+    /// Wall-clock time at creation.
+    wall_anchor: SystemTime,
+    /// Monotonic time at creation.
+    monotonic_anchor: Instant,
+}
+
+impl MonotonicClock {
+    /// Creates a clock anchored at the current wall-clock time.
+    pub fn new() -> Self {
+        MonotonicClock {
+            wall_anchor: SystemTime::now(),
+            monotonic_anchor: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> SystemTime {
+        self.wall_anchor + self.monotonic_anchor.elapsed()
+    }
+}
+
+/// A clock that reads the wall clock directly on every call.
+///
+/// Use this when timestamps must follow wall-clock adjustments, e.g. for very long-running
+/// processes without reliable time synchronization at startup.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to, for deterministic tests.
+///
+/// # Examples
+///
+/// ```
+/// use pogr_tracing_rs::{Clock, ManualClock};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+/// clock.advance(Duration::from_millis(250));
+///
+/// assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_millis(1_700_000_000_250));
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    /// The time returned by `now`.
+    now: Mutex<SystemTime>,
+}
+
+impl ManualClock {
+    /// Creates a clock frozen at `start`.
+    pub fn new(start: SystemTime) -> Self {
+        ManualClock {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    /// Sets the clock to `time`.
+    pub fn set(&self, time: SystemTime) {
+        *self.now.lock().unwrap() = time;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Allows sharing one clock between a test and the layer it configures.
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// Returns the process-wide default clock, a `MonotonicClock` anchored on first use.
+pub(crate) fn default_clock() -> Arc<dyn Clock> {
+    static DEFAULT_CLOCK: OnceLock<Arc<MonotonicClock>> = OnceLock::new();
+    DEFAULT_CLOCK.get_or_init(|| Arc::new(MonotonicClock::new())).clone()
+}
+
+/// Formats a timestamp as an RFC 3339 UTC string with millisecond precision,
+/// e.g. `2024-03-01T12:30:05.042Z`.
+pub(crate) fn format_rfc3339(time: SystemTime) -> String {
+    // Times before the epoch are not meaningful for log messages; clamp them to the epoch.
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let millis = since_epoch.subsec_millis();
+
+    let days = (seconds / 86_400) as i64;
+    let seconds_of_day = seconds % 86_400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60,
+        millis
+    )
+}
+
+/// Converts days since the Unix epoch into a proleptic Gregorian (year, month, day).
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Parses an HTTP `Date` header in the IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// # Returns
+///
+/// The time, or `None` if the value is not an IMF-fixdate after the Unix epoch.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let mut parts = value.split_whitespace();
+    let _weekday = parts.next()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':');
+    let hour: u64 = time.next()?.parse().ok()?;
+    let minute: u64 = time.next()?.parse().ok()?;
+    let second: u64 = time.next()?.parse().ok()?;
+    if parts.next()? != "GMT" || parts.next().is_some() || time.next().is_some() {
+        return None;
+    }
+    if !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86_400 + hour * 3_600 + minute * 60 + second))
+}
+
+/// Converts a proleptic Gregorian (year, month, day) into days since the Unix epoch.
+///
+/// This is Howard Hinnant's `days_from_civil` algorithm, the inverse of `civil_from_days`.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let day_of_year = (153 * month_index + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parses an RFC 3339 timestamp such as `2024-03-01T12:30:05.042Z` or
+/// `2024-03-01T14:30:05+02:00`.
+///
+/// # Returns
+///
+/// The time, or `None` if the value is not a valid RFC 3339 timestamp after the Unix epoch.
+pub(crate) fn parse_rfc3339(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Split off the offset: `Z`, or a sign followed by `hh:mm`.
+    let (time, offset_seconds) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else {
+        let sign_at = time.rfind(['+', '-'])?;
+        let (time, offset) = time.split_at(sign_at);
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let seconds = hours.parse::<i64>().ok()? * 3_600 + minutes.parse::<i64>().ok()? * 60;
+        (time, if offset.starts_with('-') { -seconds } else { seconds })
+    };
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time_parts = time.splitn(3, ':');
+    let hour: i64 = time_parts.next()?.parse().ok()?;
+    let minute: i64 = time_parts.next()?.parse().ok()?;
+    let second: i64 = time_parts.next()?.parse().ok()?;
+    if hour > 23 || minute > 59 || second > 60 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let nanos: u32 = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse().ok()?;
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second - offset_seconds;
+    let seconds = u64::try_from(seconds).ok()?;
+    Some(UNIX_EPOCH + Duration::new(seconds, nanos))
+}
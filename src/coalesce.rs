@@ -0,0 +1,172 @@
+//! Coalescing repeated messages.
+//!
+//! A failing dependency or a tight retry loop can log the same message thousands of times a
+//! second, drowning out everything else. With coalescing, the first occurrence of a message is
+//! sent as usual and opens a window; identical messages within the window are only counted.
+//! When the window closes, one more event is sent for them, carrying the number of repeats,
+//! when the first and last of them happened, and the fields of the first repeat as a
+//! representative set: the classic "last message repeated N times".
+//!
+//! Messages are identical when they come from the same callsite with the same `message`,
+//! whatever their other fields. Events with attachments are never coalesced.
+
+use crate::pipeline::InFlight;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::runtime::Handle;
+use tracing::callsite::Identifier;
+
+/// Number of tracked messages above which expired windows are pruned.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Configuration for coalescing repeated messages.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{Coalescing, PogrAppender, PogrLayer};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_coalescing(Coalescing::new(Duration::from_secs(10)));
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Coalescing {
+    /// How long repeats of a message are counted after its first occurrence.
+    window: Duration,
+}
+
+impl Coalescing {
+    /// Counts the repeats of a message for `window` after its first occurrence.
+    pub fn new(window: Duration) -> Self {
+        Coalescing { window }
+    }
+}
+
+/// Identifies identical messages: their callsite and their `message`.
+pub(crate) type MessageKey = (Identifier, String);
+
+/// The outcome of observing a message.
+pub(crate) enum Observed {
+    /// The message opens a new window and is sent as usual.
+    First,
+    /// The message is the first repeat in its window; the repeats must be emitted once the
+    /// window closes, at the given instant.
+    FirstRepeat(Instant),
+    /// The message is counted as a further repeat.
+    Repeat,
+}
+
+/// Repeats counted in a window.
+pub(crate) struct Repeats {
+    /// Number of repeats, not counting the first occurrence.
+    pub(crate) count: u64,
+    /// When the first repeat happened.
+    pub(crate) first_seen: SystemTime,
+    /// When the last repeat happened.
+    pub(crate) last_seen: SystemTime,
+    /// Fields of the first repeat.
+    pub(crate) tags: Value,
+}
+
+/// The window opened by a message.
+struct Window {
+    /// When the window closes.
+    closes: Instant,
+    /// The repeats counted so far, if any.
+    repeats: Option<Repeats>,
+}
+
+/// Tracks the windows of recent messages.
+pub(crate) struct Coalescer {
+    /// How long windows stay open.
+    window: Duration,
+    /// Open windows, by message.
+    windows: Mutex<HashMap<MessageKey, Window>>,
+}
+
+impl Coalescer {
+    /// Creates a tracker with the configured window.
+    pub(crate) fn new(config: Coalescing) -> Self {
+        Coalescer {
+            window: config.window,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Observes a message, counting it if it repeats one whose window is open.
+    ///
+    /// Once a window has repeats, it stays open until they are taken.
+    pub(crate) fn observe(&self, key: MessageKey, tags: &Value, now: SystemTime) -> Observed {
+        let mut windows = self.windows.lock().unwrap_or_else(|p| p.into_inner());
+        let opened = Instant::now();
+
+        if let Some(window) = windows.get_mut(&key) {
+            match &mut window.repeats {
+                Some(repeats) => {
+                    repeats.count += 1;
+                    repeats.last_seen = now;
+                    return Observed::Repeat;
+                }
+                None if opened < window.closes => {
+                    window.repeats = Some(Repeats {
+                        count: 1,
+                        first_seen: now,
+                        last_seen: now,
+                        tags: tags.clone(),
+                    });
+                    return Observed::FirstRepeat(window.closes);
+                }
+                None => {}
+            }
+        }
+
+        if windows.len() >= PRUNE_THRESHOLD {
+            windows.retain(|_, window| window.repeats.is_some() || opened < window.closes);
+        }
+        windows.insert(
+            key,
+            Window {
+                closes: opened + self.window,
+                repeats: None,
+            },
+        );
+        Observed::First
+    }
+
+    /// Waits until the window of a message closes, or until the layer is flushed, then takes
+    /// its repeats and hands them to `emit`.
+    ///
+    /// The window counts as in flight while it waits, so flushing the layer waits for its
+    /// repeats to be emitted. The wait runs on `runtime`.
+    pub(crate) fn schedule<F>(
+        self: &Arc<Self>,
+        key: MessageKey,
+        closes: Instant,
+        in_flight: &Arc<InFlight>,
+        runtime: &Handle,
+        emit: F,
+    ) where
+        F: FnOnce(Repeats) + Send + 'static,
+    {
+        let coalescer = Arc::clone(self);
+        let in_flight = Arc::clone(in_flight);
+        let ticket = in_flight.start();
+        let flushes = in_flight.flushes();
+        runtime.spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep_until(closes.into()) => {}
+                _ = in_flight.flushing(flushes) => {}
+            }
+            let window = coalescer.windows.lock().unwrap_or_else(|p| p.into_inner()).remove(&key);
+            if let Some(repeats) = window.and_then(|window| window.repeats) {
+                emit(repeats);
+            }
+            drop(ticket);
+        });
+    }
+}
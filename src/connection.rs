@@ -0,0 +1,497 @@
+//! HTTP connections to the POGR intake.
+//!
+//! The intake client keeps connections open and reuses them for later submissions. A
+//! connection stays pinned to the address its host name resolved to when it was opened, so
+//! when the intake's load balancer rotates its addresses, a long-running process keeps
+//! talking to the old ones until the connection breaks. With a connection max age, the
+//! delivery worker replaces the client once its connections are older than that: the old
+//! pool is closed, and the next submission resolves the host name again and connects to
+//! wherever it points now.
+//!
+//! Over HTTP/1.1, each connection carries one request at a time, so concurrent submissions
+//! open one connection each. Over HTTP/2 they are multiplexed as streams on a single
+//! connection. `HttpConfig` selects the protocol and tunes HTTP/2 flow control for the
+//! client the delivery worker uses.
+//!
+//! Where the defaults of the network stack produce pathological latency or failing
+//! connections, e.g. on console devkits or in restricted networks, `HttpConfig` also tunes the
+//! sockets: whether `TCP_NODELAY` is set, which address family is tried first or at all when
+//! a host name resolves to both IPv4 and IPv6 addresses, and which local address, and with it
+//! which network interface, connections are bound to.
+//!
+//! Opening a connection costs a DNS lookup and a TLS handshake, which the first submission
+//! would otherwise pay for. With a `ConnectionWarmup`, connections to the logs endpoints are
+//! opened as soon as the layer is added to a subscriber, right after the session was
+//! initialized, and touched periodically so that idle timeouts do not close them.
+//!
+//! Endpoints must use `https`. Plain `http` is only accepted for loopback hosts, or when
+//! explicitly allowed, e.g. for an on-prem collector inside a VPC whose TLS is terminated by
+//! a load balancer in front of it.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pause::KillSwitch;
+use crate::pipeline::InFlight;
+use crate::{http_dump, PogrAppender};
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::{Client, Url};
+use std::env;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::task::JoinSet;
+
+/// Environment variable allowing plain `http` endpoints.
+const ALLOW_INSECURE_ENV: &str = "POGR_ALLOW_INSECURE";
+
+/// Returns `true` if `POGR_ALLOW_INSECURE` allows plain `http` endpoints, i.e. is set to
+/// anything other than an empty string, `0` or `false`.
+pub(crate) fn allow_insecure_from_env() -> bool {
+    env::var(ALLOW_INSECURE_ENV).is_ok_and(|value| !matches!(value.trim(), "" | "0" | "false"))
+}
+
+/// Checks that an endpoint is a valid URL with a scheme the client may use. `sample` is the
+/// endpoint with its placeholders filled in, which is parsed instead.
+///
+/// `https` is always accepted; `http` only for loopback hosts, or if `allow_insecure` is set.
+pub(crate) fn check_scheme(name: &str, url: &str, sample: &str, allow_insecure: bool) -> Result<(), String> {
+    let parsed = Url::parse(sample).map_err(|err| format!("Invalid {} endpoint {}: {}", name, url, err))?;
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if allow_insecure || is_loopback(&parsed) => Ok(()),
+        "http" => Err(format!(
+            "Invalid {} endpoint {}: plain HTTP must be allowed explicitly, with `allow_insecure` or {}=1",
+            name, url, ALLOW_INSECURE_ENV
+        )),
+        scheme => Err(format!("Invalid {} endpoint {}: unsupported scheme `{}`, expected https", name, url, scheme)),
+    }
+}
+
+/// Returns `true` if the URL points to the local host, so its traffic never leaves it.
+fn is_loopback(url: &Url) -> bool {
+    match url.host_str() {
+        Some("localhost") => true,
+        Some(host) => host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    }
+}
+
+/// Which HTTP version the intake client speaks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// Negotiate the version: HTTP/2 over TLS if the server offers it through ALPN,
+    /// HTTP/1.1 otherwise, and always HTTP/1.1 for `http` endpoints.
+    #[default]
+    Negotiate,
+    /// Only use HTTP/1.1.
+    Http1Only,
+    /// Only use HTTP/2, starting it without negotiation ("prior knowledge"). This also works
+    /// for `http` endpoints, e.g. a local collector, but fails against servers without HTTP/2
+    /// support.
+    Http2PriorKnowledge,
+}
+
+/// Which IP address family connections to the intake use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressFamily {
+    /// Try the addresses in the order the system resolver returns them, falling back to the
+    /// other family if the first one does not connect quickly.
+    #[default]
+    Any,
+    /// Try IPv4 addresses first, and IPv6 addresses if they do not connect.
+    PreferIpv4,
+    /// Try IPv6 addresses first, and IPv4 addresses if they do not connect.
+    PreferIpv6,
+    /// Only connect to IPv4 addresses.
+    Ipv4Only,
+    /// Only connect to IPv6 addresses.
+    Ipv6Only,
+}
+
+impl AddressFamily {
+    /// Orders the resolved addresses for connecting, leaving out those of an excluded family.
+    fn arrange(self, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        match self {
+            AddressFamily::Any => {}
+            AddressFamily::PreferIpv4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            AddressFamily::PreferIpv6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+            AddressFamily::Ipv4Only => addrs.retain(SocketAddr::is_ipv4),
+            AddressFamily::Ipv6Only => addrs.retain(SocketAddr::is_ipv6),
+        }
+        addrs
+    }
+}
+
+/// Resolves host names with the system resolver, arranging the addresses by family.
+struct FamilyResolver(AddressFamily);
+
+impl Resolve for FamilyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let family = self.0;
+        Box::pin(async move {
+            // The connector sets the port of the endpoint on the addresses.
+            let resolved = tokio::net::lookup_host((name.as_str(), 0)).await?;
+            let addrs = family.arrange(resolved.collect());
+            if addrs.is_empty() {
+                let message = format!("{} has no address of the family {:?}", name.as_str(), family);
+                return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// HTTP and socket settings for the client that submits records to the intake.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{BatchConfig, HttpConfig, HttpVersion, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender)
+///     .with_http(HttpConfig::new().with_version(HttpVersion::Http2PriorKnowledge))
+///     .with_batching(BatchConfig::new().with_max_batch_size(100).with_max_concurrent_requests(8));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// Which HTTP version to speak.
+    version: HttpVersion,
+    /// Whether HTTP/2 flow-control windows adapt to the measured bandwidth-delay product.
+    adaptive_window: bool,
+    /// Interval of HTTP/2 keep-alive pings, if any.
+    keep_alive_interval: Option<Duration>,
+    /// Whether sockets have `TCP_NODELAY` set.
+    tcp_nodelay: bool,
+    /// Which address family connections use.
+    address_family: AddressFamily,
+    /// Local address connections are bound to, if any.
+    local_address: Option<IpAddr>,
+}
+
+impl HttpConfig {
+    /// Creates the default configuration: negotiated version, adaptive HTTP/2 flow-control
+    /// windows, no keep-alive pings, `TCP_NODELAY` set, addresses of either family and no
+    /// local address.
+    pub fn new() -> Self {
+        HttpConfig {
+            version: HttpVersion::Negotiate,
+            adaptive_window: true,
+            keep_alive_interval: None,
+            tcp_nodelay: true,
+            address_family: AddressFamily::Any,
+            local_address: None,
+        }
+    }
+
+    /// Sets which HTTP version the client speaks.
+    pub fn with_version(mut self, version: HttpVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Enables or disables adaptive HTTP/2 flow-control windows.
+    ///
+    /// With fixed windows, large batches on a high-latency link stall while waiting for the
+    /// server to extend the window; adaptive windows grow to the link's bandwidth-delay product.
+    pub fn with_adaptive_window(mut self, enabled: bool) -> Self {
+        self.adaptive_window = enabled;
+        self
+    }
+
+    /// Sends HTTP/2 keep-alive pings at the given interval, also while no request is in flight,
+    /// so that idle connections are not silently dropped by middleboxes.
+    pub fn with_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Sets whether sockets have `TCP_NODELAY` set, which sends small requests right away
+    /// instead of coalescing them with later writes.
+    ///
+    /// It is set by default. Some network stacks, e.g. of console devkits, perform better
+    /// with Nagle's algorithm left on.
+    pub fn with_tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.tcp_nodelay = enabled;
+        self
+    }
+
+    /// Sets which address family connections use when a host name resolves to both IPv4 and
+    /// IPv6 addresses.
+    ///
+    /// Preferring a family helps where the other one is broken but advertised, which
+    /// otherwise delays every new connection until the fallback kicks in; restricting to a
+    /// family avoids the other one altogether.
+    pub fn with_address_family(mut self, family: AddressFamily) -> Self {
+        self.address_family = family;
+        self
+    }
+
+    /// Binds connections to a local address, e.g. that of the network interface they should
+    /// leave through.
+    ///
+    /// Only connections to addresses of the same family are bound; combine it with
+    /// `with_address_family` to keep the other family from being used.
+    pub fn with_local_address(mut self, address: IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    /// Returns which HTTP version the client speaks.
+    pub fn version(&self) -> HttpVersion {
+        self.version
+    }
+
+    /// Returns whether HTTP/2 flow-control windows are adaptive.
+    pub fn adaptive_window(&self) -> bool {
+        self.adaptive_window
+    }
+
+    /// Returns the interval of HTTP/2 keep-alive pings, if enabled.
+    pub fn keep_alive_interval(&self) -> Option<Duration> {
+        self.keep_alive_interval
+    }
+
+    /// Returns whether sockets have `TCP_NODELAY` set.
+    pub fn tcp_nodelay(&self) -> bool {
+        self.tcp_nodelay
+    }
+
+    /// Returns which address family connections use.
+    pub fn address_family(&self) -> AddressFamily {
+        self.address_family
+    }
+
+    /// Returns the local address connections are bound to, if any.
+    pub fn local_address(&self) -> Option<IpAddr> {
+        self.local_address
+    }
+
+    /// Builds a client with these settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the TLS backend cannot be initialized, like `reqwest::Client::new`.
+    pub(crate) fn build_client(&self) -> Client {
+        let mut builder = Client::builder().http2_adaptive_window(self.adaptive_window);
+        builder = match self.version {
+            HttpVersion::Negotiate => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        };
+        if let Some(interval) = self.keep_alive_interval {
+            builder = builder.http2_keep_alive_interval(interval).http2_keep_alive_while_idle(true);
+        }
+        builder = builder.tcp_nodelay(self.tcp_nodelay).local_address(self.local_address);
+        if self.address_family != AddressFamily::Any {
+            builder = builder.dns_resolver(Arc::new(FamilyResolver(self.address_family)));
+        }
+
+        builder
+            .build()
+            .unwrap_or_else(|err| panic!("Failed to build the HTTP client: {}", err))
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds the HTTP client used to talk to the intake.
+pub(crate) fn new_client() -> Client {
+    HttpConfig::default().build_client()
+}
+
+/// Replaces the appender's client with one built from the layer's `HttpConfig`, and again
+/// whenever its connections reach their maximum age.
+pub(crate) struct ConnectionRefresh {
+    /// Settings for the replacement clients.
+    http: HttpConfig,
+    /// How long connections may be reused.
+    max_age: Option<Duration>,
+    /// When the current client was created, or `None` while the appender's own client still
+    /// has to be replaced.
+    created: Mutex<Option<Instant>>,
+}
+
+impl ConnectionRefresh {
+    /// Creates a refresh policy, or returns `None` if the appender's client can be kept.
+    pub(crate) fn new(http: Option<HttpConfig>, max_age: Option<Duration>) -> Option<Self> {
+        if http.is_none() && max_age.is_none() {
+            return None;
+        }
+
+        Some(ConnectionRefresh {
+            created: Mutex::new(if http.is_some() { None } else { Some(Instant::now()) }),
+            http: http.unwrap_or_default(),
+            max_age,
+        })
+    }
+
+    /// Replaces `client` if it has not been replaced yet or has reached the maximum age.
+    ///
+    /// Dropping the old client closes its idle connections, so the new one resolves the
+    /// endpoints again when it connects.
+    pub(crate) fn refresh(&self, client: &mut Client) {
+        let mut created = self.created.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let expired = match *created {
+            None => true,
+            Some(created) => self.max_age.is_some_and(|max_age| created.elapsed() >= max_age),
+        };
+        if expired {
+            *client = self.http.build_client();
+            *created = Some(Instant::now());
+        }
+    }
+}
+
+/// Default time between two touches of the warm connections, below the idle timeouts of the
+/// client's connection pool and of common load balancers.
+pub const DEFAULT_KEEP_WARM_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Connections to open to the logs endpoints before the first submission.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{ConnectionWarmup, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_connection_warmup(ConnectionWarmup::new().with_connections(4));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionWarmup {
+    /// Number of connections opened to each logs endpoint.
+    connections: usize,
+    /// Time between two touches of the connections, if they are kept warm.
+    keep_warm_interval: Option<Duration>,
+}
+
+impl ConnectionWarmup {
+    /// Creates the default warm-up: one connection to each logs endpoint, touched every
+    /// `DEFAULT_KEEP_WARM_INTERVAL`.
+    pub fn new() -> Self {
+        ConnectionWarmup {
+            connections: 1,
+            keep_warm_interval: Some(DEFAULT_KEEP_WARM_INTERVAL),
+        }
+    }
+
+    /// Sets the number of connections opened to each logs endpoint, at least one.
+    ///
+    /// Over HTTP/1.1, each concurrent submission needs a connection of its own, so match this
+    /// to `BatchConfig::with_max_concurrent_requests`. Over HTTP/2, one connection carries
+    /// them all.
+    pub fn with_connections(mut self, connections: usize) -> Self {
+        self.connections = connections.max(1);
+        self
+    }
+
+    /// Sets the time between two touches of the connections, or `None` to only open them once
+    /// and let them close when idle.
+    pub fn with_keep_warm_interval(mut self, interval: Option<Duration>) -> Self {
+        self.keep_warm_interval = interval;
+        self
+    }
+
+    /// Returns the number of connections opened to each logs endpoint.
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
+
+    /// Returns the time between two touches of the connections, if they are kept warm.
+    pub fn keep_warm_interval(&self) -> Option<Duration> {
+        self.keep_warm_interval
+    }
+}
+
+impl Default for ConnectionWarmup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Opens connections to the logs endpoints, or the appender's logs endpoint for `None`, and
+/// keeps them warm until the layer and its guards are gone.
+///
+/// Each connection is opened with a `HEAD` request in the appender's session, through the
+/// client the delivery workers use, replaced first if `refresh` says so. Whatever the intake
+/// responds, the connection stays in the client's pool. Connections are not touched while the
+/// intake has paused delivery.
+pub(crate) fn warm_up(
+    warmup: ConnectionWarmup,
+    appender: Arc<tokio::sync::Mutex<PogrAppender>>,
+    refresh: Option<Arc<ConnectionRefresh>>,
+    endpoints: Vec<Option<String>>,
+    kill_switch: Arc<KillSwitch>,
+    in_flight: Weak<InFlight>,
+    runtime: &Handle,
+) {
+    runtime.spawn(async move {
+        // Whether the last warm-up failed, so failures are reported once until one succeeds.
+        let mut failing = false;
+        loop {
+            if kill_switch.paused_until().is_none() {
+                let appender = {
+                    let mut appender = appender.lock().await;
+                    if let Some(refresh) = &refresh {
+                        refresh.refresh(&mut appender.client);
+                    }
+                    appender.clone()
+                };
+                match touch(&appender, &endpoints, warmup.connections).await {
+                    Ok(()) => failing = false,
+                    Err(err) if !failing => {
+                        failing = true;
+                        diagnostics::warn(DiagnosticKind::Delivery, format!("Failed to warm up connections: {}", err));
+                    }
+                    Err(_) => {}
+                }
+            }
+
+            let Some(interval) = warmup.keep_warm_interval else {
+                break;
+            };
+            tokio::time::sleep(interval).await;
+            if in_flight.strong_count() == 0 {
+                break;
+            }
+        }
+    });
+}
+
+/// Sends `connections` concurrent `HEAD` requests to each endpoint, so the client opens, or
+/// reuses, that many connections to it.
+async fn touch(appender: &PogrAppender, endpoints: &[Option<String>], connections: usize) -> Result<(), String> {
+    let mut requests = JoinSet::new();
+    for endpoint in endpoints {
+        let url = appender.resolve_endpoint(endpoint.as_deref().unwrap_or(&appender.logs_endpoint));
+        for _ in 0..connections {
+            let client = appender.client.clone();
+            let request = client.head(&url).header("INTAKE_SESSION_ID", &appender.session_id);
+            let url = url.clone();
+            requests.spawn(async move {
+                http_dump::send(&client, request)
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| format!("{}: {}", url, err))
+            });
+        }
+    }
+
+    let mut result = Ok(());
+    while let Some(joined) = requests.join_next().await {
+        if let Ok(Err(err)) = joined {
+            result = Err(err);
+        }
+    }
+    result
+}
@@ -0,0 +1,244 @@
+//! Context merged into every event: global, per thread, and task-local.
+//!
+//! Some values only become known after the subscriber is built, such as the deployment wave a
+//! server was assigned to or the map it loaded. The global context holds such values; they can
+//! be changed at any time and are merged into every subsequent event:
+//!
+//! ```
+//! pogr_tracing_rs::context::insert("deployment_wave", "canary");
+//! pogr_tracing_rs::context::insert("map", "dust2");
+//! // ...
+//! pogr_tracing_rs::context::remove("map");
+//! ```
+//!
+//! Threaded game servers, which handle a match or a request on a thread of their own, have a
+//! per-thread diagnostic context, like log4j's MDC. Entries are pushed onto the current
+//! thread's stack and popped when their guard is dropped, or explicitly:
+//!
+//! ```
+//! let _match = pogr_tracing_rs::context::push("match_id", 7);
+//! {
+//!     let _round = pogr_tracing_rs::context::push("round", 3);
+//!     tracing::info!("Round started"); // tagged with `match_id` and `round`
+//! }
+//! tracing::info!("Match ended"); // tagged with `match_id`
+//! ```
+//!
+//! Identifiers such as the request, player or match being handled are known where a task
+//! starts, but every event deep inside it should carry them. Spans can hold them, but only for
+//! the code that is instrumented with them. A `TaskContext` is bound to a tokio task instead:
+//! every event emitted while the task runs inside the context's scope is tagged with its
+//! fields, including events from `.instrument()`-ed futures and spans it awaits.
+//!
+//! Contexts nest: an inner scope keeps the fields of the outer one, overriding those it sets
+//! again. Tasks spawned from a scope do not inherit it; pass `TaskContext::current()` along
+//! and scope the spawned future with it. Fields of the event and of its spans take precedence
+//! over the context's, the task's context over the thread's, and the thread's over the global
+//! one.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::RwLock;
+
+/// The global context.
+static GLOBAL: RwLock<BTreeMap<String, Value>> = RwLock::new(BTreeMap::new());
+
+thread_local! {
+    /// The diagnostic context of the current thread, most recent entries last.
+    static THREAD: RefCell<Vec<(String, Value)>> = const { RefCell::new(Vec::new()) };
+}
+
+tokio::task_local! {
+    /// The context of the current task.
+    static CONTEXT: TaskContext;
+}
+
+/// Adds a tag to every subsequent event, replacing an earlier value for the same name.
+pub fn insert(name: impl Into<String>, value: impl Into<Value>) {
+    GLOBAL.write().unwrap_or_else(|p| p.into_inner()).insert(name.into(), value.into());
+}
+
+/// Stops adding a tag to subsequent events, and returns its value.
+pub fn remove(name: &str) -> Option<Value> {
+    GLOBAL.write().unwrap_or_else(|p| p.into_inner()).remove(name)
+}
+
+/// Returns the value of a tag of the global context.
+pub fn get(name: &str) -> Option<Value> {
+    GLOBAL.read().unwrap_or_else(|p| p.into_inner()).get(name).cloned()
+}
+
+/// Removes every tag of the global context.
+pub fn clear() {
+    GLOBAL.write().unwrap_or_else(|p| p.into_inner()).clear();
+}
+
+/// Pushes a tag onto the current thread's diagnostic context, until the returned guard is
+/// dropped.
+///
+/// Events emitted on this thread are tagged with every entry of the stack; a later entry
+/// overrides an earlier one of the same name.
+pub fn push(name: impl Into<String>, value: impl Into<Value>) -> ThreadContextGuard {
+    tag_scope([(name, value)])
+}
+
+/// Attaches tags to every event emitted on the current thread while the returned guard is
+/// alive, without creating a span.
+///
+/// This pushes all the tags onto the thread's diagnostic context at once; dropping the guard
+/// pops them.
+///
+/// # Examples
+///
+/// ```
+/// let _level = pogr_tracing_rs::tag_scope([("level_name", "dust2"), ("mode", "ranked")]);
+/// tracing::info!("Level loaded"); // tagged with `level_name` and `mode`
+/// ```
+pub fn tag_scope<I, K, V>(tags: I) -> ThreadContextGuard
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<Value>,
+{
+    let depth = THREAD.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        let depth = stack.len();
+        stack.extend(tags.into_iter().map(|(name, value)| (name.into(), value.into())));
+        depth
+    });
+    ThreadContextGuard {
+        depth,
+        _thread: PhantomData,
+    }
+}
+
+/// Pops the most recent entry of the current thread's diagnostic context, and returns it.
+pub fn pop() -> Option<(String, Value)> {
+    THREAD.with(|stack| stack.borrow_mut().pop())
+}
+
+/// Removes pushed entries from the current thread's diagnostic context when dropped, along
+/// with the entries pushed after them that are still there.
+#[must_use = "the entry is popped as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct ThreadContextGuard {
+    /// Length of the stack before the entry was pushed.
+    depth: usize,
+    /// Keeps the guard on the thread whose stack it belongs to.
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for ThreadContextGuard {
+    fn drop(&mut self) {
+        let _ = THREAD.try_with(|stack| stack.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// Fields tagged on every event emitted within a task.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::TaskContext;
+///
+/// # async fn handle_join(player: &str) {}
+/// # async fn run() {
+/// TaskContext::new()
+///     .with_request_id("7f3e2a")
+///     .with_player_id("player-42")
+///     .scope(async {
+///         // Events in here, and in the futures awaited here, carry `request_id` and `player_id`.
+///         tracing::info!("Joining lobby");
+///         handle_join("player-42").await;
+///     })
+///     .await;
+///
+/// // Spawned tasks take the context along explicitly.
+/// let context = TaskContext::current().unwrap_or_default();
+/// tokio::spawn(context.scope(async { tracing::info!("Saving profile") }));
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskContext {
+    /// Tags of the context, by name.
+    fields: BTreeMap<String, Value>,
+}
+
+impl TaskContext {
+    /// Creates an empty context.
+    pub fn new() -> Self {
+        TaskContext::default()
+    }
+
+    /// Sets the `request_id` tag.
+    pub fn with_request_id(self, request_id: impl Into<String>) -> Self {
+        self.with_field("request_id", request_id.into())
+    }
+
+    /// Sets the `player_id` tag.
+    pub fn with_player_id(self, player_id: impl Into<String>) -> Self {
+        self.with_field("player_id", player_id.into())
+    }
+
+    /// Sets the `match_id` tag.
+    pub fn with_match_id(self, match_id: impl Into<String>) -> Self {
+        self.with_field("match_id", match_id.into())
+    }
+
+    /// Sets a tag of any name.
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.fields.insert(name.into(), value.into());
+        self
+    }
+
+    /// Returns the value of a tag.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.fields.get(name)
+    }
+
+    /// Returns the context of the current task, if it runs inside a scope.
+    pub fn current() -> Option<TaskContext> {
+        CONTEXT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` with this context, merged into the current task's context if there is
+    /// one.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CONTEXT.scope(self.merged(), future).await
+    }
+
+    /// Runs `f` with this context, merged into the current task's context if there is one.
+    ///
+    /// This suits synchronous code, such as a thread handling a request outside tokio.
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CONTEXT.sync_scope(self.merged(), f)
+    }
+
+    /// Returns the fields of the current context, overridden by these.
+    fn merged(self) -> TaskContext {
+        match TaskContext::current() {
+            Some(mut outer) => {
+                outer.fields.extend(self.fields);
+                outer
+            }
+            None => self,
+        }
+    }
+}
+
+/// Returns the tags of the global context, then those of the current thread's and task's
+/// contexts.
+pub(crate) fn current_tags() -> Vec<(String, Value)> {
+    let mut tags: Vec<(String, Value)> = GLOBAL
+        .read()
+        .unwrap_or_else(|p| p.into_inner())
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    let _ = THREAD.try_with(|stack| tags.extend(stack.borrow().iter().cloned()));
+    let _ = CONTEXT.try_with(|context| tags.extend(context.fields.iter().map(|(name, value)| (name.clone(), value.clone()))));
+    tags
+}
@@ -0,0 +1,229 @@
+//! Native crash reporting through minidumps.
+//!
+//! Native crashes (segfaults, aborts, stack overflows) kill the process before any log can be
+//! submitted. Instead, a breakpad-style crash handler writes a minidump to disk, and the next
+//! time the application starts, `CrashReporter::upload_pending` submits it to POGR as an event
+//! with the dump attached, linked to the session that crashed.
+//!
+//! `CrashReporter` manages the dump directory and does not capture dumps itself. With the
+//! `minidumper` feature it implements `minidumper::ServerHandler`, so it can be plugged directly
+//! into a `minidumper` crash server; other handlers can use `minidump_file` and
+//! `complete_minidump`, or `persist_minidump` for dumps produced in memory.
+
+use crate::attachment::{Attachment, AttachmentEncoding};
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pipeline::LogRecord;
+use crate::{LogRequest, PogrAppender};
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Extension of completed minidumps waiting for upload.
+const MINIDUMP_EXTENSION: &str = "dmp";
+/// Extension of minidumps that are still being written.
+const PARTIAL_EXTENSION: &str = "partial";
+/// File holding the session ID of the most recent run.
+const SESSION_FILE: &str = "session";
+/// MIME type of uploaded minidumps.
+const MINIDUMP_CONTENT_TYPE: &str = "application/x-dmp";
+
+/// Persists native crash minidumps and uploads them to POGR on the next start.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{CrashReporter, PogrAppender};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let appender = PogrAppender::new(None, None).await;
+/// let crashes = CrashReporter::new("crashes");
+///
+/// // Submit dumps left behind by a previous crash, linked to the session that crashed.
+/// let uploaded = crashes.upload_pending(&appender).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CrashReporter {
+    /// Directory holding minidumps and the last session ID.
+    directory: PathBuf,
+}
+
+impl CrashReporter {
+    /// Creates a reporter that keeps its minidumps in `directory`.
+    ///
+    /// The directory is created when the first dump or session ID is written.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        CrashReporter {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the directory holding the minidumps.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Creates a file for a crash handler to write a minidump into.
+    ///
+    /// The file is not picked up for upload until it is passed to `complete_minidump`, so a
+    /// dump that was only partially written is never submitted.
+    ///
+    /// # Returns
+    ///
+    /// The open file and its path.
+    pub fn minidump_file(&self) -> io::Result<(File, PathBuf)> {
+        fs::create_dir_all(&self.directory)?;
+        let path = self
+            .directory
+            .join(format!("{}.{}.{}", Uuid::now_v7(), MINIDUMP_EXTENSION, PARTIAL_EXTENSION));
+        let file = File::create(&path)?;
+        Ok((file, path))
+    }
+
+    /// Marks a minidump created with `minidump_file` as complete, queueing it for upload.
+    ///
+    /// # Returns
+    ///
+    /// The path of the completed minidump.
+    pub fn complete_minidump(&self, path: &Path) -> io::Result<PathBuf> {
+        let completed = path.with_extension("");
+        fs::rename(path, &completed)?;
+        Ok(completed)
+    }
+
+    /// Writes a minidump produced in memory and queues it for upload.
+    ///
+    /// # Returns
+    ///
+    /// The path of the persisted minidump.
+    pub fn persist_minidump(&self, contents: &[u8]) -> io::Result<PathBuf> {
+        let (mut file, path) = self.minidump_file()?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        self.complete_minidump(&path)
+    }
+
+    /// Uploads the minidumps left behind by previous runs and records the current session.
+    ///
+    /// Call this once at startup, after the `PogrAppender` has been initialized. Each dump is
+    /// submitted as an error event with the dump attached, tagged with the session ID of the
+    /// run that crashed (`pogr.crashed_session_id`) and timestamped with the time the dump was
+    /// written. Uploaded dumps are deleted; dumps that fail to upload are kept for the next
+    /// start.
+    ///
+    /// # Returns
+    ///
+    /// The number of minidumps that were uploaded.
+    pub async fn upload_pending(&self, appender: &PogrAppender) -> io::Result<usize> {
+        let crashed_session_id = fs::read_to_string(self.directory.join(SESSION_FILE))
+            .ok()
+            .map(|session_id| session_id.trim().to_string())
+            .filter(|session_id| !session_id.is_empty());
+
+        let mut uploaded = 0;
+        for path in self.pending_minidumps()? {
+            match self.upload(appender, &path, crashed_session_id.as_deref()).await {
+                Ok(()) => {
+                    fs::remove_file(&path)?;
+                    uploaded += 1;
+                }
+                Err(err) => diagnostics::error(DiagnosticKind::Crash, format!("Failed to upload minidump {}: {}", path.display(), err)),
+            }
+        }
+
+        fs::create_dir_all(&self.directory)?;
+        fs::write(self.directory.join(SESSION_FILE), &appender.session_id)?;
+
+        Ok(uploaded)
+    }
+
+    /// Returns the completed minidumps in the directory, oldest first.
+    fn pending_minidumps(&self) -> io::Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut minidumps = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == MINIDUMP_EXTENSION) {
+                minidumps.push(path);
+            }
+        }
+        // File names start with a UUIDv7, so they sort by creation time.
+        minidumps.sort();
+
+        Ok(minidumps)
+    }
+
+    /// Submits a single minidump as an event with the dump attached.
+    async fn upload(&self, appender: &PogrAppender, path: &Path, crashed_session_id: Option<&str>) -> io::Result<()> {
+        let contents = fs::read(path)?;
+        let crashed_at = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let record = LogRecord {
+            event_id: Uuid::now_v7(),
+            timestamp: crashed_at,
+            request: LogRequest {
+                service: appender.service_name.clone(),
+                environment: appender.environment.clone(),
+                severity: "ERROR".to_string(),
+                r#type: appender.service_type.clone(),
+                log: "native crash minidump".to_string(),
+                data: json!({ "minidump_size": contents.len() }),
+                tags: json!({ "pogr.crashed_session_id": crashed_session_id }),
+            },
+            attachments: vec![Attachment::new(name, MINIDUMP_CONTENT_TYPE, contents)],
+            validation_error: None,
+            quarantine_error: OnceLock::new(),
+        };
+
+        // Minidumps are binary and often several megabytes, so they are always sent as multipart.
+        appender
+            .try_send(&[&record], AttachmentEncoding::Multipart)
+            .await
+            .map(|_| ())
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+    }
+}
+
+/// Lets a `minidumper` crash server write its dumps into the reporter's directory.
+///
+/// The server stops after the first dump, since the crashed client is gone by then.
+#[cfg(feature = "minidumper")]
+impl minidumper::ServerHandler for CrashReporter {
+    fn create_minidump_file(&self) -> Result<(File, PathBuf), io::Error> {
+        self.minidump_file()
+    }
+
+    fn on_minidump_created(&self, result: Result<minidumper::MinidumpBinary, minidumper::Error>) -> minidumper::LoopAction {
+        match result {
+            Ok(binary) => {
+                if let Err(err) = self.complete_minidump(&binary.path) {
+                    diagnostics::error(
+                        DiagnosticKind::Crash,
+                        format!("Failed to persist minidump {}: {}", binary.path.display(), err),
+                    );
+                }
+            }
+            Err(err) => diagnostics::error(DiagnosticKind::Crash, format!("Failed to write minidump: {}", err)),
+        }
+
+        minidumper::LoopAction::Exit
+    }
+
+    fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {}
+}
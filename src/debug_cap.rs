@@ -0,0 +1,77 @@
+//! Capping the `Debug` output recorded for event and span fields.
+//!
+//! Fields recorded with `?value` or `%value` are formatted into a string, and a misbehaving
+//! `Debug` implementation, e.g. of a type holding a whole level or asset buffer, can produce
+//! megabytes of text per event. Only the first `max_debug_length` bytes of such output are
+//! kept. The rest is still formatted, but only into a running hash, so the original length
+//! and a hash of the full text are appended and repeated giants remain identifiable:
+//!
+//! ```text
+//! LevelState { tiles: [Tile { kind: Grass, … [truncated: 4718592 bytes, fnv1a64 9f2c4b1e07a3d5c6]
+//! ```
+
+use std::fmt::{self, Write};
+
+/// Default number of bytes of `Debug` output kept per field.
+pub const DEFAULT_MAX_DEBUG_LENGTH: usize = 64 * 1024;
+
+/// 64-bit FNV-1a offset basis.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+/// 64-bit FNV-1a prime.
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Keeps the first `max` bytes written to it, and the length and hash of everything.
+struct CappedWriter {
+    /// The output kept so far.
+    kept: String,
+    /// Number of bytes to keep.
+    max: usize,
+    /// Number of bytes written.
+    length: usize,
+    /// FNV-1a hash of the bytes written.
+    hash: u64,
+}
+
+impl Write for CappedWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.length += s.len();
+        self.hash = s
+            .bytes()
+            .fold(self.hash, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME));
+
+        let room = self.max.saturating_sub(self.kept.len());
+        if room >= s.len() {
+            self.kept.push_str(s);
+        } else {
+            // Cut on a character boundary, leaving out a character that does not fit whole.
+            let cut = (0..=room).rev().find(|index| s.is_char_boundary(*index)).unwrap_or(0);
+            self.kept.push_str(&s[..cut]);
+            self.max = self.kept.len();
+        }
+        Ok(())
+    }
+}
+
+/// Formats `value` with `Debug`, keeping at most `max` bytes of the output.
+///
+/// Longer output is cut and followed by its original length in bytes and its 64-bit FNV-1a
+/// hash, without ever holding the whole output in memory. If the implementation returns an
+/// error, fails with the output written until then.
+pub(crate) fn format_capped(value: &dyn fmt::Debug, max: usize) -> Result<String, String> {
+    let mut writer = CappedWriter {
+        kept: String::new(),
+        max,
+        length: 0,
+        hash: FNV_OFFSET,
+    };
+    let result = write!(writer, "{:?}", value);
+    let formatted = if writer.length <= max {
+        writer.kept
+    } else {
+        format!(
+            "{}… [truncated: {} bytes, fnv1a64 {:016x}]",
+            writer.kept, writer.length, writer.hash
+        )
+    };
+    result.map(|()| formatted.clone()).map_err(|_| formatted)
+}
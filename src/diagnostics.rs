@@ -0,0 +1,179 @@
+//! Reporting of the crate's own problems.
+//!
+//! `PogrLayer` never forwards this crate's events, since a failing submission that logs its
+//! failure through the layer would trigger another submission. Without another outlet, the
+//! crate's own problems (a session that cannot be initialized, a rejected submission, a sink
+//! that cannot be written, records that are truncated or dropped) would go unnoticed unless
+//! they panic. They are reported here instead: to the handler installed with
+//! `set_diagnostic_handler`, or, without one, as `tracing` events on the
+//! `pogr_tracing_rs::diagnostics` target, which local layers such as `fmt` display and
+//! `PogrLayer` ignores.
+
+use std::cell::Cell;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// The severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    /// Data was altered or lost, but logging continues.
+    Warn,
+    /// An operation failed.
+    Error,
+}
+
+/// What a diagnostic is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+    /// Initializing the session or installing the subscriber failed.
+    Init,
+    /// Submitting records to the intake failed.
+    Delivery,
+    /// Writing records to a sink failed.
+    Sink,
+    /// Records or attachments were dropped.
+    Dropped,
+    /// A record was truncated to fit into a request.
+    Truncated,
+    /// Persisting or uploading a crash minidump failed.
+    Crash,
+    /// Fetching or applying the remote configuration failed.
+    Config,
+    /// An audit event lacked required fields, and was not sent.
+    Audit,
+    /// A record did not satisfy the configured JSON Schemas, and was not sent.
+    Schema,
+    /// Records pile up in the queue, or take long to be delivered.
+    Saturation,
+    /// A callsite keeps producing payloads above the oversize threshold.
+    PayloadSize,
+    /// A record could not be serialized, and was submitted in a degraded form.
+    Serialization,
+}
+
+/// A problem inside the crate, passed to the diagnostic handler.
+#[derive(Debug)]
+pub struct Diagnostic<'a> {
+    /// The severity.
+    level: DiagnosticLevel,
+    /// What the diagnostic is about.
+    kind: DiagnosticKind,
+    /// Human-readable description.
+    message: &'a str,
+}
+
+impl Diagnostic<'_> {
+    /// Returns the severity.
+    pub fn level(&self) -> DiagnosticLevel {
+        self.level
+    }
+
+    /// Returns what the diagnostic is about.
+    pub fn kind(&self) -> DiagnosticKind {
+        self.kind
+    }
+
+    /// Returns the human-readable description.
+    pub fn message(&self) -> &str {
+        self.message
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+/// A diagnostic handler.
+type Handler = Arc<dyn Fn(&Diagnostic<'_>) + Send + Sync>;
+
+/// The installed handler, if any.
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+thread_local! {
+    /// Whether a diagnostic is being reported on this thread.
+    static REPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs a handler receiving the crate's diagnostics, replacing the previous one.
+///
+/// The handler is called on the thread where the problem occurs, usually the background
+/// worker, and should return quickly. Events it records are never forwarded to POGR, and
+/// diagnostics raised while it runs are discarded, so it cannot feed back into itself.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::{set_diagnostic_handler, DiagnosticLevel};
+///
+/// set_diagnostic_handler(|diagnostic| {
+///     if diagnostic.level() == DiagnosticLevel::Error {
+///         eprintln!("pogr: {:?}: {}", diagnostic.kind(), diagnostic);
+///     }
+/// });
+/// ```
+pub fn set_diagnostic_handler<F>(handler: F)
+where
+    F: Fn(&Diagnostic<'_>) + Send + Sync + 'static,
+{
+    *HANDLER.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(handler));
+}
+
+/// Removes the installed handler, so diagnostics are emitted as `tracing` events again.
+pub fn clear_diagnostic_handler() {
+    *HANDLER.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// Returns `true` while a diagnostic is being reported on this thread.
+///
+/// The layer drops events and spans recorded at such times.
+pub(crate) fn is_reporting() -> bool {
+    REPORTING.with(Cell::get)
+}
+
+/// Reports a problem to the handler, or as a `tracing` event if none is installed.
+pub(crate) fn report(level: DiagnosticLevel, kind: DiagnosticKind, message: impl fmt::Display) {
+    if is_reporting() {
+        return;
+    }
+
+    /// Clears the reporting flag, even if the handler panics.
+    struct Reporting;
+
+    impl Drop for Reporting {
+        fn drop(&mut self) {
+            REPORTING.with(|reporting| reporting.set(false));
+        }
+    }
+
+    REPORTING.with(|reporting| reporting.set(true));
+    let _reporting = Reporting;
+
+    let message = message.to_string();
+    let diagnostic = Diagnostic {
+        level,
+        kind,
+        message: &message,
+    };
+
+    let handler = HANDLER.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    match handler {
+        Some(handler) => handler(&diagnostic),
+        None => match level {
+            DiagnosticLevel::Warn => tracing::warn!(kind = ?kind, "{}", message),
+            DiagnosticLevel::Error => tracing::error!(kind = ?kind, "{}", message),
+        },
+    }
+}
+
+/// Reports an error.
+pub(crate) fn error(kind: DiagnosticKind, message: impl fmt::Display) {
+    report(DiagnosticLevel::Error, kind, message);
+}
+
+/// Reports a warning.
+pub(crate) fn warn(kind: DiagnosticKind, message: impl fmt::Display) {
+    report(DiagnosticLevel::Warn, kind, message);
+}
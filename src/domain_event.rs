@@ -0,0 +1,102 @@
+//! Domain events, such as a match ending or a purchase, sent to POGR's event intake.
+//!
+//! Logs describe what the application did; domain events describe what happened in the game.
+//! They are emitted with the `pogr_event!` macro, which takes the kind of the event and its
+//! fields:
+//!
+//! ```rust,no_run
+//! use pogr_tracing_rs::pogr_event;
+//!
+//! let team_id = 7;
+//! pogr_event!(kind: "match_end", winner = team_id, duration_s = 312);
+//! ```
+//!
+//! The fields are serialized with `serde`, so they keep their types, and any `Serialize` value
+//! can be a field. The event goes through `tracing` with the target `pogr_event` at the `INFO`
+//! level, and the layer sends it to the event intake rather than the logs endpoint, without
+//! sampling or coalescing it.
+//!
+//! When no subscriber is interested in the event, the macro costs a single check of the
+//! callsite's interest and its fields are not evaluated. Compiling with a `tracing` static
+//! level below `INFO`, e.g. its `release_max_level_warn` feature, removes the events entirely.
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
+
+/// Target of the events emitted with `pogr_event!`.
+pub const EVENT_TARGET: &str = "pogr_event";
+
+/// Default event intake, unless `POGR_EVENTS_ENDPOINT` is set.
+const DEFAULT_EVENTS_ENDPOINT: &str = "https://api.pogr.io/v1/intake/event";
+
+/// Field of the `tracing` event carrying the kind of a domain event.
+const KIND_FIELD: &str = "pogr.kind";
+
+/// Field of the `tracing` event carrying the serialized fields of a domain event.
+const FIELDS_FIELD: &str = "pogr.fields";
+
+/// Emits a domain event of the given kind, with named fields, to POGR's event intake.
+///
+/// Fields are written as `name = value`, where the value is any expression of a type
+/// implementing `Serialize`. The values are only evaluated if a subscriber is interested in the
+/// event.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::pogr_event;
+///
+/// let team_id = 7;
+/// let players = vec!["ana", "kim"];
+/// pogr_event!(kind: "match_end", winner = team_id, duration_s = 312, players = players);
+/// pogr_event!(kind: "server_ready");
+/// ```
+#[macro_export]
+macro_rules! pogr_event {
+    (kind: $kind:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::__private::tracing::event!(
+            target: "pogr_event",
+            $crate::__private::tracing::Level::INFO,
+            "pogr.kind" = $kind,
+            "pogr.fields" = $crate::__private::encode_event_fields(&[
+                $((stringify!($field), $crate::__private::event_field(&$value))),*
+            ])
+            .as_str()
+        )
+    };
+}
+
+/// Serializes a field of a domain event.
+pub fn event_field<T: Serialize + ?Sized>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or_else(|err| json!({ "serialization_error": err.to_string() }))
+}
+
+/// Serializes the fields of a domain event for its `tracing` event.
+pub fn encode_event_fields(fields: &[(&str, Value)]) -> String {
+    let fields: Map<String, Value> = fields.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+    Value::Object(fields).to_string()
+}
+
+/// Returns the event intake from `POGR_EVENTS_ENDPOINT`, or POGR's.
+pub(crate) fn endpoint_from_env() -> String {
+    env::var("POGR_EVENTS_ENDPOINT").unwrap_or_else(|_| DEFAULT_EVENTS_ENDPOINT.to_string())
+}
+
+/// Takes the kind and fields of a domain event out of the fields of its `tracing` event, if it
+/// is one, adds them to the record's `data`, and returns the event's kind.
+pub(crate) fn take_event(fields: &mut HashMap<String, Value>, data: &mut Value) -> Option<String> {
+    let Some(Value::String(kind)) = fields.remove(KIND_FIELD) else {
+        return None;
+    };
+    let event_fields = match fields.remove(FIELDS_FIELD) {
+        Some(Value::String(encoded)) => serde_json::from_str(&encoded).unwrap_or(Value::String(encoded)),
+        _ => json!({}),
+    };
+    if let Value::Object(data) = data {
+        data.insert("kind".to_string(), json!(kind));
+        data.insert("fields".to_string(), event_fields);
+    }
+    Some(kind)
+}
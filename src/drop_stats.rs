@@ -0,0 +1,157 @@
+//! Periodic summaries of the events that were not forwarded.
+//!
+//! Sampling, rate limits, audit validation and the time-to-live drop events on purpose, and a
+//! stopped delivery worker drops them by accident. Either way, an analyst looking at the data in POGR cannot
+//! tell that it is incomplete. With drop reports, the layer counts the dropped events by level
+//! and reason, and periodically sends one compact summary record for them:
+//!
+//! ```text
+//! {"severity": "WARN", "log": "rust tracing events dropped",
+//!  "data": {"window_seconds": 60.0, "total": 12431,
+//!           "dropped": {"DEBUG": {"sampling": 12000, "rate_limit": 400}, "INFO": {"rate_limit": 31}}},
+//!  "tags": {"message": "Dropped 12431 events in the last 60 s (INFO: 31, DEBUG: 12400)"}}
+//! ```
+//!
+//! Nothing is sent for windows without drops. The summaries themselves are not subject to
+//! sampling or rate limits.
+
+use crate::{LogRequest, PogrAppender};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tracing::Level;
+
+/// Default time between two drop summaries.
+pub const DEFAULT_DROP_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Why an event was not forwarded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum DropReason {
+    /// The sampling rate of its level.
+    Sampling,
+    /// Key-based sampling.
+    KeySampling,
+    /// The rate limit of its target.
+    RateLimit,
+    /// A missing field required of audit events.
+    Audit,
+    /// The delivery worker had stopped.
+    WorkerStopped,
+    /// It waited for delivery longer than the time-to-live.
+    Expired,
+    /// The queue of the delivery worker was full.
+    Overflow,
+}
+
+impl DropReason {
+    /// Returns the name of the reason in summaries.
+    fn as_str(self) -> &'static str {
+        match self {
+            DropReason::Sampling => "sampling",
+            DropReason::KeySampling => "key_sampling",
+            DropReason::RateLimit => "rate_limit",
+            DropReason::Audit => "audit",
+            DropReason::WorkerStopped => "worker_stopped",
+            DropReason::Expired => "expired",
+            DropReason::Overflow => "overflow",
+        }
+    }
+}
+
+/// Counts of the dropped events of a layer, by level and reason.
+#[derive(Debug)]
+pub(crate) struct DropStats {
+    /// Time between two summaries.
+    interval: Duration,
+    /// Events dropped since the previous summary.
+    counts: Mutex<BTreeMap<(Level, DropReason), u64>>,
+    /// Set once the periodic summaries are started, with the first drop.
+    reporting: OnceLock<()>,
+}
+
+impl DropStats {
+    /// Creates the counts of a layer sending a summary every `interval`.
+    pub(crate) fn new(interval: Duration) -> Self {
+        DropStats {
+            interval,
+            counts: Mutex::new(BTreeMap::new()),
+            reporting: OnceLock::new(),
+        }
+    }
+
+    /// Counts a dropped event.
+    pub(crate) fn record(&self, level: Level, reason: DropReason) {
+        *self
+            .counts
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .entry((level, reason))
+            .or_default() += 1;
+    }
+
+    /// Starts sending the summaries with `send` on `runtime`, unless they are already being
+    /// sent.
+    pub(crate) fn start_reporting<F>(self: &Arc<Self>, runtime: &Handle, send: F)
+    where
+        F: Fn(Box<dyn FnOnce(&PogrAppender) -> LogRequest + Send>) + Send + 'static,
+    {
+        if self.reporting.set(()).is_err() {
+            return;
+        }
+        let stats = Arc::clone(self);
+        runtime.spawn(async move {
+            let mut ticks = tokio::time::interval(stats.interval);
+            // The first tick completes immediately, right after the first drop.
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let counts = std::mem::take(&mut *stats.counts.lock().unwrap_or_else(|p| p.into_inner()));
+                if !counts.is_empty() {
+                    let window = stats.interval;
+                    send(Box::new(move |appender| summary_request(appender, counts, window)));
+                }
+            }
+        });
+    }
+
+    /// Returns `true` once the periodic summaries are started.
+    pub(crate) fn is_reporting(&self) -> bool {
+        self.reporting.get().is_some()
+    }
+}
+
+/// Builds the summary record of the events dropped during a window.
+fn summary_request(appender: &PogrAppender, counts: BTreeMap<(Level, DropReason), u64>, window: Duration) -> LogRequest {
+    let mut dropped: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+    let mut per_level: BTreeMap<Level, u64> = BTreeMap::new();
+    for ((level, reason), count) in &counts {
+        dropped
+            .entry(level.to_string())
+            .or_default()
+            .insert(reason.as_str().to_string(), json!(count));
+        *per_level.entry(*level).or_default() += count;
+    }
+    let total: u64 = per_level.values().sum();
+    let levels: Vec<String> = per_level
+        .iter()
+        .map(|(level, count)| format!("{}: {}", level, count))
+        .collect();
+
+    LogRequest {
+        service: appender.service_name.clone(),
+        environment: appender.environment.clone(),
+        severity: Level::WARN.to_string(),
+        r#type: appender.service_type.clone(),
+        log: "rust tracing events dropped".to_string(),
+        data: json!({
+            "window_seconds": window.as_secs_f64(),
+            "total": total,
+            "dropped": dropped,
+        }),
+        tags: json!({
+            "message": format!("Dropped {} events in the last {} s ({})", total, window.as_secs_f64(), levels.join(", ")),
+        }),
+    }
+}
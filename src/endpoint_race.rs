@@ -0,0 +1,161 @@
+//! Racing equivalent intake endpoints for the lowest latency.
+//!
+//! Globally distributed servers are closer to some regional intake than to others, and which
+//! one answers fastest changes with routing and load. With an `EndpointRace`, the appender's
+//! logs endpoint and equivalent regional endpoints are raced, happy-eyeballs style, before the
+//! first submission: each is sent a `HEAD` request in the session at once, and records are
+//! submitted to the first that responds. The race is run again once the re-evaluation
+//! interval has passed, so delivery follows the fastest endpoint over time.
+//!
+//! Only probes are raced, never records, so no record reaches two intakes. The winner's probe
+//! leaves an open connection behind, which the submission then reuses. Endpoints that fail to
+//! connect or answer with a server error do not win; if none responds in time, the previous
+//! endpoint is kept until the next race.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::{http_dump, PogrAppender};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+/// Default time after which the endpoints are raced again.
+pub const DEFAULT_RACE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Time the endpoints have to respond to a race.
+const RACE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Logs endpoints equivalent to the appender's, raced for the fastest.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{EndpointRace, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_endpoint_race(EndpointRace::new([
+///     "https://eu.api.pogr.io/v1/intake/logs",
+///     "https://ap.api.pogr.io/v1/intake/logs",
+/// ]));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EndpointRace {
+    /// The endpoints raced besides the appender's logs endpoint.
+    endpoints: Vec<String>,
+    /// Time after which the endpoints are raced again.
+    interval: Duration,
+}
+
+impl EndpointRace {
+    /// Races the appender's logs endpoint against `endpoints`, which must accept the same
+    /// records in the same session, again every `DEFAULT_RACE_INTERVAL`.
+    ///
+    /// Endpoints may contain the placeholders of endpoint templates.
+    pub fn new<I>(endpoints: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        EndpointRace {
+            endpoints: endpoints.into_iter().map(Into::into).collect(),
+            interval: DEFAULT_RACE_INTERVAL,
+        }
+    }
+
+    /// Sets the time after which the endpoints are raced again, before the next submission.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns the endpoints raced besides the appender's logs endpoint.
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    /// Returns the time after which the endpoints are raced again.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// Picks the endpoint records are submitted to, shared by the workers of a layer.
+pub(crate) struct EndpointRacer {
+    /// The raced endpoints and how often.
+    race: EndpointRace,
+    /// The endpoint that won the last race, and when it was run.
+    winner: Mutex<Option<(String, Instant)>>,
+}
+
+impl EndpointRacer {
+    /// Creates a racer that has not raced yet.
+    pub(crate) fn new(race: EndpointRace) -> Self {
+        EndpointRacer {
+            race,
+            winner: Mutex::new(None),
+        }
+    }
+
+    /// Returns the endpoint to submit to, racing the endpoints first if they have not been
+    /// raced yet or the re-evaluation interval has passed.
+    ///
+    /// Concurrent submissions wait for the same race.
+    pub(crate) async fn endpoint(&self, appender: &PogrAppender) -> String {
+        let mut winner = self.winner.lock().await;
+        match &*winner {
+            Some((endpoint, raced)) if raced.elapsed() < self.race.interval => return endpoint.clone(),
+            _ => {}
+        }
+
+        let mut candidates = vec![appender.logs_endpoint.clone()];
+        for endpoint in &self.race.endpoints {
+            if !candidates.contains(endpoint) {
+                candidates.push(endpoint.clone());
+            }
+        }
+        let previous = winner.as_ref().map_or(&appender.logs_endpoint, |(endpoint, _)| endpoint).clone();
+        let endpoint = race(appender, candidates).await.unwrap_or_else(|| {
+            diagnostics::warn(
+                DiagnosticKind::Delivery,
+                format!(
+                    "None of the raced logs endpoints responded within {} s, submitting to {}",
+                    RACE_TIMEOUT.as_secs(),
+                    previous
+                ),
+            );
+            previous
+        });
+        *winner = Some((endpoint.clone(), Instant::now()));
+        endpoint
+    }
+}
+
+/// Sends a `HEAD` request to each endpoint at once, and returns the first that responds
+/// without a server error.
+async fn race(appender: &PogrAppender, candidates: Vec<String>) -> Option<String> {
+    let mut probes = JoinSet::new();
+    for endpoint in candidates {
+        let client = appender.client.clone();
+        let request = client
+            .head(appender.resolve_endpoint(&endpoint))
+            .header("INTAKE_SESSION_ID", &appender.session_id);
+        probes.spawn(async move {
+            match http_dump::send(&client, request).await {
+                Ok((status, _)) if !status.is_server_error() => Some(endpoint),
+                _ => None,
+            }
+        });
+    }
+
+    // Dropping the probes still running cancels them.
+    let first = async {
+        while let Some(probe) = probes.join_next().await {
+            if let Ok(Some(endpoint)) = probe {
+                return Some(endpoint);
+            }
+        }
+        None
+    };
+    tokio::time::timeout(RACE_TIMEOUT, first).await.ok().flatten()
+}
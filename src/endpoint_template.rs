@@ -0,0 +1,80 @@
+//! Endpoint URL templates.
+//!
+//! Deployments that front POGR with a gateway sometimes route by path, e.g.
+//! `https://gateway.example.com/telemetry/{service}/logs`. Endpoints may contain placeholders,
+//! resolved from the appender's session for every request:
+//!
+//! - `{service}`: the name of the service,
+//! - `{environment}`: its deployment environment,
+//! - `{type}`: the type of the service,
+//! - `{session}`: the session ID, which the init endpoint cannot use, as the session does not
+//!   exist yet when it is called.
+//!
+//! Values are percent-encoded, so they always stay within one path segment or query value.
+//! Endpoints without placeholders are used as they are.
+
+/// The placeholders every endpoint may contain.
+const PLACEHOLDERS: &[&str] = &["service", "environment", "type", "session"];
+
+/// Replaces the placeholders of `template` with the values `value` returns for their names.
+fn substitute<'a>(template: &str, value: impl Fn(&str) -> Option<&'a str>) -> Result<String, String> {
+    let mut resolved = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        resolved.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("unclosed placeholder in {}", template));
+        };
+        let name = &rest[start + 1..start + end];
+        match value(name) {
+            Some(value) => percent_encode(value, &mut resolved),
+            None => return Err(format!("unknown placeholder `{{{}}}` in {}", name, template)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Resolves an endpoint for a session.
+///
+/// Unknown placeholders are left as they are; `check` rejects them when the appender is
+/// created.
+pub(crate) fn resolve(template: &str, service: &str, environment: &str, service_type: &str, session: Option<&str>) -> String {
+    if !template.contains('{') {
+        return template.to_string();
+    }
+    substitute(template, |name| match name {
+        "service" => Some(service),
+        "environment" => Some(environment),
+        "type" => Some(service_type),
+        "session" => session,
+        _ => None,
+    })
+    .unwrap_or_else(|_| template.to_string())
+}
+
+/// Checks the placeholders of an endpoint, and returns it with sample values, to be checked
+/// as a URL.
+pub(crate) fn check(name: &str, template: &str) -> Result<String, String> {
+    if name == "init" && template.contains("{session}") {
+        return Err("Invalid init endpoint template: `{session}` is not known before the session is initialized".to_string());
+    }
+    substitute(template, |placeholder| {
+        PLACEHOLDERS
+            .iter()
+            .copied()
+            .find(|known| *known == placeholder)
+    })
+    .map_err(|reason| format!("Invalid {} endpoint template: {}", name, reason))
+}
+
+/// Appends `value` to `out`, percent-encoding everything but unreserved characters.
+fn percent_encode(value: &str, out: &mut String) {
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+}
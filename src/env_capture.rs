@@ -0,0 +1,45 @@
+//! Capture of allowlisted environment variables.
+//!
+//! Deployment details such as the region, the deploy ID or the Fly.io allocation are often only
+//! available as environment variables. Only the variables named in an allowlist are captured,
+//! once, when the appender or the layer is configured; everything else in the environment,
+//! which may hold secrets, is never read.
+
+use serde_json::{Map, Value};
+use std::env;
+
+/// Reads the allowlisted variables that are set, in the order they are listed.
+///
+/// Variables that are unset, or not valid Unicode, are skipped.
+pub(crate) fn capture<I>(names: I) -> Vec<(String, String)>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let name = name.as_ref();
+            env::var(name).ok().map(|value| (name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Returns the name of the event tag carrying a captured variable, e.g. `env.REGION`.
+pub(crate) fn tag_name(name: &str) -> String {
+    format!("env.{}", name)
+}
+
+/// Adds captured variables to the `env` object of the session metadata, creating it if needed.
+pub(crate) fn insert_into_session(fields: &mut Map<String, Value>, captured: Vec<(String, String)>) {
+    if captured.is_empty() {
+        return;
+    }
+    let env = fields.entry("env").or_insert_with(|| Value::Object(Map::new()));
+    if !env.is_object() {
+        *env = Value::Object(Map::new());
+    }
+    if let Value::Object(env) = env {
+        env.extend(captured.into_iter().map(|(name, value)| (name, Value::from(value))));
+    }
+}
@@ -0,0 +1,67 @@
+//! The execution context a record was emitted from.
+//!
+//! Records name the thread that emitted them, and the tokio task when there is one, so that
+//! deadlocks and runaway tasks can be traced back from the logs. Tokio only exposes task IDs
+//! as an unstable API, so they are captured when the crate is built with
+//! `RUSTFLAGS="--cfg tokio_unstable"`, like `tokio-console` requires.
+
+use serde_json::{json, Value};
+use std::thread;
+
+/// The thread, and the tokio task if any, that emitted a record.
+pub(crate) struct ExecutionContext {
+    /// Name of the thread, if it has one.
+    thread_name: Option<String>,
+    /// Number of the thread's `ThreadId`, unique within the process.
+    thread_id: u64,
+    /// ID of the running tokio task, if the emitter runs in a task and task IDs are available.
+    task_id: Option<u64>,
+}
+
+impl ExecutionContext {
+    /// Captures the context of the calling thread.
+    pub(crate) fn current() -> Self {
+        let thread = thread::current();
+        ExecutionContext {
+            thread_name: thread.name().map(str::to_string),
+            thread_id: thread_number(thread.id()),
+            task_id: current_task_id(),
+        }
+    }
+
+    /// Adds the context to a record's `data` as `thread_name`, `thread_id` and, when known,
+    /// `task_id`.
+    pub(crate) fn insert_into(self, data: &mut Value) {
+        let Value::Object(data) = data else {
+            return;
+        };
+        data.insert("thread_name".to_string(), json!(self.thread_name));
+        data.insert("thread_id".to_string(), json!(self.thread_id));
+        if let Some(task_id) = self.task_id {
+            data.insert("task_id".to_string(), json!(task_id));
+        }
+    }
+}
+
+/// Returns the number of a `ThreadId`, which is only exposed through its `Debug` formatting,
+/// e.g. `ThreadId(7)`.
+fn thread_number(id: thread::ThreadId) -> u64 {
+    let formatted = format!("{:?}", id);
+    formatted
+        .trim_start_matches("ThreadId(")
+        .trim_end_matches(')')
+        .parse()
+        .unwrap_or(0)
+}
+
+/// Returns the ID of the running tokio task, if any.
+#[cfg(tokio_unstable)]
+fn current_task_id() -> Option<u64> {
+    tokio::task::try_id().and_then(|id| id.to_string().parse().ok())
+}
+
+/// Returns the ID of the running tokio task, which is unknown without `tokio_unstable`.
+#[cfg(not(tokio_unstable))]
+fn current_task_id() -> Option<u64> {
+    None
+}
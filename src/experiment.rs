@@ -0,0 +1,107 @@
+//! Tagging events with the active experiments and feature flags.
+//!
+//! Analysts segment telemetry by experiment arm: did the new matchmaker raise the disconnect
+//! rate, does the crash only happen with the new renderer? Experiments and feature flags
+//! registered on the layer are added to every event as tags:
+//!
+//! | Registered                                   | Tag                                       |
+//! |----------------------------------------------|-------------------------------------------|
+//! | experiment `matchmaking_v2`, arm `treatment` | `experiment.matchmaking_v2 = "treatment"` |
+//! | feature flag `new_renderer`, on              | `feature_flag.new_renderer = true`        |
+//!
+//! Assignments often change at runtime, e.g. once a player's profile is loaded, so they are
+//! kept behind an `Experiments` handle that can be updated after the layer is installed. For
+//! assignments that only hold in some scope, such as one match, record fields with the same
+//! names on a span: like other span fields, they are added to the events inside it and take
+//! precedence over the layer's.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+/// Prefix of the tags naming an experiment.
+const EXPERIMENT_PREFIX: &str = "experiment.";
+
+/// Prefix of the tags naming a feature flag.
+const FEATURE_FLAG_PREFIX: &str = "feature_flag.";
+
+/// The experiments and feature flags added to every event of a layer.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+/// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_feature_flag("new_renderer", true);
+/// let experiments = layer.experiments();
+/// tracing_subscriber::registry().with(layer).init();
+///
+/// // Once the player has been assigned to an arm:
+/// experiments.set_experiment("matchmaking_v2", "treatment");
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Experiments {
+    /// Tags of the registered experiments and flags, by tag name.
+    tags: Arc<RwLock<BTreeMap<String, Value>>>,
+}
+
+impl Experiments {
+    /// Registers the arm of an experiment, replacing a previous assignment.
+    pub fn set_experiment(&self, id: &str, arm: impl Into<String>) {
+        self.insert(format!("{}{}", EXPERIMENT_PREFIX, id), Value::String(arm.into()));
+    }
+
+    /// Removes an experiment, e.g. once it has concluded.
+    pub fn remove_experiment(&self, id: &str) {
+        self.remove(&format!("{}{}", EXPERIMENT_PREFIX, id));
+    }
+
+    /// Registers the state of a feature flag, replacing a previous one.
+    ///
+    /// Flags that are off are tagged too, so that events can be compared across both states.
+    pub fn set_feature_flag(&self, name: &str, enabled: bool) {
+        self.insert(format!("{}{}", FEATURE_FLAG_PREFIX, name), Value::Bool(enabled));
+    }
+
+    /// Removes a feature flag.
+    pub fn remove_feature_flag(&self, name: &str) {
+        self.remove(&format!("{}{}", FEATURE_FLAG_PREFIX, name));
+    }
+
+    /// Returns the arm registered for an experiment.
+    pub fn experiment(&self, id: &str) -> Option<String> {
+        self.read()
+            .get(&format!("{}{}", EXPERIMENT_PREFIX, id))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    /// Returns the state registered for a feature flag.
+    pub fn feature_flag(&self, name: &str) -> Option<bool> {
+        self.read().get(&format!("{}{}", FEATURE_FLAG_PREFIX, name)).and_then(Value::as_bool)
+    }
+
+    /// Returns the tags of the registered experiments and flags.
+    pub(crate) fn tags(&self) -> Vec<(String, Value)> {
+        self.read().iter().map(|(name, value)| (name.clone(), value.clone())).collect()
+    }
+
+    /// Sets a tag.
+    fn insert(&self, name: String, value: Value) {
+        self.tags.write().unwrap_or_else(|p| p.into_inner()).insert(name, value);
+    }
+
+    /// Removes a tag.
+    fn remove(&self, name: &str) {
+        self.tags.write().unwrap_or_else(|p| p.into_inner()).remove(name);
+    }
+
+    /// Locks the tags for reading.
+    fn read(&self) -> RwLockReadGuard<'_, BTreeMap<String, Value>> {
+        self.tags.read().unwrap_or_else(|p| p.into_inner())
+    }
+}
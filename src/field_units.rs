@@ -0,0 +1,120 @@
+//! Annotating numeric fields with their units.
+//!
+//! A bare `latency = 12` leaves every dashboard guessing whether it is milliseconds or
+//! seconds, and `asset_size = 4096` whether it is bytes or kilobytes. With field units, the
+//! layer records numeric fields whose name ends in a unit suffix, such as `latency_ms` or
+//! `asset_bytes`, as `{"value": 12, "unit": "ms"}` objects, so POGR charts get correct axes.
+//!
+//! A unit can also be given explicitly, by a companion field named `pogr.unit.` followed by
+//! the field's name, e.g. `pogr.unit.heap = "MiB"` for `heap`. The companion takes precedence
+//! over the suffixes, and is not recorded itself. Fields that are not numbers are left as they
+//! are.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Prefix of the companion fields that give the unit of another field.
+pub const UNIT_FIELD_PREFIX: &str = "pogr.unit.";
+
+/// Field name suffixes and their units that `FieldUnits::new` starts with.
+const DEFAULT_SUFFIXES: [(&str, &str); 8] = [
+    ("_ns", "ns"),
+    ("_us", "us"),
+    ("_ms", "ms"),
+    ("_secs", "s"),
+    ("_seconds", "s"),
+    ("_bytes", "bytes"),
+    ("_pct", "percent"),
+    ("_percent", "percent"),
+];
+
+/// Which numeric fields are annotated with which units.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{FieldUnits, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_field_units(FieldUnits::new().with_suffix("_fps", "fps"));
+///
+/// // Recorded as `"frame_ms": {"value": 16.7, "unit": "ms"}` and
+/// // `"heap": {"value": 512, "unit": "MiB"}`.
+/// tracing::info!(frame_ms = 16.7, heap = 512, "pogr.unit.heap" = "MiB", "frame stats");
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldUnits {
+    /// Field name suffixes and their units, later entries taking precedence.
+    suffixes: Vec<(String, String)>,
+}
+
+impl FieldUnits {
+    /// Creates field units recognizing the suffixes `_ns`, `_us`, `_ms`, `_secs`, `_seconds`,
+    /// `_bytes`, `_pct` and `_percent`, and companion fields.
+    pub fn new() -> Self {
+        FieldUnits {
+            suffixes: DEFAULT_SUFFIXES
+                .iter()
+                .map(|(suffix, unit)| (suffix.to_string(), unit.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Creates field units recognizing only companion fields, and suffixes added with
+    /// `with_suffix`.
+    pub fn without_suffixes() -> Self {
+        FieldUnits {
+            suffixes: Vec::new(),
+        }
+    }
+
+    /// Annotates numeric fields whose name ends in `suffix`, and is longer than it, with
+    /// `unit`. Suffixes added later take precedence.
+    pub fn with_suffix(mut self, suffix: impl Into<String>, unit: impl Into<String>) -> Self {
+        self.suffixes.push((suffix.into(), unit.into()));
+        self
+    }
+
+    /// Returns the unit of a field named `name` by its suffix, if it has one.
+    pub fn unit_of(&self, name: &str) -> Option<&str> {
+        self.suffixes
+            .iter()
+            .rev()
+            .find(|(suffix, _)| name.len() > suffix.len() && name.ends_with(suffix.as_str()))
+            .map(|(_, unit)| unit.as_str())
+    }
+
+    /// Replaces numeric fields that have a unit with `{value, unit}` objects, and removes the
+    /// companion fields.
+    pub(crate) fn annotate(&self, fields: &mut HashMap<String, Value>) {
+        let companions: Vec<String> = fields
+            .keys()
+            .filter(|name| name.starts_with(UNIT_FIELD_PREFIX))
+            .cloned()
+            .collect();
+        for companion in companions {
+            let unit = fields.remove(&companion);
+            if let (Some(Value::String(unit)), Some(value)) =
+                (unit, fields.get_mut(&companion[UNIT_FIELD_PREFIX.len()..]))
+            {
+                if value.is_number() {
+                    *value = json!({ "value": value.take(), "unit": unit });
+                }
+            }
+        }
+
+        for (name, value) in fields.iter_mut() {
+            if let (true, Some(unit)) = (value.is_number(), self.unit_of(name)) {
+                *value = json!({ "value": value.take(), "unit": unit });
+            }
+        }
+    }
+}
+
+impl Default for FieldUnits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,189 @@
+//! A sink writing records to rotating local files.
+//!
+//! Each record is written as one line of JSON, serialized exactly like the payload submitted
+//! to the intake, so the files can be inspected with the same tools or replayed later.
+
+use crate::sink::{Sink, SinkRecord};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default size, in bytes, after which the log file is rotated.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Default number of rotated files kept next to the current one.
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+/// A `Sink` that appends records as JSON lines to a file, rotating it by size and age.
+///
+/// When the current file would exceed `max_file_size`, or has been open for longer than
+/// the rotation interval, it is renamed to `<path>.1`, the previous `<path>.1` to `<path>.2`
+/// and so on. Only `max_files` rotated files are kept; older ones are deleted.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, RotatingFileSink, SinkMode};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let mirror = RotatingFileSink::new("logs/pogr.jsonl")
+///     .with_max_file_size(5 * 1024 * 1024)
+///     .with_rotation_interval(Duration::from_secs(24 * 60 * 60))
+///     .with_max_files(7);
+///
+/// let layer = PogrLayer::new(appender).with_sink(mirror, SinkMode::Mirror);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RotatingFileSink {
+    /// Path of the current log file.
+    path: PathBuf,
+    /// Size in bytes after which the file is rotated.
+    max_file_size: u64,
+    /// Age after which the file is rotated, if any.
+    rotation_interval: Option<Duration>,
+    /// Number of rotated files kept.
+    max_files: usize,
+    /// The open file, opened on the first write.
+    current: Mutex<Option<OpenFile>>,
+}
+
+/// The log file currently being written.
+#[derive(Debug)]
+struct OpenFile {
+    /// Buffered writer for the file.
+    writer: BufWriter<File>,
+    /// Current size of the file in bytes.
+    size: u64,
+    /// When the file was opened, for time-based rotation.
+    opened_at: Instant,
+}
+
+impl RotatingFileSink {
+    /// Creates a sink writing to `path`, rotating at 10 MiB and keeping 5 rotated files.
+    ///
+    /// The file and its parent directories are created on the first write; an existing file
+    /// is appended to.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        RotatingFileSink {
+            path: path.into(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            rotation_interval: None,
+            max_files: DEFAULT_MAX_FILES,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Sets the size, in bytes, after which the file is rotated.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Rotates the file once it has been open for `interval`, regardless of its size.
+    pub fn with_rotation_interval(mut self, interval: Duration) -> Self {
+        self.rotation_interval = Some(interval);
+        self
+    }
+
+    /// Sets how many rotated files are kept. With zero, the file is started over on rotation.
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Returns the path of the current log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of the `index`-th rotated file.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    /// Opens the current log file for appending.
+    fn open(&self) -> io::Result<OpenFile> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let size = file.metadata()?.len();
+        Ok(OpenFile {
+            writer: BufWriter::new(file),
+            size,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Shifts the rotated files up by one and moves the current file to `<path>.1`.
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+
+        match fs::remove_file(self.rotated_path(self.max_files)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+
+    /// Returns `true` if writing `len` more bytes to `file` should start a new file first.
+    fn needs_rotation(&self, file: &OpenFile, len: u64) -> bool {
+        let too_large = file.size > 0 && file.size + len > self.max_file_size;
+        let too_old = self
+            .rotation_interval
+            .is_some_and(|interval| file.opened_at.elapsed() >= interval);
+        too_large || too_old
+    }
+}
+
+impl Sink for RotatingFileSink {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        let mut line = record.to_json();
+        line.push('\n');
+        let len = line.len() as u64;
+
+        let mut current = self.current.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut file = match current.take() {
+            Some(file) => file,
+            None => self.open()?,
+        };
+
+        if self.needs_rotation(&file, len) {
+            file.writer.flush()?;
+            // The file must be closed before it can be renamed on every platform.
+            drop(file);
+            self.rotate()?;
+            file = self.open()?;
+        }
+
+        file.writer.write_all(line.as_bytes())?;
+        file.size += len;
+        *current = Some(file);
+
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match current.as_mut() {
+            Some(file) => file.writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
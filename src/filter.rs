@@ -0,0 +1,217 @@
+//! Per-target severity thresholds for the POGR layer.
+//!
+//! `TargetLevels` holds a default minimum level plus a list of target-specific overrides,
+//! written in the familiar `matchmaking=debug,netcode=warn,sqlx=error` form. The most specific
+//! (longest) matching target wins, so `netcode=warn,netcode::rollback=debug` keeps rollback
+//! diagnostics while silencing the rest of the netcode subsystem.
+//!
+//! Besides being applied directly with `PogrLayer::with_target_levels`, `TargetLevels`
+//! implements `tracing_subscriber::layer::Filter`, so it can be used as a per-layer filter
+//! (`PogrLayer::new(appender).with_filter(levels)`). As a per-layer filter, disabled callsites
+//! are cached by `tracing` and the layer is never invoked for them.
+//!
+//! Operators can set the thresholds at startup with the `POGR_LOG_LEVEL` environment variable,
+//! in the same directive syntax, without changing code.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use tracing::subscriber::Interest;
+use tracing::Metadata;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Filter};
+
+/// Minimum severity levels for events forwarded to POGR, configurable per target.
+///
+/// A target directive applies to the target itself and to every module below it, e.g. a
+/// directive for `netcode` also matches `netcode::rollback`, but not `netcode_utils`.
+///
+/// # Examples
+///
+/// ```
+/// use pogr_tracing_rs::TargetLevels;
+/// use tracing_subscriber::filter::LevelFilter;
+///
+/// let levels: TargetLevels = "info,matchmaking=debug,netcode=warn,sqlx=error".parse().unwrap();
+///
+/// assert_eq!(levels.level_for("matchmaking::queue"), LevelFilter::DEBUG);
+/// assert_eq!(levels.level_for("netcode"), LevelFilter::WARN);
+/// assert_eq!(levels.level_for("game::inventory"), LevelFilter::INFO);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetLevels {
+    /// Level applied to targets that do not match any directive.
+    default: LevelFilter,
+    /// Target prefixes with their minimum level, sorted from most to least specific.
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl TargetLevels {
+    /// Creates a set of thresholds with the given default level and no target overrides.
+    pub fn new(default: LevelFilter) -> Self {
+        TargetLevels {
+            default,
+            directives: Vec::new(),
+        }
+    }
+
+    /// Adds (or replaces) the minimum level for a target and everything below it.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The target prefix, e.g. `netcode` or `game::physics`.
+    /// * `level` - The minimum level for events with a matching target.
+    pub fn with_target(mut self, target: impl Into<String>, level: LevelFilter) -> Self {
+        let target = target.into();
+        self.directives.retain(|(existing, _)| *existing != target);
+        self.directives.push((target, level));
+        // Longest targets first, so the first match is always the most specific one.
+        self.directives.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    /// Parses the thresholds set by the `POGR_LOG_LEVEL` environment variable, e.g.
+    /// `info,netcode=debug`.
+    ///
+    /// Returns `None` if the variable is not set, or if it cannot be parsed, in which case a
+    /// warning is reported as a diagnostic. `PogrLayer::new` starts from these thresholds.
+    pub fn from_env() -> Option<Self> {
+        match env_levels() {
+            Ok(levels) => levels,
+            Err(e) => {
+                diagnostics::warn(DiagnosticKind::Init, format_args!("Ignoring POGR_LOG_LEVEL: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Returns the level applied to targets without a matching directive.
+    pub fn default_level(&self) -> LevelFilter {
+        self.default
+    }
+
+    /// Returns the minimum level that applies to `target`.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|(prefix, _)| target_matches(target, prefix))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// Returns `true` if an event or span with the given metadata meets its target's threshold.
+    pub fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.level_for(metadata.target()) >= *metadata.level()
+    }
+
+    /// Returns the most verbose level enabled for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, std::cmp::max)
+    }
+}
+
+/// Allows `TargetLevels` to be used as a per-layer filter.
+///
+/// Thresholds only depend on static metadata, so the decision is made once per callsite.
+impl<S> Filter<S> for TargetLevels {
+    fn enabled(&self, metadata: &Metadata<'_>, _cx: &Context<'_, S>) -> bool {
+        TargetLevels::enabled(self, metadata)
+    }
+
+    fn callsite_enabled(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if TargetLevels::enabled(self, metadata) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.max_level())
+    }
+}
+
+/// Allows every level for every target.
+impl Default for TargetLevels {
+    fn default() -> Self {
+        TargetLevels::new(LevelFilter::TRACE)
+    }
+}
+
+/// Parses a comma-separated list of directives.
+///
+/// Each directive is either a bare level (`warn`), which sets the default, or a
+/// `target=level` pair. Whitespace around directives is ignored.
+impl FromStr for TargetLevels {
+    type Err = ParseTargetLevelsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut levels = TargetLevels::default();
+
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    let target = target.trim();
+                    if target.is_empty() {
+                        return Err(ParseTargetLevelsError::new(directive));
+                    }
+                    let level = parse_level(level.trim(), directive)?;
+                    levels = levels.with_target(target, level);
+                }
+                None => levels.default = parse_level(directive, directive)?,
+            }
+        }
+
+        Ok(levels)
+    }
+}
+
+/// Parses `POGR_LOG_LEVEL`, if it is set to a non-empty value.
+pub(crate) fn env_levels() -> Result<Option<TargetLevels>, ParseTargetLevelsError> {
+    match env::var("POGR_LOG_LEVEL") {
+        Ok(directives) if !directives.trim().is_empty() => directives.parse().map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Parses a single level name, reporting the whole directive on failure.
+fn parse_level(level: &str, directive: &str) -> Result<LevelFilter, ParseTargetLevelsError> {
+    level
+        .parse::<LevelFilter>()
+        .map_err(|_| ParseTargetLevelsError::new(directive))
+}
+
+/// Returns `true` if `target` is `prefix` or a module below it.
+pub(crate) fn target_matches(target: &str, prefix: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// Error returned when a target level directive string cannot be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTargetLevelsError {
+    /// The directive that failed to parse.
+    directive: String,
+}
+
+impl ParseTargetLevelsError {
+    fn new(directive: &str) -> Self {
+        ParseTargetLevelsError {
+            directive: directive.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseTargetLevelsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid target level directive `{}`", self.directive)
+    }
+}
+
+impl Error for ParseTargetLevelsError {}
@@ -0,0 +1,170 @@
+//! A handle that enqueues records from synchronous code.
+//!
+//! Game logic threads run a fixed tick and must never await, nor block on a lock that an
+//! async task may hold. A `PogrHandle` pushes records straight onto the layer's delivery
+//! queues: building the record and queueing it are the only work done on the calling thread,
+//! and the call returns immediately, whether or not a tokio runtime runs on that thread.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pipeline::{InFlight, Queued};
+use crate::ring::{self, QueueSender, Rejected};
+use crate::{Clock, IdGenerator, IdKind, LogRequest, PogrAppender};
+use serde_json::{json, Map, Value};
+use std::panic::Location;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::Level;
+use uuid::Uuid;
+
+/// A cheap, cloneable handle that queues records for a `PogrLayer` without awaiting.
+///
+/// Records queued through the handle go through the layer's delivery pipeline, like events
+/// captured from `tracing`: batching, level routes, sinks and pauses apply to them. The
+/// layer's filters, sampling and rate limits do not, as the records are queued explicitly.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+/// use serde_json::json;
+/// use tracing::Level;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender);
+/// let handle = layer.handle();
+///
+/// std::thread::spawn(move || loop {
+///     // ... simulate a tick ...
+///     handle.event(Level::INFO, "Tick simulated", json!({ "entities": 1204 }));
+/// });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PogrHandle {
+    /// The state shared by the clones of the handle.
+    inner: Arc<HandleInner>,
+}
+
+/// The queues of a layer, and what the handle adds to its records.
+struct HandleInner {
+    /// The queue of each level, from the most to the least severe.
+    queues: Vec<(Level, QueueSender)>,
+    /// Submissions tracked for flushing.
+    in_flight: Arc<InFlight>,
+    /// Clock assigning the records' timestamps.
+    clock: Arc<dyn Clock>,
+    /// Generator of the records' `event_id`s.
+    ids: Arc<dyn IdGenerator>,
+    /// Hosting and default tags of the layer, added to every event.
+    tags: Map<String, Value>,
+}
+
+impl PogrHandle {
+    /// Creates a handle for the queues of a layer.
+    pub(crate) fn new(
+        queues: Vec<(Level, QueueSender)>,
+        in_flight: Arc<InFlight>,
+        clock: Arc<dyn Clock>,
+        ids: Arc<dyn IdGenerator>,
+        tags: Map<String, Value>,
+    ) -> Self {
+        PogrHandle {
+            inner: Arc::new(HandleInner {
+                queues,
+                in_flight,
+                clock,
+                ids,
+                tags,
+            }),
+        }
+    }
+
+    /// Queues an event with a message and fields, and returns its `event_id` right away.
+    ///
+    /// `fields` should be a JSON object; its entries become tags of the record, next to the
+    /// layer's default tags and the `message`. Any other value is sent as the `fields` tag. The
+    /// record's metadata holds the location of the call, like the file and line of a
+    /// `tracing` event.
+    #[track_caller]
+    pub fn event(&self, level: Level, message: impl Into<String>, fields: Value) -> Uuid {
+        let location = Location::caller();
+        let mut tags = self.inner.tags.clone();
+        match fields {
+            Value::Object(fields) => tags.extend(fields),
+            Value::Null => {}
+            fields => {
+                tags.insert("fields".to_string(), fields);
+            }
+        }
+        tags.insert("message".to_string(), Value::String(message.into()));
+        let tags = Value::Object(tags);
+        let data = json!({
+            "name": "pogr handle event",
+            "target": "pogr_handle",
+            "level": format!("{:?}", level),
+            "file": location.file(),
+            "line": location.line(),
+        });
+
+        let size = ring::RECORD_OVERHEAD + ring::estimated_size(&data) + ring::estimated_size(&tags);
+        self.enqueue(level, None, size, move |appender| LogRequest {
+            service: appender.service_name.clone(),
+            environment: appender.environment.clone(),
+            severity: level.to_string(),
+            r#type: appender.service_type.clone(),
+            log: "rust tracing log captured".to_string(),
+            data,
+            tags,
+        })
+    }
+
+    /// Queues a log request as is, and returns its `event_id` right away.
+    ///
+    /// The request is routed by its `severity`, as `INFO` if it is not a level.
+    pub fn log(&self, request: LogRequest) -> Uuid {
+        self.log_at(None, request)
+    }
+
+    /// Queues a log request as is, timestamped with `timestamp` instead of the layer's clock if
+    /// given, and returns its `event_id`.
+    pub(crate) fn log_at(&self, timestamp: Option<SystemTime>, request: LogRequest) -> Uuid {
+        let level = request.severity.parse().unwrap_or(Level::INFO);
+        let size = ring::RECORD_OVERHEAD + ring::estimated_size(&request.data) + ring::estimated_size(&request.tags);
+        self.enqueue(level, timestamp, size, move |_| request)
+    }
+
+    /// Queues a record at `level` of an estimated `size` in bytes, timestamped with `timestamp`
+    /// or the layer's clock, unless the layer is shutting down.
+    fn enqueue<F>(&self, level: Level, timestamp: Option<SystemTime>, size: usize, build: F) -> Uuid
+    where
+        F: FnOnce(&PogrAppender) -> LogRequest + Send + 'static,
+    {
+        let inner = &self.inner;
+        let event_id = inner.ids.generate(IdKind::Event);
+        if inner.in_flight.is_closed() {
+            return event_id;
+        }
+        let Some((_, queue)) = inner.queues.iter().find(|(queued, _)| *queued == level) else {
+            return event_id;
+        };
+
+        // If the worker is gone the record is dropped, and so is its ticket.
+        let sent = queue.send(Queued {
+            event_id,
+            level,
+            timestamp: timestamp.unwrap_or_else(|| inner.clock.now()),
+            build: Box::new(build),
+            attachments: Vec::new(),
+            log_id: None,
+            ticket: inner.in_flight.start(),
+            size,
+            charge: None,
+        });
+        // Overflowing records are reported by the queue itself.
+        if let Err(Rejected::Stopped) = sent {
+            diagnostics::warn(DiagnosticKind::Dropped, "Dropped a log record because the delivery worker has stopped");
+        }
+        event_id
+    }
+}
@@ -0,0 +1,342 @@
+//! Detection of the hardware a game client runs on.
+//!
+//! Crashes and performance issues often only happen on some GPUs, drivers or amounts of
+//! memory. With the `client-telemetry` feature, the init request describes the hardware: the
+//! GPUs with their drivers, the CPU model and core count, and the RAM size. The hardware is
+//! probed once per process, the first time it is needed, without extra dependencies:
+//!
+//! * On Linux, from `/proc/cpuinfo`, `/proc/meminfo` and the DRM devices in `/sys/class/drm`,
+//!   whose PCI vendors are named and whose kernel drivers report their versions. NVIDIA's
+//!   driver also names the GPU models in `/proc/driver/nvidia/gpus`.
+//! * On macOS, from `sysctl` and `system_profiler SPDisplaysDataType`.
+//! * On Windows, from `wmic`.
+//!
+//! What cannot be read is left out; detection never fails.
+
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::thread;
+
+/// The hardware a session runs on, as detected at startup.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::HardwareInfo;
+///
+/// let hardware = HardwareInfo::detect();
+/// println!("{:?} with {:?} cores", hardware.cpu_model(), hardware.cpu_cores());
+/// for gpu in hardware.gpus() {
+///     println!("{:?} ({:?} {:?})", gpu.model(), gpu.driver(), gpu.driver_version());
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct HardwareInfo {
+    /// Model name of the CPU.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_model: Option<String>,
+    /// Number of logical CPU cores.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_cores: Option<usize>,
+    /// Size of the physical memory, in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_bytes: Option<u64>,
+    /// The GPUs, in the order the system lists them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    gpus: Vec<GpuInfo>,
+}
+
+/// A GPU and its driver.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct GpuInfo {
+    /// Model name of the GPU, or its PCI device ID where no name is available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// Vendor of the GPU, e.g. `NVIDIA`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vendor: Option<String>,
+    /// Name of the driver, e.g. `nvidia` or `amdgpu`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    driver: Option<String>,
+    /// Version of the driver.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    driver_version: Option<String>,
+}
+
+impl HardwareInfo {
+    /// Returns the hardware of this machine, probed on the first call.
+    pub fn detect() -> &'static HardwareInfo {
+        static DETECTED: OnceLock<HardwareInfo> = OnceLock::new();
+        DETECTED.get_or_init(|| HardwareInfo {
+            cpu_model: probe::cpu_model(),
+            cpu_cores: thread::available_parallelism().ok().map(usize::from),
+            memory_bytes: probe::memory_bytes(),
+            gpus: probe::gpus(),
+        })
+    }
+
+    /// Returns the model name of the CPU, if known.
+    pub fn cpu_model(&self) -> Option<&str> {
+        self.cpu_model.as_deref()
+    }
+
+    /// Returns the number of logical CPU cores, if known.
+    pub fn cpu_cores(&self) -> Option<usize> {
+        self.cpu_cores
+    }
+
+    /// Returns the size of the physical memory in bytes, if known.
+    pub fn memory_bytes(&self) -> Option<u64> {
+        self.memory_bytes
+    }
+
+    /// Returns the detected GPUs.
+    pub fn gpus(&self) -> &[GpuInfo] {
+        &self.gpus
+    }
+}
+
+impl GpuInfo {
+    /// Returns the model name of the GPU, if known.
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Returns the vendor of the GPU, if known.
+    pub fn vendor(&self) -> Option<&str> {
+        self.vendor.as_deref()
+    }
+
+    /// Returns the name of the driver, if known.
+    pub fn driver(&self) -> Option<&str> {
+        self.driver.as_deref()
+    }
+
+    /// Returns the version of the driver, if known.
+    pub fn driver_version(&self) -> Option<&str> {
+        self.driver_version.as_deref()
+    }
+}
+
+/// Returns the value of the first `key: value` line of `text` with the given key.
+#[cfg_attr(not(any(target_os = "linux", target_os = "macos", target_os = "windows")), allow(dead_code))]
+fn field<'a>(text: &'a str, key: &str, separator: char) -> Option<&'a str> {
+    text.lines().find_map(|line| {
+        let (name, value) = line.split_once(separator)?;
+        (name.trim() == key).then(|| value.trim()).filter(|value| !value.is_empty())
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod probe {
+    use super::{field, GpuInfo};
+    use std::fs;
+    use std::path::Path;
+
+    /// Names of the GPU vendors by PCI vendor ID.
+    const VENDORS: &[(&str, &str)] = &[
+        ("0x10de", "NVIDIA"),
+        ("0x1002", "AMD"),
+        ("0x8086", "Intel"),
+        ("0x13b5", "ARM"),
+        ("0x5143", "Qualcomm"),
+        ("0x15ad", "VMware"),
+        ("0x1af4", "Red Hat"),
+    ];
+
+    /// Reads the model name from `/proc/cpuinfo`, or the hardware name on ARM boards.
+    pub(super) fn cpu_model() -> Option<String> {
+        let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+        field(&cpuinfo, "model name", ':')
+            .or_else(|| field(&cpuinfo, "Hardware", ':'))
+            .or_else(|| field(&cpuinfo, "Model", ':'))
+            .map(str::to_string)
+    }
+
+    /// Reads `MemTotal` from `/proc/meminfo`.
+    pub(super) fn memory_bytes() -> Option<u64> {
+        let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+        let kilobytes = field(&meminfo, "MemTotal", ':')?.trim_end_matches("kB").trim().parse::<u64>().ok()?;
+        Some(kilobytes * 1024)
+    }
+
+    /// Lists the DRM cards, such as `/sys/class/drm/card0`, skipping their connectors.
+    pub(super) fn gpus() -> Vec<GpuInfo> {
+        let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+            return Vec::new();
+        };
+        let mut cards: Vec<String> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.strip_prefix("card").is_some_and(|index| index.chars().all(|c| c.is_ascii_digit())))
+            .collect();
+        cards.sort();
+        let mut nvidia_models = nvidia_models().into_iter();
+        cards
+            .iter()
+            .map(|card| Path::new("/sys/class/drm").join(card).join("device"))
+            .map(|device| {
+                let vendor_id = read_trimmed(&device.join("vendor"));
+                let driver = fs::read_link(device.join("driver"))
+                    .ok()
+                    .and_then(|driver| Some(driver.file_name()?.to_string_lossy().into_owned()));
+                let model = match driver.as_deref() {
+                    Some("nvidia") => nvidia_models.next(),
+                    _ => None,
+                }
+                .or_else(|| read_trimmed(&device.join("device")).map(|id| format!("PCI device {}", id)));
+                GpuInfo {
+                    model,
+                    vendor: vendor_id.map(|id| match VENDORS.iter().find(|(known, _)| *known == id) {
+                        Some((_, name)) => name.to_string(),
+                        None => id,
+                    }),
+                    driver_version: driver.as_deref().and_then(driver_version),
+                    driver,
+                }
+            })
+            .collect()
+    }
+
+    /// Reads the GPU models named by NVIDIA's driver, in bus order.
+    fn nvidia_models() -> Vec<String> {
+        let Ok(entries) = fs::read_dir("/proc/driver/nvidia/gpus") else {
+            return Vec::new();
+        };
+        let mut gpus: Vec<_> = entries.filter_map(|entry| Some(entry.ok()?.path())).collect();
+        gpus.sort();
+        gpus.iter()
+            .filter_map(|gpu| {
+                let information = fs::read_to_string(gpu.join("information")).ok()?;
+                field(&information, "Model", ':').map(str::to_string)
+            })
+            .collect()
+    }
+
+    /// Reads the version of a kernel driver: NVIDIA's from its banner, others from their module.
+    fn driver_version(driver: &str) -> Option<String> {
+        if driver == "nvidia" {
+            let banner = fs::read_to_string("/proc/driver/nvidia/version").ok()?;
+            let version = banner.split_whitespace().find(|word| word.chars().next().is_some_and(|c| c.is_ascii_digit()) && word.contains('.'))?;
+            return Some(version.to_string());
+        }
+        read_trimmed(&Path::new("/sys/module").join(driver).join("version"))
+    }
+
+    /// Reads a sysfs attribute, without its trailing newline.
+    fn read_trimmed(path: &Path) -> Option<String> {
+        let value = fs::read_to_string(path).ok()?;
+        Some(value.trim().to_string()).filter(|value| !value.is_empty())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod probe {
+    use super::{field, GpuInfo};
+    use std::process::Command;
+
+    /// Runs a command and returns its output, if it succeeds.
+    fn output(program: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Reads `machdep.cpu.brand_string`.
+    pub(super) fn cpu_model() -> Option<String> {
+        Some(output("sysctl", &["-n", "machdep.cpu.brand_string"])?.trim().to_string()).filter(|model| !model.is_empty())
+    }
+
+    /// Reads `hw.memsize`.
+    pub(super) fn memory_bytes() -> Option<u64> {
+        output("sysctl", &["-n", "hw.memsize"])?.trim().parse().ok()
+    }
+
+    /// Lists the displays' chipsets reported by `system_profiler`. Their drivers are part of
+    /// macOS, so the driver version is the Metal support, when reported.
+    pub(super) fn gpus() -> Vec<GpuInfo> {
+        let Some(report) = output("system_profiler", &["SPDisplaysDataType"]) else {
+            return Vec::new();
+        };
+        report
+            .split("Chipset Model:")
+            .skip(1)
+            .map(|section| {
+                let section = format!("Chipset Model:{}", section);
+                GpuInfo {
+                    model: field(&section, "Chipset Model", ':').map(str::to_string),
+                    vendor: field(&section, "Vendor", ':').map(|vendor| vendor.split(" (").next().unwrap_or(vendor).to_string()),
+                    driver: Some("metal".to_string()),
+                    driver_version: field(&section, "Metal Support", ':')
+                        .or_else(|| field(&section, "Metal Family", ':'))
+                        .map(str::to_string),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod probe {
+    use super::{field, GpuInfo};
+    use std::process::Command;
+
+    /// Runs a `wmic` query in list format and returns its records.
+    fn wmic(args: &[&str]) -> Vec<String> {
+        let Ok(output) = Command::new("wmic").args(args).arg("/format:list").output() else {
+            return Vec::new();
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .replace('\r', "")
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|record| !record.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Reads the name of the first processor.
+    pub(super) fn cpu_model() -> Option<String> {
+        let records = wmic(&["cpu", "get", "Name"]);
+        field(records.first()?, "Name", '=').map(str::to_string)
+    }
+
+    /// Reads the total physical memory.
+    pub(super) fn memory_bytes() -> Option<u64> {
+        let records = wmic(&["ComputerSystem", "get", "TotalPhysicalMemory"]);
+        field(records.first()?, "TotalPhysicalMemory", '=')?.parse().ok()
+    }
+
+    /// Lists the video controllers with their drivers.
+    pub(super) fn gpus() -> Vec<GpuInfo> {
+        wmic(&["path", "Win32_VideoController", "get", "Name,AdapterCompatibility,InstalledDisplayDrivers,DriverVersion"])
+            .iter()
+            .map(|record| GpuInfo {
+                model: field(record, "Name", '=').map(str::to_string),
+                vendor: field(record, "AdapterCompatibility", '=').map(str::to_string),
+                driver: field(record, "InstalledDisplayDrivers", '=')
+                    .and_then(|drivers| drivers.split(',').next())
+                    .map(|driver| driver.rsplit('\\').next().unwrap_or(driver).to_string()),
+                driver_version: field(record, "DriverVersion", '=').map(str::to_string),
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod probe {
+    use super::GpuInfo;
+
+    /// The CPU model is not known on this platform.
+    pub(super) fn cpu_model() -> Option<String> {
+        None
+    }
+
+    /// The memory size is not known on this platform.
+    pub(super) fn memory_bytes() -> Option<u64> {
+        None
+    }
+
+    /// GPUs are not detected on this platform.
+    pub(super) fn gpus() -> Vec<GpuInfo> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,242 @@
+//! Connectivity checks against the POGR endpoints.
+//!
+//! `PogrAppender::health_check` lets a service include POGR in its readiness probe. Each
+//! endpoint is checked step by step (DNS resolution, then the connection including the TLS
+//! handshake, with its round-trip latency), and the session is checked against the logs
+//! endpoint, so a failing probe says which step broke.
+
+use crate::clock::parse_http_date;
+use crate::{endpoint_template, metadata, PogrAppender};
+use reqwest::Client;
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long each step of a health check may take.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The server's clock, as read from the `Date` header of a response, and the local clock when
+/// the response arrived.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ClockSample {
+    /// Local time when the response arrived.
+    pub(crate) local: SystemTime,
+    /// Time from the response's `Date` header.
+    pub(crate) server: SystemTime,
+}
+
+/// The outcome of one step of a health check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The step succeeded.
+    Passed,
+    /// The step failed, with the reason.
+    Failed(String),
+    /// The step was not run because an earlier one failed.
+    Skipped,
+}
+
+impl CheckOutcome {
+    /// Returns `true` if the step succeeded.
+    pub fn is_passed(&self) -> bool {
+        matches!(self, CheckOutcome::Passed)
+    }
+}
+
+impl fmt::Display for CheckOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckOutcome::Passed => f.write_str("ok"),
+            CheckOutcome::Failed(reason) => write!(f, "failed: {}", reason),
+            CheckOutcome::Skipped => f.write_str("skipped"),
+        }
+    }
+}
+
+/// Connectivity of one endpoint.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct EndpointHealth {
+    /// The endpoint URL.
+    pub url: String,
+    /// Whether the host name resolved.
+    pub dns: CheckOutcome,
+    /// Whether a connection, including the TLS handshake for `https` endpoints, was made and
+    /// answered with any HTTP response.
+    pub connection: CheckOutcome,
+    /// Round-trip time of the connection check, if it succeeded.
+    pub latency: Option<Duration>,
+}
+
+impl EndpointHealth {
+    /// Returns `true` if the endpoint is reachable.
+    pub fn is_healthy(&self) -> bool {
+        self.dns.is_passed() && self.connection.is_passed()
+    }
+}
+
+/// The result of `PogrAppender::health_check`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct HealthStatus {
+    /// Connectivity of the init endpoint.
+    pub init: EndpointHealth,
+    /// Connectivity of the logs endpoint.
+    pub logs: EndpointHealth,
+    /// Whether the logs endpoint accepts the appender's session.
+    pub session: CheckOutcome,
+}
+
+impl HealthStatus {
+    /// Returns `true` if both endpoints are reachable and the session is accepted.
+    pub fn is_healthy(&self) -> bool {
+        self.init.is_healthy() && self.logs.is_healthy() && self.session.is_passed()
+    }
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, endpoint) in [("init", &self.init), ("logs", &self.logs)] {
+            write!(f, "{} endpoint {}: dns {}, connection {}", name, endpoint.url, endpoint.dns, endpoint.connection)?;
+            if let Some(latency) = endpoint.latency {
+                write!(f, " ({} ms)", latency.as_millis())?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "session: {}", self.session)
+    }
+}
+
+impl PogrAppender {
+    /// Checks connectivity to the init and logs endpoints and that the session is accepted.
+    ///
+    /// For each endpoint, the host name is resolved, then a `HEAD` request is sent; any HTTP
+    /// response counts as reachable, and its round-trip time is reported as the latency. The
+    /// session is checked by submitting an empty batch to the logs endpoint. Each step times
+    /// out after five seconds, so the check is suitable for readiness probes.
+    ///
+    /// # Returns
+    ///
+    /// A `HealthStatus` with the outcome of each step.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::PogrAppender;
+    ///
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    ///
+    /// let health = appender.health_check().await;
+    /// if !health.is_healthy() {
+    ///     eprintln!("POGR is unreachable:\n{}", health);
+    /// }
+    /// # }
+    /// ```
+    pub async fn health_check(&self) -> HealthStatus {
+        let init_url = endpoint_template::resolve(&self.init_endpoint, &self.service_name, &self.environment, &self.service_type, None);
+        let logs_url = self.resolve_endpoint(&self.logs_endpoint);
+        let ((init, _), (logs, _)) = tokio::join!(
+            check_endpoint(&self.client, &init_url),
+            check_endpoint(&self.client, &logs_url)
+        );
+
+        let session = if logs.is_healthy() {
+            self.check_session().await
+        } else {
+            CheckOutcome::Skipped
+        };
+
+        HealthStatus { init, logs, session }
+    }
+
+    /// Submits an empty batch to check that the intake accepts the session.
+    pub(crate) async fn check_session(&self) -> CheckOutcome {
+        let empty_batch = self
+            .try_post(None, |request| {
+                request
+                    .header("Content-Type", "application/json")
+                    .body(self.api_version().encode_serialized(&[]))
+                    .timeout(CHECK_TIMEOUT)
+            })
+            .await;
+
+        match empty_batch {
+            Ok(_) => CheckOutcome::Passed,
+            Err(err) => CheckOutcome::Failed(err.to_string()),
+        }
+    }
+}
+
+/// Resolves an endpoint's host and sends it a `HEAD` request.
+///
+/// # Returns
+///
+/// The endpoint's connectivity, and a clock sample if the response had a `Date` header.
+pub(crate) async fn check_endpoint(client: &Client, url: &str) -> (EndpointHealth, Option<ClockSample>) {
+    let mut health = EndpointHealth {
+        url: url.to_string(),
+        dns: CheckOutcome::Skipped,
+        connection: CheckOutcome::Skipped,
+        latency: None,
+    };
+
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            health.dns = CheckOutcome::Failed(format!("invalid URL: {}", err));
+            return (health, None);
+        }
+    };
+    let (host, port) = match (parsed.host_str(), parsed.port_or_known_default()) {
+        (Some(host), Some(port)) => (host.trim_matches(|c| c == '[' || c == ']').to_string(), port),
+        _ => {
+            health.dns = CheckOutcome::Failed("URL has no host".to_string());
+            return (health, None);
+        }
+    };
+
+    health.dns = match tokio::time::timeout(CHECK_TIMEOUT, tokio::net::lookup_host((host.as_str(), port))).await {
+        Ok(Ok(addresses)) => match addresses.count() {
+            0 => CheckOutcome::Failed(format!("{} has no addresses", host)),
+            _ => CheckOutcome::Passed,
+        },
+        Ok(Err(err)) => CheckOutcome::Failed(err.to_string()),
+        Err(_) => CheckOutcome::Failed("timed out".to_string()),
+    };
+    if !health.dns.is_passed() {
+        return (health, None);
+    }
+
+    let started = Instant::now();
+    let mut clock = None;
+    health.connection = match metadata::apply(client.head(url)).timeout(CHECK_TIMEOUT).send().await {
+        Ok(response) => {
+            health.latency = Some(started.elapsed());
+            clock = response
+                .headers()
+                .get(reqwest::header::DATE)
+                .and_then(|date| date.to_str().ok())
+                .and_then(parse_http_date)
+                .map(|server| ClockSample {
+                    local: SystemTime::now(),
+                    server,
+                });
+            CheckOutcome::Passed
+        }
+        Err(err) => CheckOutcome::Failed(error_chain(&err)),
+    };
+
+    (health, clock)
+}
+
+/// Formats an error with its sources, which carry the useful detail for TLS failures.
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
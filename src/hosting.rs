@@ -0,0 +1,152 @@
+//! Detection of the container or cloud environment a service runs in.
+//!
+//! Services are tagged with where they run, so logs from the same service can be told apart
+//! by deployment without configuration. Detection looks at the variables orchestrators inject
+//! into containers and at files container runtimes create:
+//!
+//! * Kubernetes sets `KUBERNETES_SERVICE_HOST` in every pod.
+//! * ECS sets `ECS_CONTAINER_METADATA_URI_V4` (or `ECS_CONTAINER_METADATA_URI`) in every task.
+//! * Docker creates `/.dockerenv`, Podman `/run/.containerenv`, and the cgroup and mount
+//!   tables of containerized processes refer to the container runtime.
+//!
+//! Detection is on by default. It is turned off with `POGR_DETECT_HOSTING=0`, or in code with
+//! `PogrAppenderBuilder::with_hosting_detection` and `PogrLayer::with_hosting_detection`.
+
+use serde_json::Value;
+use std::path::Path;
+use std::{env, fs};
+
+/// Environment variable that turns hosting detection off when set to `0` or `false`.
+const DETECT_ENV: &str = "POGR_DETECT_HOSTING";
+
+/// File holding the namespace of a Kubernetes pod's service account.
+const K8S_NAMESPACE_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// Files created by container runtimes in the container's root file system.
+const CONTAINER_MARKER_FILES: &[&str] = &["/.dockerenv", "/run/.containerenv"];
+
+/// Process tables that mention the container runtime in containerized processes.
+const CONTAINER_PROC_FILES: &[&str] = &["/proc/self/cgroup", "/proc/self/mountinfo"];
+
+/// Strings identifying a container runtime in the cgroup and mount tables.
+const CONTAINER_RUNTIMES: &[&str] = &["docker", "containerd", "libpod", "kubepods"];
+
+/// Where a service runs.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::Hosting;
+///
+/// println!("running on {}", Hosting::detect().as_str());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hosting {
+    /// A Kubernetes pod.
+    Kubernetes,
+    /// An Amazon ECS task.
+    Ecs,
+    /// A Docker container, or a container of another runtime such as Podman or containerd.
+    Docker,
+    /// Not in a container: a physical or virtual machine.
+    BareMetal,
+}
+
+impl Hosting {
+    /// Detects where this process runs.
+    ///
+    /// Orchestrators are checked before container runtimes, so a Kubernetes pod is reported
+    /// as `Kubernetes` even though it also runs in a container.
+    pub fn detect() -> Self {
+        if has_var("KUBERNETES_SERVICE_HOST") {
+            Hosting::Kubernetes
+        } else if has_var("ECS_CONTAINER_METADATA_URI_V4")
+            || has_var("ECS_CONTAINER_METADATA_URI")
+            || env::var("AWS_EXECUTION_ENV").is_ok_and(|value| value.starts_with("AWS_ECS"))
+        {
+            Hosting::Ecs
+        } else if is_container() {
+            Hosting::Docker
+        } else {
+            Hosting::BareMetal
+        }
+    }
+
+    /// Returns the name used in the `hosting` tag: `kubernetes`, `ecs`, `docker` or
+    /// `bare-metal`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Hosting::Kubernetes => "kubernetes",
+            Hosting::Ecs => "ecs",
+            Hosting::Docker => "docker",
+            Hosting::BareMetal => "bare-metal",
+        }
+    }
+
+    /// Returns the tags describing where the service runs: `hosting`, and in Kubernetes the
+    /// `k8s_namespace` and `k8s_pod` if known.
+    pub(crate) fn tags(self) -> Vec<(String, Value)> {
+        let mut tags = vec![("hosting".to_string(), Value::from(self.as_str()))];
+        if self == Hosting::Kubernetes {
+            if let Some(namespace) = k8s_namespace() {
+                tags.push(("k8s_namespace".to_string(), Value::from(namespace)));
+            }
+            // Kubernetes sets the hostname of a pod to the pod's name.
+            if let Ok(pod) = env::var("HOSTNAME") {
+                tags.push(("k8s_pod".to_string(), Value::from(pod)));
+            }
+        }
+        tags
+    }
+
+    /// Returns the environment to use if `ENVIRONMENT` is not set: the namespace of a
+    /// Kubernetes pod, which deployments commonly name after their environment.
+    pub(crate) fn default_environment(self) -> Option<String> {
+        match self {
+            Hosting::Kubernetes => k8s_namespace(),
+            _ => None,
+        }
+    }
+}
+
+/// Detects where this process runs, unless detection is turned off with `POGR_DETECT_HOSTING`.
+pub(crate) fn detect_if_enabled() -> Option<Hosting> {
+    let enabled = env::var(DETECT_ENV)
+        .map(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "0" | "false"))
+        .unwrap_or(true);
+    enabled.then(Hosting::detect)
+}
+
+/// Returns the name of the host, or in a container the container's hostname.
+pub(crate) fn host_name() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .or_else(|| env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns `true` if the environment variable is set to a non-empty value.
+fn has_var(name: &str) -> bool {
+    env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+/// Returns `true` if this process runs in a container.
+fn is_container() -> bool {
+    CONTAINER_MARKER_FILES.iter().any(|path| Path::new(path).exists())
+        || CONTAINER_PROC_FILES.iter().any(|path| {
+            fs::read_to_string(path)
+                .is_ok_and(|table| CONTAINER_RUNTIMES.iter().any(|runtime| table.contains(runtime)))
+        })
+}
+
+/// Returns the namespace of the Kubernetes pod, from `POD_NAMESPACE` (commonly set through
+/// the downward API) or the service account.
+fn k8s_namespace() -> Option<String> {
+    env::var("POD_NAMESPACE")
+        .ok()
+        .or_else(|| fs::read_to_string(K8S_NAMESPACE_FILE).ok())
+        .map(|namespace| namespace.trim().to_string())
+        .filter(|namespace| !namespace.is_empty())
+}
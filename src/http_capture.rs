@@ -0,0 +1,471 @@
+//! Recording the traffic to POGR to a file, and replaying it against another endpoint.
+//!
+//! When staging and production collectors disagree about the same data, the quickest way to
+//! find out why is to send both exactly the same requests. While recording, enabled with the
+//! `POGR_RECORD_HTTP` environment variable or `record_http_traffic`, every request the crate
+//! sends and its response are appended to a file as HAR entries, one JSON object per line.
+//! Credentials are masked as in HTTP debug mode: `POGR_SECRET` entirely, the access key and
+//! session IDs down to their first four characters.
+//!
+//! `HttpCapture` loads such a file and re-sends the recorded requests against another base URL,
+//! in the session of the appender doing the replay, and reports how the target answered
+//! compared to the original.
+
+use crate::clock::format_rfc3339;
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::http_dump::{mask, mask_json};
+use crate::PogrAppender;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE, HOST};
+use reqwest::{Method, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// Environment variable naming the file to record the traffic to.
+const RECORD_ENV: &str = "POGR_RECORD_HTTP";
+
+/// Header carrying the session of requests to the intake.
+const SESSION_HEADER: &str = "intake_session_id";
+
+/// Header carrying the secret of session initialization requests.
+const SECRET_HEADER: &str = "pogr_secret";
+
+/// Whether traffic is being recorded, to skip the lock when it is not.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// Starts recording the traffic to POGR to `path`, appending to the file if it exists.
+///
+/// Every request the crate sends from now on, and its response, is written to the file as a
+/// HAR entry on a line of its own, with credentials masked. Replay the file with
+/// `HttpCapture`. Recording to another file replaces the current one.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{record_http_traffic, stop_http_recording, PogrAppender};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// record_http_traffic("pogr-traffic.har.jsonl")?;
+/// let appender = PogrAppender::new(None, None).await;
+/// // ...
+/// stop_http_recording();
+/// # Ok(())
+/// # }
+/// ```
+pub fn record_http_traffic(path: impl AsRef<Path>) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *recorder().lock().unwrap_or_else(|p| p.into_inner()) = Some(file);
+    RECORDING.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Stops recording the traffic to POGR, including a recording started with `POGR_RECORD_HTTP`.
+pub fn stop_http_recording() {
+    *recorder().lock().unwrap_or_else(|p| p.into_inner()) = None;
+    RECORDING.store(false, Ordering::SeqCst);
+}
+
+/// Returns the file traffic is recorded to, opening the one named by `POGR_RECORD_HTTP` on
+/// first use.
+fn recorder() -> &'static Mutex<Option<File>> {
+    static RECORDER: OnceLock<Mutex<Option<File>>> = OnceLock::new();
+    RECORDER.get_or_init(|| {
+        let file = std::env::var(RECORD_ENV).ok().filter(|path| !path.trim().is_empty()).and_then(|path| {
+            match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    diagnostics::error(DiagnosticKind::Sink, format!("Failed to open {} to record HTTP traffic: {}", path, err));
+                    None
+                }
+            }
+        });
+        RECORDING.store(file.is_some(), Ordering::SeqCst);
+        Mutex::new(file)
+    })
+}
+
+/// Returns `true` if traffic is being recorded.
+pub(crate) fn recording() -> bool {
+    recorder();
+    RECORDING.load(Ordering::SeqCst)
+}
+
+/// A recorded exchange, in the format of a HAR 1.2 entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    /// When the request was sent, as an RFC 3339 UTC timestamp.
+    started_date_time: String,
+    /// Milliseconds until the response was read.
+    time: f64,
+    /// The request.
+    request: EntryRequest,
+    /// The response, with status `0` if the request failed.
+    response: EntryResponse,
+}
+
+/// A recorded request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryRequest {
+    /// HTTP method.
+    method: String,
+    /// Full URL.
+    url: String,
+    /// Headers, with credentials masked.
+    headers: Vec<EntryHeader>,
+    /// Body, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    post_data: Option<EntryBody>,
+}
+
+/// A recorded response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryResponse {
+    /// Status code, or `0` if no response was received.
+    status: u16,
+    /// Headers.
+    headers: Vec<EntryHeader>,
+    /// Body.
+    content: EntryBody,
+    /// Why no response was received, if so.
+    #[serde(rename = "_error", default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A recorded header.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct EntryHeader {
+    /// Header name.
+    name: String,
+    /// Header value.
+    value: String,
+}
+
+/// A recorded body.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryBody {
+    /// Value of the `Content-Type` header.
+    mime_type: String,
+    /// The body as text, or base64 if `encoding` says so.
+    text: String,
+    /// `base64` for binary bodies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+    /// Whether the body was streamed, e.g. multipart, and could not be recorded.
+    #[serde(rename = "_streamed", default, skip_serializing_if = "std::ops::Not::not")]
+    streamed: bool,
+}
+
+impl EntryBody {
+    /// Records a body, masking identifying JSON fields and encoding binary data as base64.
+    fn new(headers: &HeaderMap, body: &[u8]) -> Self {
+        let mime_type = headers
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+            .unwrap_or_default();
+        if let Ok(mut json) = serde_json::from_slice::<Value>(body) {
+            if mask_json(&mut json) {
+                return EntryBody { mime_type, text: json.to_string(), ..EntryBody::default() };
+            }
+        }
+        match std::str::from_utf8(body) {
+            Ok(text) => EntryBody { mime_type, text: text.to_string(), ..EntryBody::default() },
+            Err(_) => EntryBody {
+                mime_type,
+                text: STANDARD.encode(body),
+                encoding: Some("base64".to_string()),
+                streamed: false,
+            },
+        }
+    }
+
+    /// Returns the bytes of the body.
+    fn bytes(&self) -> Result<Vec<u8>, String> {
+        match self.encoding.as_deref() {
+            Some("base64") => STANDARD.decode(&self.text).map_err(|err| err.to_string()),
+            _ => Ok(self.text.clone().into_bytes()),
+        }
+    }
+}
+
+/// Records headers, masking credentials.
+fn entry_headers(headers: &HeaderMap) -> Vec<EntryHeader> {
+    headers
+        .iter()
+        .map(|(name, value)| EntryHeader {
+            name: name.to_string(),
+            value: mask(name.as_str(), &String::from_utf8_lossy(value.as_bytes())),
+        })
+        .collect()
+}
+
+/// A request being recorded, waiting for its response.
+pub(crate) struct PendingEntry {
+    /// When the request was sent.
+    started: SystemTime,
+    /// The request.
+    request: EntryRequest,
+}
+
+impl PendingEntry {
+    /// Records a request about to be sent.
+    pub(crate) fn new(request: &Request) -> Self {
+        let post_data = request.body().map(|body| match body.as_bytes() {
+            Some(bytes) => EntryBody::new(request.headers(), bytes),
+            None => EntryBody { streamed: true, ..EntryBody::new(request.headers(), &[]) },
+        });
+        PendingEntry {
+            started: SystemTime::now(),
+            request: EntryRequest {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                headers: entry_headers(request.headers()),
+                post_data,
+            },
+        }
+    }
+
+    /// Appends the exchange to the recording, with the response or why there was none.
+    pub(crate) fn finish(self, elapsed: Duration, response: Result<(StatusCode, &HeaderMap, &[u8]), String>) {
+        let response = match response {
+            Ok((status, headers, body)) => EntryResponse {
+                status: status.as_u16(),
+                headers: entry_headers(headers),
+                content: EntryBody::new(headers, body),
+                error: None,
+            },
+            Err(err) => EntryResponse {
+                status: 0,
+                headers: Vec::new(),
+                content: EntryBody::default(),
+                error: Some(err),
+            },
+        };
+        let entry = Entry {
+            started_date_time: format_rfc3339(self.started),
+            time: elapsed.as_secs_f64() * 1000.0,
+            request: self.request,
+            response,
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+
+        let mut recorder = recorder().lock().unwrap_or_else(|p| p.into_inner());
+        if let Some(file) = recorder.as_mut() {
+            if let Err(err) = file.write_all(line.as_bytes()) {
+                drop(recorder);
+                diagnostics::error(DiagnosticKind::Sink, format!("Failed to record HTTP traffic: {}", err));
+            }
+        }
+    }
+}
+
+/// Traffic recorded with `record_http_traffic` or `POGR_RECORD_HTTP`, loaded for replay.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{HttpCapture, PogrAppender};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// // Send the requests captured in production to the staging collector.
+/// let capture = HttpCapture::load("pogr-traffic.har.jsonl")?;
+/// let staging = PogrAppender::new(Some("https://staging.pogr.io/v1/intake/init".to_string()), None).await;
+/// for replayed in capture.replay(&staging, "https://staging.pogr.io").await {
+///     if !replayed.matches() {
+///         println!("{} {}: {:?}", replayed.recorded_status(), replayed.url(), replayed.outcome());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpCapture {
+    /// The recorded exchanges, in the order they were recorded.
+    entries: Vec<Entry>,
+}
+
+impl HttpCapture {
+    /// Loads a recording, one HAR entry per line.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file cannot be read, or a line is not a HAR entry.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|err| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {} is not a HAR entry: {}", index + 1, err))
+            })?;
+            entries.push(entry);
+        }
+        Ok(HttpCapture { entries })
+    }
+
+    /// Returns the number of recorded requests.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sends the recorded requests again, one after the other, with the scheme, host and port
+    /// of their URLs replaced by `base_url`.
+    ///
+    /// Requests to the intake are sent in the session of `appender`, whose client is used.
+    /// Session initializations are skipped, since their credentials were not recorded, and so
+    /// are requests whose body was streamed.
+    pub async fn replay(&self, appender: &PogrAppender, base_url: &str) -> Vec<ReplayedRequest> {
+        let mut replayed = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let url = rebase(&entry.request.url, base_url);
+            let outcome = replay_entry(appender, entry, &url).await;
+            replayed.push(ReplayedRequest {
+                method: entry.request.method.clone(),
+                recorded_url: entry.request.url.clone(),
+                url,
+                recorded_status: entry.response.status,
+                outcome,
+            });
+        }
+        replayed
+    }
+}
+
+/// Replaces the scheme, host and port of `url` with `base_url`, keeping its path and query.
+fn rebase(url: &str, base_url: &str) -> String {
+    let path = match reqwest::Url::parse(url) {
+        Ok(url) => match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        },
+        Err(_) => return url.to_string(),
+    };
+    format!("{}{}", base_url.trim_end_matches('/'), path)
+}
+
+/// Sends a recorded request to `url`.
+async fn replay_entry(appender: &PogrAppender, entry: &Entry, url: &str) -> ReplayOutcome {
+    let request = &entry.request;
+    let has_header = |name: &str| request.headers.iter().any(|header| header.name.eq_ignore_ascii_case(name));
+    if has_header(SECRET_HEADER) {
+        return ReplayOutcome::Skipped("session initialization, whose credentials were not recorded");
+    }
+    if request.post_data.as_ref().is_some_and(|body| body.streamed) {
+        return ReplayOutcome::Skipped("streamed body, which was not recorded");
+    }
+    let Ok(method) = Method::from_bytes(request.method.as_bytes()) else {
+        return ReplayOutcome::Failed(format!("invalid method {}", request.method));
+    };
+
+    let mut builder = appender.client.request(method, url);
+    for header in &request.headers {
+        let name = header.name.to_ascii_lowercase();
+        if name == SESSION_HEADER {
+            builder = builder.header(header.name.as_str(), &appender.session_id);
+        } else if name != CONTENT_LENGTH.as_str() && name != HOST.as_str() {
+            builder = builder.header(header.name.as_str(), header.value.as_str());
+        }
+    }
+    if let Some(body) = &request.post_data {
+        match body.bytes() {
+            Ok(bytes) => builder = builder.body(bytes),
+            Err(err) => return ReplayOutcome::Failed(format!("invalid recorded body: {}", err)),
+        }
+    }
+
+    match builder.send().await {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            match response.bytes().await {
+                Ok(body) => ReplayOutcome::Sent { status, body: body.to_vec() },
+                Err(err) => ReplayOutcome::Failed(err.to_string()),
+            }
+        }
+        Err(err) => ReplayOutcome::Failed(err.to_string()),
+    }
+}
+
+/// What happened to a recorded request when it was replayed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReplayOutcome {
+    /// The target answered with `status` and `body`.
+    Sent {
+        /// Status code of the response.
+        status: u16,
+        /// Body of the response.
+        body: Vec<u8>,
+    },
+    /// The request could not be sent, or the response could not be read.
+    Failed(String),
+    /// The request was not sent, for the given reason.
+    Skipped(&'static str),
+}
+
+/// A recorded request and how the target answered it when replayed.
+#[derive(Clone, Debug)]
+pub struct ReplayedRequest {
+    /// HTTP method.
+    method: String,
+    /// URL the request was originally sent to.
+    recorded_url: String,
+    /// URL the request was replayed to.
+    url: String,
+    /// Status of the recorded response, `0` if there was none.
+    recorded_status: u16,
+    /// What happened when replaying.
+    outcome: ReplayOutcome,
+}
+
+impl ReplayedRequest {
+    /// Returns the HTTP method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the URL the request was originally sent to.
+    pub fn recorded_url(&self) -> &str {
+        &self.recorded_url
+    }
+
+    /// Returns the URL the request was replayed to.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the status of the recorded response, `0` if the original request failed.
+    pub fn recorded_status(&self) -> u16 {
+        self.recorded_status
+    }
+
+    /// Returns what happened when replaying.
+    pub fn outcome(&self) -> &ReplayOutcome {
+        &self.outcome
+    }
+
+    /// Returns `true` if the target answered with the recorded status.
+    pub fn matches(&self) -> bool {
+        matches!(self.outcome, ReplayOutcome::Sent { status, .. } if status == self.recorded_status)
+    }
+}
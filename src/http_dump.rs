@@ -0,0 +1,224 @@
+//! Sending requests to POGR, with an optional dump of the raw traffic.
+//!
+//! When the intake rejects requests during onboarding, the parsed response alone rarely
+//! says why. In HTTP debug mode, enabled with the `POGR_DEBUG_HTTP` environment variable or
+//! `set_http_debug`, every init and log request is printed to stderr with its headers and
+//! body, followed by the raw response. Credentials are masked: `POGR_SECRET` entirely, the
+//! access key and session IDs down to their first four characters. The traffic can also be
+//! recorded to a file and replayed, see `http_capture`.
+
+use crate::http_capture::{self, PendingEntry};
+use crate::metadata;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::Value;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+
+/// Environment variable enabling HTTP debug mode.
+const DEBUG_ENV: &str = "POGR_DEBUG_HTTP";
+
+/// Debug mode has not been determined yet.
+const UNSET: u8 = 0;
+/// Debug mode is off.
+const OFF: u8 = 1;
+/// Debug mode is on.
+const ON: u8 = 2;
+
+/// Whether HTTP debug mode is on, read from the environment on first use.
+static DEBUG: AtomicU8 = AtomicU8::new(UNSET);
+
+/// Headers whose values are never shown.
+const SECRET_HEADERS: &[&str] = &["pogr_secret", "authorization"];
+
+/// Headers and JSON fields whose values are shortened to their first characters.
+const IDENTIFYING_NAMES: &[&str] = &["pogr_access", "intake_session_id", "session_id"];
+
+/// Number of characters left visible in identifying values.
+const VISIBLE_PREFIX: usize = 4;
+
+/// Turns HTTP debug mode on or off, overriding the `POGR_DEBUG_HTTP` environment variable.
+///
+/// In debug mode, every request to the init and logs endpoints and its response are printed
+/// to stderr, with credentials masked. Use it to find out why the intake rejects requests;
+/// it is too verbose for production.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{set_http_debug, PogrAppender};
+///
+/// # async fn run() {
+/// set_http_debug(true);
+/// let appender = PogrAppender::new(None, None).await;
+/// # }
+/// ```
+pub fn set_http_debug(enabled: bool) {
+    DEBUG.store(if enabled { ON } else { OFF }, Ordering::Relaxed);
+}
+
+/// Returns `true` if HTTP debug mode is on.
+///
+/// Unless set explicitly, debug mode is on if `POGR_DEBUG_HTTP` is set to anything other than
+/// an empty string, `0` or `false`.
+fn enabled() -> bool {
+    match DEBUG.load(Ordering::Relaxed) {
+        ON => true,
+        OFF => false,
+        _ => {
+            let enabled = std::env::var(DEBUG_ENV)
+                .map(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "" | "0" | "false"))
+                .unwrap_or(false);
+            // Keep an explicit setting made in the meantime.
+            let _ = DEBUG.compare_exchange(UNSET, if enabled { ON } else { OFF }, Ordering::Relaxed, Ordering::Relaxed);
+            DEBUG.load(Ordering::Relaxed) == ON
+        }
+    }
+}
+
+/// Sends a request with the client's identification headers and returns the response status
+/// and raw body.
+///
+/// In HTTP debug mode, the request and the response are printed to stderr.
+pub(crate) async fn send(client: &Client, request: RequestBuilder) -> reqwest::Result<(StatusCode, Vec<u8>)> {
+    let (status, _, body) = send_with_headers(client, request).await?;
+    Ok((status, body))
+}
+
+/// Like `send`, also returning the response headers.
+///
+/// While traffic is recorded, the request and the response are appended to the recording.
+pub(crate) async fn send_with_headers(
+    client: &Client,
+    request: RequestBuilder,
+) -> reqwest::Result<(StatusCode, HeaderMap, Vec<u8>)> {
+    let request = metadata::apply(request);
+    let debug = enabled();
+    if !debug && !http_capture::recording() {
+        let response = request.send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        return Ok((status, headers, response.bytes().await?.to_vec()));
+    }
+
+    let request = request.build()?;
+    if debug {
+        let mut dump = format!("> {} {}\n", request.method(), request.url());
+        write_headers(&mut dump, '>', request.headers());
+        match request.body().and_then(|body| body.as_bytes()) {
+            Some(body) => write_body(&mut dump, '>', body),
+            None if request.body().is_some() => dump.push_str(">\n> <streamed body, e.g. multipart>\n"),
+            None => {}
+        }
+        print_dump(&dump);
+    }
+    let recorded = http_capture::recording().then(|| PendingEntry::new(&request));
+
+    let started = Instant::now();
+    let response = match client.execute(request).await {
+        Ok(response) => response,
+        Err(err) => {
+            if debug {
+                print_dump(&format!("< request failed after {} ms: {}\n", started.elapsed().as_millis(), err));
+            }
+            if let Some(recorded) = recorded {
+                recorded.finish(started.elapsed(), Err(err.to_string()));
+            }
+            return Err(err);
+        }
+    };
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?.to_vec();
+    if debug {
+        let mut dump = format!("< {} ({} ms)\n", status, started.elapsed().as_millis());
+        write_headers(&mut dump, '<', &headers);
+        write_body(&mut dump, '<', &body);
+        print_dump(&dump);
+    }
+    if let Some(recorded) = recorded {
+        recorded.finish(started.elapsed(), Ok((status, &headers, &body)));
+    }
+
+    Ok((status, headers, body))
+}
+
+/// Prints a dump to stderr in one piece, so concurrent dumps do not interleave.
+fn print_dump(dump: &str) {
+    let prefixed: String = dump.lines().map(|line| format!("pogr_tracing_rs: {}\n", line)).collect();
+    eprint!("{}", prefixed);
+}
+
+/// Appends headers, masking credentials.
+fn write_headers(dump: &mut String, direction: char, headers: &HeaderMap) {
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        let _ = writeln!(dump, "{} {}: {}", direction, name, mask(name.as_str(), &value));
+    }
+}
+
+/// Appends a body after a blank line, masking identifying JSON fields.
+///
+/// The body is shown as sent, unless it is JSON with fields that had to be masked.
+fn write_body(dump: &mut String, direction: char, body: &[u8]) {
+    let masked = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|mut json| mask_json(&mut json).then(|| json.to_string()));
+    let text = masked.unwrap_or_else(|| String::from_utf8_lossy(body).into_owned());
+
+    let _ = writeln!(dump, "{}", direction);
+    for line in text.lines() {
+        let _ = writeln!(dump, "{} {}", direction, line);
+    }
+}
+
+/// Masks the value of a header or field according to its name.
+pub(crate) fn mask(name: &str, value: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    if SECRET_HEADERS.contains(&name.as_str()) {
+        "****".to_string()
+    } else if IDENTIFYING_NAMES.contains(&name.as_str()) {
+        let visible: String = value.chars().take(VISIBLE_PREFIX).collect();
+        if visible.len() < value.len() {
+            format!("{}****", visible)
+        } else {
+            "****".to_string()
+        }
+    } else {
+        value.to_string()
+    }
+}
+
+/// Masks identifying string fields anywhere in a JSON value.
+///
+/// # Returns
+///
+/// `true` if any field was masked.
+pub(crate) fn mask_json(value: &mut Value) -> bool {
+    let mut masked = false;
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(text) => {
+                        let replacement = mask(key, text);
+                        if replacement != *text {
+                            *text = replacement;
+                            masked = true;
+                        }
+                    }
+                    other => masked |= mask_json(other),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                masked |= mask_json(item);
+            }
+        }
+        _ => {}
+    }
+    masked
+}
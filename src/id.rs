@@ -0,0 +1,129 @@
+//! Generation of the identifiers the layer assigns.
+//!
+//! The layer assigns an `event_id` to every record it captures, a `trace_id` to every exported
+//! trace, and an idempotency key to every request to the logs endpoint, which is sent again
+//! with the retries of the request so the intake can discard duplicates. By default these are
+//! UUIDv7s, which sort by creation time.
+//!
+//! IDs are taken through the `IdGenerator` trait, so tests can inject a
+//! `SequentialIdGenerator` and get deterministic payloads, and services with an ID scheme of
+//! their own can encode it in the 128 bits of a UUID.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+/// Header carrying the idempotency key of a request to the logs endpoint.
+pub(crate) const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+
+/// What an identifier is generated for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IdKind {
+    /// The `event_id` of a record.
+    Event,
+    /// The `trace_id` of an exported trace.
+    Trace,
+    /// The `Idempotency-Key` of a request to the logs endpoint.
+    IdempotencyKey,
+}
+
+/// A source of identifiers for records, traces and requests.
+///
+/// Implementations must be cheap to call, since an ID is generated for every captured event,
+/// and must not return the same ID twice for the same kind.
+///
+/// # Examples
+///
+/// ```
+/// use pogr_tracing_rs::{IdGenerator, IdKind};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use uuid::Uuid;
+///
+/// /// Encodes the shard of the service in the top bits of its IDs.
+/// struct ShardedIds {
+///     shard: u16,
+///     next: AtomicU64,
+/// }
+///
+/// impl IdGenerator for ShardedIds {
+///     fn generate(&self, _kind: IdKind) -> Uuid {
+///         let sequence = self.next.fetch_add(1, Ordering::Relaxed);
+///         Uuid::from_u128((u128::from(self.shard) << 112) | u128::from(sequence))
+///     }
+/// }
+/// ```
+pub trait IdGenerator: Send + Sync {
+    /// Returns a new identifier for `kind`.
+    fn generate(&self, kind: IdKind) -> Uuid;
+}
+
+/// Generates UUIDv7s, which start with their creation time in milliseconds. This is the
+/// default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UuidV7Generator;
+
+impl IdGenerator for UuidV7Generator {
+    fn generate(&self, _kind: IdKind) -> Uuid {
+        Uuid::now_v7()
+    }
+}
+
+/// Generates random UUIDv4s, which do not reveal when they were created.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UuidV4Generator;
+
+impl IdGenerator for UuidV4Generator {
+    fn generate(&self, _kind: IdKind) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// Generates consecutive IDs from a starting value, for deterministic tests.
+///
+/// IDs of all kinds share the sequence.
+///
+/// # Examples
+///
+/// ```
+/// use pogr_tracing_rs::{IdGenerator, IdKind, SequentialIdGenerator};
+/// use uuid::Uuid;
+///
+/// let ids = SequentialIdGenerator::new(1);
+///
+/// assert_eq!(ids.generate(IdKind::Event), Uuid::from_u128(1));
+/// assert_eq!(ids.generate(IdKind::Trace), Uuid::from_u128(2));
+/// ```
+#[derive(Debug)]
+pub struct SequentialIdGenerator {
+    /// The value of the next ID.
+    next: AtomicU64,
+}
+
+impl SequentialIdGenerator {
+    /// Creates a generator whose first ID is `start`.
+    pub fn new(start: u64) -> Self {
+        SequentialIdGenerator {
+            next: AtomicU64::new(start),
+        }
+    }
+}
+
+impl IdGenerator for SequentialIdGenerator {
+    fn generate(&self, _kind: IdKind) -> Uuid {
+        Uuid::from_u128(u128::from(self.next.fetch_add(1, Ordering::Relaxed)))
+    }
+}
+
+/// Allows sharing one generator between a test and the layer it configures.
+impl<G: IdGenerator + ?Sized> IdGenerator for Arc<G> {
+    fn generate(&self, kind: IdKind) -> Uuid {
+        (**self).generate(kind)
+    }
+}
+
+/// Returns the process-wide default generator, a `UuidV7Generator`.
+pub(crate) fn default_id_generator() -> Arc<dyn IdGenerator> {
+    static DEFAULT_IDS: OnceLock<Arc<UuidV7Generator>> = OnceLock::new();
+    DEFAULT_IDS.get_or_init(|| Arc::new(UuidV7Generator)).clone()
+}
@@ -0,0 +1,152 @@
+//! A sink writing records to the systemd journal.
+//!
+//! Records are sent over journald's native protocol rather than printed to stderr, so every
+//! field stays a separate, queryable journal field (`journalctl PLAYER_ID=42`). Use it as a
+//! fallback to keep logs locally when POGR is unreachable, or as a mirror for local debugging.
+
+use crate::clock;
+use crate::sink::{Sink, SinkRecord};
+use serde_json::Value;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+
+/// Path of journald's native protocol socket.
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// A `Sink` that writes structured records to journald.
+///
+/// Each record becomes one journal entry:
+///
+/// * `MESSAGE` is the event's message, `PRIORITY` is derived from its severity and
+///   `SYSLOG_IDENTIFIER` is the service name.
+/// * `POGR_EVENT_ID`, `POGR_TIMESTAMP`, `POGR_ENVIRONMENT`, `POGR_TYPE` and `POGR_LOG` carry the
+///   record's POGR metadata; `CODE_FILE`, `CODE_LINE` and `TARGET` its source location.
+/// * Every event field is added under its upper-cased name, e.g. `player_id` becomes
+///   `PLAYER_ID`. Non-string values are written as JSON.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{JournaldSink, PogrAppender, PogrLayer, SinkMode};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_sink(JournaldSink::new()?, SinkMode::Fallback);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JournaldSink {
+    /// Datagram socket connected to journald.
+    socket: UnixDatagram,
+}
+
+impl JournaldSink {
+    /// Connects to the local journald.
+    ///
+    /// Fails if journald is not running, e.g. on hosts without systemd.
+    pub fn new() -> io::Result<Self> {
+        Self::with_socket_path(JOURNALD_SOCKET)
+    }
+
+    /// Connects to a journald native protocol socket at a custom path.
+    pub fn with_socket_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(JournaldSink { socket })
+    }
+}
+
+impl Sink for JournaldSink {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        let request = record.request();
+        let mut entry = Vec::new();
+
+        let message = request
+            .tags
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or(&request.log);
+        put_field(&mut entry, "MESSAGE", message);
+        put_field(&mut entry, "PRIORITY", priority(&request.severity));
+        put_field(&mut entry, "SYSLOG_IDENTIFIER", &request.service);
+
+        put_field(&mut entry, "POGR_EVENT_ID", &record.event_id().to_string());
+        put_field(&mut entry, "POGR_TIMESTAMP", &clock::format_rfc3339(record.timestamp()));
+        put_field(&mut entry, "POGR_ENVIRONMENT", &request.environment);
+        put_field(&mut entry, "POGR_TYPE", &request.r#type);
+        put_field(&mut entry, "POGR_LOG", &request.log);
+
+        if let Value::Object(data) = &request.data {
+            for (key, value) in data {
+                let name = match key.as_str() {
+                    "file" => "CODE_FILE".to_string(),
+                    "line" => "CODE_LINE".to_string(),
+                    "target" => "TARGET".to_string(),
+                    other => format!("POGR_{}", field_name(other)),
+                };
+                put_value(&mut entry, &name, value);
+            }
+        }
+
+        if let Value::Object(tags) = &request.tags {
+            for (key, value) in tags.iter().filter(|(key, _)| *key != "message") {
+                put_value(&mut entry, &field_name(key), value);
+            }
+        }
+
+        self.socket.send(&entry).map(|_| ())
+    }
+}
+
+/// Maps a POGR severity to a syslog priority.
+fn priority(severity: &str) -> &'static str {
+    match severity.to_ascii_uppercase().as_str() {
+        "ERROR" => "3",
+        "WARN" => "4",
+        "INFO" => "6",
+        _ => "7",
+    }
+}
+
+/// Converts a field name into a valid journal field name.
+///
+/// Journal field names consist of upper-case letters, digits and underscores, and must not
+/// start with an underscore (reserved for trusted fields) or a digit.
+fn field_name(name: &str) -> String {
+    let mut field: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+
+    if !field.starts_with(|c: char| c.is_ascii_uppercase()) {
+        field.insert_str(0, "F_");
+    }
+    field.truncate(64);
+    field
+}
+
+/// Appends a JSON value as a field; strings are written as-is, other values as JSON.
+fn put_value(entry: &mut Vec<u8>, name: &str, value: &Value) {
+    match value {
+        Value::Null => {}
+        Value::String(text) => put_field(entry, name, text),
+        other => put_field(entry, name, &other.to_string()),
+    }
+}
+
+/// Appends a field in journald's native format.
+///
+/// Values containing newlines use the length-prefixed binary form.
+fn put_field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
@@ -0,0 +1,155 @@
+//! A sink publishing records to Kafka.
+//!
+//! Records are published as the same JSON payloads the intake receives, so a data platform
+//! that mirrors telemetry into Kafka sees exactly what POGR sees. Records are collected while
+//! a batch is written and produced together when the sink is flushed.
+//!
+//! The `kafka` producer is synchronous, so batches are produced on a dedicated thread: waiting
+//! for the brokers' acknowledgements on the async delivery worker would let an unavailable
+//! broker stall delivery to POGR, which a fallback sink is meant to protect.
+
+use crate::sink::{Sink, SinkRecord, SinkThread};
+use kafka::producer::{Producer, Record, RequiredAcks};
+use serde_json::Value;
+use std::io;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// A `Sink` that publishes records to a Kafka topic.
+///
+/// Available with the `kafka` feature.
+///
+/// Flushing hands the batch to a producer thread and returns without waiting for the brokers.
+/// Up to 16 batches are queued; while the thread is that far behind, further batches are
+/// dropped and the flush fails. Failures to produce are reported as `Sink` diagnostics.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{KafkaSink, PogrAppender, PogrLayer, SinkMode};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let appender = PogrAppender::new(None, None).await;
+/// let kafka = KafkaSink::new(vec!["kafka-1:9092".to_string()], "game-telemetry")?
+///     .with_key_field("player_id");
+///
+/// let layer = PogrLayer::new(appender).with_sink(kafka, SinkMode::Mirror);
+/// # Ok(())
+/// # }
+/// ```
+pub struct KafkaSink {
+    /// Producer connected to the cluster, until it is moved to the producer thread.
+    producer: Mutex<Option<Producer>>,
+    /// Topic the records are published to.
+    topic: String,
+    /// Event field whose value is used as the message key.
+    key_field: Option<String>,
+    /// Keys and payloads written since the last flush.
+    pending: Mutex<Vec<(Vec<u8>, String)>>,
+    /// Produces the flushed batches, owning the producer.
+    producer_thread: SinkThread<Vec<(Vec<u8>, String)>>,
+}
+
+impl KafkaSink {
+    /// Connects to the Kafka cluster and publishes records to `topic`.
+    ///
+    /// Each record is acknowledged by the partition leader before the batch counts as sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `hosts` - Bootstrap brokers, e.g. `kafka-1:9092`.
+    /// * `topic` - The topic records are published to.
+    pub fn new(hosts: Vec<String>, topic: impl Into<String>) -> io::Result<Self> {
+        let producer = Producer::from_hosts(hosts)
+            .with_ack_timeout(Duration::from_secs(5))
+            .with_required_acks(RequiredAcks::One)
+            .create()
+            .map_err(to_io_error)?;
+
+        Ok(KafkaSink {
+            producer: Mutex::new(Some(producer)),
+            topic: topic.into(),
+            key_field: None,
+            pending: Mutex::new(Vec::new()),
+            producer_thread: SinkThread::new("pogr-kafka-producer"),
+        })
+    }
+
+    /// Uses the value of an event field as the message key, e.g. `player_id`, so that all
+    /// records for the same value land in the same partition and stay in order.
+    ///
+    /// String values are used as-is, other values as JSON. Records without the field are
+    /// published without a key.
+    pub fn with_key_field(mut self, field: impl Into<String>) -> Self {
+        self.key_field = Some(field.into());
+        self
+    }
+
+    /// Returns the message key for a record.
+    fn key(&self, record: &SinkRecord<'_>) -> Vec<u8> {
+        match self.key_field.as_deref().and_then(|field| record.field(field)) {
+            Some(Value::String(key)) => key.clone().into_bytes(),
+            Some(Value::Null) | None => Vec::new(),
+            Some(other) => other.to_string().into_bytes(),
+        }
+    }
+
+    /// Locks the pending records, recovering them if a previous holder panicked.
+    fn pending(&self) -> MutexGuard<'_, Vec<(Vec<u8>, String)>> {
+        self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Produces a batch of keys and payloads to `topic`, waiting for the partition leaders.
+fn produce(producer: &mut Producer, topic: &str, pending: &[(Vec<u8>, String)]) -> io::Result<()> {
+    let records: Vec<Record<'_, &[u8], &[u8]>> = pending
+        .iter()
+        .map(|(key, payload)| Record::from_key_value(topic, key.as_slice(), payload.as_bytes()))
+        .collect();
+
+    let confirms = producer.send_all(&records).map_err(to_io_error)?;
+
+    for confirm in confirms {
+        for partition in confirm.partition_confirms {
+            if let Err(code) = partition.offset {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("partition {} of {} rejected records: {:?}", partition.partition, confirm.topic, code),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Sink for KafkaSink {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        let key = self.key(record);
+        self.pending().push((key, record.to_json()));
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let pending = std::mem::take(&mut *self.pending());
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        self.producer_thread.send(pending, || {
+            let mut producer = self
+                .producer
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .take()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "the Kafka producer has stopped"))?;
+            let topic = self.topic.clone();
+            Ok(move |pending: Vec<(Vec<u8>, String)>| produce(&mut producer, &topic, &pending))
+        })
+    }
+}
+
+/// Converts a Kafka error into an I/O error for the public API.
+fn to_io_error(err: kafka::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
@@ -0,0 +1,153 @@
+//! Several independent `PogrLayer`s in one process, and routing code to one of them.
+//!
+//! Plugin-style applications give each plugin its own session and credentials, i.e. a
+//! `PogrLayer` with an appender of its own, even though all of them share one subscriber
+//! stack. Each layer has an identity of its own: what it stores in span extensions is kept
+//! apart from what other layers store, and attachments queued for an event are handed to
+//! every layer that records the event.
+//!
+//! Every layer sees every event, though. A `LayerScope`, obtained with
+//! `PogrLayer::layer_scope`, routes a subtree of code to one layer: events emitted while a
+//! future or closure runs inside the scope are only recorded by that layer, while events
+//! outside any scope are recorded by all of them.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing_subscriber::registry::{Extensions, ExtensionsMut};
+
+/// Identity of a `PogrLayer`, unique within the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct LayerId(u64);
+
+impl LayerId {
+    /// Returns an identity no other layer has.
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        LayerId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+tokio::task_local! {
+    /// The layer that events of the current task or closure are routed to.
+    static SCOPE: LayerId;
+}
+
+/// Routes the events of a subtree of code to a single `PogrLayer`.
+///
+/// Scopes nest: the innermost one decides. Tasks spawned from a scope do not inherit it;
+/// scope the spawned future as well. Span records, such as enter, exit and close records and
+/// trace documents, are routed by where the span transition happens.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+/// use tracing_subscriber::{layer::SubscriberExt, Registry};
+///
+/// # async fn run() {
+/// let host = PogrLayer::new(PogrAppender::new(None, None).await);
+/// let plugin = PogrLayer::new(
+///     PogrAppender::new(Some("plugin-client-id".to_string()), Some("plugin-build-id".to_string())).await,
+/// );
+/// let plugin_scope = plugin.layer_scope();
+///
+/// let subscriber = Registry::default().with(host).with(plugin);
+/// tracing::subscriber::set_global_default(subscriber).unwrap();
+///
+/// tracing::info!("Server started"); // recorded by both layers
+/// plugin_scope
+///     .scope(async {
+///         tracing::info!("Plugin loaded"); // recorded by the plugin's layer only
+///     })
+///     .await;
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayerScope {
+    /// The layer the events are routed to.
+    layer: LayerId,
+}
+
+impl LayerScope {
+    /// Creates a scope routing events to the layer with the given identity.
+    pub(crate) fn new(layer: LayerId) -> Self {
+        LayerScope { layer }
+    }
+
+    /// Runs `future`, recording the events it emits with this scope's layer only.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        SCOPE.scope(self.layer, future).await
+    }
+
+    /// Runs `f`, recording the events it emits with this scope's layer only.
+    ///
+    /// This suits synchronous code, such as a plugin callback invoked outside tokio.
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        SCOPE.sync_scope(self.layer, f)
+    }
+
+    /// Returns the scope the current task or closure runs in, if any.
+    pub fn current() -> Option<LayerScope> {
+        SCOPE.try_with(|layer| LayerScope::new(*layer)).ok()
+    }
+}
+
+/// Returns `true` if the layer `id` records events emitted here, i.e. outside any scope or in
+/// a scope of that layer.
+pub(crate) fn admits(id: LayerId) -> bool {
+    SCOPE.try_with(|layer| *layer == id).unwrap_or(true)
+}
+
+/// Values of type `T` stored in a span's extensions, one per layer.
+///
+/// A span's extensions hold a single value per type, so layers storing the same type directly
+/// would collide.
+struct PerLayer<T> {
+    /// The values, by layer.
+    values: Vec<(LayerId, T)>,
+}
+
+/// Returns the value of type `T` the layer `id` stored in a span's extensions.
+pub(crate) fn get<'a, T: Send + Sync + 'static>(extensions: &'a Extensions<'_>, id: LayerId) -> Option<&'a T> {
+    extensions
+        .get::<PerLayer<T>>()?
+        .values
+        .iter()
+        .find_map(|(layer, value)| (*layer == id).then_some(value))
+}
+
+/// Returns the values of type `T` every layer stored in a span's extensions.
+pub(crate) fn all<'a, T: Send + Sync + 'static>(extensions: &'a Extensions<'_>) -> impl Iterator<Item = &'a T> {
+    extensions
+        .get::<PerLayer<T>>()
+        .into_iter()
+        .flat_map(|stored| stored.values.iter().map(|(_, value)| value))
+}
+
+/// Returns the value of type `T` the layer `id` stored in a span's extensions, for changing it.
+pub(crate) fn get_mut<'a, T: Send + Sync + 'static>(extensions: &'a mut ExtensionsMut<'_>, id: LayerId) -> Option<&'a mut T> {
+    extensions
+        .get_mut::<PerLayer<T>>()?
+        .values
+        .iter_mut()
+        .find_map(|(layer, value)| (*layer == id).then_some(value))
+}
+
+/// Stores a value of type `T` for the layer `id` in a span's extensions, replacing the one it
+/// stored before.
+pub(crate) fn insert<T: Send + Sync + 'static>(extensions: &mut ExtensionsMut<'_>, id: LayerId, value: T) {
+    match extensions.get_mut::<PerLayer<T>>() {
+        Some(stored) => {
+            stored.values.retain(|(layer, _)| *layer != id);
+            stored.values.push((id, value));
+        }
+        None => extensions.insert(PerLayer { values: vec![(id, value)] }),
+    }
+}
+
+/// Removes the value of type `T` the layer `id` stored in a span's extensions, and returns it.
+pub(crate) fn remove<T: Send + Sync + 'static>(extensions: &mut ExtensionsMut<'_>, id: LayerId) -> Option<T> {
+    let stored = extensions.get_mut::<PerLayer<T>>()?;
+    let index = stored.values.iter().position(|(layer, _)| *layer == id)?;
+    Some(stored.values.remove(index).1)
+}
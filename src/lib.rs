@@ -39,16 +39,12 @@
 //! ```rust,no_run
 //! use pogr_tracing_rs::{PogrLayer, PogrAppender};
 //! use tracing_subscriber::{Registry, layer::SubscriberExt};
-//! use std::sync::Arc;
-//! use tokio::sync::Mutex;
 //! 
 //! #[tokio::main]
 //! async fn main() {
 //! 
 //!     let appender = PogrAppender::new(None, None).await;
-//!     let layer = PogrLayer {
-//!         appender: Arc::new(Mutex::new(appender)),
-//!     };
+//!     let layer = PogrLayer::new(appender);
 //! 
 //!     let subscriber = Registry::default().with(layer);
 //!     tracing::subscriber::set_global_default(subscriber)
@@ -59,6 +55,20 @@
 //! This will enable your application to automatically capture and send log data to the POGR
 //! analytics platform, leveraging Rust's async capabilities for efficient logging.
 //! 
+//! For the quickest setup, annotate your async `main` with `#[pogr_tracing_rs::main]`. It builds
+//! the tokio runtime, installs a subscriber with a console layer and a `PogrLayer`, registers a
+//! panic hook that reports panics to POGR, and flushes pending logs when `main` returns:
+//! 
+//! ```rust,no_run
+//! #[pogr_tracing_rs::main]
+//! async fn main() {
+//!     tracing::info!("Application started");
+//! }
+//! ```
+//! 
+//! `#[pogr_tracing_rs::test]` does the same for async tests, using a scoped subscriber instead of
+//! the global one.
+//! 
 //! # Features
 //! 
 //! - Easy integration with the `tracing` ecosystem for Rust applications.
@@ -79,17 +89,223 @@
 #![allow(dead_code)]
 
 
-use tracing::{Event, Subscriber, error};
+mod analytics;
+mod api_version;
+mod attachment;
+mod audit;
+mod backpressure;
+mod builder;
+mod client;
+mod clock;
+mod coalesce;
+mod connection;
+pub mod context;
+mod crash;
+mod debug_cap;
+mod diagnostics;
+mod domain_event;
+mod drop_stats;
+mod endpoint_race;
+mod endpoint_template;
+mod env_capture;
+mod execution;
+mod experiment;
+mod field_units;
+mod file_sink;
+mod filter;
+mod handle;
+#[cfg(feature = "client-telemetry")]
+mod hardware;
+mod health;
+mod hosting;
+mod http_capture;
+mod http_dump;
+mod id;
+#[cfg(target_os = "linux")]
+mod journald;
+#[cfg(feature = "kafka")]
+mod kafka_sink;
+mod layer_scope;
+mod locale;
+mod log_id;
+mod lossy;
+mod metadata;
+mod metrics_intake;
+#[cfg(feature = "metrics")]
+mod metrics_recorder;
+mod mqtt_sink;
+mod nats_sink;
+mod otlp;
+mod pause;
+mod payload;
+mod payload_size;
+mod pipeline;
+mod platform;
+mod pool;
+mod process_info;
+mod profile;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+mod query;
+mod rate_limit;
+mod reload;
+mod remote_config;
+mod retry;
+mod ring;
+mod routing;
+#[cfg(feature = "s3")]
+mod s3_sink;
+mod sampling;
+mod sanitize;
+mod saturation;
+#[cfg(feature = "schema")]
+mod schema;
+mod setup;
+mod shutdown;
+mod sink;
+#[cfg(feature = "sqlite")]
+mod sqlite_buffer;
+mod span;
+mod span_budget;
+mod span_latency;
+mod time_fields;
+mod trace;
+mod typed_event;
+mod validation;
+
+pub use pogr_tracing_rs_macros::{main, test, PogrEvent};
+pub use analytics::{Aggregation, AnalyticsQuery, EventCount, Interval, MetricPoint, SeverityCount};
+pub use api_version::IntakeApiVersion;
+pub use attachment::{
+    attach, Attachment, AttachmentConfig, AttachmentEncoding, DEFAULT_MAX_ATTACHMENTS_SIZE, DEFAULT_MAX_ATTACHMENT_SIZE,
+};
+pub use audit::{AuditPolicy, DEFAULT_AUDIT_FIELDS};
+pub use backpressure::DEFAULT_BACKPRESSURE_THRESHOLD;
+pub use builder::PogrAppenderBuilder;
+pub use client::PogrClient;
+pub use clock::{Clock, ManualClock, MonotonicClock, SystemClock};
+pub use coalesce::Coalescing;
+pub use connection::{AddressFamily, ConnectionWarmup, HttpConfig, HttpVersion, DEFAULT_KEEP_WARM_INTERVAL};
+pub use context::{tag_scope, TaskContext};
+pub use crash::CrashReporter;
+pub use debug_cap::DEFAULT_MAX_DEBUG_LENGTH;
+pub use diagnostics::{
+    clear_diagnostic_handler, set_diagnostic_handler, Diagnostic, DiagnosticKind, DiagnosticLevel,
+};
+pub use domain_event::EVENT_TARGET;
+pub use endpoint_race::{EndpointRace, DEFAULT_RACE_INTERVAL};
+pub use drop_stats::DEFAULT_DROP_REPORT_INTERVAL;
+pub use experiment::Experiments;
+pub use field_units::{FieldUnits, UNIT_FIELD_PREFIX};
+pub use file_sink::{RotatingFileSink, DEFAULT_MAX_FILES, DEFAULT_MAX_FILE_SIZE};
+pub use filter::{ParseTargetLevelsError, TargetLevels};
+pub use handle::PogrHandle;
+#[cfg(feature = "client-telemetry")]
+pub use hardware::{GpuInfo, HardwareInfo};
+pub use health::{CheckOutcome, EndpointHealth, HealthStatus};
+pub use hosting::Hosting;
+pub use http_capture::{record_http_traffic, stop_http_recording, HttpCapture, ReplayOutcome, ReplayedRequest};
+pub use http_dump::set_http_debug;
+pub use id::{IdGenerator, IdKind, SequentialIdGenerator, UuidV4Generator, UuidV7Generator};
+#[cfg(target_os = "linux")]
+pub use journald::JournaldSink;
+#[cfg(feature = "kafka")]
+pub use kafka_sink::KafkaSink;
+pub use layer_scope::LayerScope;
+pub use locale::LocaleInfo;
+pub use log_id::{span_log_ids, LogReference};
+pub use lossy::SERIALIZATION_ERRORS_FIELD;
+pub use metadata::{clear_client_metadata, set_client_metadata, InvalidClientMetadata};
+#[cfg(feature = "metrics")]
+pub use metrics_recorder::{PogrMetricsHandle, PogrRecorder, DEFAULT_METRICS_INTERVAL};
+pub use mqtt_sink::{MqttQos, MqttSink};
+pub use nats_sink::NatsSink;
+pub use otlp::{OtlpConfig, DEFAULT_OTLP_ENDPOINT};
+pub use pause::{PausePolicy, MAX_PAUSE};
+pub use payload::{PayloadEncoding, SCHEMA_VERSION};
+pub use payload_size::{
+    PayloadSizeStats, PayloadSizeTracking, PayloadStats, DEFAULT_OVERSIZE_THRESHOLD, DEFAULT_OVERSIZE_WARN_AFTER,
+};
+pub use pipeline::{BatchConfig, FlushTimedOut, ShippingSchedule, DEFAULT_MAX_REQUEST_SIZE};
+pub use platform::DistributionPlatform;
+pub use process_info::{ArgRedaction, DEFAULT_REDACTED_ARGS};
+pub use profile::{ParseProfileError, VerbosityProfile};
+pub use query::{LogPage, LogQuery, PogrQueryClient, QueryError, StoredLog, DEFAULT_PAGE_SIZE};
+pub use rate_limit::RateLimits;
+pub use reload::{ReloadHandle, FORWARDING_TOGGLE};
+pub use remote_config::{RemoteConfig, DEFAULT_CONFIG_INTERVAL};
+pub use retry::{
+    ErrorClass, ExponentialBackoff, FixedInterval, RetryPolicy, StatusAction, StatusTable, DEFAULT_INITIAL_BACKOFF,
+    DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_BACKOFF,
+};
+pub use ring::{OverflowPolicy, DEFAULT_MEMORY_BUDGET, DEFAULT_QUEUE_CAPACITY};
+pub use routing::LevelRoute;
+#[cfg(feature = "s3")]
+pub use s3_sink::{S3ArchiveSink, S3Partitioning};
+pub use sampling::KeySampling;
+pub use saturation::SaturationAlerts;
+#[cfg(feature = "schema")]
+pub use schema::{InvalidSchema, SchemaValidation};
+pub use setup::{init, install_panic_hook, PogrGuard};
+pub use shutdown::{shutdown_signal, DEFAULT_SHUTDOWN_TIMEOUT};
+pub use sink::{Sink, SinkMode, SinkRecord};
+pub use span::SpanEvents;
+pub use span_budget::SpanBudgets;
+pub use span_latency::{SpanLatency, SpanLatencyHandle, DEFAULT_LATENCY_INTERVAL};
+pub use time_fields::{time_field, TimeField};
+pub use trace::{TraceExport, DEFAULT_MAX_TRACE_SPANS};
+pub use typed_event::{log_struct, PogrEvent, REDACTED, STRUCT_TARGET};
+#[cfg(feature = "sqlite")]
+pub use sqlite_buffer::{SqliteBuffer, DEFAULT_MAX_BUFFER_SIZE, REPLAYED_TAG, SPOOL_DELAY_TAG};
+pub use validation::{ValidationError, ValidationReport, MAX_CLOCK_SKEW};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::domain_event::{encode_event_fields, event_field};
+    pub use crate::setup::{run_main, run_test};
+    pub use crate::typed_event::encode_fields;
+    pub use tracing;
+}
+
+use execution::ExecutionContext;
+use process_info::ProcessInfo;
+use span::SpanFields;
+use span_latency::LatencyState;
+use trace::{TraceDocument, TraceMembership, TracedSpan};
+use tracing::{span::{Attributes, Id, Record}, Event, Subscriber};
 use tracing_subscriber::{layer::Context, Layer, registry::LookupSpan};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::sync::{OnceLock, RwLock};
+use tokio::runtime::Handle;
 use tokio::sync::Mutex;
+use attachment::AttachmentManifest;
+use builder::SessionMetadata;
+use coalesce::{Coalescer, Observed, Repeats};
+use drop_stats::{DropReason, DropStats};
+use connection::ConnectionRefresh;
+use endpoint_race::EndpointRacer;
+use layer_scope::LayerId;
+use log_id::{LogIdSlot, SpanLogIds};
+use pause::KillSwitch;
+use payload::{EncodingNegotiation, LogEnvelope};
+use pipeline::{Delivery, InFlight, LogRecord, Queued, Validate};
+use pool::Pool;
+use reload::{LiveSettings, SharedSettings};
+use ring::{QueueSender, Rejected};
+use routing::Route;
+use saturation::SaturationMonitor;
+use sink::Sinks;
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, RequestBuilder};
 use std::{env, fmt};
 use tracing::field::{Field, Visit};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use tracing::Metadata;
-use serde_json::{json, to_value, Value};
+use tracing::{Level, Metadata};
+use serde_json::{json, to_value, Map, Value};
+use uuid::Uuid;
 
 /// A `JsonVisitor` is responsible for visiting fields of a log event and collecting
 /// their values into a structured format. This structure is particularly useful
@@ -140,6 +356,10 @@ pub struct JsonVisitor {
     /// represented as a `serde_json::Value`, which can encompass various JSON
     /// data types (e.g., strings, numbers, arrays, objects).
     pub fields: HashMap<String, Value>,
+    /// Number of bytes of `Debug` output kept per field, if capped.
+    max_debug_length: Option<usize>,
+    /// Whether escape sequences and control characters are stripped from strings.
+    sanitize: bool,
 }
 
 impl JsonVisitor {
@@ -166,8 +386,59 @@ impl JsonVisitor {
     pub fn new() -> Self {
         JsonVisitor {
             fields: HashMap::new(),
+            max_debug_length: Some(DEFAULT_MAX_DEBUG_LENGTH),
+            sanitize: true,
         }
     }
+
+    /// Sets how many bytes of `Debug` output are kept per field, or `None` to keep all of it.
+    ///
+    /// Defaults to `DEFAULT_MAX_DEBUG_LENGTH`. Longer output is cut, and followed by its
+    /// original length and a hash of the full text, so that repeated giants can still be
+    /// told apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pogr_tracing_rs::JsonVisitor;
+    ///
+    /// let visitor = JsonVisitor::new().with_max_debug_length(Some(1024));
+    /// ```
+    pub fn with_max_debug_length(mut self, max_debug_length: Option<usize>) -> Self {
+        self.max_debug_length = max_debug_length;
+        self
+    }
+
+    /// Enables or disables stripping ANSI escape sequences and control characters other than
+    /// tabs and line breaks from string, error and `Debug` fields. Enabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pogr_tracing_rs::JsonVisitor;
+    ///
+    /// // Keep escape codes, e.g. to render them in a terminal later.
+    /// let visitor = JsonVisitor::new().with_sanitization(false);
+    /// ```
+    pub fn with_sanitization(mut self, enabled: bool) -> Self {
+        self.sanitize = enabled;
+        self
+    }
+
+    /// Records a string value, sanitized if enabled.
+    fn insert_string(&mut self, field: &Field, value: String) {
+        let stripped = match self.sanitize.then(|| sanitize::strip_control(&value)) {
+            Some(Cow::Owned(stripped)) => Some(stripped),
+            _ => None,
+        };
+        self.fields.insert(field.name().to_string(), Value::String(stripped.unwrap_or(value)));
+    }
+}
+
+impl Default for JsonVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Implementation of the `Visit` trait for `JsonVisitor`.
@@ -201,8 +472,14 @@ impl Visit for JsonVisitor {
     /// Records a field with a `f64` value.
     ///
     /// Similar to `record_i64`, but for 64-bit floating-point numbers.
+    /// Non-finite numbers, which JSON cannot represent, are recorded as strings such as
+    /// `"NaN"`, and noted in the `serialization_errors` field.
     fn record_f64(&mut self, field: &Field, value: f64) {
-        self.fields.insert(field.name().to_string(), json!(value));
+        let (value, error) = lossy::float(value);
+        self.fields.insert(field.name().to_string(), value);
+        if let Some(error) = error {
+            lossy::note(&mut self.fields, field.name(), error);
+        }
     }
 
     /// Records a field with a `bool` value.
@@ -216,7 +493,7 @@ impl Visit for JsonVisitor {
     ///
     /// Similar to `record_i64`, but for string slices.
     fn record_str(&mut self, field: &Field, value: &str) {
-        self.fields.insert(field.name().to_string(), json!(value));
+        self.insert_string(field, value.to_string());
     }
 
     /// Records a field that contains an error.
@@ -227,9 +504,14 @@ impl Visit for JsonVisitor {
     /// * `value` - The error to record, which implements `std::error::Error`.
     ///
     /// This method converts the error into a string representation before storing it,
-    /// ensuring that error information is preserved in the log data.
+    /// ensuring that error information is preserved in the log data. If its `Display`
+    /// implementation fails, the output until then is kept, see `record_debug`.
     fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
-        self.fields.insert(field.name().to_string(), json!(value.to_string()));
+        let (formatted, error) = lossy::display(value);
+        self.insert_string(field, formatted);
+        if let Some(error) = error {
+            lossy::note(&mut self.fields, field.name(), error);
+        }
     }
 
     /// Records a field with a value that implements `fmt::Debug`.
@@ -240,9 +522,35 @@ impl Visit for JsonVisitor {
     /// * `value` - The value to record, which implements `fmt::Debug`.
     ///
     /// Uses the debug formatting of the value for its representation in the log data,
-    /// allowing for complex types to be logged in an easily readable format.
-    fn record_debug(&mut self, field: &Field, value: &(dyn fmt::Debug)) {
-        self.fields.insert(field.name().to_string(), json!(format!("{:?}", value)));
+    /// allowing for complex types to be logged in an easily readable format. Output longer
+    /// than the maximum debug length is cut, see `with_max_debug_length`.
+    ///
+    /// If the implementation returns an error, the output until then is kept; if it panics,
+    /// `<unformattable>` is recorded instead. Either is noted in the `serialization_errors`
+    /// field, and the event is recorded all the same.
+    ///
+    /// With the `chrono` or `time` feature, the output of timestamp and duration types is
+    /// recorded as an RFC 3339 string or a number of milliseconds instead, except for the
+    /// message.
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let ((formatted, error), time_field) = time_fields::capture(|| lossy::debug(value, self.max_debug_length));
+        let time_value = time_field.filter(|_| error.is_none() && field.name() != "message");
+        match time_value.and_then(|time_field| time_field.value_for(&formatted)) {
+            Some(Ok(value)) => {
+                self.fields.insert(field.name().to_string(), value);
+                return;
+            }
+            Some(Err(error)) => {
+                self.insert_string(field, formatted);
+                lossy::note(&mut self.fields, field.name(), error);
+                return;
+            }
+            None => {}
+        }
+        self.insert_string(field, formatted);
+        if let Some(error) = error {
+            lossy::note(&mut self.fields, field.name(), error);
+        }
     }
 }
 
@@ -251,6 +559,7 @@ impl Visit for JsonVisitor {
 /// This struct encapsulates the necessary details and client for sending log messages
 /// to a specific logging service. It includes configuration like service name, environment,
 /// and session management for authenticated requests.
+#[derive(Clone)]
 pub struct PogrAppender {
     /// HTTP client used to make requests to the POGR service.
     pub client: Client,
@@ -262,18 +571,49 @@ pub struct PogrAppender {
     pub service_type: String,
     /// Session ID for authenticating with the POGR service.
     pub session_id: String,
-    /// Endpoint URL to which logs are sent.
+    /// Endpoint URL to which logs are sent. It may contain placeholders such as `{service}`,
+    /// resolved for each request.
     pub logs_endpoint: String,
-    /// Endpoint URL for session initialization with the POGR service.
+    /// Endpoint URL for session initialization with the POGR service. It may contain
+    /// placeholders such as `{service}`, except `{session}`.
     pub init_endpoint: String,
 }
 
-/// Represents an empty request structure for initializing a session with the POGR service.
+/// Represents the request body for initializing a session with the POGR service.
 ///
-/// This struct is serialized and sent as part of the session initialization process.
-/// Currently, it does not carry any data, but it is designed to be extensible for future needs.
+/// This struct is serialized and sent as part of the session initialization process, so that
+/// the session is attributed to its service, build and platform from the start.
 #[derive(Serialize)]
-struct InitRequest {}
+struct InitRequest<'a> {
+    /// Name of the service opening the session.
+    service: &'a str,
+    /// Deployment environment of the service.
+    environment: &'a str,
+    /// Type of the service.
+    r#type: &'a str,
+    /// Name of the SDK submitting the logs.
+    sdk: &'static str,
+    /// Version of the SDK submitting the logs.
+    sdk_version: &'static str,
+    /// Identifier of the build the session runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_id: Option<&'a str>,
+    /// Platform the session runs on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    platform: Option<&'a str>,
+    /// Where the service runs, e.g. `kubernetes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hosting: Option<&'static str>,
+    /// Arbitrary key/value metadata.
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    metadata: &'a Map<String, Value>,
+    /// The process the session runs in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process: Option<ProcessInfo>,
+    /// The hardware the session runs on.
+    #[cfg(feature = "client-telemetry")]
+    hardware: &'static HardwareInfo,
+}
 
 /// Represents the response from the POGR service upon session initialization.
 ///
@@ -294,6 +634,9 @@ struct InitResponse {
 struct InitPayload {
     /// The session ID assigned by the POGR service for the current session.
     session_id: String,
+    /// The version of the intake API the POGR service selected, if it negotiates one.
+    #[serde(default)]
+    api_version: Option<u32>,
 }
 
 /// Represents a structured log request to be sent to the POGR service.
@@ -346,220 +689,2203 @@ struct LogPayload {
 /// The `PogrLayer` holds an `Arc<Mutex<PogrAppender>>`, allowing it to be safely shared
 /// across asynchronous tasks and threads. This ensures that log data can be sent concurrently
 /// from different parts of an application without data races or other concurrency issues.
+///
+/// `PogrLayer` works with per-layer filters: wrap it with `Layer::with_filter` to keep verbose
+/// console output while only shipping a subset of events to POGR. Any
+/// `tracing_subscriber::layer::Filter` can be used, including `LevelFilter`, `Targets`,
+/// `EnvFilter` and this crate's `TargetLevels`.
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+/// use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, Layer, Registry};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+///
+/// let subscriber = Registry::default()
+///     .with(tracing_subscriber::fmt::layer())
+///     .with(PogrLayer::new(appender).with_filter(LevelFilter::WARN));
+/// # }
+/// ```
 pub struct PogrLayer {
     /// Shared state allowing concurrent access to the `PogrAppender` instance.
     /// This appender is responsible for sending log data to the configured POGR endpoints.
     pub appender: Arc<Mutex<PogrAppender>>,
+    /// Identity of the layer, keeping its span state apart from that of other layers.
+    id: LayerId,
+    /// Tracks log submissions that have been spawned but not yet completed, so that
+    /// `flush` can wait for them before the application exits.
+    in_flight: Arc<InFlight>,
+    /// Target levels, sampling rates and toggles, which can be changed while the layer runs.
+    settings: SharedSettings,
+    /// Which span enter/exit transitions produce records.
+    span_events: SpanEvents,
+    /// Duration budgets of spans, whose overruns produce warnings.
+    span_budgets: SpanBudgets,
+    /// Latency histograms of closed spans, if enabled.
+    span_latency: Option<Arc<LatencyState>>,
+    /// Age after which events inside a span are flagged as slow, if enabled.
+    slow_span_age: Option<Duration>,
+    /// Which span trees are exported as trace documents, if enabled.
+    trace_export: Option<TraceExport>,
+    /// Field whose value decides which events are kept, if enabled.
+    key_sampling: Option<KeySampling>,
+    /// Which numeric fields are recorded with their units, if enabled.
+    field_units: Option<FieldUnits>,
+    /// Counts repeats of identical messages instead of sending them, if enabled.
+    coalescer: Option<Arc<Coalescer>>,
+    /// Token buckets limiting the events forwarded per target, if enabled.
+    rate_limits: Option<RateLimits>,
+    /// Counts of the dropped events, sent as periodic summaries, if enabled.
+    drop_stats: Option<Arc<DropStats>>,
+    /// Warns about records piling up or delivered late, if enabled.
+    saturation: Option<Arc<SaturationMonitor>>,
+    /// Serialized sizes of the records by target and callsite, if tracked.
+    payload_stats: Option<PayloadStats>,
+    /// Age past which queued records are dropped instead of delivered, if any.
+    ttl: Option<Duration>,
+    /// Runtime the layer was created in, which runs its background tasks when an event is
+    /// emitted from a thread outside of any runtime.
+    runtime: Option<Handle>,
+    /// Clock used to timestamp records when they are captured.
+    clock: Arc<dyn Clock>,
+    /// Generates the IDs of records, traces and requests.
+    ids: Arc<dyn IdGenerator>,
+    /// Number of bytes of `Debug` output kept per field, if capped.
+    max_debug_length: Option<usize>,
+    /// Whether escape sequences and control characters are stripped from recorded strings.
+    sanitize: bool,
+    /// Emptied field maps of captured events, reused for the next events.
+    field_maps: Pool<HashMap<String, Value>>,
+    /// How captured records are grouped into intake requests.
+    batching: BatchConfig,
+    /// Size limits and encoding for event attachments.
+    attachments: AttachmentConfig,
+    /// How request bodies are encoded, shared by all workers.
+    payload_encoding: Arc<EncodingNegotiation>,
+    /// Local sinks receiving copies of the submitted records.
+    sinks: Sinks,
+    /// Whether records are submitted to the POGR intake.
+    intake: bool,
+    /// OpenTelemetry collector that records are exported to instead of the intake.
+    otlp: Option<Arc<OtlpConfig>>,
+    /// HTTP settings for the intake client, if it is to be replaced.
+    http: Option<HttpConfig>,
+    /// How long intake connections may be reused before the client is replaced.
+    connection_max_age: Option<Duration>,
+    /// Connections opened to the logs endpoints when the layer is added to a subscriber.
+    warmup: Option<ConnectionWarmup>,
+    /// Picks the fastest of equivalent logs endpoints, if they are raced.
+    endpoint_race: Option<Arc<EndpointRacer>>,
+    /// Decides whether failed submissions are retried, if they are.
+    retry: Option<Arc<dyn RetryPolicy>>,
+    /// How responses with particular statuses are handled, if overridden.
+    statuses: Option<Arc<StatusTable>>,
+    /// Whether the log IDs assigned by the intake are kept in the spans' extensions.
+    track_log_ids: bool,
+    /// Tags describing where the service runs, added to every event unless the event or its
+    /// spans have a field of the same name.
+    hosting_tags: Vec<(String, Value)>,
+    /// Tags added to every event, unless the event or its spans have a field of the same name.
+    default_tags: HashMap<String, Value>,
+    /// Experiments and feature flags added to every event as tags, which can be changed while
+    /// the layer runs.
+    experiments: Experiments,
+    /// Which events are audit events and the fields they must carry, if audit mode is on.
+    audit: Option<AuditPolicy>,
+    /// Pipelines of their own for records at or above a severity, most severe first.
+    routes: Vec<Route>,
+    /// Event intake that domain events are sent to.
+    events_endpoint: String,
+    /// Whether the intake has paused delivery, shared by all workers.
+    kill_switch: Arc<KillSwitch>,
+    /// Checks records before they are submitted, if they are validated.
+    validate: Option<Validate>,
+    /// Refreshes the intake client, shared by all workers.
+    connection_refresh: OnceLock<Option<Arc<ConnectionRefresh>>>,
+    /// Queue of the background worker, started with the first captured record.
+    queue: OnceLock<QueueSender>,
+    /// Queue of the worker delivering domain events, started with the first one.
+    events_queue: OnceLock<QueueSender>,
 }
 
-/// Serializes metadata from a `tracing` event into a JSON value.
-///
-/// This function takes metadata from a log event, such as the log level, target,
-/// file, and line number, and converts it into a structured JSON representation.
-/// This serialized form is suitable for inclusion in log messages sent to external
-/// services, providing rich contextual information about each log event.
+/// Creates a layer forwarding captured events to an appender that is already shared.
 ///
-/// # Arguments
+/// This replaces constructing the layer with a struct literal, `PogrLayer { appender }`, which
+/// no longer compiles since the layer has private settings. Prefer `PogrLayer::new` unless the
+/// appender is shared with other code.
 ///
-/// * `metadata` - Metadata from a `tracing` event.
+/// # Examples
 ///
-/// # Returns
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+/// use std::sync::Arc;
+/// use tokio::sync::Mutex;
 ///
-/// A `serde_json::Value` representing the serialized metadata.
-fn serialize_metadata(metadata: &Metadata) -> Value {
-    let mut map = HashMap::new();
+/// # async fn run() {
+/// let appender = Arc::new(Mutex::new(PogrAppender::new(None, None).await));
+/// let layer = PogrLayer::from(Arc::clone(&appender));
+/// # }
+/// ```
+impl From<Arc<Mutex<PogrAppender>>> for PogrLayer {
+    fn from(appender: Arc<Mutex<PogrAppender>>) -> Self {
+        PogrLayer {
+            appender,
+            id: LayerId::next(),
+            in_flight: Arc::new(InFlight::default()),
+            settings: Arc::new(RwLock::new(LiveSettings {
+                target_levels: TargetLevels::from_env().unwrap_or_default(),
+                ..LiveSettings::default()
+            })),
+            span_events: SpanEvents::default(),
+            span_budgets: SpanBudgets::default(),
+            span_latency: None,
+            slow_span_age: None,
+            trace_export: None,
+            key_sampling: None,
+            field_units: None,
+            coalescer: None,
+            rate_limits: None,
+            drop_stats: None,
+            saturation: None,
+            payload_stats: None,
+            ttl: None,
+            runtime: Handle::try_current().ok(),
+            clock: clock::default_clock(),
+            ids: id::default_id_generator(),
+            max_debug_length: Some(DEFAULT_MAX_DEBUG_LENGTH),
+            sanitize: true,
+            field_maps: Pool::new(POOLED_FIELD_MAPS, MAX_POOLED_FIELDS),
+            batching: BatchConfig::default(),
+            attachments: AttachmentConfig::default(),
+            payload_encoding: Arc::new(EncodingNegotiation::default()),
+            sinks: Sinks::default(),
+            intake: true,
+            otlp: None,
+            http: None,
+            connection_max_age: None,
+            warmup: None,
+            endpoint_race: None,
+            retry: None,
+            statuses: None,
+            track_log_ids: false,
+            hosting_tags: hosting::detect_if_enabled().map(Hosting::tags).unwrap_or_default(),
+            default_tags: HashMap::new(),
+            experiments: Experiments::default(),
+            audit: None,
+            routes: Vec::new(),
+            events_endpoint: domain_event::endpoint_from_env(),
+            kill_switch: Arc::new(KillSwitch::new(PausePolicy::default())),
+            validate: None,
+            connection_refresh: OnceLock::new(),
+            queue: OnceLock::new(),
+            events_queue: OnceLock::new(),
+        }
+    }
+}
 
-    map.insert("name", Value::from(metadata.name()));
-    map.insert("target", Value::from(metadata.target()));
-    map.insert("level", Value::from(format!("{:?}", metadata.level())));
-    map.insert("file", metadata.file().map(Value::from).unwrap_or(Value::Null));
-    map.insert("line", metadata.line().map(|line| Value::from(line as i64)).unwrap_or(Value::Null));
+impl PogrLayer {
+    /// Creates a new `PogrLayer` that forwards captured events to the given appender.
+    ///
+    /// # Arguments
+    ///
+    /// * `appender` - An initialized `PogrAppender` used to submit log data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+    ///
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender);
+    /// # }
+    /// ```
+    pub fn new(appender: PogrAppender) -> Self {
+        Self::from(Arc::new(Mutex::new(appender)))
+    }
 
-    // Convert the HashMap<&str, Value> to Value directly using to_value
-    to_value(map).unwrap_or_else(|_| Value::Null)
-}
+    /// Configures how captured records are grouped into intake requests.
+    ///
+    /// By default each record is submitted on its own. Whatever the batch size, request bodies
+    /// never exceed `BatchConfig::max_request_size`: batches are split across several requests,
+    /// and a single oversized record is truncated and annotated rather than rejected.
+    pub fn with_batching(mut self, batching: BatchConfig) -> Self {
+        self.batching = batching;
+        self
+    }
 
-impl PogrAppender {
-    /// Constructs a new `PogrAppender` with optional custom endpoints.
+    /// Gives the records at or above a severity their own pipeline, with its own batching
+    /// settings and, optionally, its own logs endpoint.
     ///
-    /// Initializes a session with the POGR service using provided or default endpoints.
-    /// Requires `POGR_ACCESS` and `POGR_SECRET` environment variables for authentication.
+    /// Routes can be added for several levels; a record takes the route of the most severe
+    /// level it reaches, and records matching no route use the layer's batching settings and
+    /// the appender's logs endpoint.
+    pub fn with_level_route(mut self, route: LevelRoute) -> Self {
+        self.routes.retain(|existing| existing.config.level() != route.level());
+        self.routes.push(Route::new(route));
+        self.routes.sort_by_key(|route| route.config.level());
+        self
+    }
+
+    /// Sets the event intake that domain events emitted with `pogr_event!` are sent to, instead
+    /// of `POGR_EVENTS_ENDPOINT` or POGR's.
     ///
-    /// # Arguments
+    /// Domain events have a worker of their own, with the layer's batching settings and payload
+    /// encoding, whatever their level routes.
+    pub fn with_events_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.events_endpoint = endpoint.into();
+        self
+    }
+
+    /// Sets the size limits and encoding for attachments added with `attach`.
     ///
-    /// * `init_endpoint` - Optional custom URL for the session initialization endpoint.
-    /// * `logs_endpoint` - Optional custom URL for the log submission endpoint.
+    /// Attachments over the limits are dropped when the event is captured, and their names are
+    /// listed in the event's `pogr.dropped_attachments` tag.
+    pub fn with_attachments(mut self, attachments: AttachmentConfig) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Sets how request bodies are encoded for the logs endpoint. JSON by default.
     ///
-    /// # Returns
+    /// `PayloadEncoding::Ndjson` sends one payload per line, for collectors that process
+    /// request bodies as a stream. Level routes can use an encoding of their own, with
+    /// `LevelRoute::with_payload_encoding`.
     ///
-    /// A new instance of `PogrAppender` configured and initialized for logging.
+    /// With the `protobuf` feature, `PayloadEncoding::Protobuf` roughly halves the size of the
+    /// requests; with the `msgpack` feature, `PayloadEncoding::MessagePack` keeps the structure
+    /// of the JSON payloads, but is faster to encode and smaller; and with the `cbor` feature,
+    /// `PayloadEncoding::Cbor` does the same in CBOR. If the intake answers `415 Unsupported
+    /// Media Type`, the layer falls back to JSON for the rest of its lifetime, sends the refused
+    /// request again, and reports it as a `Delivery` diagnostic. Records with attachments sent
+    /// as `multipart/form-data` always carry a JSON payload.
     ///
-    /// # Panics
+    /// # Examples
     ///
-    /// Panics if session initialization fails or required environment variables are missing.
-    pub async fn new(init_endpoint: Option<String>, logs_endpoint: Option<String>) -> Self {
-        let client = Client::new();
+    /// ```rust,no_run
+    /// # #[cfg(feature = "protobuf")]
+    /// # async fn run() {
+    /// use pogr_tracing_rs::{PayloadEncoding, PogrAppender, PogrLayer};
+    ///
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender).with_payload_encoding(PayloadEncoding::Protobuf);
+    /// # }
+    /// ```
+    pub fn with_payload_encoding(mut self, encoding: PayloadEncoding) -> Self {
+        self.payload_encoding = Arc::new(EncodingNegotiation::new(encoding));
+        self
+    }
 
-        let service_name = env::var("SERVICE_NAME").unwrap_or_else(|_| env::current_exe().unwrap().file_name().unwrap().to_str().unwrap().to_owned());
-        let environment = env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_owned());
-        let service_type = env::var("SERVICE_TYPE").unwrap_or_else(|_| "service".to_owned());
+    /// Adds a local sink, such as a `RotatingFileSink`, that receives copies of the records.
+    ///
+    /// With `SinkMode::Fallback` the sink only receives records that could not be delivered
+    /// to POGR; with `SinkMode::Mirror` it receives every record. Several sinks can be added.
+    pub fn with_sink(mut self, sink: impl Sink + 'static, mode: SinkMode) -> Self {
+        self.sinks.push(Arc::new(sink), mode);
+        self
+    }
 
-        let init_endpoint_url = init_endpoint
-            .or_else(|| env::var("POGR_INIT_ENDPOINT").ok())
-            .unwrap_or_else(|| "https://api.pogr.io/v1/intake/init".to_string());
-        let logs_endpoint_url = logs_endpoint
-            .or_else(|| env::var("POGR_LOGS_ENDPOINT").ok())
-            .unwrap_or_else(|| "https://api.pogr.io/v1/intake/logs".to_string());
+    /// Sets what happens to records captured while the intake has paused delivery.
+    ///
+    /// The intake can ask clients to stop sending for a while during incidents, with a
+    /// `X-POGR-Pause` header, or a `429` or `503` response with `Retry-After`. Delivery resumes
+    /// automatically once the pause ends. By default, records are spooled in memory until then
+    /// (`PausePolicy::Spool`); with `PausePolicy::Drop`, they go to the fallback sinks instead.
+    pub fn with_pause_policy(mut self, policy: PausePolicy) -> Self {
+        self.kill_switch = Arc::new(KillSwitch::new(policy));
+        self
+    }
 
-        let pogr_client = env::var("POGR_ACCESS").expect("POGR_ACCESS must be set");
-        let pogr_build = env::var("POGR_SECRET").expect("POGR_SECRET must be set");
+    /// Checks the tags and data of every record against JSON Schemas before it is submitted.
+    ///
+    /// Records that do not satisfy the schemas are handed to the fallback sinks, with
+    /// `SinkRecord::validation_error` saying why, and reported as `Schema` diagnostics. They
+    /// are neither submitted nor mirrored.
+    #[cfg(feature = "schema")]
+    pub fn with_schema_validation(mut self, schema: SchemaValidation) -> Self {
+        self.validate = Some(Arc::new(move |request: &LogRequest| schema.validate(request)));
+        self
+    }
 
-        let init_response: InitResponse = client.post(&init_endpoint_url)
-            .header("POGR_ACCESS", pogr_client)
-            .header("POGR_SECRET", pogr_build)
-            .header("Content-Type", "application/json")
-            .send()
-            .await
-            .expect("Failed to send init request")
-            .json()
-            .await
-            .expect("Failed to deserialize init response");
+    /// Enables or disables submitting records to the POGR intake over HTTP, or to the
+    /// OpenTelemetry collector set with `with_otlp`.
+    ///
+    /// Intake delivery is enabled by default. With it disabled, records are only written to
+    /// the sinks added with `SinkMode::Mirror`, e.g. to route telemetry through Kafka instead.
+    pub fn with_intake_delivery(mut self, enabled: bool) -> Self {
+        self.intake = enabled;
+        self
+    }
 
-        if init_response.success {
-            PogrAppender {
-                client,
-                service_name,
-                environment,
-                service_type,
-                session_id: init_response.payload.session_id,
-                logs_endpoint: logs_endpoint_url,
-                init_endpoint: init_endpoint_url,
-            }
-        } else {
-            panic!("Failed to initialize POGR session");
-        }
+    /// Exports records to an OpenTelemetry collector instead of submitting them to the intake.
+    ///
+    /// The records are batched, size-checked and handed to the sinks as usual, but each request
+    /// is sent to the collector as an OTLP/HTTP logs export, encoded as JSON. The session is
+    /// still initialized with POGR, and its ID is sent as the `pogr.session_id` resource
+    /// attribute. The collector does not return log IDs, so `with_log_id_tracking` has no effect.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{BatchConfig, OtlpConfig, PogrAppender, PogrLayer};
+    ///
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender)
+    ///     .with_otlp(OtlpConfig::from_env())
+    ///     .with_batching(BatchConfig::new().with_max_batch_size(512));
+    /// # }
+    /// ```
+    pub fn with_otlp(mut self, otlp: OtlpConfig) -> Self {
+        self.otlp = Some(Arc::new(otlp));
+        self
     }
 
-    /// Asynchronously sends a log message to the POGR service.
+    /// Sets the HTTP version, tuning and socket options of the client that submits records to
+    /// the intake.
     ///
-    /// Constructs and sends a log request to the configured POGR endpoint. This method
-    /// handles serialization of the log message and metadata, and sends the data using
-    /// the internal HTTP client. It ensures that each log message is associated with
-    /// the current session via the `INTAKE_SESSION_ID` header.
+    /// The worker replaces the appender's client with one built from these settings before
+    /// its first submission. HTTP/2 multiplexes concurrent submissions, enabled with
+    /// `BatchConfig::with_max_concurrent_requests`, over a single connection.
+    pub fn with_http(mut self, http: HttpConfig) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Limits how long connections to the intake are reused.
     ///
-    /// # Arguments
+    /// Connections are kept open and reused by default, which pins them to the addresses the
+    /// endpoints resolved to when they were opened. With a maximum age, the client is replaced
+    /// before the first submission after that age is reached: its connections are closed and
+    /// the endpoints are resolved again, so traffic follows address changes of the intake
+    /// without a restart. The replacement is built from the `HttpConfig` set with `with_http`,
+    /// or with the default settings, also if the appender was built with a custom client.
     ///
-    /// * `log_request` - The log message and associated data to send.
+    /// # Examples
     ///
-    /// # Panics
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+    /// use std::time::Duration;
     ///
-    /// Panics if the log request fails to send or if the response cannot be deserialized.
-    pub async fn log(&self, log_request: LogRequest) {
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender).with_connection_max_age(Duration::from_secs(300));
+    /// # }
+    /// ```
+    pub fn with_connection_max_age(mut self, max_age: Duration) -> Self {
+        self.connection_max_age = Some(max_age);
+        self
+    }
+
+    /// Opens connections to the logs endpoints as soon as the layer is added to a subscriber,
+    /// and keeps them warm, so the first submissions do not pay for DNS lookups and TLS
+    /// handshakes.
+    ///
+    /// Warm-up is off by default. The connections are opened with `HEAD` requests in the
+    /// appender's session, through the client the delivery workers use, to the appender's
+    /// logs endpoint and those of the level routes. It needs a tokio runtime, and is skipped
+    /// without intake delivery or when exporting to an OpenTelemetry collector.
+    pub fn with_connection_warmup(mut self, warmup: ConnectionWarmup) -> Self {
+        self.warmup = Some(warmup);
+        self
+    }
 
-        let log_endpoint = self.logs_endpoint.clone();
+    /// Races the appender's logs endpoint against equivalent regional endpoints, and submits
+    /// records to the fastest.
+    ///
+    /// Racing is off by default. The endpoints are probed with `HEAD` requests before the first
+    /// submission and again after the race's interval; records go to the first to respond.
+    /// Level routes with a logs endpoint of their own are not raced.
+    pub fn with_endpoint_race(mut self, race: EndpointRace) -> Self {
+        self.endpoint_race = Some(Arc::new(EndpointRacer::new(race)));
+        self
+    }
 
+    /// Retries failed submissions to the intake as the policy decides.
+    ///
+    /// Failed submissions are not retried by default; their records go to the fallback sinks
+    /// right away. With a policy, they only do once it gives up. Retries carry the request's
+    /// idempotency key, and a flush waits for them unless it times out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{ExponentialBackoff, PogrAppender, PogrLayer};
+    /// use std::time::Duration;
+    ///
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender)
+    ///     .with_retry_policy(ExponentialBackoff::new().with_max_delay(Duration::from_secs(10)));
+    /// # }
+    /// ```
+    pub fn with_retry_policy(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry = Some(Arc::new(policy));
+        self
+    }
 
-        let response: LogResponse = self.client.post(&log_endpoint)
-            .header("INTAKE_SESSION_ID", &self.session_id)
-            .header("Content-Type", "application/json")
-            .json(&log_request)
-            .send()
-            .await
-            .expect("Failed to send log request")
-            .json()
-            .await
-            .expect("Failed to deserialize log response");
+    /// Overrides how responses with particular HTTP statuses are handled: whether they are
+    /// retried, answered by initializing a new session, or dead-lettered to the fallback sinks.
+    ///
+    /// A new session is initialized with the appender's endpoints and the credentials in the
+    /// environment, and is described like one initialized by `PogrAppender::new`. Subsequent
+    /// submissions of all workers use it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{PogrAppender, PogrLayer, StatusAction, StatusTable};
+    ///
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender).with_status_table(
+    ///     StatusTable::new()
+    ///         .with_status(400, StatusAction::DeadLetter)
+    ///         .with_status(401, StatusAction::Reinitialize)
+    ///         .with_status(503, StatusAction::Retry),
+    /// );
+    /// # }
+    /// ```
+    pub fn with_status_table(mut self, statuses: StatusTable) -> Self {
+        self.statuses = Some(Arc::new(statuses));
+        self
+    }
+
+    /// Enables keeping the log IDs the intake assigns to events in the extensions of the spans
+    /// the events were recorded in.
+    ///
+    /// Tracking is off by default. Look the IDs up with `span_log_ids`. Only events submitted
+    /// in a request of their own are correlated, since the intake returns a single log ID per
+    /// request; with batching enabled, batched events are not tracked.
+    pub fn with_log_id_tracking(mut self, enabled: bool) -> Self {
+        self.track_log_ids = enabled;
+        self
+    }
+
+    /// Enables or disables tagging every event with where the service runs.
+    ///
+    /// Hosting detection is enabled by default. Setting `POGR_DETECT_HOSTING` to `0` turns it
+    /// off regardless of this setting. Events are tagged with `hosting` (`kubernetes`, `ecs`, `docker` or `bare-metal`) and,
+    /// in Kubernetes, with `k8s_namespace` and `k8s_pod`.
+    pub fn with_hosting_detection(mut self, enabled: bool) -> Self {
+        self.hosting_tags = match enabled {
+            true => hosting::detect_if_enabled().map(Hosting::tags).unwrap_or_default(),
+            false => Vec::new(),
+        };
+        self
+    }
 
-        if !response.success {
-            error!("Failed to log to POGR: {:?}", response);
+    /// Stamps every event with the distribution platform the game was launched from.
+    ///
+    /// Detection is off by default. If `DistributionPlatform::detect` recognizes the platform,
+    /// events are tagged with `platform` (e.g. `steam`) and, if known, `platform_id` (e.g. the
+    /// Steam app ID). Use `PogrAppenderBuilder::with_platform_detection` to also attribute
+    /// the session to the platform.
+    pub fn with_platform_detection(mut self) -> Self {
+        if let Some(platform) = DistributionPlatform::detect() {
+            self.default_tags.extend(platform.tags());
         }
+        self
     }
-}
 
-/// Implements the `Layer` trait from the `tracing` crate for `PogrLayer`.
-///
-/// This implementation allows `PogrLayer` to interact with the `tracing` ecosystem,
-/// capturing log events, processing them, and then forwarding them to an external
-/// logging service defined by `PogrAppender`. It leverages asynchronous execution
-/// to ensure that the logging process does not block the main application flow.
-///
-/// The `PogrLayer` captures metadata and structured fields from log events,
-/// serializes them into a JSON format, and then sends them to the configured
-/// POGR endpoint via the `PogrAppender`. This process is done asynchronously
-/// to optimize performance and reduce the impact on application throughput.
-///
-/// # Type Parameters
-///
-/// * `S` - The subscriber type. This layer can be added to any subscriber that
-///         implements `Subscriber` and `for<'a> LookupSpan<'a>`, allowing it to
-///         interact with the span data.
-///
-/// # Examples
-///
-/// To use `PogrLayer` with a subscriber:
-///
-/// ```rust,no_run
-/// use tracing_subscriber::{Registry, layer::SubscriberExt};
-/// use pogr_tracing_rs::{PogrLayer, PogrAppender};
-/// use std::sync::Arc;
-/// use tokio::sync::Mutex;
-///
-/// #[tokio::main]
-/// async fn main() {
-///     // Initialize your PogrAppender here...
-///     let appender = PogrAppender::new(None, None).await;
-///
-///     let layer = PogrLayer {
-///         appender: Arc::new(Mutex::new(appender)),
-///     };
-///
-///     let subscriber = Registry::default().with(layer);
-///
-///     tracing::subscriber::set_global_default(subscriber)
-///         .expect("Failed to set global subscriber");
-/// }
-/// ```
-///
-/// This example sets up `PogrLayer` with a default `tracing` subscriber, allowing
-/// log events to be processed and forwarded to the POGR platform.
-impl<S> Layer<S> for PogrLayer
-where
-    S: Subscriber + for<'a> LookupSpan<'a>,
-{
-    /// Responds to log events captured by the `tracing` framework.
+    /// Tags every event with the allowlisted environment variables, e.g. `REGION` or
+    /// `DEPLOY_ID`.
     ///
-    /// This method is called automatically by the `tracing` framework for each log event.
-    /// It extracts event metadata and fields, serializes them into a structured log format,
-    /// and forwards them to the POGR platform using the `PogrAppender`. The actual submission
-    /// of log data is performed asynchronously to avoid blocking the execution of the event's
-    /// source code.
+    /// The variables are read once, now, and the ones that are set are added as `env.NAME`
+    /// tags, e.g. `env.REGION`. No other variable is read, so secrets in the environment are
+    /// never captured. Use `PogrAppenderBuilder::with_env_capture` to attach them to the
+    /// session instead, once.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `event` - The log event being processed.
-    /// * `_ctx` - The context provided by the `tracing` framework, allowing for interaction
-    ///            with the rest of the tracing system, such as querying for active spans.
-    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
-        let appender = Arc::clone(&self.appender);
-        let metadata = event.metadata();
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+    ///
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender).with_env_capture(["REGION", "DEPLOY_ID", "FLY_ALLOC_ID"]);
+    /// # }
+    /// ```
+    pub fn with_env_capture<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.default_tags.extend(
+            env_capture::capture(names)
+                .into_iter()
+                .map(|(name, value)| (env_capture::tag_name(&name), Value::from(value))),
+        );
+        self
+    }
 
-        let mut visitor = JsonVisitor::new();
-        event.record(&mut visitor);
+    /// Tags every event with the locale, region and timezone of the machine.
+    ///
+    /// Detection is off by default. `LocaleInfo::detect` runs once, now, and events are
+    /// tagged with what it finds: `locale` (e.g. `en-US`), `region` (e.g. `US`), `timezone`
+    /// (e.g. `America/New_York`) and `utc_offset` (e.g. `-05:00`). Use
+    /// `PogrAppenderBuilder::with_locale_detection` to describe the session as well.
+    pub fn with_locale_detection(mut self) -> Self {
+        self.default_tags.extend(LocaleInfo::detect().tags());
+        self
+    }
 
-        tokio::spawn(async move {
-            let appender = appender.lock().await;
-            
+    /// Sets the clock used to timestamp captured records.
+    ///
+    /// Defaults to a `MonotonicClock`, which is immune to wall-clock jumps while the process
+    /// runs. Tests can inject a `ManualClock` for deterministic timestamps.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
 
-        let log_request = LogRequest {
-            service: appender.service_name.clone(),
-            environment: appender.environment.clone(),
-            severity: metadata.level().to_string(),
-            r#type: appender.service_type.clone(),
-            log: "rust tracing log captured".to_string(),
-            data: serialize_metadata(metadata),
-            tags: serde_json::to_value(visitor.fields).unwrap_or_else(|_| serde_json::json!({})),
+    /// Sets the generator of event IDs, trace IDs and idempotency keys.
+    ///
+    /// Defaults to a `UuidV7Generator`, whose IDs sort by creation time. Tests can inject a
+    /// `SequentialIdGenerator` for deterministic payloads.
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Arc::new(ids);
+        self
+    }
+
+    /// Sets how many bytes of `Debug` output are kept per field of events and spans, or
+    /// `None` to keep all of it.
+    ///
+    /// Defaults to `DEFAULT_MAX_DEBUG_LENGTH` (64 KiB). Fields recorded with `?value` or
+    /// `%value` whose output is longer are cut, and followed by their original length and a
+    /// hash of the full text, e.g. `… [truncated: 4718592 bytes, fnv1a64 9f2c4b1e07a3d5c6]`.
+    pub fn with_max_debug_length(mut self, max_debug_length: Option<usize>) -> Self {
+        self.max_debug_length = max_debug_length;
+        self
+    }
+
+    /// Enables or disables stripping ANSI escape sequences and control characters other than
+    /// tabs and line breaks from messages and string fields of events and spans.
+    ///
+    /// Enabled by default, so that output styled for a terminal arrives in POGR as plain text.
+    pub fn with_sanitization(mut self, enabled: bool) -> Self {
+        self.sanitize = enabled;
+        self
+    }
+
+    /// Sets per-target minimum levels for events forwarded to POGR.
+    ///
+    /// Events below their target's threshold are skipped by this layer only; other layers in
+    /// the subscriber (e.g. console output) still see them. By default the thresholds are read
+    /// from `POGR_LOG_LEVEL`, and without it every level is forwarded; these replace them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+    ///
+    /// # async fn run() {
+    /// let appender = PogrAppender::new(None, None).await;
+    /// let layer = PogrLayer::new(appender)
+    ///     .with_target_levels("info,matchmaking=debug,netcode=warn,sqlx=error".parse().unwrap());
+    /// # }
+    /// ```
+    pub fn with_target_levels(self, target_levels: TargetLevels) -> Self {
+        reload::write(&self.settings).target_levels = target_levels;
+        self
+    }
+
+    /// Enables lightweight records for span enter/exit transitions on the selected targets.
+    ///
+    /// These records are off by default. They use the span's level and are subject to the
+    /// same target thresholds as events. Close records, which include the span's lifetime,
+    /// can be enabled with `SpanEvents::with_close`.
+    pub fn with_span_events(mut self, span_events: SpanEvents) -> Self {
+        self.span_events = span_events;
+        self
+    }
+
+    /// Sends a `WARN` record with the span's fields and actual duration whenever a span closes
+    /// after more than its budget.
+    ///
+    /// Budgets are set per span name, optionally for some targets only. The warnings are
+    /// subject to the target thresholds at `WARN`, whatever the level of the span.
+    pub fn with_span_budgets(mut self, span_budgets: SpanBudgets) -> Self {
+        self.span_budgets = span_budgets;
+        self
+    }
+
+    /// Aggregates the lifetimes of closed spans into latency histograms per span name, and
+    /// periodically reports their p50, p90 and p99 to POGR's metrics intake.
+    ///
+    /// Summaries are off by default. Unlike close records, they do not ship a record per span,
+    /// so they suit hot code paths. Spans are subject to the same target thresholds as events.
+    pub fn with_span_latency(mut self, span_latency: SpanLatency) -> Self {
+        self.span_latency = Some(Arc::new(LatencyState::new(span_latency, Arc::clone(&self.appender))));
+        self
+    }
+
+    /// Returns a handle for reporting the span latency summaries on demand, if they are
+    /// enabled with `with_span_latency`.
+    pub fn span_latency_handle(&self) -> Option<SpanLatencyHandle> {
+        self.span_latency.as_ref().map(|state| SpanLatencyHandle::new(Arc::clone(state)))
+    }
+
+    /// Flags events emitted inside spans older than `age`, so long-running operations are easy
+    /// to filter in POGR.
+    ///
+    /// Such events are tagged with `slow = true`, the name of their oldest enclosing span as
+    /// `slow_span`, and its age when the event was emitted as `slow_duration_ms`. Flagging is
+    /// off by default.
+    pub fn with_slow_span_threshold(mut self, age: Duration) -> Self {
+        self.slow_span_age = Some(age);
+        self
+    }
+
+    /// Exports the span trees of sampled requests as trace documents, for waterfall views.
+    ///
+    /// Trace export is off by default. Each root span accepted by the layer is sampled with
+    /// `TraceExport::with_sample_rate`; the whole tree of a sampled root, with parent/child
+    /// IDs, timings and fields, is sent as one record once its last span is closed. Events
+    /// recorded inside a sampled trace are tagged with its `trace_id`.
+    pub fn with_trace_export(mut self, trace_export: TraceExport) -> Self {
+        self.trace_export = Some(trace_export);
+        self
+    }
+
+    /// Keeps only the events whose value of a key field, such as `player_id`, is sampled.
+    ///
+    /// Sampling is off by default. Unlike sampling a fraction of all events, the same key is
+    /// always kept or always dropped, so the sessions of the sampled players stay complete.
+    /// Span and trace records are not affected.
+    pub fn with_key_sampling(mut self, key_sampling: KeySampling) -> Self {
+        self.key_sampling = Some(key_sampling);
+        self
+    }
+
+    /// Records numeric fields that have a unit, by the suffix of their name or a
+    /// `pogr.unit.<field>` companion field, as `{"value": ..., "unit": ...}` objects.
+    ///
+    /// Units are off by default, so dashboards built on plain numbers keep working. Companion
+    /// fields are removed whether or not their field is numeric.
+    pub fn with_field_units(mut self, field_units: FieldUnits) -> Self {
+        self.field_units = Some(field_units);
+        self
+    }
+
+    /// Keeps a random fraction `rate`, between 0 and 1, of the events at exactly `level`.
+    ///
+    /// Unlike `with_key_sampling`, each event is sampled on its own. Setting a level again
+    /// replaces its rate.
+    pub fn with_level_sampling(self, level: Level, rate: f64) -> Self {
+        reload::write(&self.settings).set_level_sampling(level, rate);
+        self
+    }
+
+    /// Applies the thresholds and sampling of a verbosity profile.
+    ///
+    /// This replaces the target levels, and the sampling rate of `INFO` events. Use
+    /// `VerbosityProfile::detect` to pick the profile from the session's environment, as
+    /// `init` does.
+    ///
+    /// `POGR_LOG_LEVEL` takes precedence over the profile: if it is set, the layer keeps its
+    /// thresholds and events are not sampled.
+    pub fn with_profile(self, profile: VerbosityProfile) -> Self {
+        if let Ok(Some(levels)) = filter::env_levels() {
+            return self.with_target_levels(levels);
+        }
+        self.with_target_levels(profile.target_levels())
+            .with_level_sampling(Level::INFO, profile.info_sample_rate())
+    }
+
+    /// Coalesces identical messages within a window into one event carrying their count.
+    ///
+    /// Coalescing is off by default. The first occurrence of a message is sent as usual; its
+    /// repeats within `window` are counted and sent as one more event once the window closes,
+    /// tagged with `repeat_count`, `first_seen` and `last_seen`. Flushing the layer closes
+    /// open windows right away.
+    pub fn with_coalescing(mut self, coalescing: Coalescing) -> Self {
+        self.coalescer = Some(Arc::new(Coalescer::new(coalescing)));
+        self
+    }
+
+    /// Limits the rate of events forwarded to POGR per target prefix, so that one noisy
+    /// subsystem does not crowd out the others.
+    ///
+    /// Events over their target's limit are dropped, and their number is reported as a
+    /// `Dropped` diagnostic. Sampling and coalescing apply first, so events they drop do not
+    /// count against the limits.
+    pub fn with_rate_limits(mut self, rate_limits: RateLimits) -> Self {
+        self.rate_limits = Some(rate_limits);
+        self
+    }
+
+    /// Sends a summary of the events dropped by sampling, rate limits, audit validation or the
+    /// time-to-live, or because the delivery worker had stopped, every `interval`, so analysts
+    /// know when and why the data is incomplete.
+    ///
+    /// Summaries are off by default; `DEFAULT_DROP_REPORT_INTERVAL` is a sensible interval.
+    /// They count the dropped events by level and reason, and are only sent for intervals
+    /// with drops.
+    pub fn with_drop_reports(mut self, interval: Duration) -> Self {
+        self.drop_stats = Some(Arc::new(DropStats::new(interval)));
+        self
+    }
+
+    /// Warns when records pile up in the queue past high-water marks, or take longer than a
+    /// threshold from capture to delivery, so operators learn about telemetry backpressure
+    /// before data is lost.
+    ///
+    /// Alerts are reported as `Saturation` diagnostics, and sent to POGR as well if enabled
+    /// with `SaturationAlerts::with_pogr_events`.
+    pub fn with_saturation_alerts(mut self, alerts: SaturationAlerts) -> Self {
+        self.saturation = Some(Arc::new(SaturationMonitor::new(alerts)));
+        self
+    }
+
+    /// Measures every record as serialized for the intake, and keeps the sizes by target and
+    /// callsite, to find the events that make up most of the telemetry volume.
+    ///
+    /// Look the sizes up through `payload_stats`. A callsite that keeps producing payloads
+    /// above the oversize threshold is reported once as a `PayloadSize` diagnostic.
+    pub fn with_payload_size_tracking(mut self, tracking: PayloadSizeTracking) -> Self {
+        self.payload_stats = Some(PayloadStats::new(tracking));
+        self
+    }
+
+    /// Returns a handle to the payload sizes measured by this layer, if tracking is enabled
+    /// with `with_payload_size_tracking`.
+    pub fn payload_stats(&self) -> Option<PayloadStats> {
+        self.payload_stats.clone()
+    }
+
+    /// Returns a scope routing the events of a subtree of code to this layer only.
+    ///
+    /// Several layers, each with an appender and session of its own, can share one subscriber
+    /// stack, e.g. one per plugin. Events emitted inside the scope are recorded by this layer
+    /// and ignored by the others; events outside any scope are recorded by all of them. The
+    /// scope stays valid after the layer has been moved into a subscriber.
+    pub fn layer_scope(&self) -> LayerScope {
+        LayerScope::new(self.id)
+    }
+
+    /// Drops records that have waited for delivery longer than `ttl`, e.g. in the queue or
+    /// spooled during a pause, instead of delivering them long after the fact.
+    ///
+    /// The age is measured from when the event was captured. Expired records are reported as
+    /// a `Dropped` diagnostic, and counted in the drop reports if enabled. They are not written
+    /// to the sinks either. Records never expire by default.
+    pub fn with_event_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the number of records waiting for delivery at which `PogrGuard::is_backpressured`
+    /// reports the pipeline as backpressured. Defaults to `DEFAULT_BACKPRESSURE_THRESHOLD`.
+    ///
+    /// The pipeline stays backpressured until the backlog drains to half of the threshold.
+    pub fn with_backpressure_threshold(self, threshold: usize) -> Self {
+        self.in_flight.backpressure.set_threshold(threshold);
+        self
+    }
+
+    /// Tags every event with the arm of an experiment, as `experiment.<id>`.
+    ///
+    /// Use `experiments` to change the assignments after the layer is installed.
+    pub fn with_experiment(self, id: &str, arm: impl Into<String>) -> Self {
+        self.experiments.set_experiment(id, arm);
+        self
+    }
+
+    /// Tags every event with the state of a feature flag, as `feature_flag.<name>`.
+    pub fn with_feature_flag(self, name: &str, enabled: bool) -> Self {
+        self.experiments.set_feature_flag(name, enabled);
+        self
+    }
+
+    /// Returns a handle for registering and removing experiments and feature flags after the
+    /// layer has been moved into a subscriber.
+    ///
+    /// Changes apply to the next event. Fields of the same name on the event or its spans take
+    /// precedence, so assignments can also be scoped to a span.
+    pub fn experiments(&self) -> Experiments {
+        self.experiments.clone()
+    }
+
+    /// Turns on audit mode: audit events missing one of the required fields are reported as
+    /// `Audit` diagnostics instead of being sent.
+    ///
+    /// Audit events are those on the policy's targets and those with a `classification =
+    /// "audit"` field. Their fields are checked after merging those of their spans and
+    /// contexts.
+    pub fn with_audit(mut self, audit: AuditPolicy) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Returns a handle for changing the target levels, sampling rates and toggles of this
+    /// layer after it has been moved into a subscriber.
+    ///
+    /// Changes apply to the next event. Handles, and changes made through them, stay valid for
+    /// as long as the layer exists.
+    pub fn reload_handle(&self) -> ReloadHandle {
+        ReloadHandle::new(Arc::clone(&self.settings))
+    }
+
+    /// Periodically fetches target levels, sampling rates and toggles from a config endpoint,
+    /// and applies them through the layer's reload handle.
+    ///
+    /// Polling is off by default. It starts right away, in the appender's session, so this must
+    /// be called inside a tokio runtime, and stops when the layer is dropped. See `RemoteConfig`
+    /// for the document the endpoint serves.
+    pub fn with_remote_config(self, remote_config: RemoteConfig) -> Self {
+        let settings = Arc::downgrade(&self.settings);
+        if !remote_config::spawn(remote_config, Arc::clone(&self.appender), settings) {
+            diagnostics::error(DiagnosticKind::Config, "Remote configuration polling needs a tokio runtime");
+        }
+        self
+    }
+
+    /// Returns a `PogrHandle` that queues records for this layer from any thread, without
+    /// awaiting, after it has been moved into a subscriber.
+    ///
+    /// The delivery workers of the layer are started right away, on the current runtime, the
+    /// runtime the layer was created in, or a thread of their own.
+    pub fn handle(&self) -> PogrHandle {
+        let queues = [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG, Level::TRACE]
+            .into_iter()
+            .map(|level| (level, self.queue(&level).clone()))
+            .collect();
+        let mut tags: Map<String, Value> = self.hosting_tags.iter().cloned().collect();
+        tags.extend(self.default_tags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        PogrHandle::new(queues, Arc::clone(&self.in_flight), Arc::clone(&self.clock), Arc::clone(&self.ids), tags)
+    }
+
+    /// Returns a `PogrClient` that submits `LogRequest`s through this layer's delivery
+    /// pipeline, bypassing `tracing`, e.g. to import historical data.
+    ///
+    /// The layer does not need to be added to a subscriber; it can be configured only for the
+    /// client's records.
+    pub fn client(&self) -> PogrClient {
+        PogrClient::for_layer(self)
+    }
+
+    /// Returns a visitor recording fields with this layer's debug length limit and
+    /// sanitization.
+    fn visitor(&self) -> JsonVisitor {
+        let mut visitor = JsonVisitor::new()
+            .with_max_debug_length(self.max_debug_length)
+            .with_sanitization(self.sanitize);
+        visitor.fields = self.field_maps.take();
+        visitor
+    }
+
+    /// Returns a `PogrGuard` that can flush this layer after it has been moved into a subscriber.
+    pub fn guard(&self) -> PogrGuard {
+        PogrGuard::new(Arc::clone(&self.in_flight), Arc::clone(&self.kill_switch), Arc::clone(&self.appender))
+    }
+
+    /// Waits until every log submission started by this layer has completed.
+    ///
+    /// Call this before shutting down the tokio runtime, otherwise events that were captured
+    /// shortly before exit may never reach the POGR platform.
+    pub async fn flush(&self) {
+        self.in_flight.wait_idle().await;
+    }
+
+    /// Waits until every log submission started by this layer has completed, or `timeout`
+    /// has passed, so shutting down cannot hang while the intake is unreachable.
+    ///
+    /// The error says how many records were left behind. What happens to them follows the
+    /// pause policy: with `PausePolicy::Spool` they are still delivered in the background,
+    /// with `PausePolicy::Drop` they are handed to the fallback sinks.
+    pub async fn flush_timeout(&self, timeout: Duration) -> Result<(), FlushTimedOut> {
+        self.in_flight.drain(timeout, self.kill_switch.policy()).await
+    }
+
+    /// Builds a log request with the appender's service details and queues it for the background worker.
+    ///
+    /// The record's `event_id` and timestamp are assigned here, at capture time, so that they
+    /// reflect when the event happened rather than when it was sent. The submission is tracked
+    /// so that `flush` waits for it. `size` is the estimated size of the record in bytes,
+    /// counted against the worker's memory budget.
+    fn submit<F>(&self, level: &Level, size: usize, build: F)
+    where
+        F: FnOnce(&PogrAppender) -> LogRequest + Send + 'static,
+    {
+        self.submit_to(self.queue(level), level, size, Vec::new(), None, build);
+    }
+
+    /// Like `submit`, on the given worker's queue, with binary artifacts that are sent along
+    /// with the log request, and a slot receiving the log ID the intake assigns.
+    fn submit_to<F>(
+        &self,
+        queue: &QueueSender,
+        level: &Level,
+        size: usize,
+        attachments: Vec<Attachment>,
+        log_id: Option<LogIdSlot>,
+        build: F,
+    ) where
+        F: FnOnce(&PogrAppender) -> LogRequest + Send + 'static,
+    {
+        // If the worker is gone the record is dropped, and so is its ticket.
+        let sent = queue.send(Queued {
+            event_id: self.ids.generate(IdKind::Event),
+            level: *level,
+            timestamp: self.clock.now(),
+            build: Box::new(build),
+            attachments,
+            log_id,
+            ticket: self.in_flight.start(),
+            size,
+            charge: None,
+        });
+        match sent {
+            Ok(()) => {}
+            Err(Rejected::Stopped) => {
+                diagnostics::warn(DiagnosticKind::Dropped, "Dropped a log record because the delivery worker has stopped");
+                self.record_drop(level, DropReason::WorkerStopped);
+            }
+            Err(Rejected::Overflow(dropped)) => {
+                for level in &dropped {
+                    self.record_drop(level, DropReason::Overflow);
+                }
+            }
+        }
+        if self.ttl.is_some() {
+            // Records expire in the worker, which cannot start the summaries itself.
+            self.start_drop_reports();
+        }
+        if let Some(saturation) = &self.saturation {
+            if saturation.needs_queue() {
+                saturation.connect(
+                    self.queue(&Level::WARN).clone(),
+                    Arc::clone(&self.in_flight),
+                    Arc::clone(&self.clock),
+                    Arc::clone(&self.ids),
+                );
+            }
+            saturation.check_pending(self.in_flight.pending());
+        }
+    }
+
+    /// Returns the queue for records at `level`: the queue of the first route it matches, or
+    /// the default one. Workers are started on first use.
+    fn queue(&self, level: &Level) -> &QueueSender {
+        match self.routes.iter().find(|route| route.matches(level)) {
+            Some(route) => route.queue.get_or_init(|| {
+                self.spawn_worker(route.batching(), route.logs_endpoint(), self.route_encoding(route))
+            }),
+            None => self
+                .queue
+                .get_or_init(|| self.spawn_worker(&self.batching, None, Arc::clone(&self.payload_encoding))),
+        }
+    }
+
+    /// Returns the queue for domain events, starting their worker on first use.
+    fn events_queue(&self) -> &QueueSender {
+        self.events_queue.get_or_init(|| {
+            let payload_encoding = Arc::new(EncodingNegotiation::new(self.payload_encoding.preferred()));
+            self.spawn_worker(&self.batching, Some(&self.events_endpoint), payload_encoding)
+        })
+    }
+
+    /// Returns the payload encoding of a route's worker. Routes to the appender's logs endpoint
+    /// with the layer's encoding share its negotiation; the others negotiate on their own, so
+    /// one endpoint refusing an encoding does not change what the others receive.
+    fn route_encoding(&self, route: &Route) -> Arc<EncodingNegotiation> {
+        match (route.logs_endpoint(), route.payload_encoding()) {
+            (None, None) => Arc::clone(&self.payload_encoding),
+            (_, encoding) => Arc::new(EncodingNegotiation::new(
+                encoding.unwrap_or_else(|| self.payload_encoding.preferred()),
+            )),
+        }
+    }
+
+    /// Starts a worker delivering with the given batching settings and payload encoding, to
+    /// `logs_endpoint` or the appender's logs endpoint.
+    fn spawn_worker(
+        &self,
+        batching: &BatchConfig,
+        logs_endpoint: Option<&str>,
+        payload_encoding: Arc<EncodingNegotiation>,
+    ) -> QueueSender {
+        let delivery = Delivery {
+            appender: Arc::clone(&self.appender),
+            logs_endpoint: logs_endpoint.map(str::to_string),
+            max_request_size: batching.max_request_size(),
+            encoding: self.attachments.encoding(),
+            payload_encoding,
+            sinks: self.sinks.clone(),
+            intake: self.intake,
+            connection_refresh: self.connection_refresh(),
+            endpoint_race: self.endpoint_race.clone(),
+            retry: self.retry.clone(),
+            statuses: self.statuses.clone(),
+            otlp: self.otlp.as_ref().map(|otlp| Arc::new(otlp.as_ref().clone().with_hosting(&self.hosting_tags))),
+            kill_switch: Arc::clone(&self.kill_switch),
+            validate: self.validate.clone(),
+            saturation: self.saturation.clone(),
+            ttl: self.ttl,
+            drop_stats: self.drop_stats.clone(),
+            ids: Arc::clone(&self.ids),
+            payload_stats: self.payload_stats.clone(),
+            batches: pipeline::batch_pool(),
+        };
+        pipeline::spawn_worker(delivery, batching.clone(), Arc::clone(&self.in_flight), self.runtime())
+    }
+
+    /// Returns the policy refreshing the intake client, shared by all workers.
+    fn connection_refresh(&self) -> Option<Arc<ConnectionRefresh>> {
+        self.connection_refresh
+            .get_or_init(|| ConnectionRefresh::new(self.http.clone(), self.connection_max_age).map(Arc::new))
+            .clone()
+    }
+
+    /// Starts warming up connections to the logs endpoints, if enabled.
+    fn start_warmup(&self) {
+        let Some(warmup) = &self.warmup else {
+            return;
+        };
+        if !self.intake || self.otlp.is_some() {
+            return;
+        }
+        let Some(runtime) = self.runtime() else {
+            diagnostics::error(DiagnosticKind::Config, "Connection warm-up needs a tokio runtime");
+            return;
+        };
+
+        let mut endpoints = vec![None];
+        for endpoint in self.routes.iter().filter_map(Route::logs_endpoint) {
+            if !endpoints.contains(&Some(endpoint.to_string())) {
+                endpoints.push(Some(endpoint.to_string()));
+            }
+        }
+        connection::warm_up(
+            warmup.clone(),
+            Arc::clone(&self.appender),
+            self.connection_refresh(),
+            endpoints,
+            Arc::clone(&self.kill_switch),
+            Arc::downgrade(&self.in_flight),
+            &runtime,
+        );
+    }
+
+    /// Returns the runtime to run background tasks on: the current one, or the one the layer
+    /// was created in.
+    fn runtime(&self) -> Option<Handle> {
+        Handle::try_current().ok().or_else(|| self.runtime.clone())
+    }
+
+    /// Counts an event that was not forwarded, if drop reports are enabled, and starts
+    /// sending the summaries with the first drop.
+    fn record_drop(&self, level: &Level, reason: DropReason) {
+        let Some(stats) = &self.drop_stats else {
+            return;
+        };
+        stats.record(*level, reason);
+        self.start_drop_reports();
+    }
+
+    /// Starts sending the drop summaries, if drop reports are enabled and they are not sent
+    /// yet, and a runtime is available.
+    fn start_drop_reports(&self) {
+        let Some(stats) = &self.drop_stats else {
+            return;
+        };
+        if stats.is_reporting() {
+            return;
+        }
+        let Some(runtime) = self.runtime() else {
+            return;
+        };
+
+        let queue = self.queue(&Level::WARN).clone();
+        let in_flight = Arc::clone(&self.in_flight);
+        let clock = Arc::clone(&self.clock);
+        let ids = Arc::clone(&self.ids);
+        stats.start_reporting(&runtime, move |build| {
+            let _ = queue.send(Queued {
+                event_id: ids.generate(IdKind::Event),
+                level: Level::WARN,
+                timestamp: clock.now(),
+                build,
+                attachments: Vec::new(),
+                log_id: None,
+                ticket: in_flight.start(),
+                size: ring::RECORD_OVERHEAD,
+                charge: None,
+            });
+        });
+    }
+
+    /// Returns `true` if records for spans or events with this metadata may be sent to POGR.
+    fn accepts(&self, metadata: &Metadata) -> bool {
+        self.accepts_at(metadata.target(), metadata.level())
+    }
+
+    /// Returns `true` if records at `level` for `target` may be sent to POGR.
+    fn accepts_at(&self, target: &str, level: &Level) -> bool {
+        !is_internal_target(target)
+            && !diagnostics::is_reporting()
+            && !self.in_flight.is_closed()
+            && layer_scope::admits(self.id)
+            && reload::read(&self.settings).level_enabled(target, level)
+    }
+}
+
+/// Number of emptied field maps a layer keeps for reuse.
+const POOLED_FIELD_MAPS: usize = 64;
+
+/// Largest number of fields a field map may have room for to be reused.
+const MAX_POOLED_FIELDS: usize = 256;
+
+/// Targets whose events are never forwarded to POGR.
+///
+/// Submitting a log goes through `reqwest` and `hyper`, which emit their own `tracing`
+/// events. Forwarding those (or this crate's own diagnostics) would cause every submission
+/// to trigger further submissions, so the layer drops them.
+const INTERNAL_TARGETS: &[&str] = &["pogr_tracing_rs", "reqwest", "hyper", "h2", "want", "mio", "rustls", "tokio_util"];
+
+/// Returns `true` if `target` belongs to this crate or to the HTTP stack it uses.
+fn is_internal_target(target: &str) -> bool {
+    INTERNAL_TARGETS.iter().any(|internal| filter::target_matches(target, internal))
+}
+
+/// Builds the record sent when a span is entered, exited or closed.
+///
+/// The span's metadata is serialized like an event's, with the span ID, the transition
+/// (`enter`, `exit` or `close`), the IDs of the spans it follows from and, if known, the span's
+/// lifetime in milliseconds added to `data`. The span's fields are sent as tags.
+fn span_transition_request(
+    appender: &PogrAppender,
+    metadata: &Metadata,
+    span_id: u64,
+    transition: &str,
+    fields: Value,
+    duration: Option<Duration>,
+    follows_from: Vec<u64>,
+) -> LogRequest {
+    let mut data = serialize_metadata(metadata);
+    if let Value::Object(map) = &mut data {
+        map.insert("span_id".to_string(), json!(span_id));
+        map.insert("span_event".to_string(), json!(transition));
+        map.insert("follows_from".to_string(), json!(follows_from));
+        if let Some(duration) = duration {
+            map.insert("duration_ms".to_string(), json!(duration.as_secs_f64() * 1000.0));
+        }
+    }
+
+    LogRequest {
+        service: appender.service_name.clone(),
+        environment: appender.environment.clone(),
+        severity: metadata.level().to_string(),
+        r#type: appender.service_type.clone(),
+        log: format!("rust tracing span {}", transition),
+        data,
+        tags: fields,
+    }
+}
+
+/// Builds the warning sent when a span closes after more than its budget.
+///
+/// The span's metadata is serialized like an event's, with the span ID, its lifetime and its
+/// budget in milliseconds added to `data`. The span's fields are sent as tags.
+fn span_budget_request(appender: &PogrAppender, metadata: &Metadata, span_id: u64, fields: Value, duration: Duration, budget: Duration) -> LogRequest {
+    let mut data = serialize_metadata(metadata);
+    if let Value::Object(map) = &mut data {
+        map.insert("span_id".to_string(), json!(span_id));
+        map.insert("duration_ms".to_string(), json!(duration.as_secs_f64() * 1000.0));
+        map.insert("budget_ms".to_string(), json!(budget.as_secs_f64() * 1000.0));
+    }
+
+    LogRequest {
+        service: appender.service_name.clone(),
+        environment: appender.environment.clone(),
+        severity: Level::WARN.to_string(),
+        r#type: appender.service_type.clone(),
+        log: "rust tracing span over budget".to_string(),
+        data,
+        tags: fields,
+    }
+}
+
+/// Builds the record standing for the repeats of a message.
+///
+/// The fields of the first repeat are sent as tags, with the number of repeats as
+/// `repeat_count` and the first and last of them as `first_seen` and `last_seen`.
+fn repeated_request(appender: &PogrAppender, metadata: &Metadata, repeats: Repeats) -> LogRequest {
+    let mut tags = repeats.tags;
+    if let Value::Object(map) = &mut tags {
+        map.insert("repeat_count".to_string(), json!(repeats.count));
+        map.insert("first_seen".to_string(), json!(clock::format_rfc3339(repeats.first_seen)));
+        map.insert("last_seen".to_string(), json!(clock::format_rfc3339(repeats.last_seen)));
+    }
+
+    LogRequest {
+        service: appender.service_name.clone(),
+        environment: appender.environment.clone(),
+        severity: metadata.level().to_string(),
+        r#type: appender.service_type.clone(),
+        log: "rust tracing log repeated".to_string(),
+        data: serialize_metadata(metadata),
+        tags,
+    }
+}
+
+/// Builds the record carrying a finished trace.
+///
+/// The trace document is sent as `data`, with the root span's level as the severity. The
+/// `trace_id` is also sent as a tag, so the trace can be found alongside its events.
+fn trace_request(appender: &PogrAppender, document: TraceDocument) -> LogRequest {
+    let severity = document
+        .spans
+        .iter()
+        .find(|span| span.parent_id.is_none())
+        .map_or_else(|| "INFO".to_string(), |root| root.level.clone());
+
+    LogRequest {
+        service: appender.service_name.clone(),
+        environment: appender.environment.clone(),
+        severity,
+        r#type: appender.service_type.clone(),
+        log: "rust tracing trace".to_string(),
+        tags: json!({ "trace_id": document.trace_id.to_string() }),
+        data: to_value(&document).unwrap_or_else(|err| json!({ "serialization_error": err.to_string() })),
+    }
+}
+
+/// Serializes metadata from a `tracing` event into a JSON value.
+///
+/// This function takes metadata from a log event, such as the log level, target,
+/// file, and line number, and converts it into a structured JSON representation.
+/// This serialized form is suitable for inclusion in log messages sent to external
+/// services, providing rich contextual information about each log event.
+///
+/// # Arguments
+///
+/// * `metadata` - Metadata from a `tracing` event.
+///
+/// # Returns
+///
+/// A `serde_json::Value` representing the serialized metadata.
+fn serialize_metadata(metadata: &Metadata) -> Value {
+    let mut map = HashMap::new();
+
+    map.insert("name", Value::from(metadata.name()));
+    map.insert("target", Value::from(metadata.target()));
+    map.insert("level", Value::from(format!("{:?}", metadata.level())));
+    map.insert("file", metadata.file().map(Value::from).unwrap_or(Value::Null));
+    map.insert("line", metadata.line().map(|line| Value::from(line as i64)).unwrap_or(Value::Null));
+
+    // Convert the HashMap<&str, Value> to Value directly using to_value
+    to_value(map).unwrap_or(Value::Null)
+}
+
+/// Returns the init and logs endpoint URLs: the given ones, or those from the environment, or
+/// the defaults.
+pub(crate) fn endpoints(init_endpoint: Option<String>, logs_endpoint: Option<String>) -> (String, String) {
+    let init_endpoint_url = init_endpoint
+        .or_else(|| env::var("POGR_INIT_ENDPOINT").ok())
+        .unwrap_or_else(|| "https://api.pogr.io/v1/intake/init".to_string());
+    let logs_endpoint_url = logs_endpoint
+        .or_else(|| env::var("POGR_LOGS_ENDPOINT").ok())
+        .unwrap_or_else(|| "https://api.pogr.io/v1/intake/logs".to_string());
+
+    (init_endpoint_url, logs_endpoint_url)
+}
+
+/// Checks the schemes of the init and logs endpoints, see `connection::check_scheme`.
+pub(crate) fn check_endpoints(init_endpoint_url: &str, logs_endpoint_url: &str, allow_insecure: bool) -> Result<(), String> {
+    connection::check_scheme("init", init_endpoint_url, &endpoint_template::check("init", init_endpoint_url)?, allow_insecure)?;
+    connection::check_scheme("logs", logs_endpoint_url, &endpoint_template::check("logs", logs_endpoint_url)?, allow_insecure)
+}
+
+/// Reports a failed session initialization as a diagnostic, then panics with the same message.
+fn init_failed(message: impl fmt::Display) -> ! {
+    let message = message.to_string();
+    diagnostics::error(DiagnosticKind::Init, &message);
+    panic!("{}", message);
+}
+
+impl PogrAppender {
+    /// Constructs a new `PogrAppender` with optional custom endpoints.
+    ///
+    /// Initializes a session with the POGR service using provided or default endpoints.
+    /// Requires `POGR_ACCESS` and `POGR_SECRET` environment variables for authentication.
+    ///
+    /// # Arguments
+    ///
+    /// * `init_endpoint` - Optional custom URL for the session initialization endpoint.
+    /// * `logs_endpoint` - Optional custom URL for the log submission endpoint.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `PogrAppender` configured and initialized for logging.
+    ///
+    /// # Panics
+    ///
+    /// Panics if session initialization fails or required environment variables are missing.
+    /// Also panics if an endpoint is not a valid `https` URL: plain `http` is only accepted
+    /// for loopback hosts, unless `POGR_ALLOW_INSECURE` is set to `1` (see
+    /// `PogrAppenderBuilder::with_allow_insecure`).
+    pub async fn new(init_endpoint: Option<String>, logs_endpoint: Option<String>) -> Self {
+        let (init_endpoint_url, logs_endpoint_url) = endpoints(init_endpoint, logs_endpoint);
+        check_endpoints(&init_endpoint_url, &logs_endpoint_url, connection::allow_insecure_from_env())
+            .unwrap_or_else(|message| init_failed(message));
+        Self::initialize(connection::new_client(), init_endpoint_url, logs_endpoint_url, &SessionMetadata::default())
+            .await
+            .unwrap_or_else(|message| init_failed(message))
+    }
+
+    /// Initializes a session, returning the reason instead of panicking if that fails.
+    pub(crate) async fn initialize(
+        client: Client,
+        init_endpoint_url: String,
+        logs_endpoint_url: String,
+        session: &SessionMetadata,
+    ) -> Result<Self, String> {
+        let service_name = env::var("SERVICE_NAME").unwrap_or_else(|_| env::current_exe().unwrap().file_name().unwrap().to_str().unwrap().to_owned());
+        let hosting = if session.detect_hosting { hosting::detect_if_enabled() } else { None };
+        let environment = env::var("ENVIRONMENT")
+            .ok()
+            .or_else(|| hosting.and_then(Hosting::default_environment))
+            .unwrap_or_else(|| "development".to_owned());
+        let service_type = env::var("SERVICE_TYPE").unwrap_or_else(|_| "service".to_owned());
+
+        let pogr_client = env::var("POGR_ACCESS").map_err(|_| "POGR_ACCESS must be set".to_string())?;
+        let pogr_build = env::var("POGR_SECRET").map_err(|_| "POGR_SECRET must be set".to_string())?;
+
+        let init_url = endpoint_template::resolve(&init_endpoint_url, &service_name, &environment, &service_type, None);
+        let offered = api_version::offered(&logs_endpoint_url, session.api_version);
+        let init_request = client.post(&init_url)
+            .header(api_version::OFFER_HEADER, api_version::offer_header(&offered))
+            .header("POGR_ACCESS", pogr_client)
+            .header("POGR_SECRET", pogr_build)
+            .json(&InitRequest {
+                service: &service_name,
+                environment: &environment,
+                r#type: &service_type,
+                sdk: env!("CARGO_PKG_NAME"),
+                sdk_version: env!("CARGO_PKG_VERSION"),
+                build_id: session.build_id.as_deref(),
+                platform: session.platform.as_deref(),
+                hosting: hosting.map(Hosting::as_str),
+                metadata: &session.fields,
+                process: session.process.as_ref().map(ProcessInfo::current),
+                #[cfg(feature = "client-telemetry")]
+                hardware: HardwareInfo::detect(),
+            });
+        let (status, init_body) = http_dump::send(&client, init_request)
+            .await
+            .map_err(|err| format!("Failed to send init request: {:?}", err))?;
+        let init_response: InitResponse = match serde_json::from_slice(&init_body) {
+            Ok(init_response) => init_response,
+            Err(_) if !status.is_success() => {
+                return Err(format!(
+                    "Failed to initialize POGR session: {}: {}",
+                    status,
+                    String::from_utf8_lossy(&init_body)
+                ))
+            }
+            Err(err) => return Err(format!("Failed to deserialize init response: {:?}", err)),
+        };
+
+        if init_response.success {
+            let api_version = api_version::negotiate(&offered, init_response.payload.api_version)?;
+            let logs_endpoint_url = api_version.apply(&logs_endpoint_url);
+            if IntakeApiVersion::of_endpoint(&logs_endpoint_url) != api_version {
+                return Err(format!(
+                    "The logs endpoint {} has no version segment to switch to the intake API {}",
+                    logs_endpoint_url, api_version
+                ));
+            }
+            builder::remember_session(&init_response.payload.session_id, session);
+            Ok(PogrAppender {
+                client,
+                service_name,
+                environment,
+                service_type,
+                session_id: init_response.payload.session_id,
+                logs_endpoint: logs_endpoint_url,
+                init_endpoint: init_endpoint_url,
+            })
+        } else {
+            Err("Failed to initialize POGR session".to_string())
+        }
+    }
+
+    /// Initializes a new session with the appender's client and endpoints, returning its ID.
+    ///
+    /// The session is described by the metadata the current session was initialized with, or
+    /// like one initialized by `new` if it was not initialized by this process.
+    pub(crate) async fn reinitialize(&self) -> Result<String, String> {
+        let appender = Self::initialize(
+            self.client.clone(),
+            self.init_endpoint.clone(),
+            self.logs_endpoint.clone(),
+            &builder::session_metadata(&self.session_id),
+        )
+        .await?;
+        Ok(appender.session_id)
+    }
+
+    /// Asynchronously sends a log message to the POGR service.
+    ///
+    /// Constructs and sends a log request to the configured POGR endpoint. This method
+    /// handles serialization of the log message and metadata, and sends the data using
+    /// the internal HTTP client. It ensures that each log message is associated with
+    /// the current session via the `INTAKE_SESSION_ID` header.
+    ///
+    /// Each log message is assigned a new UUIDv7 as its `event_id`, which is included in the
+    /// payload and returned, so the message can be referenced later (e.g. in a support ticket).
+    /// The message is timestamped with the crate's default `MonotonicClock`.
+    ///
+    /// # Arguments
+    ///
+    /// * `log_request` - The log message and associated data to send.
+    ///
+    /// # Returns
+    ///
+    /// The client-generated `event_id` of the submitted log message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the log request fails to send or if the response cannot be deserialized.
+    pub async fn log(&self, log_request: LogRequest) -> Uuid {
+        let event_id = Uuid::now_v7();
+        self.log_with_id(event_id, log_request).await;
+        event_id
+    }
+
+    /// Asynchronously sends a log message with a caller-provided `event_id` to the POGR service.
+    ///
+    /// Use this instead of `log` when the identifier has already been assigned, e.g. when the
+    /// same event is submitted again, so that every attempt carries the same `event_id`.
+    ///
+    /// # Arguments
+    ///
+    /// * `event_id` - The client-side identifier of the log message.
+    /// * `log_request` - The log message and associated data to send.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the log request fails to send or if the response cannot be deserialized.
+    pub async fn log_with_id(&self, event_id: Uuid, log_request: LogRequest) {
+        let record = LogRecord {
+            event_id,
+            timestamp: clock::default_clock().now(),
+            request: log_request,
+            attachments: Vec::new(),
+            validation_error: None,
+            quarantine_error: OnceLock::new(),
+        };
+        match self.try_send(&[&record], AttachmentEncoding::Base64).await {
+            Ok(_) => {}
+            Err(DeliveryError::Transport(err)) => panic!("Failed to send log request: {:?}", err),
+            Err(DeliveryError::InvalidResponse(err)) => panic!("Failed to deserialize log response: {:?}", err),
+            Err(err) => diagnostics::error(DiagnosticKind::Delivery, format!("Failed to log to POGR: {}", err)),
+        }
+    }
+
+    /// Resolves the placeholders of an endpoint for this appender's session.
+    pub(crate) fn resolve_endpoint(&self, template: &str) -> String {
+        endpoint_template::resolve(template, &self.service_name, &self.environment, &self.service_type, Some(&self.session_id))
+    }
+
+    /// Submits records to the logs endpoint in a single request.
+    ///
+    /// A single record is sent as a JSON object, several records as a JSON array. With
+    /// `AttachmentEncoding::Multipart`, a single record that carries attachments is sent as
+    /// `multipart/form-data` instead; such records must not be batched with others.
+    ///
+    /// # Returns
+    ///
+    /// The log ID the intake assigned, or `None` if there was nothing to submit.
+    pub(crate) async fn try_send(
+        &self,
+        records: &[&LogRecord],
+        encoding: AttachmentEncoding,
+    ) -> Result<Option<String>, DeliveryError> {
+        self.try_send_encoded(records, encoding, PayloadEncoding::Json, None).await
+    }
+
+    /// Submits records to the logs endpoint in a single request, encoded with
+    /// `payload_encoding`, with an `Idempotency-Key` header if a key is given.
+    ///
+    /// Records sent as `multipart/form-data` always carry a JSON payload.
+    pub(crate) async fn try_send_encoded(
+        &self,
+        records: &[&LogRecord],
+        encoding: AttachmentEncoding,
+        payload_encoding: PayloadEncoding,
+        idempotency_key: Option<Uuid>,
+    ) -> Result<Option<String>, DeliveryError> {
+        let log_id = match records {
+            [] => return Ok(None),
+            [record] if encoding == AttachmentEncoding::Multipart && !record.attachments.is_empty() => {
+                let form = multipart_form(record)?;
+                self.try_post(idempotency_key, |request| request.multipart(form)).await
+            }
+            records => match payload_encoding {
+                PayloadEncoding::Json => {
+                    let envelopes: Vec<LogEnvelope> = records.iter().map(|record| record.envelope()).collect();
+                    let body = self.api_version().encode(envelopes).map_err(|err| DeliveryError::Serialization(err.to_string()))?;
+                    self.try_post(idempotency_key, |request| request.header("Content-Type", "application/json").body(body))
+                        .await
+                }
+                PayloadEncoding::Ndjson => {
+                    let envelopes: Vec<LogEnvelope> = records.iter().map(|record| record.envelope()).collect();
+                    let body = payload::encode_ndjson(&envelopes).map_err(|err| DeliveryError::Serialization(err.to_string()))?;
+                    self.try_post(idempotency_key, |request| request.header("Content-Type", payload::NDJSON_CONTENT_TYPE).body(body))
+                        .await
+                }
+                #[cfg(feature = "msgpack")]
+                PayloadEncoding::MessagePack => {
+                    let envelopes: Vec<LogEnvelope> = records.iter().map(|record| record.envelope()).collect();
+                    let body = payload::encode_msgpack(&self.api_version().request_body(envelopes))
+                        .map_err(|err| DeliveryError::Serialization(err.to_string()))?;
+                    self.try_post(idempotency_key, |request| request.header("Content-Type", payload::MSGPACK_CONTENT_TYPE).body(body))
+                        .await
+                }
+                #[cfg(feature = "cbor")]
+                PayloadEncoding::Cbor => {
+                    let envelopes: Vec<LogEnvelope> = records.iter().map(|record| record.envelope()).collect();
+                    let body = payload::encode_cbor(&self.api_version().request_body(envelopes))
+                        .map_err(|err| DeliveryError::Serialization(err.to_string()))?;
+                    self.try_post(idempotency_key, |request| request.header("Content-Type", payload::CBOR_CONTENT_TYPE).body(body))
+                        .await
+                }
+                #[cfg(feature = "protobuf")]
+                PayloadEncoding::Protobuf => {
+                    let body = protobuf::encode(records);
+                    self.try_post(idempotency_key, |request| request.header("Content-Type", protobuf::CONTENT_TYPE).body(body))
+                        .await
+                }
+            },
+        };
+        log_id.map(Some)
+    }
+
+    /// Submits already serialized log messages in a single request.
+    ///
+    /// Each payload must be a serialized `LogEnvelope`. They are sent in the body of the
+    /// logs endpoint's API version.
+    pub(crate) async fn try_send_serialized(&self, payloads: &[String]) -> Result<(), DeliveryError> {
+        if payloads.is_empty() {
+            return Ok(());
+        }
+        let body = self.api_version().encode_serialized(payloads);
+
+        self.try_post(None, |request| request.header("Content-Type", "application/json").body(body))
+            .await
+            .map(|_| ())
+    }
+
+    /// Posts a request with the given body, and idempotency key if any, to the logs endpoint
+    /// and checks the response.
+    ///
+    /// A response asking to pause delivery fails with `DeliveryError::Paused`, and a
+    /// `415 Unsupported Media Type` response with `DeliveryError::UnsupportedEncoding`.
+    ///
+    /// # Returns
+    ///
+    /// The log ID the intake assigned.
+    async fn try_post(
+        &self,
+        idempotency_key: Option<Uuid>,
+        body: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<String, DeliveryError> {
+
+        let log_endpoint = self.resolve_endpoint(&self.logs_endpoint);
+
+        let mut request = self.client.post(&log_endpoint)
+            .header("INTAKE_SESSION_ID", &self.session_id);
+        if let Some(key) = idempotency_key {
+            request = request.header(id::IDEMPOTENCY_HEADER, key.to_string());
+        }
+
+        let (status, headers, response_body) = http_dump::send_with_headers(&self.client, body(request))
+            .await
+            .map_err(DeliveryError::Transport)?;
+        if let Some(duration) = pause::requested(status, &headers) {
+            return Err(DeliveryError::Paused(duration));
+        }
+        if status == reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE {
+            return Err(DeliveryError::UnsupportedEncoding);
+        }
+
+        self.api_version().parse_response(status, &response_body).map_err(|err| match err {
+            DeliveryError::Rejected(response) if !status.is_success() => DeliveryError::Status {
+                status,
+                retry_after: pause::retry_after(&headers),
+                response,
+            },
+            err => err,
+        })
+    }
+
+    /// Returns the version of the intake API the logs endpoint targets.
+    pub fn api_version(&self) -> IntakeApiVersion {
+        IntakeApiVersion::of_endpoint(&self.logs_endpoint)
+    }
+}
+
+/// Why a request to the logs endpoint failed.
+#[derive(Debug)]
+pub(crate) enum DeliveryError {
+    /// The request could not be sent, or the response could not be read.
+    Transport(reqwest::Error),
+    /// The response is not a valid intake response.
+    InvalidResponse(serde_json::Error),
+    /// The intake answered, but did not accept the log messages.
+    Rejected(String),
+    /// The intake, or a proxy in front of it, answered with an error status.
+    Status {
+        /// The status of the response.
+        status: reqwest::StatusCode,
+        /// How long the response asked to wait before retrying, if it did.
+        retry_after: Option<Duration>,
+        /// The intake's rejection or the body of the response.
+        response: String,
+    },
+    /// The OpenTelemetry collector answered, but did not accept the log messages.
+    CollectorRejected(String),
+    /// The intake asked to pause delivery for a while, and did not accept the log messages.
+    Paused(Duration),
+    /// A flush that timed out gave up on the log messages.
+    Abandoned,
+    /// The intake does not accept the encoding of the request body.
+    UnsupportedEncoding,
+    /// The log messages could not be serialized into a request body.
+    Serialization(String),
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::Transport(err) => write!(f, "{}", err),
+            DeliveryError::InvalidResponse(err) => write!(f, "invalid response from the intake: {}", err),
+            DeliveryError::Rejected(response) | DeliveryError::Status { response, .. } => {
+                write!(f, "rejected by the intake: {}", response)
+            }
+            DeliveryError::CollectorRejected(response) => write!(f, "rejected by the collector: {}", response),
+            DeliveryError::Paused(duration) => write!(f, "the intake paused delivery for {} s", duration.as_secs()),
+            DeliveryError::Abandoned => write!(f, "abandoned by a flush that timed out"),
+            DeliveryError::UnsupportedEncoding => write!(f, "the intake does not accept the payload encoding"),
+            DeliveryError::Serialization(err) => write!(f, "failed to serialize the request: {}", err),
+        }
+    }
+}
+
+impl DeliveryError {
+    /// Returns the status of the response, for failures with a non-success status.
+    pub(crate) fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            DeliveryError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Returns the class of the failure and the `Retry-After` of the response, for failures
+    /// a `RetryPolicy` decides about.
+    pub(crate) fn retry_class(&self) -> Option<(ErrorClass, Option<Duration>)> {
+        match self {
+            DeliveryError::Transport(_) => Some((ErrorClass::Transport, None)),
+            DeliveryError::InvalidResponse(_) => Some((ErrorClass::InvalidResponse, None)),
+            DeliveryError::Rejected(_) | DeliveryError::CollectorRejected(_) => Some((ErrorClass::Rejected, None)),
+            DeliveryError::Status { status, retry_after, .. } => {
+                let class = if *status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    ErrorClass::RateLimited
+                } else if status.is_server_error() {
+                    ErrorClass::ServerError
+                } else {
+                    ErrorClass::Rejected
+                };
+                Some((class, *retry_after))
+            }
+            DeliveryError::Paused(_)
+            | DeliveryError::Abandoned
+            | DeliveryError::UnsupportedEncoding
+            | DeliveryError::Serialization(_) => None,
+        }
+    }
+}
+
+/// Builds a `multipart/form-data` body for a record with attachments.
+///
+/// The JSON payload goes into the `log` part; each attachment is a binary part named
+/// `attachment-<index>`, which the payload's attachment list refers to.
+fn multipart_form(record: &LogRecord) -> Result<Form, DeliveryError> {
+    let envelope = LogEnvelope {
+        attachments: record
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(index, attachment)| AttachmentManifest::multipart(attachment, format!("attachment-{}", index)))
+            .collect(),
+        ..record.envelope()
+    };
+
+    let payload = serde_json::to_string(&envelope).map_err(|err| DeliveryError::Serialization(err.to_string()))?;
+    let mut form = Form::new().part(
+        "log",
+        Part::text(payload).mime_str("application/json").expect("Invalid MIME type"),
+    );
+
+    for (index, attachment) in record.attachments.iter().enumerate() {
+        let part = Part::bytes(attachment.data().to_vec()).file_name(attachment.name().to_string());
+        // Fall back to a generic binary part if the caller supplied an unparsable MIME type.
+        let part = match part.mime_str(attachment.content_type()) {
+            Ok(part) => part,
+            Err(_) => Part::bytes(attachment.data().to_vec()).file_name(attachment.name().to_string()),
+        };
+        form = form.part(format!("attachment-{}", index), part);
+    }
+
+    Ok(form)
+}
+
+/// Implements the `Layer` trait from the `tracing` crate for `PogrLayer`.
+///
+/// This implementation allows `PogrLayer` to interact with the `tracing` ecosystem,
+/// capturing log events, processing them, and then forwarding them to an external
+/// logging service defined by `PogrAppender`. It leverages asynchronous execution
+/// to ensure that the logging process does not block the main application flow.
+///
+/// The `PogrLayer` captures metadata and structured fields from log events,
+/// serializes them into a JSON format, and then sends them to the configured
+/// POGR endpoint via the `PogrAppender`. This process is done asynchronously
+/// to optimize performance and reduce the impact on application throughput.
+///
+/// # Type Parameters
+///
+/// * `S` - The subscriber type. This layer can be added to any subscriber that
+///   implements `Subscriber` and `for<'a> LookupSpan<'a>`, allowing it to
+///   interact with the span data.
+///
+/// # Examples
+///
+/// To use `PogrLayer` with a subscriber:
+///
+/// ```rust,no_run
+/// use tracing_subscriber::{Registry, layer::SubscriberExt};
+/// use pogr_tracing_rs::{PogrLayer, PogrAppender};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     // Initialize your PogrAppender here...
+///     let appender = PogrAppender::new(None, None).await;
+///
+///     let layer = PogrLayer::new(appender);
+///
+///     let subscriber = Registry::default().with(layer);
+///
+///     tracing::subscriber::set_global_default(subscriber)
+///         .expect("Failed to set global subscriber");
+/// }
+/// ```
+///
+/// This example sets up `PogrLayer` with a default `tracing` subscriber, allowing
+/// log events to be processed and forwarded to the POGR platform.
+impl<S> Layer<S> for PogrLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    /// Starts warming up connections once the layer is added to a subscriber, after all of
+    /// its settings are known.
+    fn on_layer(&mut self, _subscriber: &mut S) {
+        self.start_warmup();
+    }
+
+    /// Responds to log events captured by the `tracing` framework.
+    ///
+    /// This method is called automatically by the `tracing` framework for each log event.
+    /// It extracts event metadata and fields, serializes them into a structured log format,
+    /// and forwards them to the POGR platform using the `PogrAppender`. The actual submission
+    /// of log data is performed asynchronously to avoid blocking the execution of the event's
+    /// source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The log event being processed.
+    /// * `_ctx` - The context provided by the `tracing` framework, allowing for interaction
+    ///   with the rest of the tracing system, such as querying for active spans.
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if !self.accepts(metadata) {
+            return;
+        }
+
+        // Default tags, the task's context and the fields of the enclosing spans come first, from
+        // the root down, so that fields on inner spans and on the event itself take precedence.
+        let mut visitor = self.visitor();
+        visitor.fields.extend(self.hosting_tags.iter().cloned());
+        visitor.fields.extend(self.default_tags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        visitor.fields.extend(self.experiments.tags());
+        visitor.fields.extend(context::current_tags());
+        let mut oldest: Option<(&'static str, Duration)> = None;
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(stored) = layer_scope::get::<SpanFields>(&span.extensions(), self.id) {
+                    visitor.fields.extend(stored.fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    let age = stored.created_at.elapsed();
+                    if oldest.map_or(true, |(_, oldest)| age > oldest) {
+                        oldest = Some((span.name(), age));
+                    }
+                }
+            }
+        }
+        if let (Some(threshold), Some((name, age))) = (self.slow_span_age, oldest) {
+            if age > threshold {
+                visitor.fields.insert("slow".to_string(), json!(true));
+                visitor.fields.insert("slow_span".to_string(), json!(name));
+                visitor.fields.insert("slow_duration_ms".to_string(), json!(age.as_secs_f64() * 1000.0));
+            }
+        }
+        if let Some(span) = ctx.event_span(event) {
+            if let Some(membership) = layer_scope::get::<TraceMembership>(&span.extensions(), self.id) {
+                visitor.fields.insert("trace_id".to_string(), json!(membership.trace.trace_id.to_string()));
+            }
+        }
+        event.record(&mut visitor);
+        let event_type = typed_event::merge_fields(&mut visitor.fields);
+        let mut data = serialize_metadata(metadata);
+        ExecutionContext::current().insert_into(&mut data);
+        typed_event::take_struct(&mut visitor.fields, &mut data);
+        let domain_kind = if metadata.target() == EVENT_TARGET {
+            domain_event::take_event(&mut visitor.fields, &mut data)
+        } else {
+            None
+        };
+
+        let pending = attachment::take_pending(event, self.id);
+        if let Some(missing) = self.audit.as_ref().and_then(|audit| audit.missing_fields(metadata.target(), &visitor.fields)) {
+            if !missing.is_empty() {
+                diagnostics::error(
+                    DiagnosticKind::Audit,
+                    format!(
+                        "Dropped an audit event from {} at {}:{} missing {}",
+                        metadata.target(),
+                        metadata.file().unwrap_or("unknown"),
+                        metadata.line().unwrap_or(0),
+                        missing.join(", ")
+                    ),
+                );
+                self.record_drop(metadata.level(), DropReason::Audit);
+                return;
+            }
+        }
+        if self.key_sampling.as_ref().is_some_and(|sampling| !sampling.keeps(&visitor.fields)) {
+            self.record_drop(metadata.level(), DropReason::KeySampling);
+            return;
+        }
+        if domain_kind.is_none() && !reload::read(&self.settings).sample(metadata.level()) {
+            self.record_drop(metadata.level(), DropReason::Sampling);
+            return;
+        }
+        if let Some(field_units) = &self.field_units {
+            field_units.annotate(&mut visitor.fields);
+        }
+
+        let (attachments, dropped) = self.attachments.apply_limits(pending);
+        let mut fields = visitor.fields;
+        let mut tags = Value::Object(fields.drain().collect());
+        self.field_maps.put(fields);
+        attachment::annotate_dropped(&mut tags, &dropped);
+        if !dropped.is_empty() {
+            diagnostics::warn(
+                DiagnosticKind::Dropped,
+                format!("Dropped attachments over the size limits: {}", dropped.join(", ")),
+            );
+        }
+
+        if domain_kind.is_none() && attachments.is_empty() && dropped.is_empty() && self.coalesce(metadata, &tags) {
+            return;
+        }
+        if self.rate_limits.as_ref().is_some_and(|rate_limits| !rate_limits.admit(metadata.target())) {
+            self.record_drop(metadata.level(), DropReason::RateLimit);
+            return;
+        }
+
+        // Register a slot for the log ID in the event's span, filled once the intake accepts it.
+        let log_id = self.track_log_ids.then(LogIdSlot::default);
+        if let (Some(slot), Some(span)) = (&log_id, ctx.event_span(event)) {
+            let mut extensions = span.extensions_mut();
+            match layer_scope::get_mut::<SpanLogIds>(&mut extensions, self.id) {
+                Some(log_ids) => log_ids.push(Arc::clone(slot)),
+                None => {
+                    let mut log_ids = SpanLogIds::default();
+                    log_ids.push(Arc::clone(slot));
+                    layer_scope::insert(&mut extensions, self.id, log_ids);
+                }
+            }
+        }
+
+        // Domain events go to the event intake, with their kind as the message.
+        let queue = match domain_kind {
+            Some(_) => self.events_queue(),
+            None => self.queue(metadata.level()),
+        };
+        let size = ring::RECORD_OVERHEAD
+            + ring::estimated_size(&data)
+            + ring::estimated_size(&tags)
+            + attachments.iter().map(Attachment::size).sum::<usize>();
+        self.submit_to(queue, metadata.level(), size, attachments, log_id, move |appender| LogRequest {
+            service: appender.service_name.clone(),
+            environment: appender.environment.clone(),
+            severity: metadata.level().to_string(),
+            r#type: event_type.unwrap_or_else(|| appender.service_type.clone()),
+            log: domain_kind.unwrap_or_else(|| "rust tracing log captured".to_string()),
+            data,
+            tags,
+        });
+    }
+
+    /// Captures the fields of a newly created span.
+    ///
+    /// Fields are kept in the span's extensions until the span is closed, and are attached
+    /// to every event recorded inside the span as well as to the span's own records.
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if is_internal_target(attrs.metadata().target()) {
+            return;
+        }
+
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut visitor = self.visitor();
+        attrs.record(&mut visitor);
+        let membership = self.trace_export.as_ref().and_then(|trace_export| match span.parent() {
+            Some(parent) => layer_scope::get::<TraceMembership>(&parent.extensions(), self.id)
+                .map(|membership| membership.join(self.clock.now())),
+            None if self.accepts(attrs.metadata()) => trace_export.start(attrs.metadata().target(), self.clock.now(), &*self.ids),
+            None => None,
+        });
+
+        let mut extensions = span.extensions_mut();
+        layer_scope::insert(
+            &mut extensions,
+            self.id,
+            SpanFields {
+                fields: visitor.fields,
+                created_at: Instant::now(),
+                follows_from: Vec::new(),
+            },
+        );
+        if let Some(membership) = membership {
+            layer_scope::insert(&mut extensions, self.id, membership);
+        }
+    }
+
+    /// Records that the span `id` follows from the span `follows`.
+    ///
+    /// The related span IDs are included in the span's records, so fan-out/fan-in workflows
+    /// such as job queues or joined futures can be reconstructed.
+    fn on_follows_from(&self, id: &Id, follows: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        if let Some(stored) = layer_scope::get_mut::<SpanFields>(&mut extensions, self.id) {
+            let follows = follows.into_u64();
+            if !stored.follows_from.contains(&follows) {
+                stored.follows_from.push(follows);
+            }
+        }
+    }
+
+    /// Updates the stored fields of a span when values are recorded after its creation,
+    /// e.g. through `span.record("key", value)`.
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        if let Some(stored) = layer_scope::get_mut::<SpanFields>(&mut extensions, self.id) {
+            let mut visitor = self.visitor();
+            values.record(&mut visitor);
+            stored.fields.extend(visitor.fields.drain());
+            self.field_maps.put(visitor.fields);
+        }
+    }
+
+    /// Emits an enter record for spans on targets selected with `with_span_events`.
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        self.on_transition(id, ctx, "enter", SpanEvents::enter_enabled);
+    }
+
+    /// Emits an exit record for spans on targets selected with `with_span_events`.
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        self.on_transition(id, ctx, "exit", SpanEvents::exit_enabled);
+    }
+
+    /// Emits a close record, including the span's lifetime, for spans on targets selected
+    /// with `with_span_events` and `SpanEvents::with_close`, and adds the lifetime to the
+    /// latency histograms if enabled with `with_span_latency`. Spans closing after more than
+    /// their budget produce a warning. Closing the last open span of a sampled trace sends its
+    /// trace document.
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        self.close_traced_span(&id, &ctx);
+        self.check_span_budget(&id, &ctx);
+        if let (Some(latency), Some(span)) = (&self.span_latency, ctx.span(&id)) {
+            let metadata = span.metadata();
+            if let Some(stored) = layer_scope::get::<SpanFields>(&span.extensions(), self.id).filter(|_| self.accepts(metadata)) {
+                latency.record(metadata.target(), metadata.name(), stored.created_at.elapsed());
+            }
+        }
+        self.on_transition(&id, ctx, "close", SpanEvents::close_enabled);
+    }
+}
+
+impl PogrLayer {
+    /// Counts the event if it repeats a recent identical message, and schedules the event for
+    /// its repeats when it is the first of them.
+    ///
+    /// Returns `true` if the event was coalesced and must not be sent on its own. Events are
+    /// only coalesced if a tokio runtime is available, which is needed to close the windows.
+    fn coalesce(&self, metadata: &'static Metadata<'static>, tags: &Value) -> bool {
+        let Some(coalescer) = &self.coalescer else {
+            return false;
+        };
+        let Some(runtime) = self.runtime() else {
+            return false;
+        };
+
+        let message = tags.get("message").and_then(Value::as_str).unwrap_or_default().to_string();
+        let key = (metadata.callsite(), message);
+        let closes = match coalescer.observe(key.clone(), tags, self.clock.now()) {
+            Observed::First => return false,
+            Observed::Repeat => return true,
+            Observed::FirstRepeat(closes) => closes,
+        };
+
+        let size = ring::RECORD_OVERHEAD + ring::estimated_size(tags);
+        let queue = self.queue(metadata.level()).clone();
+        let in_flight = Arc::clone(&self.in_flight);
+        let ids = Arc::clone(&self.ids);
+        coalescer.schedule(key, closes, &self.in_flight, &runtime, move |repeats| {
+            let _ = queue.send(Queued {
+                event_id: ids.generate(IdKind::Event),
+                level: *metadata.level(),
+                timestamp: repeats.last_seen,
+                build: Box::new(move |appender| repeated_request(appender, metadata, repeats)),
+                attachments: Vec::new(),
+                log_id: None,
+                ticket: in_flight.start(),
+                size,
+                charge: None,
+            });
+        });
+        true
+    }
+
+    /// Adds a closing span to its trace, if it belongs to a sampled one, and submits the trace
+    /// document once all its spans are closed.
+    fn close_traced_span<S>(&self, id: &Id, ctx: &Context<'_, S>)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let Some(membership) = layer_scope::remove::<TraceMembership>(&mut span.extensions_mut(), self.id) else {
+            return;
+        };
+
+        let metadata = span.metadata();
+        let extensions = span.extensions();
+        let stored = layer_scope::get::<SpanFields>(&extensions, self.id);
+        let traced = TracedSpan {
+            span_id: membership.span_id,
+            parent_id: membership.parent_id,
+            name: metadata.name(),
+            target: metadata.target(),
+            level: metadata.level().to_string(),
+            start: clock::format_rfc3339(membership.started_at),
+            duration_ms: stored.map_or(0.0, |stored| stored.created_at.elapsed().as_secs_f64() * 1000.0),
+            fields: stored.map(|stored| stored.fields.clone()).unwrap_or_default(),
+        };
+
+        if let Some(document) = membership.trace.close(traced) {
+            let level = document
+                .spans
+                .iter()
+                .find(|span| span.parent_id.is_none())
+                .and_then(|root| root.level.parse().ok())
+                .unwrap_or(Level::INFO);
+            let size = ring::RECORD_OVERHEAD + document.estimated_size();
+            self.submit(&level, size, move |appender| trace_request(appender, document));
+        }
+    }
+
+    /// Submits a warning for a closing span if it lived longer than its budget.
+    fn check_span_budget<S>(&self, id: &Id, ctx: &Context<'_, S>)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if self.span_budgets.is_empty() {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let metadata = span.metadata();
+        let Some(budget) = self.span_budgets.budget_for(metadata.target(), metadata.name()) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(stored) = layer_scope::get::<SpanFields>(&extensions, self.id) else {
+            return;
+        };
+        let duration = stored.created_at.elapsed();
+        if duration <= budget || !self.accepts_at(metadata.target(), &Level::WARN) {
+            return;
+        }
+
+        let fields = to_value(&stored.fields).unwrap_or_else(|_| json!({}));
+        let span_id = id.into_u64();
+        let size = ring::RECORD_OVERHEAD + ring::estimated_size(&fields);
+        self.submit(&Level::WARN, size, move |appender| {
+            span_budget_request(appender, metadata, span_id, fields, duration, budget)
+        });
+    }
+
+    /// Submits a record for a span transition if `enabled` selects the span's target.
+    fn on_transition<S>(&self, id: &Id, ctx: Context<'_, S>, transition: &'static str, enabled: fn(&SpanEvents, &str) -> bool)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let Some(span) = ctx.span(id) else {
+            return;
         };
-            appender.log(log_request).await;
+
+        let metadata = span.metadata();
+        if !enabled(&self.span_events, metadata.target()) || !self.accepts(metadata) {
+            return;
+        }
+
+        let extensions = span.extensions();
+        let stored = layer_scope::get::<SpanFields>(&extensions, self.id);
+        let fields = stored
+            .and_then(|stored| to_value(&stored.fields).ok())
+            .unwrap_or_else(|| json!({}));
+        // Only the close record reports how long the span lived.
+        let duration = stored
+            .filter(|_| transition == "close")
+            .map(|stored| stored.created_at.elapsed());
+        let follows_from = stored
+            .map(|stored| stored.follows_from.clone())
+            .unwrap_or_default();
+        let span_id = id.into_u64();
+        let execution = ExecutionContext::current();
+
+        let size = ring::RECORD_OVERHEAD + ring::estimated_size(&fields);
+        self.submit(metadata.level(), size, move |appender| {
+            let mut request = span_transition_request(appender, metadata, span_id, transition, fields, duration, follows_from);
+            execution.insert_into(&mut request.data);
+            request
         });
     }
 }
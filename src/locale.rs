@@ -0,0 +1,196 @@
+//! Detection of the locale, region and timezone of the machine a service or client runs on.
+//!
+//! Player-facing issues are often specific to a language or a part of the world: a
+//! translation overflowing a button, a date parsed in the wrong format, a server region with
+//! high latency. Locale detection tags events (and optionally the session) with the user's
+//! locale, its region and the timezone offset, without instrumentation in the application.
+//!
+//! * The locale is read from `LC_ALL`, `LC_MESSAGES` or `LANG`, in that order, and on macOS
+//!   from the `AppleLocale` preference when none is set. `C` and `POSIX` are no locale.
+//! * The timezone is read from `TZ`, or from the zone `/etc/localtime` links to, and the UTC
+//!   offset from `date +%z`.
+//! * On Windows, the culture and timezone are read from PowerShell.
+//!
+//! Detection runs once, when the layer or appender is configured, so the offset of a session
+//! that runs across a daylight saving change is the one it started with.
+
+use serde_json::Value;
+#[cfg(not(target_os = "windows"))]
+use std::env;
+use std::process::Command;
+
+/// The locale, region and timezone of the machine.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::LocaleInfo;
+///
+/// let locale = LocaleInfo::detect();
+/// println!("{:?} in {:?}, UTC{:?}", locale.locale(), locale.region(), locale.utc_offset());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LocaleInfo {
+    /// BCP 47 language tag, e.g. `en-US`.
+    locale: Option<String>,
+    /// Name of the timezone, e.g. `Europe/Berlin`.
+    timezone: Option<String>,
+    /// Offset from UTC, in minutes east of Greenwich.
+    utc_offset_minutes: Option<i32>,
+}
+
+impl LocaleInfo {
+    /// Detects the locale, timezone and UTC offset of this machine.
+    ///
+    /// What cannot be detected is left out; detection never fails.
+    pub fn detect() -> Self {
+        #[cfg(target_os = "windows")]
+        {
+            windows::detect()
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            let timezone = unix_timezone();
+            LocaleInfo {
+                locale: unix_locale(),
+                utc_offset_minutes: match timezone.as_deref() {
+                    Some("UTC" | "Etc/UTC" | "UTC0" | "GMT" | "Etc/GMT") => Some(0),
+                    _ => unix_utc_offset(),
+                },
+                timezone,
+            }
+        }
+    }
+
+    /// Returns the locale as a BCP 47 language tag, e.g. `en-US`, if known.
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Returns the region of the locale, e.g. `US` for `en-US`, if it has one.
+    pub fn region(&self) -> Option<&str> {
+        self.locale
+            .as_deref()?
+            .split('-')
+            .skip(1)
+            .find(|subtag| {
+                (subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_uppercase()))
+                    || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()))
+            })
+    }
+
+    /// Returns the name of the timezone, e.g. `Europe/Berlin`, if known.
+    pub fn timezone(&self) -> Option<&str> {
+        self.timezone.as_deref()
+    }
+
+    /// Returns the offset from UTC in minutes, positive east of Greenwich, if known.
+    pub fn utc_offset_minutes(&self) -> Option<i32> {
+        self.utc_offset_minutes
+    }
+
+    /// Returns the offset from UTC as in RFC 3339, e.g. `+02:00` or `-05:30`, if known.
+    pub fn utc_offset(&self) -> Option<String> {
+        let minutes = self.utc_offset_minutes?;
+        let sign = if minutes < 0 { '-' } else { '+' };
+        Some(format!("{}{:02}:{:02}", sign, minutes.abs() / 60, minutes.abs() % 60))
+    }
+
+    /// Returns the `locale`, `region`, `timezone` and `utc_offset` tags, for what is known.
+    pub(crate) fn tags(&self) -> Vec<(String, Value)> {
+        [
+            ("locale", self.locale().map(str::to_string)),
+            ("region", self.region().map(str::to_string)),
+            ("timezone", self.timezone().map(str::to_string)),
+            ("utc_offset", self.utc_offset()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.to_string(), Value::from(value?))))
+        .collect()
+    }
+}
+
+/// Converts a POSIX locale name, e.g. `en_US.UTF-8` or `sr_RS@latin`, to a language tag.
+#[cfg(not(target_os = "windows"))]
+fn language_tag(posix: &str) -> Option<String> {
+    let name = posix.split(['.', '@']).next()?.trim();
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return None;
+    }
+    Some(name.replace('_', "-"))
+}
+
+/// Runs a command and returns its trimmed output, if it succeeds with some.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !stdout.is_empty()).then_some(stdout)
+}
+
+/// Reads the locale from the environment, or from the user's preferences on macOS.
+#[cfg(not(target_os = "windows"))]
+fn unix_locale() -> Option<String> {
+    let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()));
+    match from_env {
+        Some(value) => language_tag(&value),
+        None if cfg!(target_os = "macos") => language_tag(&command_output("defaults", &["read", "-g", "AppleLocale"])?),
+        None => None,
+    }
+}
+
+/// Reads the timezone from `TZ`, or from the zone `/etc/localtime` links to.
+#[cfg(not(target_os = "windows"))]
+fn unix_timezone() -> Option<String> {
+    if let Ok(tz) = env::var("TZ") {
+        let tz = tz.trim_start_matches(':');
+        if !tz.is_empty() {
+            return Some(tz.to_string());
+        }
+    }
+    let target = std::fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_string_lossy();
+    let (_, zone) = target.split_once("zoneinfo/")?;
+    Some(zone.to_string())
+}
+
+/// Reads the current UTC offset from `date +%z`, e.g. `+0200`.
+#[cfg(not(target_os = "windows"))]
+fn unix_utc_offset() -> Option<i32> {
+    let offset = command_output("date", &["+%z"])?;
+    let (sign, digits) = match (offset.strip_prefix('+'), offset.strip_prefix('-')) {
+        (Some(digits), _) => (1, digits),
+        (_, Some(digits)) => (-1, digits),
+        _ => return None,
+    };
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::{command_output, LocaleInfo};
+
+    /// Prints the culture, the timezone ID and the current UTC offset in minutes, one per line.
+    const SCRIPT: &str = "(Get-Culture).Name; (Get-TimeZone).Id; \
+        [TimeZoneInfo]::Local.GetUtcOffset([DateTime]::Now).TotalMinutes";
+
+    /// Reads the culture and timezone from PowerShell.
+    pub(super) fn detect() -> LocaleInfo {
+        let Some(output) = command_output("powershell", &["-NoProfile", "-NonInteractive", "-Command", SCRIPT]) else {
+            return LocaleInfo::default();
+        };
+        let mut lines = output.lines().map(str::trim);
+        let mut next = || lines.next().filter(|line| !line.is_empty()).map(str::to_string);
+        LocaleInfo {
+            locale: next(),
+            timezone: next(),
+            utc_offset_minutes: next().and_then(|minutes| minutes.parse::<f64>().ok()).map(|minutes| minutes as i32),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Correlation of submitted records with the log IDs POGR assigns to them.
+//!
+//! The intake answers every accepted submission with the `log_id` of the stored record.
+//! With log ID tracking enabled on `PogrLayer`, the IDs of events recorded inside a span are
+//! kept in that span's extensions, so application code can look them up with
+//! `span_log_ids`, e.g. to put a link to the backend record into a support ticket.
+
+use crate::layer_scope;
+use std::sync::{Arc, OnceLock};
+use tracing::Span;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Registry;
+use uuid::Uuid;
+
+/// Maximum number of log IDs kept per span; the oldest are discarded beyond that.
+pub(crate) const MAX_LOG_IDS_PER_SPAN: usize = 1000;
+
+/// A submitted record and the log ID POGR assigned to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogReference {
+    /// The client-generated identifier of the record.
+    pub event_id: Uuid,
+    /// The identifier the POGR intake assigned to the stored record.
+    pub log_id: String,
+}
+
+/// Receives the reference of a record once the intake has accepted it.
+pub(crate) type LogIdSlot = Arc<OnceLock<LogReference>>;
+
+/// Slots of the events recorded inside a span, stored in the span's extensions.
+#[derive(Default)]
+pub(crate) struct SpanLogIds {
+    /// One slot per event, oldest first.
+    pub(crate) slots: Vec<LogIdSlot>,
+}
+
+impl SpanLogIds {
+    /// Adds the slot of a newly captured event, discarding the oldest beyond the limit.
+    pub(crate) fn push(&mut self, slot: LogIdSlot) {
+        if self.slots.len() >= MAX_LOG_IDS_PER_SPAN {
+            self.slots.remove(0);
+        }
+        self.slots.push(slot);
+    }
+}
+
+/// Returns the log IDs POGR assigned to events recorded directly inside `span`.
+///
+/// Only events captured while log ID tracking was enabled with
+/// `PogrLayer::with_log_id_tracking`, submitted in a request of their own and already
+/// accepted by the intake are included, oldest first, and with several layers, those of each
+/// layer in turn. Flush the layer first to include the latest events. The subscriber must be
+/// built on `tracing_subscriber::Registry`; otherwise the result is always empty.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::span_log_ids;
+/// use tracing::{error, info_span};
+///
+/// let span = info_span!("checkout");
+/// span.in_scope(|| error!("Payment declined"));
+///
+/// // After the layer has been flushed:
+/// for reference in span_log_ids(&span) {
+///     println!("https://pogr.io/logs/{}", reference.log_id);
+/// }
+/// ```
+pub fn span_log_ids(span: &Span) -> Vec<LogReference> {
+    span.with_subscriber(|(id, dispatch)| {
+        let registry = dispatch.downcast_ref::<Registry>()?;
+        let span = registry.span(id)?;
+        let extensions = span.extensions();
+        let references = layer_scope::all::<SpanLogIds>(&extensions)
+            .flat_map(|log_ids| log_ids.slots.iter().filter_map(|slot| slot.get().cloned()))
+            .collect();
+        Some(references)
+    })
+    .flatten()
+    .unwrap_or_default()
+}
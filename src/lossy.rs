@@ -0,0 +1,140 @@
+//! Degrading values that cannot be serialized, instead of losing their events.
+//!
+//! Telemetry is most valuable exactly when something is off, which is also when values turn
+//! odd: a ratio divided by zero is `NaN`, a `Debug` implementation of a half-torn-down object
+//! returns an error or panics. Rather than dropping such an event or panicking inside the
+//! subscriber, the offending value is recorded in a degraded form, e.g. `NaN` as the string
+//! `"NaN"` or the output a failing `Debug` implementation produced before it failed, and the
+//! event gets a `serialization_errors` field noting, per field, what went wrong:
+//!
+//! ```text
+//! { "ratio": "NaN", "serialization_errors": { "ratio": "non-finite number" } }
+//! ```
+//!
+//! A record whose payload cannot be serialized as a whole is submitted once more with its data
+//! and tags replaced by their `Debug` output, noted under the `record` key.
+
+use crate::debug_cap;
+use crate::pipeline::LogRecord;
+use crate::LogRequest;
+use serde_json::{json, Map, Value};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::OnceLock;
+
+/// Field noting why the values of other fields, or the record itself, were degraded.
+pub const SERIALIZATION_ERRORS_FIELD: &str = "serialization_errors";
+
+/// Recorded in place of the output of a `Debug` or `Display` implementation that panicked.
+const UNFORMATTABLE: &str = "<unformattable>";
+
+/// Returns a finite `value` as a number, or a non-finite one as its `Debug` output with a note.
+pub(crate) fn float(value: f64) -> (Value, Option<String>) {
+    if value.is_finite() {
+        (json!(value), None)
+    } else {
+        (json!(format!("{:?}", value)), Some("non-finite number".to_string()))
+    }
+}
+
+/// Formats `value` with `Debug`, keeping at most `max_length` bytes of the output if capped.
+///
+/// If the implementation returns an error, the output written until then is returned with a
+/// note; if it panics, a placeholder is.
+pub(crate) fn debug(value: &dyn fmt::Debug, max_length: Option<usize>) -> (String, Option<String>) {
+    let formatted = panic::catch_unwind(AssertUnwindSafe(|| match max_length {
+        Some(max) => debug_cap::format_capped(value, max),
+        None => {
+            let mut formatted = String::new();
+            match write!(formatted, "{:?}", value) {
+                Ok(()) => Ok(formatted),
+                Err(_) => Err(formatted),
+            }
+        }
+    }));
+    match formatted {
+        Ok(Ok(formatted)) => (formatted, None),
+        Ok(Err(partial)) => (partial, Some("the Debug implementation returned an error".to_string())),
+        Err(payload) => (
+            UNFORMATTABLE.to_string(),
+            Some(format!("the Debug implementation panicked: {}", panic_message(&*payload))),
+        ),
+    }
+}
+
+/// Formats `value` with `Display`, like `debug`.
+pub(crate) fn display(value: &dyn fmt::Display) -> (String, Option<String>) {
+    let formatted = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut formatted = String::new();
+        match write!(formatted, "{}", value) {
+            Ok(()) => Ok(formatted),
+            Err(_) => Err(formatted),
+        }
+    }));
+    match formatted {
+        Ok(Ok(formatted)) => (formatted, None),
+        Ok(Err(partial)) => (partial, Some("the Display implementation returned an error".to_string())),
+        Err(payload) => (
+            UNFORMATTABLE.to_string(),
+            Some(format!("the Display implementation panicked: {}", panic_message(&*payload))),
+        ),
+    }
+}
+
+/// Notes in `fields` why the value of `field` was degraded.
+pub(crate) fn note(fields: &mut HashMap<String, Value>, field: &str, error: String) {
+    let notes = fields
+        .entry(SERIALIZATION_ERRORS_FIELD.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(notes) = notes {
+        notes.insert(field.to_string(), Value::String(error));
+    }
+}
+
+/// Returns a copy of a record whose payload could not be serialized, with its data and the
+/// values of its tags replaced by their `Debug` output, without its attachments, and with
+/// `error` noted in its tags.
+pub(crate) fn degrade(record: &LogRecord, error: &str) -> LogRecord {
+    let mut tags: Map<String, Value> = match &record.request.tags {
+        Value::Object(tags) => tags
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::String(_) => value.clone(),
+                    value => Value::String(format!("{:?}", value)),
+                };
+                (name.clone(), value)
+            })
+            .collect(),
+        tags => Map::from_iter([("tags".to_string(), Value::String(format!("{:?}", tags)))]),
+    };
+    tags.insert(SERIALIZATION_ERRORS_FIELD.to_string(), json!({ "record": error }));
+
+    LogRecord {
+        event_id: record.event_id,
+        timestamp: record.timestamp,
+        request: LogRequest {
+            service: record.request.service.clone(),
+            environment: record.request.environment.clone(),
+            severity: record.request.severity.clone(),
+            r#type: record.request.r#type.clone(),
+            log: record.request.log.clone(),
+            data: Value::String(format!("{:?}", record.request.data)),
+            tags: Value::Object(tags),
+        },
+        attachments: Vec::new(),
+        validation_error: None,
+        quarantine_error: OnceLock::new(),
+    }
+}
+
+/// Returns the message of a panic, if it has one.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message")
+}
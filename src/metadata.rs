@@ -0,0 +1,102 @@
+//! Identification of the client in requests to POGR.
+//!
+//! Every request to the init and logs endpoints carries a `User-Agent` naming this crate and
+//! its version, the operating system and the CPU architecture, so that the backend and the
+//! proxies in front of it can tell client versions apart when debugging. Applications can
+//! add their own details, such as a build number or a region, with `set_client_metadata`;
+//! they are sent as `pogr-client-<key>` headers.
+
+use reqwest::header::{HeaderName, HeaderValue, USER_AGENT};
+use reqwest::RequestBuilder;
+use std::error::Error;
+use std::fmt;
+use std::sync::RwLock;
+
+/// Prefix of the client metadata header names.
+const HEADER_PREFIX: &str = "pogr-client-";
+
+/// Client metadata headers added to every request.
+static METADATA: RwLock<Vec<(HeaderName, HeaderValue)>> = RwLock::new(Vec::new());
+
+/// Returns the `User-Agent` sent with every request, e.g.
+/// `pogr_tracing_rs/0.0.35 (linux; x86_64)`.
+pub(crate) fn user_agent() -> String {
+    format!(
+        "{}/{} ({}; {})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+/// Adds a client metadata header sent with every request to POGR, replacing an earlier
+/// value for the same key.
+///
+/// The header is named `pogr-client-<key>`, with the key in lowercase.
+///
+/// # Arguments
+///
+/// * `key` - The name of the detail, made of ASCII letters, digits and `-`, e.g. `build`.
+/// * `value` - The value, made of visible ASCII characters and spaces.
+///
+/// # Returns
+///
+/// An error if the key or the value cannot be sent in a header.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::set_client_metadata;
+///
+/// set_client_metadata("build", "2024.03.1-4711").unwrap();
+/// set_client_metadata("region", "eu-west").unwrap();
+/// ```
+pub fn set_client_metadata(key: &str, value: &str) -> Result<(), InvalidClientMetadata> {
+    let invalid = || InvalidClientMetadata {
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(invalid());
+    }
+    let name = HeaderName::from_bytes(format!("{}{}", HEADER_PREFIX, key.to_ascii_lowercase()).as_bytes())
+        .map_err(|_| invalid())?;
+    let value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+
+    let mut metadata = METADATA.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    metadata.retain(|(existing, _)| *existing != name);
+    metadata.push((name, value));
+    Ok(())
+}
+
+/// Removes all client metadata headers added with `set_client_metadata`.
+pub fn clear_client_metadata() {
+    METADATA.write().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+}
+
+/// Adds the `User-Agent` and the client metadata headers to a request.
+pub(crate) fn apply(mut request: RequestBuilder) -> RequestBuilder {
+    request = request.header(USER_AGENT, user_agent());
+    for (name, value) in METADATA.read().unwrap_or_else(|poisoned| poisoned.into_inner()).iter() {
+        request = request.header(name.clone(), value.clone());
+    }
+    request
+}
+
+/// Error returned when client metadata cannot be sent in a header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidClientMetadata {
+    /// The rejected key.
+    key: String,
+    /// The rejected value.
+    value: String,
+}
+
+impl fmt::Display for InvalidClientMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid client metadata `{}`: `{}`", self.key, self.value)
+    }
+}
+
+impl Error for InvalidClientMetadata {}
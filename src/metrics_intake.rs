@@ -0,0 +1,83 @@
+//! Reporting metrics to POGR's metrics intake.
+//!
+//! Metrics are sent in the session of a `PogrAppender`, as a JSON array of reports:
+//!
+//! ```text
+//! POST /v1/intake/metrics
+//! INTAKE_SESSION_ID: ...
+//!
+//! [{"name": "matches_started", "type": "counter", "labels": {"mode": "ranked"}, "value": 12, ...}]
+//! ```
+//!
+//! Both the `metrics` recorder and the span latency summaries report through this intake.
+
+use crate::{http_dump, PogrAppender};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::env;
+
+/// Default metrics intake, unless `POGR_METRICS_ENDPOINT` is set.
+const DEFAULT_METRICS_ENDPOINT: &str = "https://api.pogr.io/v1/intake/metrics";
+
+/// A metric as sent to the metrics intake.
+#[derive(Serialize)]
+pub(crate) struct MetricReport {
+    /// Name of the metric.
+    pub(crate) name: String,
+    /// `counter`, `gauge`, `histogram` or `summary`.
+    pub(crate) r#type: &'static str,
+    /// Labels of the metric.
+    pub(crate) labels: Map<String, Value>,
+    /// Total of a counter or value of a gauge.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) value: Option<f64>,
+    /// Values recorded in a histogram since the previous report.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) values: Option<Vec<f64>>,
+    /// Number of values a summary was computed from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) count: Option<u64>,
+    /// Quantiles of a summary, e.g. `p99`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) quantiles: Option<BTreeMap<&'static str, f64>>,
+    /// Unit of the values, e.g. `milliseconds`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) unit: Option<&'static str>,
+    /// What the metric measures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    /// Name of the service reporting the metric.
+    pub(crate) service: String,
+    /// Deployment environment of the service.
+    pub(crate) environment: String,
+    /// When the values were read, as an RFC 3339 UTC timestamp.
+    pub(crate) timestamp: String,
+}
+
+/// The response of the metrics intake.
+#[derive(Deserialize)]
+struct MetricsResponse {
+    /// Indicates whether the metrics were accepted.
+    success: bool,
+}
+
+/// Returns the metrics intake from `POGR_METRICS_ENDPOINT`, or POGR's.
+pub(crate) fn endpoint_from_env() -> String {
+    env::var("POGR_METRICS_ENDPOINT").unwrap_or_else(|_| DEFAULT_METRICS_ENDPOINT.to_string())
+}
+
+/// Sends a report to the metrics intake, in the appender's session.
+pub(crate) async fn send(appender: &PogrAppender, endpoint: &str, reports: &[MetricReport]) -> Result<(), String> {
+    let client = &appender.client;
+    let request = client
+        .post(endpoint)
+        .header("INTAKE_SESSION_ID", &appender.session_id)
+        .json(reports);
+    let (status, body) = http_dump::send(client, request).await.map_err(|err| err.to_string())?;
+
+    match serde_json::from_slice::<MetricsResponse>(&body) {
+        Ok(response) if response.success => Ok(()),
+        _ => Err(format!("{}: {}", status, String::from_utf8_lossy(&body))),
+    }
+}
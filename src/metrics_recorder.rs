@@ -0,0 +1,359 @@
+//! A `metrics` recorder forwarding to POGR's metrics intake.
+//!
+//! Applications instrumented with the `metrics` crate's `counter!`, `gauge!` and
+//! `histogram!` macros get POGR support by installing `PogrRecorder` as their recorder. The
+//! recorder keeps the current value of every metric in memory and reports them periodically
+//! in the session of a `PogrAppender`:
+//!
+//! ```text
+//! POST /v1/intake/metrics
+//! INTAKE_SESSION_ID: ...
+//!
+//! [{"name": "matches_started", "type": "counter", "labels": {"mode": "ranked"}, "value": 12, ...}]
+//! ```
+//!
+//! Counters are reported with their running total and gauges with their latest value, so a
+//! lost report does not skew them. Histograms are reported with the values recorded since the
+//! previous report, and left out of reports while they have none.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::metrics_intake::{self, MetricReport};
+use crate::{clock, PogrAppender};
+use metrics::{Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SetRecorderError, SharedString, Unit};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Default time between two reports.
+pub const DEFAULT_METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A `metrics::Recorder` that reports metrics to POGR.
+///
+/// Available with the `metrics` feature.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrRecorder};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let metrics = PogrRecorder::new(appender)
+///     .with_interval(Duration::from_secs(30))
+///     .install()
+///     .expect("no other recorder is installed");
+///
+/// metrics::counter!("matches_started", "mode" => "ranked").increment(1);
+/// metrics::histogram!("queue_time_ms").record(812.5);
+///
+/// // Report the last values before exiting.
+/// metrics.flush().await;
+/// # }
+/// ```
+pub struct PogrRecorder {
+    /// The metrics and where they are reported.
+    state: Arc<MetricsState>,
+    /// Time between two reports.
+    interval: Duration,
+}
+
+/// A handle to the metrics of a `PogrRecorder`, used to report them on demand.
+#[derive(Clone)]
+pub struct PogrMetricsHandle {
+    /// The metrics and where they are reported.
+    state: Arc<MetricsState>,
+}
+
+/// The metrics registered with a recorder, and where they are reported.
+struct MetricsState {
+    /// Appender whose session and client are used for the reports.
+    appender: PogrAppender,
+    /// URL of the metrics intake.
+    endpoint: String,
+    /// The registered metrics.
+    metrics: Mutex<HashMap<Key, Metric>>,
+    /// Units and descriptions of the metrics, by name.
+    descriptions: Mutex<HashMap<String, Description>>,
+    /// Serializes reports, so histogram values are reported exactly once and in order.
+    reporting: tokio::sync::Mutex<()>,
+}
+
+/// A registered metric.
+#[derive(Clone)]
+enum Metric {
+    /// A counter.
+    Counter(Arc<CounterValue>),
+    /// A gauge.
+    Gauge(Arc<GaugeValue>),
+    /// A histogram.
+    Histogram(Arc<HistogramValues>),
+}
+
+/// The unit and description of a metric.
+struct Description {
+    /// The unit of the metric's values.
+    unit: Option<Unit>,
+    /// What the metric measures.
+    description: String,
+}
+
+/// The running total of a counter.
+#[derive(Default)]
+struct CounterValue(AtomicU64);
+
+impl CounterFn for CounterValue {
+    fn increment(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.0.fetch_max(value, Ordering::Relaxed);
+    }
+}
+
+/// The latest value of a gauge, stored as the bits of an `f64`.
+#[derive(Default)]
+struct GaugeValue(AtomicU64);
+
+impl GaugeValue {
+    /// Applies a change to the value.
+    fn update(&self, change: impl Fn(f64) -> f64) {
+        let _ = self.0.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some(change(f64::from_bits(bits)).to_bits())
+        });
+    }
+
+    /// Returns the value.
+    fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+impl GaugeFn for GaugeValue {
+    fn increment(&self, value: f64) {
+        self.update(|current| current + value);
+    }
+
+    fn decrement(&self, value: f64) {
+        self.update(|current| current - value);
+    }
+
+    fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// The values recorded in a histogram since the previous report.
+#[derive(Default)]
+struct HistogramValues(Mutex<Vec<f64>>);
+
+impl HistogramFn for HistogramValues {
+    fn record(&self, value: f64) {
+        self.0.lock().unwrap_or_else(|p| p.into_inner()).push(value);
+    }
+}
+
+impl PogrRecorder {
+    /// Creates a recorder that reports metrics in the appender's session.
+    ///
+    /// The metrics intake is taken from `POGR_METRICS_ENDPOINT`, or defaults to POGR's.
+    pub fn new(appender: PogrAppender) -> Self {
+        PogrRecorder {
+            state: Arc::new(MetricsState {
+                appender,
+                endpoint: metrics_intake::endpoint_from_env(),
+                metrics: Mutex::new(HashMap::new()),
+                descriptions: Mutex::new(HashMap::new()),
+                reporting: tokio::sync::Mutex::new(()),
+            }),
+            interval: DEFAULT_METRICS_INTERVAL,
+        }
+    }
+
+    /// Sets the URL of the metrics intake.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after `handle`, while the handle is still alive.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        Arc::get_mut(&mut self.state)
+            .expect("the endpoint must be set before the recorder is shared")
+            .endpoint = endpoint.into();
+        self
+    }
+
+    /// Sets the time between two reports. Defaults to `DEFAULT_METRICS_INTERVAL`.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns a handle for reporting the recorder's metrics on demand.
+    pub fn handle(&self) -> PogrMetricsHandle {
+        PogrMetricsHandle {
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    /// Installs the recorder as the global `metrics` recorder and starts reporting the
+    /// metrics every interval.
+    ///
+    /// # Returns
+    ///
+    /// A handle for reporting the metrics on demand, or an error if another recorder was
+    /// installed before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn install(self) -> Result<PogrMetricsHandle, SetRecorderError<Self>> {
+        let handle = self.handle();
+        let interval = self.interval;
+        metrics::set_global_recorder(self)?;
+        tokio::spawn(handle.clone().report_every(interval));
+        Ok(handle)
+    }
+
+    /// Returns the registered metric for the key, registering it with `new` if needed.
+    fn register(&self, key: &Key, new: impl FnOnce() -> Metric) -> Metric {
+        self.state
+            .metrics
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .entry(key.clone())
+            .or_insert_with(new)
+            .clone()
+    }
+
+    /// Keeps the unit and description of a metric.
+    fn describe(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.state.descriptions.lock().unwrap_or_else(|p| p.into_inner()).insert(
+            key.as_str().to_string(),
+            Description {
+                unit,
+                description: description.into_owned(),
+            },
+        );
+    }
+}
+
+impl Recorder for PogrRecorder {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        match self.register(key, || Metric::Counter(Arc::default())) {
+            Metric::Counter(counter) => Counter::from_arc(counter),
+            // The name is already used by a metric of another type.
+            _ => Counter::noop(),
+        }
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        match self.register(key, || Metric::Gauge(Arc::default())) {
+            Metric::Gauge(gauge) => Gauge::from_arc(gauge),
+            _ => Gauge::noop(),
+        }
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        match self.register(key, || Metric::Histogram(Arc::default())) {
+            Metric::Histogram(histogram) => Histogram::from_arc(histogram),
+            _ => Histogram::noop(),
+        }
+    }
+}
+
+impl PogrMetricsHandle {
+    /// Reports the current values of all metrics now.
+    ///
+    /// Failures are reported through the diagnostic handler; the histogram values of a
+    /// failed report are lost.
+    pub async fn flush(&self) {
+        let _reporting = self.state.reporting.lock().await;
+        let reports = self.state.snapshot();
+        if reports.is_empty() {
+            return;
+        }
+
+        if let Err(message) = metrics_intake::send(&self.state.appender, &self.state.endpoint, &reports).await {
+            diagnostics::error(DiagnosticKind::Delivery, format!("Failed to report metrics to POGR: {}", message));
+        }
+    }
+
+    /// Reports the metrics every `interval`, for as long as the runtime runs.
+    async fn report_every(self, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        // The first tick completes immediately, before anything was recorded.
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            self.flush().await;
+        }
+    }
+}
+
+impl MetricsState {
+    /// Reads the values of all metrics, taking the values recorded in histograms.
+    fn snapshot(&self) -> Vec<MetricReport> {
+        let metrics: Vec<(Key, Metric)> = self
+            .metrics
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .iter()
+            .map(|(key, metric)| (key.clone(), metric.clone()))
+            .collect();
+        let descriptions = self.descriptions.lock().unwrap_or_else(|p| p.into_inner());
+        let timestamp = clock::format_rfc3339(SystemTime::now());
+
+        metrics
+            .into_iter()
+            .filter_map(|(key, metric)| {
+                let (r#type, value, values) = match metric {
+                    Metric::Counter(counter) => ("counter", Some(counter.0.load(Ordering::Relaxed) as f64), None),
+                    Metric::Gauge(gauge) => ("gauge", Some(gauge.get()), None),
+                    Metric::Histogram(histogram) => {
+                        let values = std::mem::take(&mut *histogram.0.lock().unwrap_or_else(|p| p.into_inner()));
+                        if values.is_empty() {
+                            return None;
+                        }
+                        ("histogram", None, Some(values))
+                    }
+                };
+                let description = descriptions.get(key.name());
+
+                Some(MetricReport {
+                    name: key.name().to_string(),
+                    r#type,
+                    labels: key
+                        .labels()
+                        .map(|label| (label.key().to_string(), Value::from(label.value())))
+                        .collect(),
+                    value,
+                    values,
+                    count: None,
+                    quantiles: None,
+                    unit: description.and_then(|description| description.unit).map(|unit| unit.as_str()),
+                    description: description
+                        .map(|description| description.description.clone())
+                        .filter(|description| !description.is_empty()),
+                    service: self.appender.service_name.clone(),
+                    environment: self.appender.environment.clone(),
+                    timestamp: timestamp.clone(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,377 @@
+//! A sink publishing records to an MQTT broker.
+//!
+//! Field hardware such as arcade cabinets and kiosks often only reaches the outside world
+//! through an MQTT broker. This sink speaks MQTT 3.1.1 directly over TCP, so those devices can
+//! feed POGR through a broker-side bridge without an extra client library. Records are
+//! collected while a batch is written and published when the sink is flushed; with QoS 1 or
+//! 2, a batch counts as published once the broker has acknowledged every record.
+//!
+//! Publishing runs on a dedicated thread, because connecting and waiting for acknowledgements
+//! must not block the async delivery worker: a slow or unreachable broker would otherwise
+//! stall delivery to POGR. The connection is plaintext only; there is no TLS support.
+
+use crate::sink::{Sink, SinkRecord, SinkThread};
+use std::collections::HashSet;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How long to wait for the broker when connecting and flushing.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// MQTT control packet types.
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const PUBACK: u8 = 4;
+const PUBREC: u8 = 5;
+const PUBREL: u8 = 6;
+const PUBCOMP: u8 = 7;
+
+/// MQTT delivery guarantee for published records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MqttQos {
+    /// QoS 0: records are sent once and not acknowledged.
+    AtMostOnce,
+    /// QoS 1: records are acknowledged by the broker and may be delivered twice.
+    #[default]
+    AtLeastOnce,
+    /// QoS 2: records are delivered exactly once, at the cost of an extra round trip.
+    ExactlyOnce,
+}
+
+/// A `Sink` that publishes records to MQTT topics derived from each record.
+///
+/// The topic is built from a template with these placeholders:
+///
+/// * `{target}` - the event's target, with `::` replaced by `/`, e.g. `game/matchmaking`.
+/// * `{level}` - the event's severity in lower case, e.g. `warn`.
+/// * `{service}` and `{environment}` - the appender's service name and environment.
+///
+/// Whitespace, `+`, `#` and `/` in the placeholder values are replaced with `_`, so each value
+/// stays a single topic level.
+///
+/// Flushing hands the batch to a publishing thread and returns without waiting for the
+/// broker. Up to 16 batches are queued; while the thread is that far behind, further batches
+/// are dropped and the flush fails. Failures to publish are reported as `Sink` diagnostics.
+///
+/// The connection is opened for the first batch, with a clean session and keep-alive
+/// disabled. If a reused connection fails, for example because the broker dropped it, the
+/// batch is published once more on a new one.
+///
+/// Records and credentials are sent in plaintext, since TLS is not supported; connect to a
+/// local broker or a TLS-terminating proxy.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{MqttQos, MqttSink, PogrAppender, PogrLayer, SinkMode};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let mqtt = MqttSink::new("localhost:1883", "arcade/cabinet-17/{level}")
+///     .with_qos(MqttQos::AtLeastOnce)
+///     .with_client_id("cabinet-17");
+///
+/// let layer = PogrLayer::new(appender)
+///     .with_intake_delivery(false)
+///     .with_sink(mqtt, SinkMode::Mirror);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MqttSink {
+    /// The broker the records are published to.
+    broker: Broker,
+    /// Topic template.
+    topic: String,
+    /// Topics and payloads written since the last flush.
+    pending: Mutex<Vec<(String, String)>>,
+    /// Publishes the flushed batches, owning the connection.
+    publisher: SinkThread<Vec<(String, String)>>,
+}
+
+/// How to reach the broker, and how records are published to it.
+#[derive(Clone, Debug)]
+struct Broker {
+    /// Address of the broker, e.g. `localhost:1883`.
+    address: String,
+    /// Delivery guarantee.
+    qos: MqttQos,
+    /// Whether the broker retains the last record on each topic.
+    retain: bool,
+    /// Client identifier sent to the broker.
+    client_id: String,
+    /// User name and password, if the broker requires them.
+    credentials: Option<(String, String)>,
+}
+
+/// An open connection to the broker.
+#[derive(Debug)]
+struct Connection {
+    /// Reads packets from the broker.
+    reader: BufReader<TcpStream>,
+    /// Buffers packets for the broker.
+    writer: BufWriter<TcpStream>,
+    /// Identifier of the next QoS 1 or 2 publish.
+    next_packet_id: u16,
+}
+
+impl MqttSink {
+    /// Creates a sink publishing to the broker at `address`, using `topic` as the topic
+    /// template.
+    ///
+    /// Records are published with QoS 1 under a random client identifier.
+    pub fn new(address: impl Into<String>, topic: impl Into<String>) -> Self {
+        MqttSink {
+            broker: Broker {
+                address: address.into(),
+                qos: MqttQos::default(),
+                retain: false,
+                client_id: format!("pogr-{}", Uuid::now_v7().simple()),
+                credentials: None,
+            },
+            topic: topic.into(),
+            pending: Mutex::new(Vec::new()),
+            publisher: SinkThread::new("pogr-mqtt-publisher"),
+        }
+    }
+
+    /// Sets the delivery guarantee.
+    pub fn with_qos(mut self, qos: MqttQos) -> Self {
+        self.broker.qos = qos;
+        self
+    }
+
+    /// Asks the broker to retain the last record on each topic for new subscribers.
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.broker.retain = retain;
+        self
+    }
+
+    /// Sets the client identifier, which must be unique per broker.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.broker.client_id = client_id.into();
+        self
+    }
+
+    /// Authenticates with a user name and password.
+    pub fn with_user_password(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.broker.credentials = Some((user.into(), password.into()));
+        self
+    }
+}
+
+impl Broker {
+
+    /// Connects to the broker and waits for it to accept the session.
+    fn connect(&self) -> io::Result<Connection> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "address did not resolve");
+        for address in self.address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, TIMEOUT) {
+                Ok(stream) => return self.handshake(stream),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Sends `CONNECT` and reads the broker's `CONNACK`.
+    fn handshake(&self, stream: TcpStream) -> io::Result<Connection> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut connection = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            next_packet_id: 1,
+        };
+
+        // Clean session, plus the user name and password flags if credentials are set.
+        let mut flags = 0x02;
+        if self.credentials.is_some() {
+            flags |= 0x80 | 0x40;
+        }
+
+        let mut body = Vec::new();
+        put_string(&mut body, "MQTT");
+        body.push(4);
+        body.push(flags);
+        // Keep-alive disabled, since the connection is idle between batches.
+        body.extend_from_slice(&0u16.to_be_bytes());
+        put_string(&mut body, &self.client_id);
+        if let Some((user, password)) = &self.credentials {
+            put_string(&mut body, user);
+            put_string(&mut body, password);
+        }
+        connection.send(CONNECT << 4, &body)?;
+        connection.writer.flush()?;
+
+        let (kind, body) = connection.receive()?;
+        if kind != CONNACK || body.len() != 2 {
+            return Err(protocol_error(format!("expected CONNACK, got packet type {}", kind)));
+        }
+        if body[1] != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("MQTT broker refused the connection with return code {}", body[1]),
+            ));
+        }
+
+        Ok(connection)
+    }
+
+    /// Publishes a batch, once more on a new connection if a reused one fails.
+    fn deliver(&self, slot: &mut Option<Connection>, messages: &[(String, String)]) -> io::Result<()> {
+        let reused = slot.is_some();
+        match self.publish(slot, messages) {
+            Err(_) if reused => self.publish(slot, messages),
+            result => result,
+        }
+    }
+
+    /// Publishes messages on the open connection, connecting first if needed.
+    ///
+    /// The connection is closed if publishing fails.
+    fn publish(&self, slot: &mut Option<Connection>, messages: &[(String, String)]) -> io::Result<()> {
+        let connection = match slot {
+            Some(connection) => connection,
+            None => slot.insert(self.connect()?),
+        };
+
+        let result = connection.publish(messages, self.qos, self.retain);
+        if result.is_err() {
+            *slot = None;
+        }
+        result
+    }
+}
+
+impl Connection {
+    /// Publishes messages and waits until the broker has acknowledged them.
+    fn publish(&mut self, messages: &[(String, String)], qos: MqttQos, retain: bool) -> io::Result<()> {
+        let mut unacknowledged = HashSet::new();
+
+        for (topic, payload) in messages {
+            let mut body = Vec::with_capacity(topic.len() + payload.len() + 4);
+            put_string(&mut body, topic);
+            if qos != MqttQos::AtMostOnce {
+                let packet_id = self.next_packet_id();
+                body.extend_from_slice(&packet_id.to_be_bytes());
+                unacknowledged.insert(packet_id);
+            }
+            body.extend_from_slice(payload.as_bytes());
+
+            let flags = (qos as u8) << 1 | u8::from(retain);
+            self.send(PUBLISH << 4 | flags, &body)?;
+        }
+        self.writer.flush()?;
+
+        while !unacknowledged.is_empty() {
+            let (kind, body) = self.receive()?;
+            let packet_id = match body.get(..2) {
+                Some(id) => u16::from_be_bytes([id[0], id[1]]),
+                None => continue,
+            };
+
+            match kind {
+                PUBACK | PUBCOMP => {
+                    unacknowledged.remove(&packet_id);
+                }
+                PUBREC => {
+                    self.send(PUBREL << 4 | 0x02, &packet_id.to_be_bytes())?;
+                    self.writer.flush()?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the next packet identifier, skipping zero.
+    fn next_packet_id(&mut self) -> u16 {
+        let packet_id = self.next_packet_id;
+        self.next_packet_id = self.next_packet_id.checked_add(1).unwrap_or(1);
+        packet_id
+    }
+
+    /// Writes a packet with the given first header byte.
+    fn send(&mut self, header: u8, body: &[u8]) -> io::Result<()> {
+        self.writer.write_all(&[header])?;
+
+        // The remaining length is a variable-length integer, seven bits per byte.
+        let mut len = body.len();
+        loop {
+            let mut byte = (len % 128) as u8;
+            len /= 128;
+            if len > 0 {
+                byte |= 0x80;
+            }
+            self.writer.write_all(&[byte])?;
+            if len == 0 {
+                break;
+            }
+        }
+
+        self.writer.write_all(body)
+    }
+
+    /// Reads a packet and returns its type and body.
+    fn receive(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        let mut byte = [0u8; 1];
+        self.reader.read_exact(&mut byte)?;
+        let kind = byte[0] >> 4;
+
+        let mut len = 0usize;
+        for shift in 0..4 {
+            self.reader.read_exact(&mut byte)?;
+            len |= ((byte[0] & 0x7f) as usize) << (7 * shift);
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let mut body = vec![0u8; len];
+        self.reader.read_exact(&mut body)?;
+        Ok((kind, body))
+    }
+}
+
+impl Sink for MqttSink {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        let topic = record.render(&self.topic, "/", &['+', '#', '/']);
+        lock(&self.pending).push((topic, record.to_json()));
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let pending = std::mem::take(&mut *lock(&self.pending));
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        self.publisher.send(pending, || {
+            let broker = self.broker.clone();
+            let mut connection = None;
+            Ok(move |messages: Vec<(String, String)>| broker.deliver(&mut connection, &messages))
+        })
+    }
+}
+
+/// Appends a length-prefixed UTF-8 string.
+fn put_string(body: &mut Vec<u8>, value: &str) {
+    body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    body.extend_from_slice(value.as_bytes());
+}
+
+/// Locks a mutex, recovering the data if a previous holder panicked.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Creates an error for an unexpected broker packet.
+fn protocol_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,247 @@
+//! A sink publishing records to NATS.
+//!
+//! The NATS client protocol is a small line-based text protocol, so the sink speaks it
+//! directly over TCP instead of pulling in a full client. Records are collected while a batch
+//! is written and published with `PUB` when the sink is flushed, followed by a `PING`; the
+//! server's `PONG` confirms that every record before it has been processed.
+//!
+//! Publishing runs on a dedicated thread, because connecting and waiting for the server must
+//! not block the async delivery worker: a slow or unreachable server would otherwise stall
+//! delivery to POGR. The connection is plaintext only; there is no TLS support.
+
+use crate::sink::{Sink, SinkRecord, SinkThread};
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// How long to wait for the server when connecting and flushing.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A `Sink` that publishes records to NATS subjects derived from each record.
+///
+/// The subject is built from a template with these placeholders:
+///
+/// * `{target}` - the event's target, with `::` replaced by `.`, e.g. `game.matchmaking`.
+/// * `{level}` - the event's severity in lower case, e.g. `warn`.
+/// * `{service}` and `{environment}` - the appender's service name and environment.
+///
+/// Whitespace, `*`, `>` and `.` in the placeholder values are replaced with `_`, so each
+/// value stays a single subject token.
+///
+/// Flushing hands the batch to a publishing thread and returns without waiting for the
+/// server. Up to 16 batches are queued; while the thread is that far behind, further batches
+/// are dropped and the flush fails. Failures to publish are reported as `Sink` diagnostics.
+///
+/// The connection is opened for the first batch. If a reused connection fails, for example
+/// because the server dropped it while idle, the batch is published once more on a new one,
+/// so a record may occasionally be delivered twice.
+///
+/// Records and credentials are sent in plaintext, since TLS is not supported; use a local
+/// leaf node or a TLS-terminating proxy for remote servers.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{NatsSink, PogrAppender, PogrLayer, SinkMode};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let nats = NatsSink::new("nats.internal:4222", "telemetry.{service}.{target}.{level}")
+///     .with_token("s3cr3t");
+///
+/// let layer = PogrLayer::new(appender).with_sink(nats, SinkMode::Mirror);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct NatsSink {
+    /// The server the records are published to.
+    server: Server,
+    /// Subject template.
+    subject: String,
+    /// Subjects and payloads written since the last flush.
+    pending: Mutex<Vec<(String, String)>>,
+    /// Publishes the flushed batches, owning the connection.
+    publisher: SinkThread<Vec<(String, String)>>,
+}
+
+/// How to reach and authenticate with the NATS server.
+#[derive(Clone, Debug)]
+struct Server {
+    /// Address of the NATS server, e.g. `localhost:4222`.
+    address: String,
+    /// Credentials sent in the `CONNECT` message.
+    credentials: Map<String, Value>,
+}
+
+/// An open connection to the NATS server.
+#[derive(Debug)]
+struct Connection {
+    /// Reads server messages.
+    reader: BufReader<TcpStream>,
+    /// Buffers client messages.
+    writer: BufWriter<TcpStream>,
+}
+
+impl NatsSink {
+    /// Creates a sink publishing to the NATS server at `address`, using `subject` as the
+    /// subject template.
+    pub fn new(address: impl Into<String>, subject: impl Into<String>) -> Self {
+        NatsSink {
+            server: Server { address: address.into(), credentials: Map::new() },
+            subject: subject.into(),
+            pending: Mutex::new(Vec::new()),
+            publisher: SinkThread::new("pogr-nats-publisher"),
+        }
+    }
+
+    /// Authenticates with a token.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.server.credentials.insert("auth_token".to_string(), json!(token.into()));
+        self
+    }
+
+    /// Authenticates with a user name and password.
+    pub fn with_user_password(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.server.credentials.insert("user".to_string(), json!(user.into()));
+        self.server.credentials.insert("pass".to_string(), json!(password.into()));
+        self
+    }
+}
+
+impl Server {
+
+    /// Connects to the server and sends the `CONNECT` message.
+    fn connect(&self) -> io::Result<Connection> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "address did not resolve");
+        for address in self.address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, TIMEOUT) {
+                Ok(stream) => return self.handshake(stream),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Reads the server's `INFO` and sends `CONNECT`.
+    fn handshake(&self, stream: TcpStream) -> io::Result<Connection> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut connection = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        };
+
+        let info = connection.read_line()?;
+        if !info.starts_with("INFO") {
+            return Err(protocol_error(format!("expected INFO, got `{}`", info)));
+        }
+
+        let mut options = self.credentials.clone();
+        options.insert("verbose".to_string(), json!(false));
+        options.insert("pedantic".to_string(), json!(false));
+        options.insert("lang".to_string(), json!("rust"));
+        options.insert("name".to_string(), json!(env!("CARGO_PKG_NAME")));
+        options.insert("version".to_string(), json!(env!("CARGO_PKG_VERSION")));
+        write!(connection.writer, "CONNECT {}\r\n", Value::Object(options))?;
+
+        Ok(connection)
+    }
+
+    /// Publishes a batch, once more on a new connection if a reused one fails.
+    fn deliver(&self, slot: &mut Option<Connection>, messages: &[(String, String)]) -> io::Result<()> {
+        let reused = slot.is_some();
+        match self.publish(slot, messages) {
+            Err(_) if reused => self.publish(slot, messages),
+            result => result,
+        }
+    }
+
+    /// Publishes messages on the open connection, connecting first if needed.
+    ///
+    /// The connection is closed if publishing fails.
+    fn publish(&self, slot: &mut Option<Connection>, messages: &[(String, String)]) -> io::Result<()> {
+        let connection = match slot {
+            Some(connection) => connection,
+            None => slot.insert(self.connect()?),
+        };
+
+        let result = connection.publish(messages);
+        if result.is_err() {
+            *slot = None;
+        }
+        result
+    }
+}
+
+impl Connection {
+    /// Publishes messages and waits until the server has processed them.
+    fn publish(&mut self, messages: &[(String, String)]) -> io::Result<()> {
+        for (subject, payload) in messages {
+            write!(self.writer, "PUB {} {}\r\n{}\r\n", subject, payload.len(), payload)?;
+        }
+        self.ping()
+    }
+
+    /// Reads one protocol line without the trailing `\r\n`.
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by the NATS server"));
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    /// Sends a `PING` and waits for the matching `PONG`.
+    fn ping(&mut self) -> io::Result<()> {
+        self.writer.write_all(b"PING\r\n")?;
+        self.writer.flush()?;
+
+        loop {
+            let line = self.read_line()?;
+            match line.as_str() {
+                "PONG" => return Ok(()),
+                "PING" => {
+                    self.writer.write_all(b"PONG\r\n")?;
+                    self.writer.flush()?;
+                }
+                _ if line.starts_with("-ERR") => return Err(protocol_error(line)),
+                // `+OK` and `INFO` updates need no reply.
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Sink for NatsSink {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        let subject = record.render(&self.subject, ".", &['*', '>', '.']);
+        lock(&self.pending).push((subject, record.to_json()));
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let pending = std::mem::take(&mut *lock(&self.pending));
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        self.publisher.send(pending, || {
+            let server = self.server.clone();
+            let mut connection = None;
+            Ok(move |messages: Vec<(String, String)>| server.deliver(&mut connection, &messages))
+        })
+    }
+}
+
+/// Locks a mutex, recovering the data if a previous holder panicked.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Creates an error for an unexpected server message.
+fn protocol_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
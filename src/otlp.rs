@@ -0,0 +1,287 @@
+//! Exporting records to an OpenTelemetry collector instead of the POGR intake.
+//!
+//! Organizations that run an OpenTelemetry collector can point the layer at it, so that the
+//! collector buffers and processes the records before fanning them out, to POGR among other
+//! destinations. Everything up to the transport stays the same: the layer batches records,
+//! enforces the size limits, and hands failed requests to the fallback sinks. Only the
+//! encoding and the destination change. Each request is a logs export of the OTLP/HTTP
+//! protocol, encoded as JSON:
+//!
+//! ```text
+//! POST /v1/logs
+//! Content-Type: application/json
+//!
+//! {"resourceLogs": [{"resource": {...}, "scopeLogs": [{"scope": {...}, "logRecords": [...]}]}]}
+//! ```
+//!
+//! The resource describes the service with the OpenTelemetry semantic conventions, so the
+//! records join cleanly with other telemetry: `service.name`, `service.instance.id` (the POGR
+//! session ID), `deployment.environment`, `host.name`, `host.arch` and `os.type`, and, derived
+//! from hosting detection, `k8s.namespace.name` and `k8s.pod.name`, `cloud.provider` and
+//! `cloud.platform` on ECS, or `container.runtime`. POGR-specific details are added under
+//! `pogr.*`, such as `pogr.session_id`, so the collector can forward records to the right
+//! session. Each log record carries the event's fields as attributes, its `data` as
+//! `pogr.data.*` attributes, and its attachments as `pogr.attachment.*` byte attributes.
+
+use crate::pipeline::LogRecord;
+use crate::{hosting, http_dump, DeliveryError, PogrAppender};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fmt};
+
+/// Default logs endpoint of an OpenTelemetry collector on the local host.
+pub const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4318/v1/logs";
+
+/// Path of the logs export below a collector's base URL.
+const LOGS_PATH: &str = "/v1/logs";
+
+/// Where and how records are exported to an OpenTelemetry collector.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{OtlpConfig, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_otlp(
+///     OtlpConfig::new("http://otel-collector:4318/v1/logs").with_header("Authorization", "Bearer token"),
+/// );
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct OtlpConfig {
+    /// URL of the collector's logs endpoint.
+    endpoint: String,
+    /// Headers sent with every export, e.g. for authentication.
+    headers: Vec<(String, String)>,
+    /// Resource attributes describing where the service runs, set by the layer.
+    resource: Vec<(&'static str, String)>,
+}
+
+/// Shows the configuration without the header values, which often hold credentials.
+impl fmt::Debug for OtlpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers: Vec<&str> = self.headers.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("OtlpConfig")
+            .field("endpoint", &self.endpoint)
+            .field("headers", &headers)
+            .finish()
+    }
+}
+
+impl OtlpConfig {
+    /// Exports to the given logs endpoint, e.g. `http://otel-collector:4318/v1/logs`.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        OtlpConfig {
+            endpoint: endpoint.into(),
+            headers: Vec::new(),
+            resource: Vec::new(),
+        }
+    }
+
+    /// Configures the export from the standard OpenTelemetry environment variables.
+    ///
+    /// The endpoint is `OTEL_EXPORTER_OTLP_LOGS_ENDPOINT`, or `OTEL_EXPORTER_OTLP_ENDPOINT`
+    /// followed by `/v1/logs`, or `DEFAULT_OTLP_ENDPOINT`. Headers are taken from
+    /// `OTEL_EXPORTER_OTLP_HEADERS`, a comma-separated list of `name=value` pairs.
+    pub fn from_env() -> Self {
+        let endpoint = env::var("OTEL_EXPORTER_OTLP_LOGS_ENDPOINT")
+            .or_else(|_| env::var("OTEL_EXPORTER_OTLP_ENDPOINT").map(|base| format!("{}{}", base.trim_end_matches('/'), LOGS_PATH)))
+            .unwrap_or_else(|_| DEFAULT_OTLP_ENDPOINT.to_string());
+
+        let mut config = OtlpConfig::new(endpoint);
+        for pair in env::var("OTEL_EXPORTER_OTLP_HEADERS").unwrap_or_default().split(',') {
+            if let Some((name, value)) = pair.split_once('=') {
+                config = config.with_header(name.trim(), value.trim());
+            }
+        }
+        config
+    }
+
+    /// Adds a header sent with every export.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Returns the URL of the collector's logs endpoint.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Adds the resource attributes describing the host, and the hosting environment described
+    /// by the layer's hosting tags.
+    pub(crate) fn with_hosting(mut self, hosting_tags: &[(String, Value)]) -> Self {
+        self.resource = host_attributes();
+        for (key, value) in hosting_tags {
+            let Some(value) = value.as_str() else {
+                continue;
+            };
+            match (key.as_str(), value) {
+                ("k8s_namespace", namespace) => self.resource.push(("k8s.namespace.name", namespace.to_string())),
+                ("k8s_pod", pod) => self.resource.push(("k8s.pod.name", pod.to_string())),
+                ("hosting", "ecs") => {
+                    self.resource.push(("cloud.provider", "aws".to_string()));
+                    self.resource.push(("cloud.platform", "aws_ecs".to_string()));
+                }
+                ("hosting", "docker") => self.resource.push(("container.runtime", "docker".to_string())),
+                _ => {}
+            }
+            if key == "hosting" {
+                self.resource.push(("pogr.hosting", value.to_string()));
+            }
+        }
+        self
+    }
+
+    /// Exports records in a single request.
+    pub(crate) async fn send(&self, client: &Client, appender: &PogrAppender, records: &[&LogRecord]) -> Result<(), DeliveryError> {
+        let mut request = client.post(&self.endpoint).json(&export_request(appender, &self.resource, records));
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let (status, body) = http_dump::send(client, request).await.map_err(DeliveryError::Transport)?;
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(DeliveryError::CollectorRejected(format!("{}: {}", status, String::from_utf8_lossy(&body))))
+        }
+    }
+}
+
+/// Builds the body of a logs export request.
+fn export_request(appender: &PogrAppender, hosting: &[(&'static str, String)], records: &[&LogRecord]) -> Value {
+    let service = [
+        ("service.name", appender.service_name.as_str()),
+        ("service.instance.id", appender.session_id.as_str()),
+        ("deployment.environment", appender.environment.as_str()),
+        ("telemetry.sdk.name", env!("CARGO_PKG_NAME")),
+        ("telemetry.sdk.language", "rust"),
+        ("telemetry.sdk.version", env!("CARGO_PKG_VERSION")),
+        ("pogr.service_type", appender.service_type.as_str()),
+        ("pogr.session_id", appender.session_id.as_str()),
+    ];
+    let resource: Vec<Value> = service
+        .into_iter()
+        .chain(hosting.iter().map(|(key, value)| (*key, value.as_str())))
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect();
+    let observed = unix_nanos(SystemTime::now());
+
+    json!({
+        "resourceLogs": [{
+            "resource": { "attributes": resource },
+            "scopeLogs": [{
+                "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "logRecords": records.iter().map(|record| log_record(record, &observed)).collect::<Vec<_>>()
+            }]
+        }]
+    })
+}
+
+/// Returns the resource attributes describing the host: `host.name`, `host.arch` and `os.type`.
+fn host_attributes() -> Vec<(&'static str, String)> {
+    let mut attributes = Vec::new();
+    if let Some(name) = hosting::host_name() {
+        attributes.push(("host.name", name));
+    }
+    // The semantic conventions name architectures like Go does.
+    let arch = match env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "x86",
+        "arm" => "arm32",
+        other => other,
+    };
+    attributes.push(("host.arch", arch.to_string()));
+    attributes.push(("os.type", env::consts::OS.to_string()));
+    attributes
+}
+
+/// Encodes a record as an OTLP log record.
+///
+/// The body is the event's `message` field, or the record's log text if it has none.
+fn log_record(record: &LogRecord, observed: &str) -> Value {
+    let request = &record.request;
+    let tags = request.tags.as_object();
+    let body = tags
+        .and_then(|tags| tags.get("message"))
+        .and_then(Value::as_str)
+        .unwrap_or(&request.log);
+
+    let mut attributes = vec![attribute("pogr.event_id", &json!(record.event_id.to_string()))];
+    attributes.push(attribute("pogr.log", &json!(request.log)));
+    if let Some(tags) = tags {
+        attributes.extend(tags.iter().filter(|(key, _)| *key != "message").map(|(key, value)| attribute(key, value)));
+    }
+    if let Some(data) = request.data.as_object() {
+        attributes.extend(
+            data.iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| attribute(&format!("pogr.data.{}", key), value)),
+        );
+    }
+    attributes.extend(record.attachments.iter().map(|attachment| {
+        json!({
+            "key": format!("pogr.attachment.{}", attachment.name()),
+            "value": { "bytesValue": STANDARD.encode(attachment.data()) }
+        })
+    }));
+
+    json!({
+        "timeUnixNano": unix_nanos(record.timestamp),
+        "observedTimeUnixNano": observed,
+        "severityNumber": severity_number(&request.severity),
+        "severityText": request.severity,
+        "body": { "stringValue": body },
+        "attributes": attributes
+    })
+}
+
+/// Encodes a key and a JSON value as an OTLP attribute.
+fn attribute(key: &str, value: &Value) -> Value {
+    json!({ "key": key, "value": any_value(value) })
+}
+
+/// Encodes a JSON value as an OTLP `AnyValue`.
+///
+/// 64-bit integers are encoded as strings, as the protobuf JSON mapping requires; `null`
+/// becomes an empty value.
+fn any_value(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({}),
+        Value::Bool(value) => json!({ "boolValue": value }),
+        Value::Number(number) if number.is_i64() || number.is_u64() => json!({ "intValue": number.to_string() }),
+        Value::Number(number) => json!({ "doubleValue": number.as_f64() }),
+        Value::String(value) => json!({ "stringValue": value }),
+        Value::Array(values) => json!({ "arrayValue": { "values": values.iter().map(any_value).collect::<Vec<_>>() } }),
+        Value::Object(entries) => json!({ "kvlistValue": { "values": key_values(entries) } }),
+    }
+}
+
+/// Encodes the entries of a JSON object as OTLP key/value pairs.
+fn key_values(entries: &Map<String, Value>) -> Vec<Value> {
+    entries.iter().map(|(key, value)| attribute(key, value)).collect()
+}
+
+/// Returns the OTLP severity number of a `tracing` level.
+fn severity_number(severity: &str) -> u8 {
+    match severity {
+        "TRACE" => 1,
+        "DEBUG" => 5,
+        "INFO" => 9,
+        "WARN" => 13,
+        "ERROR" => 17,
+        _ => 0,
+    }
+}
+
+/// Returns the nanoseconds since the Unix epoch, as a string.
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
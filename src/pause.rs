@@ -0,0 +1,138 @@
+//! Pausing delivery when the intake asks for it.
+//!
+//! During an incident, the intake can shed load by telling clients to stop sending for a
+//! while. A response is a pause request when it carries a `X-POGR-Pause` header with the
+//! number of seconds to pause for, or when it is a `429 Too Many Requests` or
+//! `503 Service Unavailable` with a `Retry-After` in seconds. The records of such a response
+//! are not accepted.
+//!
+//! While delivery is paused, no requests are sent to the intake. What happens to the records
+//! captured in the meantime depends on the `PausePolicy`: they are spooled in memory and sent
+//! once the pause ends, or handed to the fallback sinks right away. Delivery resumes
+//! automatically; pauses are capped at `MAX_PAUSE`, so a faulty header cannot silence a
+//! client for good.
+
+use crate::clock;
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pipeline::InFlight;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Longest pause honored, whatever the intake asks for.
+pub const MAX_PAUSE: Duration = Duration::from_secs(60 * 60);
+
+/// Header carrying the number of seconds to pause delivery for.
+const PAUSE_HEADER: &str = "x-pogr-pause";
+
+/// What happens to records captured while the intake has paused delivery.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PausePolicy {
+    /// Records are kept in memory and sent once the pause ends. A flush during the pause hands
+    /// them to the fallback sinks instead of waiting, so that shutting down is not delayed.
+    #[default]
+    Spool,
+    /// Records are handed to the fallback sinks right away, as if delivery had failed. Without
+    /// fallback sinks they are dropped.
+    Drop,
+}
+
+/// Returns the pause requested by a response, if any, capped at `MAX_PAUSE`.
+pub(crate) fn requested(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    let seconds = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .filter(|seconds| *seconds > 0)
+    };
+    let retry_after = matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE)
+        .then(|| seconds(RETRY_AFTER.as_str()))
+        .flatten();
+
+    seconds(PAUSE_HEADER)
+        .or(retry_after)
+        .map(|seconds| Duration::from_secs(seconds).min(MAX_PAUSE))
+}
+
+/// Returns how long a response asked to wait before retrying, from its `Retry-After` header in
+/// seconds or as an HTTP date, capped at `MAX_PAUSE`.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => clock::parse_http_date(value)?
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    };
+    Some(delay.min(MAX_PAUSE))
+}
+
+/// Whether delivery to the intake is paused, shared by all the workers of a layer.
+#[derive(Debug)]
+pub(crate) struct KillSwitch {
+    /// What happens to records while delivery is paused.
+    policy: PausePolicy,
+    /// When the current pause ends, if delivery is paused.
+    until: Mutex<Option<Instant>>,
+}
+
+impl KillSwitch {
+    /// Creates a switch, not paused, applying `policy` during pauses.
+    pub(crate) fn new(policy: PausePolicy) -> Self {
+        KillSwitch {
+            policy,
+            until: Mutex::new(None),
+        }
+    }
+
+    /// Returns what happens to records while delivery is paused.
+    pub(crate) fn policy(&self) -> PausePolicy {
+        self.policy
+    }
+
+    /// Pauses delivery for `duration`, unless it is already paused for longer.
+    pub(crate) fn pause(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut current = self.until.lock().unwrap_or_else(|p| p.into_inner());
+        if current.is_some_and(|current| current >= until) {
+            return;
+        }
+        let resumed = current.map_or(true, |current| current <= Instant::now());
+        *current = Some(until);
+        drop(current);
+
+        if resumed {
+            diagnostics::warn(
+                DiagnosticKind::Delivery,
+                format!("The intake paused delivery for {} s", duration.as_secs()),
+            );
+        }
+    }
+
+    /// Returns when the current pause ends, if delivery is paused.
+    pub(crate) fn paused_until(&self) -> Option<Instant> {
+        self.until
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .filter(|until| *until > Instant::now())
+    }
+
+    /// Waits until delivery is no longer paused.
+    ///
+    /// Returns `false` if a flush is waiting, or starts while waiting.
+    pub(crate) async fn resumed(&self, in_flight: &InFlight) -> bool {
+        while let Some(until) = self.paused_until() {
+            let flushes = in_flight.flushes();
+            if in_flight.is_flushing() {
+                return false;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep_until(until.into()) => {}
+                _ = in_flight.flushing(flushes) => return false,
+            }
+        }
+        true
+    }
+}
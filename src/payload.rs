@@ -0,0 +1,213 @@
+//! Encoding of the payloads sent to the logs endpoint.
+//!
+//! Every payload carries a `schema_version`, so that the intake and the dashboards built on
+//! stored records can tell which shape a record has. Changes to the shape of a payload, such
+//! as new fields or renamed keys, get a new version with an encoder of its own, while records
+//! of older versions, e.g. replayed from a buffer, keep the version they were encoded with.
+//!
+//! Version 1 is the `LogRequest` fields at the top level, with the `event_id`, `timestamp`
+//! and, if any, `attachments` assigned by the client:
+//!
+//! ```text
+//! {"schema_version": 1, "event_id": "0190...", "timestamp": "2024-05-01T12:00:00.000Z",
+//!  "service": "...", "environment": "...", "severity": "INFO", "type": "...",
+//!  "log": "rust tracing log captured", "data": {...}, "tags": {...}}
+//! ```
+
+use crate::attachment::AttachmentManifest;
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pipeline::LogRecord;
+use crate::pool;
+use crate::{clock, LogRequest};
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+/// Version of the payloads sent to the logs endpoint.
+pub const SCHEMA_VERSION: u32 = SchemaVersion::CURRENT.number();
+
+/// The versions of the payload shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SchemaVersion {
+    /// The `LogRequest` fields at the top level, with the client-assigned metadata.
+    V1,
+}
+
+impl SchemaVersion {
+    /// The version new payloads are encoded with.
+    pub(crate) const CURRENT: SchemaVersion = SchemaVersion::V1;
+
+    /// Returns the number sent as `schema_version`.
+    pub(crate) const fn number(self) -> u32 {
+        match self {
+            SchemaVersion::V1 => 1,
+        }
+    }
+}
+
+/// The payload sent to the logs endpoint: a `LogRequest` together with the metadata the
+/// client assigns to every submitted log message.
+#[derive(Serialize, Debug)]
+pub(crate) struct LogEnvelope<'a> {
+    /// Version of the payload shape.
+    pub(crate) schema_version: u32,
+    /// Client-generated UUIDv7 identifying the log message, stable across resubmissions.
+    pub(crate) event_id: Uuid,
+    /// When the log message was captured, as an RFC 3339 UTC timestamp.
+    pub(crate) timestamp: String,
+    /// The log message itself; its fields are serialized at the top level of the payload.
+    #[serde(flatten)]
+    pub(crate) request: &'a LogRequest,
+    /// Binary artifacts submitted with the log message.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) attachments: Vec<AttachmentManifest<'a>>,
+}
+
+/// Encodes a record as a payload of the given version, with its attachments inline.
+pub(crate) fn encode(record: &LogRecord, version: SchemaVersion) -> LogEnvelope<'_> {
+    match version {
+        SchemaVersion::V1 => LogEnvelope {
+            schema_version: version.number(),
+            event_id: record.event_id,
+            timestamp: clock::format_rfc3339(record.timestamp),
+            request: &record.request,
+            attachments: record.attachments.iter().map(AttachmentManifest::inline).collect(),
+        },
+    }
+}
+
+/// How request bodies are encoded for the logs endpoint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PayloadEncoding {
+    /// JSON, as described by the payload's `schema_version`.
+    #[default]
+    Json,
+    /// Newline-delimited JSON, sent as `application/x-ndjson`: one payload per line, whatever
+    /// the number of records and the intake API version, so streaming collectors can process
+    /// the records of a request as they arrive. Intakes that answer `415 Unsupported Media
+    /// Type` get JSON instead.
+    Ndjson,
+    /// Protobuf, as described by `protobuf::SCHEMA`, sent as `application/x-protobuf`. About
+    /// half the size of JSON. Intakes that answer `415 Unsupported Media Type` get JSON
+    /// instead.
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+    /// MessagePack, with the same structure as JSON, sent as `application/msgpack`. Faster to
+    /// encode and smaller than JSON, especially for events with many fields. Intakes that
+    /// answer `415 Unsupported Media Type` get JSON instead.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// CBOR, with the same structure as JSON, sent as `application/cbor`. Intakes that answer
+    /// `415 Unsupported Media Type` get JSON instead.
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl fmt::Display for PayloadEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PayloadEncoding::Json => "JSON",
+            PayloadEncoding::Ndjson => "NDJSON",
+            #[cfg(feature = "protobuf")]
+            PayloadEncoding::Protobuf => "protobuf",
+            #[cfg(feature = "msgpack")]
+            PayloadEncoding::MessagePack => "MessagePack",
+            #[cfg(feature = "cbor")]
+            PayloadEncoding::Cbor => "CBOR",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Content type of NDJSON request bodies.
+pub(crate) const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Encodes payloads as NDJSON, each followed by a newline.
+pub(crate) fn encode_ndjson(envelopes: &[LogEnvelope<'_>]) -> serde_json::Result<Vec<u8>> {
+    pool::with_buffer(|encoded| {
+        for envelope in envelopes {
+            serde_json::to_writer(&mut *encoded, envelope)?;
+            encoded.push(b'\n');
+        }
+        Ok(())
+    })
+}
+
+/// Content type of MessagePack request bodies.
+#[cfg(feature = "msgpack")]
+pub(crate) const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Encodes a request body as MessagePack, with the same structure as its JSON encoding:
+/// structs as maps, and event IDs and other identifiers as strings.
+#[cfg(feature = "msgpack")]
+pub(crate) fn encode_msgpack(body: &impl Serialize) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let mut encoded = Vec::new();
+    body.serialize(&mut rmp_serde::Serializer::new(&mut encoded).with_struct_map().with_human_readable())?;
+    Ok(encoded)
+}
+
+/// Content type of CBOR request bodies.
+#[cfg(feature = "cbor")]
+pub(crate) const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// Encodes a request body as CBOR, with the same structure as its JSON encoding. The body goes
+/// through its JSON data model first, so event IDs and other identifiers are strings rather
+/// than byte strings.
+#[cfg(feature = "cbor")]
+pub(crate) fn encode_cbor(body: &impl Serialize) -> Result<Vec<u8>, String> {
+    let value = serde_json::to_value(body).map_err(|err| err.to_string())?;
+    let mut encoded = Vec::new();
+    ciborium::ser::into_writer(&value, &mut encoded).map_err(|err| err.to_string())?;
+    Ok(encoded)
+}
+
+/// The encoding the workers of a layer submit with: the preferred one, until the intake
+/// refuses it.
+#[derive(Debug, Default)]
+pub(crate) struct EncodingNegotiation {
+    /// The configured encoding.
+    preferred: PayloadEncoding,
+    /// Whether the intake refused the configured encoding.
+    refused: AtomicBool,
+}
+
+impl EncodingNegotiation {
+    /// Starts with the configured encoding.
+    pub(crate) fn new(preferred: PayloadEncoding) -> Self {
+        EncodingNegotiation {
+            preferred,
+            refused: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the configured encoding.
+    pub(crate) fn preferred(&self) -> PayloadEncoding {
+        self.preferred
+    }
+
+    /// Returns the encoding to submit with.
+    pub(crate) fn current(&self) -> PayloadEncoding {
+        if self.refused.load(Ordering::Relaxed) {
+            PayloadEncoding::Json
+        } else {
+            self.preferred
+        }
+    }
+
+    /// Falls back to JSON after the intake refused `encoding`, and returns `false` if there is
+    /// nothing to fall back to.
+    pub(crate) fn refuse(&self, encoding: PayloadEncoding) -> bool {
+        if encoding == PayloadEncoding::Json {
+            return false;
+        }
+        if !self.refused.swap(true, Ordering::Relaxed) {
+            diagnostics::warn(
+                DiagnosticKind::Delivery,
+                format!("The intake does not accept {} payloads, falling back to JSON", encoding),
+            );
+        }
+        true
+    }
+}
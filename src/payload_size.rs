@@ -0,0 +1,277 @@
+//! Telemetry about the serialized size of captured records.
+//!
+//! A single event that attaches a whole request body or a large collection to its fields can
+//! make up most of a service's telemetry volume, and with it most of its bandwidth and
+//! storage. Payload size tracking measures every record as serialized for the intake, before
+//! any truncation, and keeps totals per target and callsite, so the largest contributors can
+//! be looked up through a `PayloadStats` handle.
+//!
+//! A callsite that keeps producing payloads above the oversize threshold is reported once,
+//! as a `PayloadSize` diagnostic, after it has done so `warn_after` times.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::LogRequest;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Default size, in bytes, above which a payload counts as oversized.
+pub const DEFAULT_OVERSIZE_THRESHOLD: usize = 64 * 1024;
+
+/// Default number of oversized payloads after which a callsite is reported.
+pub const DEFAULT_OVERSIZE_WARN_AFTER: u64 = 10;
+
+/// Settings for payload size telemetry.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PayloadSizeTracking, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_payload_size_tracking(
+///     PayloadSizeTracking::new()
+///         .with_oversize_threshold(16 * 1024)
+///         .with_warn_after(5),
+/// );
+/// let stats = layer.payload_stats().unwrap();
+/// // ...
+/// for callsite in stats.top_callsites(10) {
+///     println!("{:?}: {} bytes in {} records", callsite.callsite(), callsite.total_bytes(), callsite.count());
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayloadSizeTracking {
+    /// Size in bytes above which a payload counts as oversized.
+    oversize_threshold: usize,
+    /// Number of oversized payloads after which a callsite is reported.
+    warn_after: u64,
+}
+
+impl PayloadSizeTracking {
+    /// Creates the default settings: payloads above 64 KiB are oversized, and a callsite is
+    /// reported after ten of them.
+    pub fn new() -> Self {
+        PayloadSizeTracking {
+            oversize_threshold: DEFAULT_OVERSIZE_THRESHOLD,
+            warn_after: DEFAULT_OVERSIZE_WARN_AFTER,
+        }
+    }
+
+    /// Sets the size, in bytes, above which a payload counts as oversized.
+    pub fn with_oversize_threshold(mut self, bytes: usize) -> Self {
+        self.oversize_threshold = bytes;
+        self
+    }
+
+    /// Sets how many oversized payloads a callsite produces before it is reported. Values
+    /// below one are treated as one.
+    pub fn with_warn_after(mut self, count: u64) -> Self {
+        self.warn_after = count.max(1);
+        self
+    }
+
+    /// Returns the size, in bytes, above which a payload counts as oversized.
+    pub fn oversize_threshold(&self) -> usize {
+        self.oversize_threshold
+    }
+
+    /// Returns how many oversized payloads a callsite produces before it is reported.
+    pub fn warn_after(&self) -> u64 {
+        self.warn_after
+    }
+}
+
+impl Default for PayloadSizeTracking {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serialized sizes of the records of a target, or of a single callsite in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayloadSizeStats {
+    /// Target of the records.
+    target: String,
+    /// Source location, as `file:line`, if the stats are for a callsite.
+    callsite: Option<String>,
+    /// Number of records measured.
+    count: u64,
+    /// Sum of their sizes in bytes.
+    total_bytes: u64,
+    /// Size of the largest one in bytes.
+    max_bytes: usize,
+    /// Number of records above the oversize threshold.
+    oversized: u64,
+}
+
+impl PayloadSizeStats {
+    /// Returns the target of the records.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Returns the source location of the callsite as `file:line`, if the stats are for a
+    /// callsite and it is known.
+    pub fn callsite(&self) -> Option<&str> {
+        self.callsite.as_deref()
+    }
+
+    /// Returns the number of records measured.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the sum of the sizes of the records, in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Returns the size of the largest record, in bytes.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Returns the average size of the records, in bytes.
+    pub fn average_bytes(&self) -> u64 {
+        self.total_bytes.checked_div(self.count).unwrap_or_default()
+    }
+
+    /// Returns the number of records above the oversize threshold.
+    pub fn oversized(&self) -> u64 {
+        self.oversized
+    }
+
+    /// Adds the stats of a callsite to those of its target.
+    fn merge(&mut self, other: &PayloadSizeStats) {
+        self.count += other.count;
+        self.total_bytes += other.total_bytes;
+        self.max_bytes = self.max_bytes.max(other.max_bytes);
+        self.oversized += other.oversized;
+    }
+}
+
+/// A target and the source location in it, as `file:line`, if known.
+type CallsiteKey = (String, Option<String>);
+
+/// Per-callsite sizes, and whether the callsite has been reported.
+struct Callsite {
+    /// The sizes.
+    stats: PayloadSizeStats,
+    /// Whether the callsite has been reported for its oversized payloads.
+    warned: bool,
+}
+
+/// Handle to the payload size telemetry of a layer, obtained with `PogrLayer::payload_stats`.
+///
+/// Handles are cheap to clone and stay valid after the layer has been moved into a subscriber.
+#[derive(Clone)]
+pub struct PayloadStats {
+    /// The settings.
+    config: Arc<PayloadSizeTracking>,
+    /// Sizes by target and source location.
+    callsites: Arc<Mutex<HashMap<CallsiteKey, Callsite>>>,
+}
+
+impl PayloadStats {
+    /// Creates empty telemetry with the given settings.
+    pub(crate) fn new(config: PayloadSizeTracking) -> Self {
+        PayloadStats {
+            config: Arc::new(config),
+            callsites: Arc::default(),
+        }
+    }
+
+    /// Returns the `limit` callsites that produced the most bytes, largest first.
+    pub fn top_callsites(&self, limit: usize) -> Vec<PayloadSizeStats> {
+        let callsites = self.callsites.lock().unwrap_or_else(|p| p.into_inner());
+        top(callsites.values().map(|callsite| callsite.stats.clone()).collect(), limit)
+    }
+
+    /// Returns the `limit` targets that produced the most bytes, largest first.
+    pub fn top_targets(&self, limit: usize) -> Vec<PayloadSizeStats> {
+        let callsites = self.callsites.lock().unwrap_or_else(|p| p.into_inner());
+        let mut targets: HashMap<&str, PayloadSizeStats> = HashMap::new();
+        for callsite in callsites.values() {
+            let stats = &callsite.stats;
+            targets
+                .entry(&stats.target)
+                .or_insert_with(|| PayloadSizeStats {
+                    target: stats.target.clone(),
+                    callsite: None,
+                    count: 0,
+                    total_bytes: 0,
+                    max_bytes: 0,
+                    oversized: 0,
+                })
+                .merge(stats);
+        }
+        top(targets.into_values().collect(), limit)
+    }
+
+    /// Forgets every size measured so far, and which callsites were reported.
+    pub fn reset(&self) {
+        self.callsites.lock().unwrap_or_else(|p| p.into_inner()).clear();
+    }
+
+    /// Records the serialized size of a record, and reports its callsite if it keeps
+    /// producing oversized payloads.
+    ///
+    /// Records without a target, such as those queued through a `PogrHandle`, are not tracked.
+    pub(crate) fn observe(&self, request: &LogRequest, size: usize) {
+        let Some(target) = request.data.get("target").and_then(Value::as_str) else {
+            return;
+        };
+        let file = request.data.get("file").and_then(Value::as_str);
+        let line = request.data.get("line").and_then(Value::as_u64);
+        let location = match (file, line) {
+            (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+            (Some(file), None) => Some(file.to_string()),
+            _ => None,
+        };
+
+        let oversized = size > self.config.oversize_threshold;
+        let mut callsites = self.callsites.lock().unwrap_or_else(|p| p.into_inner());
+        let callsite = callsites
+            .entry((target.to_string(), location))
+            .or_insert_with_key(|(target, location)| Callsite {
+                stats: PayloadSizeStats {
+                    target: target.clone(),
+                    callsite: location.clone(),
+                    count: 0,
+                    total_bytes: 0,
+                    max_bytes: 0,
+                    oversized: 0,
+                },
+                warned: false,
+            });
+        let stats = &mut callsite.stats;
+        stats.count += 1;
+        stats.total_bytes += size as u64;
+        stats.max_bytes = stats.max_bytes.max(size);
+        stats.oversized += u64::from(oversized);
+
+        if oversized && !callsite.warned && stats.oversized >= self.config.warn_after {
+            callsite.warned = true;
+            let message = format!(
+                "{} at {} produced {} payloads above {} bytes, up to {} bytes",
+                stats.target,
+                stats.callsite.as_deref().unwrap_or("an unknown location"),
+                stats.oversized,
+                self.config.oversize_threshold,
+                stats.max_bytes,
+            );
+            drop(callsites);
+            diagnostics::warn(DiagnosticKind::PayloadSize, message);
+        }
+    }
+}
+
+/// Sorts stats by total size, largest first, and keeps the first `limit`.
+fn top(mut stats: Vec<PayloadSizeStats>, limit: usize) -> Vec<PayloadSizeStats> {
+    stats.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then_with(|| a.target.cmp(&b.target)));
+    stats.truncate(limit);
+    stats
+}
@@ -0,0 +1,1243 @@
+//! The delivery pipeline between `PogrLayer` and the POGR intake.
+//!
+//! Captured events are queued to a background worker, which groups them into batches, makes
+//! sure every request stays within the intake's size limit, and submits them through the
+//! `PogrAppender`. Oversized batches are split into several requests; a single event that is
+//! too large on its own is truncated and annotated instead of failing the request. Requests
+//! that fail are handed to the layer's fallback sinks.
+
+use crate::attachment::{self, Attachment, AttachmentEncoding};
+use crate::backpressure::Backpressure;
+use crate::connection::ConnectionRefresh;
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::drop_stats::{DropReason, DropStats};
+use crate::endpoint_race::EndpointRacer;
+use crate::log_id::{LogIdSlot, LogReference};
+use crate::lossy;
+use crate::otlp::OtlpConfig;
+use crate::pause::{KillSwitch, PausePolicy};
+use crate::saturation::SaturationMonitor;
+use crate::sink::{SinkMode, Sinks};
+use crate::payload::{self, EncodingNegotiation, LogEnvelope, SchemaVersion};
+use crate::payload_size::PayloadStats;
+use crate::pool::{self, Pool};
+use crate::retry::{ErrorClass, ExponentialBackoff, RetryPolicy, StatusAction, StatusTable};
+use crate::ring::{self, MemoryCharge, OverflowPolicy, QueueReceiver, QueueSender, DEFAULT_MEMORY_BUDGET, DEFAULT_QUEUE_CAPACITY};
+use crate::{DeliveryError, IdGenerator, IdKind, LogRequest, PogrAppender};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::runtime::Handle;
+use tokio::sync::{Mutex, Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::time::MissedTickBehavior;
+use tracing::Level;
+use uuid::Uuid;
+
+/// Default upper bound, in bytes, for the body of a single intake request.
+pub const DEFAULT_MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// Tag added to events that had to be truncated to fit into a request.
+const TRUNCATED_TAG: &str = "pogr.truncated";
+/// Tag holding the serialized size, in bytes, of a truncated event before truncation.
+const ORIGINAL_SIZE_TAG: &str = "pogr.original_size";
+/// Tag listing the fields whose values were shortened.
+const TRUNCATED_FIELDS_TAG: &str = "pogr.truncated_fields";
+/// Tag holding the beginning of the serialized tags when they had to be dropped entirely.
+const PREVIEW_TAG: &str = "pogr.preview";
+/// Appended to string values that were shortened.
+const ELLIPSIS: &str = "…";
+
+/// Controls how captured events are grouped into intake requests.
+///
+/// By default every event is submitted on its own, as soon as it is captured. With a
+/// `max_batch_size` above one, the worker waits up to `linger` for more events and submits
+/// them together as a JSON array. Regardless of batching, no request body exceeds
+/// `max_request_size`: batches are split, and single oversized events are truncated.
+/// Batches are submitted one at a time unless `max_concurrent_requests` allows more.
+///
+/// On metered connections, `ShippingSchedule::Interval` instead submits whatever was captured
+/// at a fixed interval, except for events at or above `immediate_level`, which are submitted
+/// right away along with everything queued before them.
+///
+/// Captured events wait for the worker in a queue of up to `queue_capacity` events, and the
+/// events it holds may take up at most `memory_budget` bytes. If they are captured faster
+/// than they are delivered and either limit is reached, the `OverflowPolicy` decides which
+/// event is dropped.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{BatchConfig, PogrAppender, PogrLayer};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_batching(
+///     BatchConfig::new()
+///         .with_max_batch_size(100)
+///         .with_linger(Duration::from_millis(500))
+///         .with_max_request_size(512 * 1024),
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// Maximum number of events per request.
+    max_batch_size: usize,
+    /// Maximum size of a request body in bytes.
+    max_request_size: usize,
+    /// How long to wait for a batch to fill up before submitting it.
+    linger: Duration,
+    /// Maximum number of batches submitted at the same time.
+    max_concurrent_requests: usize,
+    /// When queued events are submitted.
+    schedule: ShippingSchedule,
+    /// Severity from which events are submitted right away under an interval schedule.
+    immediate_level: Option<Level>,
+    /// Maximum number of events waiting for the worker.
+    queue_capacity: usize,
+    /// Which event is dropped when the queue is full.
+    overflow: OverflowPolicy,
+    /// Maximum number of bytes the events held by the worker may take up, if limited.
+    memory_budget: Option<usize>,
+}
+
+impl BatchConfig {
+    /// Creates the default configuration: no batching and a 1 MiB request size limit.
+    pub fn new() -> Self {
+        BatchConfig {
+            max_batch_size: 1,
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            linger: Duration::from_secs(1),
+            max_concurrent_requests: 1,
+            schedule: ShippingSchedule::BatchSize,
+            immediate_level: Some(Level::ERROR),
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            overflow: OverflowPolicy::DropNewest,
+            memory_budget: Some(DEFAULT_MEMORY_BUDGET),
+        }
+    }
+
+    /// Sets the maximum number of events submitted in one request. Values below one are
+    /// treated as one.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Sets the maximum size of a request body, in bytes.
+    pub fn with_max_request_size(mut self, max_request_size: usize) -> Self {
+        self.max_request_size = max_request_size;
+        self
+    }
+
+    /// Sets how long the worker waits for a batch to fill up before submitting it anyway.
+    pub fn with_linger(mut self, linger: Duration) -> Self {
+        self.linger = linger;
+        self
+    }
+
+    /// Sets how many batches may be submitted at the same time. Values below one are treated
+    /// as one.
+    ///
+    /// With more than one, a slow response no longer holds up the batches behind it, but
+    /// batches may reach the intake out of order. Over HTTP/1.1 each concurrent submission
+    /// needs its own connection; over HTTP/2, configured with `PogrLayer::with_http`, they
+    /// share one.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    /// Sets when queued events are submitted. Defaults to `ShippingSchedule::BatchSize`.
+    pub fn with_schedule(mut self, schedule: ShippingSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Sets the severity from which events are submitted as soon as they are captured under
+    /// `ShippingSchedule::Interval`, taking everything queued before them along. `None` holds
+    /// every event until the next interval or flush. Defaults to `ERROR`.
+    pub fn with_immediate_level(mut self, immediate_level: Option<Level>) -> Self {
+        self.immediate_level = immediate_level;
+        self
+    }
+
+    /// Sets how many captured events may wait for the worker. Values below one are treated as
+    /// one. Defaults to `DEFAULT_QUEUE_CAPACITY`.
+    ///
+    /// The queue's memory is reserved up front, when the worker starts.
+    pub fn with_queue_capacity(mut self, queue_capacity: usize) -> Self {
+        self.queue_capacity = queue_capacity.max(1);
+        self
+    }
+
+    /// Sets which event is dropped when an event is captured while the queue is full.
+    /// Defaults to `OverflowPolicy::DropNewest`.
+    pub fn with_overflow_policy(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sets how many bytes the events held by the worker may take up, from when they are
+    /// captured until they are built into requests, or `None` for no limit. Defaults to
+    /// `DEFAULT_MEMORY_BUDGET`.
+    ///
+    /// Sizes are estimated from the events' fields and attachments. An event that does not fit
+    /// is dropped, or under `OverflowPolicy::DropOldest`, the oldest queued events are dropped
+    /// until it does. An event larger than the whole budget is always dropped.
+    pub fn with_memory_budget(mut self, bytes: Option<usize>) -> Self {
+        self.memory_budget = bytes;
+        self
+    }
+
+    /// Returns the maximum number of events per request.
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    /// Returns the maximum size of a request body in bytes.
+    pub fn max_request_size(&self) -> usize {
+        self.max_request_size
+    }
+
+    /// Returns how long the worker waits for a batch to fill up.
+    pub fn linger(&self) -> Duration {
+        self.linger
+    }
+
+    /// Returns how many batches may be submitted at the same time.
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests
+    }
+
+    /// Returns when queued events are submitted.
+    pub fn schedule(&self) -> ShippingSchedule {
+        self.schedule
+    }
+
+    /// Returns the severity from which events skip the wait under an interval schedule.
+    pub fn immediate_level(&self) -> Option<Level> {
+        self.immediate_level
+    }
+
+    /// Returns how many captured events may wait for the worker.
+    pub fn queue_capacity(&self) -> usize {
+        self.queue_capacity
+    }
+
+    /// Returns which event is dropped when the queue is full.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow
+    }
+
+    /// Returns how many bytes the events held by the worker may take up, if limited.
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// When the delivery worker submits the events it has queued.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{BatchConfig, PogrAppender, PogrLayer, ShippingSchedule};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// // Ship once a minute; errors still go out as soon as they happen.
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_batching(
+///     BatchConfig::new().with_schedule(ShippingSchedule::Interval(Duration::from_secs(60))),
+/// );
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShippingSchedule {
+    /// Submit a batch once it reaches `max_batch_size` events or has waited `linger`.
+    BatchSize,
+    /// Submit everything queued once per interval, however few or many events it is.
+    ///
+    /// `max_batch_size` and `linger` do not apply; requests are only split to stay within
+    /// `max_request_size`. Events at or above the immediate level, and flushes, submit what
+    /// is queued right away.
+    Interval(Duration),
+}
+
+/// A log message ready to be submitted, with the metadata assigned at capture time.
+pub(crate) struct LogRecord {
+    /// Client-generated identifier of the log message.
+    pub(crate) event_id: Uuid,
+    /// When the log message was captured.
+    pub(crate) timestamp: SystemTime,
+    /// The log message itself.
+    pub(crate) request: LogRequest,
+    /// Binary artifacts submitted with the log message.
+    pub(crate) attachments: Vec<Attachment>,
+    /// Why the log message does not satisfy the configured schemas, if it does not.
+    pub(crate) validation_error: Option<String>,
+    /// Why the intake refused the log message on its own, once it has.
+    pub(crate) quarantine_error: OnceLock<String>,
+}
+
+/// Checks a built `LogRequest`, describing what is wrong with it if it is invalid.
+pub(crate) type Validate = Arc<dyn Fn(&LogRequest) -> Result<(), String> + Send + Sync>;
+
+/// Builds the `LogRequest` for a captured event once the appender is available.
+pub(crate) type BuildRequest = Box<dyn FnOnce(&PogrAppender) -> LogRequest + Send>;
+
+/// A captured event waiting in the worker's queue.
+pub(crate) struct Queued {
+    /// Client-generated identifier of the log message.
+    pub(crate) event_id: Uuid,
+    /// Severity of the event.
+    pub(crate) level: Level,
+    /// When the event was captured.
+    pub(crate) timestamp: SystemTime,
+    /// Produces the log message.
+    pub(crate) build: BuildRequest,
+    /// Binary artifacts attached to the event, already checked against the size limits.
+    pub(crate) attachments: Vec<Attachment>,
+    /// Receives the log ID the intake assigns, if it is tracked.
+    pub(crate) log_id: Option<LogIdSlot>,
+    /// Keeps the event counted as in flight until it has been submitted.
+    pub(crate) ticket: InFlightTicket,
+    /// Estimated size of the event in bytes, counted against the memory budget.
+    pub(crate) size: usize,
+    /// Keeps the size counted until the event is built into a request, once queued.
+    pub(crate) charge: Option<MemoryCharge>,
+}
+
+/// Counts log submissions that have been captured but not yet completed.
+///
+/// Every queued event holds an `InFlightTicket`; dropping the last ticket wakes any task
+/// waiting in `wait_idle`. Waiting also asks the worker to submit a partially filled batch
+/// right away instead of lingering.
+#[derive(Default)]
+pub(crate) struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+    flush_requested: Notify,
+    /// Number of flushes started, so that records held back, such as coalesced repeats, are
+    /// released by flushes started before they began waiting.
+    flushes: AtomicU64,
+    /// Wakes everything holding back records when a flush starts.
+    flushing: Notify,
+    /// Number of flushes waiting for submissions to complete.
+    waiting: AtomicUsize,
+    /// Whether so many submissions are pending that the pipeline is backpressured.
+    pub(crate) backpressure: Backpressure,
+    /// Number of submissions started so far, numbering their tickets.
+    started: AtomicU64,
+    /// Submissions numbered below this are abandoned to the fallback sinks.
+    abandon_before: AtomicU64,
+    /// Wakes the submissions waiting on the intake when some are abandoned.
+    abandoning: Notify,
+    /// Set once the layer is shutting down, and stops accepting new events.
+    closed: AtomicBool,
+}
+
+impl InFlight {
+    /// Registers a new submission and returns the ticket that marks its completion.
+    pub(crate) fn start(self: &Arc<Self>) -> InFlightTicket {
+        let pending = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+        self.backpressure.update(pending);
+        InFlightTicket(Arc::clone(self), self.started.fetch_add(1, Ordering::SeqCst))
+    }
+
+    /// Resolves once no submissions are running.
+    pub(crate) async fn wait_idle(&self) {
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        let _waiting = WaitingFlush(self);
+        loop {
+            // Register interest before checking the counter so a completion between the
+            // check and the await cannot be missed.
+            let notified = self.idle.notified();
+            if self.count.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            self.flush_requested.notify_one();
+            self.flushes.fetch_add(1, Ordering::SeqCst);
+            self.flushing.notify_waiters();
+            notified.await;
+        }
+    }
+
+    /// Waits until every submission has completed, or `timeout` has passed.
+    ///
+    /// On timeout, the submissions still pending keep going under `PausePolicy::Spool`, and
+    /// are abandoned to the fallback sinks under `PausePolicy::Drop`.
+    pub(crate) async fn drain(&self, timeout: Duration, policy: PausePolicy) -> Result<(), FlushTimedOut> {
+        if tokio::time::timeout(timeout, self.wait_idle()).await.is_ok() {
+            return Ok(());
+        }
+
+        let left_behind = self.pending();
+        if left_behind == 0 {
+            return Ok(());
+        }
+        let fate = match policy {
+            PausePolicy::Spool => "kept for delivery",
+            PausePolicy::Drop => {
+                self.abandon_before.store(self.started.load(Ordering::SeqCst), Ordering::SeqCst);
+                self.abandoning.notify_waiters();
+                "handed to the fallback sinks"
+            }
+        };
+        diagnostics::warn(
+            DiagnosticKind::Dropped,
+            format!("Flush timed out after {} ms with {} records {}", timeout.as_millis(), left_behind, fate),
+        );
+        Err(FlushTimedOut { left_behind, policy })
+    }
+
+    /// Stops the layer from accepting new events.
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` once the layer is shutting down.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the submission with the given ticket number was abandoned.
+    fn is_abandoned(&self, ticket: u64) -> bool {
+        ticket < self.abandon_before.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the submission with the given ticket number is abandoned.
+    async fn abandoned(&self, ticket: u64) {
+        loop {
+            let notified = self.abandoning.notified();
+            if self.is_abandoned(ticket) {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Returns the number of submissions that have not completed yet.
+    pub(crate) fn pending(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of flushes started so far, to wait for the next one with `flushing`.
+    pub(crate) fn flushes(&self) -> u64 {
+        self.flushes.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` while a flush is waiting for submissions to complete.
+    pub(crate) fn is_flushing(&self) -> bool {
+        self.waiting.load(Ordering::SeqCst) > 0
+    }
+
+    /// Resolves once a flush is started after `flushes` flushes.
+    pub(crate) async fn flushing(&self, flushes: u64) {
+        loop {
+            let notified = self.flushing.notified();
+            if self.flushes.load(Ordering::SeqCst) > flushes {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Counts a flush as waiting until it completes or is cancelled.
+struct WaitingFlush<'a>(&'a InFlight);
+
+impl Drop for WaitingFlush<'_> {
+    fn drop(&mut self) {
+        self.0.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Marks a single in-flight submission, numbered in the order they started; the submission is
+/// considered complete when dropped.
+pub(crate) struct InFlightTicket(Arc<InFlight>, u64);
+
+/// Error returned when a flush with a timeout leaves records behind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlushTimedOut {
+    /// Number of records that were still pending.
+    left_behind: usize,
+    /// What happens to them.
+    policy: PausePolicy,
+}
+
+impl FlushTimedOut {
+    /// Returns how many records were still waiting for delivery when the flush timed out.
+    pub fn left_behind(&self) -> usize {
+        self.left_behind
+    }
+
+    /// Returns what happens to the records left behind: with `PausePolicy::Spool` they are
+    /// still delivered in the background, with `PausePolicy::Drop` they are handed to the
+    /// fallback sinks instead.
+    pub fn policy(&self) -> PausePolicy {
+        self.policy
+    }
+}
+
+impl fmt::Display for FlushTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "flush timed out with {} records left behind", self.left_behind)
+    }
+}
+
+impl Error for FlushTimedOut {}
+
+impl Drop for InFlightTicket {
+    fn drop(&mut self) {
+        let pending = self.0.count.fetch_sub(1, Ordering::SeqCst) - 1;
+        self.0.backpressure.update(pending);
+        if pending == 0 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// Everything the worker needs to submit a batch.
+#[derive(Clone)]
+pub(crate) struct Delivery {
+    /// Appender used to submit requests.
+    pub(crate) appender: Arc<Mutex<PogrAppender>>,
+    /// Logs endpoint used instead of the appender's, for level routes with their own.
+    pub(crate) logs_endpoint: Option<String>,
+    /// Maximum size of a request body in bytes.
+    pub(crate) max_request_size: usize,
+    /// How attachments are transferred.
+    pub(crate) encoding: AttachmentEncoding,
+    /// How request bodies are encoded, shared by all workers.
+    pub(crate) payload_encoding: Arc<EncodingNegotiation>,
+    /// Local sinks receiving copies of the records.
+    pub(crate) sinks: Sinks,
+    /// Whether records are submitted to the POGR intake, or only written to the sinks.
+    pub(crate) intake: bool,
+    /// Replaces the appender's client once its connections reach their maximum age.
+    pub(crate) connection_refresh: Option<Arc<ConnectionRefresh>>,
+    /// Picks the logs endpoint among equivalent ones, if they are raced.
+    pub(crate) endpoint_race: Option<Arc<EndpointRacer>>,
+    /// Decides whether failed submissions are retried, if they are.
+    pub(crate) retry: Option<Arc<dyn RetryPolicy>>,
+    /// How responses with particular statuses are handled, if overridden.
+    pub(crate) statuses: Option<Arc<StatusTable>>,
+    /// OpenTelemetry collector that records are exported to instead of the intake.
+    pub(crate) otlp: Option<Arc<OtlpConfig>>,
+    /// Whether the intake has paused delivery, shared by all workers.
+    pub(crate) kill_switch: Arc<KillSwitch>,
+    /// Checks records before they are submitted, if they are validated.
+    pub(crate) validate: Option<Validate>,
+    /// Checks how long records take to be delivered, if saturation alerts are enabled.
+    pub(crate) saturation: Option<Arc<SaturationMonitor>>,
+    /// Age past which records are dropped instead of delivered, if any.
+    pub(crate) ttl: Option<Duration>,
+    /// Counts the records dropped for their age, if drop reports are enabled.
+    pub(crate) drop_stats: Option<Arc<DropStats>>,
+    /// Generates the idempotency keys of the requests.
+    pub(crate) ids: Arc<dyn IdGenerator>,
+    /// Measures the serialized records, if payload size telemetry is enabled.
+    pub(crate) payload_stats: Option<PayloadStats>,
+    /// Emptied batches of the worker, reused for its next batches.
+    pub(crate) batches: Arc<Pool<Vec<Queued>>>,
+}
+
+/// Number of emptied batches a worker keeps for reuse.
+const POOLED_BATCHES: usize = 8;
+
+/// Largest number of records a batch may have room for to be reused.
+const MAX_POOLED_BATCH: usize = 4096;
+
+/// Returns a pool for the emptied batches of a worker.
+pub(crate) fn batch_pool() -> Arc<Pool<Vec<Queued>>> {
+    Arc::new(Pool::new(POOLED_BATCHES, MAX_POOLED_BATCH))
+}
+
+impl Delivery {
+    /// Removes the records older than the time-to-live from a request, counting them as
+    /// dropped.
+    fn expire(&self, request: &mut Vec<&LogRecord>) {
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+        let now = SystemTime::now();
+        let before = request.len();
+        request.retain(|record| {
+            let expired = now.duration_since(record.timestamp).is_ok_and(|age| age > ttl);
+            if let (true, Some(stats)) = (expired, &self.drop_stats) {
+                stats.record(record.request.severity.parse().unwrap_or(Level::INFO), DropReason::Expired);
+            }
+            !expired
+        });
+        let expired = before - request.len();
+        if expired > 0 {
+            diagnostics::warn(
+                DiagnosticKind::Dropped,
+                format!("Dropped {} records older than the time-to-live of {} ms", expired, ttl.as_millis()),
+            );
+        }
+    }
+}
+
+/// Spawns the background worker on `runtime` and returns the sender used to queue events for
+/// it.
+///
+/// Without a runtime, the worker runs on a thread of its own, so that logging from a thread
+/// outside of any runtime never panics.
+pub(crate) fn spawn_worker(
+    delivery: Delivery,
+    config: BatchConfig,
+    in_flight: Arc<InFlight>,
+    runtime: Option<Handle>,
+) -> QueueSender {
+    let (sender, receiver) = ring::queue(config.queue_capacity, config.overflow, config.memory_budget);
+    let worker = run_worker(delivery, config, in_flight, receiver);
+    match runtime {
+        Some(runtime) => {
+            runtime.spawn(worker);
+        }
+        None => {
+            // If the thread cannot start, the receiver is dropped with the worker, and records
+            // are reported as dropped when they are queued.
+            let _ = std::thread::Builder::new()
+                .name("pogr-delivery".to_string())
+                .spawn(move || match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                    Ok(runtime) => runtime.block_on(worker),
+                    Err(err) => diagnostics::error(
+                        DiagnosticKind::Delivery,
+                        format!("Failed to start a runtime for the delivery worker: {}", err),
+                    ),
+                });
+        }
+    }
+    sender
+}
+
+/// Receives queued events, groups them into batches and submits them.
+async fn run_worker(
+    delivery: Delivery,
+    config: BatchConfig,
+    in_flight: Arc<InFlight>,
+    mut receiver: QueueReceiver,
+) {
+    let submissions = Arc::new(Semaphore::new(config.max_concurrent_requests));
+    if let ShippingSchedule::Interval(interval) = config.schedule {
+        run_scheduled(&delivery, &config, interval, &in_flight, &mut receiver, &submissions).await;
+        let _ = submissions.acquire_many(config.max_concurrent_requests as u32).await;
+        return;
+    }
+
+    while let Some(first) = receiver.recv().await {
+        let mut batch = delivery.batches.take();
+        batch.push(first);
+
+        if config.max_batch_size > 1 {
+            let linger = tokio::time::sleep(config.linger);
+            tokio::pin!(linger);
+
+            while batch.len() < config.max_batch_size {
+                // Drain queued events before honoring a flush, so a flush submits everything
+                // captured before it in one go.
+                tokio::select! {
+                    biased;
+                    queued = receiver.recv() => match queued {
+                        Some(queued) => batch.push(queued),
+                        None => break,
+                    },
+                    _ = &mut linger => break,
+                    _ = in_flight.flush_requested.notified() => break,
+                }
+            }
+        }
+
+        dispatch(&delivery, batch, &in_flight, &submissions).await;
+    }
+
+    // Wait for the submissions still running, which a dedicated runtime would otherwise cancel
+    // when the worker returns.
+    let _ = submissions.acquire_many(config.max_concurrent_requests as u32).await;
+}
+
+/// Receives queued events and submits everything queued once per `interval`, or right away
+/// when a flush starts or an event at or above the immediate level arrives.
+async fn run_scheduled(
+    delivery: &Delivery,
+    config: &BatchConfig,
+    interval: Duration,
+    in_flight: &Arc<InFlight>,
+    receiver: &mut QueueReceiver,
+    submissions: &Arc<Semaphore>,
+) {
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut pending = Vec::new();
+    let mut open = true;
+
+    while open {
+        let flushes = in_flight.flushes();
+        // A flush that started before `flushes` was read is still waiting for this worker.
+        let ship = (in_flight.is_flushing() && !pending.is_empty())
+            || tokio::select! {
+                biased;
+                queued = receiver.recv() => match queued {
+                    Some(queued) => {
+                        let immediate = config.immediate_level.is_some_and(|level| queued.level <= level);
+                        pending.push(queued);
+                        immediate || in_flight.is_flushing()
+                    }
+                    None => {
+                        open = false;
+                        true
+                    }
+                },
+                _ = ticks.tick() => true,
+                _ = in_flight.flushing(flushes) => true,
+            };
+        if !ship {
+            continue;
+        }
+
+        while let Some(queued) = receiver.try_recv() {
+            pending.push(queued);
+        }
+        if !pending.is_empty() {
+            let batch = std::mem::replace(&mut pending, delivery.batches.take());
+            dispatch(delivery, batch, in_flight, submissions).await;
+        }
+    }
+}
+
+/// Submits a batch on a task of its own, once one of the `submissions` permits is free.
+async fn dispatch(delivery: &Delivery, batch: Vec<Queued>, in_flight: &Arc<InFlight>, submissions: &Arc<Semaphore>) {
+    // Submit on a separate task so that a failing submission only loses its own batch and
+    // never takes the worker down with it. The permit is released when the task ends, even if
+    // it panics.
+    let permit = Arc::clone(submissions)
+        .acquire_owned()
+        .await
+        .expect("the submission semaphore is never closed");
+    tokio::spawn(deliver(delivery.clone(), batch, Arc::clone(in_flight), permit));
+}
+
+/// Builds, size-checks and submits a batch of queued events.
+///
+/// Records with multipart attachments are submitted on their own; the rest are split into
+/// requests that fit the size limit. Each request is mirrored to the mirror sinks, submitted to
+/// the intake or exported to the OpenTelemetry collector, and handed to the fallback sinks if
+/// it fails. Without intake delivery, only the mirror sinks are used. Records failing
+/// validation only go to the fallback sinks.
+async fn deliver(delivery: Delivery, mut batch: Vec<Queued>, in_flight: Arc<InFlight>, _permit: OwnedSemaphorePermit) {
+    // Work on a copy, so that concurrent submissions do not wait for each other's lock.
+    let appender = {
+        let mut appender = delivery.appender.lock().await;
+        if let (true, Some(refresh)) = (delivery.intake, &delivery.connection_refresh) {
+            refresh.refresh(&mut appender.client);
+        }
+        let mut appender = appender.clone();
+        if let Some(logs_endpoint) = &delivery.logs_endpoint {
+            appender.logs_endpoint = logs_endpoint.clone();
+        }
+        appender
+    };
+    let mut appender = match (&delivery.logs_endpoint, &delivery.endpoint_race) {
+        (None, Some(race)) if delivery.intake && delivery.otlp.is_none() => PogrAppender {
+            logs_endpoint: race.endpoint(&appender).await,
+            ..appender
+        },
+        _ => appender,
+    };
+
+    let mut records = Vec::with_capacity(batch.len());
+    let mut invalid = Vec::new();
+    let mut tickets = Vec::with_capacity(batch.len());
+    let mut log_ids = HashMap::new();
+    for queued in batch.drain(..) {
+        if let Some(slot) = queued.log_id {
+            log_ids.insert(queued.event_id, slot);
+        }
+        let mut record = LogRecord {
+            event_id: queued.event_id,
+            timestamp: queued.timestamp,
+            request: (queued.build)(&appender),
+            attachments: queued.attachments,
+            validation_error: None,
+            quarantine_error: OnceLock::new(),
+        };
+        tickets.push(queued.ticket);
+        if let Some(Err(err)) = delivery.validate.as_ref().map(|validate| validate(&record.request)) {
+            diagnostics::warn(
+                DiagnosticKind::Schema,
+                format!("Record {} does not satisfy the schema: {}", record.event_id, err),
+            );
+            record.validation_error = Some(err);
+            invalid.push(record);
+            continue;
+        }
+        if let Some(payload_stats) = &delivery.payload_stats {
+            payload_stats.observe(&record.request, record_size(&record));
+        }
+        drop_attachments_to_fit(&mut record, delivery.max_request_size);
+        fit_to_size(&mut record, delivery.max_request_size);
+        records.push(record);
+    }
+    delivery.batches.put(batch);
+    if !invalid.is_empty() {
+        delivery.sinks.write(SinkMode::Fallback, &invalid.iter().collect::<Vec<_>>());
+    }
+
+    let newest_ticket = tickets.iter().map(|ticket: &InFlightTicket| ticket.1).max().unwrap_or_default();
+    let (multipart, json): (Vec<&LogRecord>, Vec<&LogRecord>) = records
+        .iter()
+        .partition(|record| delivery.encoding == AttachmentEncoding::Multipart && !record.attachments.is_empty());
+
+    let mut requests = split_by_size(json, delivery.max_request_size);
+    requests.extend(multipart.into_iter().map(|record| vec![record]));
+
+    for mut request in requests {
+        delivery.expire(&mut request);
+        if request.is_empty() {
+            continue;
+        }
+        delivery.sinks.write(SinkMode::Mirror, &request);
+        if !delivery.intake {
+            continue;
+        }
+
+        let result = match &delivery.otlp {
+            Some(otlp) => otlp.send(&appender.client, &appender, &request).await.map(|()| None),
+            None => submit(&delivery, &mut appender, &mut request, &in_flight, newest_ticket).await,
+        };
+        // A record the intake refuses must not take the rest of the request down with it.
+        let outcomes = match result {
+            Err(err) if request.len() > 1 && refused(&delivery, &err) => {
+                isolate(&delivery, &mut appender, request, &in_flight, newest_ticket).await
+            }
+            result => vec![(request, result)],
+        };
+        for (request, mut result) in outcomes {
+            if let (Err(DeliveryError::Serialization(err)), [record]) = (&result, request.as_slice()) {
+                result = submit_degraded(&delivery, &mut appender, record, err, &in_flight, newest_ticket).await;
+            }
+            if let (Ok(_), Some(saturation)) = (&result, &delivery.saturation) {
+                if let Some(oldest) = request.iter().map(|record| record.timestamp).min() {
+                    saturation.check_latency(oldest);
+                }
+            }
+            match result {
+                // The intake returns one log ID per request, so only single records are correlated.
+                Ok(Some(log_id)) => {
+                    if let [record] = request.as_slice() {
+                        if let Some(slot) = log_ids.get(&record.event_id) {
+                            let _ = slot.set(LogReference {
+                                event_id: record.event_id,
+                                log_id,
+                            });
+                        }
+                    }
+                }
+                Ok(None) => {}
+                // The pause was reported when it started, and abandoned records by the flush.
+                Err(DeliveryError::Paused(_) | DeliveryError::Abandoned) => {
+                    delivery.sinks.write(SinkMode::Fallback, &request)
+                }
+                Err(err) => {
+                    diagnostics::error(DiagnosticKind::Delivery, format!("Failed to log to POGR: {}", err));
+                    if let [record] = request.as_slice() {
+                        if refused(&delivery, &err) {
+                            let _ = record.quarantine_error.set(err.to_string());
+                        }
+                    }
+                    delivery.sinks.write(SinkMode::Fallback, &request);
+                }
+            }
+        }
+    }
+}
+
+/// Returns `true` if the intake refused the records of a request on every attempt, or they
+/// could not be serialized, so that a single record may be to blame.
+///
+/// Failures the status table handles are not put down to the records.
+fn refused(delivery: &Delivery, err: &DeliveryError) -> bool {
+    if delivery.otlp.is_some() {
+        return false;
+    }
+    let handled = delivery
+        .statuses
+        .as_ref()
+        .zip(err.status())
+        .is_some_and(|(statuses, status)| statuses.action(status.as_u16()).is_some());
+    match err {
+        DeliveryError::Serialization(_) => true,
+        _ => !handled && matches!(err.retry_class(), Some((ErrorClass::Rejected, _))),
+    }
+}
+
+/// Submits a record that could not be serialized once more, in the degraded form of
+/// `lossy::degrade`.
+async fn submit_degraded(
+    delivery: &Delivery,
+    appender: &mut PogrAppender,
+    record: &LogRecord,
+    error: &str,
+    in_flight: &InFlight,
+    newest_ticket: u64,
+) -> Result<Option<String>, DeliveryError> {
+    diagnostics::warn(
+        DiagnosticKind::Serialization,
+        format!("Record {} could not be serialized, submitting it degraded: {}", record.event_id, error),
+    );
+    let degraded = lossy::degrade(record, error);
+    submit(delivery, appender, &mut vec![&degraded], in_flight, newest_ticket).await
+}
+
+/// Splits a request the intake refused into halves submitted on their own, until the records
+/// it refuses on their own are isolated, and returns each submission with its outcome.
+///
+/// If the intake refuses both halves of a request, it does not refuse a single record, and the
+/// halves are not split any further.
+async fn isolate<'a>(
+    delivery: &Delivery,
+    appender: &mut PogrAppender,
+    request: Vec<&'a LogRecord>,
+    in_flight: &InFlight,
+    newest_ticket: u64,
+) -> Vec<(Vec<&'a LogRecord>, Result<Option<String>, DeliveryError>)> {
+    let mut outcomes = Vec::new();
+    let mut refused_requests = vec![request];
+    while let Some(mut first) = refused_requests.pop() {
+        let second = first.split_off(first.len() / 2);
+        let mut halves = Vec::with_capacity(2);
+        for mut half in [first, second] {
+            let result = submit(delivery, appender, &mut half, in_flight, newest_ticket).await;
+            halves.push((half, result));
+        }
+        let both_refused = halves.iter().all(|(_, result)| matches!(result, Err(err) if refused(delivery, err)));
+        for (half, result) in halves {
+            match result {
+                Err(err) if !both_refused && half.len() > 1 && refused(delivery, &err) => refused_requests.push(half),
+                result => outcomes.push((half, result)),
+            }
+        }
+    }
+    outcomes
+}
+
+/// Submits a request to the intake, unless the intake has paused delivery.
+///
+/// With `PausePolicy::Spool`, the request waits for the pause to end, including a pause the
+/// intake asks for in response to the request itself. It fails with `DeliveryError::Paused` if
+/// delivery is paused under `PausePolicy::Drop`, or if a flush interrupts the wait. It fails
+/// with `DeliveryError::Abandoned` once a flush that timed out abandons the request, whose
+/// newest ticket is `newest_ticket`. Records that expire while waiting for a pause to end are
+/// removed from the request. If the intake refuses the payload encoding, the request is sent
+/// again as JSON. Other failures are handled as the status table says, if it has an entry for
+/// their status, and are otherwise retried as long as the retry policy, if any, decides to.
+/// The session is re-initialized at most once per request.
+async fn submit(
+    delivery: &Delivery,
+    appender: &mut PogrAppender,
+    request: &mut Vec<&LogRecord>,
+    in_flight: &InFlight,
+    newest_ticket: u64,
+) -> Result<Option<String>, DeliveryError> {
+    let kill_switch = &delivery.kill_switch;
+    // Retries of the request carry the same key, so the intake can discard duplicates.
+    let idempotency_key = delivery.ids.generate(IdKind::IdempotencyKey);
+    let mut attempt = 0;
+    let mut reinitialized = false;
+    loop {
+        if in_flight.is_abandoned(newest_ticket) {
+            return Err(DeliveryError::Abandoned);
+        }
+
+        let paused = match kill_switch.policy() {
+            PausePolicy::Spool => !kill_switch.resumed(in_flight).await,
+            PausePolicy::Drop => kill_switch.paused_until().is_some(),
+        };
+        if paused {
+            let remaining = kill_switch.paused_until().map(|until| until.saturating_duration_since(Instant::now()));
+            return Err(DeliveryError::Paused(remaining.unwrap_or_default()));
+        }
+        delivery.expire(request);
+        if request.is_empty() {
+            return Ok(None);
+        }
+
+        let payload_encoding = delivery.payload_encoding.current();
+        let result = tokio::select! {
+            result = appender.try_send_encoded(request, delivery.encoding, payload_encoding, Some(idempotency_key)) => result,
+            _ = in_flight.abandoned(newest_ticket) => Err(DeliveryError::Abandoned),
+        };
+        match result {
+            Err(DeliveryError::Paused(duration)) => kill_switch.pause(duration),
+            Err(DeliveryError::UnsupportedEncoding) if delivery.payload_encoding.refuse(payload_encoding) => {}
+            Err(err) => {
+                let action = delivery
+                    .statuses
+                    .as_ref()
+                    .zip(err.status())
+                    .and_then(|(statuses, status)| statuses.action(status.as_u16()));
+                let retry_class = match action {
+                    Some(StatusAction::DeadLetter) => return Err(err),
+                    Some(StatusAction::Reinitialize) if reinitialized => return Err(err),
+                    Some(StatusAction::Reinitialize) => {
+                        reinitialized = true;
+                        match reinitialize(delivery, &appender.session_id).await {
+                            Ok(session_id) => appender.session_id = session_id,
+                            Err(message) => {
+                                diagnostics::error(
+                                    DiagnosticKind::Init,
+                                    format!("Failed to re-initialize the POGR session: {}", message),
+                                );
+                                return Err(err);
+                            }
+                        }
+                        continue;
+                    }
+                    Some(StatusAction::Retry) => err.retry_class().map(|(_, retry_after)| (ErrorClass::Retryable, retry_after)),
+                    None => err.retry_class(),
+                };
+                attempt += 1;
+                let delay = match (&delivery.retry, retry_class) {
+                    (Some(retry), Some((class, retry_after))) => retry.next_delay(attempt, class, retry_after),
+                    (None, Some((class, retry_after))) if action == Some(StatusAction::Retry) => {
+                        ExponentialBackoff::new().next_delay(attempt, class, retry_after)
+                    }
+                    _ => None,
+                };
+                let Some(delay) = delay else {
+                    return Err(err);
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = in_flight.abandoned(newest_ticket) => return Err(DeliveryError::Abandoned),
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Initializes a new session for the layer's appender after the intake refused the session
+/// `refused`, and returns the session to submit in.
+///
+/// If another worker has replaced the refused session already, its replacement is returned.
+/// The appender is not locked during the init request, so other workers and events are not
+/// held up by it.
+async fn reinitialize(delivery: &Delivery, refused: &str) -> Result<String, String> {
+    let appender = {
+        let appender = delivery.appender.lock().await;
+        if appender.session_id != refused {
+            return Ok(appender.session_id.clone());
+        }
+        appender.clone()
+    };
+    let session_id = appender.reinitialize().await?;
+
+    let mut appender = delivery.appender.lock().await;
+    if appender.session_id == refused {
+        appender.session_id = session_id;
+    }
+    Ok(appender.session_id.clone())
+}
+
+impl LogRecord {
+    /// Returns the payload sent to the intake for this record.
+    pub(crate) fn envelope(&self) -> LogEnvelope<'_> {
+        payload::encode(self, SchemaVersion::CURRENT)
+    }
+}
+
+/// Returns the serialized size of a record in bytes.
+fn record_size(record: &LogRecord) -> usize {
+    pool::json_size(&record.envelope())
+}
+
+/// Groups records into chunks whose JSON array encoding fits within `max_request_size`.
+///
+/// A record that is too large on its own still gets a chunk of its own.
+fn split_by_size(records: Vec<&LogRecord>, max_request_size: usize) -> Vec<Vec<&LogRecord>> {
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    // Size of the current chunk encoded as a JSON array: brackets plus separating commas.
+    let mut current_size = 2;
+
+    for record in records {
+        let size = record_size(record);
+        let separator = usize::from(!current.is_empty());
+
+        if !current.is_empty() && current_size + separator + size > max_request_size {
+            chunks.push(std::mem::take(&mut current));
+            current_size = 2;
+        }
+
+        current_size += usize::from(!current.is_empty()) + size;
+        current.push(record);
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Drops attachments, largest first, from a record that does not fit into a request.
+///
+/// Attachments are the least essential part of an event, so they go before any field is
+/// truncated. Their names are listed in the `pogr.dropped_attachments` tag.
+fn drop_attachments_to_fit(record: &mut LogRecord, max_request_size: usize) {
+    let mut dropped = Vec::new();
+    while !record.attachments.is_empty() && record_size(record) > max_request_size {
+        let largest = (0..record.attachments.len())
+            .max_by_key(|&index| record.attachments[index].size())
+            .unwrap_or_default();
+        dropped.push(record.attachments.remove(largest).name().to_string());
+    }
+
+    attachment::annotate_dropped(&mut record.request.tags, &dropped);
+    if !dropped.is_empty() {
+        diagnostics::warn(
+            DiagnosticKind::Dropped,
+            format!(
+                "Dropped attachments of event {} to fit into a request: {}",
+                record.event_id,
+                dropped.join(", ")
+            ),
+        );
+    }
+}
+
+/// Shrinks a record that does not fit into a request on its own.
+///
+/// Long string fields are shortened first, largest first, so the event keeps its structure.
+/// If that is not enough, the tags are replaced by a preview of their serialized form. Either
+/// way the tags are annotated with `pogr.truncated` and `pogr.original_size`.
+fn fit_to_size(record: &mut LogRecord, max_request_size: usize) {
+    let original_size = record_size(record);
+    if original_size <= max_request_size {
+        return;
+    }
+    diagnostics::warn(
+        DiagnosticKind::Truncated,
+        format!(
+            "Truncated event {} of {} bytes to fit into a request of at most {} bytes",
+            record.event_id, original_size, max_request_size
+        ),
+    );
+
+    let original_tags = std::mem::take(&mut record.request.tags);
+    let mut tags = match original_tags.clone() {
+        Value::Object(tags) => tags,
+        Value::Null => Map::new(),
+        other => {
+            let mut tags = Map::new();
+            tags.insert("value".to_string(), other);
+            tags
+        }
+    };
+    tags.insert(TRUNCATED_TAG.to_string(), json!(true));
+    tags.insert(ORIGINAL_SIZE_TAG.to_string(), json!(original_size));
+    tags.insert(TRUNCATED_FIELDS_TAG.to_string(), json!([]));
+
+    // JSON escaping makes the serialized length of a string hard to predict, so shorten in
+    // a few passes, each based on the remaining overshoot.
+    let mut truncated_fields: Vec<String> = Vec::new();
+    for _ in 0..3 {
+        record.request.tags = Value::Object(tags.clone());
+        let excess = record_size(record).saturating_sub(max_request_size);
+        if excess == 0 {
+            return;
+        }
+
+        for field in shorten_strings(&mut tags, excess) {
+            if !truncated_fields.contains(&field) {
+                truncated_fields.push(field);
+            }
+        }
+        tags.insert(TRUNCATED_FIELDS_TAG.to_string(), json!(truncated_fields));
+    }
+
+    record.request.tags = Value::Object(tags);
+    if record_size(record) <= max_request_size {
+        return;
+    }
+
+    // The fields are too many or too nested to shorten one by one; keep a preview instead.
+    let serialized = serde_json::to_string(&original_tags).unwrap_or_default();
+    let mut annotation = Map::new();
+    annotation.insert(TRUNCATED_TAG.to_string(), json!(true));
+    annotation.insert(ORIGINAL_SIZE_TAG.to_string(), json!(original_size));
+    annotation.insert(PREVIEW_TAG.to_string(), json!(""));
+    record.request.tags = Value::Object(annotation.clone());
+
+    let mut preview_len = max_request_size.saturating_sub(record_size(record));
+    for _ in 0..4 {
+        annotation.insert(PREVIEW_TAG.to_string(), json!(truncate_str(&serialized, preview_len)));
+        record.request.tags = Value::Object(annotation.clone());
+
+        let overshoot = record_size(record).saturating_sub(max_request_size);
+        if overshoot == 0 || preview_len == 0 {
+            return;
+        }
+        preview_len = preview_len.saturating_sub(overshoot);
+    }
+}
+
+/// Shortens top-level string values, largest first, until about `excess` bytes were removed.
+///
+/// Returns the names of the fields that were shortened.
+fn shorten_strings(tags: &mut Map<String, Value>, excess: usize) -> Vec<String> {
+    let mut candidates: Vec<(String, usize)> = tags
+        .iter()
+        .filter_map(|(key, value)| value.as_str().map(|text| (key.clone(), text.len())))
+        .collect();
+    candidates.sort_by_key(|(_, len)| std::cmp::Reverse(*len));
+
+    let mut remaining = excess;
+    let mut shortened = Vec::new();
+    for (key, len) in candidates {
+        if remaining == 0 {
+            break;
+        }
+
+        // Leave room for the ellipsis and a little slack for JSON escaping.
+        let keep = len.saturating_sub(remaining + ELLIPSIS.len() + 16);
+        if let Some(Value::String(text)) = tags.get_mut(&key) {
+            let mut truncated = truncate_str(text, keep).to_string();
+            truncated.push_str(ELLIPSIS);
+            remaining = remaining.saturating_sub(len.saturating_sub(truncated.len()));
+            *text = truncated;
+            shortened.push(key);
+        }
+    }
+
+    shortened
+}
+
+/// Returns the longest prefix of `text` that is at most `max_len` bytes and ends on a char boundary.
+fn truncate_str(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
@@ -0,0 +1,87 @@
+//! Detection of the distribution platform a game client was launched from.
+//!
+//! Storefront launchers identify the game to the process they start: the Steam client sets
+//! `SteamAppId` (or `SteamGameId`) in the environment, and the Epic Games Launcher passes
+//! `-epicapp=<app name>` on the command line. Consoles have no such convention, so console
+//! builds set `POGR_PLATFORM` (e.g. `ps5`) and `POGR_PLATFORM_ID` (e.g. the title's SKU) in
+//! their launch environment. These two variables take precedence over the launcher detection,
+//! so they can also correct it on PC.
+
+use serde_json::Value;
+use std::env;
+
+/// Environment variables the Steam client sets to the app ID of the launched game.
+const STEAM_APP_ID_VARS: [&str; 2] = ["SteamAppId", "SteamGameId"];
+
+/// Command-line argument prefix the Epic Games Launcher uses for the app name.
+const EPIC_APP_ARG: &str = "-epicapp=";
+
+/// The storefront or console a game client was distributed through.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::DistributionPlatform;
+///
+/// match DistributionPlatform::detect() {
+///     Some(platform) => println!("launched from {} ({:?})", platform.name(), platform.id()),
+///     None => println!("not launched from a known platform"),
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DistributionPlatform {
+    /// Name of the platform, e.g. `steam`, `epic` or `ps5`.
+    name: String,
+    /// The game's identifier on the platform, e.g. the Steam app ID or a console SKU.
+    id: Option<String>,
+}
+
+impl DistributionPlatform {
+    /// Creates a platform with the given name and, if known, the game's identifier on it.
+    pub fn new(name: impl Into<String>, id: Option<String>) -> Self {
+        DistributionPlatform { name: name.into(), id }
+    }
+
+    /// Detects the platform from `POGR_PLATFORM` and `POGR_PLATFORM_ID`, the Steam client's
+    /// environment variables, or the Epic Games Launcher's command-line arguments.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the process was not launched from a known platform.
+    pub fn detect() -> Option<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.trim().is_empty());
+
+        if let Some(name) = var("POGR_PLATFORM") {
+            return Some(DistributionPlatform::new(name.trim().to_lowercase(), var("POGR_PLATFORM_ID")));
+        }
+        if let Some(app_id) = STEAM_APP_ID_VARS.iter().find_map(|name| var(name)) {
+            return Some(DistributionPlatform::new("steam", Some(app_id)));
+        }
+        env::args()
+            .find_map(|arg| {
+                let prefix = arg.get(..EPIC_APP_ARG.len())?;
+                prefix.eq_ignore_ascii_case(EPIC_APP_ARG).then(|| arg[EPIC_APP_ARG.len()..].to_string())
+            })
+            .map(|app_name| DistributionPlatform::new("epic", Some(app_name).filter(|name| !name.is_empty())))
+    }
+
+    /// Returns the name of the platform, e.g. `steam`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the game's identifier on the platform, if known.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the `platform` and `platform_id` tags describing the platform.
+    pub(crate) fn tags(&self) -> Vec<(String, Value)> {
+        let mut tags = vec![("platform".to_string(), Value::from(self.name.as_str()))];
+        if let Some(id) = &self.id {
+            tags.push(("platform_id".to_string(), Value::from(id.as_str())));
+        }
+        tags
+    }
+}
+
@@ -0,0 +1,124 @@
+//! Reuse of the memory of scratch objects in steady-state logging.
+//!
+//! Every captured event needs a map for its fields, every batch a vector of its records, and
+//! every request a buffer to serialize its body into. Allocating them afresh each time shows
+//! up as allocation churn under load, so they are kept in pools and reused instead:
+//!
+//! - field maps and batches are taken from a lock-free `Pool` and put back, emptied, once they
+//!   are done with; objects that grew beyond a limit are dropped instead of retained,
+//! - request bodies are serialized into a scratch buffer kept per thread, and copied into an
+//!   exactly sized body, instead of growing a fresh buffer step by step,
+//! - record sizes are measured by counting the serialized bytes, without a buffer at all.
+
+use crossbeam_queue::ArrayQueue;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{self, Write};
+
+/// Largest capacity, in bytes, a serialization buffer keeps between uses.
+const MAX_RETAINED_BUFFER: usize = 2 * 1024 * 1024;
+
+thread_local! {
+    /// Serialization buffer of the current thread.
+    static BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An object whose memory can be reused once it is emptied.
+pub(crate) trait Recycle: Default {
+    /// Empties the object, keeping its memory, and returns its capacity.
+    fn recycle(&mut self) -> usize;
+}
+
+impl<T> Recycle for Vec<T> {
+    fn recycle(&mut self) -> usize {
+        self.clear();
+        self.capacity()
+    }
+}
+
+impl<K: Eq + Hash, V> Recycle for HashMap<K, V> {
+    fn recycle(&mut self) -> usize {
+        self.clear();
+        self.capacity()
+    }
+}
+
+/// A bounded, lock-free pool of reusable objects.
+pub(crate) struct Pool<T> {
+    /// The free objects.
+    free: ArrayQueue<T>,
+    /// Largest capacity an object may have to be kept.
+    max_capacity: usize,
+}
+
+impl<T: Recycle> Pool<T> {
+    /// Creates a pool keeping up to `size` free objects of at most `max_capacity` elements each.
+    pub(crate) fn new(size: usize, max_capacity: usize) -> Self {
+        Pool {
+            free: ArrayQueue::new(size.max(1)),
+            max_capacity,
+        }
+    }
+
+    /// Returns a free object, or a new one if there is none.
+    pub(crate) fn take(&self) -> T {
+        self.free.pop().unwrap_or_default()
+    }
+
+    /// Empties an object and keeps it for reuse, unless it grew too large or the pool is full.
+    pub(crate) fn put(&self, mut object: T) {
+        let capacity = object.recycle();
+        if capacity > 0 && capacity <= self.max_capacity {
+            let _ = self.free.push(object);
+        }
+    }
+}
+
+/// Serializes `value` as JSON through the current thread's buffer, and returns an exactly sized
+/// copy.
+pub(crate) fn to_json_vec(value: &impl Serialize) -> serde_json::Result<Vec<u8>> {
+    with_buffer(|buffer| serde_json::to_writer(&mut *buffer, value))
+}
+
+/// Runs `write` on the current thread's serialization buffer, emptied, and returns an exactly
+/// sized copy of what it wrote.
+///
+/// A fresh buffer is used if the thread's buffer is already in use.
+pub(crate) fn with_buffer<E>(write: impl FnOnce(&mut Vec<u8>) -> Result<(), E>) -> Result<Vec<u8>, E> {
+    BUFFER.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut buffer) => {
+            buffer.clear();
+            let written = write(&mut buffer).map(|()| buffer.to_vec());
+            if buffer.capacity() > MAX_RETAINED_BUFFER {
+                *buffer = Vec::new();
+            }
+            written
+        }
+        Err(_) => {
+            let mut buffer = Vec::new();
+            write(&mut buffer).map(|()| buffer)
+        }
+    })
+}
+
+/// Returns the size of `value` serialized as JSON, in bytes, or zero if it cannot be serialized.
+pub(crate) fn json_size(value: &impl Serialize) -> usize {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).map_or(0, |()| counter.0)
+}
+
+/// Counts the bytes written to it.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0 += bytes.len();
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! Process metadata sent with the session.
+//!
+//! Reproducing an issue starts with knowing how the process was started and where it ran. The
+//! init request describes the process: its command-line arguments, the operating system and its
+//! version, and the CPU architecture. Arguments often carry credentials, such as
+//! `--db-password hunter2`, so the values of flags that look sensitive are redacted before they
+//! leave the process.
+//!
+//! The OS version is read from `/proc/sys/kernel/osrelease` on Linux, where the distribution is
+//! also read from `/etc/os-release`, from `sw_vers` on macOS and from `ver` on Windows.
+
+use crate::REDACTED;
+use serde::Serialize;
+use std::env;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::process::Command;
+
+/// Words that mark a flag's value as sensitive by default.
+pub const DEFAULT_REDACTED_ARGS: &[&str] = &["password", "passwd", "secret", "token", "key", "auth", "credential"];
+
+/// Which command-line arguments are redacted in the session's process metadata.
+///
+/// A flag is sensitive if its name, without its leading dashes and in lowercase, contains one
+/// of the redacted words. The value of a sensitive flag, given as `--flag=value` or as the next
+/// argument, is replaced by `"[redacted]"`.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::ArgRedaction;
+///
+/// let redaction = ArgRedaction::new().with_words(["license"]);
+/// let args = ["server", "--db-password", "hunter2", "--license=ABC", "--port", "7777"];
+/// assert_eq!(
+///     redaction.redact(args),
+///     ["server", "--db-password", "[redacted]", "--license=[redacted]", "--port", "7777"]
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgRedaction {
+    /// Words marking a flag as sensitive, in lowercase.
+    words: Vec<String>,
+}
+
+impl Default for ArgRedaction {
+    fn default() -> Self {
+        ArgRedaction::new()
+    }
+}
+
+impl ArgRedaction {
+    /// Redacts the flags containing one of `DEFAULT_REDACTED_ARGS`.
+    pub fn new() -> Self {
+        ArgRedaction {
+            words: DEFAULT_REDACTED_ARGS.iter().map(|word| word.to_string()).collect(),
+        }
+    }
+
+    /// Also redacts the flags containing one of `words`.
+    pub fn with_words<I, T>(mut self, words: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.words
+            .extend(words.into_iter().map(|word| word.into().trim_start_matches('-').to_lowercase()));
+        self
+    }
+
+    /// Returns the arguments with the values of sensitive flags redacted.
+    pub fn redact<I, T>(&self, args: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let mut redact_next = false;
+        args.into_iter()
+            .map(|arg| {
+                let arg = arg.into();
+                if std::mem::take(&mut redact_next) && !arg.starts_with('-') {
+                    return REDACTED.to_string();
+                }
+                let Some(flag) = arg.strip_prefix('-') else {
+                    return arg;
+                };
+                match flag.split_once('=') {
+                    Some((name, _)) if self.is_sensitive(name) => format!("-{}={}", name, REDACTED),
+                    Some(_) => arg,
+                    None => {
+                        redact_next = self.is_sensitive(flag);
+                        arg
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the flag `name`, with or without dashes, is sensitive.
+    fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.trim_start_matches('-').to_lowercase();
+        self.words.iter().any(|word| name.contains(word.as_str()))
+    }
+}
+
+/// The process a session runs in, as sent in the init request.
+#[derive(Serialize)]
+pub(crate) struct ProcessInfo {
+    /// Command-line arguments, with sensitive values redacted.
+    args: Vec<String>,
+    /// Operating system, e.g. `linux`, `macos` or `windows`.
+    os: &'static str,
+    /// Version of the operating system, or of the kernel on Linux, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    os_version: Option<String>,
+    /// Linux distribution, e.g. `Ubuntu 22.04.4 LTS`, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distribution: Option<String>,
+    /// CPU architecture, e.g. `x86_64` or `aarch64`.
+    arch: &'static str,
+}
+
+impl ProcessInfo {
+    /// Describes the current process, redacting its arguments with `redaction`.
+    pub(crate) fn current(redaction: &ArgRedaction) -> Self {
+        ProcessInfo {
+            args: redaction.redact(env::args_os().map(|arg| arg.to_string_lossy().into_owned())),
+            os: env::consts::OS,
+            os_version: os_version(),
+            distribution: distribution(),
+            arch: env::consts::ARCH,
+        }
+    }
+}
+
+/// Returns the kernel release.
+#[cfg(target_os = "linux")]
+fn os_version() -> Option<String> {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    Some(release.trim().to_string()).filter(|release| !release.is_empty())
+}
+
+/// Returns the product version reported by `sw_vers`.
+#[cfg(target_os = "macos")]
+fn os_version() -> Option<String> {
+    let output = Command::new("sw_vers").arg("-productVersion").output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|version| !version.is_empty())
+}
+
+/// Returns the version reported by `ver`, e.g. `10.0.19045.3803`.
+#[cfg(target_os = "windows")]
+fn os_version() -> Option<String> {
+    let output = Command::new("cmd").args(["/C", "ver"]).output().ok()?;
+    let banner = String::from_utf8_lossy(&output.stdout);
+    let version = banner.split("Version ").nth(1)?;
+    Some(version.trim().trim_end_matches(']').to_string())
+}
+
+/// Returns `None`: the OS version is not known on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn os_version() -> Option<String> {
+    None
+}
+
+/// Returns the `PRETTY_NAME` of `/etc/os-release`.
+#[cfg(target_os = "linux")]
+fn distribution() -> Option<String> {
+    let os_release = std::fs::read_to_string("/etc/os-release").ok()?;
+    os_release.lines().find_map(|line| {
+        let name = line.strip_prefix("PRETTY_NAME=")?;
+        Some(name.trim_matches('"').to_string())
+    })
+}
+
+/// Returns `None`: distributions only exist on Linux.
+#[cfg(not(target_os = "linux"))]
+fn distribution() -> Option<String> {
+    None
+}
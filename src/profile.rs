@@ -0,0 +1,138 @@
+//! Default verbosity profiles, selected from the deployment environment.
+//!
+//! The right amount of logging depends on where the service runs: developers want debug
+//! output on their console, staging wants everything informative, and production wants
+//! warnings and errors plus a sample of the informational events, without paying for all of
+//! them. Profiles bundle these defaults, so `init` behaves sensibly in every environment
+//! without configuration:
+//!
+//! | Profile       | Forwarded to POGR                     | Console output |
+//! |---------------|---------------------------------------|----------------|
+//! | `production`  | `WARN` and above, 10% of `INFO`       | no             |
+//! | `staging`     | `INFO` and above                      | no             |
+//! | `development` | `DEBUG` and above                     | yes            |
+//!
+//! The profile follows the session's environment, unless `POGR_PROFILE` names one.
+
+use crate::filter::TargetLevels;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use tracing_subscriber::filter::LevelFilter;
+
+/// Fraction of the `INFO` events forwarded in production.
+const PRODUCTION_INFO_SAMPLE_RATE: f64 = 0.1;
+
+/// A bundle of verbosity defaults for a kind of environment.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, VerbosityProfile};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let profile = VerbosityProfile::detect(&appender.environment);
+/// let layer = PogrLayer::new(appender).with_profile(profile);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VerbosityProfile {
+    /// Warnings and errors, plus a sample of the informational events.
+    Production,
+    /// Informational events and above.
+    Staging,
+    /// Debug events and above, echoed to the console.
+    Development,
+}
+
+impl VerbosityProfile {
+    /// Returns the profile named by `POGR_PROFILE`, or else the profile for `environment`.
+    pub fn detect(environment: &str) -> Self {
+        env::var("POGR_PROFILE")
+            .ok()
+            .and_then(|profile| profile.parse().ok())
+            .unwrap_or_else(|| VerbosityProfile::for_environment(environment))
+    }
+
+    /// Returns the profile for an environment name.
+    ///
+    /// `production`, `prod` and `live` select `Production`; `staging`, `stage`, `preprod`,
+    /// `uat`, `qa` and `test` select `Staging`; anything else selects `Development`. Names are
+    /// compared case-insensitively, and a suffix after `-` or `_` is ignored, so
+    /// `production-eu` is a production environment.
+    pub fn for_environment(environment: &str) -> Self {
+        let environment = environment.trim().to_ascii_lowercase();
+        let kind = environment.split(['-', '_']).next().unwrap_or_default();
+        match kind {
+            "production" | "prod" | "live" => VerbosityProfile::Production,
+            "staging" | "stage" | "preprod" | "uat" | "qa" | "test" => VerbosityProfile::Staging,
+            _ => VerbosityProfile::Development,
+        }
+    }
+
+    /// Returns the minimum levels of the events forwarded to POGR.
+    ///
+    /// In production, `INFO` events pass the thresholds and are then sampled; see
+    /// `info_sample_rate`.
+    pub fn target_levels(&self) -> TargetLevels {
+        match self {
+            VerbosityProfile::Production | VerbosityProfile::Staging => TargetLevels::new(LevelFilter::INFO),
+            VerbosityProfile::Development => TargetLevels::new(LevelFilter::DEBUG),
+        }
+    }
+
+    /// Returns the fraction of the `INFO` events forwarded to POGR.
+    pub fn info_sample_rate(&self) -> f64 {
+        match self {
+            VerbosityProfile::Production => PRODUCTION_INFO_SAMPLE_RATE,
+            VerbosityProfile::Staging | VerbosityProfile::Development => 1.0,
+        }
+    }
+
+    /// Returns `true` if events are also printed to the console.
+    pub fn console_echo(&self) -> bool {
+        *self == VerbosityProfile::Development
+    }
+
+    /// Returns the name of the profile, as accepted by `POGR_PROFILE`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerbosityProfile::Production => "production",
+            VerbosityProfile::Staging => "staging",
+            VerbosityProfile::Development => "development",
+        }
+    }
+}
+
+impl fmt::Display for VerbosityProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses a profile name: `production`, `staging` or `development`, case-insensitively.
+impl FromStr for VerbosityProfile {
+    type Err = ParseProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "production" => Ok(VerbosityProfile::Production),
+            "staging" => Ok(VerbosityProfile::Staging),
+            "development" => Ok(VerbosityProfile::Development),
+            _ => Err(ParseProfileError(s.to_string())),
+        }
+    }
+}
+
+/// Error returned when parsing an unknown profile name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseProfileError(String);
+
+impl fmt::Display for ParseProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown verbosity profile `{}`, expected production, staging or development", self.0)
+    }
+}
+
+impl std::error::Error for ParseProfileError {}
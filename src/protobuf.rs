@@ -0,0 +1,115 @@
+//! Protobuf encoding of the payloads sent to the logs endpoint.
+//!
+//! Protobuf bodies are about half the size of the same records as JSON, mostly because field
+//! names are not repeated for every record and attachments are sent as raw bytes instead of
+//! base64. The schema ships with the crate as [`SCHEMA`], so collectors can generate decoders
+//! for it; the message types of this module implement [`prost::Message`] for Rust collectors.
+//!
+//! A request body is always a [`LogBatch`], whatever the number of records and the intake API
+//! version. The `data` and `tags` of a record are arbitrary JSON, so they are embedded as JSON
+//! documents. Responses are JSON, as for JSON requests.
+
+use crate::clock;
+use crate::pipeline::LogRecord;
+use crate::payload::SchemaVersion;
+use prost::Message;
+
+/// The protobuf schema of the payloads, `proto/pogr_intake.proto`.
+pub const SCHEMA: &str = include_str!("../proto/pogr_intake.proto");
+
+/// Content type of protobuf request bodies.
+pub const CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The body of a request to the logs endpoint.
+#[derive(Clone, PartialEq, Message)]
+pub struct LogBatch {
+    /// The submitted records.
+    #[prost(message, repeated, tag = "1")]
+    pub records: Vec<LogEnvelope>,
+}
+
+/// A log message together with the metadata the client assigns to it.
+#[derive(Clone, PartialEq, Message)]
+pub struct LogEnvelope {
+    /// Version of the payload shape.
+    #[prost(uint32, tag = "1")]
+    pub schema_version: u32,
+    /// Client-generated UUIDv7 identifying the log message, in its hyphenated form.
+    #[prost(string, tag = "2")]
+    pub event_id: String,
+    /// When the log message was captured, as an RFC 3339 UTC timestamp.
+    #[prost(string, tag = "3")]
+    pub timestamp: String,
+    /// Name of the service generating the log.
+    #[prost(string, tag = "4")]
+    pub service: String,
+    /// Deployment environment of the service.
+    #[prost(string, tag = "5")]
+    pub environment: String,
+    /// Severity level of the log message.
+    #[prost(string, tag = "6")]
+    pub severity: String,
+    /// Type of the service.
+    #[prost(string, tag = "7")]
+    pub r#type: String,
+    /// The log message itself.
+    #[prost(string, tag = "8")]
+    pub log: String,
+    /// Structured data of the log message, as a JSON document.
+    #[prost(string, tag = "9")]
+    pub data: String,
+    /// Tags of the log message, as a JSON document.
+    #[prost(string, tag = "10")]
+    pub tags: String,
+    /// Binary artifacts submitted with the log message.
+    #[prost(message, repeated, tag = "11")]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A binary artifact submitted with a log message.
+#[derive(Clone, PartialEq, Message)]
+pub struct Attachment {
+    /// File name of the attachment.
+    #[prost(string, tag = "1")]
+    pub name: String,
+    /// MIME type of the attachment.
+    #[prost(string, tag = "2")]
+    pub content_type: String,
+    /// Raw contents of the attachment.
+    #[prost(bytes = "vec", tag = "3")]
+    pub data: Vec<u8>,
+}
+
+/// Encodes records as a protobuf request body.
+pub(crate) fn encode(records: &[&LogRecord]) -> Vec<u8> {
+    LogBatch {
+        records: records.iter().map(|record| envelope(record)).collect(),
+    }
+    .encode_to_vec()
+}
+
+/// Converts a record to its protobuf payload.
+fn envelope(record: &LogRecord) -> LogEnvelope {
+    let request = &record.request;
+    LogEnvelope {
+        schema_version: SchemaVersion::CURRENT.number(),
+        event_id: record.event_id.to_string(),
+        timestamp: clock::format_rfc3339(record.timestamp),
+        service: request.service.clone(),
+        environment: request.environment.clone(),
+        severity: request.severity.clone(),
+        r#type: request.r#type.clone(),
+        log: request.log.clone(),
+        data: request.data.to_string(),
+        tags: request.tags.to_string(),
+        attachments: record
+            .attachments
+            .iter()
+            .map(|attachment| Attachment {
+                name: attachment.name().to_string(),
+                content_type: attachment.content_type().to_string(),
+                data: attachment.data().to_vec(),
+            })
+            .collect(),
+    }
+}
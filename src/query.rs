@@ -0,0 +1,338 @@
+//! Reading back logs from POGR.
+//!
+//! `PogrQueryClient` pages through the logs stored for a session or a service, using read
+//! credentials. Test suites can use it to check that records captured by `PogrLayer` actually
+//! arrived, and ops tooling to pull the logs of a misbehaving session.
+//!
+//! The query endpoint takes the filters as query parameters and answers with a page of logs
+//! and, if there are more, a cursor for the next page:
+//!
+//! ```text
+//! GET /v1/intake/logs/query?session_id=...&limit=100&cursor=...
+//!
+//! {"success": true, "payload": {"logs": [...], "next_cursor": "..."}}
+//! ```
+
+use crate::connection::new_client;
+use crate::http_dump;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::error::Error;
+use std::{env, fmt};
+use uuid::Uuid;
+
+/// Default query endpoint, unless `POGR_QUERY_ENDPOINT` is set.
+const DEFAULT_QUERY_ENDPOINT: &str = "https://api.pogr.io/v1/intake/logs/query";
+
+/// Default base URL of the analytics endpoints, unless `POGR_ANALYTICS_ENDPOINT` is set.
+const DEFAULT_ANALYTICS_ENDPOINT: &str = "https://api.pogr.io/v1/analytics";
+
+/// Default number of logs per page.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Selects the logs returned by `PogrQueryClient`.
+///
+/// # Examples
+///
+/// ```rust
+/// use pogr_tracing_rs::LogQuery;
+///
+/// let query = LogQuery::for_service("matchmaking")
+///     .with_environment("staging")
+///     .with_severity("ERROR")
+///     .with_page_size(500);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogQuery {
+    /// Filters as query parameters, in the order they were added.
+    filters: Vec<(&'static str, String)>,
+    /// Maximum number of logs per page.
+    page_size: usize,
+}
+
+impl LogQuery {
+    /// Selects the logs submitted in an intake session, e.g. `PogrAppender::session_id`.
+    pub fn for_session(session_id: impl Into<String>) -> Self {
+        LogQuery::new("session_id", session_id.into())
+    }
+
+    /// Selects the logs submitted by a service.
+    pub fn for_service(service: impl Into<String>) -> Self {
+        LogQuery::new("service", service.into())
+    }
+
+    /// Creates a query with a single filter.
+    fn new(name: &'static str, value: String) -> Self {
+        LogQuery {
+            filters: vec![(name, value)],
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Only selects logs from the given environment.
+    pub fn with_environment(self, environment: impl Into<String>) -> Self {
+        self.with_filter("environment", environment.into())
+    }
+
+    /// Only selects logs with the given severity, e.g. `ERROR`.
+    pub fn with_severity(self, severity: impl Into<String>) -> Self {
+        self.with_filter("severity", severity.into())
+    }
+
+    /// Only selects the log with the given client-generated `event_id`.
+    pub fn with_event_id(self, event_id: Uuid) -> Self {
+        self.with_filter("event_id", event_id.to_string())
+    }
+
+    /// Sets the maximum number of logs per page. Values below one are treated as one.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Returns the maximum number of logs per page.
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Adds a filter, replacing an earlier one with the same name.
+    fn with_filter(mut self, name: &'static str, value: String) -> Self {
+        self.filters.retain(|(existing, _)| *existing != name);
+        self.filters.push((name, value));
+        self
+    }
+}
+
+/// A log stored by POGR, as returned by the query endpoint.
+#[derive(Clone, Debug, Deserialize)]
+#[non_exhaustive]
+pub struct StoredLog {
+    /// The identifier POGR assigned to the log.
+    pub log_id: String,
+    /// The client-generated identifier of the log, if it was submitted with one.
+    #[serde(default)]
+    pub event_id: Option<Uuid>,
+    /// When the log was captured, as an RFC 3339 timestamp.
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    /// Name of the service that submitted the log.
+    #[serde(default)]
+    pub service: String,
+    /// Deployment environment of the service.
+    #[serde(default)]
+    pub environment: String,
+    /// Severity level of the log.
+    #[serde(default)]
+    pub severity: String,
+    /// Type of the log.
+    #[serde(default)]
+    pub r#type: String,
+    /// Text of the log.
+    #[serde(default)]
+    pub log: String,
+    /// Structured data submitted with the log.
+    #[serde(default)]
+    pub data: Value,
+    /// Tags submitted with the log.
+    #[serde(default)]
+    pub tags: Value,
+}
+
+/// One page of query results.
+#[derive(Clone, Debug, Deserialize)]
+#[non_exhaustive]
+pub struct LogPage {
+    /// The logs on this page, oldest first.
+    #[serde(default)]
+    pub logs: Vec<StoredLog>,
+    /// Cursor of the next page, or `None` on the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// The response of the query endpoint.
+#[derive(Deserialize, Debug)]
+struct QueryResponse {
+    /// Indicates whether the query was successful.
+    success: bool,
+    /// The page of results, or details of the failure.
+    payload: Value,
+}
+
+/// Why a query failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum QueryError {
+    /// The request could not be sent, or the response could not be read.
+    Transport(reqwest::Error),
+    /// The endpoint answered, but refused the query, e.g. because of invalid credentials.
+    Rejected(String),
+    /// The response is not a valid query response.
+    InvalidResponse(serde_json::Error),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Transport(err) => write!(f, "{}", err),
+            QueryError::Rejected(response) => write!(f, "query rejected: {}", response),
+            QueryError::InvalidResponse(err) => write!(f, "invalid response from the query endpoint: {}", err),
+        }
+    }
+}
+
+impl Error for QueryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QueryError::Transport(err) => Some(err),
+            QueryError::Rejected(_) => None,
+            QueryError::InvalidResponse(err) => Some(err),
+        }
+    }
+}
+
+/// A client for reading logs back from POGR.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{LogQuery, PogrAppender, PogrQueryClient};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// // ... log through a PogrLayer and flush it ...
+///
+/// let query_client = PogrQueryClient::from_env().unwrap();
+/// let logs = query_client
+///     .fetch_all(&LogQuery::for_session(appender.session_id.clone()))
+///     .await
+///     .unwrap();
+/// assert!(logs.iter().any(|log| log.severity == "ERROR"));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PogrQueryClient {
+    /// HTTP client used for the queries.
+    client: Client,
+    /// URL of the query endpoint.
+    endpoint: String,
+    /// Base URL of the analytics endpoints.
+    pub(crate) analytics_endpoint: String,
+    /// Read access key.
+    access: String,
+    /// Read secret.
+    secret: String,
+}
+
+/// Shows the client without its secret.
+impl fmt::Debug for PogrQueryClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PogrQueryClient")
+            .field("endpoint", &self.endpoint)
+            .field("analytics_endpoint", &self.analytics_endpoint)
+            .field("access", &self.access)
+            .field("secret", &"****")
+            .finish()
+    }
+}
+
+impl PogrQueryClient {
+    /// Creates a query client with the given read credentials.
+    ///
+    /// The endpoints are taken from `POGR_QUERY_ENDPOINT` and `POGR_ANALYTICS_ENDPOINT`, or
+    /// default to POGR's.
+    pub fn new(access: impl Into<String>, secret: impl Into<String>) -> Self {
+        PogrQueryClient {
+            client: new_client(),
+            endpoint: env::var("POGR_QUERY_ENDPOINT").unwrap_or_else(|_| DEFAULT_QUERY_ENDPOINT.to_string()),
+            analytics_endpoint: env::var("POGR_ANALYTICS_ENDPOINT")
+                .unwrap_or_else(|_| DEFAULT_ANALYTICS_ENDPOINT.to_string()),
+            access: access.into(),
+            secret: secret.into(),
+        }
+    }
+
+    /// Creates a query client with the read credentials from `POGR_READ_ACCESS` and
+    /// `POGR_READ_SECRET`, falling back to `POGR_ACCESS` and `POGR_SECRET`.
+    ///
+    /// # Returns
+    ///
+    /// `None` if no credentials are set.
+    pub fn from_env() -> Option<Self> {
+        let access = env::var("POGR_READ_ACCESS").or_else(|_| env::var("POGR_ACCESS")).ok()?;
+        let secret = env::var("POGR_READ_SECRET").or_else(|_| env::var("POGR_SECRET")).ok()?;
+        Some(PogrQueryClient::new(access, secret))
+    }
+
+    /// Sets the URL of the query endpoint.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Sets the base URL of the analytics endpoints.
+    pub fn with_analytics_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.analytics_endpoint = endpoint.into();
+        self
+    }
+
+    /// Sets the HTTP client used for the queries.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Fetches one page of logs.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Selects the logs.
+    /// * `cursor` - The `next_cursor` of the previous page, or `None` for the first page.
+    pub async fn fetch_page(&self, query: &LogQuery, cursor: Option<&str>) -> Result<LogPage, QueryError> {
+        let page_size = query.page_size.to_string();
+        let mut parameters: Vec<(&str, &str)> = query.filters.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        parameters.push(("limit", &page_size));
+        if let Some(cursor) = cursor {
+            parameters.push(("cursor", cursor));
+        }
+
+        self.get(&self.endpoint, &parameters).await
+    }
+
+    /// Fetches all logs selected by the query, following the cursors page by page.
+    pub async fn fetch_all(&self, query: &LogQuery) -> Result<Vec<StoredLog>, QueryError> {
+        let mut logs = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let page = self.fetch_page(query, cursor.as_deref()).await?;
+            logs.extend(page.logs);
+            match page.next_cursor {
+                Some(next) if !next.is_empty() => cursor = Some(next),
+                _ => return Ok(logs),
+            }
+        }
+    }
+
+    /// Sends an authenticated `GET` request and returns the payload of a successful response.
+    pub(crate) async fn get<T: DeserializeOwned>(&self, url: &str, parameters: &[(&str, &str)]) -> Result<T, QueryError> {
+        let request = self
+            .client
+            .get(url)
+            .query(parameters)
+            .header("POGR_ACCESS", &self.access)
+            .header("POGR_SECRET", &self.secret);
+        let (status, body) = http_dump::send(&self.client, request).await.map_err(QueryError::Transport)?;
+
+        match serde_json::from_slice::<QueryResponse>(&body) {
+            Ok(response) if response.success => serde_json::from_value(response.payload).map_err(QueryError::InvalidResponse),
+            Ok(response) => Err(QueryError::Rejected(format!("{}: {}", status, response.payload))),
+            Err(_) if !status.is_success() => {
+                Err(QueryError::Rejected(format!("{}: {}", status, String::from_utf8_lossy(&body))))
+            }
+            Err(err) => Err(QueryError::InvalidResponse(err)),
+        }
+    }
+}
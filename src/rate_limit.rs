@@ -0,0 +1,146 @@
+//! Per-target rate limits.
+//!
+//! A subsystem stuck in a loop can emit thousands of events per second. A single limit for
+//! the whole layer would then drop the events of every other subsystem too, so limits are set
+//! per target prefix instead: each prefix has a token bucket of its own, refilled at its rate
+//! and holding up to one second's worth of events. The most specific prefix matching an
+//! event's target applies; targets without a limit are not limited, unless a default limit is
+//! set, which all of them share.
+//!
+//! Events over a limit are dropped. They are counted, and reported as a `Dropped` diagnostic
+//! once the bucket lets events through again, so a noisy subsystem does not flood the
+//! diagnostics either.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::filter;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Token buckets limiting the events forwarded to POGR per target.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, RateLimits};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_rate_limits(
+///     RateLimits::new()
+///         .with_default_limit(1000.0)
+///         .with_limit("netcode", 100.0)
+///         .with_unlimited("audit"),
+/// );
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct RateLimits {
+    /// Target prefixes with their bucket, `None` if unlimited, sorted from most to least
+    /// specific.
+    limits: Vec<(String, Option<Mutex<Bucket>>)>,
+    /// Bucket shared by the targets without a limit of their own, if they are limited.
+    default: Option<Mutex<Bucket>>,
+}
+
+impl RateLimits {
+    /// Creates a set without any limit.
+    pub fn new() -> Self {
+        RateLimits::default()
+    }
+
+    /// Limits the events of targets matching `target` to `per_second` events per second.
+    ///
+    /// A target matches itself and every module below it, so `netcode` covers
+    /// `netcode::replication` too, and all of them share the bucket.
+    pub fn with_limit(self, target: impl Into<String>, per_second: f64) -> Self {
+        self.with(target.into(), Some(Mutex::new(Bucket::new(per_second))))
+    }
+
+    /// Exempts targets matching `target` from the default limit, and from the limits of less
+    /// specific prefixes.
+    pub fn with_unlimited(self, target: impl Into<String>) -> Self {
+        self.with(target.into(), None)
+    }
+
+    /// Limits the events of the targets without a limit of their own to `per_second` events
+    /// per second, all together.
+    pub fn with_default_limit(mut self, per_second: f64) -> Self {
+        self.default = Some(Mutex::new(Bucket::new(per_second)));
+        self
+    }
+
+    /// Sets the limit of a prefix, replacing a previous one.
+    fn with(mut self, target: String, bucket: Option<Mutex<Bucket>>) -> Self {
+        self.limits.retain(|(existing, _)| *existing != target);
+        self.limits.push((target, bucket));
+        self.limits.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    /// Takes a token for an event of `target`, and returns `false` if the event is over its
+    /// limit and must be dropped.
+    pub(crate) fn admit(&self, target: &str) -> bool {
+        let (prefix, bucket) = match self.limits.iter().find(|(prefix, _)| filter::target_matches(target, prefix)) {
+            Some((prefix, bucket)) => (prefix.as_str(), bucket.as_ref()),
+            None => ("other targets", self.default.as_ref()),
+        };
+        let Some(bucket) = bucket else {
+            return true;
+        };
+
+        let mut bucket = bucket.lock().unwrap_or_else(|p| p.into_inner());
+        if !bucket.take(Instant::now()) {
+            bucket.dropped += 1;
+            return false;
+        }
+        let dropped = std::mem::take(&mut bucket.dropped);
+        drop(bucket);
+
+        if dropped > 0 {
+            diagnostics::warn(
+                DiagnosticKind::Dropped,
+                format!("Dropped {} events from {} over the rate limit", dropped, prefix),
+            );
+        }
+        true
+    }
+}
+
+/// A token bucket holding up to one second's worth of events.
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens added per second, and the capacity of the bucket.
+    rate: f64,
+    /// Tokens currently available.
+    tokens: f64,
+    /// When the tokens were last refilled.
+    refilled_at: Instant,
+    /// Events dropped since the last one let through.
+    dropped: u64,
+}
+
+impl Bucket {
+    /// Creates a full bucket.
+    fn new(per_second: f64) -> Self {
+        let rate = per_second.max(0.0);
+        Bucket {
+            rate,
+            tokens: rate,
+            refilled_at: Instant::now(),
+            dropped: 0,
+        }
+    }
+
+    /// Refills the bucket for the time elapsed, and takes a token if one is available.
+    fn take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate.max(1.0));
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Changing the verbosity of a running layer.
+//!
+//! Once a `PogrLayer` is moved into a subscriber, its builder methods are out of reach. The
+//! settings that decide which events are forwarded — target levels, per-level sampling rates
+//! and toggles — live behind a `ReloadHandle` instead, taken from the layer before it is
+//! installed. Changes apply to the next event, without restarting the service; this is what
+//! remote configuration polling uses to apply the fleet-wide settings.
+
+use crate::filter::TargetLevels;
+use crate::sampling;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::{Level, Metadata};
+
+/// Name of the toggle that pauses forwarding events to POGR when it is off.
+pub const FORWARDING_TOGGLE: &str = "forwarding";
+
+/// The settings of a layer that can be changed while it runs.
+#[derive(Debug, Default)]
+pub(crate) struct LiveSettings {
+    /// Minimum severity levels, per target, for events forwarded to POGR.
+    pub(crate) target_levels: TargetLevels,
+    /// Fractions of the events kept at some levels, e.g. a sample of `INFO` in production.
+    pub(crate) level_sampling: Vec<(Level, f64)>,
+    /// Named switches, such as `forwarding`.
+    pub(crate) toggles: HashMap<String, bool>,
+}
+
+impl LiveSettings {
+    /// Sets the fraction of the events kept at `level`, replacing its previous rate.
+    pub(crate) fn set_level_sampling(&mut self, level: Level, rate: f64) {
+        self.level_sampling.retain(|(existing, _)| *existing != level);
+        self.level_sampling.push((level, rate.clamp(0.0, 1.0)));
+    }
+
+    /// Returns `true` if spans and events with this metadata meet the thresholds, and
+    /// forwarding is not paused.
+    pub(crate) fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.level_enabled(metadata.target(), metadata.level())
+    }
+
+    /// Returns `true` if records at `level` for `target` meet the thresholds, and forwarding is
+    /// not paused.
+    pub(crate) fn level_enabled(&self, target: &str, level: &Level) -> bool {
+        self.toggles.get(FORWARDING_TOGGLE) != Some(&false) && self.target_levels.level_for(target) >= *level
+    }
+
+    /// Draws whether an event at `level` is kept by the sampling rate of its level.
+    pub(crate) fn sample(&self, level: &Level) -> bool {
+        match self.level_sampling.iter().find(|(sampled, _)| sampled == level) {
+            Some((_, rate)) => *rate >= 1.0 || sampling::random_fraction() < *rate,
+            None => true,
+        }
+    }
+}
+
+/// Shared settings of a layer and its reload handles.
+pub(crate) type SharedSettings = Arc<RwLock<LiveSettings>>;
+
+/// Locks the settings for reading, even if a writer panicked.
+pub(crate) fn read(settings: &SharedSettings) -> RwLockReadGuard<'_, LiveSettings> {
+    settings.read().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Locks the settings for writing, even if a writer panicked.
+pub(crate) fn write(settings: &SharedSettings) -> RwLockWriteGuard<'_, LiveSettings> {
+    settings.write().unwrap_or_else(|p| p.into_inner())
+}
+
+/// A handle for changing the verbosity of a `PogrLayer` after it has been installed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer};
+/// use tracing::Level;
+/// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender);
+/// let reload = layer.reload_handle();
+/// tracing_subscriber::registry().with(layer).init();
+///
+/// // Later, e.g. from an admin command:
+/// reload.set_target_levels("info,netcode=debug".parse().unwrap());
+/// reload.set_level_sampling(Level::INFO, 0.25);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReloadHandle {
+    /// The settings shared with the layer.
+    settings: SharedSettings,
+}
+
+impl ReloadHandle {
+    /// Wraps the settings of a layer.
+    pub(crate) fn new(settings: SharedSettings) -> Self {
+        ReloadHandle { settings }
+    }
+
+    /// Replaces the per-target minimum levels of the events forwarded to POGR.
+    pub fn set_target_levels(&self, target_levels: TargetLevels) {
+        write(&self.settings).target_levels = target_levels;
+    }
+
+    /// Returns the current per-target minimum levels.
+    pub fn target_levels(&self) -> TargetLevels {
+        read(&self.settings).target_levels.clone()
+    }
+
+    /// Keeps a random fraction `rate`, between 0 and 1, of the events at exactly `level`,
+    /// replacing its previous rate.
+    pub fn set_level_sampling(&self, level: Level, rate: f64) {
+        write(&self.settings).set_level_sampling(level, rate);
+    }
+
+    /// Removes the sampling rates of all levels, so every event meeting the thresholds is
+    /// forwarded.
+    pub fn clear_level_sampling(&self) {
+        write(&self.settings).level_sampling.clear();
+    }
+
+    /// Returns the sampling rate of `level`, if it has one.
+    pub fn level_sampling(&self, level: Level) -> Option<f64> {
+        read(&self.settings)
+            .level_sampling
+            .iter()
+            .find(|(sampled, _)| *sampled == level)
+            .map(|(_, rate)| *rate)
+    }
+
+    /// Turns a named toggle on or off.
+    ///
+    /// The layer pauses forwarding events while `FORWARDING_TOGGLE` is off. Other toggles are
+    /// free for the application to read with `toggle`, e.g. to switch features of a fleet
+    /// through remote configuration.
+    pub fn set_toggle(&self, name: impl Into<String>, enabled: bool) {
+        write(&self.settings).toggles.insert(name.into(), enabled);
+    }
+
+    /// Returns the state of a named toggle, if it has been set.
+    pub fn toggle(&self, name: &str) -> Option<bool> {
+        read(&self.settings).toggles.get(name).copied()
+    }
+}
@@ -0,0 +1,206 @@
+//! Polling logging configuration from a remote endpoint.
+//!
+//! Live operations need to turn verbosity up on a whole fleet while an incident is going on,
+//! and back down once it is over, without redeploying. With remote configuration, the layer
+//! periodically fetches its settings, in its session, from POGR's config endpoint or any URL
+//! serving the same document, and applies them through its `ReloadHandle`:
+//!
+//! ```text
+//! GET /v1/intake/config
+//! INTAKE_SESSION_ID: ...
+//!
+//! {"success": true, "payload": {
+//!     "levels": "info,netcode=debug",
+//!     "sampling": {"info": 0.1},
+//!     "toggles": {"forwarding": true, "new_matchmaker": false}
+//! }}
+//! ```
+//!
+//! Every part is optional, and omitted parts are left as they are. `levels` replaces the
+//! target levels, `sampling` replaces the sampling rates of all levels, and `toggles` sets the
+//! listed toggles. A document is only applied when it differs from the previous one, so
+//! settings changed locally in between are kept until the remote configuration changes. A
+//! document that cannot be fetched or parsed is reported as a diagnostic and changes nothing.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::filter::TargetLevels;
+use crate::reload::{self, LiveSettings, SharedSettings};
+use crate::{http_dump, PogrAppender};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+use tracing::Level;
+
+/// Default time between two fetches of the remote configuration.
+pub const DEFAULT_CONFIG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default config endpoint, unless `POGR_CONFIG_ENDPOINT` is set.
+const DEFAULT_CONFIG_ENDPOINT: &str = "https://api.pogr.io/v1/intake/config";
+
+/// Configuration for polling logging settings from a remote endpoint.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, RemoteConfig};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender)
+///     .with_remote_config(RemoteConfig::new().with_interval(Duration::from_secs(30)));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteConfig {
+    /// URL the configuration is fetched from.
+    endpoint: String,
+    /// Time between two fetches.
+    interval: Duration,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        RemoteConfig::new()
+    }
+}
+
+impl RemoteConfig {
+    /// Fetches the configuration every `DEFAULT_CONFIG_INTERVAL`.
+    ///
+    /// The endpoint is taken from `POGR_CONFIG_ENDPOINT`, or defaults to POGR's.
+    pub fn new() -> Self {
+        RemoteConfig {
+            endpoint: env::var("POGR_CONFIG_ENDPOINT").unwrap_or_else(|_| DEFAULT_CONFIG_ENDPOINT.to_string()),
+            interval: DEFAULT_CONFIG_INTERVAL,
+        }
+    }
+
+    /// Sets the URL the configuration is fetched from, e.g. a service of your own.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Sets the time between two fetches. Defaults to `DEFAULT_CONFIG_INTERVAL`.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+/// The response of the config endpoint.
+#[derive(Deserialize)]
+struct ConfigResponse {
+    /// Indicates whether the configuration could be read.
+    success: bool,
+    /// The configuration.
+    #[serde(default)]
+    payload: ConfigDocument,
+}
+
+/// The logging settings served by the config endpoint.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+struct ConfigDocument {
+    /// Target level directives, e.g. `info,netcode=debug`.
+    #[serde(default)]
+    levels: Option<String>,
+    /// Sampling rates by level name.
+    #[serde(default)]
+    sampling: Option<HashMap<String, f64>>,
+    /// Toggles to set.
+    #[serde(default)]
+    toggles: Option<HashMap<String, bool>>,
+}
+
+/// Starts polling the configuration, in the appender's session, for as long as the layer
+/// owning `settings` exists.
+///
+/// Returns `false` if polling cannot start because there is no tokio runtime.
+pub(crate) fn spawn(config: RemoteConfig, appender: Arc<tokio::sync::Mutex<PogrAppender>>, settings: Weak<RwLock<LiveSettings>>) -> bool {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return false;
+    };
+
+    runtime.spawn(async move {
+        // The last document fetched, applied or not, so each one is applied or reported once.
+        let mut seen = None;
+        loop {
+            let appender = appender.lock().await.clone();
+            let fetched = fetch(&appender, &config.endpoint).await;
+            let Some(settings) = settings.upgrade() else {
+                break;
+            };
+            match fetched {
+                Ok(document) if seen.as_ref() != Some(&document) => {
+                    if let Err(e) = apply(&document, &settings) {
+                        diagnostics::warn(DiagnosticKind::Config, format!("Ignoring the remote configuration: {}", e));
+                    }
+                    seen = Some(document);
+                }
+                Ok(_) => {}
+                Err(e) => diagnostics::warn(
+                    DiagnosticKind::Config,
+                    format!("Failed to fetch the remote configuration from {}: {}", config.endpoint, e),
+                ),
+            }
+            drop(settings);
+            tokio::time::sleep(config.interval).await;
+        }
+    });
+    true
+}
+
+/// Fetches the configuration document.
+async fn fetch(appender: &PogrAppender, endpoint: &str) -> Result<ConfigDocument, String> {
+    let client = &appender.client;
+    let request = client.get(endpoint).header("INTAKE_SESSION_ID", &appender.session_id);
+    let (status, body) = http_dump::send(client, request).await.map_err(|err| err.to_string())?;
+
+    match serde_json::from_slice::<ConfigResponse>(&body) {
+        Ok(response) if response.success => Ok(response.payload),
+        _ => Err(format!("{}: {}", status, String::from_utf8_lossy(&body))),
+    }
+}
+
+/// Applies a configuration document to the settings of a layer.
+///
+/// The document is validated first, so that an invalid one changes nothing.
+fn apply(document: &ConfigDocument, settings: &SharedSettings) -> Result<(), String> {
+    let levels = document
+        .levels
+        .as_deref()
+        .map(str::parse::<TargetLevels>)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let sampling = document
+        .sampling
+        .as_ref()
+        .map(|rates| {
+            rates
+                .iter()
+                .map(|(level, rate)| match level.parse::<Level>() {
+                    Ok(level) => Ok((level, *rate)),
+                    Err(_) => Err(format!("invalid sampling level `{}`", level)),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+
+    let mut settings = reload::write(settings);
+    if let Some(levels) = levels {
+        settings.target_levels = levels;
+    }
+    if let Some(sampling) = sampling {
+        settings.level_sampling.clear();
+        for (level, rate) in sampling {
+            settings.set_level_sampling(level, rate);
+        }
+    }
+    if let Some(toggles) = &document.toggles {
+        settings.toggles.extend(toggles.iter().map(|(name, enabled)| (name.clone(), *enabled)));
+    }
+    Ok(())
+}
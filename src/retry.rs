@@ -0,0 +1,292 @@
+//! Retrying failed submissions.
+//!
+//! Whether a failed submission is worth retrying, and how long to wait before doing so, depends
+//! on the deployment: a game server on a flaky link wants to retry quickly a few times, a
+//! batch job behind a rate-limited proxy wants to back off for minutes. A `RetryPolicy`
+//! decides, from the number of the failed attempt, the class of the failure and the
+//! `Retry-After` the intake sent, if any, whether and when the request is sent again.
+//! `ExponentialBackoff` and `FixedInterval` cover the common cases.
+//!
+//! A retried request carries the same idempotency key, so the intake can discard duplicates.
+//! Responses that pause delivery are not retried by the policy; they wait for the pause to
+//! end under the pause policy. Once the policy gives up, the records are handed to the
+//! fallback sinks. Without a policy, failed submissions are not retried.
+//!
+//! Intakes and the proxies in front of them do not agree on what a status means. A
+//! `StatusTable` overrides how responses with particular statuses are handled: retried under
+//! the retry policy, answered by initializing a new session and sending the request again,
+//! or dead-lettered, i.e. handed to the fallback sinks right away.
+
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default delay before the first retry of an `ExponentialBackoff`.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Default longest delay between two retries of an `ExponentialBackoff`.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Default number of attempts, including the first, of the provided policies.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// What kind of failure a submission ran into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorClass {
+    /// The request could not be sent or its response could not be read, e.g. because the
+    /// connection was refused, reset or timed out.
+    Transport,
+    /// The intake, or a proxy in front of it, answered with a `5xx` status.
+    ServerError,
+    /// The intake answered `429 Too Many Requests`.
+    RateLimited,
+    /// The intake answered, but refused the records, e.g. with a `4xx` status.
+    Rejected,
+    /// The response was not a valid intake response.
+    InvalidResponse,
+    /// The intake answered with a status the `StatusTable` marks as retryable.
+    Retryable,
+}
+
+impl ErrorClass {
+    /// Returns `true` for failures that may go away by themselves: transport failures, server
+    /// errors, rate limiting and statuses marked as retryable.
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Transport | ErrorClass::ServerError | ErrorClass::RateLimited | ErrorClass::Retryable
+        )
+    }
+}
+
+/// Decides whether and when a failed submission is retried.
+///
+/// # Examples
+///
+/// Retrying transport failures right away, once:
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{ErrorClass, PogrAppender, PogrLayer, RetryPolicy};
+/// use std::time::Duration;
+///
+/// struct RetryOnce;
+///
+/// impl RetryPolicy for RetryOnce {
+///     fn next_delay(&self, attempt: u32, class: ErrorClass, _retry_after: Option<Duration>) -> Option<Duration> {
+///         (attempt == 1 && class == ErrorClass::Transport).then_some(Duration::ZERO)
+///     }
+/// }
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_retry_policy(RetryOnce);
+/// # }
+/// ```
+pub trait RetryPolicy: Send + Sync {
+    /// Returns how long to wait before sending the request again, or `None` to give up.
+    ///
+    /// `attempt` is the number of the attempt that failed, starting at 1, `class` what kind of
+    /// failure it ran into, and `retry_after` how long the response asked to wait, if it had a
+    /// `Retry-After` header.
+    fn next_delay(&self, attempt: u32, class: ErrorClass, retry_after: Option<Duration>) -> Option<Duration>;
+}
+
+/// Allows sharing one policy between several layers, or keeping a handle to it.
+impl<P: RetryPolicy + ?Sized> RetryPolicy for Arc<P> {
+    fn next_delay(&self, attempt: u32, class: ErrorClass, retry_after: Option<Duration>) -> Option<Duration> {
+        (**self).next_delay(attempt, class, retry_after)
+    }
+}
+
+/// Retries transient failures with exponentially growing delays.
+///
+/// The first retry waits the initial delay, each one after it twice as long as the one
+/// before, up to the maximum delay. A longer `Retry-After` is honored instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExponentialBackoff {
+    /// Delay before the first retry.
+    initial: Duration,
+    /// Longest delay between two retries.
+    max_delay: Duration,
+    /// Number of attempts, including the first.
+    max_attempts: u32,
+}
+
+impl ExponentialBackoff {
+    /// Creates a backoff starting at `DEFAULT_INITIAL_BACKOFF`, capped at
+    /// `DEFAULT_MAX_BACKOFF`, for `DEFAULT_MAX_ATTEMPTS` attempts.
+    pub fn new() -> Self {
+        ExponentialBackoff {
+            initial: DEFAULT_INITIAL_BACKOFF,
+            max_delay: DEFAULT_MAX_BACKOFF,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_initial_delay(mut self, initial: Duration) -> Self {
+        self.initial = initial;
+        self
+    }
+
+    /// Sets the longest delay between two retries.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the number of attempts, including the first; 1 disables retries.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Returns the delay before the first retry.
+    pub fn initial_delay(&self) -> Duration {
+        self.initial
+    }
+
+    /// Returns the longest delay between two retries.
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// Returns the number of attempts, including the first.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay(&self, attempt: u32, class: ErrorClass, retry_after: Option<Duration>) -> Option<Duration> {
+        if !class.is_transient() || attempt >= self.max_attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.initial.saturating_mul(factor).min(self.max_delay);
+        Some(retry_after.map_or(delay, |retry_after| retry_after.max(delay)))
+    }
+}
+
+/// Retries transient failures after a fixed delay.
+///
+/// A longer `Retry-After` is honored instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixedInterval {
+    /// Delay between two attempts.
+    interval: Duration,
+    /// Number of attempts, including the first.
+    max_attempts: u32,
+}
+
+impl FixedInterval {
+    /// Retries after `interval`, for `DEFAULT_MAX_ATTEMPTS` attempts.
+    pub fn new(interval: Duration) -> Self {
+        FixedInterval {
+            interval,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Sets the number of attempts, including the first; 1 disables retries.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Returns the delay between two attempts.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the number of attempts, including the first.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+}
+
+impl RetryPolicy for FixedInterval {
+    fn next_delay(&self, attempt: u32, class: ErrorClass, retry_after: Option<Duration>) -> Option<Duration> {
+        if !class.is_transient() || attempt >= self.max_attempts {
+            return None;
+        }
+        Some(retry_after.map_or(self.interval, |retry_after| retry_after.max(self.interval)))
+    }
+}
+
+/// How a response with a particular status is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusAction {
+    /// Retry the request as the retry policy decides, with `ErrorClass::Retryable`, or with
+    /// the default `ExponentialBackoff` if the layer has no retry policy.
+    Retry,
+    /// Initialize a new session and send the request again in it, once. If that fails, or the
+    /// new session is refused as well, the records are handed to the fallback sinks.
+    Reinitialize,
+    /// Hand the records to the fallback sinks right away, without retrying.
+    DeadLetter,
+}
+
+/// How responses with particular HTTP statuses are handled, overriding the default
+/// classification.
+///
+/// Statuses without an entry are classified as usual. Pause requests and `415 Unsupported
+/// Media Type` responses are always handled as such.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{ExponentialBackoff, PogrAppender, PogrLayer, StatusAction, StatusTable};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender)
+///     .with_retry_policy(ExponentialBackoff::new())
+///     .with_status_table(
+///         StatusTable::new()
+///             .with_status(400, StatusAction::DeadLetter)
+///             .with_status(401, StatusAction::Reinitialize)
+///             .with_statuses(502..=504, StatusAction::Retry),
+///     );
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatusTable {
+    /// Status ranges with their actions, later entries taking precedence.
+    actions: Vec<(RangeInclusive<u16>, StatusAction)>,
+}
+
+impl StatusTable {
+    /// Creates an empty table, leaving every status to the default classification.
+    pub fn new() -> Self {
+        StatusTable::default()
+    }
+
+    /// Handles responses with `status` with `action`.
+    pub fn with_status(self, status: u16, action: StatusAction) -> Self {
+        self.with_statuses(status..=status, action)
+    }
+
+    /// Handles responses with a status in `statuses` with `action`, e.g. `500..=599` for all
+    /// server errors. Entries added later take precedence.
+    pub fn with_statuses(mut self, statuses: RangeInclusive<u16>, action: StatusAction) -> Self {
+        self.actions.push((statuses, action));
+        self
+    }
+
+    /// Returns how responses with `status` are handled, if the table says.
+    pub fn action(&self, status: u16) -> Option<StatusAction> {
+        self.actions
+            .iter()
+            .rev()
+            .find(|(statuses, _)| statuses.contains(&status))
+            .map(|(_, action)| *action)
+    }
+}
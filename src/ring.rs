@@ -0,0 +1,279 @@
+//! The queue between a `PogrLayer` and its delivery workers.
+//!
+//! Every captured record is handed to a worker through a bounded, lock-free ring buffer, so
+//! queueing a record from `on_event` costs a few atomic operations, without taking a lock,
+//! even with dozens of threads logging at once. The worker is only woken when it waits for
+//! records.
+//!
+//! When records are captured faster than they are delivered and the buffer is full, the
+//! configured `OverflowPolicy` decides which record is dropped. Dropped records are counted in
+//! the drop reports and reported as a `Dropped` diagnostic, once until the queue has drained.
+//!
+//! A bound on the number of records does not protect against a few giant events, e.g. with
+//! megabytes of fields or attachments, exhausting memory. Every record therefore carries an
+//! estimate of its size, and the records a worker holds, from when they are queued until they
+//! are built into requests, must fit into its memory budget. A record that does not fit is
+//! dropped, or under `DropOldest`, the oldest queued records are dropped until it does.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pipeline::Queued;
+use crossbeam_queue::ArrayQueue;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tracing::Level;
+
+/// Default number of records a worker's queue holds.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 16 * 1024;
+
+/// Default number of bytes the records held by a worker may take up.
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+/// Estimated size, in bytes, of the parts every record has, such as its metadata and the
+/// service details.
+pub(crate) const RECORD_OVERHEAD: usize = 256;
+
+/// Which record is dropped when a worker's queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the record being queued, keeping the older ones.
+    #[default]
+    DropNewest,
+    /// Drop the oldest queued record to make room, keeping the most recent ones.
+    DropOldest,
+}
+
+/// Why a record was not queued.
+pub(crate) enum Rejected {
+    /// The worker has stopped, and the record was dropped.
+    Stopped,
+    /// The queue was full or over its memory budget, and records at these levels were dropped
+    /// under the overflow policy.
+    Overflow(Vec<Level>),
+}
+
+/// Estimates the size, in bytes, of a JSON value as serialized.
+pub(crate) fn estimated_size(value: &Value) -> usize {
+    match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) => 8,
+        Value::String(text) => text.len() + 2,
+        Value::Array(items) => 2 + items.iter().map(|item| estimated_size(item) + 1).sum::<usize>(),
+        Value::Object(entries) => {
+            2 + entries
+                .iter()
+                .map(|(name, value)| name.len() + 4 + estimated_size(value))
+                .sum::<usize>()
+        }
+    }
+}
+
+/// Describes why records over the memory budget are dropped.
+fn over_budget(budget: usize) -> String {
+    format!("the queued records exceed the memory budget of {} bytes", budget)
+}
+
+/// Keeps the size of a record counted against the memory budget until it is dropped.
+pub(crate) struct MemoryCharge {
+    /// Bytes counted against the budget of the queue.
+    bytes: Arc<AtomicUsize>,
+    /// Estimated size of the record.
+    size: usize,
+}
+
+impl Drop for MemoryCharge {
+    fn drop(&mut self) {
+        self.bytes.fetch_sub(self.size, Ordering::AcqRel);
+    }
+}
+
+/// State shared by the senders and the receiver of a queue.
+struct Shared {
+    /// The queued records.
+    buffer: ArrayQueue<Queued>,
+    /// What to drop when the buffer is full.
+    overflow: OverflowPolicy,
+    /// Number of bytes the records held by the worker may take up, if limited.
+    memory_budget: Option<usize>,
+    /// Estimated size of the records held by the worker.
+    bytes: Arc<AtomicUsize>,
+    /// Number of live senders; the worker stops once it is zero and the buffer is empty.
+    senders: AtomicUsize,
+    /// Set when the receiver is gone.
+    closed: AtomicBool,
+    /// Set when a record was dropped for lack of room, until the buffer drains.
+    overflowing: AtomicBool,
+    /// Wakes the receiver when records arrive or the last sender is dropped.
+    ready: Notify,
+}
+
+impl Shared {
+    /// Drops the records left in the buffer, ending their tickets.
+    fn discard(&self) {
+        while self.buffer.pop().is_some() {}
+    }
+}
+
+/// Creates a queue holding up to `capacity` records, at least one, and records of at most
+/// `memory_budget` bytes altogether.
+pub(crate) fn queue(capacity: usize, overflow: OverflowPolicy, memory_budget: Option<usize>) -> (QueueSender, QueueReceiver) {
+    let shared = Arc::new(Shared {
+        buffer: ArrayQueue::new(capacity.max(1)),
+        overflow,
+        memory_budget,
+        bytes: Arc::default(),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        overflowing: AtomicBool::new(false),
+        ready: Notify::new(),
+    });
+    (
+        QueueSender {
+            shared: Arc::clone(&shared),
+        },
+        QueueReceiver { shared },
+    )
+}
+
+/// Queues records for a worker.
+pub(crate) struct QueueSender {
+    /// The queue.
+    shared: Arc<Shared>,
+}
+
+impl QueueSender {
+    /// Queues a record for the worker.
+    pub(crate) fn send(&self, mut queued: Queued) -> Result<(), Rejected> {
+        let shared = &*self.shared;
+        if shared.closed.load(Ordering::Acquire) {
+            return Err(Rejected::Stopped);
+        }
+
+        let mut dropped = Vec::new();
+        if let Some(budget) = shared.memory_budget {
+            match self.reserve(queued.size, budget, &mut dropped) {
+                Some(charge) => queued.charge = Some(charge),
+                None => {
+                    dropped.push(queued.level);
+                    return Err(self.overflowed(dropped, over_budget(budget)));
+                }
+            }
+        }
+
+        let displaced = match shared.overflow {
+            OverflowPolicy::DropNewest => shared.buffer.push(queued).err(),
+            OverflowPolicy::DropOldest => shared.buffer.force_push(queued),
+        };
+        shared.ready.notify_one();
+        // The receiver may have gone while the record was queued; nobody will take it then.
+        if shared.closed.load(Ordering::Acquire) {
+            shared.discard();
+            return Err(Rejected::Stopped);
+        }
+
+        if let Some(displaced) = displaced {
+            dropped.push(displaced.level);
+            let cause = format!("the delivery queue of {} records is full", shared.buffer.capacity());
+            return Err(self.overflowed(dropped, cause));
+        }
+        match shared.memory_budget {
+            Some(budget) if !dropped.is_empty() => Err(self.overflowed(dropped, over_budget(budget))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts `size` bytes against the memory budget, dropping the oldest queued records to
+    /// make room under `DropOldest`, and adding their levels to `dropped`.
+    ///
+    /// Returns `None` if the record does not fit.
+    fn reserve(&self, size: usize, budget: usize, dropped: &mut Vec<Level>) -> Option<MemoryCharge> {
+        let shared = &*self.shared;
+        if size > budget {
+            return None;
+        }
+        loop {
+            if shared.bytes.fetch_add(size, Ordering::AcqRel) + size <= budget {
+                return Some(MemoryCharge {
+                    bytes: Arc::clone(&shared.bytes),
+                    size,
+                });
+            }
+            shared.bytes.fetch_sub(size, Ordering::AcqRel);
+            if shared.overflow == OverflowPolicy::DropNewest {
+                return None;
+            }
+            // Dropping the oldest record releases its charge. Records the worker has already
+            // taken cannot be dropped, so the new one is if they take up the budget.
+            let oldest = shared.buffer.pop()?;
+            dropped.push(oldest.level);
+        }
+    }
+
+    /// Reports dropped records, once until the queue has drained.
+    fn overflowed(&self, dropped: Vec<Level>, cause: String) -> Rejected {
+        if !self.shared.overflowing.swap(true, Ordering::Relaxed) {
+            diagnostics::warn(DiagnosticKind::Dropped, format!("Dropping log records because {}", cause));
+        }
+        Rejected::Overflow(dropped)
+    }
+}
+
+impl Clone for QueueSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        QueueSender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Drop for QueueSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.ready.notify_one();
+        }
+    }
+}
+
+/// Takes queued records in the worker.
+pub(crate) struct QueueReceiver {
+    /// The queue.
+    shared: Arc<Shared>,
+}
+
+impl QueueReceiver {
+    /// Waits for the next record, or returns `None` once every sender is gone and the queue is
+    /// empty.
+    ///
+    /// Cancelling the returned future never loses a record.
+    pub(crate) async fn recv(&mut self) -> Option<Queued> {
+        let shared = &*self.shared;
+        loop {
+            // Register for a wakeup before looking, so a record queued in between is noticed.
+            let ready = shared.ready.notified();
+            tokio::pin!(ready);
+            ready.as_mut().enable();
+
+            if let Some(queued) = shared.buffer.pop() {
+                return Some(queued);
+            }
+            shared.overflowing.store(false, Ordering::Relaxed);
+            if shared.senders.load(Ordering::Acquire) == 0 {
+                return shared.buffer.pop();
+            }
+            ready.await;
+        }
+    }
+
+    /// Returns the next record if one is queued, without waiting.
+    pub(crate) fn try_recv(&mut self) -> Option<Queued> {
+        self.shared.buffer.pop()
+    }
+}
+
+impl Drop for QueueReceiver {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.discard();
+    }
+}
@@ -0,0 +1,123 @@
+//! Routing records to intake endpoints by severity.
+//!
+//! Errors are the records operators need first, yet with a single pipeline they wait behind
+//! the bulk of info and debug records, lingering in the same batches. Level routes give the
+//! records at or above a severity their own pipeline: their own worker, their own batching
+//! settings and, optionally, their own logs endpoint, such as a low-latency priority intake,
+//! and their own payload encoding.
+//! Records matching no route go through the layer's default pipeline.
+
+use crate::pipeline::BatchConfig;
+use crate::ring::QueueSender;
+use crate::PayloadEncoding;
+use std::sync::OnceLock;
+use tracing::Level;
+
+/// A pipeline for the records at or above a severity.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{BatchConfig, LevelRoute, PogrAppender, PogrLayer};
+/// use std::time::Duration;
+/// use tracing::Level;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender)
+///     // Bulk records are batched...
+///     .with_batching(BatchConfig::new().with_max_batch_size(500).with_linger(Duration::from_secs(5)))
+///     // ...while errors are sent right away to the priority intake.
+///     .with_level_route(
+///         LevelRoute::new(Level::ERROR).with_logs_endpoint("https://priority.pogr.io/v1/intake/logs"),
+///     );
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelRoute {
+    /// Least severe level of the routed records.
+    level: Level,
+    /// Logs endpoint of the route, instead of the appender's.
+    logs_endpoint: Option<String>,
+    /// How the route groups records into requests.
+    batching: BatchConfig,
+    /// How the route encodes request bodies, instead of the layer's encoding.
+    payload_encoding: Option<PayloadEncoding>,
+}
+
+impl LevelRoute {
+    /// Routes the records at `level` or more severe, e.g. `Level::WARN` for warnings and
+    /// errors.
+    ///
+    /// By default, the route submits each record on its own, to the appender's logs endpoint.
+    pub fn new(level: Level) -> Self {
+        LevelRoute {
+            level,
+            logs_endpoint: None,
+            batching: BatchConfig::new(),
+            payload_encoding: None,
+        }
+    }
+
+    /// Sets the logs endpoint the route submits to.
+    pub fn with_logs_endpoint(mut self, logs_endpoint: impl Into<String>) -> Self {
+        self.logs_endpoint = Some(logs_endpoint.into());
+        self
+    }
+
+    /// Sets how the route groups records into requests.
+    pub fn with_batching(mut self, batching: BatchConfig) -> Self {
+        self.batching = batching;
+        self
+    }
+
+    /// Sets how the route encodes request bodies, e.g. `PayloadEncoding::Ndjson` for a
+    /// streaming collector. By default, the route uses the layer's encoding.
+    pub fn with_payload_encoding(mut self, encoding: PayloadEncoding) -> Self {
+        self.payload_encoding = Some(encoding);
+        self
+    }
+
+    /// Returns the least severe level of the routed records.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+}
+
+/// A level route with the queue of its worker, started with the first routed record.
+pub(crate) struct Route {
+    /// The route's configuration.
+    pub(crate) config: LevelRoute,
+    /// Queue of the route's worker.
+    pub(crate) queue: OnceLock<QueueSender>,
+}
+
+impl Route {
+    /// Creates a route whose worker is not started yet.
+    pub(crate) fn new(config: LevelRoute) -> Self {
+        Route {
+            config,
+            queue: OnceLock::new(),
+        }
+    }
+
+    /// Returns `true` if records at `level` take this route.
+    pub(crate) fn matches(&self, level: &Level) -> bool {
+        *level <= self.config.level
+    }
+
+    /// Returns the logs endpoint of the route, if it has its own.
+    pub(crate) fn logs_endpoint(&self) -> Option<&str> {
+        self.config.logs_endpoint.as_deref()
+    }
+
+    /// Returns the payload encoding of the route, if it has its own.
+    pub(crate) fn payload_encoding(&self) -> Option<PayloadEncoding> {
+        self.config.payload_encoding
+    }
+
+    /// Returns how the route groups records into requests.
+    pub(crate) fn batching(&self) -> &BatchConfig {
+        &self.config.batching
+    }
+}
@@ -0,0 +1,377 @@
+//! A sink archiving records to S3-compatible object storage.
+//!
+//! Each batch is written as one gzip-compressed NDJSON object, using the same JSON payloads
+//! the intake receives, under a time-partitioned key. Requests are signed with AWS Signature
+//! Version 4 and sent with path-style URLs, so the sink works with AWS S3 as well as MinIO,
+//! Ceph, R2 and other S3-compatible stores. Uploads run on a dedicated thread, because the
+//! blocking HTTP client must not run on the async worker.
+
+use crate::clock;
+use crate::sink::{Sink, SinkRecord};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256};
+use std::io::{self, Write};
+use std::sync::mpsc;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+/// How long an upload may take before it is abandoned.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How archived objects are grouped into key prefixes by time.
+///
+/// Partitions use the Hive `key=value` layout, e.g. `year=2024/month=03/day=01/`, so that
+/// Athena, Spark and similar tools can prune by date. The time is that of the batch's first
+/// record, in UTC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum S3Partitioning {
+    /// All objects directly under the prefix.
+    None,
+    /// One partition per day.
+    #[default]
+    Daily,
+    /// One partition per hour.
+    Hourly,
+}
+
+/// A `Sink` that archives each batch as a gzip-compressed NDJSON object in S3.
+///
+/// Objects are named `<prefix><partition><time>-<event id>.ndjson.gz`, where the time and
+/// event ID are those of the batch's first record. Credentials are read from the
+/// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN` environment variables
+/// unless set explicitly.
+///
+/// Available with the `s3` feature.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, S3ArchiveSink, S3Partitioning, SinkMode};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let archive = S3ArchiveSink::new("telemetry-archive")
+///     .with_region("eu-west-1")
+///     .with_prefix("pogr/game-server")
+///     .with_partitioning(S3Partitioning::Hourly);
+///
+/// let layer = PogrLayer::new(appender).with_sink(archive, SinkMode::Mirror);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct S3ArchiveSink {
+    /// Bucket the objects are written to.
+    bucket: String,
+    /// Key prefix, empty or ending with `/`.
+    prefix: String,
+    /// Region used for signing.
+    region: String,
+    /// Custom endpoint, for S3-compatible stores.
+    endpoint: Option<String>,
+    /// How objects are partitioned by time.
+    partitioning: S3Partitioning,
+    /// Access key ID.
+    access_key_id: String,
+    /// Secret access key.
+    secret_access_key: String,
+    /// Session token for temporary credentials.
+    session_token: Option<String>,
+    /// Timestamps and payloads written since the last flush.
+    pending: Mutex<Vec<(SystemTime, Uuid, String)>>,
+    /// Sends uploads to the upload thread, started on the first flush.
+    uploader: Mutex<Option<mpsc::Sender<Upload>>>,
+}
+
+/// A signed request for the upload thread.
+struct Upload {
+    /// Object URL.
+    url: String,
+    /// Signed headers.
+    headers: HeaderMap,
+    /// Compressed object.
+    body: Vec<u8>,
+    /// Receives the outcome of the upload.
+    done: mpsc::Sender<io::Result<()>>,
+}
+
+impl S3ArchiveSink {
+    /// Creates a sink archiving to `bucket` in `us-east-1`, partitioned by day.
+    pub fn new(bucket: impl Into<String>) -> Self {
+        S3ArchiveSink {
+            bucket: bucket.into(),
+            prefix: String::new(),
+            region: std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            endpoint: None,
+            partitioning: S3Partitioning::default(),
+            access_key_id: std::env::var("AWS_ACCESS_KEY_ID").unwrap_or_default(),
+            secret_access_key: std::env::var("AWS_SECRET_ACCESS_KEY").unwrap_or_default(),
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            pending: Mutex::new(Vec::new()),
+            uploader: Mutex::new(None),
+        }
+    }
+
+    /// Sets the key prefix, e.g. `pogr/game-server`. A trailing `/` is added if missing.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        if !self.prefix.is_empty() && !self.prefix.ends_with('/') {
+            self.prefix.push('/');
+        }
+        self
+    }
+
+    /// Sets the region, which defaults to `AWS_REGION` or `us-east-1`.
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Sets the endpoint of an S3-compatible store, e.g. `http://minio.internal:9000`.
+    ///
+    /// Defaults to `https://s3.<region>.amazonaws.com`.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Sets how objects are partitioned by time.
+    pub fn with_partitioning(mut self, partitioning: S3Partitioning) -> Self {
+        self.partitioning = partitioning;
+        self
+    }
+
+    /// Sets the access key ID and secret access key.
+    pub fn with_credentials(mut self, access_key_id: impl Into<String>, secret_access_key: impl Into<String>) -> Self {
+        self.access_key_id = access_key_id.into();
+        self.secret_access_key = secret_access_key.into();
+        self
+    }
+
+    /// Sets the session token of temporary credentials.
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.session_token = Some(session_token.into());
+        self
+    }
+
+    /// Returns the key of the object for a batch starting with the given record.
+    fn object_key(&self, timestamp: SystemTime, event_id: Uuid) -> String {
+        // `2024-03-01T12:30:05.042Z`
+        let time = clock::format_rfc3339(timestamp);
+        let partition = match self.partitioning {
+            S3Partitioning::None => String::new(),
+            S3Partitioning::Daily => format!("year={}/month={}/day={}/", &time[0..4], &time[5..7], &time[8..10]),
+            S3Partitioning::Hourly => format!(
+                "year={}/month={}/day={}/hour={}/",
+                &time[0..4],
+                &time[5..7],
+                &time[8..10],
+                &time[11..13]
+            ),
+        };
+
+        format!("{}{}{}-{}.ndjson.gz", self.prefix, partition, compact_time(&time), event_id)
+    }
+
+    /// Builds the object URL and its AWS Signature Version 4 headers.
+    fn sign(&self, key: &str, body: &[u8], now: SystemTime) -> io::Result<(String, HeaderMap)> {
+        let endpoint = self
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", self.region));
+        let path = format!("/{}/{}", uri_encode(&self.bucket), uri_encode(key));
+        let url = format!("{}{}", endpoint, path);
+
+        let parsed = reqwest::Url::parse(&url).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let host = match (parsed.host_str(), parsed.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "S3 endpoint has no host")),
+        };
+
+        let amz_date = compact_time(&clock::format_rfc3339(now));
+        let date = &amz_date[..8];
+        let payload_hash = hex(&Sha256::digest(body));
+
+        let mut signed = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            signed.push(("x-amz-security-token", token.clone()));
+        }
+
+        let canonical_headers: String = signed.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let signed_headers = signed.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_request = format!(
+            "PUT\n{}\n\n{}\n{}\n{}",
+            path, canonical_headers, signed_headers, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let mut key = hmac(format!("AWS4{}", self.secret_access_key).as_bytes(), date.as_bytes());
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in signed.iter().filter(|(name, _)| *name != "host") {
+            headers.insert(*name, to_header_value(value)?);
+        }
+        headers.insert(
+            "authorization",
+            to_header_value(&format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id, scope, signed_headers, signature
+            ))?,
+        );
+        headers.insert("content-type", to_header_value("application/gzip")?);
+
+        Ok((url, headers))
+    }
+
+    /// Hands an upload to the upload thread, starting it if needed.
+    fn submit(&self, upload: Upload) -> io::Result<()> {
+        let mut uploader = lock(&self.uploader);
+        let sender = match uploader.as_ref() {
+            Some(sender) => sender,
+            None => uploader.insert(spawn_uploader()?),
+        };
+
+        sender.send(upload).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "S3 upload thread has stopped")
+        })
+    }
+}
+
+impl Sink for S3ArchiveSink {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        lock(&self.pending).push((record.timestamp(), record.event_id(), record.to_json()));
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let pending = std::mem::take(&mut *lock(&self.pending));
+        let (timestamp, event_id) = match pending.first() {
+            Some((timestamp, event_id, _)) => (*timestamp, *event_id),
+            None => return Ok(()),
+        };
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        for (_, _, payload) in &pending {
+            encoder.write_all(payload.as_bytes())?;
+            encoder.write_all(b"\n")?;
+        }
+        let body = encoder.finish()?;
+
+        let key = self.object_key(timestamp, event_id);
+        let (url, headers) = self.sign(&key, &body, SystemTime::now())?;
+
+        let (done, outcome) = mpsc::channel();
+        self.submit(Upload { url, headers, body, done })?;
+        outcome
+            .recv()
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::BrokenPipe, "S3 upload thread has stopped")))
+    }
+}
+
+/// Starts the thread performing uploads, which owns the blocking HTTP client.
+fn spawn_uploader() -> io::Result<mpsc::Sender<Upload>> {
+    let (sender, receiver) = mpsc::channel::<Upload>();
+
+    std::thread::Builder::new()
+        .name("pogr-s3-uploader".to_string())
+        .spawn(move || {
+            let client = Client::builder().timeout(UPLOAD_TIMEOUT).build();
+            for upload in receiver {
+                let result = match &client {
+                    Ok(client) => put(client, upload.url, upload.headers, upload.body),
+                    Err(err) => Err(io::Error::new(io::ErrorKind::Other, err.to_string())),
+                };
+                let _ = upload.done.send(result);
+            }
+        })?;
+
+    Ok(sender)
+}
+
+/// Uploads an object.
+fn put(client: &Client, url: String, headers: HeaderMap, body: Vec<u8>) -> io::Result<()> {
+    let response = client
+        .put(url)
+        .headers(headers)
+        .body(body)
+        .send()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        let text = response.text().unwrap_or_default();
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("S3 rejected the upload with {}: {}", status, text),
+        ))
+    }
+}
+
+/// Converts `2024-03-01T12:30:05.042Z` into the compact `20240301T123005Z` form.
+fn compact_time(rfc3339: &str) -> String {
+    format!(
+        "{}{}{}T{}{}{}Z",
+        &rfc3339[0..4],
+        &rfc3339[5..7],
+        &rfc3339[8..10],
+        &rfc3339[11..13],
+        &rfc3339[14..16],
+        &rfc3339[17..19]
+    )
+}
+
+/// Percent-encodes a path as S3 expects, keeping `/` separators.
+fn uri_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Computes an HMAC-SHA256.
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Formats bytes as lower-case hex.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Converts a string into a header value.
+fn to_header_value(value: &str) -> io::Result<reqwest::header::HeaderValue> {
+    reqwest::header::HeaderValue::from_str(value).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+/// Locks a mutex, recovering the data if a previous holder panicked.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
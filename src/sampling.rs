@@ -0,0 +1,106 @@
+//! Sampling events by the value of a key field.
+//!
+//! Sampling a fixed fraction of all events thins out every player's session alike, leaving
+//! none of them complete. Key-based sampling instead hashes the value of a configured field,
+//! such as `player_id`, and keeps the events whose hash falls in the sampled range: the same
+//! key is always kept or always dropped, so 1% of the players are recorded in full.
+//!
+//! The hash is FNV-1a over the field's value, followed by the SplitMix64 finalizer so that
+//! short keys such as small numeric IDs spread evenly. It is stable across processes,
+//! restarts and crate versions, so the game client and its servers agree on which players
+//! are sampled.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// FNV-1a 64-bit offset basis.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a 64-bit prime.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Configuration for sampling events by the value of a key field.
+///
+/// The field is looked up among the event's fields, then the fields of its spans and the
+/// layer's default tags, so a `player_id` recorded on a session span samples every event
+/// inside it.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{KeySampling, PogrAppender, PogrLayer};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// // Keep every event of 1% of the players.
+/// let layer = PogrLayer::new(appender).with_key_sampling(KeySampling::new("player_id", 0.01));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeySampling {
+    /// Name of the field whose value is hashed.
+    field: String,
+    /// Fraction of keys whose events are kept, between 0 and 1.
+    rate: f64,
+    /// Whether events without the field are kept.
+    keep_unkeyed: bool,
+}
+
+impl KeySampling {
+    /// Keeps the events of the fraction `rate` of the values of `field`, between 0 and 1.
+    ///
+    /// Events without the field are kept.
+    pub fn new(field: impl Into<String>, rate: f64) -> Self {
+        KeySampling {
+            field: field.into(),
+            rate: rate.clamp(0.0, 1.0),
+            keep_unkeyed: true,
+        }
+    }
+
+    /// Sets whether events without the field are kept. Defaults to `true`.
+    pub fn with_unkeyed(mut self, keep: bool) -> Self {
+        self.keep_unkeyed = keep;
+        self
+    }
+
+    /// Returns `true` if an event with these fields is sampled.
+    pub(crate) fn keeps(&self, fields: &HashMap<String, Value>) -> bool {
+        match fields.get(&self.field) {
+            None | Some(Value::Null) => self.keep_unkeyed,
+            Some(value) => self.keeps_key(value),
+        }
+    }
+
+    /// Returns `true` if events with this key are sampled.
+    fn keeps_key(&self, key: &Value) -> bool {
+        if self.rate >= 1.0 {
+            return true;
+        }
+        // Strings are hashed without their JSON quotes, so `"42"` and `42` are the same key.
+        let hash = match key {
+            Value::String(key) => key_hash(key.as_bytes()),
+            other => key_hash(other.to_string().as_bytes()),
+        };
+        (hash as f64 / u64::MAX as f64) < self.rate
+    }
+}
+
+/// Returns a random number between 0 and 1, for sampling decisions.
+///
+/// Version 7 UUIDs carry random bits; hashing one makes a uniform draw without another
+/// dependency.
+pub(crate) fn random_fraction() -> f64 {
+    key_hash(Uuid::now_v7().as_bytes()) as f64 / u64::MAX as f64
+}
+
+/// Hashes a key with 64-bit FNV-1a and mixes the result with the SplitMix64 finalizer.
+fn key_hash(bytes: &[u8]) -> u64 {
+    let mut hash = bytes
+        .iter()
+        .fold(FNV_OFFSET, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME));
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
@@ -0,0 +1,68 @@
+//! Stripping terminal escape codes and control characters from recorded strings.
+//!
+//! Messages formatted for a colored terminal, e.g. by a library that styles its own output,
+//! carry ANSI escape sequences such as `\x1b[31m`. In POGR they show up as noise, break
+//! searches and can confuse viewers that render them. Sanitization removes ANSI escape
+//! sequences (CSI, OSC and two-character escapes) and every other control character except
+//! tabs and line breaks from message and string fields before they are serialized.
+
+use std::borrow::Cow;
+
+/// The escape character starting ANSI sequences.
+const ESC: char = '\u{1b}';
+/// The bell character, which ends OSC sequences.
+const BEL: char = '\u{7}';
+/// The single-character form of the CSI introducer.
+const CSI: char = '\u{9b}';
+
+/// Returns `true` for the control characters that are kept.
+fn is_kept(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r') || !c.is_control()
+}
+
+/// Removes ANSI escape sequences and control characters other than tabs and line breaks.
+///
+/// Text without any control characters is returned as is, without allocating.
+pub(crate) fn strip_control(text: &str) -> Cow<'_, str> {
+    if text.chars().all(is_kept) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => match chars.next() {
+                // CSI: parameters and intermediates up to a final byte in `@`..=`~`.
+                Some('[') => skip_csi(&mut chars),
+                // OSC, e.g. hyperlinks and window titles: up to BEL or `ESC \`.
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == BEL || (c == ESC && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Other escapes: intermediates in ` `..=`/`, then a final character.
+                Some(' '..='/') => {
+                    while chars.next_if(|c| matches!(c, ' '..='/')).is_some() {}
+                    chars.next();
+                }
+                _ => {}
+            },
+            CSI => skip_csi(&mut chars),
+            c if is_kept(c) => stripped.push(c),
+            _ => {}
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+/// Skips the rest of a CSI sequence, up to and including its final byte.
+fn skip_csi(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    for c in chars.by_ref() {
+        if matches!(c, '@'..='~') {
+            break;
+        }
+    }
+}
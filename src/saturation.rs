@@ -0,0 +1,201 @@
+//! Warnings about telemetry backpressure.
+//!
+//! Records wait in the layer's queue until they are delivered. When the intake slows down or
+//! the application logs faster than records can be sent, the queue grows, memory use with it,
+//! and records are eventually lost, e.g. at shutdown. Saturation alerts warn before that
+//! happens: when the number of pending records crosses one of the high-water marks upwards,
+//! and when records take longer than a threshold from capture to delivery.
+//!
+//! Alerts are reported as `Saturation` diagnostics, so they are visible locally, and
+//! optionally sent to POGR as `WARN` records too. Each condition is reported once when it
+//! starts; a high-water mark is re-armed once the queue drains below it, and the latency alert
+//! once a request is delivered within the threshold again.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pipeline::{InFlight, Queued};
+use crate::ring::{self, QueueSender};
+use crate::{Clock, IdGenerator, IdKind, LogRequest, PogrAppender};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
+use tracing::Level;
+
+/// Thresholds for telemetry backpressure warnings.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, SaturationAlerts};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_saturation_alerts(
+///     SaturationAlerts::new()
+///         .with_high_water_marks([1_000, 10_000, 100_000])
+///         .with_latency_threshold(Duration::from_secs(30))
+///         .with_pogr_events(true),
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SaturationAlerts {
+    /// Numbers of pending records that raise an alert when crossed, in increasing order.
+    high_water_marks: Vec<usize>,
+    /// Time from capture to delivery that raises an alert when exceeded, if any.
+    latency_threshold: Option<Duration>,
+    /// Whether alerts are also sent to POGR.
+    pogr_events: bool,
+}
+
+impl SaturationAlerts {
+    /// Creates alerts without any threshold.
+    pub fn new() -> Self {
+        SaturationAlerts::default()
+    }
+
+    /// Warns when the number of records waiting for delivery crosses one of `marks` upwards.
+    pub fn with_high_water_marks(mut self, marks: impl IntoIterator<Item = usize>) -> Self {
+        self.high_water_marks = marks.into_iter().filter(|mark| *mark > 0).collect();
+        self.high_water_marks.sort_unstable();
+        self.high_water_marks.dedup();
+        self
+    }
+
+    /// Warns when records take longer than `threshold` from capture to delivery.
+    pub fn with_latency_threshold(mut self, threshold: Duration) -> Self {
+        self.latency_threshold = Some(threshold);
+        self
+    }
+
+    /// Also sends the alerts to POGR, as `rust tracing telemetry saturated` records. Off by
+    /// default.
+    pub fn with_pogr_events(mut self, enabled: bool) -> Self {
+        self.pogr_events = enabled;
+        self
+    }
+}
+
+/// Where alerts sent to POGR are queued.
+struct AlertQueue {
+    /// Queue of the layer's worker.
+    queue: QueueSender,
+    /// Submissions tracked for flushing.
+    in_flight: Arc<InFlight>,
+    /// Clock assigning the alerts' timestamps.
+    clock: Arc<dyn Clock>,
+    /// Generator of the alerts' `event_id`s.
+    ids: Arc<dyn IdGenerator>,
+}
+
+/// The state of the saturation alerts of a layer.
+pub(crate) struct SaturationMonitor {
+    /// The thresholds.
+    config: SaturationAlerts,
+    /// Number of high-water marks currently crossed.
+    crossed: AtomicUsize,
+    /// Whether the latency alert has been raised and not cleared yet.
+    slow: AtomicBool,
+    /// Where alerts are sent to POGR, once connected.
+    alerts: OnceLock<AlertQueue>,
+}
+
+impl SaturationMonitor {
+    /// Creates the state for the given thresholds.
+    pub(crate) fn new(config: SaturationAlerts) -> Self {
+        SaturationMonitor {
+            config,
+            crossed: AtomicUsize::new(0),
+            slow: AtomicBool::new(false),
+            alerts: OnceLock::new(),
+        }
+    }
+
+    /// Returns `true` if the alerts are sent to POGR, but the queue is not connected yet.
+    pub(crate) fn needs_queue(&self) -> bool {
+        self.config.pogr_events && self.alerts.get().is_none()
+    }
+
+    /// Connects the queue that alerts are sent to POGR through.
+    pub(crate) fn connect(
+        &self,
+        queue: QueueSender,
+        in_flight: Arc<InFlight>,
+        clock: Arc<dyn Clock>,
+        ids: Arc<dyn IdGenerator>,
+    ) {
+        let _ = self.alerts.set(AlertQueue { queue, in_flight, clock, ids });
+    }
+
+    /// Checks the number of records waiting for delivery against the high-water marks.
+    pub(crate) fn check_pending(&self, pending: usize) {
+        let marks = &self.config.high_water_marks;
+        let crossed = marks.iter().take_while(|mark| pending >= **mark).count();
+        let previous = self.crossed.swap(crossed, Ordering::SeqCst);
+        if crossed > previous {
+            let mark = marks[crossed - 1];
+            self.alert(
+                format!("{} records are waiting for delivery, over the high-water mark of {}", pending, mark),
+                json!({ "condition": "queue", "pending": pending, "high_water_mark": mark }),
+            );
+        }
+    }
+
+    /// Checks how long the oldest record of a delivered request took from capture.
+    pub(crate) fn check_latency(&self, captured_at: SystemTime) {
+        let Some(threshold) = self.config.latency_threshold else {
+            return;
+        };
+        let latency = SystemTime::now().duration_since(captured_at).unwrap_or_default();
+        let slow = latency > threshold;
+        if slow && !self.slow.swap(true, Ordering::SeqCst) {
+            self.alert(
+                format!(
+                    "Records took {} ms from capture to delivery, over the threshold of {} ms",
+                    latency.as_millis(),
+                    threshold.as_millis()
+                ),
+                json!({
+                    "condition": "latency",
+                    "latency_ms": latency.as_secs_f64() * 1000.0,
+                    "threshold_ms": threshold.as_secs_f64() * 1000.0,
+                }),
+            );
+        } else if !slow {
+            self.slow.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Reports an alert locally, and to POGR if enabled.
+    fn alert(&self, message: String, data: Value) {
+        diagnostics::warn(DiagnosticKind::Saturation, &message);
+        let Some(alerts) = self.alerts.get().filter(|_| self.config.pogr_events) else {
+            return;
+        };
+        let _ = alerts.queue.send(Queued {
+            event_id: alerts.ids.generate(IdKind::Event),
+            level: Level::WARN,
+            timestamp: alerts.clock.now(),
+            build: Box::new(move |appender| alert_request(appender, message, data)),
+            attachments: Vec::new(),
+            log_id: None,
+            ticket: alerts.in_flight.start(),
+            size: ring::RECORD_OVERHEAD,
+            charge: None,
+        });
+    }
+}
+
+/// Builds the record sent to POGR for an alert.
+fn alert_request(appender: &PogrAppender, message: String, data: Value) -> LogRequest {
+    LogRequest {
+        service: appender.service_name.clone(),
+        environment: appender.environment.clone(),
+        severity: Level::WARN.to_string(),
+        r#type: appender.service_type.clone(),
+        log: "rust tracing telemetry saturated".to_string(),
+        data,
+        tags: json!({ "message": message }),
+    }
+}
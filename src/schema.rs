@@ -0,0 +1,113 @@
+//! Validation of outgoing records against JSON Schemas.
+//!
+//! Analytics in POGR rely on the shape of the tags and data of each record. When a field is
+//! renamed or changes type in one service, the records still arrive, and the dashboards built
+//! on them silently break. `SchemaValidation` checks every record against a JSON Schema for
+//! its tags, its data, or both, before it is submitted.
+//!
+//! A record that does not satisfy its schema is not submitted to the intake. It is handed to
+//! the fallback sinks instead, where `SinkRecord::validation_error` describes what is wrong,
+//! and reported as a `Schema` diagnostic. Records are validated as they are built, before they
+//! are truncated to fit into a request.
+
+use crate::LogRequest;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// JSON Schemas that the tags and data of outgoing records must satisfy.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, SchemaValidation};
+/// use serde_json::json;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let schema = SchemaValidation::new()
+///     .with_tags_schema(&json!({
+///         "type": "object",
+///         "properties": { "player_id": { "type": "string" }, "score": { "type": "integer" } },
+///     }))
+///     .unwrap();
+/// let layer = PogrLayer::new(appender).with_schema_validation(schema);
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct SchemaValidation {
+    /// Schema of the records' tags, if they are validated.
+    tags: Option<Arc<JSONSchema>>,
+    /// Schema of the records' data, if it is validated.
+    data: Option<Arc<JSONSchema>>,
+}
+
+impl fmt::Debug for SchemaValidation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaValidation")
+            .field("tags", &self.tags.is_some())
+            .field("data", &self.data.is_some())
+            .finish()
+    }
+}
+
+impl SchemaValidation {
+    /// Creates a validation that accepts every record.
+    pub fn new() -> Self {
+        SchemaValidation::default()
+    }
+
+    /// Validates the tags of every record, i.e. the fields of the event and of its spans,
+    /// against `schema`.
+    pub fn with_tags_schema(mut self, schema: &Value) -> Result<Self, InvalidSchema> {
+        self.tags = Some(compile(schema)?);
+        Ok(self)
+    }
+
+    /// Validates the data of every record, i.e. its metadata such as the target and level,
+    /// against `schema`.
+    pub fn with_data_schema(mut self, schema: &Value) -> Result<Self, InvalidSchema> {
+        self.data = Some(compile(schema)?);
+        Ok(self)
+    }
+
+    /// Checks a record against the schemas, describing every violation if it fails.
+    pub(crate) fn validate(&self, request: &LogRequest) -> Result<(), String> {
+        let mut violations = Vec::new();
+        for (part, schema, instance) in [("tags", &self.tags, &request.tags), ("data", &self.data, &request.data)] {
+            let Some(schema) = schema else {
+                continue;
+            };
+            if let Err(errors) = schema.validate(instance) {
+                violations.extend(errors.map(|error| format!("{}{}: {}", part, error.instance_path, error)));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations.join("; "))
+        }
+    }
+}
+
+/// Compiles a schema.
+fn compile(schema: &Value) -> Result<Arc<JSONSchema>, InvalidSchema> {
+    JSONSchema::compile(schema)
+        .map(Arc::new)
+        .map_err(|err| InvalidSchema(err.to_string()))
+}
+
+/// Error returned when a JSON Schema cannot be compiled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidSchema(String);
+
+impl fmt::Display for InvalidSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid JSON Schema: {}", self.0)
+    }
+}
+
+impl Error for InvalidSchema {}
@@ -0,0 +1,280 @@
+//! Turnkey setup helpers used by `#[pogr_tracing_rs::main]` and `#[pogr_tracing_rs::test]`.
+//!
+//! These helpers wire a `PogrLayer` into a `tracing` subscriber with sensible defaults, install
+//! a panic hook that reports panics to POGR, and make sure pending log submissions are flushed
+//! before the application or test exits.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::filter;
+use crate::pause::KillSwitch;
+use crate::pipeline::{FlushTimedOut, InFlight};
+use crate::shutdown;
+use crate::{PogrAppender, PogrLayer, TargetLevels, VerbosityProfile};
+use std::future::Future;
+use std::panic;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tracing::dispatcher::DefaultGuard;
+use tracing_subscriber::{layer::SubscriberExt, Layer, Registry};
+
+/// A handle returned by `init` (or `PogrLayer::guard`) that can flush pending log submissions.
+///
+/// When obtained from a scoped setup, the guard also keeps the scoped subscriber installed
+/// until it is dropped.
+pub struct PogrGuard {
+    /// In-flight submissions of the layer this guard belongs to.
+    in_flight: Arc<InFlight>,
+    /// Whether the intake has paused delivery, and what happens to records meanwhile.
+    kill_switch: Arc<KillSwitch>,
+    /// Appender of the layer, whose session is ended at shutdown.
+    appender: Arc<Mutex<PogrAppender>>,
+    /// Keeps a thread-local default subscriber alive for scoped setups.
+    _default: Option<DefaultGuard>,
+}
+
+impl PogrGuard {
+    /// Creates a guard watching the given in-flight submissions.
+    pub(crate) fn new(in_flight: Arc<InFlight>, kill_switch: Arc<KillSwitch>, appender: Arc<Mutex<PogrAppender>>) -> Self {
+        PogrGuard {
+            in_flight,
+            kill_switch,
+            appender,
+            _default: None,
+        }
+    }
+
+    /// Waits until every log submission started by the associated layer has completed.
+    pub async fn flush(&self) {
+        self.in_flight.wait_idle().await;
+    }
+
+    /// Waits until every log submission started by the associated layer has completed, or
+    /// `timeout` has passed.
+    ///
+    /// See `PogrLayer::flush_timeout`.
+    pub async fn flush_timeout(&self, timeout: Duration) -> Result<(), FlushTimedOut> {
+        self.in_flight.drain(timeout, self.kill_switch.policy()).await
+    }
+
+    /// Shuts the telemetry of the associated layer down: stops accepting new events, flushes
+    /// the pending records within `timeout`, and ends the POGR session.
+    ///
+    /// Events emitted afterwards are ignored. The error says how many records were left behind
+    /// by the flush; a failure to end the session is only reported as a `Delivery` diagnostic.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<(), FlushTimedOut> {
+        self.in_flight.close();
+        let drained = self.flush_timeout(timeout).await;
+
+        let appender = self.appender.lock().await.clone();
+        if let Err(reason) = shutdown::end_session(&appender).await {
+            diagnostics::warn(DiagnosticKind::Delivery, format!("Failed to end the POGR session: {}", reason));
+        }
+        drained
+    }
+
+    /// Waits for `SIGTERM` or Ctrl-C, then shuts the telemetry down with `shutdown`, and
+    /// resolves once it is done, so the application can exit cleanly.
+    ///
+    /// Run it next to the application's own work, so the telemetry is stopped whichever comes
+    /// first. This must be called inside a tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::DEFAULT_SHUTDOWN_TIMEOUT;
+    ///
+    /// # async fn serve() {}
+    /// # async fn run() {
+    /// let guard = pogr_tracing_rs::init().await;
+    ///
+    /// tokio::select! {
+    ///     _ = serve() => guard.shutdown(DEFAULT_SHUTDOWN_TIMEOUT).await,
+    ///     result = guard.shutdown_on_signal(DEFAULT_SHUTDOWN_TIMEOUT) => result,
+    /// }
+    /// .ok();
+    /// # }
+    /// ```
+    pub async fn shutdown_on_signal(&self, timeout: Duration) -> Result<(), FlushTimedOut> {
+        shutdown::shutdown_signal().await;
+        self.shutdown(timeout).await
+    }
+
+    /// Returns `true` while so many records are waiting for delivery that the layer's pipeline
+    /// is backpressured.
+    ///
+    /// This does not block, so latency-critical code can call it to skip optional logging. The
+    /// threshold is set with `PogrLayer::with_backpressure_threshold`.
+    pub fn is_backpressured(&self) -> bool {
+        self.in_flight.backpressure.is_backpressured()
+    }
+
+    /// Returns a receiver that is notified whenever the pipeline becomes backpressured, or
+    /// recovers.
+    pub fn backpressure(&self) -> watch::Receiver<bool> {
+        self.in_flight.backpressure.subscribe()
+    }
+}
+
+/// Builds a subscriber with a `PogrLayer` configured by the verbosity profile of the
+/// session's environment, and a console layer if the profile echoes events.
+///
+/// The `PogrAppender` is created with `PogrAppender::new(None, None)`, so the usual environment
+/// variables (`POGR_ACCESS`, `POGR_SECRET`, `POGR_INIT_ENDPOINT`, ...) apply. The profile can
+/// be forced with `POGR_PROFILE`.
+async fn default_subscriber() -> (impl tracing::Subscriber + Send + Sync, PogrGuard) {
+    let appender = PogrAppender::new(None, None).await;
+    let profile = VerbosityProfile::detect(&appender.environment);
+    let layer = PogrLayer::new(appender).with_profile(profile);
+    let guard = layer.guard();
+
+    let console = profile
+        .console_echo()
+        .then(|| tracing_subscriber::fmt::layer().with_filter(console_levels(profile)));
+    let subscriber = Registry::default()
+        .with(console)
+        .with(layer);
+
+    (subscriber, guard)
+}
+
+/// Returns the thresholds of the console output: `POGR_LOG_LEVEL` if it is set, and the
+/// profile's otherwise.
+fn console_levels(profile: VerbosityProfile) -> TargetLevels {
+    filter::env_levels().ok().flatten().unwrap_or_else(|| profile.target_levels())
+}
+
+/// Installs a default POGR subscriber globally and registers the POGR panic hook.
+///
+/// The subscriber follows the `VerbosityProfile` of the session's environment: in production,
+/// warnings and errors plus a sample of the informational events are forwarded; in staging,
+/// informational events and above; in development, debug events and above, also printed to
+/// the console. `POGR_LOG_LEVEL` replaces the thresholds and sampling of the profile.
+///
+/// This is what `#[pogr_tracing_rs::main]` calls before running the body of `main`. If a global
+/// subscriber has already been set, the existing one is kept and a warning is reported as a
+/// diagnostic.
+///
+/// # Returns
+///
+/// A `PogrGuard` that should be flushed before the application exits.
+///
+/// # Panics
+///
+/// Panics under the same conditions as `PogrAppender::new`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #[tokio::main]
+/// async fn main() {
+///     let guard = pogr_tracing_rs::init().await;
+///
+///     tracing::info!("Application started");
+///
+///     guard.flush().await;
+/// }
+/// ```
+pub async fn init() -> PogrGuard {
+    let (subscriber, guard) = default_subscriber().await;
+
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        diagnostics::warn(DiagnosticKind::Init, "A global subscriber is already set, keeping the existing one");
+    }
+    install_panic_hook();
+
+    guard
+}
+
+/// Installs a default POGR subscriber for the current thread only.
+async fn init_scoped() -> PogrGuard {
+    let (subscriber, mut guard) = default_subscriber().await;
+    guard._default = Some(tracing::subscriber::set_default(subscriber));
+    guard
+}
+
+/// Registers a panic hook that reports panics as `ERROR` events before running the
+/// previously installed hook.
+///
+/// The event is emitted with the `panic` target and carries the panic message and location
+/// as fields, so it shows up in POGR like any other error.
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        // The layer hands events to the tokio runtime; reporting from a thread without one
+        // would panic inside the hook and abort the process.
+        if tokio::runtime::Handle::try_current().is_ok() {
+            let payload = info
+                .payload()
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Box<dyn Any>".to_string());
+            let location = info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            tracing::error!(target: "panic", payload = %payload, location = %location, "panicked");
+        }
+
+        previous(info);
+    }));
+}
+
+/// Runs the body of a `#[pogr_tracing_rs::main]` function.
+///
+/// The body is run as a local task so that a panic can be observed, logged and flushed before
+/// it is resumed on the calling thread.
+pub fn run_main<F>(body: F) -> F::Output
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed building the Runtime");
+
+    runtime.block_on(async move {
+        let guard = init().await;
+        let output = run_local(body).await;
+        guard.flush().await;
+
+        output.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+/// Runs the body of a `#[pogr_tracing_rs::test]` function.
+pub fn run_test<F>(body: F) -> F::Output
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed building the Runtime");
+
+    runtime.block_on(async move {
+        let guard = init_scoped().await;
+        let output = run_local(body).await;
+        guard.flush().await;
+
+        output.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+/// Drives a future to completion on a `LocalSet`, capturing a panic payload instead of unwinding.
+async fn run_local<F>(body: F) -> Result<F::Output, Box<dyn std::any::Any + Send>>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(tokio::task::spawn_local(body))
+        .await
+        .map_err(|error| error.into_panic())
+}
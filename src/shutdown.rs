@@ -0,0 +1,98 @@
+//! Graceful shutdown on termination signals.
+//!
+//! Container orchestrators stop a service by sending it `SIGTERM`, and kill it once a grace
+//! period has passed. `PogrGuard::shutdown_on_signal` waits for `SIGTERM` or Ctrl-C, then
+//! shuts the telemetry down within that grace period:
+//!
+//! 1. the layer stops accepting new events, so the backlog cannot grow any further,
+//! 2. the pending records are flushed, with a deadline, as with `PogrGuard::flush_timeout`,
+//! 3. the POGR session is ended, so the intake knows that no more records will follow.
+//!
+//! The session is ended by posting to the `end` endpoint next to the appender's `init`
+//! endpoint, e.g. `https://api.pogr.io/v1/intake/end`, unless `POGR_END_ENDPOINT` is set:
+//!
+//! ```text
+//! POST /v1/intake/end
+//! INTAKE_SESSION_ID: ...
+//! ```
+
+use crate::{http_dump, PogrAppender};
+use serde::Deserialize;
+use std::env;
+use std::time::Duration;
+
+/// Default time `PogrGuard::shutdown` gives the pending records to be delivered.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long ending the session may take.
+const END_SESSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The response of the session end endpoint.
+#[derive(Deserialize)]
+struct EndResponse {
+    /// Indicates whether the session was ended.
+    success: bool,
+}
+
+/// Resolves once the process is asked to terminate, by `SIGTERM` or Ctrl-C (`SIGINT`).
+///
+/// On platforms without Unix signals, only Ctrl-C is watched. This can also be handed to a
+/// server's own graceful shutdown, so that both stop on the same signal.
+///
+/// This must be called inside a tokio runtime.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        // Without a handler, Ctrl-C keeps terminating the process as usual.
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Returns the session end endpoint from `POGR_END_ENDPOINT`, or the one next to the init
+/// endpoint.
+fn end_endpoint(appender: &PogrAppender) -> String {
+    env::var("POGR_END_ENDPOINT").unwrap_or_else(|_| {
+        let init = appender.resolve_endpoint(&appender.init_endpoint);
+        let init = init.trim_end_matches('/');
+        match init.rsplit_once('/') {
+            Some((base, _)) => format!("{}/end", base),
+            None => format!("{}/end", init),
+        }
+    })
+}
+
+/// Ends the appender's session.
+pub(crate) async fn end_session(appender: &PogrAppender) -> Result<(), String> {
+    let client = &appender.client;
+    let request = client
+        .post(end_endpoint(appender))
+        .header("INTAKE_SESSION_ID", &appender.session_id);
+    let (status, body) = tokio::time::timeout(END_SESSION_TIMEOUT, http_dump::send(client, request))
+        .await
+        .map_err(|_| format!("no response within {} s", END_SESSION_TIMEOUT.as_secs()))?
+        .map_err(|err| err.to_string())?;
+
+    match serde_json::from_slice::<EndResponse>(&body) {
+        Ok(response) if response.success => Ok(()),
+        _ => Err(format!("{}: {}", status, String::from_utf8_lossy(&body))),
+    }
+}
@@ -0,0 +1,230 @@
+//! Local sinks that receive a copy of the records sent to POGR.
+//!
+//! A sink is either a fallback, receiving only the records that could not be delivered to
+//! the intake, or a mirror, receiving every record whether or not delivery succeeds. Sinks
+//! are called from the background worker, after records have been built, truncated and
+//! batched, so they see exactly what the intake receives.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::pipeline::LogRecord;
+use crate::LogRequest;
+use serde_json::Value;
+use std::fmt;
+use std::io;
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// A local destination for log records, such as journald or a file.
+///
+/// Sinks are called from the background worker and should return quickly; a slow sink
+/// delays delivery to POGR.
+pub trait Sink: Send + Sync {
+    /// Writes a single record.
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()>;
+
+    /// Flushes buffered records, if the sink buffers any. Called after each batch.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Allows sharing one sink between several layers, or keeping a handle to it.
+impl<S: Sink + ?Sized> Sink for Arc<S> {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        (**self).write(record)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+/// When a sink receives records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SinkMode {
+    /// Only records that could not be delivered to POGR.
+    Fallback,
+    /// Every record, whether or not delivery succeeds.
+    Mirror,
+}
+
+/// A record handed to a `Sink`.
+pub struct SinkRecord<'a> {
+    /// The record as it is submitted to the intake.
+    record: &'a LogRecord,
+}
+
+impl<'a> SinkRecord<'a> {
+    pub(crate) fn new(record: &'a LogRecord) -> Self {
+        SinkRecord { record }
+    }
+
+    /// Returns the client-generated identifier of the record.
+    pub fn event_id(&self) -> Uuid {
+        self.record.event_id
+    }
+
+    /// Returns when the record was captured.
+    pub fn timestamp(&self) -> SystemTime {
+        self.record.timestamp
+    }
+
+    /// Returns the log message.
+    pub fn request(&self) -> &LogRequest {
+        &self.record.request
+    }
+
+    /// Returns why the record does not satisfy the configured JSON Schemas, if it was handed to
+    /// the fallback sinks because of that.
+    pub fn validation_error(&self) -> Option<&str> {
+        self.record.validation_error.as_deref()
+    }
+
+    /// Returns why the intake refused the record, if it was quarantined to the fallback sinks
+    /// because the intake refused it on its own, or it could not be serialized.
+    pub fn quarantine_error(&self) -> Option<&str> {
+        self.record.quarantine_error.get().map(String::as_str)
+    }
+
+    /// Returns the value of an event field, if the record has it.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.record.request.tags.get(name)
+    }
+
+    /// Serializes the record exactly as it is submitted to the intake in a JSON request,
+    /// including base64-encoded attachments.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.record.envelope()).unwrap_or_default()
+    }
+
+    /// Renders a subject or topic template for this record.
+    ///
+    /// `{target}` is replaced with the event's target, with `::` replaced by `separator`;
+    /// `{level}` with the lower-cased severity; `{service}` and `{environment}` with the
+    /// appender's service name and environment. Whitespace and the `reserved` characters are
+    /// replaced with `_`.
+    pub(crate) fn render(&self, template: &str, separator: &str, reserved: &[char]) -> String {
+        let request = self.request();
+        let target = request
+            .data
+            .get("target")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        let sanitize = |value: &str| -> String {
+            value
+                .chars()
+                .map(|c| if c.is_whitespace() || reserved.contains(&c) { '_' } else { c })
+                .collect()
+        };
+
+        template
+            .replace("{target}", &sanitize(target).replace("::", separator))
+            .replace("{level}", &sanitize(&request.severity.to_ascii_lowercase()))
+            .replace("{service}", &sanitize(&request.service))
+            .replace("{environment}", &sanitize(&request.environment))
+    }
+}
+
+/// The sinks configured on a layer.
+#[derive(Clone, Default)]
+pub(crate) struct Sinks {
+    /// Each sink with the records it receives.
+    sinks: Vec<(Arc<dyn Sink>, SinkMode)>,
+}
+
+impl Sinks {
+    /// Adds a sink.
+    pub(crate) fn push(&mut self, sink: Arc<dyn Sink>, mode: SinkMode) {
+        self.sinks.push((sink, mode));
+    }
+
+    /// Writes records to the sinks with the given mode.
+    ///
+    /// Sink failures are reported on the console but do not affect delivery to POGR.
+    pub(crate) fn write(&self, mode: SinkMode, records: &[&LogRecord]) {
+        for (sink, _) in self.sinks.iter().filter(|(_, sink_mode)| *sink_mode == mode) {
+            let result = records
+                .iter()
+                .try_for_each(|record| sink.write(&SinkRecord::new(record)))
+                .and_then(|()| sink.flush());
+
+            if let Err(err) = result {
+                diagnostics::error(DiagnosticKind::Sink, format!("Failed to write log records to {:?} sink: {}", mode, err));
+            }
+        }
+    }
+}
+
+/// Number of batches a `SinkThread` queues before further batches are dropped.
+const SINK_QUEUE_CAPACITY: usize = 16;
+
+/// A thread doing a network sink's blocking I/O, fed batches over a bounded channel.
+///
+/// Sinks are called from the async delivery worker, so a sink that connects, writes and waits
+/// for acknowledgements itself would block the worker for as long as its destination is slow
+/// or unreachable, stalling delivery to POGR. Such sinks queue each batch here instead: the
+/// flush returns once the batch is queued, or fails at once if the thread is too far behind,
+/// and failures to publish a batch are reported as `Sink` diagnostics.
+pub(crate) struct SinkThread<T> {
+    /// Name of the thread, also used in diagnostics.
+    name: &'static str,
+    /// Sends batches to the thread, started on the first batch.
+    sender: Mutex<Option<mpsc::SyncSender<T>>>,
+}
+
+impl<T: Send + 'static> SinkThread<T> {
+    /// Creates a thread named `name`, which is started on the first batch.
+    pub(crate) fn new(name: &'static str) -> Self {
+        SinkThread { name, sender: Mutex::new(None) }
+    }
+
+    /// Queues a batch, starting the thread with the handler returned by `start` if needed.
+    pub(crate) fn send<F>(&self, batch: T, start: impl FnOnce() -> io::Result<F>) -> io::Result<()>
+    where
+        F: FnMut(T) -> io::Result<()> + Send + 'static,
+    {
+        let mut sender = self.sender.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let queue = match sender.as_ref() {
+            Some(queue) => queue,
+            None => sender.insert(spawn(self.name, start()?)?),
+        };
+
+        queue.try_send(batch).map_err(|err| match err {
+            TrySendError::Full(_) => io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is {} batches behind, dropped a batch", self.name, SINK_QUEUE_CAPACITY),
+            ),
+            TrySendError::Disconnected(_) => {
+                io::Error::new(io::ErrorKind::BrokenPipe, format!("{} has stopped", self.name))
+            }
+        })
+    }
+}
+
+impl<T> fmt::Debug for SinkThread<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SinkThread").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+/// Starts a thread handling the batches received over a bounded channel, until the sending
+/// sink is dropped.
+fn spawn<T: Send + 'static>(
+    name: &'static str,
+    mut handle: impl FnMut(T) -> io::Result<()> + Send + 'static,
+) -> io::Result<mpsc::SyncSender<T>> {
+    let (sender, receiver) = mpsc::sync_channel::<T>(SINK_QUEUE_CAPACITY);
+
+    std::thread::Builder::new().name(name.to_string()).spawn(move || {
+        for batch in receiver {
+            if let Err(err) = handle(batch) {
+                diagnostics::error(DiagnosticKind::Sink, format!("Failed to publish log records from {}: {}", name, err));
+            }
+        }
+    })?;
+
+    Ok(sender)
+}
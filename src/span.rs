@@ -0,0 +1,110 @@
+//! Span support for the POGR layer.
+//!
+//! Span fields are captured when a span is created and stored in the span's extensions, so
+//! they can be attached to events recorded inside the span and to the records the layer
+//! produces for the span itself. Fields recorded later with `Span::record` update the stored
+//! values. Optionally, the layer can also emit lightweight records whenever a span is entered,
+//! exited or closed, which helps when debugging concurrency issues.
+
+use crate::filter;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Fields recorded on a span, stored in the span's extensions by `PogrLayer`.
+pub(crate) struct SpanFields {
+    /// Field names mapped to their JSON values.
+    pub(crate) fields: HashMap<String, Value>,
+    /// When the span was created, used to report its lifetime on close.
+    pub(crate) created_at: Instant,
+    /// IDs of spans this span follows from, e.g. the jobs a join point waited on.
+    pub(crate) follows_from: Vec<u64>,
+}
+
+/// Configuration for span enter, exit and close records.
+///
+/// Enter/exit records are opt-in and limited to the selected targets, since busy async code
+/// can enter and exit the same span many times. Each record carries the span's name, ID and
+/// fields and is sent through the normal log pipeline with the span's level.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, SpanEvents};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender)
+///     .with_span_events(SpanEvents::for_targets(["matchmaking", "netcode::sync"]).with_exit(false));
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpanEvents {
+    /// Target prefixes for which enter/exit records are emitted.
+    targets: Vec<String>,
+    /// Whether to emit a record when a span is entered.
+    enter: bool,
+    /// Whether to emit a record when a span is exited.
+    exit: bool,
+    /// Whether to emit a record when a span is closed.
+    close: bool,
+}
+
+impl SpanEvents {
+    /// Emits enter and exit records for spans whose target matches one of `targets`.
+    ///
+    /// A target matches itself and every module below it.
+    pub fn for_targets<I, T>(targets: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        SpanEvents {
+            targets: targets.into_iter().map(Into::into).collect(),
+            enter: true,
+            exit: true,
+            close: false,
+        }
+    }
+
+    /// Enables or disables records for span entries.
+    pub fn with_enter(mut self, enter: bool) -> Self {
+        self.enter = enter;
+        self
+    }
+
+    /// Enables or disables records for span exits.
+    pub fn with_exit(mut self, exit: bool) -> Self {
+        self.exit = exit;
+        self
+    }
+
+    /// Enables or disables records for span closes.
+    ///
+    /// Close records are sent once per span, with the span's final fields and its lifetime.
+    pub fn with_close(mut self, close: bool) -> Self {
+        self.close = close;
+        self
+    }
+
+    /// Returns `true` if entering spans with the given target should produce a record.
+    pub(crate) fn enter_enabled(&self, target: &str) -> bool {
+        self.enter && self.matches(target)
+    }
+
+    /// Returns `true` if exiting spans with the given target should produce a record.
+    pub(crate) fn exit_enabled(&self, target: &str) -> bool {
+        self.exit && self.matches(target)
+    }
+
+    /// Returns `true` if closing spans with the given target should produce a record.
+    pub(crate) fn close_enabled(&self, target: &str) -> bool {
+        self.close && self.matches(target)
+    }
+
+    fn matches(&self, target: &str) -> bool {
+        self.targets
+            .iter()
+            .any(|prefix| filter::target_matches(target, prefix))
+    }
+}
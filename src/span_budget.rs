@@ -0,0 +1,80 @@
+//! Warnings for spans that outlive their duration budget.
+//!
+//! Latency summaries show how a span's timings drift over minutes; a budget flags each slow
+//! span on its own. When a span with a budget closes after more than its budget, the layer
+//! sends a `WARN` record for it, with the span's fields as tags and its actual duration and
+//! budget in `data`:
+//!
+//! ```text
+//! {"severity": "WARN", "log": "rust tracing span over budget",
+//!  "data": {"name": "load_level", "span_id": 1, "duration_ms": 312.4, "budget_ms": 250.0, ...},
+//!  "tags": {"level_name": "dust2"}}
+//! ```
+//!
+//! The warnings are subject to the target thresholds at `WARN`, so a slow span is reported
+//! even if its own level is below the threshold of its target.
+
+use crate::filter;
+use std::time::Duration;
+
+/// Duration budgets of spans, by span name.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, SpanBudgets};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender).with_span_budgets(
+///     SpanBudgets::new()
+///         .with_budget("load_level", Duration::from_millis(250))
+///         .with_target_budget("matchmaking", "find_match", Duration::from_secs(5)),
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SpanBudgets {
+    /// Span names, optional target prefixes and budgets, most recently added first.
+    budgets: Vec<(String, Option<String>, Duration)>,
+}
+
+impl SpanBudgets {
+    /// Creates a set without any budget.
+    pub fn new() -> Self {
+        SpanBudgets::default()
+    }
+
+    /// Sets the budget of the spans named `span_name`, on any target.
+    pub fn with_budget(mut self, span_name: impl Into<String>, budget: Duration) -> Self {
+        self.budgets.insert(0, (span_name.into(), None, budget));
+        self
+    }
+
+    /// Sets the budget of the spans named `span_name` whose target matches `target`.
+    ///
+    /// A target matches itself and every module below it. This budget takes precedence over
+    /// one set with `with_budget` for the same name.
+    pub fn with_target_budget(mut self, target: impl Into<String>, span_name: impl Into<String>, budget: Duration) -> Self {
+        self.budgets.insert(0, (span_name.into(), Some(target.into()), budget));
+        self
+    }
+
+    /// Returns `true` if no budget is set.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.budgets.is_empty()
+    }
+
+    /// Returns the budget of spans with the given target and name, if they have one.
+    pub(crate) fn budget_for(&self, target: &str, span_name: &str) -> Option<Duration> {
+        let matching = |scoped: bool| {
+            self.budgets.iter().find(|(name, prefix, _)| {
+                name == span_name
+                    && prefix.is_some() == scoped
+                    && prefix.as_deref().map_or(true, |prefix| filter::target_matches(target, prefix))
+            })
+        };
+        matching(true).or_else(|| matching(false)).map(|(_, _, budget)| *budget)
+    }
+}
@@ -0,0 +1,300 @@
+//! Latency summaries of closed spans.
+//!
+//! Shipping a record for every closed span gives exact timings, but is far too much traffic
+//! for hot code paths. Instead, the layer can aggregate the lifetimes of closed spans per span
+//! name into histograms and periodically report their p50, p90 and p99 to POGR's metrics
+//! intake, which is enough to track latency objectives:
+//!
+//! ```text
+//! [{"name": "span_duration", "type": "summary", "labels": {"span": "load_level", "target": "game::world"},
+//!   "count": 1204, "quantiles": {"p50": 12.1, "p90": 40.3, "p99": 97.0}, "unit": "milliseconds", ...}]
+//! ```
+//!
+//! The histograms follow the HDR histogram layout: buckets are linear below 128 microseconds
+//! and then split every power of two into 64 sub-buckets, so a reported quantile is within
+//! 1.6% of the exact value whatever the magnitude, with a bounded memory footprint. Each
+//! report covers the spans closed since the previous one.
+
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::filter;
+use crate::metrics_intake::{self, MetricReport};
+use crate::{clock, PogrAppender};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// Default time between two latency reports.
+pub const DEFAULT_LATENCY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Name of the reported metric.
+const METRIC_NAME: &str = "span_duration";
+
+/// Quantiles reported for each span name.
+const QUANTILES: &[(&str, f64)] = &[("p50", 0.5), ("p90", 0.9), ("p99", 0.99)];
+
+/// Values below this many microseconds have a bucket each.
+const LINEAR_BUCKETS: u64 = 128;
+
+/// Number of sub-buckets per power of two above `LINEAR_BUCKETS`.
+const SUB_BUCKETS: u64 = LINEAR_BUCKETS / 2;
+
+/// Configuration for span latency summaries.
+///
+/// By default the lifetimes of all spans accepted by the layer are aggregated; use
+/// `for_targets` to limit the summaries to some targets.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, SpanLatency};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// let layer = PogrLayer::new(appender)
+///     .with_span_latency(SpanLatency::for_targets(["matchmaking"]).with_interval(Duration::from_secs(30)));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpanLatency {
+    /// Target prefixes of the aggregated spans; all spans if empty.
+    targets: Vec<String>,
+    /// Time between two reports.
+    interval: Duration,
+    /// URL of the metrics intake.
+    endpoint: String,
+}
+
+impl Default for SpanLatency {
+    fn default() -> Self {
+        SpanLatency::new()
+    }
+}
+
+impl SpanLatency {
+    /// Aggregates the lifetimes of all spans, reported every `DEFAULT_LATENCY_INTERVAL`.
+    ///
+    /// The metrics intake is taken from `POGR_METRICS_ENDPOINT`, or defaults to POGR's.
+    pub fn new() -> Self {
+        SpanLatency {
+            targets: Vec::new(),
+            interval: DEFAULT_LATENCY_INTERVAL,
+            endpoint: metrics_intake::endpoint_from_env(),
+        }
+    }
+
+    /// Aggregates the lifetimes of spans whose target matches one of `targets`.
+    ///
+    /// A target matches itself and every module below it.
+    pub fn for_targets<I, T>(targets: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        SpanLatency {
+            targets: targets.into_iter().map(Into::into).collect(),
+            ..SpanLatency::new()
+        }
+    }
+
+    /// Sets the time between two reports. Defaults to `DEFAULT_LATENCY_INTERVAL`.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the URL of the metrics intake.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Returns `true` if the lifetimes of spans with the given target are aggregated.
+    fn matches(&self, target: &str) -> bool {
+        self.targets.is_empty() || self.targets.iter().any(|prefix| filter::target_matches(target, prefix))
+    }
+}
+
+/// A handle to the span latency summaries of a `PogrLayer`, used to report them on demand.
+#[derive(Clone)]
+pub struct SpanLatencyHandle {
+    /// The histograms and where they are reported.
+    state: Arc<LatencyState>,
+}
+
+/// The latency histograms of a layer, and where they are reported.
+pub(crate) struct LatencyState {
+    /// Which spans are aggregated, and where they are reported.
+    config: SpanLatency,
+    /// Appender whose session and client are used for the reports.
+    appender: Arc<tokio::sync::Mutex<PogrAppender>>,
+    /// Histograms of the spans closed since the previous report, by target and span name.
+    histograms: Mutex<HashMap<(&'static str, &'static str), LatencyHistogram>>,
+    /// Set once the periodic reports are started, with the first closed span.
+    reporting: OnceLock<()>,
+    /// Serializes reports, so each span is reported exactly once and in order.
+    sending: tokio::sync::Mutex<()>,
+}
+
+impl LatencyState {
+    /// Creates the histograms of a layer reporting in the appender's session.
+    pub(crate) fn new(config: SpanLatency, appender: Arc<tokio::sync::Mutex<PogrAppender>>) -> Self {
+        LatencyState {
+            config,
+            appender,
+            histograms: Mutex::new(HashMap::new()),
+            reporting: OnceLock::new(),
+            sending: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Records the lifetime of a closed span, if its target is aggregated.
+    ///
+    /// The periodic reports are started with the first recorded span, provided it is closed
+    /// inside a tokio runtime.
+    pub(crate) fn record(self: &Arc<Self>, target: &'static str, name: &'static str, duration: Duration) {
+        if !self.config.matches(target) {
+            return;
+        }
+
+        self.histograms
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .entry((target, name))
+            .or_default()
+            .record(duration);
+
+        if self.reporting.get().is_none() {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                if self.reporting.set(()).is_ok() {
+                    runtime.spawn(SpanLatencyHandle { state: Arc::clone(self) }.report_every(self.config.interval));
+                }
+            }
+        }
+    }
+}
+
+impl SpanLatencyHandle {
+    /// Wraps the histograms of a layer.
+    pub(crate) fn new(state: Arc<LatencyState>) -> Self {
+        SpanLatencyHandle { state }
+    }
+
+    /// Reports the latency summaries of the spans closed since the previous report now.
+    ///
+    /// Failures are reported through the diagnostic handler; the summaries of a failed
+    /// report are lost.
+    pub async fn flush(&self) {
+        let _sending = self.state.sending.lock().await;
+        let histograms = std::mem::take(&mut *self.state.histograms.lock().unwrap_or_else(|p| p.into_inner()));
+        if histograms.is_empty() {
+            return;
+        }
+
+        let appender = self.state.appender.lock().await.clone();
+        let timestamp = clock::format_rfc3339(SystemTime::now());
+        let reports: Vec<MetricReport> = histograms
+            .into_iter()
+            .map(|((target, name), histogram)| {
+                let mut labels = Map::new();
+                labels.insert("span".to_string(), Value::from(name));
+                labels.insert("target".to_string(), Value::from(target));
+                MetricReport {
+                    name: METRIC_NAME.to_string(),
+                    r#type: "summary",
+                    labels,
+                    value: None,
+                    values: None,
+                    count: Some(histogram.count),
+                    quantiles: Some(
+                        QUANTILES
+                            .iter()
+                            .map(|(label, quantile)| (*label, histogram.quantile(*quantile) as f64 / 1000.0))
+                            .collect::<BTreeMap<_, _>>(),
+                    ),
+                    unit: Some("milliseconds"),
+                    description: Some("Lifetime of closed spans".to_string()),
+                    service: appender.service_name.clone(),
+                    environment: appender.environment.clone(),
+                    timestamp: timestamp.clone(),
+                }
+            })
+            .collect();
+
+        if let Err(message) = metrics_intake::send(&appender, &self.state.config.endpoint, &reports).await {
+            diagnostics::error(DiagnosticKind::Delivery, format!("Failed to report span latencies to POGR: {}", message));
+        }
+    }
+
+    /// Reports the summaries every `interval`, for as long as the runtime runs.
+    async fn report_every(self, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        // The first tick completes immediately, right after the first span was recorded.
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            self.flush().await;
+        }
+    }
+}
+
+/// A histogram of span lifetimes, in microseconds, with HDR-style log-linear buckets.
+#[derive(Default)]
+struct LatencyHistogram {
+    /// Number of values in each bucket, up to the highest bucket used.
+    buckets: Vec<u64>,
+    /// Number of recorded values.
+    count: u64,
+    /// Largest recorded value, which bounds the reported quantiles.
+    max: u64,
+}
+
+impl LatencyHistogram {
+    /// Adds a value.
+    fn record(&mut self, duration: Duration) {
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        let index = bucket_index(micros);
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.max = self.max.max(micros);
+    }
+
+    /// Returns the value below which the fraction `quantile` of the values fall, rounded up
+    /// to the top of its bucket.
+    fn quantile(&self, quantile: f64) -> u64 {
+        let rank = ((quantile * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_top(index).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// Returns the bucket of a value.
+fn bucket_index(value: u64) -> usize {
+    if value < LINEAR_BUCKETS {
+        return value as usize;
+    }
+    // Above the linear range, each power of two is split into `SUB_BUCKETS` buckets.
+    let shift = (63 - value.leading_zeros()) - SUB_BUCKETS.trailing_zeros();
+    (u64::from(shift) * SUB_BUCKETS + (value >> shift)) as usize
+}
+
+/// Returns the largest value in a bucket.
+fn bucket_top(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let sub_bucket = index - shift * SUB_BUCKETS;
+    (sub_bucket << shift) + ((1 << shift) - 1)
+}
@@ -0,0 +1,275 @@
+//! A durable buffer for undelivered records, stored in SQLite.
+//!
+//! Client installs (game launchers, player machines) crash, get killed and lose power. A
+//! custom spool format is easy to corrupt under those conditions; SQLite is not. Used as a
+//! fallback sink, `SqliteBuffer` stores every record that could not be delivered in a single
+//! transaction, evicts the oldest records once it grows past its size limit, and hands the
+//! records back to POGR with `SqliteBuffer::replay` once the intake is reachable again.
+//!
+//! Replayed records keep the timestamps they were captured at, and are tagged with
+//! `replayed: true` and `spool_delay_ms`, the time between capture and replay, so dashboards
+//! can tell backfilled data from live telemetry and account for the delay.
+
+use crate::clock;
+use crate::diagnostics::{self, DiagnosticKind};
+use crate::sink::{Sink, SinkRecord};
+use crate::PogrAppender;
+use rusqlite::{params, Connection, OpenFlags};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Tag marking a record that was replayed from the buffer.
+pub const REPLAYED_TAG: &str = "replayed";
+
+/// Tag holding the time, in milliseconds, between the capture and the replay of a record.
+pub const SPOOL_DELAY_TAG: &str = "spool_delay_ms";
+
+/// Default maximum size, in bytes, of the buffered payloads.
+pub const DEFAULT_MAX_BUFFER_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Suffix appended to a database file that failed its integrity check.
+const CORRUPT_SUFFIX: &str = "corrupt";
+
+/// A SQLite-backed buffer for records that could not be delivered.
+///
+/// Available with the `sqlite` feature.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, SinkMode, SqliteBuffer};
+/// use std::sync::Arc;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let appender = PogrAppender::new(None, None).await;
+/// let buffer = Arc::new(SqliteBuffer::open("pogr-buffer.sqlite3")?);
+///
+/// // Resubmit what previous runs could not deliver.
+/// buffer.replay(&appender, 100).await?;
+///
+/// let layer = PogrLayer::new(appender).with_sink(Arc::clone(&buffer), SinkMode::Fallback);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SqliteBuffer {
+    /// Path of the database file.
+    path: PathBuf,
+    /// Maximum combined size of the buffered payloads in bytes.
+    max_size: u64,
+    /// Connection to the database.
+    connection: Mutex<Connection>,
+}
+
+impl SqliteBuffer {
+    /// Opens the buffer at `path`, creating it if needed.
+    ///
+    /// If the existing database is corrupt, it is moved aside to `<path>.corrupt` and a new,
+    /// empty buffer is created in its place, so a damaged file never blocks logging.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+
+        let connection = match open_checked(&path) {
+            Ok(connection) => connection,
+            Err(_) if path.exists() => {
+                let mut corrupt = path.clone().into_os_string();
+                corrupt.push(format!(".{}", CORRUPT_SUFFIX));
+                fs::rename(&path, corrupt)?;
+                open_checked(&path).map_err(to_io_error)?
+            }
+            Err(err) => return Err(to_io_error(err)),
+        };
+
+        Ok(SqliteBuffer {
+            path,
+            max_size: DEFAULT_MAX_BUFFER_SIZE,
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Sets the maximum combined size, in bytes, of the buffered payloads.
+    ///
+    /// When a new record would exceed it, the oldest records are evicted.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Returns the path of the database file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of buffered records.
+    pub fn len(&self) -> io::Result<usize> {
+        let connection = self.connection();
+        connection
+            .query_row("SELECT COUNT(*) FROM records", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .map_err(to_io_error)
+    }
+
+    /// Returns `true` if no records are buffered.
+    pub fn is_empty(&self) -> io::Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Submits the buffered records to POGR, oldest first, in batches of up to `batch_size`.
+    ///
+    /// A batch is only removed from the buffer after the intake has accepted it. Replaying
+    /// stops at the first batch that fails, leaving it and everything after it buffered.
+    ///
+    /// Records are submitted with their original timestamps, tagged with `REPLAYED_TAG` and
+    /// `SPOOL_DELAY_TAG`.
+    ///
+    /// # Returns
+    ///
+    /// The number of records that were delivered.
+    pub async fn replay(&self, appender: &PogrAppender, batch_size: usize) -> io::Result<usize> {
+        let mut delivered = 0;
+
+        loop {
+            let batch = self.peek(batch_size.max(1))?;
+            if batch.is_empty() {
+                return Ok(delivered);
+            }
+
+            let now = SystemTime::now();
+            let payloads: Vec<String> = batch.iter().map(|(_, payload)| mark_replayed(payload, now)).collect();
+            appender
+                .try_send_serialized(&payloads)
+                .await
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+
+            let last_id = batch.last().map(|(id, _)| *id).unwrap_or_default();
+            self.connection()
+                .execute("DELETE FROM records WHERE id <= ?1", params![last_id])
+                .map_err(to_io_error)?;
+            delivered += batch.len();
+        }
+    }
+
+    /// Returns the oldest buffered records without removing them.
+    fn peek(&self, limit: usize) -> io::Result<Vec<(i64, String)>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare("SELECT id, payload FROM records ORDER BY id LIMIT ?1")
+            .map_err(to_io_error)?;
+        let rows = statement
+            .query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(to_io_error)?;
+        rows.collect::<Result<_, _>>().map_err(to_io_error)
+    }
+
+    /// Stores a payload and evicts the oldest records beyond the size limit, atomically.
+    fn enqueue(&self, event_id: &str, payload: &str) -> rusqlite::Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO records (event_id, payload, size) VALUES (?1, ?2, ?3)",
+            params![event_id, payload, payload.len() as i64],
+        )?;
+
+        let total: i64 = transaction.query_row("SELECT COALESCE(SUM(size), 0) FROM records", [], |row| row.get(0))?;
+        let mut excess = total - self.max_size as i64;
+        if excess > 0 {
+            // Find the newest record that has to go so that the rest fits.
+            let mut cutoff = 0;
+            {
+                let mut statement = transaction.prepare("SELECT id, size FROM records ORDER BY id")?;
+                let mut rows = statement.query([])?;
+                while let Some(row) = rows.next()? {
+                    if excess <= 0 {
+                        break;
+                    }
+                    cutoff = row.get(0)?;
+                    excess -= row.get::<_, i64>(1)?;
+                }
+            }
+            let evicted = transaction.execute("DELETE FROM records WHERE id <= ?1", params![cutoff])?;
+            diagnostics::warn(
+                DiagnosticKind::Dropped,
+                format!("Evicted {} buffered records over the size limit of {}", evicted, self.path.display()),
+            );
+        }
+
+        transaction.commit()
+    }
+
+    /// Locks the connection, recovering it if a previous holder panicked.
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Sink for SqliteBuffer {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        self.enqueue(&record.event_id().to_string(), &record.to_json())
+            .map_err(to_io_error)
+    }
+}
+
+/// Tags a buffered payload as replayed at `now`, with the time since it was captured.
+///
+/// Payloads that are not JSON objects are returned as they are.
+fn mark_replayed(payload: &str, now: SystemTime) -> String {
+    let Ok(Value::Object(mut envelope)) = serde_json::from_str::<Value>(payload) else {
+        return payload.to_string();
+    };
+    let delay = envelope
+        .get("timestamp")
+        .and_then(Value::as_str)
+        .and_then(clock::parse_rfc3339)
+        .map(|captured| now.duration_since(captured).unwrap_or_default());
+
+    let tags = envelope.entry("tags").or_insert(Value::Null);
+    if tags.is_null() {
+        *tags = Value::Object(Map::new());
+    }
+    if let Some(tags) = tags.as_object_mut() {
+        tags.insert(REPLAYED_TAG.to_string(), Value::Bool(true));
+        if let Some(delay) = delay {
+            tags.insert(SPOOL_DELAY_TAG.to_string(), Value::from(delay.as_millis() as u64));
+        }
+    }
+    serde_json::to_string(&envelope).unwrap_or_else(|_| payload.to_string())
+}
+
+/// Opens the database, verifies its integrity and makes sure the schema exists.
+fn open_checked(path: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+    )?;
+
+    let status: String = connection.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if status != "ok" {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CORRUPT),
+            Some(status),
+        ));
+    }
+
+    connection.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         PRAGMA synchronous = NORMAL;
+         CREATE TABLE IF NOT EXISTS records (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             event_id TEXT NOT NULL,
+             payload TEXT NOT NULL,
+             size INTEGER NOT NULL
+         );",
+    )?;
+
+    Ok(connection)
+}
+
+/// Converts a SQLite error into an I/O error for the public API.
+fn to_io_error(err: rusqlite::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
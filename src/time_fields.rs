@@ -0,0 +1,213 @@
+//! Recording timestamp and duration fields as values POGR can sort and aggregate.
+//!
+//! `tracing` hands the layer values recorded with `?value` or `%value` only as their `Debug`
+//! or `Display` output, so a `SystemTime` arrives as `SystemTime { tv_sec: 1709296205,
+//! tv_nsec: 42000000 }` and a `Duration` as `1.5s`: opaque text that POGR can neither sort
+//! nor aggregate. Wrapping such a value in a `TimeField` records timestamps as RFC 3339 UTC
+//! strings with millisecond precision, and durations as numbers of milliseconds:
+//!
+//! ```text
+//! %time_field(SystemTime { tv_sec: 1709296205, tv_nsec: 42000000 })  →  "2024-03-01T12:30:05.042Z"
+//! %time_field(Duration::from_millis(1500))                           →  1500
+//! %time_field(Duration::from_nanos(250_500))                         →  0.2505
+//! ```
+//!
+//! Only values converted from a known type are normalized; other fields are never parsed. When
+//! the layer records a `TimeField`, its formatting hands the converted value to the visitor
+//! through a thread-local slot, the same way `attach` hands over attachments, so other layers
+//! simply see the normalized text. `std::time::SystemTime` and `Duration` are always
+//! supported; the `chrono` feature adds `chrono`'s `DateTime`, `NaiveDateTime` and
+//! `TimeDelta`, the `time` feature `time`'s `OffsetDateTime`, `UtcDateTime`,
+//! `PrimitiveDateTime` and `Duration`.
+
+use crate::clock;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Nanoseconds per second.
+#[cfg(feature = "chrono")]
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// Nanoseconds per millisecond.
+const NANOS_PER_MILLI: i128 = 1_000_000;
+
+thread_local! {
+    /// The last `TimeField` formatted on this thread while the layer records a field, if the
+    /// layer is recording one.
+    static CAPTURED: RefCell<Option<Option<TimeField>>> = const { RefCell::new(None) };
+}
+
+/// A timestamp or duration recorded as an RFC 3339 string or a number of milliseconds.
+///
+/// Record it with `%` or `?`. Timestamps before 1970 or after 2554, and durations longer
+/// than about 292 years, cannot be recorded; their field keeps the value's `Debug` output,
+/// and the error is noted in the event's `serialization_errors` field.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::time_field;
+/// use std::time::{Instant, SystemTime};
+///
+/// let started = Instant::now();
+/// // Recorded as `"saved_at": "2024-03-01T12:30:05.042Z", "load_time": 1500`.
+/// tracing::info!(
+///     saved_at = %time_field(SystemTime::now()),
+///     load_time = %time_field(started.elapsed()),
+///     "level loaded"
+/// );
+/// ```
+#[derive(Clone, PartialEq)]
+pub struct TimeField {
+    /// The text other layers see.
+    text: String,
+    /// The recorded value, or why the value cannot be recorded.
+    value: Result<Value, String>,
+}
+
+/// Wraps a timestamp or duration to be recorded as an RFC 3339 string or milliseconds.
+pub fn time_field(value: impl Into<TimeField>) -> TimeField {
+    value.into()
+}
+
+impl TimeField {
+    /// Creates a timestamp field from nanoseconds since the Unix epoch.
+    fn timestamp(nanos: Option<i128>, source: &dyn fmt::Debug) -> Self {
+        match nanos.and_then(|nanos| u64::try_from(nanos).ok()) {
+            Some(nanos) => {
+                let text = clock::format_rfc3339(UNIX_EPOCH + Duration::from_nanos(nanos));
+                TimeField { value: Ok(json!(text)), text }
+            }
+            None => TimeField {
+                text: format!("{:?}", source),
+                value: Err("timestamp outside of 1970 to 2554".to_string()),
+            },
+        }
+    }
+
+    /// Creates a duration field from nanoseconds.
+    fn duration(nanos: Option<i128>, source: &dyn fmt::Debug) -> Self {
+        match nanos {
+            Some(nanos) => {
+                let millis = millis(nanos);
+                TimeField { text: format!("{}ms", millis), value: Ok(millis) }
+            }
+            None => TimeField {
+                text: format!("{:?}", source),
+                value: Err("duration too long to record in nanoseconds".to_string()),
+            },
+        }
+    }
+
+    /// Returns the value to record for a field whose output was `formatted`, or why it cannot
+    /// be recorded, if the output is exactly this field's text.
+    ///
+    /// A `TimeField` nested in another value, e.g. in `Some(field)`, is not picked up.
+    pub(crate) fn value_for(self, formatted: &str) -> Option<Result<Value, String>> {
+        (self.text == formatted).then_some(self.value)
+    }
+}
+
+impl fmt::Display for TimeField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        CAPTURED.with(|captured| {
+            if let Some(slot) = captured.borrow_mut().as_mut() {
+                *slot = Some(self.clone());
+            }
+        });
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Debug for TimeField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<SystemTime> for TimeField {
+    fn from(time: SystemTime) -> Self {
+        let nanos = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => i128::try_from(since.as_nanos()).ok(),
+            Err(_) => None,
+        };
+        TimeField::timestamp(nanos, &time)
+    }
+}
+
+impl From<Duration> for TimeField {
+    fn from(duration: Duration) -> Self {
+        TimeField::duration(i128::try_from(duration.as_nanos()).ok(), &duration)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for TimeField {
+    fn from(time: chrono::DateTime<Tz>) -> Self {
+        let nanos = i128::from(time.timestamp()) * NANOS_PER_SECOND + i128::from(time.timestamp_subsec_nanos());
+        TimeField::timestamp(Some(nanos), &time.naive_utc())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::NaiveDateTime> for TimeField {
+    /// Treats the date and time as UTC.
+    fn from(time: chrono::NaiveDateTime) -> Self {
+        TimeField::from(time.and_utc())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::TimeDelta> for TimeField {
+    fn from(delta: chrono::TimeDelta) -> Self {
+        TimeField::duration(delta.num_nanoseconds().map(i128::from), &delta)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for TimeField {
+    fn from(time: time::OffsetDateTime) -> Self {
+        TimeField::timestamp(Some(time.unix_timestamp_nanos()), &time)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::UtcDateTime> for TimeField {
+    fn from(time: time::UtcDateTime) -> Self {
+        TimeField::timestamp(Some(time.unix_timestamp_nanos()), &time)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::PrimitiveDateTime> for TimeField {
+    /// Treats the date and time as UTC.
+    fn from(time: time::PrimitiveDateTime) -> Self {
+        TimeField::from(time.assume_utc())
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::Duration> for TimeField {
+    fn from(duration: time::Duration) -> Self {
+        TimeField::duration(Some(duration.whole_nanoseconds()), &duration)
+    }
+}
+
+/// Runs `format`, and returns its result with the last `TimeField` it formatted, if any.
+pub(crate) fn capture<R>(format: impl FnOnce() -> R) -> (R, Option<TimeField>) {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(None));
+    let result = format();
+    let field = CAPTURED.with(|captured| captured.borrow_mut().take()).flatten();
+    (result, field)
+}
+
+/// Returns a duration as a number of milliseconds, whole if it is a whole number.
+fn millis(nanos: i128) -> Value {
+    if nanos % NANOS_PER_MILLI == 0 {
+        json!((nanos / NANOS_PER_MILLI) as i64)
+    } else {
+        json!(nanos as f64 / NANOS_PER_MILLI as f64)
+    }
+}
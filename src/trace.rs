@@ -0,0 +1,255 @@
+//! Exporting sampled span trees as trace documents.
+//!
+//! Span close records show spans one at a time, which makes it hard to see where a request
+//! spent its time. With trace export, the layer collects every span of a sampled request,
+//! from the root span down, and sends them together as a single trace document once the last
+//! of them is closed, so POGR can render the request as a waterfall:
+//!
+//! ```text
+//! {"trace_id": "...", "root_span_id": 1, "name": "handle_login", "duration_ms": 41.2, "spans": [
+//!   {"span_id": 1, "parent_id": null, "name": "handle_login", "start": "...", "duration_ms": 41.2, "fields": {...}},
+//!   {"span_id": 2, "parent_id": 1, "name": "load_profile", "start": "...", "duration_ms": 30.8, "fields": {...}}
+//! ]}
+//! ```
+//!
+//! Sampling is decided once per trace, when its root span is created, so a trace is either
+//! exported whole or not at all. The document is sent through the normal log pipeline, and
+//! events recorded inside a sampled trace are tagged with its `trace_id`.
+
+use crate::ring::{self, RECORD_OVERHEAD};
+use crate::{filter, sampling, IdGenerator, IdKind};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Default maximum number of spans kept per trace.
+pub const DEFAULT_MAX_TRACE_SPANS: usize = 1000;
+
+/// Configuration for trace export.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::{PogrAppender, PogrLayer, TraceExport};
+///
+/// # async fn run() {
+/// let appender = PogrAppender::new(None, None).await;
+/// // Export one request in a hundred.
+/// let layer = PogrLayer::new(appender).with_trace_export(TraceExport::new().with_sample_rate(0.01));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceExport {
+    /// Fraction of root spans whose trace is exported, between 0 and 1.
+    sample_rate: f64,
+    /// Target prefixes of the root spans that start traces; all targets if empty.
+    targets: Vec<String>,
+    /// Maximum number of spans kept per trace.
+    max_spans: usize,
+}
+
+impl Default for TraceExport {
+    fn default() -> Self {
+        TraceExport::new()
+    }
+}
+
+impl TraceExport {
+    /// Exports the trace of every root span.
+    pub fn new() -> Self {
+        TraceExport {
+            sample_rate: 1.0,
+            targets: Vec::new(),
+            max_spans: DEFAULT_MAX_TRACE_SPANS,
+        }
+    }
+
+    /// Sets the fraction of traces that are exported, between 0 (none) and 1 (all).
+    ///
+    /// The decision is made when the root span is created, and applies to all its descendants.
+    pub fn with_sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Only starts traces at root spans whose target matches one of `targets`.
+    ///
+    /// A target matches itself and every module below it. Descendants of a sampled root are
+    /// included whatever their target.
+    pub fn with_targets<I, T>(mut self, targets: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.targets = targets.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the maximum number of spans kept per trace. Defaults to `DEFAULT_MAX_TRACE_SPANS`.
+    ///
+    /// Spans closed once a trace is full are left out, and counted in its `dropped_spans`.
+    /// The root span, which closes last, is always kept.
+    pub fn with_max_spans(mut self, max_spans: usize) -> Self {
+        self.max_spans = max_spans;
+        self
+    }
+
+    /// Decides whether a new root span with the given target starts an exported trace.
+    pub(crate) fn start(&self, target: &str, now: SystemTime, ids: &dyn IdGenerator) -> Option<TraceMembership> {
+        if !self.targets.is_empty() && !self.targets.iter().any(|prefix| filter::target_matches(target, prefix)) {
+            return None;
+        }
+
+        let sampled = self.sample_rate >= 1.0 || sampling::random_fraction() < self.sample_rate;
+        sampled.then(|| TraceMembership {
+            span_id: 1,
+            parent_id: None,
+            started_at: now,
+            trace: Arc::new(TraceBuffer {
+                trace_id: ids.generate(IdKind::Trace),
+                max_spans: self.max_spans,
+                open: AtomicUsize::new(1),
+                last_span_id: AtomicU64::new(1),
+                spans: Mutex::new(Vec::new()),
+                dropped_spans: AtomicUsize::new(0),
+            }),
+        })
+    }
+}
+
+/// Marks a span as part of an exported trace, stored in the span's extensions by `PogrLayer`.
+pub(crate) struct TraceMembership {
+    /// The trace the span belongs to.
+    pub(crate) trace: Arc<TraceBuffer>,
+    /// ID of the span within the trace.
+    ///
+    /// The registry reuses the IDs of closed spans, so spans are numbered per trace instead,
+    /// starting from 1 for the root span.
+    pub(crate) span_id: u64,
+    /// ID of the parent span, or `None` for the root span.
+    pub(crate) parent_id: Option<u64>,
+    /// When the span was created.
+    pub(crate) started_at: SystemTime,
+}
+
+impl TraceMembership {
+    /// Adds a new child of this span to the trace.
+    pub(crate) fn join(&self, now: SystemTime) -> TraceMembership {
+        self.trace.open.fetch_add(1, Ordering::Relaxed);
+        TraceMembership {
+            trace: Arc::clone(&self.trace),
+            span_id: self.trace.last_span_id.fetch_add(1, Ordering::Relaxed) + 1,
+            parent_id: Some(self.span_id),
+            started_at: now,
+        }
+    }
+}
+
+/// The spans of a trace collected so far.
+pub(crate) struct TraceBuffer {
+    /// ID of the trace.
+    pub(crate) trace_id: Uuid,
+    /// Maximum number of spans kept.
+    max_spans: usize,
+    /// Number of spans of the trace that are not closed yet.
+    open: AtomicUsize,
+    /// ID of the last span added to the trace.
+    last_span_id: AtomicU64,
+    /// Closed spans of the trace.
+    spans: Mutex<Vec<TracedSpan>>,
+    /// Number of spans left out because the trace was full.
+    dropped_spans: AtomicUsize,
+}
+
+impl TraceBuffer {
+    /// Adds a closed span to the trace.
+    ///
+    /// # Returns
+    ///
+    /// The trace document if this was the last open span of the trace.
+    pub(crate) fn close(&self, span: TracedSpan) -> Option<TraceDocument> {
+        {
+            let mut spans = self.spans.lock().unwrap_or_else(|p| p.into_inner());
+            if spans.len() < self.max_spans || span.parent_id.is_none() {
+                spans.push(span);
+            } else {
+                self.dropped_spans.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        if self.open.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return None;
+        }
+
+        let mut spans = std::mem::take(&mut *self.spans.lock().unwrap_or_else(|p| p.into_inner()));
+        // Spans close from the leaves up; list them in the order they started.
+        spans.sort_by_key(|span| span.span_id);
+        let root = spans.iter().find(|span| span.parent_id.is_none());
+        Some(TraceDocument {
+            trace_id: self.trace_id,
+            root_span_id: root.map(|root| root.span_id),
+            name: root.map(|root| root.name),
+            duration_ms: root.map(|root| root.duration_ms),
+            dropped_spans: self.dropped_spans.load(Ordering::Relaxed),
+            spans,
+        })
+    }
+}
+
+/// A closed span of a trace.
+#[derive(Serialize)]
+pub(crate) struct TracedSpan {
+    /// ID of the span within the trace.
+    pub(crate) span_id: u64,
+    /// ID of the parent span, or `None` for the root span.
+    pub(crate) parent_id: Option<u64>,
+    /// Name of the span.
+    pub(crate) name: &'static str,
+    /// Target of the span.
+    pub(crate) target: &'static str,
+    /// Level of the span.
+    pub(crate) level: String,
+    /// When the span was created, as an RFC 3339 UTC timestamp.
+    pub(crate) start: String,
+    /// How long the span lived, in milliseconds.
+    pub(crate) duration_ms: f64,
+    /// Final fields of the span.
+    pub(crate) fields: HashMap<String, Value>,
+}
+
+/// The spans of a finished trace, sent as the `data` of a log record.
+#[derive(Serialize)]
+pub(crate) struct TraceDocument {
+    /// ID of the trace.
+    pub(crate) trace_id: Uuid,
+    /// ID of the root span, if it was kept.
+    pub(crate) root_span_id: Option<u64>,
+    /// Name of the root span.
+    pub(crate) name: Option<&'static str>,
+    /// Lifetime of the root span, in milliseconds.
+    pub(crate) duration_ms: Option<f64>,
+    /// Number of spans left out because the trace was full.
+    pub(crate) dropped_spans: usize,
+    /// The spans, in the order they started.
+    pub(crate) spans: Vec<TracedSpan>,
+}
+
+impl TraceDocument {
+    /// Estimates the size of the document in bytes, as serialized.
+    pub(crate) fn estimated_size(&self) -> usize {
+        self.spans
+            .iter()
+            .map(|span| {
+                RECORD_OVERHEAD
+                    + span
+                        .fields
+                        .iter()
+                        .map(|(name, value)| name.len() + 4 + ring::estimated_size(value))
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+}
@@ -0,0 +1,188 @@
+//! Typed events with compile-time checked schemas.
+//!
+//! Events logged with `tracing` macros are free-form: a misspelled field or a changed type only
+//! shows up in the dashboards. Deriving `PogrEvent` on a struct makes the struct the schema of
+//! an event instead. Its fields are serialized with `serde`, and the event is emitted through
+//! `tracing` at the level given in the derive's attributes, so it passes through the same
+//! filters, sampling and pipelines as any other event:
+//!
+//! ```rust,no_run
+//! use pogr_tracing_rs::PogrEvent;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize, PogrEvent)]
+//! #[pogr(severity = "warn", type = "gameplay")]
+//! struct PlayerDied {
+//!     player_id: u64,
+//!     cause: String,
+//!     #[pogr(redact)]
+//!     ip_address: String,
+//! }
+//!
+//! PlayerDied { player_id: 42, cause: "lava".to_string(), ip_address: "10.0.0.7".to_string() }.emit();
+//! ```
+//!
+//! The record's message is the event's name, `player_died`, and its tags are the serialized
+//! fields, with redacted fields replaced by `"[redacted]"`. A `type` attribute replaces the
+//! service type of the record.
+//!
+//! The struct-level attributes are `severity` (`trace`, `debug`, `info`, the default, `warn` or
+//! `error`), `type` and `name`, which replaces the name derived from the struct's. The
+//! field-level attribute is `redact`. Redacted fields are matched by their serialized name, so
+//! they may be renamed with `#[serde(rename = "...")]`, but not with `#[serde(rename_all)]`.
+//!
+//! Values without an event type of their own, such as a struct from another crate, can be
+//! logged with `log_struct` instead. The value is serialized into the record's `data` as it is,
+//! rather than through its `Debug` formatting, so its structure and types are preserved.
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::panic::Location;
+use tracing::Level;
+
+/// Value of the redacted fields of a typed event.
+pub const REDACTED: &str = "[redacted]";
+
+/// Field of the `tracing` event carrying the serialized fields of a typed event.
+pub(crate) const EVENT_FIELD: &str = "pogr.event";
+
+/// Field of the `tracing` event carrying the type of a typed event, if it has one.
+pub(crate) const TYPE_FIELD: &str = "pogr.type";
+
+/// Target of the events logged with `log_struct`.
+pub const STRUCT_TARGET: &str = "pogr_struct";
+
+/// Field of the `tracing` event carrying the value logged with `log_struct`.
+const STRUCT_FIELD: &str = "pogr.struct";
+
+/// Field of the `tracing` event carrying the file `log_struct` was called from.
+const FILE_FIELD: &str = "pogr.file";
+
+/// Field of the `tracing` event carrying the line `log_struct` was called from.
+const LINE_FIELD: &str = "pogr.line";
+
+/// An event with a fixed schema, usually derived with `#[derive(PogrEvent)]`.
+pub trait PogrEvent: Serialize {
+    /// Name of the event, sent as the record's message.
+    const NAME: &'static str;
+    /// Level the event is emitted at.
+    const LEVEL: Level;
+    /// Type of the record, instead of the service type, if any.
+    const TYPE: Option<&'static str>;
+    /// Serialized names of the fields that are redacted.
+    const REDACTED_FIELDS: &'static [&'static str];
+
+    /// Emits the event through `tracing`.
+    fn emit(&self);
+
+    /// Returns the fields of the event as sent to POGR, with the redacted fields masked.
+    ///
+    /// Events that do not serialize to a map have their value in a `value` field.
+    fn fields(&self) -> Map<String, Value> {
+        let mut fields = match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(Value::Null) => Map::new(),
+            Ok(value) => Map::from_iter([("value".to_string(), value)]),
+            Err(err) => Map::from_iter([("serialization_error".to_string(), json!(err.to_string()))]),
+        };
+        for name in Self::REDACTED_FIELDS {
+            if let Some(value) = fields.get_mut(*name) {
+                *value = json!(REDACTED);
+            }
+        }
+        fields
+    }
+}
+
+/// Serializes the fields of a typed event for its `tracing` event.
+pub fn encode_fields<E: PogrEvent + ?Sized>(event: &E) -> String {
+    Value::Object(event.fields()).to_string()
+}
+
+/// Takes the fields of a typed event out of the fields of its `tracing` event, merging them in,
+/// and returns the event's type, if it has one.
+pub(crate) fn merge_fields(fields: &mut HashMap<String, Value>) -> Option<String> {
+    if let Some(Value::String(encoded)) = fields.remove(EVENT_FIELD) {
+        if let Ok(Value::Object(event)) = serde_json::from_str(&encoded) {
+            fields.extend(event);
+        }
+    }
+    match fields.remove(TYPE_FIELD) {
+        Some(Value::String(r#type)) => Some(r#type),
+        _ => None,
+    }
+}
+
+/// Logs any serializable value as an event named `name` at `level`.
+///
+/// The value is serialized into the record's `data`, under `struct`, with its structure and
+/// types preserved, and the record's message is `name`. The event is emitted through `tracing`
+/// with the target `pogr_struct`, so filters apply to it as usual; the record's `data` names
+/// the file and line `log_struct` was called from.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pogr_tracing_rs::log_struct;
+/// use serde::Serialize;
+/// use tracing::Level;
+///
+/// #[derive(Serialize)]
+/// struct Inventory {
+///     player_id: u64,
+///     items: Vec<(String, u32)>,
+/// }
+///
+/// let inventory = Inventory { player_id: 42, items: vec![("potion".to_string(), 3)] };
+/// log_struct(Level::INFO, "inventory_sync", &inventory);
+/// ```
+#[track_caller]
+pub fn log_struct<T: Serialize + ?Sized>(level: Level, name: &str, value: &T) {
+    let location = Location::caller();
+    let encoded = serde_json::to_string(value)
+        .unwrap_or_else(|err| json!({ "serialization_error": err.to_string() }).to_string());
+
+    // Callsites are static, so each level needs one of its own.
+    macro_rules! emit {
+        ($level:expr) => {
+            tracing::event!(
+                target: STRUCT_TARGET,
+                $level,
+                "pogr.struct" = encoded.as_str(),
+                "pogr.file" = location.file(),
+                "pogr.line" = location.line(),
+                "{}",
+                name
+            )
+        };
+    }
+    match level {
+        Level::TRACE => emit!(Level::TRACE),
+        Level::DEBUG => emit!(Level::DEBUG),
+        Level::INFO => emit!(Level::INFO),
+        Level::WARN => emit!(Level::WARN),
+        Level::ERROR => emit!(Level::ERROR),
+    }
+}
+
+/// Takes the value logged with `log_struct` out of the fields of its `tracing` event, if it is
+/// one, and adds it to the record's `data` with the caller's location.
+pub(crate) fn take_struct(fields: &mut HashMap<String, Value>, data: &mut Value) {
+    let Some(Value::String(encoded)) = fields.remove(STRUCT_FIELD) else {
+        return;
+    };
+    let file = fields.remove(FILE_FIELD);
+    let line = fields.remove(LINE_FIELD);
+    let Value::Object(data) = data else {
+        return;
+    };
+    data.insert(
+        "struct".to_string(),
+        serde_json::from_str(&encoded).unwrap_or(Value::String(encoded)),
+    );
+    if let Some(name) = fields.get("message") {
+        data.insert("name".to_string(), name.clone());
+    }
+    data.extend([("file".to_string(), file.unwrap_or(Value::Null)), ("line".to_string(), line.unwrap_or(Value::Null))]);
+}
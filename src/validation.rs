@@ -0,0 +1,216 @@
+//! Startup validation of the POGR configuration.
+//!
+//! `PogrAppender::new` panics when the session cannot be initialized, but an unreachable logs
+//! endpoint, a session the intake rejects, or a badly skewed clock only show up later, as
+//! submissions that fail in the background while the service keeps running.
+//! `PogrAppender::new_validated` checks all of these before returning, so a deployment can
+//! refuse to start with a report saying what is wrong.
+
+use crate::builder::SessionMetadata;
+use crate::connection::{allow_insecure_from_env, new_client};
+use crate::health::{check_endpoint, CheckOutcome, ClockSample, EndpointHealth};
+use crate::{check_endpoints, endpoint_template, endpoints, PogrAppender};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Largest difference between the local clock and the intake's clock that validation accepts.
+///
+/// Timestamps from a clock that is further off place records far from the surrounding ones
+/// in POGR.
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// The result of startup validation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ValidationReport {
+    /// Connectivity of the init endpoint.
+    pub init: EndpointHealth,
+    /// Connectivity of the logs endpoint.
+    pub logs: EndpointHealth,
+    /// Whether the init endpoint accepted `POGR_ACCESS` and `POGR_SECRET` and opened a session.
+    pub credentials: CheckOutcome,
+    /// Whether the logs endpoint accepts the new session.
+    pub session: CheckOutcome,
+    /// Whether the local clock is within `MAX_CLOCK_SKEW` of the intake's clock.
+    pub clock_skew: CheckOutcome,
+    /// How far the local clock is off from the intake's clock, if the intake reported its time.
+    pub clock_offset: Option<Duration>,
+}
+
+impl ValidationReport {
+    /// Returns `true` if every check passed.
+    ///
+    /// A clock check that was skipped because the intake did not report its time does not
+    /// count as a failure.
+    pub fn is_valid(&self) -> bool {
+        self.init.is_healthy()
+            && self.logs.is_healthy()
+            && self.credentials.is_passed()
+            && self.session.is_passed()
+            && !matches!(self.clock_skew, CheckOutcome::Failed(_))
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, endpoint) in [("init", &self.init), ("logs", &self.logs)] {
+            write!(f, "{} endpoint {}: dns {}, connection {}", name, endpoint.url, endpoint.dns, endpoint.connection)?;
+            if let Some(latency) = endpoint.latency {
+                write!(f, " ({} ms)", latency.as_millis())?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "credentials: {}", self.credentials)?;
+        writeln!(f, "session: {}", self.session)?;
+        write!(f, "clock skew: {}", self.clock_skew)
+    }
+}
+
+/// Error returned by `PogrAppender::new_validated` when a check fails.
+#[derive(Clone, Debug)]
+pub struct ValidationError {
+    /// The report with the failed checks.
+    report: Box<ValidationReport>,
+}
+
+impl ValidationError {
+    /// Returns the report with the outcome of every check.
+    pub fn report(&self) -> &ValidationReport {
+        &self.report
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "POGR startup validation failed:\n{}", self.report)
+    }
+}
+
+impl Error for ValidationError {}
+
+impl PogrAppender {
+    /// Creates a new `PogrAppender` after verifying that logs can actually be delivered.
+    ///
+    /// Where `new` only initializes a session, this also checks that both endpoints resolve
+    /// and answer, that the logs endpoint accepts the session, and that the local clock is
+    /// within `MAX_CLOCK_SKEW` of the intake's, as reported in its `Date` header. Each network
+    /// step times out after five seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `init_endpoint` - An optional string that holds the initialization endpoint.
+    /// * `logs_endpoint` - An optional string that holds the logs endpoint.
+    ///
+    /// Both fall back to the environment and the defaults exactly as in `new`. An endpoint
+    /// whose scheme `new` would reject fails the credentials check.
+    ///
+    /// # Returns
+    ///
+    /// The appender and the report if every check passed, or a `ValidationError` carrying
+    /// the report otherwise. Unlike `new`, this never panics.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pogr_tracing_rs::PogrAppender;
+    ///
+    /// # async fn run() {
+    /// let appender = match PogrAppender::new_validated(None, None).await {
+    ///     Ok((appender, _report)) => appender,
+    ///     Err(err) => {
+    ///         eprintln!("{}", err);
+    ///         std::process::exit(1);
+    ///     }
+    /// };
+    /// # }
+    /// ```
+    pub async fn new_validated(
+        init_endpoint: Option<String>,
+        logs_endpoint: Option<String>,
+    ) -> Result<(Self, ValidationReport), ValidationError> {
+        Self::validate(init_endpoint, logs_endpoint, &SessionMetadata::default(), allow_insecure_from_env()).await
+    }
+
+    /// Runs the checks of `new_validated`, initializing the session with the given metadata.
+    pub(crate) async fn validate(
+        init_endpoint: Option<String>,
+        logs_endpoint: Option<String>,
+        session: &SessionMetadata,
+        allow_insecure: bool,
+    ) -> Result<(Self, ValidationReport), ValidationError> {
+        let client = new_client();
+        let (init_endpoint_url, logs_endpoint_url) = endpoints(init_endpoint, logs_endpoint);
+        let endpoints_allowed = check_endpoints(&init_endpoint_url, &logs_endpoint_url, allow_insecure);
+
+        // The session does not exist yet, so templates are checked with sample values; the
+        // connectivity checks only depend on the hosts.
+        let init_sample = endpoint_template::check("init", &init_endpoint_url).unwrap_or_else(|_| init_endpoint_url.clone());
+        let logs_sample = endpoint_template::check("logs", &logs_endpoint_url).unwrap_or_else(|_| logs_endpoint_url.clone());
+        let ((init, init_clock), (logs, logs_clock)) = tokio::join!(
+            check_endpoint(&client, &init_sample),
+            check_endpoint(&client, &logs_sample)
+        );
+
+        let mut appender = None;
+        let credentials = if let Err(message) = endpoints_allowed {
+            CheckOutcome::Failed(message)
+        } else if init.is_healthy() {
+            match PogrAppender::initialize(client, init_endpoint_url, logs_endpoint_url, session).await {
+                Ok(initialized) => {
+                    appender = Some(initialized);
+                    CheckOutcome::Passed
+                }
+                Err(message) => CheckOutcome::Failed(message),
+            }
+        } else {
+            CheckOutcome::Skipped
+        };
+
+        let session = match &appender {
+            Some(appender) if logs.is_healthy() => appender.check_session().await,
+            _ => CheckOutcome::Skipped,
+        };
+
+        let (clock_skew, clock_offset) = check_clock(init_clock.or(logs_clock));
+
+        let report = ValidationReport {
+            init,
+            logs,
+            credentials,
+            session,
+            clock_skew,
+            clock_offset,
+        };
+        match appender {
+            Some(appender) if report.is_valid() => Ok((appender, report)),
+            _ => Err(ValidationError {
+                report: Box::new(report),
+            }),
+        }
+    }
+}
+
+/// Compares the local clock against the intake's.
+fn check_clock(sample: Option<ClockSample>) -> (CheckOutcome, Option<Duration>) {
+    let Some(sample) = sample else {
+        return (CheckOutcome::Skipped, None);
+    };
+
+    let (offset, direction) = match sample.local.duration_since(sample.server) {
+        Ok(ahead) => (ahead, "ahead of"),
+        Err(behind) => (behind.duration(), "behind"),
+    };
+    let outcome = if offset > MAX_CLOCK_SKEW {
+        CheckOutcome::Failed(format!(
+            "the local clock is {} s {} the intake's, more than the {} s allowed",
+            offset.as_secs(),
+            direction,
+            MAX_CLOCK_SKEW.as_secs()
+        ))
+    } else {
+        CheckOutcome::Passed
+    };
+
+    (outcome, Some(offset))
+}
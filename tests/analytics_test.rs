@@ -0,0 +1,143 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{Aggregation, AnalyticsQuery, Interval, PogrQueryClient, QueryError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 2024-03-01T12:00:00Z
+const START: u64 = 1_709_294_400;
+
+// Start a mock analytics API and return a client for it.
+fn mock_service() -> (mockito::ServerGuard, PogrQueryClient) {
+    let mock_server = mockito::Server::new();
+    let analytics_endpoint = format!("{}/v1/analytics", mock_server.url().trim_end_matches('/'));
+    let client = PogrQueryClient::new("read_access_key", "read_secret_key").with_analytics_endpoint(analytics_endpoint);
+    (mock_server, client)
+}
+
+// Verify that severity counts are requested with the query's filters and time range.
+#[tokio::test]
+async fn test_severity_counts() {
+    let (mut mock_server, client) = mock_service();
+
+    let m_counts = mock_server.mock("GET", "/v1/analytics/severity_counts")
+        .match_header("POGR_ACCESS", "read_access_key")
+        .match_header("POGR_SECRET", "read_secret_key")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("service".into(), "matchmaking".into()),
+            Matcher::UrlEncoded("environment".into(), "production".into()),
+            Matcher::UrlEncoded("from".into(), "2024-03-01T12:00:00.000Z".into()),
+            Matcher::UrlEncoded("to".into(), "2024-03-02T12:00:00.000Z".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "counts": [
+                { "severity": "ERROR", "count": 3 },
+                { "severity": "INFO", "count": 1200 }
+            ] }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let from = UNIX_EPOCH + Duration::from_secs(START);
+    let query = AnalyticsQuery::for_service("matchmaking")
+        .with_environment("production")
+        .with_range(from, from + Duration::from_secs(24 * 3600));
+    let counts = client.severity_counts(&query).await.unwrap();
+    m_counts.assert();
+
+    assert_eq!(counts.len(), 2);
+    assert_eq!((counts[0].severity.as_str(), counts[0].count), ("ERROR", 3));
+    assert_eq!((counts[1].severity.as_str(), counts[1].count), ("INFO", 1200));
+}
+
+// Verify that event counts over time come back with parsed bucket start times.
+#[tokio::test]
+async fn test_events_over_time() {
+    let (mut mock_server, client) = mock_service();
+
+    mock_server.mock("GET", "/v1/analytics/events_over_time")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("session_id".into(), "test_session_id".into()),
+            Matcher::UrlEncoded("interval".into(), "1h".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "buckets": [
+                { "start": "2024-03-01T12:00:00Z", "count": 10 },
+                { "start": "2024-03-01T15:00:00+02:00", "count": 4 }
+            ] }
+        }).to_string())
+        .create();
+
+    let buckets = client
+        .events_over_time(&AnalyticsQuery::for_session("test_session_id"), Interval::Hour)
+        .await
+        .unwrap();
+
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0].start, UNIX_EPOCH + Duration::from_secs(START));
+    assert_eq!(buckets[0].count, 10);
+    // Offsets are applied: 15:00+02:00 is 13:00 UTC.
+    assert_eq!(buckets[1].start, UNIX_EPOCH + Duration::from_secs(START + 3600));
+}
+
+// Verify that metric rollups pass the metric, aggregation and interval, and keep empty buckets.
+#[tokio::test]
+async fn test_metric_rollup() {
+    let (mut mock_server, client) = mock_service();
+
+    mock_server.mock("GET", "/v1/analytics/metrics")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("service".into(), "matchmaking".into()),
+            Matcher::UrlEncoded("metric".into(), "queue_time_ms".into()),
+            Matcher::UrlEncoded("aggregation".into(), "p95".into()),
+            Matcher::UrlEncoded("interval".into(), "1d".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "points": [
+                { "start": "2024-03-01T00:00:00.000Z", "value": 812.5 },
+                { "start": "2024-03-02T00:00:00.000Z", "value": null }
+            ] }
+        }).to_string())
+        .create();
+
+    let points = client
+        .metric_rollup(&AnalyticsQuery::for_service("matchmaking"), "queue_time_ms", Aggregation::P95, Interval::Day)
+        .await
+        .unwrap();
+
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].value, Some(812.5));
+    assert_eq!(points[1].value, None);
+    assert!(points[0].start < SystemTime::now());
+}
+
+// Verify that an unparseable timestamp is reported as an invalid response.
+#[tokio::test]
+async fn test_invalid_timestamp() {
+    let (mut mock_server, client) = mock_service();
+
+    mock_server.mock("GET", "/v1/analytics/events_over_time")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "buckets": [{ "start": "yesterday", "count": 1 }] }
+        }).to_string())
+        .create();
+
+    let err = client
+        .events_over_time(&AnalyticsQuery::for_service("matchmaking"), Interval::Minute)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, QueryError::InvalidResponse(_)), "{:?}", err);
+    assert!(err.to_string().contains("yesterday"), "{}", err);
+}
@@ -0,0 +1,146 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{set_diagnostic_handler, IntakeApiVersion, LogRequest, PogrAppender};
+use std::sync::{Arc, Mutex};
+
+// Start a mock POGR service whose init endpoint selects `api_version`, or predates the
+// negotiation if `None`, and return it with its base URL.
+fn mock_service(api_version: Option<u32>) -> (mockito::ServerGuard, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    let mut payload = serde_json::json!({ "session_id": "test_session_id" });
+    if let Some(api_version) = api_version {
+        payload["api_version"] = api_version.into();
+    }
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({ "success": true, "payload": payload }).to_string())
+        .create();
+    (mock_server, base_url)
+}
+
+// Build a log request to submit.
+fn log_request() -> LogRequest {
+    LogRequest {
+        service: "matchmaker".to_string(),
+        environment: "production".to_string(),
+        severity: "INFO".to_string(),
+        r#type: "server".to_string(),
+        log: "match started".to_string(),
+        data: serde_json::json!({}),
+        tags: serde_json::json!({}),
+    }
+}
+
+// Verify that the client offers every version, and switches to the version the intake selects,
+// with its request and response models.
+#[tokio::test]
+async fn test_negotiated_v2() {
+    let (mut mock_server, base_url) = mock_service(Some(2));
+    let m_logs = mock_server.mock("POST", "/v2/intake/logs")
+        .match_body(Matcher::Regex(r#"^\{"records":\[\{"schema_version":1,.*"log":"match started""#.to_string()))
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_ids": ["log-1"]}}"#)
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    assert_eq!(appender.api_version(), IntakeApiVersion::V2);
+    assert_eq!(appender.logs_endpoint, format!("{}/v2/intake/logs", base_url));
+
+    appender.log(log_request()).await;
+    m_logs.assert();
+}
+
+// Verify that an intake that predates the negotiation keeps the client on version 1.
+#[tokio::test]
+async fn test_legacy_intake() {
+    let (mut mock_server, base_url) = mock_service(None);
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::Regex(r#"^\{"schema_version":1,"#.to_string()))
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "log-1"}}"#)
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    assert_eq!(appender.api_version(), IntakeApiVersion::V1);
+
+    appender.log(log_request()).await;
+    m_logs.assert();
+}
+
+// Verify that only the pinned version is offered, and that initialization fails if the intake
+// does not select it.
+#[tokio::test]
+#[should_panic(expected = "The intake selected API version 1, but the client offered 2")]
+async fn test_pinned_version_unsupported() {
+    let (_mock_server, base_url) = mock_service(None);
+    PogrAppender::builder()
+        .with_init_endpoint(format!("{}/v1/intake/init", base_url))
+        .with_logs_endpoint(format!("{}/v1/intake/logs", base_url))
+        .with_api_version(IntakeApiVersion::V2)
+        .build()
+        .await;
+}
+
+// Verify that only version 1 is offered for a logs endpoint without a version segment.
+#[tokio::test]
+async fn test_unversioned_logs_endpoint() {
+    let (mut mock_server, base_url) = mock_service(Some(1));
+    let m_init = mock_server.mock("POST", "/gateway/init")
+        .match_header("X-POGR-API-Versions", "1")
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"session_id": "test_session_id", "api_version": 1}}"#)
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/gateway/init", base_url)),
+        Some(format!("{}/gateway/logs", base_url)),
+    )
+    .await;
+    m_init.assert();
+    assert_eq!(appender.api_version(), IntakeApiVersion::V1);
+}
+
+// Verify that the structured errors of version 2 are reported.
+#[tokio::test]
+async fn test_v2_error() {
+    let (mut mock_server, base_url) = mock_service(Some(2));
+    mock_server.mock("POST", "/v2/intake/logs")
+        .with_status(401)
+        .with_body(r#"{"success": false, "error": {"code": "session_expired", "message": "Session expired"}}"#)
+        .create();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&reports);
+    set_diagnostic_handler(move |diagnostic| {
+        if diagnostic.message().contains("session_expired") {
+            collected.lock().unwrap().push(diagnostic.message().to_string());
+        }
+    });
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    appender.log(log_request()).await;
+    assert_eq!(
+        *reports.lock().unwrap(),
+        ["Failed to log to POGR: rejected by the intake: session_expired: Session expired"]
+    );
+}
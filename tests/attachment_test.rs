@@ -0,0 +1,166 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{attach, Attachment, AttachmentConfig, AttachmentEncoding, PogrAppender, PogrLayer};
+use tracing::{error, info};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Verify that an attachment is embedded as base64 in the next event's payload.
+#[tokio::test]
+async fn test_base64_attachment() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Expect the attachment's metadata and base64 contents on the error event only.
+    let m_attached = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "severity": "ERROR",
+            "attachments": [{
+                "name": "save.bin",
+                "content_type": "application/octet-stream",
+                "size": 4,
+                "data": "3q2+7w=="
+            }]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    // The following event carries no attachments at all.
+    let m_plain = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body(Matcher::Regex(r#"^\{(?s:.)*"severity":"INFO""#.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // The attachment belongs to the next event only.
+    attach(Attachment::new("save.bin", "application/octet-stream", vec![0xde, 0xad, 0xbe, 0xef]));
+    error!("state desync detected");
+    info!("resynchronized");
+
+    guard.flush().await;
+    m_attached.assert();
+    m_plain.assert();
+}
+
+// Verify that attachments over the size limit are dropped and reported in the event's tags.
+#[tokio::test]
+async fn test_oversized_attachment_is_dropped() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Expect the event without the large attachment, but with the small one.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "tags": { "pogr.dropped_attachments": ["screenshot.png"] },
+            "attachments": [{ "name": "thumbnail.png", "size": 16 }]
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_attachments(AttachmentConfig::new().with_max_attachment_size(1024));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    attach(Attachment::new("screenshot.png", "image/png", vec![0u8; 4096]));
+    attach(Attachment::new("thumbnail.png", "image/png", vec![0u8; 16]));
+    error!("rendering glitch reported");
+
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that multipart encoding sends the payload and the raw attachment as separate parts.
+#[tokio::test]
+async fn test_multipart_attachment() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Expect a multipart body with the JSON payload referring to the binary part.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_header("content-type", Matcher::Regex("^multipart/form-data".to_string()))
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#"name="log""#.to_string()),
+            Matcher::Regex(r#""part":"attachment-0""#.to_string()),
+            Matcher::Regex(r#"name="attachment-0"; filename="repro.txt""#.to_string()),
+            Matcher::Regex("repro contents".to_string()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_attachments(AttachmentConfig::new().with_encoding(AttachmentEncoding::Multipart));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    attach(Attachment::new("repro.txt", "text/plain", b"repro contents".to_vec()));
+    error!("crash reproduced");
+
+    guard.flush().await;
+    m_logs.assert();
+}
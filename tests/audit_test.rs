@@ -0,0 +1,47 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{set_diagnostic_handler, AuditPolicy, DiagnosticKind};
+use std::sync::{Arc, Mutex};
+use tracing::{info, info_span, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that audit events missing required fields are reported instead of sent, and that
+// complete audit events and other events are sent.
+#[tokio::test]
+async fn test_audit_required_fields() {
+    let diagnostics = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&diagnostics);
+    set_diagnostic_handler(move |diagnostic| {
+        collected.lock().unwrap().push((diagnostic.kind(), diagnostic.message().to_string()));
+    });
+
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let layer = layer.with_audit(AuditPolicy::new().with_targets(["admin"]));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // Complete: the actor comes from the enclosing span.
+    info_span!("session", actor = "gm-7").in_scope(|| {
+        info!(target: "admin", action = "ban", resource = "player-42", "Player banned");
+    });
+    // Incomplete: no actor, and an empty resource.
+    warn!(target: "admin::tools", action = "grant", resource = "", "Item granted");
+    // Classified as audit outside the audit targets.
+    info!(classification = "audit", actor = "gm-7", "Config changed");
+    // Not an audit event.
+    info!("Match started");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    let messages: Vec<_> = records.iter().map(|tags| tags["message"].clone()).collect();
+    assert_eq!(messages, ["Player banned", "Match started"]);
+
+    let diagnostics = diagnostics.lock().unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics.iter().all(|(kind, _)| *kind == DiagnosticKind::Audit));
+    assert!(diagnostics[0].1.contains("admin::tools"));
+    assert!(diagnostics[0].1.ends_with("missing actor, resource"));
+    assert!(diagnostics[1].1.ends_with("missing action, resource"));
+}
@@ -0,0 +1,31 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that the guard reports backpressure once the backlog reaches the threshold, and
+// notifies watchers when it drains.
+#[tokio::test]
+async fn test_backpressure_signal() {
+    let (_mock_server, layer, _sink) = collecting_layer(CollectingSink::tags()).await;
+    let layer = layer.with_backpressure_threshold(10);
+    let guard = layer.guard();
+    let mut signal = guard.backpressure();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // The worker cannot run before the test awaits, so the records pile up.
+    for _ in 0..9 {
+        info!("Tick simulated");
+    }
+    assert!(!guard.is_backpressured());
+    info!("Tick simulated");
+    assert!(guard.is_backpressured());
+    assert!(signal.has_changed().unwrap());
+    assert!(*signal.borrow_and_update());
+
+    guard.flush().await;
+    assert!(!guard.is_backpressured());
+    assert!(!*signal.borrow_and_update());
+}
@@ -0,0 +1,263 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{BatchConfig, PogrAppender, PogrLayer, ShippingSchedule};
+use std::time::Duration;
+use tracing::{error, info};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Verify that events captured within the linger period are submitted together as one array.
+#[tokio::test]
+async fn test_batched_submission() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Expect a single request whose body is a JSON array of log messages.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body(Matcher::Regex(r"^\[.*batched message one.*batched message two.*batched message three.*\]$".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    // Batch up to ten events, lingering long enough that only the flush submits them.
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_batching(
+        BatchConfig::new()
+            .with_max_batch_size(10)
+            .with_linger(Duration::from_secs(60)),
+    );
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("batched message one");
+    info!("batched message two");
+    info!("batched message three");
+
+    // Flushing submits the partially filled batch right away.
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that a batch larger than the request size limit is split across several requests.
+#[tokio::test]
+async fn test_oversized_batch_is_split() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Each event is a little over 2 KB, so only two of them fit into a 5 KB request.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body(Matcher::Regex(r"^\[".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(2)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_batching(
+        BatchConfig::new()
+            .with_max_batch_size(10)
+            .with_linger(Duration::from_secs(60))
+            .with_max_request_size(5 * 1024),
+    );
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let padding = "x".repeat(2000);
+    for index in 0..4 {
+        info!(index, padding = %padding, "large batched message");
+    }
+
+    // The four events must arrive as two arrays of two.
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that a single event larger than the request size limit is truncated and annotated.
+#[tokio::test]
+async fn test_oversized_event_is_truncated() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Expect the event to be submitted, with its tags marked as truncated.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "log": "rust tracing log captured",
+            "tags": {
+                "pogr.truncated": true,
+                "pogr.truncated_fields": ["payload"]
+            }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_batching(BatchConfig::new().with_max_request_size(2 * 1024));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // A single field far larger than the request size limit.
+    let payload = "y".repeat(10_000);
+    info!(payload = %payload, "oversized message");
+
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Mock a successful logs submission whose body matches `pattern`, expected once.
+fn mock_logs(mock_server: &mut mockito::ServerGuard, pattern: &str) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::Regex(pattern.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create()
+}
+
+// Wait until a mock has received the requests it expects.
+async fn wait_for(mock: &mockito::Mock) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !mock.matched() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+// Verify that the interval schedule submits everything captured in an interval together,
+// regardless of the batch size.
+#[tokio::test]
+async fn test_interval_schedule() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let m_logs = mock_logs(&mut mock_server, r"^\[.*scheduled one.*scheduled two.*scheduled three.*\]$");
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_batching(
+        BatchConfig::new()
+            .with_max_batch_size(2)
+            .with_schedule(ShippingSchedule::Interval(Duration::from_secs(1))),
+    );
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("scheduled one");
+    info!("scheduled two");
+    info!("scheduled three");
+
+    // Nothing is submitted before the interval has passed.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!m_logs.matched());
+
+    wait_for(&m_logs).await;
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that an error is submitted right away under the interval schedule, along with the
+// events queued before it.
+#[tokio::test]
+async fn test_interval_schedule_ships_errors_immediately() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let m_logs = mock_logs(&mut mock_server, r"^\[.*waiting for the interval.*server crashed.*\]$");
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_batching(
+        BatchConfig::new().with_schedule(ShippingSchedule::Interval(Duration::from_secs(3600))),
+    );
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("waiting for the interval");
+    error!("server crashed");
+
+    wait_for(&m_logs).await;
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that without an immediate level, errors wait for the interval like other events,
+// and that a flush submits them.
+#[tokio::test]
+async fn test_interval_schedule_without_immediate_level() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let m_logs = mock_logs(&mut mock_server, r"held back error");
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_batching(
+        BatchConfig::new()
+            .with_schedule(ShippingSchedule::Interval(Duration::from_secs(3600)))
+            .with_immediate_level(None),
+    );
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("held back error");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!m_logs.matched());
+
+    tokio::time::timeout(Duration::from_secs(5), guard.flush()).await.unwrap();
+    m_logs.assert();
+}
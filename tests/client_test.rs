@@ -0,0 +1,117 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{BatchConfig, LogRequest, PogrAppender, PogrClient, PogrLayer};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+// Start a mock POGR service whose logs endpoint keeps the bodies it receives.
+fn mock_service(bodies: &Arc<Mutex<Vec<serde_json::Value>>>) -> (mockito::ServerGuard, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+    let captured = Arc::clone(bodies);
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            captured.lock().unwrap().push(serde_json::from_slice(request.body().unwrap()).unwrap());
+            br#"{"success": true, "payload": {"log_id": "test_log_id"}}"#.to_vec()
+        })
+        .create();
+    (mock_server, base_url)
+}
+
+// Create an appender for the mock service.
+async fn appender(base_url: &str) -> PogrAppender {
+    PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await
+}
+
+// Build a log request to submit.
+fn log_request(log: &str) -> LogRequest {
+    LogRequest {
+        service: "matchmaker".to_string(),
+        environment: "production".to_string(),
+        severity: "INFO".to_string(),
+        r#type: "server".to_string(),
+        log: log.to_string(),
+        data: serde_json::json!({}),
+        tags: serde_json::json!({ "source": "import" }),
+    }
+}
+
+// Verify that a standalone client submits a request as is.
+#[tokio::test]
+async fn test_submit() {
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let (_mock_server, base_url) = mock_service(&bodies);
+
+    let client = PogrClient::new(appender(&base_url).await);
+    let event_id = client.submit(log_request("match finished"));
+    client.flush().await;
+
+    let bodies = bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0]["event_id"], event_id.to_string());
+    assert_eq!(bodies[0]["log"], "match finished");
+    assert_eq!(bodies[0]["tags"]["source"], "import");
+}
+
+// Verify that historical records keep their timestamps, and are batched with the layer's
+// batching settings.
+#[tokio::test]
+async fn test_submit_batch_at() {
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let (_mock_server, base_url) = mock_service(&bodies);
+
+    let layer = PogrLayer::new(appender(&base_url).await)
+        .with_batching(BatchConfig::new().with_max_batch_size(10).with_linger(Duration::from_secs(60)));
+    let client = layer.client();
+    let first = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let event_ids = client.submit_batch_at([
+        (first, log_request("match started")),
+        (first + Duration::from_millis(1500), log_request("match finished")),
+    ]);
+    client.flush().await;
+
+    let bodies = bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 1);
+    let records = bodies[0].as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["event_id"], event_ids[0].to_string());
+    assert_eq!(records[0]["timestamp"], "2023-11-14T22:13:20.000Z");
+    assert_eq!(records[1]["event_id"], event_ids[1].to_string());
+    assert_eq!(records[1]["timestamp"], "2023-11-14T22:13:21.500Z");
+}
+
+// Verify that a batch submitted now is delivered in order.
+#[tokio::test]
+async fn test_submit_batch() {
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let (_mock_server, base_url) = mock_service(&bodies);
+
+    let layer = PogrLayer::new(appender(&base_url).await)
+        .with_batching(BatchConfig::new().with_max_batch_size(10).with_linger(Duration::from_secs(60)));
+    let client = layer.client();
+    let event_ids = client.submit_batch(["first", "second", "third"].map(log_request));
+    client.flush().await;
+
+    let bodies = bodies.lock().unwrap();
+    let logs: Vec<&str> = bodies[0].as_array().unwrap().iter().map(|record| record["log"].as_str().unwrap()).collect();
+    assert_eq!(logs, ["first", "second", "third"]);
+    assert_eq!(event_ids.len(), 3);
+}
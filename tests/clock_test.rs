@@ -0,0 +1,83 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{Clock, ManualClock, MonotonicClock, PogrAppender, PogrLayer};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::info;
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Verify that the monotonic clock never goes backwards.
+#[test]
+fn test_monotonic_clock_is_non_decreasing() {
+    let clock = MonotonicClock::new();
+    let mut previous = clock.now();
+
+    for _ in 0..1_000 {
+        let now = clock.now();
+        assert!(now >= previous);
+        previous = now;
+    }
+}
+
+// Verify that records are timestamped with the injected clock at capture time.
+#[tokio::test]
+async fn test_pogr_layer_uses_injected_clock() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    let _m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let log_response_success = serde_json::json!({
+        "success": true,
+        "payload": { "log_id": "test_log_id" }
+    }).to_string();
+
+    // 2023-11-14T22:13:20Z, then the same time plus 1.5 seconds.
+    let m_first = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({ "timestamp": "2023-11-14T22:13:20.000Z" })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(&log_response_success)
+        .expect(1)
+        .create();
+    let m_second = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({ "timestamp": "2023-11-14T22:13:21.500Z" })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(&log_response_success)
+        .expect(1)
+        .create();
+
+    // Share a manual clock between the test and the layer.
+    let clock = Arc::new(ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_clock(Arc::clone(&clock));
+    let guard = layer.guard();
+
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("first event");
+    clock.advance(Duration::from_millis(1_500));
+    info!("second event");
+
+    guard.flush().await;
+    m_first.assert();
+    m_second.assert();
+}
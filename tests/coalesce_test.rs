@@ -0,0 +1,100 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{Coalescing, PogrAppender, PogrLayer, SinkMode};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Create a layer that only delivers to a collecting sink, coalescing repeats within `window`.
+async fn coalescing_layer(window: Duration) -> (mockito::ServerGuard, PogrLayer, Arc<CollectingSink<(String, serde_json::Value)>>) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let sink = Arc::new(CollectingSink::new(|record| {
+        let request = record.request();
+        (request.log.clone(), request.tags.clone())
+    }));
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), None).await;
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror)
+        .with_coalescing(Coalescing::new(window));
+    (mock_server, layer, sink)
+}
+
+// Log the same warning from one callsite.
+fn connection_lost(attempt: u32) {
+    warn!(attempt, "Lost connection to the lobby server");
+}
+
+// Verify that repeats are sent as one event with their count, timestamps and the fields of
+// the first repeat, and that other messages are not affected.
+#[tokio::test]
+async fn test_coalesce_repeats() {
+    let (_mock_server, layer, sink) = coalescing_layer(Duration::from_secs(60)).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for attempt in 1..=6 {
+        connection_lost(attempt);
+    }
+    info!("Match started");
+    // Flushing closes the window without waiting for it.
+    tokio::time::timeout(Duration::from_secs(5), guard.flush()).await.unwrap();
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].0, "rust tracing log captured");
+    assert_eq!(records[0].1["attempt"], 1);
+    assert_eq!(records[1].1["message"], "Match started");
+
+    let (log, tags) = &records[2];
+    assert_eq!(log, "rust tracing log repeated");
+    assert_eq!(tags["message"], "Lost connection to the lobby server");
+    assert_eq!(tags["repeat_count"], 5);
+    assert_eq!(tags["attempt"], 2);
+    assert!(tags["first_seen"].as_str().unwrap() <= tags["last_seen"].as_str().unwrap());
+}
+
+// Verify that a message is sent again once its window has closed.
+#[tokio::test]
+async fn test_coalesce_window_closes() {
+    let (_mock_server, layer, sink) = coalescing_layer(Duration::from_millis(50)).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    connection_lost(1);
+    connection_lost(2);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    {
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].1["repeat_count"], 1);
+    }
+
+    connection_lost(3);
+    guard.flush().await;
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[2].0, "rust tracing log captured");
+    assert_eq!(records[2].1["attempt"], 3);
+}
@@ -0,0 +1,76 @@
+// Fixtures shared by the integration tests. Not every test file uses all of them.
+#![allow(dead_code)]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{PogrAppender, PogrLayer, Sink, SinkMode, SinkRecord};
+use serde_json::Value;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+// A sink that keeps what `collect` picks out of each record it receives.
+pub struct CollectingSink<T = Value>(pub Mutex<Vec<T>>, fn(&SinkRecord<'_>) -> T);
+
+impl<T> CollectingSink<T> {
+    // Create a sink that keeps what `collect` returns for each record.
+    pub fn new(collect: fn(&SinkRecord<'_>) -> T) -> Self {
+        CollectingSink(Mutex::new(Vec::new()), collect)
+    }
+}
+
+impl CollectingSink<Value> {
+    // Create a sink that keeps the tags of the records it receives.
+    pub fn tags() -> Self {
+        CollectingSink::new(|record| record.request().tags.clone())
+    }
+
+    // Create a sink that keeps the requests it receives, serialized.
+    pub fn requests() -> Self {
+        CollectingSink::new(|record| serde_json::to_value(record.request()).unwrap())
+    }
+}
+
+impl CollectingSink<String> {
+    // Create a sink that keeps the messages of the records it receives.
+    pub fn messages() -> Self {
+        CollectingSink::new(|record| {
+            record.field("message").and_then(|message| message.as_str()).unwrap_or_default().to_string()
+        })
+    }
+}
+
+impl<T: Send> Sink for CollectingSink<T> {
+    fn write(&self, record: &SinkRecord<'_>) -> io::Result<()> {
+        self.0.lock().unwrap().push((self.1)(record));
+        Ok(())
+    }
+}
+
+// Create a layer that only delivers to `sink`.
+pub async fn collecting_layer<T: Send + 'static>(
+    sink: CollectingSink<T>,
+) -> (mockito::ServerGuard, PogrLayer, Arc<CollectingSink<T>>) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let sink = Arc::new(sink);
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), None).await;
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror);
+    (mock_server, layer, sink)
+}
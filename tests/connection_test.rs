@@ -0,0 +1,216 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{AddressFamily, ConnectionWarmup, HttpConfig, PogrAppender, PogrLayer};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a keep-alive HTTP intake that accepts every log submission and counts the
+// connections it accepts and the `HEAD` requests it answers.
+async fn counting_intake() -> (String, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let logs_endpoint = format!("http://{}/v1/intake/logs", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let heads = Arc::new(AtomicUsize::new(0));
+
+    let accepted = Arc::clone(&connections);
+    let answered = Arc::clone(&heads);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            accepted.fetch_add(1, Ordering::SeqCst);
+            let answered = Arc::clone(&answered);
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                // Answer requests on the connection until the client closes it.
+                loop {
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    let mut head = None;
+                    loop {
+                        line.clear();
+                        if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        if line == "\r\n" {
+                            break;
+                        }
+                        head.get_or_insert(line.starts_with("HEAD "));
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    stream.read_exact(&mut body).await.unwrap();
+
+                    // Responses to `HEAD` requests have no body.
+                    let response_body = r#"{"success":true,"payload":{"log_id":"test_log_id"}}"#;
+                    let mut response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+                        response_body.len()
+                    );
+                    if head == Some(true) {
+                        answered.fetch_add(1, Ordering::SeqCst);
+                    } else {
+                        response.push_str(response_body);
+                    }
+                    stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+
+    (logs_endpoint, connections, heads)
+}
+
+// Build an appender submitting to the given logs endpoint.
+fn appender(logs_endpoint: String) -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_connection".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint,
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Log three events one after another and return how many connections the intake accepted.
+async fn connections_for(layer: impl FnOnce(PogrAppender) -> PogrLayer) -> usize {
+    let (logs_endpoint, connections, _) = counting_intake().await;
+    let layer = layer(appender(logs_endpoint));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for attempt in 0..3 {
+        info!(attempt, "Submitted in its own request");
+        guard.flush().await;
+    }
+
+    connections.load(Ordering::SeqCst)
+}
+
+// Verify that connections are reused without a maximum age.
+#[tokio::test]
+async fn test_connections_reused_by_default() {
+    assert_eq!(connections_for(PogrLayer::new).await, 1);
+}
+
+// Verify that connections past their maximum age are replaced by new ones.
+#[tokio::test]
+async fn test_connection_max_age() {
+    // Every connection has expired by the next submission.
+    let connections = connections_for(|appender| PogrLayer::new(appender).with_connection_max_age(Duration::ZERO)).await;
+    assert_eq!(connections, 3);
+
+    // None of them expires within the test.
+    let connections =
+        connections_for(|appender| PogrLayer::new(appender).with_connection_max_age(Duration::from_secs(3600))).await;
+    assert_eq!(connections, 1);
+}
+
+// Wait until the intake has answered at least `count` `HEAD` requests.
+async fn wait_for_heads(heads: &AtomicUsize, count: usize) {
+    for _ in 0..500 {
+        if heads.load(Ordering::SeqCst) >= count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("the intake answered {} HEAD requests, expected {}", heads.load(Ordering::SeqCst), count);
+}
+
+// Verify that warm-up opens the configured connections as soon as the layer is added to a
+// subscriber, and that the submissions afterwards reuse them.
+#[tokio::test]
+async fn test_connection_warmup() {
+    let (logs_endpoint, connections, heads) = counting_intake().await;
+    let layer = PogrLayer::new(appender(logs_endpoint))
+        .with_connection_warmup(ConnectionWarmup::new().with_connections(2).with_keep_warm_interval(None));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    wait_for_heads(&heads, 2).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    for attempt in 0..3 {
+        info!(attempt, "Submitted in its own request");
+        guard.flush().await;
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+    assert_eq!(heads.load(Ordering::SeqCst), 2);
+}
+
+// Verify that warm connections are touched periodically, over the same connection.
+#[tokio::test]
+async fn test_connections_kept_warm() {
+    let (logs_endpoint, connections, heads) = counting_intake().await;
+    let layer = PogrLayer::new(appender(logs_endpoint)).with_connection_warmup(
+        ConnectionWarmup::new().with_keep_warm_interval(Some(Duration::from_millis(20))),
+    );
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    wait_for_heads(&heads, 4).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+// Log an event to the intake through `localhost` with the given HTTP settings and return how
+// many connections the intake, listening on IPv4 only, accepted.
+async fn connections_through_localhost(http: HttpConfig) -> usize {
+    let (logs_endpoint, connections, _) = counting_intake().await;
+    let layer = PogrLayer::new(appender(logs_endpoint.replace("127.0.0.1", "localhost"))).with_http(http);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Submitted through localhost");
+    guard.flush().await;
+    connections.load(Ordering::SeqCst)
+}
+
+// Verify the default socket options.
+#[test]
+fn test_socket_option_defaults() {
+    let http = HttpConfig::new();
+    assert!(http.tcp_nodelay());
+    assert_eq!(http.address_family(), AddressFamily::Any);
+    assert_eq!(http.local_address(), None);
+}
+
+// Verify that connections only use the allowed address family.
+#[tokio::test]
+async fn test_address_family() {
+    let http = HttpConfig::new().with_address_family(AddressFamily::Ipv4Only).with_tcp_nodelay(false);
+    assert_eq!(connections_through_localhost(http).await, 1);
+    let http = HttpConfig::new().with_address_family(AddressFamily::PreferIpv4);
+    assert_eq!(connections_through_localhost(http).await, 1);
+
+    // The intake is not reachable over IPv6.
+    let http = HttpConfig::new().with_address_family(AddressFamily::Ipv6Only);
+    assert_eq!(connections_through_localhost(http).await, 0);
+}
+
+// Verify that connections are bound to the local address, and fail if it is not one of the
+// host's.
+#[tokio::test]
+async fn test_local_address() {
+    let loopback = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let http = HttpConfig::new().with_local_address(loopback);
+    assert_eq!(connections_through_localhost(http.clone()).await, 1);
+    assert_eq!(http.local_address(), Some(loopback));
+
+    // An address reserved for documentation is assigned to none of the host's interfaces.
+    let http = HttpConfig::new()
+        .with_address_family(AddressFamily::Ipv4Only)
+        .with_local_address(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+    assert_eq!(connections_through_localhost(http).await, 0);
+}
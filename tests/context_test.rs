@@ -0,0 +1,166 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{context, TaskContext};
+use std::sync::Arc;
+use tracing::instrument::WithSubscriber;
+use tracing::{info, info_span, Instrument};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Handle a join inside an instrumented future.
+async fn handle_join() {
+    tokio::task::yield_now().await;
+    info!("Joining lobby");
+}
+
+// Verify that events within a context's scope carry its fields, across instrumented futures
+// and nested scopes, and that span and event fields take precedence.
+#[tokio::test]
+async fn test_task_context() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    TaskContext::new()
+        .with_request_id("7f3e2a")
+        .with_player_id("player-42")
+        .scope(async {
+            handle_join().instrument(info_span!("join")).await;
+            TaskContext::new()
+                .with_match_id("match-7")
+                .scope(async {
+                    info!("Match started");
+                    info_span!("round", player_id = "player-43").in_scope(|| info!("Round started"));
+                })
+                .await;
+        })
+        .await;
+    info!("Outside any context");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records[0]["request_id"], "7f3e2a");
+    assert_eq!(records[0]["player_id"], "player-42");
+    assert!(records[0].get("match_id").is_none());
+    assert_eq!(records[1]["request_id"], "7f3e2a");
+    assert_eq!(records[1]["match_id"], "match-7");
+    assert_eq!(records[2]["player_id"], "player-43");
+    assert!(records[3].get("request_id").is_none());
+}
+
+// Verify that a spawned task carries a context passed along explicitly.
+#[tokio::test]
+async fn test_task_context_spawned() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let guard = layer.guard();
+    let subscriber = Arc::new(Registry::default().with(layer));
+    let _default = tracing::subscriber::set_default(Arc::clone(&subscriber));
+
+    TaskContext::new()
+        .with_field("region", "eu-west")
+        .scope(async move {
+            let context = TaskContext::current().unwrap();
+            assert_eq!(context.field("region").unwrap(), "eu-west");
+            let dispatch = tracing::Dispatch::from(subscriber);
+            tokio::spawn(context.scope(async { info!("Saving profile") }).with_subscriber(dispatch))
+                .await
+                .unwrap();
+        })
+        .await;
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records[0]["region"], "eu-west");
+}
+
+// Verify that the global context is merged into subsequent events as it changes, below the
+// task's context.
+#[tokio::test]
+async fn test_global_context() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Server started");
+    context::insert("deployment_wave", "canary");
+    context::insert("map", "dust2");
+    info!("Map loaded");
+    assert_eq!(context::remove("map"), Some(serde_json::json!("dust2")));
+    TaskContext::new()
+        .with_field("deployment_wave", "wave-2")
+        .scope(async { info!("Player joined") })
+        .await;
+    info!("Map unloaded");
+    guard.flush().await;
+    context::clear();
+    assert_eq!(context::get("deployment_wave"), None);
+
+    let records = sink.0.lock().unwrap();
+    assert!(records[0].get("deployment_wave").is_none());
+    assert_eq!(records[1]["deployment_wave"], "canary");
+    assert_eq!(records[1]["map"], "dust2");
+    assert_eq!(records[2]["deployment_wave"], "wave-2");
+    assert_eq!(records[3]["deployment_wave"], "canary");
+    assert!(records[3].get("map").is_none());
+}
+
+// Verify that the thread's diagnostic context is merged into events on that thread only, with
+// entries popped by their guards or explicitly.
+#[tokio::test]
+async fn test_thread_context() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let guard = layer.guard();
+    let dispatch = tracing::Dispatch::new(Registry::default().with(layer));
+    let runtime = tokio::runtime::Handle::current();
+
+    let worker = std::thread::spawn(move || {
+        // Delivery runs on the runtime, which the server thread hands records to.
+        let _runtime = runtime.enter();
+        tracing::dispatcher::with_default(&dispatch, || {
+            let _match = context::push("match_id", 7);
+            {
+                let _round = context::push("round", 3);
+                let _override = context::push("match_id", 8);
+                info!("Round started");
+            }
+            info!("Match ended");
+            let _lobby = context::push("lobby", "eu-1");
+            assert_eq!(context::pop(), Some(("lobby".to_string(), serde_json::json!("eu-1"))));
+            info!("Lobby closed");
+        });
+        // The guards have popped every entry.
+        assert_eq!(context::pop(), None);
+    });
+    worker.join().unwrap();
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records[0]["match_id"], 8);
+    assert_eq!(records[0]["round"], 3);
+    assert_eq!(records[1]["match_id"], 7);
+    assert!(records[1].get("round").is_none());
+    assert!(records[2].get("lobby").is_none());
+}
+
+// Verify that a tag scope tags the events emitted while its guard is alive.
+#[tokio::test]
+async fn test_tag_scope() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    {
+        let _level = pogr_tracing_rs::tag_scope([("level_name", "dust2"), ("mode", "ranked")]);
+        info!("Level loaded");
+    }
+    info!("Level unloaded");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records[0]["level_name"], "dust2");
+    assert_eq!(records[0]["mode"], "ranked");
+    assert!(records[1].get("level_name").is_none());
+}
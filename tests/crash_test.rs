@@ -0,0 +1,103 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{CrashReporter, PogrAppender};
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Create an empty directory for the crash reporter of a single test.
+fn crash_directory(name: &str) -> std::path::PathBuf {
+    let directory = std::env::temp_dir().join(format!("pogr_crash_test_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    directory
+}
+
+// Verify that a minidump left by a crashed run is uploaded with the crashed session ID.
+#[tokio::test]
+async fn test_pending_minidump_upload() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Simulate a previous run that recorded its session and then crashed.
+    let directory = crash_directory("upload");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("session"), "crashed_session_id").unwrap();
+    let reporter = CrashReporter::new(&directory);
+    let minidump = reporter.persist_minidump(b"MDMP fake minidump contents").unwrap();
+
+    // Expect a multipart upload with the dump and the session it belongs to.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_header("content-type", Matcher::Regex("^multipart/form-data".to_string()))
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#""log":"native crash minidump""#.to_string()),
+            Matcher::Regex(r#""pogr.crashed_session_id":"crashed_session_id""#.to_string()),
+            Matcher::Regex("MDMP fake minidump contents".to_string()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let uploaded = reporter.upload_pending(&appender).await.unwrap();
+
+    // The dump is gone and the current session is recorded for the next start.
+    m_logs.assert();
+    assert_eq!(uploaded, 1);
+    assert!(!minidump.exists());
+    assert_eq!(std::fs::read_to_string(directory.join("session")).unwrap(), "test_session_id");
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+// Verify that a minidump is kept for the next start when the upload fails.
+#[tokio::test]
+async fn test_failed_minidump_upload_is_kept() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    let directory = crash_directory("failure");
+    let reporter = CrashReporter::new(&directory);
+    let minidump = reporter.persist_minidump(b"MDMP").unwrap();
+
+    // Simulate an intake outage.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let uploaded = reporter.upload_pending(&appender).await.unwrap();
+
+    m_logs.assert();
+    assert_eq!(uploaded, 0);
+    assert!(minidump.exists());
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
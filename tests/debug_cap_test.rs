@@ -0,0 +1,79 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::DEFAULT_MAX_DEBUG_LENGTH;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// A value whose `Debug` output is `length` bytes long.
+struct Giant {
+    length: usize,
+}
+
+impl std::fmt::Debug for Giant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for _ in 0..self.length / 8 {
+            f.write_str("éàbcde")?;
+        }
+        Ok(())
+    }
+}
+
+// Verify that long `Debug` output is cut at the maximum length, on a character boundary,
+// and followed by its original length and hash, while short output is kept whole.
+#[tokio::test]
+async fn test_debug_output_is_capped() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_max_debug_length(Some(97));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!(state = ?Giant { length: 8_000 }, "first giant");
+    info!(state = ?Giant { length: 8_000 }, "second giant");
+    info!(state = ?Giant { length: 16_000 }, "bigger giant");
+    info!(state = ?Giant { length: 96 }, "small state");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap().clone();
+    let state = |index: usize| records[index]["tags"]["state"].as_str().unwrap().to_string();
+    let (first, second, bigger, small) = (state(0), state(1), state(2), state(3));
+
+    // 12 repetitions of the 8-byte pattern make 96 bytes, and the next, 2-byte `é` does not fit.
+    let kept = "éàbcde".repeat(12) + "…";
+    assert!(first.starts_with(&kept), "{}", first);
+    let suffix = first.strip_prefix(&kept).unwrap();
+    assert!(suffix.starts_with(" [truncated: 8000 bytes, fnv1a64 "), "{}", suffix);
+    assert_eq!(first, second);
+    assert!(bigger.contains("[truncated: 16000 bytes, fnv1a64 "));
+    assert_ne!(bigger[kept.len()..], first[kept.len()..]);
+    assert_eq!(small, "éàbcde".repeat(12));
+}
+
+// Verify that `Debug` output is capped at 64 KiB by default, and not at all without a limit.
+#[tokio::test]
+async fn test_debug_output_limit() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let default = tracing::subscriber::set_default(subscriber);
+    info!(state = ?Giant { length: 100_000 }, "default limit");
+    guard.flush().await;
+    drop(default);
+
+    let (_mock_server, layer, unlimited) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_max_debug_length(None);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+    info!(state = ?Giant { length: 100_000 }, "no limit");
+    guard.flush().await;
+
+    let capped = sink.0.lock().unwrap()[0]["tags"]["state"].as_str().unwrap().to_string();
+    assert!(capped.len() < DEFAULT_MAX_DEBUG_LENGTH + 100);
+    assert!(capped.contains("[truncated: 100000 bytes, fnv1a64 "));
+    let whole = unlimited.0.lock().unwrap()[0]["tags"]["state"].as_str().unwrap().len();
+    assert_eq!(whole, 100_000);
+}
@@ -0,0 +1,95 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{
+    clear_diagnostic_handler, set_diagnostic_handler, BatchConfig, DiagnosticKind, DiagnosticLevel, PogrAppender,
+    PogrLayer,
+};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Verify that internal problems reach the diagnostic handler and never loop back to POGR.
+//
+// The handler is process-wide, so everything is checked in a single test.
+#[tokio::test]
+async fn test_diagnostic_handler() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Simulate an intake outage. Only the two application events may be submitted; the
+    // events recorded by the handler must not be.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(2)
+        .create();
+
+    let diagnostics = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&diagnostics);
+    set_diagnostic_handler(move |diagnostic| {
+        collected
+            .lock()
+            .unwrap()
+            .push((diagnostic.level(), diagnostic.kind(), diagnostic.message().to_string()));
+
+        // A handler that logs through the same subscriber must not cause a submission.
+        error!(target: "app::alerts", "pogr diagnostic: {}", diagnostic);
+    });
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_batching(BatchConfig::new().with_max_request_size(2 * 1024));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("first message");
+    let payload = "y".repeat(10_000);
+    info!(payload = %payload, "oversized message");
+
+    guard.flush().await;
+    m_logs.assert();
+    clear_diagnostic_handler();
+
+    // Both failed submissions and the truncation were reported.
+    let diagnostics = diagnostics.lock().unwrap();
+    let failures: Vec<_> = diagnostics
+        .iter()
+        .filter(|(level, kind, _)| *level == DiagnosticLevel::Error && *kind == DiagnosticKind::Delivery)
+        .collect();
+    assert_eq!(failures.len(), 2, "{:?}", diagnostics);
+    assert!(failures[0].2.starts_with("Failed to log to POGR"), "{:?}", failures);
+
+    let truncations: Vec<_> = diagnostics
+        .iter()
+        .filter(|(level, kind, _)| *level == DiagnosticLevel::Warn && *kind == DiagnosticKind::Truncated)
+        .collect();
+    assert_eq!(truncations.len(), 1, "{:?}", diagnostics);
+    assert!(truncations[0].2.contains("of at most 2048 bytes"), "{:?}", truncations);
+}
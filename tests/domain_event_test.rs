@@ -0,0 +1,132 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{pogr_event, PogrAppender, PogrLayer};
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::Level;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service whose logs and event endpoints keep the bodies they receive.
+fn mock_service(
+    logs: &Arc<Mutex<Vec<serde_json::Value>>>,
+    events: &Arc<Mutex<Vec<serde_json::Value>>>,
+) -> (mockito::ServerGuard, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+    for (path, bodies) in [("/v1/intake/logs", logs), ("/v1/intake/event", events)] {
+        let captured = Arc::clone(bodies);
+        mock_server.mock("POST", path)
+            .with_status(200)
+            .with_body_from_request(move |request| {
+                captured.lock().unwrap().push(serde_json::from_slice(request.body().unwrap()).unwrap());
+                br#"{"success": true, "payload": {"log_id": "test_log_id"}}"#.to_vec()
+            })
+            .create();
+    }
+    (mock_server, base_url)
+}
+
+// Create a layer for the mock service, sending domain events to its event endpoint.
+async fn layer(base_url: &str) -> PogrLayer {
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    PogrLayer::new(appender).with_events_endpoint(format!("{}/v1/intake/event", base_url))
+}
+
+// A field value that is not a primitive.
+#[derive(Serialize)]
+struct Score {
+    red: u32,
+    blue: u32,
+}
+
+// Verify that domain events are sent to the event intake with their kind and typed fields,
+// while logs still go to the logs endpoint.
+#[tokio::test]
+async fn test_events_go_to_the_event_intake() {
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (_mock_server, base_url) = mock_service(&logs, &events);
+
+    let layer = layer(&base_url).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let team_id = 7;
+    pogr_event!(kind: "match_end", winner = team_id, duration_s = 312.5, overtime = false, score = Score { red: 3, blue: 1 });
+    pogr_event!(kind: "server_ready",);
+    tracing::info!("match closed");
+    guard.flush().await;
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    let match_end = &events[0];
+    assert_eq!(match_end["log"], "match_end");
+    assert_eq!(match_end["severity"], "INFO");
+    assert_eq!(match_end["data"]["kind"], "match_end");
+    assert_eq!(
+        match_end["data"]["fields"],
+        serde_json::json!({ "winner": 7, "duration_s": 312.5, "overtime": false, "score": { "red": 3, "blue": 1 } })
+    );
+    assert_eq!(match_end["data"]["target"], "pogr_event");
+    assert!(match_end["tags"].get("pogr.kind").is_none());
+    assert!(match_end["tags"].get("pogr.fields").is_none());
+    assert_eq!(events[1]["log"], "server_ready");
+    assert_eq!(events[1]["data"]["fields"], serde_json::json!({}));
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0]["tags"]["message"], "match closed");
+}
+
+// Verify that domain events are not sampled like logs.
+#[tokio::test]
+async fn test_events_are_not_sampled() {
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (_mock_server, base_url) = mock_service(&logs, &events);
+
+    let layer = layer(&base_url).await;
+    layer.reload_handle().set_level_sampling(Level::INFO, 0.0);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    pogr_event!(kind: "purchase", item = "skin_gold", price_cents = 499);
+    tracing::info!("sampled out");
+    guard.flush().await;
+
+    assert_eq!(events.lock().unwrap().len(), 1);
+    assert!(logs.lock().unwrap().is_empty());
+}
+
+// Verify that the fields of a domain event are not evaluated without an interested subscriber.
+#[test]
+fn test_disabled_events_are_not_evaluated() {
+    let evaluated = AtomicUsize::new(0);
+    let winner = || {
+        evaluated.fetch_add(1, Ordering::SeqCst);
+        7
+    };
+
+    pogr_event!(kind: "match_end", winner = winner());
+    assert_eq!(evaluated.load(Ordering::SeqCst), 0);
+}
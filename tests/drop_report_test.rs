@@ -0,0 +1,41 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::RateLimits;
+use std::time::Duration;
+use tracing::{debug, info, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that events dropped by sampling and rate limits are summarized periodically, by level
+// and reason, and that nothing is sent for intervals without drops.
+#[tokio::test]
+async fn test_drop_reports() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer
+        .with_target_levels("debug".parse().unwrap())
+        .with_level_sampling(Level::DEBUG, 0.0)
+        .with_rate_limits(RateLimits::new().with_limit("netcode", 2.0))
+        .with_drop_reports(Duration::from_millis(300));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for _ in 0..10 {
+        debug!("Tick");
+    }
+    for _ in 0..5 {
+        info!(target: "netcode", "Packet resent");
+    }
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    let summaries: Vec<_> = records.iter().filter(|record| record["log"] == "rust tracing events dropped").collect();
+    assert_eq!(records.len() - summaries.len(), 2);
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0]["severity"], "WARN");
+    assert_eq!(summaries[0]["data"]["total"], 13);
+    assert_eq!(summaries[0]["data"]["dropped"]["DEBUG"]["sampling"], 10);
+    assert_eq!(summaries[0]["data"]["dropped"]["INFO"]["rate_limit"], 3);
+    assert_eq!(summaries[0]["tags"]["message"], "Dropped 13 events in the last 0.3 s (INFO: 3, DEBUG: 10)");
+}
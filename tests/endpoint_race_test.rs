@@ -0,0 +1,128 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{EndpointRace, PogrAppender, PogrLayer, DEFAULT_RACE_INTERVAL};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start an intake that accepts connections but never responds, and return its logs endpoint.
+async fn unresponsive_intake() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let logs_endpoint = format!("http://{}/v1/intake/logs", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+    logs_endpoint
+}
+
+// Build an appender submitting to the given logs endpoint.
+fn appender(logs_endpoint: String) -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_race".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint,
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Mock a responsive intake answering `probes` probes and accepting `submissions` log
+// submissions.
+fn responsive_intake(mock_server: &mut mockito::ServerGuard, probes: usize, submissions: usize) -> (mockito::Mock, mockito::Mock) {
+    let probes = mock_server.mock("HEAD", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .with_status(200)
+        .expect(probes)
+        .create();
+    let logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(submissions)
+        .create();
+    (probes, logs)
+}
+
+// Verify the default race settings.
+#[test]
+fn test_race_defaults() {
+    let race = EndpointRace::new(["https://eu.api.pogr.io/v1/intake/logs"]);
+    assert_eq!(race.endpoints(), ["https://eu.api.pogr.io/v1/intake/logs"]);
+    assert_eq!(race.interval(), DEFAULT_RACE_INTERVAL);
+}
+
+// Verify that records are submitted to the raced endpoint that responds, instead of the
+// appender's unresponsive one.
+#[tokio::test]
+async fn test_fastest_endpoint_wins() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let (probes, logs) = responsive_intake(&mut mock_server, 1, 3);
+    let fast_endpoint = format!("{}/v1/intake/logs", mock_server.url());
+
+    let layer = PogrLayer::new(appender(unresponsive_intake().await)).with_endpoint_race(EndpointRace::new([fast_endpoint]));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for attempt in 0..3 {
+        info!(attempt, "Submitted to the fastest endpoint");
+        guard.flush().await;
+    }
+    logs.assert();
+    // The endpoints were raced once, before the first submission.
+    probes.assert();
+}
+
+// Verify that the appender's logs endpoint takes part in the race, and wins if it is the one
+// responding.
+#[tokio::test]
+async fn test_appender_endpoint_races() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let (probes, logs) = responsive_intake(&mut mock_server, 1, 1);
+    let appender = appender(format!("{}/v1/intake/logs", mock_server.url()));
+
+    let layer = PogrLayer::new(appender).with_endpoint_race(EndpointRace::new([unresponsive_intake().await]));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Submitted to the appender's endpoint");
+    guard.flush().await;
+    logs.assert();
+    probes.assert();
+}
+
+// Verify that the endpoints are raced again once the interval has passed.
+#[tokio::test]
+async fn test_race_is_reevaluated() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let (probes, logs) = responsive_intake(&mut mock_server, 3, 3);
+    let fast_endpoint = format!("{}/v1/intake/logs", mock_server.url());
+
+    let race = EndpointRace::new([fast_endpoint]).with_interval(Duration::ZERO);
+    let layer = PogrLayer::new(appender(unresponsive_intake().await)).with_endpoint_race(race);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for attempt in 0..3 {
+        info!(attempt, "Raced before every submission");
+        guard.flush().await;
+    }
+    logs.assert();
+    probes.assert();
+}
@@ -0,0 +1,69 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::PogrAppender;
+
+// Set mock environment variables required for the PogrAppender authentication process.
+fn set_credentials() {
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+}
+
+// Verify that a plain HTTP endpoint on another host is rejected before anything is sent.
+#[tokio::test]
+#[should_panic(expected = "Invalid init endpoint http://collector.internal/v1/intake/init: plain HTTP must be allowed explicitly")]
+async fn test_plain_http_rejected() {
+    set_credentials();
+    PogrAppender::builder()
+        .with_init_endpoint("http://collector.internal/v1/intake/init")
+        .with_logs_endpoint("https://collector.internal/v1/intake/logs")
+        .build()
+        .await;
+}
+
+// Verify that endpoints with other schemes are rejected.
+#[tokio::test]
+#[should_panic(expected = "unsupported scheme `ftp`, expected https")]
+async fn test_unsupported_scheme_rejected() {
+    set_credentials();
+    PogrAppender::builder()
+        .with_init_endpoint("https://collector.internal/v1/intake/init")
+        .with_logs_endpoint("ftp://collector.internal/v1/intake/logs")
+        .build()
+        .await;
+}
+
+// Verify that malformed endpoints are rejected.
+#[tokio::test]
+#[should_panic(expected = "Invalid init endpoint collector/init")]
+async fn test_malformed_endpoint_rejected() {
+    set_credentials();
+    PogrAppender::new(Some("collector/init".to_string()), None).await;
+}
+
+// Verify that plain HTTP passes the scheme check once it is allowed explicitly, so the
+// session initialization is attempted.
+#[tokio::test]
+#[should_panic(expected = "Failed to send init request")]
+async fn test_plain_http_allowed() {
+    set_credentials();
+    PogrAppender::builder()
+        .with_init_endpoint("http://collector.invalid/v1/intake/init")
+        .with_logs_endpoint("http://collector.invalid/v1/intake/logs")
+        .with_allow_insecure(true)
+        .build()
+        .await;
+}
+
+// Verify that startup validation reports a rejected endpoint instead of panicking.
+#[tokio::test]
+async fn test_plain_http_validation() {
+    set_credentials();
+    let result = PogrAppender::builder()
+        .with_init_endpoint("http://collector.invalid/v1/intake/init")
+        .with_logs_endpoint("http://collector.invalid/v1/intake/logs")
+        .build_validated()
+        .await;
+    let Err(err) = result else {
+        panic!("validation passed with a plain HTTP endpoint");
+    };
+    assert!(err.report().credentials.to_string().contains("plain HTTP must be allowed explicitly"));
+}
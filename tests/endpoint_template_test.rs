@@ -0,0 +1,69 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{PogrAppender, PogrLayer};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Set mock environment variables required for the PogrAppender authentication process, and
+// the service type the templates use.
+fn set_environment() {
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+    std::env::set_var("SERVICE_TYPE", "game server");
+}
+
+// Verify that the placeholders of the init and logs endpoints are resolved per request, with
+// percent-encoded values.
+#[tokio::test]
+async fn test_endpoint_templates() {
+    set_environment();
+
+    // Initialize a mock server to simulate a gateway in front of the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    let m_init = mock_server.mock("POST", "/telemetry/game%20server/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test session" }
+        }).to_string())
+        .create();
+    let m_logs = mock_server.mock("POST", "/telemetry/game%20server/logs?session=test%20session")
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "1"}}"#)
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/telemetry/{{type}}/init", base_url)),
+        Some(format!("{}/telemetry/{{type}}/logs?session={{session}}", base_url)),
+    )
+    .await;
+    m_init.assert();
+    assert_eq!(appender.logs_endpoint, format!("{}/telemetry/{{type}}/logs?session={{session}}", base_url));
+
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Match started");
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that unknown placeholders are rejected when the appender is created.
+#[tokio::test]
+#[should_panic(expected = "Invalid logs endpoint template: unknown placeholder `{region}`")]
+async fn test_unknown_placeholder() {
+    set_environment();
+    PogrAppender::new(None, Some("https://gateway.example.com/{region}/logs".to_string())).await;
+}
+
+// Verify that the init endpoint cannot use the session, which does not exist yet.
+#[tokio::test]
+#[should_panic(expected = "Invalid init endpoint template: `{session}` is not known before the session is initialized")]
+async fn test_session_in_init_endpoint() {
+    set_environment();
+    PogrAppender::new(Some("https://gateway.example.com/{session}/init".to_string()), None).await;
+}
@@ -0,0 +1,77 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::PogrAppender;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that the builder sends the allowlisted variables that are set, and only them, in the
+// session metadata.
+#[tokio::test]
+async fn test_session_env_capture() {
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+    std::env::set_var("ENV_CAPTURE_TEST_REGION", "eu-west");
+    std::env::set_var("ENV_CAPTURE_TEST_DEPLOY_ID", "deploy-42");
+    std::env::set_var("ENV_CAPTURE_TEST_SECRET", "hunter2");
+    std::env::remove_var("ENV_CAPTURE_TEST_UNSET");
+
+    let mut mock_server = mockito::Server::new();
+    let init_endpoint = format!("{}/v1/intake/init", mock_server.url().trim_end_matches('/'));
+    let captured = Arc::new(Mutex::new(None));
+    let init_body = Arc::clone(&captured);
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            *init_body.lock().unwrap() = Some(serde_json::from_slice::<serde_json::Value>(request.body().unwrap()).unwrap());
+            serde_json::json!({
+                "success": true,
+                "payload": { "session_id": "test_session_id" }
+            }).to_string().into_bytes()
+        })
+        .create();
+
+    PogrAppender::builder()
+        .with_init_endpoint(init_endpoint)
+        .with_session_metadata("region", "eu-west")
+        .with_env_capture(["ENV_CAPTURE_TEST_REGION", "ENV_CAPTURE_TEST_UNSET"])
+        .with_env_capture(["ENV_CAPTURE_TEST_DEPLOY_ID"])
+        .build()
+        .await;
+
+    let body = captured.lock().unwrap().take().unwrap();
+    assert_eq!(body["metadata"]["region"], "eu-west");
+    assert_eq!(
+        body["metadata"]["env"],
+        serde_json::json!({ "ENV_CAPTURE_TEST_REGION": "eu-west", "ENV_CAPTURE_TEST_DEPLOY_ID": "deploy-42" })
+    );
+}
+
+// Verify that the layer tags every event with the allowlisted variables that are set, read once
+// when the layer is configured, and that event fields take precedence.
+#[tokio::test]
+async fn test_event_env_capture() {
+    std::env::set_var("ENV_CAPTURE_TEST_ALLOC_ID", "alloc-7");
+    std::env::set_var("ENV_CAPTURE_TEST_ZONE", "a");
+    std::env::remove_var("ENV_CAPTURE_TEST_MISSING");
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_env_capture(["ENV_CAPTURE_TEST_ALLOC_ID", "ENV_CAPTURE_TEST_ZONE", "ENV_CAPTURE_TEST_MISSING"]);
+    std::env::set_var("ENV_CAPTURE_TEST_ALLOC_ID", "alloc-8");
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Server started");
+    info!(env.ENV_CAPTURE_TEST_ZONE = "b", "Zone moved");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["tags"]["env.ENV_CAPTURE_TEST_ALLOC_ID"], "alloc-7");
+    assert_eq!(records[0]["tags"]["env.ENV_CAPTURE_TEST_ZONE"], "a");
+    assert!(records[0]["tags"].get("env.ENV_CAPTURE_TEST_MISSING").is_none());
+    assert!(records[0]["tags"].get("env.ENV_CAPTURE_TEST_SECRET").is_none());
+    assert_eq!(records[1]["tags"]["env.ENV_CAPTURE_TEST_ZONE"], "b");
+}
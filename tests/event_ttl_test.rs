@@ -0,0 +1,39 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::ManualClock;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that records older than the time-to-live are dropped instead of delivered, and
+// counted in the drop reports.
+#[tokio::test]
+async fn test_event_ttl() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let clock = Arc::new(ManualClock::new(SystemTime::now() - Duration::from_secs(7200)));
+    let layer = layer
+        .with_clock(Arc::clone(&clock))
+        .with_event_ttl(Duration::from_secs(60))
+        .with_drop_reports(Duration::from_millis(200));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // Captured two hours ago, e.g. while the intake was unreachable.
+    error!("Match server unreachable");
+    clock.set(SystemTime::now());
+    info!("Match started");
+    guard.flush().await;
+
+    let logs: Vec<String> = sink.0.lock().unwrap().iter().map(|record| record["log"].as_str().unwrap().to_string()).collect();
+    assert_eq!(logs, ["rust tracing log captured"]);
+    assert_eq!(sink.0.lock().unwrap()[0]["tags"]["message"], "Match started");
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    guard.flush().await;
+    let records = sink.0.lock().unwrap();
+    let summary = records.iter().find(|record| record["log"] == "rust tracing events dropped").unwrap();
+    assert_eq!(summary["data"]["dropped"]["ERROR"]["expired"], 1);
+}
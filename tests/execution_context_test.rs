@@ -0,0 +1,59 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::SpanEvents;
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that events name the thread that emitted them.
+#[tokio::test]
+async fn test_events_name_their_thread() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let guard = layer.guard();
+    let dispatch = tracing::Dispatch::new(Registry::default().with(layer));
+    let runtime = tokio::runtime::Handle::current();
+
+    let worker = std::thread::Builder::new()
+        .name("asset-loader".to_string())
+        .spawn(move || {
+            // Delivery runs on the runtime, which the loader thread hands records to.
+            let _runtime = runtime.enter();
+            tracing::dispatcher::with_default(&dispatch, || info!("Asset loaded"));
+        })
+        .unwrap();
+    worker.join().unwrap();
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["data"]["thread_name"], "asset-loader");
+    assert!(records[0]["data"]["thread_id"].as_u64().unwrap() > 0);
+}
+
+// Verify that span transitions name the thread they happened on, and that records emitted in a
+// tokio task carry its ID when tokio's unstable API is enabled.
+#[tokio::test]
+async fn test_span_transitions_and_tasks() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let guard = layer.guard();
+    let dispatch = tracing::Dispatch::new(
+        Registry::default().with(layer.with_span_events(SpanEvents::for_targets(["execution_context_test"]))),
+    );
+
+    let task = tokio::spawn(async move {
+        tracing::dispatcher::with_default(&dispatch, || {
+            let _entered = info_span!("load").entered();
+            info!("Asset loaded");
+        });
+    });
+    task.await.unwrap();
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    let thread_name = std::thread::current().name().map(str::to_string);
+    assert!(records.len() >= 2);
+    for record in records.iter() {
+        assert_eq!(record["data"]["thread_name"], serde_json::json!(thread_name));
+        assert_eq!(record["data"].get("task_id").is_some(), cfg!(tokio_unstable));
+    }
+}
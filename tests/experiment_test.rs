@@ -0,0 +1,51 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that events carry the registered experiments and flags, as they change at runtime.
+#[tokio::test]
+async fn test_experiment_tags() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let layer = layer
+        .with_experiment("matchmaking_v2", "control")
+        .with_feature_flag("new_renderer", false);
+    let experiments = layer.experiments();
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Player joined");
+    experiments.set_experiment("matchmaking_v2", "treatment");
+    experiments.remove_feature_flag("new_renderer");
+    info!("Match started");
+    guard.flush().await;
+
+    assert_eq!(experiments.experiment("matchmaking_v2").as_deref(), Some("treatment"));
+    assert_eq!(experiments.feature_flag("new_renderer"), None);
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records[0]["experiment.matchmaking_v2"], "control");
+    assert_eq!(records[0]["feature_flag.new_renderer"], false);
+    assert_eq!(records[1]["experiment.matchmaking_v2"], "treatment");
+    assert!(records[1].get("feature_flag.new_renderer").is_none());
+}
+
+// Verify that an assignment recorded on a span takes precedence inside it.
+#[tokio::test]
+async fn test_experiment_span_scope() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let layer = layer.with_experiment("matchmaking_v2", "control");
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info_span!("match", experiment.matchmaking_v2 = "treatment").in_scope(|| info!("Match started"));
+    info!("Player left");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records[0]["experiment.matchmaking_v2"], "treatment");
+    assert_eq!(records[1]["experiment.matchmaking_v2"], "control");
+}
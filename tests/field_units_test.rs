@@ -0,0 +1,129 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{FieldUnits, PogrAppender, PogrLayer, SinkMode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Build an appender that is never submitted to.
+fn appender() -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_field_units".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint: "http://127.0.0.1:9/v1/intake/logs".to_string(),
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Record one event with `emit` through a layer with `field_units`, and return the fields the
+// sink received.
+async fn record(field_units: Option<FieldUnits>, emit: impl FnOnce()) -> Value {
+    let sink = Arc::new(CollectingSink::tags());
+    let mut layer = PogrLayer::new(appender())
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror);
+    if let Some(field_units) = field_units {
+        layer = layer.with_field_units(field_units);
+    }
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    emit();
+    guard.flush().await;
+    let mut records = sink.0.lock().unwrap().clone();
+    assert_eq!(records.len(), 1);
+    records.remove(0)
+}
+
+// Verify that numeric fields with a unit suffix are recorded with their unit.
+#[tokio::test]
+async fn test_suffix_units() {
+    let fields = record(Some(FieldUnits::new()), || {
+        info!(
+            frame_ms = 16.7,
+            asset_bytes = 4096_u64,
+            cpu_pct = 42,
+            uptime_secs = 30,
+            "frame stats"
+        )
+    })
+    .await;
+    assert_eq!(fields["frame_ms"], json!({ "value": 16.7, "unit": "ms" }));
+    assert_eq!(
+        fields["asset_bytes"],
+        json!({ "value": 4096, "unit": "bytes" })
+    );
+    assert_eq!(fields["cpu_pct"], json!({ "value": 42, "unit": "percent" }));
+    assert_eq!(fields["uptime_secs"], json!({ "value": 30, "unit": "s" }));
+}
+
+// Verify that a companion field gives its field a unit, takes precedence over the suffix, and
+// is not recorded itself.
+#[tokio::test]
+async fn test_companion_units() {
+    let fields = record(Some(FieldUnits::new()), || {
+        info!(
+            heap = 512,
+            "pogr.unit.heap" = "MiB",
+            load_ms = 2,
+            "pogr.unit.load_ms" = "s",
+            "frame stats"
+        )
+    })
+    .await;
+    assert_eq!(fields["heap"], json!({ "value": 512, "unit": "MiB" }));
+    assert_eq!(fields["load_ms"], json!({ "value": 2, "unit": "s" }));
+    assert!(fields.get("pogr.unit.heap").is_none());
+    assert!(fields.get("pogr.unit.load_ms").is_none());
+}
+
+// Verify that custom suffixes are recognized, and fields that are not numbers, or are named
+// only the suffix, are left as they are.
+#[tokio::test]
+async fn test_custom_suffixes_and_other_fields() {
+    let units = FieldUnits::without_suffixes().with_suffix("_fps", "fps");
+    assert_eq!(units.unit_of("render_fps"), Some("fps"));
+    assert_eq!(units.unit_of("frame_ms"), None);
+
+    let fields = record(Some(units), || {
+        info!(
+            render_fps = 60,
+            mode_fps = "vsync",
+            _fps = 30,
+            frame_ms = 16,
+            "pogr.unit.mode_fps" = "fps",
+            "frame stats"
+        )
+    })
+    .await;
+    assert_eq!(fields["render_fps"], json!({ "value": 60, "unit": "fps" }));
+    assert_eq!(fields["mode_fps"], json!("vsync"));
+    assert_eq!(fields["_fps"], json!(30));
+    assert_eq!(fields["frame_ms"], json!(16));
+    assert!(fields.get("pogr.unit.mode_fps").is_none());
+}
+
+// Verify that fields are recorded as plain numbers unless units are enabled.
+#[tokio::test]
+async fn test_disabled_by_default() {
+    let fields = record(None, || {
+        info!(
+            frame_ms = 16,
+            heap = 512,
+            "pogr.unit.heap" = "MiB",
+            "frame stats"
+        )
+    })
+    .await;
+    assert_eq!(fields["frame_ms"], json!(16));
+    assert_eq!(fields["heap"], json!(512));
+    assert_eq!(fields["pogr.unit.heap"], json!("MiB"));
+}
@@ -0,0 +1,164 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{PogrAppender, PogrLayer, RotatingFileSink, SinkMode};
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Create an empty directory for the log files of a single test.
+fn log_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("pogr_file_sink_test_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    directory
+}
+
+// Read the JSON lines of a log file.
+fn read_lines(path: &Path) -> Vec<serde_json::Value> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+// Verify that a mirror file gets every record, in the format submitted to POGR, and rotates.
+#[tokio::test]
+async fn test_rotating_file_mirror() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let directory = log_directory("mirror");
+    let path = directory.join("pogr.jsonl");
+
+    // Mock a successful log submission.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(5)
+        .create();
+
+    // Each record is a few hundred bytes, so every file holds a single record.
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let sink = RotatingFileSink::new(&path).with_max_file_size(64).with_max_files(2);
+    let layer = PogrLayer::new(appender).with_sink(sink, SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for index in 0..5 {
+        info!(index, "mirrored message");
+        guard.flush().await;
+    }
+    m_logs.assert();
+
+    // The newest record is in the current file, the two before it in the rotated files.
+    let current = read_lines(&path);
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["tags"]["index"], 4);
+    assert_eq!(current[0]["log"], "rust tracing log captured");
+    assert!(current[0]["event_id"].is_string());
+    assert!(current[0]["timestamp"].is_string());
+    assert_eq!(read_lines(&directory.join("pogr.jsonl.1"))[0]["tags"]["index"], 3);
+    assert_eq!(read_lines(&directory.join("pogr.jsonl.2"))[0]["tags"]["index"], 2);
+    assert!(!directory.join("pogr.jsonl.3").exists());
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+// Verify that a fallback file only gets the records that could not be delivered.
+#[tokio::test]
+async fn test_rotating_file_fallback() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let directory = log_directory("fallback");
+    let path = directory.join("undelivered.jsonl");
+
+    // Simulate an intake outage.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(2)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_sink(RotatingFileSink::new(&path), SinkMode::Fallback);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!(code = 17, "first undelivered message");
+    error!(code = 18, "second undelivered message");
+    guard.flush().await;
+    m_logs.assert();
+
+    let lines = read_lines(&path);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["tags"]["code"], 17);
+    assert_eq!(lines[1]["tags"]["code"], 18);
+    assert_eq!(lines[1]["severity"], "ERROR");
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+// Verify that with intake delivery disabled, records only go to the mirror sinks.
+#[tokio::test]
+async fn test_mirror_without_intake() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let directory = log_directory("no_intake");
+    let path = directory.join("pogr.jsonl");
+
+    // Nothing may reach the intake.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .expect(0)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(RotatingFileSink::new(&path), SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!(code = 7, "local only message");
+    guard.flush().await;
+    m_logs.assert();
+
+    let lines = read_lines(&path);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["tags"]["code"], 7);
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
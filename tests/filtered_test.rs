@@ -0,0 +1,106 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{PogrAppender, PogrLayer, TargetLevels};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{debug, info, warn, Event, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// A stand-in for a verbose console layer that counts the application's events it receives.
+// Events from the HTTP stack used to reach the mock server are ignored.
+struct CountingLayer(Arc<AtomicUsize>);
+
+impl<S: Subscriber> Layer<S> for CountingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target().starts_with("game") {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+// Spins up a mock POGR service expecting `expected_logs` submissions and returns an appender for it.
+async fn mock_appender(mock_server: &mut mockito::Server, expected_logs: usize) -> (PogrAppender, mockito::Mock) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    // Mock the logs endpoint, expecting an exact number of submissions.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(expected_logs)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    (appender, m_logs)
+}
+
+// Verify that a per-layer level filter keeps POGR quiet while other layers still see every event.
+#[tokio::test]
+async fn test_pogr_layer_with_level_filter() {
+    let mut mock_server = mockito::Server::new();
+    let (appender, m_logs) = mock_appender(&mut mock_server, 1).await;
+
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let console_events = Arc::new(AtomicUsize::new(0));
+
+    // Verbose "console" output alongside a quiet POGR layer.
+    let subscriber = Registry::default()
+        .with(CountingLayer(Arc::clone(&console_events)))
+        .with(layer.with_filter(LevelFilter::WARN));
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    debug!(target: "game", "console only");
+    info!(target: "game", "console only");
+    warn!(target: "game", "console and POGR");
+
+    guard.flush().await;
+    m_logs.assert();
+    assert_eq!(console_events.load(Ordering::SeqCst), 3);
+}
+
+// Verify that `TargetLevels` can be used as a per-layer filter.
+#[tokio::test]
+async fn test_pogr_layer_with_target_levels_filter() {
+    let mut mock_server = mockito::Server::new();
+    let (appender, m_logs) = mock_appender(&mut mock_server, 2).await;
+
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let console_events = Arc::new(AtomicUsize::new(0));
+
+    let levels: TargetLevels = "warn,game::matchmaking=debug".parse().unwrap();
+    let subscriber = Registry::default()
+        .with(CountingLayer(Arc::clone(&console_events)))
+        .with(layer.with_filter(levels));
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    debug!(target: "game::matchmaking", "console and POGR");
+    info!(target: "game::netcode", "console only");
+    warn!(target: "game::netcode", "console and POGR");
+
+    guard.flush().await;
+    m_logs.assert();
+    assert_eq!(console_events.load(Ordering::SeqCst), 3);
+}
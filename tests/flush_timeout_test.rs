@@ -0,0 +1,101 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{PausePolicy, PogrAppender, PogrLayer, SinkMode};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tracing::error;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service whose logs endpoint accepts connections but never answers, and
+// return an appender for it.
+async fn unresponsive_service() -> (mockito::ServerGuard, PogrAppender) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    // Hold every accepted connection open without reading or answering.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let logs_url = format!("http://{}/v1/intake/logs", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((connection, _)) = listener.accept().await {
+            connections.push(connection);
+        }
+    });
+
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), Some(logs_url)).await;
+    (mock_server, appender)
+}
+
+// Verify that a flush gives up after the timeout instead of waiting for an unresponsive
+// intake, and that with the `Drop` policy the records go to the fallback sinks.
+#[tokio::test]
+async fn test_flush_timeout_drop() {
+    let (_mock_server, appender) = unresponsive_service().await;
+    let sink = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender)
+        .with_pause_policy(PausePolicy::Drop)
+        .with_sink(Arc::clone(&sink), SinkMode::Fallback);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("Match server unreachable");
+    error!("Lobby closed");
+
+    let started = Instant::now();
+    let timed_out = guard.flush_timeout(Duration::from_millis(300)).await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(timed_out.left_behind(), 2);
+    assert_eq!(timed_out.policy(), PausePolicy::Drop);
+
+    // The abandoned submissions complete right away, through the fallback sinks.
+    tokio::time::timeout(Duration::from_secs(5), guard.flush()).await.unwrap();
+    let mut messages = sink.0.lock().unwrap().clone();
+    messages.sort();
+    assert_eq!(messages, ["Lobby closed", "Match server unreachable"]);
+}
+
+// Verify that with the `Spool` policy a timed out flush reports the records left behind,
+// and keeps them for delivery.
+#[tokio::test]
+async fn test_flush_timeout_spool() {
+    let (_mock_server, appender) = unresponsive_service().await;
+    let sink = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender).with_sink(Arc::clone(&sink), SinkMode::Fallback);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("Match server unreachable");
+
+    let timed_out = guard.flush_timeout(Duration::from_millis(200)).await.unwrap_err();
+    assert_eq!(timed_out.left_behind(), 1);
+    assert_eq!(timed_out.policy(), PausePolicy::Spool);
+    assert_eq!(timed_out.to_string(), "flush timed out with 1 records left behind");
+    assert!(sink.0.lock().unwrap().is_empty());
+}
+
+// Verify that a flush within the timeout succeeds.
+#[tokio::test]
+async fn test_flush_timeout_idle() {
+    let (_mock_server, appender) = unresponsive_service().await;
+    let guard = PogrLayer::new(appender).guard();
+    assert!(guard.flush_timeout(Duration::from_millis(100)).await.is_ok());
+}
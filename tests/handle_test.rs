@@ -0,0 +1,56 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::LogRequest;
+use serde_json::json;
+use std::thread;
+use tracing::Level;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that a handle queues events from synchronous threads, with their fields and the
+// location of the call, and returns their event IDs right away.
+#[tokio::test]
+async fn test_handle_event() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let handle = layer.handle();
+    let guard = layer.guard();
+    let _subscriber = Registry::default().with(layer);
+
+    let ticker = handle.clone();
+    let event_id = thread::spawn(move || ticker.event(Level::WARN, "Tick overran", json!({ "tick": 48213, "ms": 21.4 })))
+        .join()
+        .unwrap();
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["severity"], "WARN");
+    assert_eq!(records[0]["tags"]["message"], "Tick overran");
+    assert_eq!(records[0]["tags"]["tick"], 48213);
+    assert_eq!(records[0]["data"]["file"], file!());
+    assert!(!event_id.is_nil());
+}
+
+// Verify that a handle queues prepared log requests as they are.
+#[tokio::test]
+async fn test_handle_log() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let handle = layer.handle();
+    let guard = layer.guard();
+
+    handle.log(LogRequest {
+        service: "matchmaker".to_string(),
+        environment: "production".to_string(),
+        severity: "ERROR".to_string(),
+        r#type: "server".to_string(),
+        log: "queue stalled".to_string(),
+        data: json!({ "queue": "ranked" }),
+        tags: json!({}),
+    });
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records[0]["log"], "queue stalled");
+    assert_eq!(records[0]["service"], "matchmaker");
+    assert_eq!(records[0]["data"]["queue"], "ranked");
+}
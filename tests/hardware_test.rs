@@ -0,0 +1,49 @@
+#![cfg(feature = "client-telemetry")]
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{HardwareInfo, PogrAppender};
+use std::sync::{Arc, Mutex};
+
+// Verify that the hardware is detected once, with at least the core count known.
+#[test]
+fn test_detect() {
+    let hardware = HardwareInfo::detect();
+    assert!(std::ptr::eq(hardware, HardwareInfo::detect()));
+    assert!(hardware.cpu_cores().unwrap() > 0);
+    if cfg!(target_os = "linux") {
+        assert!(hardware.cpu_model().is_some());
+        assert!(hardware.memory_bytes().unwrap() > 0);
+    }
+    for gpu in hardware.gpus() {
+        assert!(gpu.model().is_some() || gpu.vendor().is_some());
+    }
+}
+
+// Verify that the init request describes the hardware.
+#[tokio::test]
+async fn test_init_request_describes_hardware() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mut mock_server = mockito::Server::new();
+    let init_endpoint = format!("{}/v1/intake/init", mock_server.url().trim_end_matches('/'));
+    let captured = Arc::new(Mutex::new(None));
+    let body = Arc::clone(&captured);
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            *body.lock().unwrap() = Some(serde_json::from_slice::<serde_json::Value>(request.body().unwrap()).unwrap());
+            serde_json::json!({
+                "success": true,
+                "payload": { "session_id": "test_session_id" }
+            }).to_string().into_bytes()
+        })
+        .create();
+
+    PogrAppender::new(Some(init_endpoint), None).await;
+
+    let body = captured.lock().unwrap().take().unwrap();
+    assert_eq!(body["hardware"], serde_json::to_value(HardwareInfo::detect()).unwrap());
+    assert!(body["hardware"]["cpu_cores"].as_u64().unwrap() > 0);
+}
@@ -0,0 +1,103 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{CheckOutcome, PogrAppender};
+use std::net::TcpListener;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Verify that a reachable service with a valid session is reported healthy.
+#[tokio::test]
+async fn test_health_check_healthy() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // The session is checked with an empty batch.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body("[]")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let health = appender.health_check().await;
+    m_logs.assert();
+
+    assert!(health.is_healthy(), "{}", health);
+    assert_eq!(health.init.dns, CheckOutcome::Passed);
+    assert_eq!(health.logs.connection, CheckOutcome::Passed);
+    assert!(health.logs.latency.is_some());
+    assert_eq!(health.session, CheckOutcome::Passed);
+}
+
+// Verify that a session the intake no longer accepts makes the check fail.
+#[tokio::test]
+async fn test_health_check_rejected_session() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(401)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": false,
+            "payload": { "error": "unknown session" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let health = appender.health_check().await;
+    m_logs.assert();
+
+    // The endpoints are reachable, only the session is not accepted.
+    assert!(!health.is_healthy());
+    assert!(health.init.is_healthy());
+    assert!(health.logs.is_healthy());
+    assert!(matches!(&health.session, CheckOutcome::Failed(reason) if reason.contains("unknown session")), "{}", health);
+}
+
+// Verify that an unreachable logs endpoint is reported, and the session check skipped.
+#[tokio::test]
+async fn test_health_check_unreachable() {
+    let (_mock_server, init_endpoint, _) = mock_service();
+
+    // Reserve a port and close it again so that nothing is listening on it.
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let logs_endpoint = format!("http://127.0.0.1:{}/v1/intake/logs", port);
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let health = appender.health_check().await;
+
+    assert!(!health.is_healthy());
+    assert!(health.init.is_healthy());
+    assert_eq!(health.logs.dns, CheckOutcome::Passed);
+    assert!(matches!(health.logs.connection, CheckOutcome::Failed(_)), "{}", health);
+    assert!(health.logs.latency.is_none());
+    assert_eq!(health.session, CheckOutcome::Skipped);
+}
@@ -0,0 +1,187 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{Hosting, PogrAppender, PogrLayer};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::Mutex;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Serializes the tests, since each of them changes the hosting environment variables.
+static ENV: Mutex<()> = Mutex::const_new(());
+
+// Set environment variables, removing the ones given as `None`.
+fn set_env(vars: &[(&str, Option<&str>)]) {
+    for (name, value) in vars {
+        match value {
+            Some(value) => std::env::set_var(name, value),
+            None => std::env::remove_var(name),
+        }
+    }
+}
+
+// Simulate a Kubernetes pod in the `staging` namespace.
+fn set_kubernetes_env() {
+    set_env(&[
+        ("POGR_DETECT_HOSTING", None),
+        ("ENVIRONMENT", None),
+        ("KUBERNETES_SERVICE_HOST", Some("10.96.0.1")),
+        ("POD_NAMESPACE", Some("staging")),
+        ("HOSTNAME", Some("matchmaking-7d9f8-x2k4q")),
+    ]);
+}
+
+// Start a mock POGR service that records the bodies of init and log requests.
+fn mock_service() -> (mockito::ServerGuard, String, String, Arc<StdMutex<Vec<serde_json::Value>>>) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    let bodies = Arc::new(StdMutex::new(Vec::new()));
+    let init_bodies = Arc::clone(&bodies);
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            init_bodies.lock().unwrap().push(serde_json::from_slice(request.body().unwrap()).unwrap());
+            serde_json::json!({
+                "success": true,
+                "payload": { "session_id": "test_session_id" }
+            }).to_string().into()
+        })
+        .create();
+
+    let log_bodies = Arc::clone(&bodies);
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            log_bodies.lock().unwrap().push(serde_json::from_slice(request.body().unwrap()).unwrap());
+            serde_json::json!({
+                "success": true,
+                "payload": { "log_id": "test_log_id" }
+            }).to_string().into()
+        })
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint, bodies)
+}
+
+// Verify that orchestrators are recognized from the variables they inject.
+#[test]
+fn test_detect() {
+    let _env = ENV.blocking_lock();
+
+    set_env(&[("KUBERNETES_SERVICE_HOST", None), ("ECS_CONTAINER_METADATA_URI_V4", Some("http://169.254.170.2/v4/abc"))]);
+    assert_eq!(Hosting::detect(), Hosting::Ecs);
+
+    // Kubernetes takes precedence.
+    set_env(&[("KUBERNETES_SERVICE_HOST", Some("10.96.0.1"))]);
+    assert_eq!(Hosting::detect(), Hosting::Kubernetes);
+    assert_eq!(Hosting::detect().as_str(), "kubernetes");
+
+    set_env(&[("KUBERNETES_SERVICE_HOST", None), ("ECS_CONTAINER_METADATA_URI_V4", None)]);
+    assert!(matches!(Hosting::detect(), Hosting::Docker | Hosting::BareMetal));
+}
+
+// Verify that the detected hosting sets the environment and tags the session and events.
+#[tokio::test]
+async fn test_kubernetes_detection() {
+    let _env = ENV.lock().await;
+    set_kubernetes_env();
+    let (_mock_server, init_endpoint, logs_endpoint, bodies) = mock_service();
+
+    let appender = PogrAppender::builder()
+        .with_init_endpoint(init_endpoint)
+        .with_logs_endpoint(logs_endpoint)
+        .build()
+        .await;
+    // The pod's namespace is used as the environment.
+    assert_eq!(appender.environment, "staging");
+
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Queue opened");
+    guard.flush().await;
+
+    let bodies = bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0]["environment"], "staging");
+    assert_eq!(bodies[0]["hosting"], "kubernetes");
+    assert_eq!(bodies[1]["environment"], "staging");
+    assert_eq!(bodies[1]["tags"]["hosting"], "kubernetes");
+    assert_eq!(bodies[1]["tags"]["k8s_namespace"], "staging");
+    assert_eq!(bodies[1]["tags"]["k8s_pod"], "matchmaking-7d9f8-x2k4q");
+}
+
+// Verify that an explicit `ENVIRONMENT` is kept.
+#[tokio::test]
+async fn test_explicit_environment() {
+    let _env = ENV.lock().await;
+    set_kubernetes_env();
+    set_env(&[("ENVIRONMENT", Some("production")), ("POGR_ACCESS", Some("test_access_key")), ("POGR_SECRET", Some("test_secret_key"))]);
+    let mut mock_server = mockito::Server::new();
+    let init_endpoint = format!("{}/v1/intake/init", mock_server.url().trim_end_matches('/'));
+
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "environment": "production",
+            "hosting": "kubernetes"
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), None).await;
+    m_init.assert();
+    assert_eq!(appender.environment, "production");
+    set_env(&[("ENVIRONMENT", None)]);
+}
+
+// Verify that detection can be turned off in code and through the environment.
+#[tokio::test]
+async fn test_detection_off() {
+    let _env = ENV.lock().await;
+    set_kubernetes_env();
+    let (_mock_server, init_endpoint, logs_endpoint, bodies) = mock_service();
+
+    let appender = PogrAppender::builder()
+        .with_init_endpoint(init_endpoint.clone())
+        .with_logs_endpoint(logs_endpoint)
+        .with_hosting_detection(false)
+        .build()
+        .await;
+    assert_eq!(appender.environment, "development");
+
+    let layer = PogrLayer::new(appender).with_hosting_detection(false);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Queue opened");
+    guard.flush().await;
+
+    // The environment variable turns detection off everywhere.
+    set_env(&[("POGR_DETECT_HOSTING", Some("0"))]);
+    let appender = PogrAppender::new(Some(init_endpoint), None).await;
+    assert_eq!(appender.environment, "development");
+    set_env(&[("POGR_DETECT_HOSTING", None)]);
+
+    let bodies = bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 3);
+    assert!(bodies[0].get("hosting").is_none(), "{}", bodies[0]);
+    assert!(bodies[1]["tags"].get("hosting").is_none(), "{}", bodies[1]);
+    assert!(bodies[2].get("hosting").is_none(), "{}", bodies[2]);
+}
@@ -0,0 +1,165 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{BatchConfig, HttpConfig, HttpVersion, PogrAppender, PogrLayer};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Build an appender submitting to the given logs endpoint.
+fn appender(logs_endpoint: String) -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_http2".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint,
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Start an HTTP/1.1 intake that takes 100 ms per submission and records the highest number
+// of submissions it was handling at the same time.
+async fn slow_intake() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let logs_endpoint = format!("http://{}/v1/intake/logs", listener.local_addr().unwrap());
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+    let max = Arc::clone(&max_in_flight);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let in_flight = Arc::clone(&in_flight);
+            let max = Arc::clone(&max);
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                loop {
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        if line == "\r\n" {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    stream.read_exact(&mut body).await.unwrap();
+
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    let response_body = r#"{"success":true,"payload":{"log_id":"test_log_id"}}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                        response_body.len(),
+                        response_body
+                    );
+                    stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+
+    (logs_endpoint, max_in_flight)
+}
+
+// Log four events, each in its own batch, and return how many submissions overlapped.
+async fn max_in_flight_for(batching: BatchConfig) -> usize {
+    let (logs_endpoint, max_in_flight) = slow_intake().await;
+    let layer = PogrLayer::new(appender(logs_endpoint)).with_batching(batching);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for attempt in 0..4 {
+        info!(attempt, "Submitted in its own request");
+    }
+    guard.flush().await;
+
+    max_in_flight.load(Ordering::SeqCst)
+}
+
+// Verify that batches are submitted one at a time by default.
+#[tokio::test]
+async fn test_submissions_sequential_by_default() {
+    assert_eq!(max_in_flight_for(BatchConfig::new()).await, 1);
+}
+
+// Verify that batches are submitted concurrently up to the configured limit.
+#[tokio::test]
+async fn test_max_concurrent_requests() {
+    assert_eq!(max_in_flight_for(BatchConfig::new().with_max_concurrent_requests(4)).await, 4);
+    assert_eq!(max_in_flight_for(BatchConfig::new().with_max_concurrent_requests(2)).await, 2);
+}
+
+// Verify that with prior knowledge the client starts HTTP/2 right away, even over `http`.
+#[tokio::test]
+async fn test_http2_prior_knowledge_preface() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let logs_endpoint = format!("http://{}/v1/intake/logs", listener.local_addr().unwrap());
+
+    // Capture the first bytes the client sends, then hang up.
+    let preface = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut preface = [0; 24];
+        stream.read_exact(&mut preface).await.unwrap();
+        preface
+    });
+
+    let layer = PogrLayer::new(appender(logs_endpoint))
+        .with_http(HttpConfig::new().with_version(HttpVersion::Http2PriorKnowledge));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Submitted over HTTP/2");
+    guard.flush().await;
+
+    assert_eq!(&preface.await.unwrap(), b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+}
+
+// Verify that concurrent submissions over HTTP/2 are delivered.
+#[tokio::test]
+async fn test_http2_concurrent_delivery() {
+    let mut mock_server = mockito::Server::new();
+    let logs_endpoint = format!("{}/v1/intake/logs", mock_server.url().trim_end_matches('/'));
+
+    // The mock server detects the HTTP/2 preface and serves the streams.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(8)
+        .create();
+
+    let layer = PogrLayer::new(appender(logs_endpoint))
+        .with_http(HttpConfig::new().with_version(HttpVersion::Http2PriorKnowledge))
+        .with_batching(BatchConfig::new().with_max_concurrent_requests(4));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for attempt in 0..8 {
+        info!(attempt, "Submitted over HTTP/2");
+    }
+    guard.flush().await;
+
+    m_logs.assert();
+}
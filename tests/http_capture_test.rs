@@ -0,0 +1,121 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{record_http_traffic, stop_http_recording, HttpCapture, LogRequest, PogrAppender, ReplayOutcome};
+
+// Start a mock POGR service that initializes sessions with `session_id` and accepts logs.
+fn mock_service(session_id: &str) -> mockito::ServerGuard {
+    let mut mock_server = mockito::Server::new();
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": session_id }
+        }).to_string())
+        .create();
+    mock_server
+}
+
+// Mock a successful logs submission in `session_id`, expected once.
+fn mock_logs(mock_server: &mut mockito::ServerGuard, session_id: &str) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", session_id)
+        .match_body(Matcher::PartialJson(serde_json::json!({ "log": "Match server crashed" })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create()
+}
+
+// Initialize a session with a mock POGR service.
+async fn connect(mock_server: &mockito::ServerGuard) -> PogrAppender {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+    PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await
+}
+
+// Verify that recorded traffic is sanitized, and replayed against another service in the
+// session of the replaying appender.
+#[tokio::test]
+async fn test_record_and_replay() {
+    let path = std::env::temp_dir().join(format!("pogr-http-capture-{}.har.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    // Record a session and a log submission against the production service.
+    let mut production = mock_service("production_session");
+    let m_production = mock_logs(&mut production, "production_session");
+    record_http_traffic(&path).unwrap();
+    let appender = connect(&production).await;
+    appender.log(LogRequest {
+        service: "TestService".to_string(),
+        environment: "test".to_string(),
+        severity: "ERROR".to_string(),
+        r#type: "TestType".to_string(),
+        log: "Match server crashed".to_string(),
+        data: serde_json::json!({}),
+        tags: serde_json::json!({}),
+    })
+    .await;
+    stop_http_recording();
+    m_production.assert();
+
+    // Credentials and session IDs are masked.
+    let recording = std::fs::read_to_string(&path).unwrap();
+    let entries: Vec<serde_json::Value> = recording.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(entries.len(), 2);
+    let header = |entry: &serde_json::Value, name: &str| {
+        entry["request"]["headers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|header| header["name"] == name)
+            .map(|header| header["value"].as_str().unwrap().to_string())
+    };
+    assert_eq!(header(&entries[0], "pogr_secret").as_deref(), Some("****"));
+    assert_eq!(header(&entries[0], "pogr_access").as_deref(), Some("test****"));
+    assert_eq!(entries[0]["response"]["status"], 200);
+    assert!(entries[0]["response"]["content"]["text"].as_str().unwrap().contains(r#""session_id":"prod****""#));
+    assert_eq!(header(&entries[1], "intake_session_id").as_deref(), Some("prod****"));
+    assert!(entries[1]["request"]["postData"]["text"].as_str().unwrap().contains("Match server crashed"));
+    assert!(!recording.contains("test_secret_key"));
+
+    // Replay the capture against the staging service.
+    let mut staging = mock_service("staging_session");
+    let m_staging = mock_logs(&mut staging, "staging_session");
+    let staging_appender = connect(&staging).await;
+    let capture = HttpCapture::load(&path).unwrap();
+    assert_eq!(capture.len(), 2);
+    let replayed = capture.replay(&staging_appender, &staging.url()).await;
+
+    assert!(matches!(replayed[0].outcome(), ReplayOutcome::Skipped(_)));
+    assert!(!replayed[0].matches());
+    assert_eq!(replayed[1].method(), "POST");
+    assert_eq!(replayed[1].recorded_url(), format!("{}/v1/intake/logs", production.url()));
+    assert_eq!(replayed[1].url(), format!("{}/v1/intake/logs", staging.url()));
+    assert_eq!(replayed[1].recorded_status(), 200);
+    assert!(replayed[1].matches(), "{:?}", replayed[1].outcome());
+    m_staging.assert();
+
+    let _ = std::fs::remove_file(&path);
+}
+
+// Verify that a file that is not a recording is rejected with the offending line.
+#[test]
+fn test_load_invalid_capture() {
+    let path = std::env::temp_dir().join(format!("pogr-http-capture-invalid-{}.har.jsonl", std::process::id()));
+    std::fs::write(&path, "\nnot a HAR entry\n").unwrap();
+
+    let err = HttpCapture::load(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().starts_with("line 2 "), "{}", err);
+
+    let _ = std::fs::remove_file(&path);
+}
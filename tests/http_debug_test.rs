@@ -0,0 +1,97 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{set_http_debug, PogrAppender, PogrLayer};
+use std::process::Command;
+use tracing::info;
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Set in the child process that produces the dump.
+const CHILD_ENV: &str = "POGR_HTTP_DEBUG_TEST_CHILD";
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Produce an HTTP dump: initialize a session and submit a rejected log.
+//
+// Only does something in the child process started by `test_http_debug_dump`, since the dump
+// goes to stderr, which the test harness captures.
+#[tokio::test]
+async fn http_debug_child() {
+    if std::env::var(CHILD_ENV).is_err() {
+        return;
+    }
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Simulate the intake rejecting the log.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(400)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": false,
+            "payload": { "error": "missing field `severity`" }
+        }).to_string())
+        .create();
+
+    set_http_debug(true);
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!(player_id = 42, "debugging the intake");
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that debug mode dumps requests and responses with credentials masked.
+#[test]
+fn test_http_debug_dump() {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["http_debug_child", "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD_ENV, "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+
+    // The init request and its response, with credentials masked.
+    assert!(stderr.contains("pogr_tracing_rs: > POST http://"), "{}", stderr);
+    assert!(stderr.contains("/v1/intake/init"), "{}", stderr);
+    assert!(stderr.contains("pogr_tracing_rs: > pogr_access: test****"), "{}", stderr);
+    assert!(stderr.contains("pogr_tracing_rs: > pogr_secret: ****"), "{}", stderr);
+    assert!(stderr.contains("\"session_id\":\"test****\""), "{}", stderr);
+    assert!(!stderr.contains("test_secret_key"), "{}", stderr);
+    assert!(!stderr.contains("test_access_key"), "{}", stderr);
+    assert!(!stderr.contains("test_session_id"), "{}", stderr);
+
+    // The log request with its full body, and the raw rejection.
+    assert!(stderr.contains("pogr_tracing_rs: > intake_session_id: test****"), "{}", stderr);
+    assert!(stderr.contains("\"player_id\":42"), "{}", stderr);
+    assert!(stderr.contains("pogr_tracing_rs: < 400 Bad Request"), "{}", stderr);
+    assert!(stderr.contains("missing field `severity`"), "{}", stderr);
+}
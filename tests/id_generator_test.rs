@@ -0,0 +1,149 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{IdGenerator, IdKind, PogrAppender, PogrLayer, SequentialIdGenerator, UuidV4Generator, UuidV7Generator};
+use std::time::Duration;
+use tracing::info;
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+use uuid::Uuid;
+
+// Verify that the built-in generators produce the UUID versions they are named after.
+#[test]
+fn test_uuid_generators() {
+    let v7 = UuidV7Generator.generate(IdKind::Event);
+    assert_eq!(v7.get_version_num(), 7);
+    assert_ne!(v7, UuidV7Generator.generate(IdKind::Event));
+
+    let v4 = UuidV4Generator.generate(IdKind::Trace);
+    assert_eq!(v4.get_version_num(), 4);
+    assert_ne!(v4, UuidV4Generator.generate(IdKind::Trace));
+}
+
+// Verify that the sequential generator counts up from its start, across kinds.
+#[test]
+fn test_sequential_generator() {
+    let ids = SequentialIdGenerator::new(41);
+    assert_eq!(ids.generate(IdKind::Event), Uuid::from_u128(41));
+    assert_eq!(ids.generate(IdKind::IdempotencyKey), Uuid::from_u128(42));
+    assert_eq!(ids.generate(IdKind::Trace), Uuid::from_u128(43));
+}
+
+// Verify that the layer takes event IDs and idempotency keys from the injected generator.
+#[tokio::test]
+async fn test_pogr_layer_uses_injected_generator() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    let _m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    // The event is assigned the first ID at capture, and its request the second.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("Idempotency-Key", Uuid::from_u128(2).to_string().as_str())
+        .match_body(Matcher::PartialJson(serde_json::json!({ "event_id": Uuid::from_u128(1) })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_id_generator(SequentialIdGenerator::new(1));
+    let guard = layer.guard();
+
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("sequential event");
+
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Wait until a mock has received the requests it expects.
+async fn wait_for(mock: &mockito::Mock) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !mock.matched() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+// Verify that a request keeps its idempotency key when it is sent again after a pause.
+#[tokio::test]
+async fn test_idempotency_key_is_stable_across_retries() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    let _m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    // Ask to pause delivery on the first attempt; the retry must carry the same key.
+    let key = Uuid::from_u128(2).to_string();
+    let m_pause = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("Idempotency-Key", key.as_str())
+        .with_status(503)
+        .with_header("X-POGR-Pause", "1")
+        .with_body("Shedding load")
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_id_generator(SequentialIdGenerator::new(1));
+    let guard = layer.guard();
+
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("retried event");
+
+    wait_for(&m_pause).await;
+    m_pause.remove();
+    let m_success = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("Idempotency-Key", key.as_str())
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    wait_for(&m_success).await;
+    guard.flush().await;
+    m_success.assert();
+}
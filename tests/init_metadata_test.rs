@@ -0,0 +1,80 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::PogrAppender;
+
+// Start a mock POGR service and return it with its init endpoint.
+fn mock_service() -> (mockito::ServerGuard, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mock_server = mockito::Server::new();
+    let init_endpoint = format!("{}/v1/intake/init", mock_server.url().trim_end_matches('/'));
+    (mock_server, init_endpoint)
+}
+
+// The init response of a successful session.
+fn init_response() -> String {
+    serde_json::json!({
+        "success": true,
+        "payload": { "session_id": "test_session_id" }
+    }).to_string()
+}
+
+// Verify that the builder sends the build, platform and metadata in the init request.
+#[tokio::test]
+async fn test_builder_sends_session_metadata() {
+    let (mut mock_server, init_endpoint) = mock_service();
+
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .match_header("Content-Type", "application/json")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "sdk": "pogr_tracing_rs",
+            "sdk_version": env!("CARGO_PKG_VERSION"),
+            "build_id": "2024.03.1-4711",
+            "platform": "steam",
+            "metadata": { "region": "eu-west", "players": 4, "ranked": true }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(init_response())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::builder()
+        .with_init_endpoint(init_endpoint)
+        .with_build_id("2024.03.1-4711")
+        .with_platform("steam")
+        .with_session_metadata("region", "eu-west")
+        .with_session_metadata("players", 4)
+        .with_session_metadata("ranked", false)
+        // A later value replaces an earlier one.
+        .with_session_metadata("ranked", true)
+        .build()
+        .await;
+    m_init.assert();
+
+    assert_eq!(appender.session_id, "test_session_id");
+}
+
+// Verify that `new` describes the service, SDK and process, and leaves out unset metadata.
+#[tokio::test]
+async fn test_new_sends_service_details() {
+    let (mut mock_server, init_endpoint) = mock_service();
+    // Keep the body independent of whether the tests run in a container.
+    std::env::set_var("POGR_DETECT_HOSTING", "0");
+
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .match_body(Matcher::Regex(format!(
+            r#"^\{{"service":"[^"]+","environment":"[^"]+","type":"[^"]+","sdk":"pogr_tracing_rs","sdk_version":"{}","process":\{{"args":\[.*\],"os":"[^"]+",.*"arch":"[^"]+"\}}(,"hardware":\{{.*\}})?\}}$"#,
+            env!("CARGO_PKG_VERSION").replace('.', r"\.")
+        )))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(init_response())
+        .expect(1)
+        .create();
+
+    PogrAppender::new(Some(init_endpoint), None).await;
+    m_init.assert();
+}
@@ -0,0 +1,133 @@
+#![cfg(target_os = "linux")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{JournaldSink, PogrAppender, PogrLayer, SinkMode};
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+use tracing::{info, warn};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Bind a datagram socket standing in for journald.
+fn fake_journald(name: &str) -> (UnixDatagram, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("pogr_journald_test_{}_{}.sock", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    (socket, path)
+}
+
+// Receive one journal entry as text.
+fn receive_entry(socket: &UnixDatagram) -> String {
+    let mut buffer = vec![0u8; 64 * 1024];
+    let len = socket.recv(&mut buffer).expect("no journal entry received");
+    String::from_utf8_lossy(&buffer[..len]).into_owned()
+}
+
+// Verify that a fallback sink receives the records that could not be delivered.
+#[tokio::test]
+async fn test_journald_fallback() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let (journald, socket_path) = fake_journald("fallback");
+
+    // Simulate an intake outage.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_sink(JournaldSink::with_socket_path(&socket_path).unwrap(), SinkMode::Fallback);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    warn!(player_id = 42, region = "eu-west", "matchmaking timed out");
+    guard.flush().await;
+    m_logs.assert();
+
+    // The entry keeps every field as a separate journal field.
+    let entry = receive_entry(&journald);
+    assert!(entry.contains("MESSAGE=matchmaking timed out\n"), "{}", entry);
+    assert!(entry.contains("PRIORITY=4\n"), "{}", entry);
+    assert!(entry.contains("PLAYER_ID=42\n"), "{}", entry);
+    assert!(entry.contains("REGION=eu-west\n"), "{}", entry);
+    assert!(entry.contains("POGR_EVENT_ID="), "{}", entry);
+    assert!(entry.contains("CODE_FILE=tests/journald_test.rs\n"), "{}", entry);
+
+    let _ = std::fs::remove_file(&socket_path);
+}
+
+// Verify that a mirror sink receives records that were delivered successfully, and that a
+// fallback sink does not.
+#[tokio::test]
+async fn test_journald_mirror() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let (mirror, mirror_path) = fake_journald("mirror");
+    let (fallback, fallback_path) = fake_journald("mirror_fallback");
+
+    // Mock a successful log submission.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_sink(JournaldSink::with_socket_path(&mirror_path).unwrap(), SinkMode::Mirror)
+        .with_sink(JournaldSink::with_socket_path(&fallback_path).unwrap(), SinkMode::Fallback);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("multi-line\nmessage");
+    guard.flush().await;
+    m_logs.assert();
+
+    // Values with newlines use the length-prefixed form.
+    let entry = receive_entry(&mirror);
+    assert!(entry.contains("MESSAGE\n\u{12}\0\0\0\0\0\0\0multi-line\nmessage\n"), "{:?}", entry);
+    assert!(entry.contains("PRIORITY=6\n"), "{}", entry);
+
+    fallback.set_nonblocking(true).unwrap();
+    assert!(fallback.recv(&mut [0u8; 16]).is_err());
+
+    let _ = std::fs::remove_file(&mirror_path);
+    let _ = std::fs::remove_file(&fallback_path);
+}
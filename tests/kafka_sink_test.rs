@@ -0,0 +1,12 @@
+#![cfg(feature = "kafka")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::KafkaSink;
+
+// Verify that creating a sink fails cleanly when no broker is reachable.
+#[test]
+fn test_unreachable_broker() {
+    // Nothing listens on port 1, so fetching the cluster metadata fails.
+    let result = KafkaSink::new(vec!["127.0.0.1:1".to_string()], "game-telemetry");
+    assert!(result.is_err());
+}
@@ -0,0 +1,79 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{LocaleInfo, PogrAppender};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Serializes the tests, which change the locale variables of the process.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// Verify that the locale is read from the environment, and that `C` is no locale.
+#[test]
+fn test_detect_locale() {
+    let _env = ENV_LOCK.blocking_lock();
+    std::env::set_var("LC_ALL", "pt_BR.UTF-8");
+    std::env::set_var("TZ", "UTC");
+    let locale = LocaleInfo::detect();
+    assert_eq!(locale.locale(), Some("pt-BR"));
+    assert_eq!(locale.region(), Some("BR"));
+    assert_eq!(locale.timezone(), Some("UTC"));
+    assert_eq!(locale.utc_offset_minutes(), Some(0));
+    assert_eq!(locale.utc_offset().as_deref(), Some("+00:00"));
+
+    std::env::set_var("LC_ALL", "C");
+    std::env::set_var("LANG", "es_419.UTF-8");
+    assert_eq!(LocaleInfo::detect().locale(), None);
+    std::env::remove_var("LC_ALL");
+    std::env::remove_var("LC_MESSAGES");
+    let locale = LocaleInfo::detect();
+    assert_eq!(locale.locale(), Some("es-419"));
+    assert_eq!(locale.region(), Some("419"));
+
+    // A POSIX timezone string is passed to `date` as is; `XYZ-5:30` is 5h30 east of UTC.
+    if cfg!(unix) {
+        std::env::set_var("TZ", "XYZ-5:30");
+        assert_eq!(LocaleInfo::detect().utc_offset().as_deref(), Some("+05:30"));
+    }
+}
+
+// Verify that the layer tags events with the detected locale, and the builder the session.
+#[tokio::test]
+async fn test_locale_tags_and_session() {
+    let _env = ENV_LOCK.lock().await;
+    std::env::set_var("LC_ALL", "pt_BR.UTF-8");
+    std::env::set_var("TZ", "UTC");
+    let (mut mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_locale_detection();
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Shop opened");
+    guard.flush().await;
+
+    let tags = sink.0.lock().unwrap()[0]["tags"].clone();
+    assert_eq!(tags["locale"], "pt-BR");
+    assert_eq!(tags["region"], "BR");
+    assert_eq!(tags["timezone"], "UTC");
+    assert_eq!(tags["utc_offset"], "+00:00");
+
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "metadata": { "locale": "pt-BR", "region": "BR", "timezone": "UTC", "utc_offset": "+00:00" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+    PogrAppender::builder()
+        .with_init_endpoint(format!("{}/v1/intake/init", mock_server.url().trim_end_matches('/')))
+        .with_locale_detection()
+        .build()
+        .await;
+    m_init.assert();
+}
@@ -0,0 +1,96 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{span_log_ids, PogrAppender, PogrLayer};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{error, info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service whose logs endpoint assigns `log-1`, `log-2`, ... in order.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    // Number the accepted submissions.
+    let submissions = AtomicUsize::new(0);
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |_| {
+            let log_id = format!("log-{}", submissions.fetch_add(1, Ordering::SeqCst) + 1);
+            serde_json::json!({
+                "success": true,
+                "payload": { "log_id": log_id }
+            }).to_string().into()
+        })
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Verify that the log IDs returned by the intake can be looked up from the originating spans.
+#[tokio::test]
+async fn test_span_log_ids() {
+    let (_mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_log_id_tracking(true);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let checkout = info_span!("checkout");
+    let refund = info_span!("refund");
+
+    // Flush after each event so the log IDs are assigned in a known order.
+    checkout.in_scope(|| info!("Cart submitted"));
+    guard.flush().await;
+    refund.in_scope(|| error!("Refund failed"));
+    guard.flush().await;
+    checkout.in_scope(|| error!("Payment declined"));
+    guard.flush().await;
+
+    // Each span only holds the IDs of the events recorded inside it, oldest first.
+    let checkout_ids = span_log_ids(&checkout);
+    let log_ids: Vec<&str> = checkout_ids.iter().map(|reference| reference.log_id.as_str()).collect();
+    assert_eq!(log_ids, ["log-1", "log-3"]);
+    assert_ne!(checkout_ids[0].event_id, checkout_ids[1].event_id);
+    assert_eq!(checkout_ids[0].event_id.get_version_num(), 7);
+
+    let refund_ids = span_log_ids(&refund);
+    assert_eq!(refund_ids.len(), 1);
+    assert_eq!(refund_ids[0].log_id, "log-2");
+}
+
+// Verify that log IDs are not kept unless tracking is enabled.
+#[tokio::test]
+async fn test_span_log_ids_disabled() {
+    let (_mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let checkout = info_span!("checkout");
+    checkout.in_scope(|| info!("Cart submitted"));
+    guard.flush().await;
+
+    assert!(span_log_ids(&checkout).is_empty());
+}
@@ -0,0 +1,105 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{PogrAppender, PogrLayer, SinkMode, TargetLevels, VerbosityProfile};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Serializes the tests that change `POGR_LOG_LEVEL`.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// Create an appender for a mock POGR service.
+async fn mock_appender() -> (mockito::ServerGuard, PogrAppender) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), None).await;
+    (mock_server, appender)
+}
+
+// Log one event per level on the default target, and one debug event on `netcode`.
+fn log_events() {
+    debug!("Tick took 3 ms");
+    info!("Player joined");
+    warn!("Ping above 200 ms");
+    debug!(target: "netcode", "Resent 2 packets");
+}
+
+// Verify that `POGR_LOG_LEVEL` is parsed with the directive syntax, and ignored when invalid.
+#[tokio::test]
+async fn test_log_level_from_env() {
+    let _lock = ENV_LOCK.lock().await;
+
+    std::env::set_var("POGR_LOG_LEVEL", "info,netcode=debug");
+    let levels = TargetLevels::from_env().unwrap();
+    assert_eq!(levels.default_level(), LevelFilter::INFO);
+    assert_eq!(levels.level_for("netcode::transport"), LevelFilter::DEBUG);
+
+    std::env::set_var("POGR_LOG_LEVEL", "info,netcode=loud");
+    assert_eq!(TargetLevels::from_env(), None);
+
+    std::env::remove_var("POGR_LOG_LEVEL");
+    assert_eq!(TargetLevels::from_env(), None);
+}
+
+// Verify that a new layer starts from the thresholds of `POGR_LOG_LEVEL`.
+#[tokio::test]
+async fn test_log_level_layer() {
+    let _lock = ENV_LOCK.lock().await;
+    let (_mock_server, appender) = mock_appender().await;
+
+    std::env::set_var("POGR_LOG_LEVEL", "warn,netcode=debug");
+    let sink = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror);
+    std::env::remove_var("POGR_LOG_LEVEL");
+
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+    log_events();
+    guard.flush().await;
+
+    assert_eq!(*sink.0.lock().unwrap(), ["Ping above 200 ms", "Resent 2 packets"]);
+}
+
+// Verify that `POGR_LOG_LEVEL` takes precedence over a verbosity profile, without sampling.
+#[tokio::test]
+async fn test_log_level_overrides_profile() {
+    let _lock = ENV_LOCK.lock().await;
+    let (_mock_server, appender) = mock_appender().await;
+
+    std::env::set_var("POGR_LOG_LEVEL", "debug");
+    let sink = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror)
+        .with_profile(VerbosityProfile::Production);
+    std::env::remove_var("POGR_LOG_LEVEL");
+
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+    log_events();
+    guard.flush().await;
+
+    assert_eq!(sink.0.lock().unwrap().len(), 4);
+}
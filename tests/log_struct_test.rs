@@ -0,0 +1,62 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::log_struct;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::Level;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// A value with nested structures and types that `Debug` formatting would flatten.
+#[derive(Serialize)]
+struct Inventory {
+    player_id: u64,
+    gold: f64,
+    premium: bool,
+    items: BTreeMap<String, u32>,
+}
+
+// Verify that serializable values are logged into the record's data with their structure and
+// types, and the location of the call.
+#[tokio::test]
+async fn test_log_struct() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let inventory = Inventory {
+        player_id: 42,
+        gold: 12.5,
+        premium: true,
+        items: BTreeMap::from([("potion".to_string(), 3), ("sword".to_string(), 1)]),
+    };
+    log_struct(Level::WARN, "inventory_sync", &inventory);
+    let line = line!() - 1;
+    log_struct(Level::DEBUG, "leaderboard", &[1, 2, 3]);
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 2);
+
+    let synced = &records[0];
+    assert_eq!(synced["severity"], "WARN");
+    assert_eq!(synced["tags"]["message"], "inventory_sync");
+    assert!(synced["tags"].as_object().unwrap().keys().all(|key| !key.starts_with("pogr.")));
+    assert_eq!(
+        synced["data"]["struct"],
+        serde_json::json!({
+            "player_id": 42,
+            "gold": 12.5,
+            "premium": true,
+            "items": { "potion": 3, "sword": 1 },
+        })
+    );
+    assert_eq!(synced["data"]["name"], "inventory_sync");
+    assert_eq!(synced["data"]["target"], "pogr_struct");
+    assert!(synced["data"]["file"].as_str().unwrap().ends_with("log_struct_test.rs"));
+    assert_eq!(synced["data"]["line"], line);
+
+    assert_eq!(records[1]["severity"], "DEBUG");
+    assert_eq!(records[1]["data"]["struct"], serde_json::json!([1, 2, 3]));
+}
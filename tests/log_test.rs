@@ -55,3 +55,55 @@ async fn test_pogr_appender_log() {
     // The function is awaited to ensure the asynchronous operation completes.
     appender.log(log_request).await;
 }
+
+// Verify that each submitted log message carries a client-generated UUIDv7 that is returned to the caller.
+#[tokio::test]
+async fn test_pogr_appender_log_event_id() {
+    // Initialize a mock server to simulate the log endpoint.
+    let mut mock_server = mockito::Server::new();
+    let logs_endpoint = format!("{}/v1/intake/logs", mock_server.url().trim_end_matches('/'));
+
+    // Only accept payloads that include a version 7 UUID as the `event_id`.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(mockito::Matcher::Regex(
+            r#""event_id":"[0-9a-f]{8}-[0-9a-f]{4}-7[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}""#.to_string(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(2)
+        .create();
+
+    // Initialize the `PogrAppender` with the mock server's logs endpoint and a predefined session ID.
+    let appender = PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_pogr_appender_log_event_id".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint,
+        init_endpoint: "".to_string(),
+    };
+
+    // Builds a simple log request.
+    let log_request = || LogRequest {
+        service: "TestService".to_string(),
+        environment: "test".to_string(),
+        severity: "INFO".to_string(),
+        r#type: "TestType".to_string(),
+        log: "This is a test log".to_string(),
+        data: serde_json::json!({}),
+        tags: serde_json::json!({}),
+    };
+
+    // Every submission gets its own identifier.
+    let first = appender.log(log_request()).await;
+    let second = appender.log(log_request()).await;
+
+    assert_eq!(first.get_version_num(), 7);
+    assert_ne!(first, second);
+    m_logs.assert();
+}
@@ -0,0 +1,104 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{PogrAppender, PogrLayer, SinkMode, SERIALIZATION_ERRORS_FIELD};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// A value whose `Debug` implementation fails halfway through.
+struct FailingDebug;
+
+impl fmt::Debug for FailingDebug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Inventory { slots: ")?;
+        Err(fmt::Error)
+    }
+}
+
+// A value whose `Debug` implementation panics.
+struct PanickingDebug;
+
+impl fmt::Debug for PanickingDebug {
+    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        panic!("inventory already dropped")
+    }
+}
+
+// Build an appender that is never submitted to.
+fn appender() -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_lossy".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint: "http://127.0.0.1:9/v1/intake/logs".to_string(),
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Record one event with `emit`, and return the fields the sink received.
+async fn record(emit: impl FnOnce()) -> Value {
+    let sink = Arc::new(CollectingSink::tags());
+    let layer = PogrLayer::new(appender())
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    emit();
+    guard.flush().await;
+    let mut records = sink.0.lock().unwrap().clone();
+    assert_eq!(records.len(), 1);
+    records.remove(0)
+}
+
+// Verify that non-finite numbers are recorded as strings, with a note, and finite ones as is.
+#[tokio::test]
+async fn test_non_finite_numbers() {
+    let fields = record(|| info!(ratio = f64::NAN, speed = f64::INFINITY, health = 0.5, "frame stats")).await;
+    assert_eq!(fields["ratio"], json!("NaN"));
+    assert_eq!(fields["speed"], json!("inf"));
+    assert_eq!(fields["health"], json!(0.5));
+    assert_eq!(
+        fields[SERIALIZATION_ERRORS_FIELD],
+        json!({ "ratio": "non-finite number", "speed": "non-finite number" })
+    );
+}
+
+// Verify that the output of a failing `Debug` implementation is kept up to the failure.
+#[tokio::test]
+async fn test_failing_debug() {
+    let fields = record(|| info!(inventory = ?FailingDebug, "inventory saved")).await;
+    assert_eq!(fields["inventory"], json!("Inventory { slots: "));
+    assert_eq!(
+        fields[SERIALIZATION_ERRORS_FIELD],
+        json!({ "inventory": "the Debug implementation returned an error" })
+    );
+    assert_eq!(fields["message"], json!("inventory saved"));
+}
+
+// Verify that a panicking `Debug` implementation neither loses the event nor panics.
+#[tokio::test]
+async fn test_panicking_debug() {
+    let fields = record(|| info!(inventory = ?PanickingDebug, "inventory saved")).await;
+    assert_eq!(fields["inventory"], json!("<unformattable>"));
+    assert_eq!(
+        fields[SERIALIZATION_ERRORS_FIELD],
+        json!({ "inventory": "the Debug implementation panicked: inventory already dropped" })
+    );
+}
+
+// Verify that events without degraded values have no note.
+#[tokio::test]
+async fn test_no_note_without_errors() {
+    let fields = record(|| info!(health = 0.5, inventory = ?vec![1, 2], "frame stats")).await;
+    assert_eq!(fields["inventory"], json!("[1, 2]"));
+    assert!(fields.get(SERIALIZATION_ERRORS_FIELD).is_none());
+}
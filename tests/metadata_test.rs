@@ -0,0 +1,85 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{set_client_metadata, PogrAppender, PogrLayer};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that init and log requests identify the client and carry its metadata.
+#[tokio::test]
+async fn test_user_agent_and_client_metadata() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Metadata is sent under lowercase `pogr-client-` headers; setting a key again replaces it.
+    set_client_metadata("Build", "1.0.0").unwrap();
+    set_client_metadata("build", "2024.03.1-4711").unwrap();
+    set_client_metadata("region", "eu west").unwrap();
+
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // The user agent names the crate, its version, the OS and the architecture.
+    let user_agent = format!(
+        "pogr_tracing_rs/{} ({}; {})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .match_header("user-agent", user_agent.as_str())
+        .match_header("pogr-client-build", "2024.03.1-4711")
+        .match_header("pogr-client-region", "eu west")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("user-agent", user_agent.as_str())
+        .match_header("pogr-client-build", "2024.03.1-4711")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    // Only one value per key is sent.
+    let m_duplicate = mock_server.mock("POST", Matcher::Any)
+        .match_header("pogr-client-build", "1.0.0")
+        .expect(0)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Identified");
+    guard.flush().await;
+
+    m_init.assert();
+    m_logs.assert();
+    m_duplicate.assert();
+}
+
+// Verify that metadata that cannot be sent in a header is rejected.
+#[test]
+fn test_invalid_client_metadata() {
+    assert!(set_client_metadata("", "value").is_err());
+    assert!(set_client_metadata("build number", "value").is_err());
+    assert!(set_client_metadata("build_number", "value").is_err());
+    let err = set_client_metadata("build", "line\nbreak").unwrap_err();
+    assert_eq!(err.to_string(), "invalid client metadata `build`: `line\nbreak`");
+}
@@ -0,0 +1,126 @@
+#![cfg(feature = "metrics")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use metrics::Unit;
+use pogr_tracing_rs::{PogrAppender, PogrRecorder};
+use std::sync::{Arc, Mutex};
+
+// Start a mock POGR service whose metrics intake records the reports it receives.
+async fn mock_service() -> (mockito::ServerGuard, PogrRecorder, Arc<Mutex<Vec<serde_json::Value>>>) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&reports);
+    mock_server.mock("POST", "/v1/intake/metrics")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            received.lock().unwrap().push(serde_json::from_slice(request.body().unwrap()).unwrap());
+            serde_json::json!({ "success": true, "payload": {} }).to_string().into()
+        })
+        .create();
+
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), None).await;
+    let recorder = PogrRecorder::new(appender).with_endpoint(format!("{}/v1/intake/metrics", base_url));
+    (mock_server, recorder, reports)
+}
+
+// Find a metric in a report by name.
+fn metric<'a>(report: &'a serde_json::Value, name: &str) -> &'a serde_json::Value {
+    report
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|metric| metric["name"] == name)
+        .unwrap_or_else(|| panic!("missing metric {}", name))
+}
+
+// Verify that counters, gauges and histograms recorded with the `metrics` macros are reported.
+#[tokio::test]
+async fn test_metrics_report() {
+    let (_mock_server, recorder, reports) = mock_service().await;
+    let handle = recorder.handle();
+
+    metrics::with_local_recorder(&recorder, || {
+        metrics::describe_histogram!("queue_time", Unit::Milliseconds, "Time spent in the matchmaking queue");
+        metrics::counter!("matches_started", "mode" => "ranked").increment(2);
+        metrics::counter!("matches_started", "mode" => "ranked").increment(1);
+        metrics::counter!("matches_started", "mode" => "casual").increment(5);
+        metrics::gauge!("players_online").set(120.0);
+        metrics::gauge!("players_online").decrement(20.0);
+        metrics::histogram!("queue_time").record(812.5);
+        metrics::histogram!("queue_time").record(90.0);
+    });
+    handle.flush().await;
+
+    {
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.as_array().unwrap().len(), 4);
+
+        let ranked = report
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|metric| metric["labels"]["mode"] == "ranked")
+            .unwrap();
+        assert_eq!(ranked["type"], "counter");
+        assert_eq!(ranked["value"], 3.0);
+
+        let players = metric(report, "players_online");
+        assert_eq!(players["type"], "gauge");
+        assert_eq!(players["value"], 100.0);
+
+        let queue_time = metric(report, "queue_time");
+        assert_eq!(queue_time["type"], "histogram");
+        assert_eq!(queue_time["values"], serde_json::json!([812.5, 90.0]));
+        assert_eq!(queue_time["unit"], "milliseconds");
+        assert_eq!(queue_time["description"], "Time spent in the matchmaking queue");
+        assert!(queue_time["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    // Histogram values are reported once; counters keep their running total.
+    metrics::with_local_recorder(&recorder, || {
+        metrics::counter!("matches_started", "mode" => "ranked").increment(1);
+    });
+    handle.flush().await;
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[1].as_array().unwrap().len(), 3);
+    assert!(reports[1].as_array().unwrap().iter().all(|metric| metric["name"] != "queue_time"));
+    let ranked = reports[1]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|metric| metric["labels"]["mode"] == "ranked")
+        .unwrap();
+    assert_eq!(ranked["value"], 4.0);
+}
+
+// Verify that nothing is sent while no metric was registered.
+#[tokio::test]
+async fn test_metrics_empty() {
+    let (_mock_server, recorder, reports) = mock_service().await;
+
+    recorder.handle().flush().await;
+    assert!(reports.lock().unwrap().is_empty());
+}
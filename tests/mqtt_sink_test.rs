@@ -0,0 +1,269 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{MqttQos, MqttSink, PogrAppender, PogrLayer, SinkMode};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Mock a successful log submission, expected `count` times.
+fn mock_logs(mock_server: &mut mockito::ServerGuard, count: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(count)
+        .create()
+}
+
+// A packet received by the fake broker.
+#[derive(Debug)]
+enum Received {
+    // The client identifier and user name of a CONNECT.
+    Connect(String, Option<String>),
+    // The topic, QoS and payload of a PUBLISH.
+    Publish(String, u8, String),
+    // The packet identifier of a PUBREL.
+    Release(u16),
+}
+
+// Read an MQTT packet as its first header byte and body.
+fn read_packet(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte).ok()?;
+    let header = byte[0];
+
+    let mut len = 0usize;
+    let mut shift = 0;
+    loop {
+        stream.read_exact(&mut byte).ok()?;
+        len |= ((byte[0] & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).ok()?;
+    Some((header, body))
+}
+
+// Read a length-prefixed string at `offset`, returning it and the offset after it.
+fn read_string(body: &[u8], offset: usize) -> (String, usize) {
+    let len = u16::from_be_bytes([body[offset], body[offset + 1]]) as usize;
+    let value = String::from_utf8(body[offset + 2..offset + 2 + len].to_vec()).unwrap();
+    (value, offset + 2 + len)
+}
+
+// Start a minimal MQTT broker that answers with `return_code` and reports what it receives.
+fn fake_broker(return_code: u8) -> (String, mpsc::Receiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        while let Some((header, body)) = read_packet(&mut stream) {
+            match header >> 4 {
+                // CONNECT: protocol name, level, flags and keep-alive, then the payload.
+                1 => {
+                    let (_, offset) = read_string(&body, 0);
+                    let flags = body[offset + 1];
+                    let (client_id, offset) = read_string(&body, offset + 4);
+                    let user = (flags & 0x80 != 0).then(|| read_string(&body, offset).0);
+                    let _ = sender.send(Received::Connect(client_id, user));
+                    stream.write_all(&[0x20, 2, 0, return_code]).unwrap();
+                }
+                // PUBLISH: acknowledge according to the QoS.
+                3 => {
+                    let qos = (header >> 1) & 0x03;
+                    let (topic, mut offset) = read_string(&body, 0);
+                    let mut packet_id = [0u8; 2];
+                    if qos > 0 {
+                        packet_id = [body[offset], body[offset + 1]];
+                        offset += 2;
+                    }
+                    let payload = String::from_utf8(body[offset..].to_vec()).unwrap();
+                    let _ = sender.send(Received::Publish(topic, qos, payload));
+                    match qos {
+                        1 => stream.write_all(&[0x40, 2, packet_id[0], packet_id[1]]).unwrap(),
+                        2 => stream.write_all(&[0x50, 2, packet_id[0], packet_id[1]]).unwrap(),
+                        _ => {}
+                    }
+                }
+                // PUBREL: complete the QoS 2 exchange.
+                6 => {
+                    let _ = sender.send(Received::Release(u16::from_be_bytes([body[0], body[1]])));
+                    stream.write_all(&[0x70, 2, body[0], body[1]]).unwrap();
+                }
+                _ => {}
+            }
+        }
+    });
+
+    (address, receiver)
+}
+
+// Verify that records are published with QoS 1 on topics rendered from the template.
+#[tokio::test]
+async fn test_mqtt_mirror() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let (address, received) = fake_broker(0);
+    let m_logs = mock_logs(&mut mock_server, 2);
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let mqtt = MqttSink::new(address, "arcade/{target}/{level}")
+        .with_client_id("cabinet-17")
+        .with_user_password("cabinet", "secret");
+    let layer = PogrLayer::new(appender).with_sink(mqtt, SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    warn!(target: "game::coins", credits = 3, "coin jam detected");
+    info!(target: "game::attract", "attract mode started");
+    guard.flush().await;
+    m_logs.assert();
+
+    let receive = || received.recv_timeout(Duration::from_secs(5)).expect("nothing received");
+
+    // The sink connects with the configured identity.
+    match receive() {
+        Received::Connect(client_id, user) => {
+            assert_eq!(client_id, "cabinet-17");
+            assert_eq!(user.as_deref(), Some("cabinet"));
+        }
+        other => panic!("expected CONNECT, got {:?}", other),
+    }
+
+    // Each record goes to the topic for its target and level.
+    match receive() {
+        Received::Publish(topic, qos, payload) => {
+            assert_eq!(topic, "arcade/game/coins/warn");
+            assert_eq!(qos, 1);
+            let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+            assert_eq!(payload["tags"]["credits"], 3);
+        }
+        other => panic!("expected PUBLISH, got {:?}", other),
+    }
+    match receive() {
+        Received::Publish(topic, _, _) => assert_eq!(topic, "arcade/game/attract/info"),
+        other => panic!("expected PUBLISH, got {:?}", other),
+    }
+}
+
+// Verify that QoS 2 completes the PUBREC/PUBREL/PUBCOMP exchange.
+#[tokio::test]
+async fn test_mqtt_exactly_once() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let (address, received) = fake_broker(0);
+    let m_logs = mock_logs(&mut mock_server, 1);
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let mqtt = MqttSink::new(address, "arcade/{level}").with_qos(MqttQos::ExactlyOnce);
+    let layer = PogrLayer::new(appender).with_sink(mqtt, SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("printer out of paper");
+    guard.flush().await;
+    m_logs.assert();
+
+    let receive = || received.recv_timeout(Duration::from_secs(5)).expect("nothing received");
+    assert!(matches!(receive(), Received::Connect(..)));
+    assert!(matches!(receive(), Received::Publish(topic, 2, _) if topic == "arcade/error"));
+    assert!(matches!(receive(), Received::Release(1)));
+}
+
+// Verify that a refused connection leaves intake delivery untouched.
+#[tokio::test]
+async fn test_mqtt_refused() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Return code 5: not authorized.
+    let (address, received) = fake_broker(5);
+    let m_logs = mock_logs(&mut mock_server, 1);
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_sink(MqttSink::new(address, "arcade/{level}"), SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("delivered despite the broken mirror");
+    guard.flush().await;
+    m_logs.assert();
+
+    // Nothing is published after the broker refuses the connection.
+    assert!(matches!(received.recv_timeout(Duration::from_secs(5)), Ok(Received::Connect(..))));
+    assert!(received.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+// Verify that a broker that never answers does not hold up delivery to POGR.
+#[tokio::test]
+async fn test_mqtt_unresponsive() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Accept the connection, then never send a CONNACK.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        let (_stream, _) = listener.accept().unwrap();
+        std::thread::sleep(Duration::from_secs(10));
+    });
+    let m_logs = mock_logs(&mut mock_server, 1);
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_sink(MqttSink::new(address, "arcade/{level}"), SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let started = Instant::now();
+    info!("delivered while the mirror hangs");
+    guard.flush().await;
+    m_logs.assert();
+    assert!(started.elapsed() < Duration::from_secs(2), "flush waited {:?}", started.elapsed());
+}
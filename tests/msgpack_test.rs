@@ -0,0 +1,118 @@
+#![cfg(feature = "msgpack")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{set_diagnostic_handler, BatchConfig, PayloadEncoding, PogrAppender, PogrLayer};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+    (mock_server, base_url)
+}
+
+// Create an appender for the mock service.
+async fn appender(base_url: &str) -> PogrAppender {
+    PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await
+}
+
+// Verify that a batch is submitted as MessagePack with the same structure as JSON.
+#[tokio::test]
+async fn test_msgpack_payload() {
+    let (mut mock_server, base_url) = mock_service();
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&bodies);
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("content-type", "application/msgpack")
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            captured.lock().unwrap().push(request.body().unwrap().clone());
+            br#"{"success": true, "payload": {"log_id": "test_log_id"}}"#.to_vec()
+        })
+        .expect(1)
+        .create();
+
+    let layer = PogrLayer::new(appender(&base_url).await)
+        .with_payload_encoding(PayloadEncoding::MessagePack)
+        .with_batching(BatchConfig::new().with_max_batch_size(10).with_linger(Duration::from_secs(60)));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!(lobby = 7, "Match started");
+    info!(lobby = 7, "Match ended");
+    guard.flush().await;
+
+    m_logs.assert();
+    let body: serde_json::Value = rmp_serde::from_slice(&bodies.lock().unwrap()[0]).unwrap();
+    let records = body.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["schema_version"], 1);
+    assert!(uuid::Uuid::parse_str(records[0]["event_id"].as_str().unwrap()).is_ok());
+    assert_eq!(records[0]["log"], "rust tracing log captured");
+    assert_eq!(records[0]["tags"]["message"], "Match started");
+    assert_eq!(records[1]["tags"]["message"], "Match ended");
+}
+
+// Verify that the layer falls back to JSON once the intake refuses MessagePack.
+#[tokio::test]
+async fn test_unsupported_media_type_falls_back_to_json() {
+    let (mut mock_server, base_url) = mock_service();
+    let m_msgpack = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("content-type", "application/msgpack")
+        .with_status(415)
+        .expect(1)
+        .create();
+    let m_json = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("content-type", "application/json")
+        .match_body(Matcher::PartialJson(serde_json::json!({ "log": "rust tracing log captured" })))
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create();
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&warnings);
+    set_diagnostic_handler(move |diagnostic| {
+        if diagnostic.message().contains("falling back to JSON") {
+            collected.lock().unwrap().push(diagnostic.message().to_string());
+        }
+    });
+
+    let layer = PogrLayer::new(appender(&base_url).await).with_payload_encoding(PayloadEncoding::MessagePack);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Match started");
+    guard.flush().await;
+
+    m_msgpack.assert();
+    m_json.assert();
+    assert_eq!(
+        *warnings.lock().unwrap(),
+        ["The intake does not accept MessagePack payloads, falling back to JSON"]
+    );
+}
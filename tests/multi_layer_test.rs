@@ -0,0 +1,154 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{attach, Attachment, LayerScope, PogrAppender, PogrLayer};
+use tracing::{error, info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service whose sessions get the given ID, and return the server with a
+// layer connected to it.
+async fn layer_with_session(session_id: &str) -> (mockito::ServerGuard, PogrLayer) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": session_id }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    ).await;
+    (mock_server, PogrLayer::new(appender))
+}
+
+// Mock the logs endpoint for records of the given session matching `body`.
+fn mock_logs(mock_server: &mut mockito::ServerGuard, session_id: &str, body: serde_json::Value, hits: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", session_id)
+        .match_body(Matcher::PartialJson(body))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(hits)
+        .create()
+}
+
+// Verify that two layers in one subscriber stack each record events inside spans, with the
+// span's fields, through their own sessions.
+#[tokio::test]
+async fn test_two_layers_record_span_events() {
+    let (mut host_server, host) = layer_with_session("host_session").await;
+    let (mut plugin_server, plugin) = layer_with_session("plugin_session").await;
+
+    let body = serde_json::json!({ "tags": { "match_id": 7, "message": "Round started" } });
+    let m_host = mock_logs(&mut host_server, "host_session", body.clone(), 1);
+    let m_plugin = mock_logs(&mut plugin_server, "plugin_session", body, 1);
+
+    let host_guard = host.guard();
+    let plugin_guard = plugin.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(host).with(plugin);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info_span!("match", match_id = 7).in_scope(|| info!("Round started"));
+
+    host_guard.flush().await;
+    plugin_guard.flush().await;
+    m_host.assert();
+    m_plugin.assert();
+}
+
+// Verify that events inside a layer scope are only recorded by that layer, in synchronous and
+// asynchronous code, while events outside any scope are recorded by every layer.
+#[tokio::test]
+async fn test_layer_scope_routes_events() {
+    let (mut host_server, host) = layer_with_session("host_session").await;
+    let (mut plugin_server, plugin) = layer_with_session("plugin_session").await;
+
+    let started = serde_json::json!({ "tags": { "message": "Server started" } });
+    let loaded = serde_json::json!({ "tags": { "message": "Plugin loaded" } });
+    let ticked = serde_json::json!({ "tags": { "message": "Plugin ticked" } });
+    let m_host_started = mock_logs(&mut host_server, "host_session", started.clone(), 1);
+    let m_host_loaded = mock_logs(&mut host_server, "host_session", loaded.clone(), 0);
+    let m_host_ticked = mock_logs(&mut host_server, "host_session", ticked.clone(), 0);
+    let m_plugin_started = mock_logs(&mut plugin_server, "plugin_session", started, 1);
+    let m_plugin_loaded = mock_logs(&mut plugin_server, "plugin_session", loaded, 1);
+    let m_plugin_ticked = mock_logs(&mut plugin_server, "plugin_session", ticked, 1);
+
+    let host_guard = host.guard();
+    let plugin_guard = plugin.guard();
+    let plugin_scope = plugin.layer_scope();
+    assert_ne!(plugin_scope, host.layer_scope());
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(host).with(plugin);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Server started");
+    plugin_scope.sync_scope(|| {
+        assert_eq!(LayerScope::current(), Some(plugin_scope));
+        info!("Plugin loaded");
+    });
+    plugin_scope.scope(async { info!("Plugin ticked") }).await;
+    assert_eq!(LayerScope::current(), None);
+
+    host_guard.flush().await;
+    plugin_guard.flush().await;
+    m_host_started.assert();
+    m_host_loaded.assert();
+    m_host_ticked.assert();
+    m_plugin_started.assert();
+    m_plugin_loaded.assert();
+    m_plugin_ticked.assert();
+}
+
+// Verify that an attachment is submitted by every layer recording its event, and not with the
+// following event.
+#[tokio::test]
+async fn test_attachments_reach_every_layer() {
+    let (mut host_server, host) = layer_with_session("host_session").await;
+    let (mut plugin_server, plugin) = layer_with_session("plugin_session").await;
+
+    let attached = serde_json::json!({
+        "severity": "ERROR",
+        "attachments": [{ "name": "save.bin", "size": 4 }]
+    });
+    let plain = serde_json::json!({ "severity": "INFO" });
+    let m_host_attached = mock_logs(&mut host_server, "host_session", attached.clone(), 1);
+    let m_host_plain = mock_logs(&mut host_server, "host_session", plain.clone(), 1);
+    let m_plugin_attached = mock_logs(&mut plugin_server, "plugin_session", attached, 1);
+    let m_plugin_plain = mock_logs(&mut plugin_server, "plugin_session", plain, 1);
+
+    let host_guard = host.guard();
+    let plugin_guard = plugin.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(host).with(plugin);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    attach(Attachment::new("save.bin", "application/octet-stream", vec![0xde, 0xad, 0xbe, 0xef]));
+    error!("state desync detected");
+    info!("resynchronized");
+
+    host_guard.flush().await;
+    plugin_guard.flush().await;
+    m_host_attached.assert();
+    m_host_plain.assert();
+    m_plugin_attached.assert();
+    m_plugin_plain.assert();
+}
@@ -0,0 +1,191 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{NatsSink, PogrAppender, PogrLayer, SinkMode};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Start a minimal NATS server that reports every CONNECT and PUB it receives.
+//
+// Returns the server's address and a channel of `(subject, payload)` pairs; the CONNECT
+// options are reported with the subject `CONNECT`.
+fn fake_nats() -> (String, mpsc::Receiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(b"INFO {\"server_id\":\"test\",\"max_payload\":1048576}\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap_or(0) > 0 {
+            let command = line.trim_end().to_string();
+            line.clear();
+
+            if let Some(options) = command.strip_prefix("CONNECT ") {
+                let _ = sender.send(("CONNECT".to_string(), options.to_string()));
+            } else if let Some(publish) = command.strip_prefix("PUB ") {
+                let subject = publish.split(' ').next().unwrap().to_string();
+                reader.read_line(&mut line).unwrap();
+                let _ = sender.send((subject, line.trim_end().to_string()));
+                line.clear();
+            } else if command == "PING" {
+                stream.write_all(b"PONG\r\n").unwrap();
+            }
+        }
+    });
+
+    (address, receiver)
+}
+
+// Verify that records are published on subjects rendered from the template.
+#[tokio::test]
+async fn test_nats_mirror() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let (address, published) = fake_nats();
+
+    // Mirrored records are still delivered to POGR.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(2)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let nats = NatsSink::new(address, "telemetry.{target}.{level}").with_token("test_token");
+    let layer = PogrLayer::new(appender).with_sink(nats, SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    warn!(target: "game::matchmaking", player_id = 42, "matchmaking timed out");
+    info!(target: "game::lobby", "lobby created");
+    guard.flush().await;
+    m_logs.assert();
+
+    let receive = || published.recv_timeout(Duration::from_secs(5)).expect("nothing published");
+
+    // The sink authenticates when it connects.
+    let (command, options) = receive();
+    assert_eq!(command, "CONNECT");
+    let options: serde_json::Value = serde_json::from_str(&options).unwrap();
+    assert_eq!(options["auth_token"], "test_token");
+    assert_eq!(options["verbose"], false);
+
+    // Each record goes to the subject for its target and level.
+    let (subject, payload) = receive();
+    assert_eq!(subject, "telemetry.game.matchmaking.warn");
+    let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+    assert_eq!(payload["tags"]["player_id"], 42);
+    assert_eq!(payload["severity"], "WARN");
+
+    let (subject, _) = receive();
+    assert_eq!(subject, "telemetry.game.lobby.info");
+}
+
+// Verify that an unreachable server leaves intake delivery untouched.
+#[tokio::test]
+async fn test_nats_unreachable() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Reserve a port and close it again so that nothing is listening on it.
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_sink(NatsSink::new(address, "telemetry.{level}"), SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("delivered despite the broken mirror");
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that a server that never answers does not hold up delivery to POGR.
+#[tokio::test]
+async fn test_nats_unresponsive() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Accept the connection, then never send `INFO` or answer a `PING`.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        let (_stream, _) = listener.accept().unwrap();
+        std::thread::sleep(Duration::from_secs(10));
+    });
+
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_sink(NatsSink::new(address, "telemetry.{level}"), SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let started = Instant::now();
+    info!("delivered while the mirror hangs");
+    guard.flush().await;
+    m_logs.assert();
+    assert!(started.elapsed() < Duration::from_secs(2), "flush waited {:?}", started.elapsed());
+}
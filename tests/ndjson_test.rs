@@ -0,0 +1,113 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{BatchConfig, LevelRoute, PayloadEncoding, PogrAppender, PogrLayer};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+    (mock_server, base_url)
+}
+
+// Mock an endpoint accepting NDJSON bodies, whose bodies are kept in `bodies`.
+fn mock_ndjson(mock_server: &mut mockito::ServerGuard, path: &str, bodies: &Arc<Mutex<Vec<String>>>) -> mockito::Mock {
+    let captured = Arc::clone(bodies);
+    mock_server.mock("POST", path)
+        .match_header("content-type", "application/x-ndjson")
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            captured.lock().unwrap().push(String::from_utf8(request.body().unwrap().clone()).unwrap());
+            br#"{"success": true, "payload": {"log_id": "test_log_id"}}"#.to_vec()
+        })
+        .expect(1)
+        .create()
+}
+
+// Create an appender for the mock service.
+async fn appender(base_url: &str) -> PogrAppender {
+    PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await
+}
+
+// Verify that a batch is submitted with one payload per line.
+#[tokio::test]
+async fn test_ndjson_batch() {
+    let (mut mock_server, base_url) = mock_service();
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let m_logs = mock_ndjson(&mut mock_server, "/v1/intake/logs", &bodies);
+
+    let layer = PogrLayer::new(appender(&base_url).await)
+        .with_payload_encoding(PayloadEncoding::Ndjson)
+        .with_batching(BatchConfig::new().with_max_batch_size(10).with_linger(Duration::from_secs(60)));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Match started");
+    info!("Match ended");
+    guard.flush().await;
+
+    m_logs.assert();
+    let body = bodies.lock().unwrap()[0].clone();
+    assert!(body.ends_with('\n'));
+    let lines: Vec<serde_json::Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["schema_version"], 1);
+    assert_eq!(lines[0]["tags"]["message"], "Match started");
+    assert_eq!(lines[1]["tags"]["message"], "Match ended");
+}
+
+// Verify that a level route to an endpoint of its own can use NDJSON while the default
+// pipeline keeps JSON.
+#[tokio::test]
+async fn test_ndjson_per_endpoint() {
+    let (mut mock_server, base_url) = mock_service();
+    let m_default = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("content-type", "application/json")
+        .match_body(Matcher::PartialJson(serde_json::json!({ "tags": { "message": "Match started" } })))
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create();
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let m_stream = mock_ndjson(&mut mock_server, "/stream/logs", &bodies);
+
+    let layer = PogrLayer::new(appender(&base_url).await).with_level_route(
+        LevelRoute::new(Level::ERROR)
+            .with_logs_endpoint(format!("{}/stream/logs", base_url))
+            .with_payload_encoding(PayloadEncoding::Ndjson),
+    );
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Match started");
+    error!("Desync detected");
+    guard.flush().await;
+
+    m_default.assert();
+    m_stream.assert();
+    let line: serde_json::Value = serde_json::from_str(bodies.lock().unwrap()[0].trim_end()).unwrap();
+    assert_eq!(line["tags"]["message"], "Desync detected");
+}
@@ -0,0 +1,197 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{BatchConfig, OtlpConfig, PogrAppender, PogrLayer, SinkMode};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service and collector, and return the init, logs and collector endpoints.
+fn mock_service() -> (mockito::ServerGuard, String, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service and the collector.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (
+        mock_server,
+        format!("{}/v1/intake/init", base_url),
+        format!("{}/v1/intake/logs", base_url),
+        format!("{}/v1/logs", base_url),
+    )
+}
+
+// Look up an attribute's value in an OTLP attribute list.
+fn attribute<'a>(attributes: &'a serde_json::Value, key: &str) -> &'a serde_json::Value {
+    attributes
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|attribute| attribute["key"] == key)
+        .map(|attribute| &attribute["value"])
+        .unwrap_or_else(|| panic!("missing attribute {}", key))
+}
+
+// Verify that a batch is exported to the collector as one OTLP logs request, and nothing
+// reaches the intake.
+#[tokio::test]
+async fn test_otlp_export() {
+    let (mut mock_server, init_endpoint, logs_endpoint, collector_endpoint) = mock_service();
+
+    let m_intake = mock_server.mock("POST", "/v1/intake/logs").expect(0).create();
+    let exports = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&exports);
+    let m_collector = mock_server.mock("POST", "/v1/logs")
+        .match_header("Content-Type", "application/json")
+        .match_header("Authorization", "Bearer collector-token")
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            let export: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            received.lock().unwrap().push(export);
+            "{}".into()
+        })
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_otlp(OtlpConfig::new(collector_endpoint).with_header("Authorization", "Bearer collector-token"))
+        .with_batching(BatchConfig::new().with_max_batch_size(10));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!(player_id = 42, ranked = true, "Match started");
+    error!(latency_ms = 812.5, "Match aborted");
+    guard.flush().await;
+    m_collector.assert();
+    m_intake.assert();
+
+    let exports = exports.lock().unwrap();
+    let resource_logs = &exports[0]["resourceLogs"][0];
+    let resource = &resource_logs["resource"]["attributes"];
+    assert_eq!(attribute(resource, "pogr.session_id")["stringValue"], "test_session_id");
+    assert_eq!(attribute(resource, "telemetry.sdk.name")["stringValue"], "pogr_tracing_rs");
+    assert!(attribute(resource, "service.name")["stringValue"].is_string());
+
+    let scope_logs = &resource_logs["scopeLogs"][0];
+    assert_eq!(scope_logs["scope"]["name"], "pogr_tracing_rs");
+    let log_records = scope_logs["logRecords"].as_array().unwrap();
+    assert_eq!(log_records.len(), 2);
+
+    let started = &log_records[0];
+    assert_eq!(started["severityNumber"], 9);
+    assert_eq!(started["severityText"], "INFO");
+    assert_eq!(started["body"]["stringValue"], "Match started");
+    assert!(started["timeUnixNano"].as_str().unwrap().parse::<u128>().unwrap() > 0);
+    assert_eq!(attribute(&started["attributes"], "player_id")["intValue"], "42");
+    assert_eq!(attribute(&started["attributes"], "ranked")["boolValue"], true);
+    assert_eq!(attribute(&started["attributes"], "pogr.data.target")["stringValue"], "otlp_test");
+    assert_eq!(attribute(&started["attributes"], "pogr.event_id")["stringValue"].as_str().unwrap().len(), 36);
+
+    let aborted = &log_records[1];
+    assert_eq!(aborted["severityNumber"], 17);
+    assert_eq!(attribute(&aborted["attributes"], "latency_ms")["doubleValue"], 812.5);
+}
+
+// Verify that records the collector rejects are handed to the fallback sinks.
+#[tokio::test]
+async fn test_otlp_rejected() {
+    let (mut mock_server, init_endpoint, logs_endpoint, collector_endpoint) = mock_service();
+
+    let m_collector = mock_server.mock("POST", "/v1/logs")
+        .with_status(503)
+        .with_body("collector overloaded")
+        .expect(1)
+        .create();
+
+    let fallback = Arc::new(CollectingSink::new(|record| record.event_id().to_string()));
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_otlp(OtlpConfig::new(collector_endpoint))
+        .with_sink(Arc::clone(&fallback), SinkMode::Fallback);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("Match aborted");
+    guard.flush().await;
+    m_collector.assert();
+
+    assert_eq!(fallback.0.lock().unwrap().len(), 1);
+}
+
+// Verify that the standard OpenTelemetry variables configure the export.
+#[test]
+fn test_otlp_from_env() {
+    std::env::remove_var("OTEL_EXPORTER_OTLP_LOGS_ENDPOINT");
+    std::env::set_var("OTEL_EXPORTER_OTLP_ENDPOINT", "http://otel-collector:4318/");
+    std::env::set_var("OTEL_EXPORTER_OTLP_HEADERS", "Authorization=Bearer secret-token,x-tenant=games");
+
+    let config = OtlpConfig::from_env();
+    assert_eq!(config.endpoint(), "http://otel-collector:4318/v1/logs");
+    // Header values are not shown.
+    let debug = format!("{:?}", config);
+    assert!(debug.contains("x-tenant"), "{}", debug);
+    assert!(!debug.contains("secret-token"), "{}", debug);
+
+    std::env::set_var("OTEL_EXPORTER_OTLP_LOGS_ENDPOINT", "http://logs-collector:4318/v1/logs");
+    assert_eq!(OtlpConfig::from_env().endpoint(), "http://logs-collector:4318/v1/logs");
+}
+
+// Verify that the resource follows the OpenTelemetry semantic conventions, including the
+// detected Kubernetes pod.
+#[tokio::test]
+async fn test_otlp_resource_semantic_conventions() {
+    let (mut mock_server, init_endpoint, logs_endpoint, collector_endpoint) = mock_service();
+    std::env::set_var("KUBERNETES_SERVICE_HOST", "10.96.0.1");
+    std::env::set_var("POD_NAMESPACE", "staging");
+    std::env::set_var("HOSTNAME", "matchmaking-7d9f8-x2k4q");
+
+    let exports = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&exports);
+    mock_server.mock("POST", "/v1/logs")
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            let export: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            received.lock().unwrap().push(export);
+            "{}".into()
+        })
+        .create();
+
+    let appender = PogrAppender::builder()
+        .with_init_endpoint(init_endpoint)
+        .with_logs_endpoint(logs_endpoint)
+        .build()
+        .await;
+    let layer = PogrLayer::new(appender).with_otlp(OtlpConfig::new(collector_endpoint));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Queue opened");
+    guard.flush().await;
+
+    let exports = exports.lock().unwrap();
+    let resource = &exports[0]["resourceLogs"][0]["resource"]["attributes"];
+    assert_eq!(attribute(resource, "service.instance.id")["stringValue"], "test_session_id");
+    assert_eq!(attribute(resource, "deployment.environment")["stringValue"], "staging");
+    assert_eq!(attribute(resource, "k8s.namespace.name")["stringValue"], "staging");
+    assert_eq!(attribute(resource, "k8s.pod.name")["stringValue"], "matchmaking-7d9f8-x2k4q");
+    assert_eq!(attribute(resource, "os.type")["stringValue"], std::env::consts::OS);
+    assert!(attribute(resource, "host.arch")["stringValue"].is_string());
+    assert!(attribute(resource, "host.name")["stringValue"].is_string());
+}
@@ -0,0 +1,132 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{PausePolicy, PogrAppender, PogrLayer, SinkMode};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::error;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service, and return an appender for it.
+async fn mock_service() -> (mockito::ServerGuard, PogrAppender) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    (mock_server, appender)
+}
+
+// Mock the intake asking to pause delivery for `seconds`, to be created by the caller.
+fn mock_pause(mock_server: &mut mockito::ServerGuard, seconds: u64) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_header("X-POGR-Pause", &seconds.to_string())
+        .with_body("Shedding load")
+}
+
+// Wait until a mock has received the requests it expects.
+async fn wait_for(mock: &mockito::Mock) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !mock.matched() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+// Verify that records are spooled while delivery is paused, and sent once it resumes,
+// including the record whose request was answered with the pause.
+#[tokio::test]
+async fn test_pause_spool() {
+    let (mut mock_server, appender) = mock_service().await;
+    let m_pause = mock_pause(&mut mock_server, 1).create();
+
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let paused = Instant::now();
+    error!("Match server crashed");
+    wait_for(&m_pause).await;
+    m_pause.remove();
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(2)
+        .create();
+
+    error!("Lobby server crashed");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!m_logs.matched());
+
+    wait_for(&m_logs).await;
+    assert!(paused.elapsed() >= Duration::from_secs(1));
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that records go to the fallback sinks while delivery is paused under the drop policy.
+#[tokio::test]
+async fn test_pause_drop() {
+    let (mut mock_server, appender) = mock_service().await;
+    let m_pause = mock_pause(&mut mock_server, 3600).expect(1).create();
+
+    let fallback = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender)
+        .with_pause_policy(PausePolicy::Drop)
+        .with_sink(Arc::clone(&fallback), SinkMode::Fallback);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("Match server crashed");
+    guard.flush().await;
+    error!("Lobby server crashed");
+    guard.flush().await;
+
+    m_pause.assert();
+    assert_eq!(*fallback.0.lock().unwrap(), ["Match server crashed", "Lobby server crashed"]);
+}
+
+// Verify that flushing during a pause hands the spooled records to the fallback sinks instead
+// of waiting for the pause to end.
+#[tokio::test]
+async fn test_pause_flush() {
+    let (mut mock_server, appender) = mock_service().await;
+    let m_pause = mock_pause(&mut mock_server, 3600).expect(1).create();
+
+    let fallback = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender).with_sink(Arc::clone(&fallback), SinkMode::Fallback);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("Match server crashed");
+    error!("Lobby server crashed");
+    tokio::time::timeout(Duration::from_secs(5), guard.flush()).await.unwrap();
+
+    m_pause.assert();
+    assert_eq!(fallback.0.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,112 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{set_diagnostic_handler, DiagnosticKind, PayloadSizeTracking, PogrAppender, PogrLayer};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Initialize a session with a mock POGR service.
+async fn appender() -> (mockito::ServerGuard, PogrAppender) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), None).await;
+    (mock_server, appender)
+}
+
+// Emit a small event from one callsite.
+fn small_event() {
+    info!(target: "lobby", "player joined");
+}
+
+// Emit a large event from another callsite.
+fn large_event(padding: &str) {
+    info!(target: "match", padding = %padding, "match state");
+}
+
+// Verify that sizes are kept per callsite and per target, largest first, and that a callsite
+// producing oversized payloads is reported once it has done so often enough.
+#[tokio::test]
+async fn test_payload_size_tracking() {
+    let diagnostics = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&diagnostics);
+    set_diagnostic_handler(move |diagnostic| {
+        if diagnostic.kind() == DiagnosticKind::PayloadSize {
+            collected.lock().unwrap().push(diagnostic.message().to_string());
+        }
+    });
+
+    let (_mock_server, appender) = appender().await;
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_payload_size_tracking(PayloadSizeTracking::new().with_oversize_threshold(2_000).with_warn_after(3));
+    let stats = layer.payload_stats().unwrap();
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let padding = "x".repeat(4_000);
+    for _ in 0..5 {
+        small_event();
+    }
+    for _ in 0..2 {
+        large_event(&padding);
+    }
+    guard.flush().await;
+    assert!(diagnostics.lock().unwrap().is_empty());
+
+    // The third oversized payload reports the callsite, and later ones do not.
+    large_event(&padding);
+    large_event(&padding);
+    guard.flush().await;
+    let reported = diagnostics.lock().unwrap().clone();
+    assert_eq!(reported.len(), 1);
+    assert!(reported[0].starts_with("match at tests/payload_size_test.rs:"), "{}", reported[0]);
+    assert!(reported[0].contains("produced 3 payloads above 2000 bytes"), "{}", reported[0]);
+
+    let callsites = stats.top_callsites(10);
+    assert_eq!(callsites.len(), 2);
+    let (large, small) = (&callsites[0], &callsites[1]);
+    assert_eq!(large.target(), "match");
+    assert!(large.callsite().unwrap().starts_with("tests/payload_size_test.rs:"));
+    assert_eq!(large.count(), 4);
+    assert_eq!(large.oversized(), 4);
+    assert!(large.max_bytes() > 4_000);
+    assert_eq!(large.average_bytes(), large.total_bytes() / 4);
+    assert_eq!(small.target(), "lobby");
+    assert_eq!(small.count(), 5);
+    assert_eq!(small.oversized(), 0);
+    assert!(small.max_bytes() < 2_000);
+    assert_eq!(stats.top_callsites(1), std::slice::from_ref(large));
+
+    let targets = stats.top_targets(10);
+    assert_eq!(targets.iter().map(|target| target.target()).collect::<Vec<_>>(), ["match", "lobby"]);
+    assert_eq!(targets[0].callsite(), None);
+    assert_eq!(targets[0].total_bytes(), large.total_bytes());
+
+    stats.reset();
+    assert!(stats.top_callsites(10).is_empty());
+}
+
+// Verify that sizes are only tracked when enabled.
+#[tokio::test]
+async fn test_payload_size_tracking_disabled() {
+    let (_mock_server, appender) = appender().await;
+    assert!(PogrLayer::new(appender).payload_stats().is_none());
+}
@@ -0,0 +1,57 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use mockito::Matcher;
+use pogr_tracing_rs::{PogrAppender, PogrLayer, SinkMode, SCHEMA_VERSION};
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that payloads sent to the intake and handed to sinks carry the schema version.
+#[tokio::test]
+async fn test_payload_schema_version() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    // Only accept payloads with the current schema version.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({ "schema_version": 1, "log": "rust tracing log captured" })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    let mirror = Arc::new(CollectingSink::new(|record| serde_json::from_str::<serde_json::Value>(&record.to_json()).unwrap()));
+    let layer = PogrLayer::new(appender).with_sink(Arc::clone(&mirror), SinkMode::Mirror);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Match started");
+    guard.flush().await;
+
+    m_logs.assert();
+    assert_eq!(SCHEMA_VERSION, 1);
+    assert_eq!(mirror.0.lock().unwrap()[0]["schema_version"], SCHEMA_VERSION);
+}
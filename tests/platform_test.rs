@@ -0,0 +1,120 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{DistributionPlatform, PogrAppender, PogrLayer};
+use tokio::sync::Mutex;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Serializes the tests, since each of them changes the platform environment variables.
+static ENV: Mutex<()> = Mutex::const_new(());
+
+// Set the platform environment variables, removing the ones given as `None`.
+fn set_platform_env(vars: &[(&str, Option<&str>)]) {
+    for (name, value) in vars {
+        match value {
+            Some(value) => std::env::set_var(name, value),
+            None => std::env::remove_var(name),
+        }
+    }
+}
+
+// Verify that the platform is detected from the Steam client's and POGR's variables.
+#[test]
+fn test_detect() {
+    let _env = ENV.blocking_lock();
+
+    set_platform_env(&[("POGR_PLATFORM", None), ("POGR_PLATFORM_ID", None), ("SteamAppId", None), ("SteamGameId", None)]);
+    assert_eq!(DistributionPlatform::detect(), None);
+
+    set_platform_env(&[("SteamGameId", Some("480"))]);
+    let platform = DistributionPlatform::detect().unwrap();
+    assert_eq!((platform.name(), platform.id()), ("steam", Some("480")));
+
+    // The explicit variables take precedence, e.g. for console SKUs.
+    set_platform_env(&[("POGR_PLATFORM", Some("PS5")), ("POGR_PLATFORM_ID", Some("PPSA01234"))]);
+    assert_eq!(DistributionPlatform::detect(), Some(DistributionPlatform::new("ps5", Some("PPSA01234".to_string()))));
+
+    set_platform_env(&[("POGR_PLATFORM_ID", None)]);
+    assert_eq!(DistributionPlatform::detect(), Some(DistributionPlatform::new("ps5", None)));
+
+    set_platform_env(&[("POGR_PLATFORM", None), ("SteamGameId", None)]);
+}
+
+// Verify that the detected platform is stamped on the session and on every event.
+#[tokio::test]
+async fn test_platform_detection() {
+    let _env = ENV.lock().await;
+    set_platform_env(&[("POGR_PLATFORM", None), ("POGR_PLATFORM_ID", None), ("SteamAppId", Some("480"))]);
+
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "platform": "steam",
+            "metadata": { "platform_id": "480" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "tags": { "platform": "steam", "platform_id": "480", "message": "Match started" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    // An event field of the same name overrides the detected platform.
+    let m_override = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "tags": { "platform": "dedicated-server", "platform_id": "480" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::builder()
+        .with_init_endpoint(init_endpoint)
+        .with_logs_endpoint(logs_endpoint)
+        .with_platform_detection()
+        .build()
+        .await;
+    m_init.assert();
+
+    let layer = PogrLayer::new(appender).with_platform_detection();
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Match started");
+    guard.flush().await;
+    info!(platform = "dedicated-server", "Match started");
+    guard.flush().await;
+
+    m_logs.assert();
+    m_override.assert();
+    set_platform_env(&[("SteamAppId", None)]);
+}
@@ -0,0 +1,81 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{BatchConfig, PogrLayer};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Create a layer that batches up to ten records and only delivers to a collecting sink.
+async fn batching_layer() -> (mockito::ServerGuard, PogrLayer, Arc<CollectingSink>) {
+    let (mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_batching(
+        BatchConfig::new()
+            .with_max_batch_size(10)
+            .with_linger(Duration::from_millis(10)),
+    );
+    (mock_server, layer, sink)
+}
+
+// Verify that records built from reused field maps and batches keep exactly their own fields,
+// across many batches and flushes.
+#[tokio::test]
+async fn test_reused_objects_keep_records_apart() {
+    let (_mock_server, layer, sink) = batching_layer().await;
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for round in 0..5 {
+        for index in 0..50 {
+            if index % 2 == 0 {
+                info!(round, index, even = true, "even event");
+            } else {
+                info!(round, index, "odd event");
+            }
+        }
+        guard.flush().await;
+    }
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 250);
+    for (position, record) in records.iter().enumerate() {
+        let tags = record["tags"].as_object().unwrap();
+        assert_eq!(tags["round"], position / 50);
+        assert_eq!(tags["index"], position % 50);
+        if position % 2 == 0 {
+            assert_eq!(tags["message"], "even event");
+            assert_eq!(tags["even"], true);
+        } else {
+            assert_eq!(tags["message"], "odd event");
+            assert!(!tags.contains_key("even"));
+        }
+    }
+}
+
+// Verify that fields recorded on a span after it was created are kept, and do not leak into
+// the events recorded afterwards.
+#[tokio::test]
+async fn test_recorded_span_fields_do_not_leak() {
+    let (_mock_server, layer, sink) = batching_layer().await;
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let span = info_span!("match", score = tracing::field::Empty);
+    span.record("score", 3);
+    span.in_scope(|| info!("inside"));
+    info!(player = "ana", "outside");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["tags"]["score"], 3);
+    assert_eq!(records[1]["tags"]["player"], "ana");
+    assert!(records[1]["tags"].get("score").is_none());
+}
@@ -0,0 +1,103 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{ArgRedaction, PogrAppender, PogrAppenderBuilder};
+use std::sync::{Arc, Mutex};
+
+// Initialize a session with a mock POGR service and return the body of its init request.
+async fn init_body(builder: PogrAppenderBuilder) -> serde_json::Value {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mut mock_server = mockito::Server::new();
+    let init_endpoint = format!("{}/v1/intake/init", mock_server.url().trim_end_matches('/'));
+    let captured = Arc::new(Mutex::new(None));
+    let body = Arc::clone(&captured);
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            *body.lock().unwrap() = Some(serde_json::from_slice::<serde_json::Value>(request.body().unwrap()).unwrap());
+            serde_json::json!({
+                "success": true,
+                "payload": { "session_id": "test_session_id" }
+            }).to_string().into_bytes()
+        })
+        .create();
+
+    builder.with_init_endpoint(init_endpoint).build().await;
+    let body = captured.lock().unwrap().take().unwrap();
+    body
+}
+
+// Verify that the init request describes the process by default.
+#[tokio::test]
+async fn test_process_metadata() {
+    let body = init_body(PogrAppender::builder()).await;
+
+    let process = &body["process"];
+    assert_eq!(process["os"], std::env::consts::OS);
+    assert_eq!(process["arch"], std::env::consts::ARCH);
+    let args: Vec<String> = std::env::args().collect();
+    assert_eq!(process["args"].as_array().unwrap().len(), args.len());
+    assert_eq!(process["args"][0], args[0]);
+    if cfg!(target_os = "linux") {
+        assert!(process["os_version"].is_string());
+    }
+}
+
+// Verify that the process can be left out of the init request.
+#[tokio::test]
+async fn test_process_metadata_disabled() {
+    let body = init_body(PogrAppender::builder().with_process_metadata(false)).await;
+    assert!(body.get("process").is_none());
+
+    // Setting a redaction enables it again.
+    let body = init_body(
+        PogrAppender::builder()
+            .with_process_metadata(false)
+            .with_arg_redaction(ArgRedaction::new().with_words(["license"])),
+    )
+    .await;
+    assert!(body["process"]["args"].is_array());
+}
+
+// Verify that the values of sensitive flags are redacted, in both flag styles.
+#[test]
+fn test_default_redaction() {
+    let args = [
+        "/usr/bin/server",
+        "--db-password",
+        "hunter2",
+        "--API_KEY=abc123",
+        "-t",
+        "--auth-token",
+        "--port",
+        "7777",
+        "positional",
+    ];
+    assert_eq!(
+        ArgRedaction::new().redact(args),
+        [
+            "/usr/bin/server",
+            "--db-password",
+            "[redacted]",
+            "--API_KEY=[redacted]",
+            "-t",
+            "--auth-token",
+            "--port",
+            "7777",
+            "positional",
+        ]
+    );
+}
+
+// Verify that extra words extend the defaults, with or without dashes and in any case.
+#[test]
+fn test_extra_words() {
+    let redaction = ArgRedaction::new().with_words(["--License", "seed"]);
+    let args = ["game", "--license=ABC", "--world-seed", "42", "--secret", "s", "--name", "ana"];
+    assert_eq!(
+        redaction.redact(args),
+        ["game", "--license=[redacted]", "--world-seed", "[redacted]", "--secret", "[redacted]", "--name", "ana"]
+    );
+}
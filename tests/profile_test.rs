@@ -0,0 +1,96 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::VerbosityProfile;
+use tracing::{debug, info, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Serializes the tests that change `POGR_PROFILE`.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// Create a sink that keeps the severity and message of the records it receives.
+fn severities() -> CollectingSink<(String, String)> {
+    CollectingSink::new(|record| {
+        let request = record.request();
+        let message = request.tags["message"].as_str().unwrap_or_default().to_string();
+        (request.severity.clone(), message)
+    })
+}
+
+// Verify that environment names map to the expected profiles.
+#[test]
+fn test_profile_for_environment() {
+    assert_eq!(VerbosityProfile::for_environment("production"), VerbosityProfile::Production);
+    assert_eq!(VerbosityProfile::for_environment("PROD"), VerbosityProfile::Production);
+    assert_eq!(VerbosityProfile::for_environment("production-eu"), VerbosityProfile::Production);
+    assert_eq!(VerbosityProfile::for_environment("staging"), VerbosityProfile::Staging);
+    assert_eq!(VerbosityProfile::for_environment("qa_2"), VerbosityProfile::Staging);
+    assert_eq!(VerbosityProfile::for_environment("development"), VerbosityProfile::Development);
+    assert_eq!(VerbosityProfile::for_environment("feature-login"), VerbosityProfile::Development);
+
+    assert!(VerbosityProfile::Development.console_echo());
+    assert!(!VerbosityProfile::Production.console_echo());
+    assert!("nightly".parse::<VerbosityProfile>().is_err());
+}
+
+// Verify that `POGR_PROFILE` takes precedence over the environment.
+#[tokio::test]
+async fn test_profile_override() {
+    let _lock = ENV_LOCK.lock().await;
+
+    std::env::set_var("POGR_PROFILE", "Staging");
+    assert_eq!(VerbosityProfile::detect("production"), VerbosityProfile::Staging);
+
+    // An unknown profile is ignored.
+    std::env::set_var("POGR_PROFILE", "verbose");
+    assert_eq!(VerbosityProfile::detect("production"), VerbosityProfile::Production);
+
+    std::env::remove_var("POGR_PROFILE");
+    assert_eq!(VerbosityProfile::detect("staging"), VerbosityProfile::Staging);
+}
+
+// Verify that the production profile drops debug events, keeps warnings and samples
+// informational events.
+#[tokio::test]
+async fn test_production_profile() {
+    let (_mock_server, layer, sink) = collecting_layer(severities()).await;
+    let layer = layer.with_profile(VerbosityProfile::Production);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for _ in 0..1000 {
+        info!("Player joined");
+    }
+    debug!("Tick took 3 ms");
+    warn!("Ping above 200 ms");
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert!(records.iter().all(|(severity, _)| severity != "DEBUG"));
+    assert_eq!(records.iter().filter(|(severity, _)| severity == "WARN").count(), 1);
+    let infos = records.iter().filter(|(severity, _)| severity == "INFO").count();
+    assert!((40..=180).contains(&infos), "kept {} of 1000 info events", infos);
+}
+
+// Verify that level sampling only applies to its level, and that a rate can be replaced.
+#[tokio::test]
+async fn test_level_sampling() {
+    let (_mock_server, layer, sink) = collecting_layer(severities()).await;
+    let layer = layer
+        .with_level_sampling(Level::INFO, 0.5)
+        .with_level_sampling(Level::INFO, 0.0);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for _ in 0..10 {
+        info!("Player joined");
+        warn!("Ping above 200 ms");
+    }
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 10);
+    assert!(records.iter().all(|(severity, _)| severity == "WARN"));
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "protobuf")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::protobuf::{self, LogBatch};
+use pogr_tracing_rs::{set_diagnostic_handler, PayloadEncoding, PogrAppender, PogrLayer};
+use prost::Message;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+    (mock_server, base_url)
+}
+
+// Create an appender for the mock service.
+async fn appender(base_url: &str) -> PogrAppender {
+    PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await
+}
+
+// Verify that records are submitted as a protobuf batch with the same fields as JSON payloads.
+#[tokio::test]
+async fn test_protobuf_payload() {
+    let (mut mock_server, base_url) = mock_service();
+    let bodies = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&bodies);
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("content-type", protobuf::CONTENT_TYPE)
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            captured.lock().unwrap().push(request.body().unwrap().clone());
+            br#"{"success": true, "payload": {"log_id": "test_log_id"}}"#.to_vec()
+        })
+        .expect(1)
+        .create();
+
+    let layer = PogrLayer::new(appender(&base_url).await).with_payload_encoding(PayloadEncoding::Protobuf);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!(lobby = 7, "Match started");
+    guard.flush().await;
+
+    m_logs.assert();
+    let batch = LogBatch::decode(bodies.lock().unwrap()[0].as_slice()).unwrap();
+    let [record] = batch.records.as_slice() else {
+        panic!("expected a single record, got {:?}", batch.records);
+    };
+    assert_eq!(record.schema_version, 1);
+    assert_eq!(record.severity, "INFO");
+    assert_eq!(record.log, "rust tracing log captured");
+    assert!(uuid::Uuid::parse_str(&record.event_id).is_ok());
+    let tags: serde_json::Value = serde_json::from_str(&record.tags).unwrap();
+    assert_eq!(tags["message"], "Match started");
+    let data: serde_json::Value = serde_json::from_str(&record.data).unwrap();
+    assert_eq!(data["target"], "protobuf_test");
+}
+
+// Verify that the layer falls back to JSON for good once the intake refuses protobuf, without
+// losing the refused request.
+#[tokio::test]
+async fn test_unsupported_media_type_falls_back_to_json() {
+    let (mut mock_server, base_url) = mock_service();
+    let m_protobuf = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("content-type", protobuf::CONTENT_TYPE)
+        .with_status(415)
+        .expect(1)
+        .create();
+    let m_json = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("content-type", "application/json")
+        .match_body(Matcher::PartialJson(serde_json::json!({ "log": "rust tracing log captured" })))
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(2)
+        .create();
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&warnings);
+    set_diagnostic_handler(move |diagnostic| {
+        if diagnostic.message().contains("falling back to JSON") {
+            collected.lock().unwrap().push(diagnostic.message().to_string());
+        }
+    });
+
+    let layer = PogrLayer::new(appender(&base_url).await).with_payload_encoding(PayloadEncoding::Protobuf);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Match started");
+    guard.flush().await;
+    info!("Match ended");
+    guard.flush().await;
+
+    m_protobuf.assert();
+    m_json.assert();
+    assert_eq!(
+        *warnings.lock().unwrap(),
+        ["The intake does not accept protobuf payloads, falling back to JSON"]
+    );
+}
+
+// Verify that the schema shipped with the crate describes the batch.
+#[test]
+fn test_schema_included() {
+    assert!(protobuf::SCHEMA.contains("message LogBatch"));
+    assert!(protobuf::SCHEMA.contains("repeated LogEnvelope records = 1;"));
+}
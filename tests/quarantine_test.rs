@@ -0,0 +1,114 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use mockito::Matcher;
+use pogr_tracing_rs::{BatchConfig, PogrAppender, PogrLayer, SinkMode};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Build an appender submitting to the mock intake.
+fn appender(mock_server: &mockito::ServerGuard) -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_quarantine".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint: format!("{}/v1/intake/logs", mock_server.url()),
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Mock `count` refused submissions of requests whose body matches `body`, answered before the
+// accepted ones.
+fn mock_refused(mock_server: &mut mockito::ServerGuard, body: Matcher, count: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(body)
+        .with_status(400)
+        .with_body("Malformed record")
+        .expect(count)
+        .create()
+}
+
+// Mock `count` accepted submissions.
+fn mock_accepted(mock_server: &mut mockito::ServerGuard, count: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(count)
+        .create()
+}
+
+// Log the messages in a single batch, and return what the fallback sink received.
+async fn log_batch(mock_server: &mockito::ServerGuard, messages: &[&str]) -> Vec<(String, Option<String>)> {
+    let sink = Arc::new(CollectingSink::new(|record| {
+        let message = record.field("message").and_then(|message| message.as_str()).unwrap_or_default();
+        (message.to_string(), record.quarantine_error().map(str::to_string))
+    }));
+    let layer = PogrLayer::new(appender(mock_server))
+        .with_batching(BatchConfig::new().with_max_batch_size(10).with_linger(Duration::from_millis(100)))
+        .with_sink(Arc::clone(&sink), SinkMode::Fallback);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for message in messages {
+        error!("{}", message);
+    }
+    guard.flush().await;
+    let received = sink.0.lock().unwrap().clone();
+    received
+}
+
+// Verify that a record the intake refuses is isolated from its batch and quarantined with the
+// error, while the rest of the batch is delivered.
+#[tokio::test]
+async fn test_refused_record_quarantined() {
+    let mut mock_server = mockito::Server::new_async().await;
+    // The batch, the half with the poison record, and the poison record alone are refused.
+    let m_refused = mock_refused(&mut mock_server, Matcher::Regex("poison".to_string()), 3);
+    // The other half, and the other record of the poisoned half, are accepted.
+    let m_accepted = mock_accepted(&mut mock_server, 2);
+
+    let received = log_batch(&mock_server, &["first", "second", "poison", "fourth"]).await;
+    assert_eq!(
+        received,
+        [("poison".to_string(), Some("rejected by the intake: 400 Bad Request: Malformed record".to_string()))]
+    );
+    m_refused.assert();
+    m_accepted.assert();
+}
+
+// Verify that a batch is not split any further once the intake refuses both of its halves,
+// and that its records are not quarantined.
+#[tokio::test]
+async fn test_refused_batch_not_quarantined() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_refused = mock_refused(&mut mock_server, Matcher::Any, 3);
+
+    let received = log_batch(&mock_server, &["first", "second", "third", "fourth"]).await;
+    let messages: Vec<_> = received.iter().map(|(message, _)| message.as_str()).collect();
+    assert_eq!(messages, ["first", "second", "third", "fourth"]);
+    assert!(received.iter().all(|(_, quarantine_error)| quarantine_error.is_none()));
+    m_refused.assert();
+}
+
+// Verify that transient failures are not put down to the records.
+#[tokio::test]
+async fn test_transient_failure_not_isolated() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_unavailable = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Intake unavailable")
+        .expect(1)
+        .create();
+
+    let received = log_batch(&mock_server, &["first", "second"]).await;
+    assert_eq!(received.len(), 2);
+    assert!(received.iter().all(|(_, quarantine_error)| quarantine_error.is_none()));
+    m_unavailable.assert();
+}
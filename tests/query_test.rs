@@ -0,0 +1,98 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use mockito::Matcher;
+use pogr_tracing_rs::{LogQuery, PogrQueryClient, QueryError};
+
+// Start a mock query endpoint and return a client for it.
+fn mock_service() -> (mockito::ServerGuard, PogrQueryClient) {
+    let mock_server = mockito::Server::new();
+    let query_endpoint = format!("{}/v1/intake/logs/query", mock_server.url().trim_end_matches('/'));
+    let client = PogrQueryClient::new("read_access_key", "read_secret_key").with_endpoint(query_endpoint);
+    (mock_server, client)
+}
+
+// Verify that all pages of a session's logs are fetched by following the cursors.
+#[tokio::test]
+async fn test_fetch_all_pages() {
+    let (mut mock_server, client) = mock_service();
+
+    // The first page points to the second one.
+    let m_first = mock_server.mock("GET", "/v1/intake/logs/query")
+        .match_header("POGR_ACCESS", "read_access_key")
+        .match_header("POGR_SECRET", "read_secret_key")
+        .match_query(Matcher::Regex("^session_id=test_session_id&severity=ERROR&limit=2$".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": {
+                "logs": [
+                    {
+                        "log_id": "log-1",
+                        "event_id": "018e0b1c-8f1a-7c3e-9a2b-3c4d5e6f7a8b",
+                        "timestamp": "2024-03-01T12:30:05.042Z",
+                        "service": "matchmaking",
+                        "environment": "testing",
+                        "severity": "ERROR",
+                        "type": "test",
+                        "log": "rust tracing log captured",
+                        "data": { "target": "matchmaking" },
+                        "tags": { "message": "Queue stalled" }
+                    },
+                    { "log_id": "log-2", "severity": "ERROR" }
+                ],
+                "next_cursor": "page-2"
+            }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    // The last page has no cursor.
+    let m_second = mock_server.mock("GET", "/v1/intake/logs/query")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("session_id".into(), "test_session_id".into()),
+            Matcher::UrlEncoded("cursor".into(), "page-2".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "logs": [{ "log_id": "log-3", "severity": "ERROR" }], "next_cursor": null }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let query = LogQuery::for_session("test_session_id").with_severity("ERROR").with_page_size(2);
+    let logs = client.fetch_all(&query).await.unwrap();
+    m_first.assert();
+    m_second.assert();
+
+    let log_ids: Vec<&str> = logs.iter().map(|log| log.log_id.as_str()).collect();
+    assert_eq!(log_ids, ["log-1", "log-2", "log-3"]);
+    assert_eq!(logs[0].event_id.unwrap().to_string(), "018e0b1c-8f1a-7c3e-9a2b-3c4d5e6f7a8b");
+    assert_eq!(logs[0].tags["message"], "Queue stalled");
+    assert_eq!(logs[0].r#type, "test");
+    // Missing fields fall back to their defaults.
+    assert!(logs[1].event_id.is_none());
+    assert_eq!(logs[1].service, "");
+}
+
+// Verify that a refused query is reported with the endpoint's answer.
+#[tokio::test]
+async fn test_fetch_rejected() {
+    let (mut mock_server, client) = mock_service();
+
+    mock_server.mock("GET", "/v1/intake/logs/query")
+        .match_query(Matcher::Any)
+        .with_status(403)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": false,
+            "payload": { "error": "missing read permission" }
+        }).to_string())
+        .create();
+
+    let err = client.fetch_page(&LogQuery::for_service("matchmaking"), None).await.unwrap_err();
+    assert!(matches!(err, QueryError::Rejected(_)), "{:?}", err);
+    assert!(err.to_string().contains("403"), "{}", err);
+    assert!(err.to_string().contains("missing read permission"), "{}", err);
+}
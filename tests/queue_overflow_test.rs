@@ -0,0 +1,146 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{BatchConfig, OverflowPolicy};
+use std::time::Duration;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Returns the messages of the collected records, except drop summaries.
+fn messages(sink: &CollectingSink) -> Vec<String> {
+    sink.0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|record| record["log"] != "rust tracing events dropped")
+        .map(|record| record["tags"]["message"].as_str().unwrap_or_default().to_string())
+        .collect()
+}
+
+// Verify that records captured while the queue is full are dropped and counted in the drop
+// reports, keeping those queued first.
+#[tokio::test]
+async fn test_overflow_drops_newest() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer
+        .with_batching(BatchConfig::new().with_queue_capacity(2))
+        .with_drop_reports(Duration::from_millis(300));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // The worker only runs once the test awaits, so all five records are queued before it.
+    for wave in 1..=5 {
+        info!(wave, "Wave spawned");
+    }
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    guard.flush().await;
+
+    let waves: Vec<_> = sink.0.lock().unwrap().iter().filter_map(|record| record["tags"]["wave"].as_u64()).collect();
+    assert_eq!(waves, vec![1, 2]);
+    let records = sink.0.lock().unwrap();
+    let summary = records.iter().find(|record| record["log"] == "rust tracing events dropped").unwrap();
+    assert_eq!(summary["data"]["dropped"]["INFO"]["overflow"], 3);
+}
+
+// Verify that the oldest queued records make room for new ones under `DropOldest`.
+#[tokio::test]
+async fn test_overflow_drops_oldest() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_batching(
+        BatchConfig::new()
+            .with_queue_capacity(2)
+            .with_overflow_policy(OverflowPolicy::DropOldest),
+    );
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for wave in 1..=5 {
+        info!(wave, "Wave spawned");
+    }
+    guard.flush().await;
+
+    let waves: Vec<_> = sink.0.lock().unwrap().iter().filter_map(|record| record["tags"]["wave"].as_u64()).collect();
+    assert_eq!(waves, vec![4, 5]);
+}
+
+// Verify that records queued from many threads at once are all delivered.
+#[tokio::test]
+async fn test_many_producer_threads() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_batching(BatchConfig::new().with_max_batch_size(100));
+    let guard = layer.guard();
+    let dispatch = tracing::Dispatch::new(Registry::default().with(layer));
+
+    let producers: Vec<_> = (0..32)
+        .map(|thread| {
+            let dispatch = dispatch.clone();
+            std::thread::spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || {
+                    for tick in 0..100 {
+                        info!(thread, tick, "Tick");
+                    }
+                })
+            })
+        })
+        .collect();
+    for producer in producers {
+        producer.join().unwrap();
+    }
+    guard.flush().await;
+
+    assert_eq!(messages(&sink).len(), 32 * 100);
+}
+
+// Verify that a record that does not fit into the memory budget next to those already queued
+// is dropped, while smaller ones after it are still queued.
+#[tokio::test]
+async fn test_memory_budget_drops_newest() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer
+        .with_batching(BatchConfig::new().with_memory_budget(Some(8 * 1024)))
+        .with_drop_reports(Duration::from_millis(300));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let level_state = "x".repeat(5000);
+    info!(wave = 1, "Wave spawned");
+    info!(wave = 2, level_state, "Wave spawned");
+    info!(wave = 3, level_state, "Wave spawned");
+    info!(wave = 4, "Wave spawned");
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    guard.flush().await;
+
+    let waves: Vec<_> = sink.0.lock().unwrap().iter().filter_map(|record| record["tags"]["wave"].as_u64()).collect();
+    assert_eq!(waves, vec![1, 2, 4]);
+    let records = sink.0.lock().unwrap();
+    let summary = records.iter().find(|record| record["log"] == "rust tracing events dropped").unwrap();
+    assert_eq!(summary["data"]["dropped"]["INFO"]["overflow"], 1);
+}
+
+// Verify that the oldest queued records make room within the memory budget under
+// `DropOldest`, and that a record larger than the whole budget is dropped on its own.
+#[tokio::test]
+async fn test_memory_budget_drops_oldest() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_batching(
+        BatchConfig::new()
+            .with_memory_budget(Some(8 * 1024))
+            .with_overflow_policy(OverflowPolicy::DropOldest),
+    );
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let level_state = "x".repeat(5000);
+    info!(wave = 1, level_state, "Wave spawned");
+    info!(wave = 2, level_state, "Wave spawned");
+    info!(wave = 3, "Wave spawned");
+    info!(wave = 4, level_state = "x".repeat(10_000), "Wave spawned");
+    guard.flush().await;
+
+    let waves: Vec<_> = sink.0.lock().unwrap().iter().filter_map(|record| record["tags"]["wave"].as_u64()).collect();
+    assert_eq!(waves, vec![2, 3]);
+}
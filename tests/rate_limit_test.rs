@@ -0,0 +1,60 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{set_diagnostic_handler, DiagnosticKind, RateLimits};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Returns how many records a sink received from each target.
+fn count(sink: &CollectingSink<String>, target: &str) -> usize {
+    sink.0.lock().unwrap().iter().filter(|received| *received == target).count()
+}
+
+// Verify that each target prefix has a bucket of its own, that unlimited prefixes are exempt
+// from the default limit, and that dropped events are reported once the bucket refills.
+#[tokio::test]
+async fn test_per_target_rate_limits() {
+    let diagnostics = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&diagnostics);
+    set_diagnostic_handler(move |diagnostic| {
+        collected.lock().unwrap().push((diagnostic.kind(), diagnostic.message().to_string()));
+    });
+
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::new(|record| {
+        record.request().data["target"].as_str().unwrap_or_default().to_string()
+    }))
+    .await;
+    let layer = layer.with_rate_limits(
+        RateLimits::new()
+            .with_default_limit(3.0)
+            .with_limit("netcode", 5.0)
+            .with_unlimited("audit"),
+    );
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for _ in 0..20 {
+        info!(target: "netcode::replication", "Packet resent");
+        info!(target: "audit", "Item granted");
+        info!(target: "matchmaking", "Queue polled");
+    }
+    guard.flush().await;
+
+    assert_eq!(count(&sink, "netcode::replication"), 5);
+    assert_eq!(count(&sink, "audit"), 20);
+    assert_eq!(count(&sink, "matchmaking"), 3);
+    assert!(diagnostics.lock().unwrap().is_empty());
+
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    info!(target: "netcode", "Packet resent");
+    guard.flush().await;
+
+    assert_eq!(count(&sink, "netcode"), 1);
+    let diagnostics = diagnostics.lock().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].0, DiagnosticKind::Dropped);
+    assert_eq!(diagnostics[0].1, "Dropped 15 events from netcode over the rate limit");
+}
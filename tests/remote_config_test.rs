@@ -0,0 +1,145 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use mockito::Matcher;
+use pogr_tracing_rs::{PogrAppender, PogrLayer, RemoteConfig, SinkMode, FORWARDING_TOGGLE};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn, Level};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service, and return an appender for it.
+async fn mock_service() -> (mockito::ServerGuard, PogrAppender) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), None).await;
+    (mock_server, appender)
+}
+
+// Log one event per level.
+fn log_events() {
+    debug!("Tick took 3 ms");
+    info!("Player joined");
+    warn!("Ping above 200 ms");
+}
+
+// Verify that the remote configuration is fetched in the session and applied to the layer.
+#[tokio::test]
+async fn test_remote_config_applied() {
+    let (mut mock_server, appender) = mock_service().await;
+    let m_config = mock_server.mock("GET", "/live-ops/logging")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": {
+                "levels": "info,netcode=debug",
+                "sampling": { "info": 0.0 },
+                "toggles": { "new_matchmaker": true }
+            }
+        }).to_string())
+        .create();
+
+    let sink = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror)
+        .with_remote_config(RemoteConfig::new().with_endpoint(format!("{}/live-ops/logging", mock_server.url())));
+    let reload = layer.reload_handle();
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while reload.toggle("new_matchmaker").is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    m_config.assert();
+    assert_eq!(reload.target_levels().level_for("netcode::transport"), LevelFilter::DEBUG);
+    assert_eq!(reload.level_sampling(Level::INFO), Some(0.0));
+
+    log_events();
+    guard.flush().await;
+    assert_eq!(*sink.0.lock().unwrap(), ["Ping above 200 ms"]);
+}
+
+// Verify that the reload handle changes the thresholds of an installed layer, and that turning
+// off the forwarding toggle pauses forwarding.
+#[tokio::test]
+async fn test_reload_handle() {
+    let (_mock_server, appender) = mock_service().await;
+    let sink = Arc::new(CollectingSink::messages());
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror)
+        .with_target_levels("warn".parse().unwrap());
+    let reload = layer.reload_handle();
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    log_events();
+    reload.set_target_levels("debug".parse().unwrap());
+    log_events();
+    reload.set_toggle(FORWARDING_TOGGLE, false);
+    log_events();
+    guard.flush().await;
+
+    assert_eq!(
+        *sink.0.lock().unwrap(),
+        ["Ping above 200 ms", "Tick took 3 ms", "Player joined", "Ping above 200 ms"]
+    );
+}
+
+// Verify that an invalid document changes nothing.
+#[tokio::test]
+async fn test_remote_config_invalid() {
+    let (mut mock_server, appender) = mock_service().await;
+    let m_config = mock_server.mock("GET", "/v1/intake/config")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "levels": "netcode=loud", "toggles": { "new_matchmaker": true } }
+        }).to_string())
+        .create();
+
+    let layer = PogrLayer::new(appender)
+        .with_target_levels("warn".parse().unwrap())
+        .with_remote_config(RemoteConfig::new().with_endpoint(format!("{}/v1/intake/config", mock_server.url())));
+    let reload = layer.reload_handle();
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !m_config.matched() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(reload.target_levels().default_level(), LevelFilter::WARN);
+    assert_eq!(reload.toggle("new_matchmaker"), None);
+}
@@ -0,0 +1,175 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{
+    ErrorClass, ExponentialBackoff, FixedInterval, PogrAppender, PogrLayer, RetryPolicy, SinkMode,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::error;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// A policy that retries right away, once, and remembers what it was asked.
+#[derive(Default)]
+struct RecordingPolicy(Mutex<Vec<(u32, ErrorClass, Option<Duration>)>>);
+
+impl RetryPolicy for RecordingPolicy {
+    fn next_delay(&self, attempt: u32, class: ErrorClass, retry_after: Option<Duration>) -> Option<Duration> {
+        self.0.lock().unwrap().push((attempt, class, retry_after));
+        (attempt == 1).then_some(Duration::ZERO)
+    }
+}
+
+// Build an appender submitting to the mock intake.
+fn appender(mock_server: &mockito::ServerGuard) -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_retry".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint: format!("{}/v1/intake/logs", mock_server.url()),
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Mock `count` failed submissions with the given status, answered before any other mock.
+fn mock_failures(mock_server: &mut mockito::ServerGuard, status: usize, count: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(status)
+        .with_body("Intake unavailable")
+        .expect(count)
+        .create()
+}
+
+// Mock `count` accepted submissions.
+fn mock_success(mock_server: &mut mockito::ServerGuard, count: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(count)
+        .create()
+}
+
+// Log an error through a layer with the given retry policy, if any, and return the messages
+// handed to the fallback sink.
+async fn log_with(mock_server: &mockito::ServerGuard, policy: Option<Box<dyn FnOnce(PogrLayer) -> PogrLayer>>) -> Vec<String> {
+    let sink = Arc::new(CollectingSink::messages());
+    let mut layer = PogrLayer::new(appender(mock_server)).with_sink(Arc::clone(&sink), SinkMode::Fallback);
+    if let Some(policy) = policy {
+        layer = policy(layer);
+    }
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("worth retrying");
+    guard.flush().await;
+    let messages = sink.0.lock().unwrap().clone();
+    messages
+}
+
+// Verify the delays of the exponential backoff: doubling up to the maximum, honoring a longer
+// Retry-After, and giving up after the last attempt or on failures that are not transient.
+#[test]
+fn test_exponential_backoff() {
+    let backoff = ExponentialBackoff::new()
+        .with_initial_delay(Duration::from_millis(100))
+        .with_max_delay(Duration::from_millis(500))
+        .with_max_attempts(6);
+    let delays: Vec<_> = (1..=6).map(|attempt| backoff.next_delay(attempt, ErrorClass::Transport, None)).collect();
+    let expected = [100, 200, 400, 500, 500].map(|millis| Some(Duration::from_millis(millis)));
+    assert_eq!(delays[..5], expected);
+    assert_eq!(delays[5], None);
+
+    let retry_after = Some(Duration::from_secs(2));
+    assert_eq!(backoff.next_delay(1, ErrorClass::RateLimited, retry_after), retry_after);
+    assert_eq!(backoff.next_delay(1, ErrorClass::Rejected, None), None);
+    assert_eq!(backoff.next_delay(1, ErrorClass::InvalidResponse, None), None);
+}
+
+// Verify the delays of the fixed interval.
+#[test]
+fn test_fixed_interval() {
+    let interval = FixedInterval::new(Duration::from_millis(250)).with_max_attempts(3);
+    assert_eq!(interval.next_delay(1, ErrorClass::ServerError, None), Some(Duration::from_millis(250)));
+    assert_eq!(interval.next_delay(2, ErrorClass::ServerError, Some(Duration::from_millis(100))), Some(Duration::from_millis(250)));
+    assert_eq!(interval.next_delay(3, ErrorClass::ServerError, None), None);
+    assert_eq!(interval.next_delay(1, ErrorClass::Rejected, None), None);
+}
+
+// Verify that failed submissions are not retried without a policy.
+#[tokio::test]
+async fn test_no_retries_by_default() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_failure = mock_failures(&mut mock_server, 500, 1);
+    let m_success = mock_success(&mut mock_server, 0);
+
+    assert_eq!(log_with(&mock_server, None).await, ["worth retrying"]);
+    m_failure.assert();
+    m_success.assert();
+}
+
+// Verify that a transient failure is retried until the intake accepts the records.
+#[tokio::test]
+async fn test_retried_until_accepted() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_failure = mock_failures(&mut mock_server, 502, 2);
+    let m_success = mock_success(&mut mock_server, 1);
+
+    let policy = Box::new(|layer: PogrLayer| layer.with_retry_policy(FixedInterval::new(Duration::from_millis(10))));
+    assert!(log_with(&mock_server, Some(policy)).await.is_empty());
+    m_failure.assert();
+    m_success.assert();
+}
+
+// Verify that the records go to the fallback sinks once the policy gives up, and that
+// rejections are not retried.
+#[tokio::test]
+async fn test_policy_gives_up() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_failure = mock_failures(&mut mock_server, 500, 2);
+    let policy = Box::new(|layer: PogrLayer| {
+        layer.with_retry_policy(FixedInterval::new(Duration::from_millis(10)).with_max_attempts(2))
+    });
+    assert_eq!(log_with(&mock_server, Some(policy)).await, ["worth retrying"]);
+    m_failure.assert();
+
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_rejected = mock_failures(&mut mock_server, 400, 1);
+    let policy = Box::new(|layer: PogrLayer| layer.with_retry_policy(ExponentialBackoff::new()));
+    assert_eq!(log_with(&mock_server, Some(policy)).await, ["worth retrying"]);
+    m_rejected.assert();
+}
+
+// Verify that the policy is given the attempt, the class of the failure and the Retry-After
+// of the response.
+#[tokio::test]
+async fn test_policy_inputs() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_failure = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(500)
+        .with_header("retry-after", "7")
+        .with_body("Intake unavailable")
+        .expect(1)
+        .create();
+    let m_rate_limited = mock_failures(&mut mock_server, 429, 1);
+
+    let recorded = Arc::new(RecordingPolicy::default());
+    let policy = Arc::clone(&recorded);
+    let policy = Box::new(move |layer: PogrLayer| layer.with_retry_policy(policy));
+    assert_eq!(log_with(&mock_server, Some(policy)).await, ["worth retrying"]);
+    m_failure.assert();
+    m_rate_limited.assert();
+
+    assert_eq!(
+        *recorded.0.lock().unwrap(),
+        [(1, ErrorClass::ServerError, Some(Duration::from_secs(7))), (2, ErrorClass::RateLimited, None)]
+    );
+}
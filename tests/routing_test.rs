@@ -0,0 +1,128 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{BatchConfig, LevelRoute, PogrAppender, PogrLayer};
+use std::time::Duration;
+use tracing::{error, info, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service, and return its init, bulk logs and priority logs endpoints.
+fn mock_service() -> (mockito::ServerGuard, String, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (
+        mock_server,
+        format!("{}/v1/intake/init", base_url),
+        format!("{}/v1/intake/logs", base_url),
+        format!("{}/v1/intake/priority", base_url),
+    )
+}
+
+// Verify that errors are sent right away to the priority endpoint, while the rest waits to be
+// batched for the bulk endpoint.
+#[tokio::test]
+async fn test_level_route() {
+    let (mut mock_server, init_endpoint, logs_endpoint, priority_endpoint) = mock_service();
+    let log_response_success = serde_json::json!({
+        "success": true,
+        "payload": { "log_id": "test_log_id" }
+    }).to_string();
+
+    let m_priority = mock_server.mock("POST", "/v1/intake/priority")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "severity": "ERROR",
+            "tags": { "message": "Match server crashed" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(&log_response_success)
+        .expect(1)
+        .create();
+    let m_bulk = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::Regex("^\\[.*Queue opened.*Match started.*\\]$".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {}}"#)
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_batching(BatchConfig::new().with_max_batch_size(100).with_linger(Duration::from_secs(60)))
+        .with_level_route(LevelRoute::new(Level::ERROR).with_logs_endpoint(priority_endpoint));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Queue opened");
+    error!("Match server crashed");
+    info!("Match started");
+
+    // The error does not wait for the bulk batch to linger.
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !m_priority.matched() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert!(!m_bulk.matched());
+
+    guard.flush().await;
+    m_priority.assert();
+    m_bulk.assert();
+}
+
+// Verify that a record takes the route of the most severe level it reaches, with that route's
+// batching.
+#[tokio::test]
+async fn test_level_route_most_severe() {
+    let (mut mock_server, init_endpoint, logs_endpoint, priority_endpoint) = mock_service();
+
+    let m_priority = mock_server.mock("POST", "/v1/intake/priority")
+        .match_body(Matcher::PartialJson(serde_json::json!({ "severity": "ERROR" })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create();
+    // Warnings share one batch on the bulk endpoint.
+    let m_warnings = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::Regex("^\\[.*WARN.*WARN.*\\]$".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {}}"#)
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_level_route(LevelRoute::new(Level::WARN).with_batching(BatchConfig::new().with_max_batch_size(10)))
+        .with_level_route(LevelRoute::new(Level::ERROR).with_logs_endpoint(priority_endpoint));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    warn!("Ping above 200 ms");
+    error!("Match server crashed");
+    warn!("Packet loss above 5%");
+    guard.flush().await;
+
+    m_priority.assert();
+    m_warnings.assert();
+}
@@ -0,0 +1,49 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{PogrLayer, SinkMode};
+use std::sync::Arc;
+use std::thread;
+use tracing::{info, Dispatch};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that events emitted from a thread without a tokio runtime are delivered by the
+// runtime the layer was created in, instead of panicking.
+#[tokio::test]
+async fn test_event_outside_runtime() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let guard = layer.guard();
+    let dispatch = Dispatch::new(Registry::default().with(layer));
+
+    thread::spawn(move || {
+        tracing::dispatcher::with_default(&dispatch, || info!("Asset loaded"));
+    })
+    .join()
+    .unwrap();
+
+    guard.flush().await;
+    assert_eq!(sink.0.lock().unwrap()[0]["message"], "Asset loaded");
+}
+
+// Verify that a layer created outside of any runtime runs its worker on a thread of its own.
+#[tokio::test]
+async fn test_layer_outside_runtime() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let appender = layer.appender.lock().await.clone();
+    let collected = Arc::clone(&sink);
+
+    let guard = thread::spawn(move || {
+        let layer = PogrLayer::new(appender)
+            .with_intake_delivery(false)
+            .with_sink(collected, SinkMode::Mirror);
+        let guard = layer.guard();
+        let dispatch = Dispatch::new(Registry::default().with(layer));
+        tracing::dispatcher::with_default(&dispatch, || info!("Asset loaded"));
+        guard
+    })
+    .join()
+    .unwrap();
+
+    guard.flush().await;
+    assert_eq!(sink.0.lock().unwrap()[0]["message"], "Asset loaded");
+}
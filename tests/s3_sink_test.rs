@@ -0,0 +1,112 @@
+#![cfg(feature = "s3")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use flate2::read::GzDecoder;
+use pogr_tracing_rs::{BatchConfig, ManualClock, PogrAppender, PogrLayer, S3ArchiveSink, S3Partitioning, SinkMode};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::{info, warn};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Verify that a batch is archived as one signed, compressed NDJSON object.
+#[tokio::test]
+async fn test_s3_archive() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+
+    // Records are still delivered to POGR, in a single batch.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .create();
+
+    // The object lands under the prefix, in the hourly partition of the first record. The
+    // `=` of the partitions is percent-encoded in the path, as signing requires.
+    let uploaded = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&uploaded);
+    let m_put = mock_server.mock(
+        "PUT",
+        mockito::Matcher::Regex(
+            r"^/archive/pogr/game/year%3D2024/month%3D03/day%3D01/hour%3D12/20240301T123005Z-[0-9a-f-]{36}\.ndjson\.gz$".to_string(),
+        ),
+    )
+        .match_header(
+            "authorization",
+            mockito::Matcher::Regex(
+                r"^AWS4-HMAC-SHA256 Credential=test_key/\d{8}/eu-west-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature=[0-9a-f]{64}$".to_string(),
+            ),
+        )
+        .match_header("content-type", "application/gzip")
+        .with_status(200)
+        .with_body_from_request(move |request| {
+            *captured.lock().unwrap() = request.body().unwrap().clone();
+            Vec::new()
+        })
+        .expect(1)
+        .create();
+
+    let archive = S3ArchiveSink::new("archive")
+        .with_endpoint(mock_server.url())
+        .with_region("eu-west-1")
+        .with_credentials("test_key", "test_secret")
+        .with_prefix("pogr/game")
+        .with_partitioning(S3Partitioning::Hourly);
+
+    // 2024-03-01T12:30:05Z
+    let clock = Arc::new(ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_709_296_205)));
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_clock(clock)
+        .with_batching(BatchConfig::new().with_max_batch_size(10).with_linger(Duration::from_secs(60)))
+        .with_sink(archive, SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    warn!(match_id = 7, "match abandoned");
+    info!(match_id = 8, "match started");
+    guard.flush().await;
+    m_logs.assert();
+    m_put.assert();
+
+    // The object holds one JSON payload per line.
+    let mut ndjson = String::new();
+    GzDecoder::new(uploaded.lock().unwrap().as_slice()).read_to_string(&mut ndjson).unwrap();
+    let lines: Vec<serde_json::Value> = ndjson.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["tags"]["match_id"], 7);
+    assert_eq!(lines[1]["tags"]["match_id"], 8);
+    assert_eq!(lines[0]["timestamp"], "2024-03-01T12:30:05.000Z");
+}
@@ -0,0 +1,99 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{KeySampling, PogrAppender, PogrLayer, SinkMode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Create a layer that only delivers to a collecting sink, with the given sampling.
+async fn sampled_layer(sampling: KeySampling) -> (mockito::ServerGuard, PogrLayer, Arc<CollectingSink>) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let sink = Arc::new(CollectingSink::tags());
+    let appender = PogrAppender::new(Some(format!("{}/v1/intake/init", base_url)), None).await;
+    let layer = PogrLayer::new(appender)
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror)
+        .with_key_sampling(sampling);
+    (mock_server, layer, sink)
+}
+
+// Emit three events for each of 1000 players and count the kept events per player.
+async fn sample_players(sampling: KeySampling) -> HashMap<i64, usize> {
+    let (_mock_server, layer, sink) = sampled_layer(sampling).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for player_id in 0..1000 {
+        info!(player_id, "Match started");
+        info!(player_id, "Match ended");
+        // The key can also come from an enclosing span.
+        info_span!("session", player_id).in_scope(|| info!("Logged out"));
+    }
+    guard.flush().await;
+
+    let mut kept = HashMap::new();
+    for tags in sink.0.lock().unwrap().iter() {
+        *kept.entry(tags["player_id"].as_i64().unwrap()).or_insert(0) += 1;
+    }
+    kept
+}
+
+// Verify that about the configured fraction of players is kept, each with all their events,
+// and that the same players are kept every time.
+#[tokio::test]
+async fn test_key_sampling() {
+    let kept = sample_players(KeySampling::new("player_id", 0.1)).await;
+    assert!((60..=140).contains(&kept.len()), "{} players kept", kept.len());
+    assert!(kept.values().all(|events| *events == 3), "{:?}", kept);
+
+    let again = sample_players(KeySampling::new("player_id", 0.1)).await;
+    let mut players: Vec<_> = kept.keys().collect();
+    let mut players_again: Vec<_> = again.keys().collect();
+    players.sort();
+    players_again.sort();
+    assert_eq!(players, players_again);
+
+    // Raising the rate keeps the players that were already sampled.
+    let more = sample_players(KeySampling::new("player_id", 0.5)).await;
+    assert!(kept.keys().all(|player_id| more.contains_key(player_id)));
+}
+
+// Verify that events without the key field are kept unless configured otherwise.
+#[tokio::test]
+async fn test_key_sampling_unkeyed() {
+    for (sampling, expected) in [
+        (KeySampling::new("player_id", 0.0), 1),
+        (KeySampling::new("player_id", 1.0).with_unkeyed(false), 1),
+    ] {
+        let (_mock_server, layer, sink) = sampled_layer(sampling).await;
+        let guard = layer.guard();
+        let subscriber = Registry::default().with(layer);
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        info!("Server started");
+        info!(player_id = 7, "Logged in");
+        guard.flush().await;
+
+        assert_eq!(sink.0.lock().unwrap().len(), expected);
+    }
+}
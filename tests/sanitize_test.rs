@@ -0,0 +1,66 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use tracing::{error, info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// An error whose message is styled for a terminal.
+#[derive(Debug)]
+struct StyledError;
+
+impl std::fmt::Display for StyledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("\x1b[1mconnection reset\x1b[0m")
+    }
+}
+
+impl std::error::Error for StyledError {}
+
+// Verify that escape sequences and control characters are stripped from messages, string,
+// error and span fields, while tabs and line breaks are kept.
+#[tokio::test]
+async fn test_sanitization() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let span = info_span!("match", map = "\x1b[32mde_dust2\x1b[39m");
+    span.in_scope(|| {
+        info!(
+            player = "\x1b]8;;https://pogr.io\x07ana\x1b]8;;\x1b\\",
+            status = %"\x1b(Bready\x00\x7f",
+            "\x1b[31;1mMatch\x1b[0m started\tat\r\nround\u{9b}2K 1\x07"
+        );
+        let err = StyledError;
+        error!(error = &err as &dyn std::error::Error, "disconnect");
+    });
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap().clone();
+    let tags = &records[0]["tags"];
+    assert_eq!(tags["message"], "Match started\tat\r\nround 1");
+    assert_eq!(tags["player"], "ana");
+    assert_eq!(tags["status"], "ready");
+    assert_eq!(tags["map"], "de_dust2");
+    assert_eq!(records[1]["tags"]["error"], "connection reset");
+}
+
+// Verify that sanitization can be disabled.
+#[tokio::test]
+async fn test_sanitization_disabled() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_sanitization(false);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("\x1b[31mred\x1b[0m");
+    guard.flush().await;
+
+    assert_eq!(sink.0.lock().unwrap()[0]["tags"]["message"], "\x1b[31mred\x1b[0m");
+}
@@ -0,0 +1,88 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{
+    set_diagnostic_handler, DiagnosticKind, PogrAppender, PogrLayer, SaturationAlerts, SinkMode,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that crossing high-water marks and slow deliveries are reported once each, locally
+// and to POGR, and that a mark is re-armed once the queue drains.
+#[tokio::test]
+async fn test_saturation_alerts() {
+    let diagnostics = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&diagnostics);
+    set_diagnostic_handler(move |diagnostic| {
+        if diagnostic.kind() == DiagnosticKind::Saturation {
+            collected.lock().unwrap().push(diagnostic.message().to_string());
+        }
+    });
+
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization and log submissions.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    let mirror = Arc::new(CollectingSink::new(|record| record.request().log.clone()));
+    let layer = PogrLayer::new(appender)
+        .with_sink(Arc::clone(&mirror), SinkMode::Mirror)
+        .with_saturation_alerts(
+            SaturationAlerts::new()
+                .with_high_water_marks([40, 10])
+                .with_latency_threshold(Duration::ZERO)
+                .with_pogr_events(true),
+        );
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // The worker cannot run before the test awaits, so the records pile up.
+    for _ in 0..50 {
+        info!("Packet resent");
+    }
+    guard.flush().await;
+    {
+        let diagnostics = diagnostics.lock().unwrap();
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics[0].ends_with("over the high-water mark of 10"));
+        assert!(diagnostics[1].ends_with("over the high-water mark of 40"));
+        assert!(diagnostics[2].ends_with("over the threshold of 0 ms"));
+    }
+
+    for _ in 0..15 {
+        info!("Packet resent");
+    }
+    guard.flush().await;
+
+    let diagnostics = diagnostics.lock().unwrap();
+    assert_eq!(diagnostics.len(), 4);
+    assert!(diagnostics[3].ends_with("over the high-water mark of 10"));
+    let alerts = mirror.0.lock().unwrap().iter().filter(|log| *log == "rust tracing telemetry saturated").count();
+    assert_eq!(alerts, 4);
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "schema")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{PogrAppender, PogrLayer, SchemaValidation, SinkMode};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Create a sink that keeps the message and validation error of the records it receives.
+fn validating_sink() -> CollectingSink<(String, Option<String>)> {
+    CollectingSink::new(|record| {
+        let message = record.request().tags["message"].as_str().unwrap_or_default().to_string();
+        (message, record.validation_error().map(str::to_string))
+    })
+}
+
+// Start a mock POGR service, and return an appender for it.
+async fn mock_service() -> (mockito::ServerGuard, PogrAppender) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    (mock_server, appender)
+}
+
+// Verify that records violating the schemas go to the fallback sinks with a validation error,
+// and that valid records are submitted.
+#[tokio::test]
+async fn test_schema_validation() {
+    let (mut mock_server, appender) = mock_service().await;
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create();
+
+    let schema = SchemaValidation::new()
+        .with_tags_schema(&json!({
+            "type": "object",
+            "properties": { "score": { "type": "integer" } },
+            "required": ["score"],
+        }))
+        .unwrap()
+        .with_data_schema(&json!({ "type": "object", "required": ["target"] }))
+        .unwrap();
+    let mirror = Arc::new(validating_sink());
+    let fallback = Arc::new(validating_sink());
+    let layer = PogrLayer::new(appender)
+        .with_schema_validation(schema)
+        .with_sink(Arc::clone(&mirror), SinkMode::Mirror)
+        .with_sink(Arc::clone(&fallback), SinkMode::Fallback);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!(score = 1200, "Match won");
+    info!(score = "high", "Match lost");
+    guard.flush().await;
+
+    m_logs.assert();
+    assert_eq!(*mirror.0.lock().unwrap(), [("Match won".to_string(), None)]);
+    let fallback = fallback.0.lock().unwrap();
+    assert_eq!(fallback.len(), 1);
+    assert_eq!(fallback[0].0, "Match lost");
+    assert!(fallback[0].1.as_deref().unwrap().starts_with("tags/score: "));
+}
+
+// Verify that a schema which cannot be compiled is rejected.
+#[test]
+fn test_invalid_schema() {
+    let err = SchemaValidation::new().with_tags_schema(&json!({ "type": 12 })).unwrap_err();
+    assert!(err.to_string().starts_with("invalid JSON Schema: "));
+}
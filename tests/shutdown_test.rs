@@ -0,0 +1,87 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{set_diagnostic_handler, DiagnosticKind, PogrAppender, PogrLayer, DEFAULT_SHUTDOWN_TIMEOUT};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service, and return an appender for it.
+async fn mock_service() -> (mockito::ServerGuard, PogrAppender) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    (mock_server, appender)
+}
+
+// Verify that a shutdown flushes the pending records, ends the session, and ignores the
+// events emitted afterwards.
+#[tokio::test]
+async fn test_shutdown() {
+    let (mut mock_server, appender) = mock_service().await;
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "1"}}"#)
+        .expect(1)
+        .create();
+    let m_end = mock_server.mock("POST", "/v1/intake/end")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .with_status(200)
+        .with_body(r#"{"success": true}"#)
+        .expect(1)
+        .create();
+
+    let layer = PogrLayer::new(appender);
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Server draining");
+    guard.shutdown(DEFAULT_SHUTDOWN_TIMEOUT).await.unwrap();
+    m_end.assert();
+
+    info!("Emitted after the shutdown");
+    guard.flush().await;
+    m_logs.assert();
+}
+
+// Verify that a failure to end the session is reported as a diagnostic, without failing the
+// shutdown.
+#[tokio::test]
+async fn test_shutdown_end_failure() {
+    let (mut mock_server, appender) = mock_service().await;
+    mock_server.mock("POST", "/v1/intake/end")
+        .with_status(500)
+        .with_body("Internal error")
+        .create();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let collected = Arc::clone(&reports);
+    set_diagnostic_handler(move |diagnostic| {
+        if diagnostic.message().contains("end the POGR session") {
+            collected.lock().unwrap().push(diagnostic.kind());
+        }
+    });
+
+    let guard = PogrLayer::new(appender).guard();
+    assert!(guard.shutdown(DEFAULT_SHUTDOWN_TIMEOUT).await.is_ok());
+    assert_eq!(*reports.lock().unwrap(), [DiagnosticKind::Delivery]);
+}
@@ -0,0 +1,34 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use std::time::Duration;
+use tracing::{info, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that events inside a span older than the threshold are flagged with its name and
+// age, and that other events are not.
+#[tokio::test]
+async fn test_slow_span_flag() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::tags()).await;
+    let layer = layer.with_slow_span_threshold(Duration::from_millis(20));
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info!("Outside any span");
+    {
+        let _match = info_span!("run_match").entered();
+        info!("Match started");
+        std::thread::sleep(Duration::from_millis(30));
+        let _round = info_span!("play_round").entered();
+        info!("Round ended");
+    }
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert!(records[0].get("slow").is_none());
+    assert!(records[1].get("slow").is_none());
+    assert_eq!(records[2]["slow"], true);
+    assert_eq!(records[2]["slow_span"], "run_match");
+    assert!(records[2]["slow_duration_ms"].as_f64().unwrap() >= 30.0);
+}
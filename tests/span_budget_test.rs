@@ -0,0 +1,65 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{SpanBudgets, TargetLevels};
+use std::time::Duration;
+use tracing::{debug_span, info_span};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Verify that a span closing after its budget produces a warning with its fields and duration,
+// and that spans within their budget or without one do not.
+#[tokio::test]
+async fn test_span_over_budget() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer.with_span_budgets(
+        SpanBudgets::new()
+            .with_budget("load_level", Duration::from_millis(20))
+            .with_budget("save_profile", Duration::from_secs(60)),
+    );
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    {
+        let _span = info_span!("load_level", level_name = "dust2").entered();
+        std::thread::sleep(Duration::from_millis(30));
+    }
+    drop(info_span!("save_profile").entered());
+    drop(info_span!("unbudgeted").entered());
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["severity"], "WARN");
+    assert_eq!(records[0]["log"], "rust tracing span over budget");
+    assert_eq!(records[0]["tags"]["level_name"], "dust2");
+    assert_eq!(records[0]["data"]["name"], "load_level");
+    assert_eq!(records[0]["data"]["budget_ms"], 20.0);
+    assert!(records[0]["data"]["duration_ms"].as_f64().unwrap() >= 30.0);
+}
+
+// Verify that target budgets take precedence, and that warnings follow the target thresholds
+// at WARN rather than the span's own level.
+#[tokio::test]
+async fn test_span_budget_targets() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let layer = layer
+        .with_target_levels("warn,chat=off".parse::<TargetLevels>().unwrap())
+        .with_span_budgets(
+            SpanBudgets::new()
+                .with_budget("tick", Duration::ZERO)
+                .with_target_budget("physics", "tick", Duration::from_secs(60)),
+        );
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    drop(debug_span!(target: "netcode", "tick").entered());
+    drop(debug_span!(target: "physics::solver", "tick").entered());
+    drop(debug_span!(target: "chat", "tick").entered());
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["data"]["target"], "netcode");
+}
@@ -0,0 +1,144 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{PogrAppender, PogrLayer, SpanEvents};
+use tracing::info_span;
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Verify that enter/exit records are emitted only for spans on the selected targets.
+#[tokio::test]
+async fn test_pogr_layer_span_events() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    let _m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let log_response_success = serde_json::json!({
+        "success": true,
+        "payload": { "log_id": "test_log_id" }
+    }).to_string();
+
+    // Expect one enter record carrying the span's fields.
+    let m_enter = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "log": "rust tracing span enter",
+            "data": { "name": "find_match", "span_event": "enter" },
+            "tags": { "queue": "ranked" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(&log_response_success)
+        .expect(1)
+        .create();
+
+    // Expect one exit record for the same span.
+    let m_exit = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "log": "rust tracing span exit",
+            "data": { "name": "find_match", "span_event": "exit" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(&log_response_success)
+        .expect(1)
+        .create();
+
+    // Only spans under the `matchmaking` target produce transition records.
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_span_events(SpanEvents::for_targets(["matchmaking"]));
+    let guard = layer.guard();
+
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // This span is selected and is entered and exited once.
+    info_span!(target: "matchmaking::queue", "find_match", queue = "ranked").in_scope(|| {});
+    // This span is not selected and must not produce any records.
+    info_span!(target: "inventory", "sync_items").in_scope(|| {});
+
+    guard.flush().await;
+    m_enter.assert();
+    m_exit.assert();
+}
+
+// Verify that follows-from relationships are included in span records.
+#[tokio::test]
+async fn test_pogr_layer_span_follows_from() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    let _m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_span_events(
+        SpanEvents::for_targets(["jobs::join"])
+            .with_enter(false)
+            .with_exit(false)
+            .with_close(true),
+    );
+    let guard = layer.guard();
+
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // Two independent jobs are joined by a third span.
+    let first = info_span!(target: "jobs::worker", "job", job = 1);
+    let second = info_span!(target: "jobs::worker", "job", job = 2);
+    let join = info_span!(target: "jobs::join", "join_results");
+    join.follows_from(&first);
+    join.follows_from(&second);
+
+    // Expect the join span's close record to reference both jobs.
+    let m_close = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "data": {
+                "name": "join_results",
+                "span_event": "close",
+                "follows_from": [first.id().unwrap().into_u64(), second.id().unwrap().into_u64()]
+            }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    drop(join);
+
+    guard.flush().await;
+    m_close.assert();
+}
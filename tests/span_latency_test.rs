@@ -0,0 +1,128 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{PogrAppender, PogrLayer, SpanLatency};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info_span;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service whose metrics intake records the reports it receives.
+fn mock_service() -> (mockito::ServerGuard, String, String, Arc<Mutex<Vec<serde_json::Value>>>) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&reports);
+    mock_server.mock("POST", "/v1/intake/metrics")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            received.lock().unwrap().push(serde_json::from_slice(request.body().unwrap()).unwrap());
+            serde_json::json!({ "success": true, "payload": {} }).to_string().into()
+        })
+        .create();
+
+    (
+        mock_server,
+        format!("{}/v1/intake/init", base_url),
+        format!("{}/v1/intake/metrics", base_url),
+        reports,
+    )
+}
+
+// Verify that closed spans are summarized per span name, with their quantiles in milliseconds.
+#[tokio::test]
+async fn test_span_latency_report() {
+    let (_mock_server, init_endpoint, metrics_endpoint, reports) = mock_service();
+
+    let appender = PogrAppender::new(Some(init_endpoint), None).await;
+    let layer = PogrLayer::new(appender).with_span_latency(SpanLatency::new().with_endpoint(metrics_endpoint));
+    let handle = layer.span_latency_handle().unwrap();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    // Nine fast spans and a slow one: the median is fast, the p99 is the slow span.
+    for _ in 0..9 {
+        let _span = info_span!("load_level").entered();
+    }
+    {
+        let _span = info_span!("load_level").entered();
+        std::thread::sleep(Duration::from_millis(30));
+    }
+    drop(info_span!("save_game").entered());
+    handle.flush().await;
+
+    {
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        let summaries = reports[0].as_array().unwrap();
+        assert_eq!(summaries.len(), 2);
+
+        let load_level = summaries
+            .iter()
+            .find(|summary| summary["labels"]["span"] == "load_level")
+            .unwrap();
+        assert_eq!(load_level["name"], "span_duration");
+        assert_eq!(load_level["type"], "summary");
+        assert_eq!(load_level["labels"]["target"], "span_latency_test");
+        assert_eq!(load_level["unit"], "milliseconds");
+        assert_eq!(load_level["count"], 10);
+        let p50 = load_level["quantiles"]["p50"].as_f64().unwrap();
+        let p90 = load_level["quantiles"]["p90"].as_f64().unwrap();
+        let p99 = load_level["quantiles"]["p99"].as_f64().unwrap();
+        assert!(p50 < 30.0, "{}", p50);
+        assert!(p50 <= p90 && p90 <= p99);
+        assert!(p99 >= 30.0, "{}", p99);
+    }
+
+    // Each report covers the spans closed since the previous one.
+    handle.flush().await;
+    assert_eq!(reports.lock().unwrap().len(), 1);
+}
+
+// Verify that only spans on the selected targets are summarized.
+#[tokio::test]
+async fn test_span_latency_targets() {
+    let (_mock_server, init_endpoint, metrics_endpoint, reports) = mock_service();
+
+    let appender = PogrAppender::new(Some(init_endpoint), None).await;
+    let layer = PogrLayer::new(appender)
+        .with_span_latency(SpanLatency::for_targets(["matchmaking"]).with_endpoint(metrics_endpoint));
+    let handle = layer.span_latency_handle().unwrap();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    drop(info_span!(target: "matchmaking::queue", "find_match").entered());
+    drop(info_span!(target: "netcode", "sync_state").entered());
+    handle.flush().await;
+
+    let reports = reports.lock().unwrap();
+    let summaries = reports[0].as_array().unwrap();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0]["labels"]["span"], "find_match");
+    assert_eq!(summaries[0]["count"], 1);
+}
+
+// Verify that summaries are off unless enabled.
+#[tokio::test]
+async fn test_span_latency_disabled() {
+    let (_mock_server, init_endpoint, _metrics_endpoint, _reports) = mock_service();
+
+    let appender = PogrAppender::new(Some(init_endpoint), None).await;
+    assert!(PogrLayer::new(appender).span_latency_handle().is_none());
+}
@@ -0,0 +1,87 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{PogrAppender, PogrLayer, SpanEvents};
+use tracing::{field, info, info_span};
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Verify that fields recorded after span creation appear on later events and on span close.
+#[tokio::test]
+async fn test_pogr_layer_span_record() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    let _m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let log_response_success = serde_json::json!({
+        "success": true,
+        "payload": { "log_id": "test_log_id" }
+    }).to_string();
+
+    // The event emitted after `record` carries both the original and the late-recorded field,
+    // with its own fields taking precedence over the span's.
+    let m_event = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "log": "rust tracing log captured",
+            "tags": { "match_id": 42, "player_id": "p-1001", "message": "joined match", "team": "blue" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(&log_response_success)
+        .expect(1)
+        .create();
+
+    // The close record carries the span's final fields.
+    let m_close = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "log": "rust tracing span close",
+            "data": { "name": "match", "span_event": "close" },
+            "tags": { "match_id": 42, "player_id": "p-1001", "team": "red" }
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(&log_response_success)
+        .expect(1)
+        .create();
+
+    // Enable only close records for the `game` target.
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_span_events(
+        SpanEvents::for_targets(["game"])
+            .with_enter(false)
+            .with_exit(false)
+            .with_close(true),
+    );
+    let guard = layer.guard();
+
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    {
+        let span = info_span!(target: "game", "match", match_id = 42, player_id = field::Empty, team = "red");
+        let _entered = span.enter();
+
+        // The player ID is only known after the span has been created.
+        span.record("player_id", "p-1001");
+        info!(target: "game", team = "blue", "joined match");
+    }
+
+    guard.flush().await;
+    m_event.assert();
+    m_close.assert();
+}
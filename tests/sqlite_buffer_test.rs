@@ -0,0 +1,197 @@
+#![cfg(feature = "sqlite")]
+
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use mockito::Matcher;
+use pogr_tracing_rs::{ManualClock, PogrAppender, PogrLayer, SinkMode, SqliteBuffer};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::error;
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Start a mock POGR service with a successful session initialization.
+fn mock_service() -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Create a fresh database path for a single test.
+fn database_path(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("pogr_sqlite_test_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory.join("buffer.sqlite3")
+}
+
+// Verify that undelivered records are buffered and replayed once the intake is back.
+#[tokio::test]
+async fn test_buffer_and_replay() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let path = database_path("replay");
+    let buffer = Arc::new(SqliteBuffer::open(&path).unwrap());
+
+    // Simulate an intake outage while the events are captured.
+    let m_outage = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(2)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint.clone()), Some(logs_endpoint.clone())).await;
+    let layer = PogrLayer::new(appender).with_sink(Arc::clone(&buffer), SinkMode::Fallback);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let default = tracing::subscriber::set_default(subscriber);
+
+    error!(code = 1, "first buffered message");
+    error!(code = 2, "second buffered message");
+    guard.flush().await;
+    drop(default);
+    m_outage.assert();
+    m_outage.remove();
+    assert_eq!(buffer.len().unwrap(), 2);
+
+    // Once the intake is back, both records are resubmitted together.
+    let m_replay = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::Regex(r#"^\[\{.*"code":1.*\},\{.*"code":2.*\}\]$"#.to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    assert_eq!(buffer.replay(&appender, 10).await.unwrap(), 2);
+    m_replay.assert();
+    assert!(buffer.is_empty().unwrap());
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+// Verify that replayed records keep their original timestamps, and are tagged as replayed
+// with the time they spent in the buffer.
+#[tokio::test]
+async fn test_replayed_records_are_marked() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let path = database_path("marked");
+    let buffer = Arc::new(SqliteBuffer::open(&path).unwrap());
+
+    // Simulate an intake outage while the event is captured, an hour ago.
+    let m_outage = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .expect(1)
+        .create();
+
+    let captured = SystemTime::now() - Duration::from_secs(3600);
+    let appender = PogrAppender::new(Some(init_endpoint.clone()), Some(logs_endpoint.clone())).await;
+    let layer = PogrLayer::new(appender)
+        .with_clock(ManualClock::new(captured))
+        .with_sink(Arc::clone(&buffer), SinkMode::Fallback);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let default = tracing::subscriber::set_default(subscriber);
+
+    error!("buffered during the outage");
+    guard.flush().await;
+    drop(default);
+    m_outage.assert();
+    m_outage.remove();
+
+    // The record is submitted with the time it was captured, an hour ago, as the spool delay
+    // measured from it shows.
+    let m_replay = mock_server.mock("POST", "/v1/intake/logs")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(r#""replayed":true"#.to_string()),
+            Matcher::Regex(r#""spool_delay_ms":3[67]\d{5}[,}]"#.to_string()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    assert_eq!(buffer.replay(&appender, 10).await.unwrap(), 1);
+    m_replay.assert();
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+// Verify that the oldest records are evicted once the buffer exceeds its size limit.
+#[tokio::test]
+async fn test_size_based_eviction() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service();
+    let path = database_path("eviction");
+    // Each record is a few hundred bytes, so only about two fit.
+    let buffer = Arc::new(SqliteBuffer::open(&path).unwrap().with_max_size(1024));
+
+    // Simulate an intake outage.
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(503)
+        .with_body("Service Unavailable")
+        .create();
+
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender).with_sink(Arc::clone(&buffer), SinkMode::Fallback);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    for code in 0..10 {
+        error!(code, "evicted message");
+    }
+    guard.flush().await;
+
+    let len = buffer.len().unwrap();
+    assert!(len > 0 && len < 10, "unexpected buffer length {}", len);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+// Verify that a corrupt database is moved aside and replaced with an empty buffer.
+#[test]
+fn test_corruption_recovery() {
+    let path = database_path("corrupt");
+    std::fs::write(&path, b"definitely not a sqlite database, just garbage bytes").unwrap();
+
+    let buffer = SqliteBuffer::open(&path).unwrap();
+    assert!(buffer.is_empty().unwrap());
+
+    let corrupt = path.with_extension("sqlite3.corrupt");
+    assert!(corrupt.exists());
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
@@ -0,0 +1,271 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use mockito::Matcher;
+use pogr_tracing_rs::{
+    ErrorClass, FixedInterval, PogrAppender, PogrLayer, RetryPolicy, SinkMode, StatusAction, StatusTable,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::error;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// A policy that retries right away, once, and remembers the classes it was asked about.
+#[derive(Default)]
+struct RecordingPolicy(Mutex<Vec<ErrorClass>>);
+
+impl RetryPolicy for RecordingPolicy {
+    fn next_delay(&self, attempt: u32, class: ErrorClass, _retry_after: Option<Duration>) -> Option<Duration> {
+        self.0.lock().unwrap().push(class);
+        (attempt == 1).then_some(Duration::ZERO)
+    }
+}
+
+// Build an appender submitting to the mock intake in the session `old_session`.
+fn appender(mock_server: &mockito::ServerGuard) -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_status_table".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "old_session".to_string(),
+        logs_endpoint: format!("{}/v1/intake/logs", mock_server.url()),
+        init_endpoint: format!("{}/v1/intake/init", mock_server.url()),
+    }
+}
+
+// Mock `count` failed submissions with the given status.
+fn mock_failures(mock_server: &mut mockito::ServerGuard, status: usize, count: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(status)
+        .with_body("Intake refused")
+        .expect(count)
+        .create()
+}
+
+// Mock `count` accepted submissions.
+fn mock_success(mock_server: &mut mockito::ServerGuard, count: usize) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(count)
+        .create()
+}
+
+// Log an error through a layer configured by `configure`, and return the messages handed to
+// the fallback sink.
+async fn log_with(mock_server: &mockito::ServerGuard, configure: impl FnOnce(PogrLayer) -> PogrLayer) -> Vec<String> {
+    let sink = Arc::new(CollectingSink::messages());
+    let layer = configure(PogrLayer::new(appender(mock_server)).with_sink(Arc::clone(&sink), SinkMode::Fallback));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("status handled");
+    guard.flush().await;
+    let messages = sink.0.lock().unwrap().clone();
+    messages
+}
+
+// Verify that statuses are looked up in single entries and ranges, later entries first.
+#[test]
+fn test_status_table_lookup() {
+    let table = StatusTable::new()
+        .with_statuses(500..=599, StatusAction::Retry)
+        .with_status(501, StatusAction::DeadLetter)
+        .with_status(401, StatusAction::Reinitialize);
+    assert_eq!(table.action(503), Some(StatusAction::Retry));
+    assert_eq!(table.action(501), Some(StatusAction::DeadLetter));
+    assert_eq!(table.action(401), Some(StatusAction::Reinitialize));
+    assert_eq!(table.action(400), None);
+    assert_eq!(StatusTable::new().action(503), None);
+}
+
+// Verify that dead-lettered statuses go to the fallback sinks right away, even if the retry
+// policy would retry them.
+#[tokio::test]
+async fn test_dead_letter() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_failure = mock_failures(&mut mock_server, 503, 1);
+    let m_success = mock_success(&mut mock_server, 0);
+
+    let messages = log_with(&mock_server, |layer| {
+        layer
+            .with_retry_policy(FixedInterval::new(Duration::from_millis(10)))
+            .with_status_table(StatusTable::new().with_status(503, StatusAction::DeadLetter))
+    })
+    .await;
+    assert_eq!(messages, ["status handled"]);
+    m_failure.assert();
+    m_success.assert();
+}
+
+// Verify that statuses marked as retryable are retried, as retryable, even if they are
+// rejections otherwise.
+#[tokio::test]
+async fn test_retryable_status() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_failure = mock_failures(&mut mock_server, 409, 1);
+    let m_success = mock_success(&mut mock_server, 1);
+
+    let recorded = Arc::new(RecordingPolicy::default());
+    let policy = Arc::clone(&recorded);
+    let messages = log_with(&mock_server, |layer| {
+        layer
+            .with_retry_policy(policy)
+            .with_status_table(StatusTable::new().with_status(409, StatusAction::Retry))
+    })
+    .await;
+    assert!(messages.is_empty());
+    assert_eq!(*recorded.0.lock().unwrap(), [ErrorClass::Retryable]);
+    m_failure.assert();
+    m_success.assert();
+}
+
+// Verify that statuses marked as retryable are retried with the default backoff if the layer
+// has no retry policy.
+#[tokio::test]
+async fn test_retryable_status_without_policy() {
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_failure = mock_failures(&mut mock_server, 408, 1);
+    let m_success = mock_success(&mut mock_server, 1);
+
+    let messages =
+        log_with(&mock_server, |layer| layer.with_status_table(StatusTable::new().with_status(408, StatusAction::Retry)))
+            .await;
+    assert!(messages.is_empty());
+    m_failure.assert();
+    m_success.assert();
+}
+
+// Verify that a refused session is replaced by a new one, in which the request is sent again.
+#[tokio::test]
+async fn test_reinitialize_session() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"session_id": "new_session"}}"#)
+        .expect(1)
+        .create();
+    let m_refused = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "old_session")
+        .with_status(401)
+        .with_body("Session expired")
+        .expect(1)
+        .create();
+    let m_accepted = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "new_session")
+        .match_body(Matcher::Regex("status handled".to_string()))
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create();
+
+    let messages = log_with(&mock_server, |layer| {
+        layer.with_status_table(StatusTable::new().with_status(401, StatusAction::Reinitialize))
+    })
+    .await;
+    assert!(messages.is_empty());
+    m_init.assert();
+    m_refused.assert();
+    m_accepted.assert();
+}
+
+// Verify that the session is re-initialized only once per request, and that the records go to
+// the fallback sinks if the new session is refused as well.
+#[tokio::test]
+async fn test_reinitialize_once() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"session_id": "new_session"}}"#)
+        .expect(1)
+        .create();
+    let m_refused = mock_failures(&mut mock_server, 401, 2);
+
+    let messages = log_with(&mock_server, |layer| {
+        layer.with_status_table(StatusTable::new().with_status(401, StatusAction::Reinitialize))
+    })
+    .await;
+    assert_eq!(messages, ["status handled"]);
+    m_init.assert();
+    m_refused.assert();
+}
+
+// Verify that the new session is described by the metadata the refused one was initialized
+// with.
+#[tokio::test]
+async fn test_reinitialize_keeps_metadata() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    let mut mock_server = mockito::Server::new_async().await;
+    let m_init_old = mock_server.mock("POST", "/v1/intake/init")
+        .match_body(Matcher::Regex("2024.03.1-4711".to_string()))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"session_id": "metadata_old_session"}}"#)
+        .expect(1)
+        .create();
+    let m_init_new = mock_server.mock("POST", "/v1/intake/init")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex("2024.03.1-4711".to_string()),
+            Matcher::Regex("steam".to_string()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"success": true, "payload": {"session_id": "metadata_new_session"}}"#)
+        .expect(1)
+        .create();
+    let m_refused = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "metadata_old_session")
+        .with_status(401)
+        .with_body("Session expired")
+        .expect(1)
+        .create();
+    let m_accepted = mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "metadata_new_session")
+        .with_status(200)
+        .with_body(r#"{"success": true, "payload": {"log_id": "test_log_id"}}"#)
+        .expect(1)
+        .create();
+
+    let appender = PogrAppender::builder()
+        .with_init_endpoint(format!("{}/v1/intake/init", mock_server.url()))
+        .with_logs_endpoint(format!("{}/v1/intake/logs", mock_server.url()))
+        .with_build_id("2024.03.1-4711")
+        .with_platform("steam")
+        .build()
+        .await;
+    let layer = PogrLayer::new(appender)
+        .with_status_table(StatusTable::new().with_status(401, StatusAction::Reinitialize));
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    error!("status handled");
+    guard.flush().await;
+    m_init_old.assert();
+    m_init_new.assert();
+    m_refused.assert();
+    m_accepted.assert();
+}
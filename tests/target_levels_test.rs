@@ -0,0 +1,82 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{PogrAppender, PogrLayer, TargetLevels};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+
+// Verify that directive strings are parsed into the expected per-target levels.
+#[test]
+fn test_target_levels_parse() {
+    let levels: TargetLevels = "warn, matchmaking=debug, netcode=warn, netcode::rollback=trace, sqlx=error"
+        .parse()
+        .expect("Failed to parse target levels");
+
+    // Bare levels set the default for unmatched targets.
+    assert_eq!(levels.default_level(), LevelFilter::WARN);
+    assert_eq!(levels.level_for("game::inventory"), LevelFilter::WARN);
+    // Directives apply to the target and to modules below it, but not to similarly named targets.
+    assert_eq!(levels.level_for("matchmaking::queue"), LevelFilter::DEBUG);
+    assert_eq!(levels.level_for("matchmaking_utils"), LevelFilter::WARN);
+    // The most specific directive wins.
+    assert_eq!(levels.level_for("netcode::rollback::snapshot"), LevelFilter::TRACE);
+    assert_eq!(levels.level_for("netcode::transport"), LevelFilter::WARN);
+    assert_eq!(levels.level_for("sqlx::query"), LevelFilter::ERROR);
+
+    // Invalid levels and empty targets are rejected.
+    assert!("netcode=loud".parse::<TargetLevels>().is_err());
+    assert!("=debug".parse::<TargetLevels>().is_err());
+}
+
+// Verify that the layer only forwards events meeting their target's threshold.
+#[tokio::test]
+async fn test_pogr_layer_target_levels() {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock a successful session initialization.
+    let _m_init = mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    // Only the events above their target's threshold should be submitted.
+    let m_logs = mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .expect(3)
+        .create();
+
+    // Dial down the noisy netcode subsystem while keeping matchmaking verbose.
+    let appender = PogrAppender::new(Some(init_endpoint), Some(logs_endpoint)).await;
+    let layer = PogrLayer::new(appender)
+        .with_target_levels("info,matchmaking=debug,netcode=warn".parse().unwrap());
+    let guard = layer.guard();
+
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    debug!(target: "matchmaking", "forwarded: matchmaking allows debug");
+    info!(target: "netcode", "dropped: netcode requires warn");
+    warn!(target: "netcode::transport", "forwarded: warn meets netcode threshold");
+    debug!(target: "inventory", "dropped: default requires info");
+    error!(target: "inventory", "forwarded: error meets default threshold");
+
+    guard.flush().await;
+    m_logs.assert();
+}
@@ -0,0 +1,162 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::CollectingSink;
+use pogr_tracing_rs::{time_field, PogrAppender, PogrLayer, SinkMode, SERIALIZATION_ERRORS_FIELD};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Build an appender that is never submitted to.
+fn appender() -> PogrAppender {
+    PogrAppender {
+        client: reqwest::Client::new(),
+        service_name: "test_time_fields".to_string(),
+        environment: "testing".to_string(),
+        service_type: "test".to_string(),
+        session_id: "test_session_id".to_string(),
+        logs_endpoint: "http://127.0.0.1:9/v1/intake/logs".to_string(),
+        init_endpoint: "".to_string(),
+    }
+}
+
+// Record one event with `emit`, and return the fields the sink received.
+async fn record(emit: impl FnOnce()) -> Value {
+    let sink = Arc::new(CollectingSink::tags());
+    let layer = PogrLayer::new(appender())
+        .with_intake_delivery(false)
+        .with_sink(Arc::clone(&sink), SinkMode::Mirror);
+    let guard = layer.guard();
+
+    // Install the subscriber for this test only.
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    emit();
+    guard.flush().await;
+    let mut records = sink.0.lock().unwrap().clone();
+    assert_eq!(records.len(), 1);
+    records.remove(0)
+}
+
+// Verify that `std` timestamps and durations are recorded as RFC 3339 strings and
+// milliseconds, with `%` or `?`.
+#[tokio::test]
+async fn test_std_types() {
+    let saved_at = UNIX_EPOCH + Duration::from_millis(1_709_296_205_042);
+    let fields = record(|| {
+        info!(
+            saved_at = %time_field(saved_at),
+            load_time = ?time_field(Duration::from_millis(1500)),
+            frame_time = %time_field(Duration::from_micros(250)),
+            tick = %time_field(Duration::from_nanos(1_000_000_001)),
+            "level loaded"
+        )
+    })
+    .await;
+    assert_eq!(fields["saved_at"], json!("2024-03-01T12:30:05.042Z"));
+    assert_eq!(fields["load_time"], json!(1500));
+    assert_eq!(fields["frame_time"], json!(0.25));
+    assert_eq!(fields["tick"], json!(1000.000001));
+}
+
+// Verify that other layers see the normalized text.
+#[test]
+fn test_display() {
+    let saved_at = UNIX_EPOCH + Duration::from_millis(1_709_296_205_042);
+    assert_eq!(time_field(saved_at).to_string(), "2024-03-01T12:30:05.042Z");
+    assert_eq!(format!("{:?}", time_field(Duration::from_millis(1500))), "1500ms");
+}
+
+// Verify that a timestamp that cannot be recorded keeps its `Debug` output and is noted,
+// instead of turning into the epoch.
+#[tokio::test]
+async fn test_out_of_range_noted() {
+    let before_epoch = UNIX_EPOCH - Duration::from_secs(1);
+    let fields = record(|| info!(saved_at = %time_field(before_epoch), "level loaded")).await;
+    assert_eq!(fields["saved_at"], json!(format!("{:?}", before_epoch)));
+    assert_eq!(
+        fields[SERIALIZATION_ERRORS_FIELD],
+        json!({ "saved_at": "timestamp outside of 1970 to 2554" })
+    );
+}
+
+// A user type whose `Debug` output looks like that of `time::Duration`.
+struct Cooldown;
+
+impl fmt::Debug for Cooldown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Duration { seconds: 1, nanoseconds: 500000000 }")
+    }
+}
+
+// Verify that values not wrapped in a `TimeField`, or nested in another value, are recorded
+// as they are, even if their text looks like a timestamp or duration.
+#[tokio::test]
+async fn test_other_output_kept() {
+    let fields = record(|| {
+        info!(
+            timeout = %"10s",
+            since = %"2024-03-01T12:30:05.042Z",
+            elapsed = ?Duration::from_millis(1500),
+            cooldown = ?Cooldown,
+            optional = ?Some(time_field(Duration::from_millis(1500))),
+            "1.5s"
+        )
+    })
+    .await;
+    assert_eq!(fields["timeout"], json!("10s"));
+    assert_eq!(fields["since"], json!("2024-03-01T12:30:05.042Z"));
+    assert_eq!(fields["elapsed"], json!("1.5s"));
+    assert_eq!(fields["cooldown"], json!("Duration { seconds: 1, nanoseconds: 500000000 }"));
+    assert_eq!(fields["optional"], json!("Some(1500ms)"));
+    assert_eq!(fields["message"], json!("1.5s"));
+}
+
+// Verify that `chrono`'s date and time types and `TimeDelta` are recorded.
+#[cfg(feature = "chrono")]
+#[tokio::test]
+async fn test_chrono_types() {
+    let utc = chrono::DateTime::from_timestamp_millis(1_709_296_205_042).unwrap();
+    let offset = utc.with_timezone(&chrono::FixedOffset::east_opt(2 * 3600).unwrap());
+    let fields = record(|| {
+        info!(
+            utc = %time_field(utc),
+            offset = %time_field(offset),
+            naive = %time_field(utc.naive_utc()),
+            cooldown = %time_field(chrono::TimeDelta::milliseconds(-1500)),
+            "ability used"
+        )
+    })
+    .await;
+    for name in ["utc", "offset", "naive"] {
+        assert_eq!(fields[name], json!("2024-03-01T12:30:05.042Z"), "{}", name);
+    }
+    assert_eq!(fields["cooldown"], json!(-1500));
+}
+
+// Verify that `time`'s date and time types and `Duration` are recorded.
+#[cfg(feature = "time")]
+#[tokio::test]
+async fn test_time_types() {
+    let utc = time::OffsetDateTime::from_unix_timestamp_nanos(1_709_296_205_042_000_000).unwrap();
+    let offset = utc.to_offset(time::UtcOffset::from_hms(2, 0, 0).unwrap());
+    let primitive = time::PrimitiveDateTime::new(utc.date(), utc.time());
+    let fields = record(|| {
+        info!(
+            utc = %time_field(utc),
+            offset = %time_field(offset),
+            utc_date_time = %time_field(utc.to_utc()),
+            primitive = %time_field(primitive),
+            cooldown = %time_field(time::Duration::milliseconds(1500)),
+            "ability used"
+        )
+    })
+    .await;
+    for name in ["utc", "offset", "utc_date_time", "primitive"] {
+        assert_eq!(fields[name], json!("2024-03-01T12:30:05.042Z"), "{}", name);
+    }
+    assert_eq!(fields["cooldown"], json!(1500));
+}
@@ -0,0 +1,132 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+use pogr_tracing_rs::{PogrAppender, PogrLayer, TraceExport};
+use std::sync::{Arc, Mutex};
+use tracing::{info, info_span, Instrument};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// Start a mock POGR service that records the log requests it receives, and return the layer.
+async fn mock_service(trace_export: TraceExport) -> (mockito::ServerGuard, PogrLayer, Arc<Mutex<Vec<serde_json::Value>>>) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url().trim_end_matches('/').to_string();
+
+    // Mock a successful session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "session_id": "test_session_id" }
+        }).to_string())
+        .create();
+
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&logs);
+    mock_server.mock("POST", "/v1/intake/logs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body_from_request(move |request| {
+            received.lock().unwrap().push(serde_json::from_slice(request.body().unwrap()).unwrap());
+            serde_json::json!({ "success": true, "payload": { "log_id": "test_log_id" } }).to_string().into()
+        })
+        .create();
+
+    let appender = PogrAppender::new(
+        Some(format!("{}/v1/intake/init", base_url)),
+        Some(format!("{}/v1/intake/logs", base_url)),
+    )
+    .await;
+    let layer = PogrLayer::new(appender).with_trace_export(trace_export);
+    (mock_server, layer, logs)
+}
+
+// Verify that a span tree is exported as one trace document, with parent/child IDs, timings
+// and fields, and that events inside it are tagged with the trace ID.
+#[tokio::test]
+async fn test_trace_export() {
+    let (_mock_server, layer, logs) = mock_service(TraceExport::new()).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    async {
+        async {
+            info!("Profile loaded");
+        }
+        .instrument(info_span!("load_profile", player_id = 42))
+        .await;
+        info_span!("load_inventory").in_scope(|| {});
+    }
+    .instrument(info_span!("handle_login", region = "eu"))
+    .await;
+    guard.flush().await;
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(logs.len(), 2);
+    let event = logs.iter().find(|log| log["log"] == "rust tracing log captured").unwrap();
+    let trace = logs.iter().find(|log| log["log"] == "rust tracing trace").unwrap();
+    assert_eq!(event["tags"]["trace_id"], trace["tags"]["trace_id"]);
+
+    let document = &trace["data"];
+    assert_eq!(document["trace_id"], trace["tags"]["trace_id"]);
+    assert_eq!(document["name"], "handle_login");
+    assert_eq!(document["dropped_spans"], 0);
+    let spans = document["spans"].as_array().unwrap();
+    let names: Vec<&str> = spans.iter().map(|span| span["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["handle_login", "load_profile", "load_inventory"]);
+
+    let root = &spans[0];
+    assert!(root["parent_id"].is_null());
+    assert_eq!(document["root_span_id"], root["span_id"]);
+    assert_eq!(root["fields"]["region"], "eu");
+    assert!(root["start"].as_str().unwrap().ends_with('Z'));
+    for child in &spans[1..] {
+        assert_eq!(child["parent_id"], root["span_id"]);
+        assert!(child["duration_ms"].as_f64().unwrap() <= root["duration_ms"].as_f64().unwrap());
+    }
+    assert_eq!(spans[1]["fields"]["player_id"], 42);
+}
+
+// Verify that a sample rate of zero exports no traces, and that events are then not tagged.
+#[tokio::test]
+async fn test_trace_export_sampled_out() {
+    let (_mock_server, layer, logs) = mock_service(TraceExport::new().with_sample_rate(0.0)).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info_span!("handle_login").in_scope(|| info!("Logged in"));
+    guard.flush().await;
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0]["tags"].get("trace_id").is_none());
+}
+
+// Verify that spans beyond the limit are counted rather than kept, except for the root.
+#[tokio::test]
+async fn test_trace_export_max_spans() {
+    let (_mock_server, layer, logs) = mock_service(TraceExport::new().with_max_spans(2)).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    info_span!("tick").in_scope(|| {
+        for _ in 0..3 {
+            info_span!("system").in_scope(|| {});
+        }
+    });
+    guard.flush().await;
+
+    let logs = logs.lock().unwrap();
+    let document = &logs[0]["data"];
+    // Spans are numbered per trace, although the registry reuses the IDs of closed spans.
+    let ids: Vec<u64> = document["spans"].as_array().unwrap().iter().map(|span| span["span_id"].as_u64().unwrap()).collect();
+    assert_eq!(ids, [1, 2, 3]);
+    assert_eq!(document["name"], "tick");
+    assert_eq!(document["dropped_spans"], 1);
+}
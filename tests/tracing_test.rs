@@ -64,9 +64,7 @@ async fn test_pogr_tracing_appender() {
     assert_eq!(appender.init_endpoint, init_endpoint);
 
     // Wrap the appender in a PogrLayer and make it shareable across threads and asynchronous tasks.
-    let layer = PogrLayer {
-        appender: Arc::new(Mutex::new(appender)),
-    };
+    let layer = PogrLayer::from(Arc::new(Mutex::new(appender)));
 
     // Create a subscriber that combines the default registry with the PogrLayer for capturing log events.
     let subscriber = Registry::default().with(layer);
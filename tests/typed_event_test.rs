@@ -0,0 +1,90 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate and the tracing ecosystem.
+mod common;
+use common::{collecting_layer, CollectingSink};
+use pogr_tracing_rs::{PogrEvent, REDACTED};
+use serde::Serialize;
+use tracing::Level;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+// An event with a severity, a type and a redacted field.
+#[derive(Serialize, PogrEvent)]
+#[pogr(severity = "warn", type = "gameplay")]
+struct PlayerDied {
+    player_id: u64,
+    cause: String,
+    #[pogr(redact)]
+    ip_address: String,
+}
+
+// An event with the default severity, a custom name and a renamed redacted field.
+#[derive(Serialize, PogrEvent)]
+#[pogr(name = "match.started")]
+struct MatchStarted {
+    map: &'static str,
+    #[serde(rename = "token")]
+    #[pogr(redact)]
+    session_token: &'static str,
+}
+
+// An event without fields.
+#[derive(Serialize, PogrEvent)]
+struct HTTPServerReady;
+
+// Verify the constants derived from the attributes.
+#[test]
+fn test_derived_constants() {
+    assert_eq!(PlayerDied::NAME, "player_died");
+    assert_eq!(PlayerDied::LEVEL, Level::WARN);
+    assert_eq!(PlayerDied::TYPE, Some("gameplay"));
+    assert_eq!(PlayerDied::REDACTED_FIELDS, ["ip_address"]);
+    assert_eq!(MatchStarted::NAME, "match.started");
+    assert_eq!(MatchStarted::LEVEL, Level::INFO);
+    assert_eq!(MatchStarted::TYPE, None);
+    assert_eq!(MatchStarted::REDACTED_FIELDS, ["token"]);
+    assert_eq!(HTTPServerReady::NAME, "http_server_ready");
+}
+
+// Verify that typed events are emitted with their fields, severity and type, and that
+// redacted fields are masked.
+#[tokio::test]
+async fn test_emit_typed_events() {
+    let (_mock_server, layer, sink) = collecting_layer(CollectingSink::requests()).await;
+    let guard = layer.guard();
+    let subscriber = Registry::default().with(layer);
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    PlayerDied { player_id: 42, cause: "lava".to_string(), ip_address: "10.0.0.7".to_string() }.emit();
+    MatchStarted { map: "harbor", session_token: "s3cr3t" }.emit();
+    HTTPServerReady.emit();
+    guard.flush().await;
+
+    let records = sink.0.lock().unwrap();
+    assert_eq!(records.len(), 3);
+
+    let died = &records[0];
+    assert_eq!(died["severity"], "WARN");
+    assert_eq!(died["type"], "gameplay");
+    assert_eq!(died["tags"]["message"], "player_died");
+    assert_eq!(died["tags"]["player_id"], 42);
+    assert_eq!(died["tags"]["cause"], "lava");
+    assert_eq!(died["tags"]["ip_address"], REDACTED);
+    assert!(died["tags"].get("pogr.event").is_none());
+    assert!(died["tags"].get("pogr.type").is_none());
+    assert_eq!(died["data"]["target"], "typed_event_test");
+
+    let started = &records[1];
+    assert_eq!(started["severity"], "INFO");
+    assert_ne!(started["type"], "gameplay");
+    assert_eq!(started["tags"]["message"], "match.started");
+    assert_eq!(started["tags"]["map"], "harbor");
+    assert_eq!(started["tags"]["token"], REDACTED);
+
+    assert_eq!(records[2]["tags"]["message"], "http_server_ready");
+}
+
+// Verify that the fields sent to POGR can be inspected without emitting the event.
+#[test]
+fn test_fields() {
+    let fields = MatchStarted { map: "harbor", session_token: "s3cr3t" }.fields();
+    assert_eq!(serde_json::Value::Object(fields), serde_json::json!({ "map": "harbor", "token": REDACTED }));
+}
@@ -0,0 +1,120 @@
+// Import the necessary modules from the `pogr_tracing_rs` crate.
+use pogr_tracing_rs::{CheckOutcome, PogrAppender};
+
+// Start a mock POGR service whose init endpoint answers with the given status and body.
+fn mock_service(init_status: usize, init_body: serde_json::Value) -> (mockito::ServerGuard, String, String) {
+    // Set mock environment variables required for the PogrAppender authentication process.
+    std::env::set_var("POGR_ACCESS", "test_access_key");
+    std::env::set_var("POGR_SECRET", "test_secret_key");
+
+    // Initialize a mock server to simulate the POGR service.
+    let mut mock_server = mockito::Server::new();
+    let base_url = mock_server.url();
+    let init_endpoint = format!("{}/v1/intake/init", base_url.trim_end_matches('/'));
+    let logs_endpoint = format!("{}/v1/intake/logs", base_url.trim_end_matches('/'));
+
+    // Mock the session initialization.
+    mock_server.mock("POST", "/v1/intake/init")
+        .with_status(init_status)
+        .with_header("content-type", "application/json")
+        .with_body(init_body.to_string())
+        .create();
+
+    (mock_server, init_endpoint, logs_endpoint)
+}
+
+// Mock the logs endpoint accepting the session check's empty batch.
+fn mock_logs(mock_server: &mut mockito::ServerGuard) -> mockito::Mock {
+    mock_server.mock("POST", "/v1/intake/logs")
+        .match_header("INTAKE_SESSION_ID", "test_session_id")
+        .match_body("[]")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::json!({
+            "success": true,
+            "payload": { "log_id": "test_log_id" }
+        }).to_string())
+        .create()
+}
+
+// Verify that a working setup passes validation and yields an appender.
+#[tokio::test]
+async fn test_new_validated_valid() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service(200, serde_json::json!({
+        "success": true,
+        "payload": { "session_id": "test_session_id" }
+    }));
+    let m_logs = mock_logs(&mut mock_server).expect(1);
+
+    let (appender, report) = PogrAppender::new_validated(Some(init_endpoint), Some(logs_endpoint))
+        .await
+        .expect("validation should pass");
+    m_logs.assert();
+
+    assert!(report.is_valid(), "{}", report);
+    assert_eq!(report.credentials, CheckOutcome::Passed);
+    assert_eq!(report.session, CheckOutcome::Passed);
+    // The mock server sends a `Date` header, so the clock is compared.
+    assert_eq!(report.clock_skew, CheckOutcome::Passed);
+    assert!(report.clock_offset.is_some());
+    assert_eq!(appender.session_id, "test_session_id");
+}
+
+// Verify that rejected credentials fail validation with the intake's answer, instead of panicking.
+#[tokio::test]
+async fn test_new_validated_bad_credentials() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service(401, serde_json::json!({
+        "error": "invalid access key"
+    }));
+    let m_logs = mock_logs(&mut mock_server).expect(0);
+
+    let err = PogrAppender::new_validated(Some(init_endpoint), Some(logs_endpoint))
+        .await
+        .err()
+        .expect("validation should fail");
+    m_logs.assert();
+
+    let report = err.report();
+    assert!(!report.is_valid());
+    assert!(report.init.is_healthy());
+    assert!(report.logs.is_healthy());
+    assert!(
+        matches!(&report.credentials, CheckOutcome::Failed(reason) if reason.contains("401") && reason.contains("invalid access key")),
+        "{}",
+        report
+    );
+    // Without a session there is nothing to check against the logs endpoint.
+    assert_eq!(report.session, CheckOutcome::Skipped);
+    assert!(err.to_string().starts_with("POGR startup validation failed:"));
+}
+
+// Verify that a local clock far from the intake's fails validation.
+#[tokio::test]
+async fn test_new_validated_clock_skew() {
+    let (mut mock_server, init_endpoint, logs_endpoint) = mock_service(200, serde_json::json!({
+        "success": true,
+        "payload": { "session_id": "test_session_id" }
+    }));
+    let _m_logs = mock_logs(&mut mock_server);
+
+    // Answer the connectivity checks with a `Date` far in the past.
+    let _m_head = mock_server.mock("HEAD", mockito::Matcher::Any)
+        .with_status(405)
+        .with_header("date", "Sun, 06 Nov 1994 08:49:37 GMT")
+        .create();
+
+    let err = PogrAppender::new_validated(Some(init_endpoint), Some(logs_endpoint))
+        .await
+        .err()
+        .expect("validation should fail");
+
+    let report = err.report();
+    assert_eq!(report.credentials, CheckOutcome::Passed);
+    assert_eq!(report.session, CheckOutcome::Passed);
+    assert!(
+        matches!(&report.clock_skew, CheckOutcome::Failed(reason) if reason.contains("ahead of")),
+        "{}",
+        report
+    );
+    assert!(report.clock_offset.unwrap().as_secs() > 365 * 24 * 3600);
+}